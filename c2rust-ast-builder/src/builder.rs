@@ -930,6 +930,19 @@ impl Builder {
         })
     }
 
+    pub fn raw_addr_of_expr<E>(self, e: E) -> P<Expr>
+    where
+        E: Make<P<Expr>>,
+    {
+        let e = e.make(&self);
+        P(Expr {
+            id: self.id,
+            kind: ExprKind::AddrOf(BorrowKind::Raw, self.mutbl, e),
+            span: self.span,
+            attrs: self.attrs.into(),
+        })
+    }
+
     pub fn mac_expr<M>(self, mac: M) -> P<Expr>
     where
         M: Make<Mac>,
@@ -1183,6 +1196,38 @@ impl Builder {
         })
     }
 
+    pub fn range_expr<E>(self, start: Option<E>, end: Option<E>, limits: RangeLimits) -> P<Expr>
+    where
+        E: Make<P<Expr>>,
+    {
+        let start = start.map(|e| e.make(&self));
+        let end = end.map(|e| e.make(&self));
+
+        P(Expr {
+            id: self.id,
+            kind: ExprKind::Range(start, end, limits),
+            span: self.span,
+            attrs: self.attrs.into(),
+        })
+    }
+
+    /// `let pat = expr`, as the condition of an `if let` or `while let`
+    pub fn let_expr<Pa, E>(self, pat: Pa, expr: E) -> P<Expr>
+    where
+        Pa: Make<P<Pat>>,
+        E: Make<P<Expr>>,
+    {
+        let pat = pat.make(&self);
+        let expr = expr.make(&self);
+
+        P(Expr {
+            id: self.id,
+            kind: ExprKind::Let(pat, expr),
+            span: self.span,
+            attrs: self.attrs.into(),
+        })
+    }
+
     // Patterns
 
     pub fn ident_pat<I>(self, name: I) -> P<Pat>
@@ -1221,6 +1266,20 @@ impl Builder {
         })
     }
 
+    pub fn tuple_struct_pat<Pa, Pt>(self, path: Pa, pats: Vec<Pt>) -> P<Pat>
+    where
+        Pa: Make<Path>,
+        Pt: Make<P<Pat>>,
+    {
+        let path = path.make(&self);
+        let pats: Vec<P<Pat>> = pats.into_iter().map(|x| x.make(&self)).collect();
+        P(Pat {
+            id: self.id,
+            kind: PatKind::TupleStruct(path, pats),
+            span: self.span,
+        })
+    }
+
     pub fn wild_pat(self) -> P<Pat> {
         P(Pat {
             id: self.id,
@@ -1759,6 +1818,34 @@ impl Builder {
         )
     }
 
+    pub fn trait_impl_item<Pa, T>(self, trait_path: Pa, ty: T, items: Vec<ImplItem>) -> P<Item>
+    where
+        Pa: Make<Path>,
+        T: Make<P<Ty>>,
+    {
+        let trait_ref = TraitRef {
+            path: trait_path.make(&self),
+            ref_id: DUMMY_NODE_ID,
+        };
+        let ty = ty.make(&self);
+        Self::item(
+            Ident::invalid(),
+            self.attrs,
+            self.vis,
+            self.span,
+            self.id,
+            ItemKind::Impl(
+                self.unsafety,
+                ImplPolarity::Positive,
+                Defaultness::Final,
+                self.generics,
+                Some(trait_ref),
+                ty,
+                items,
+            ),
+        )
+    }
+
     pub fn extern_crate_item<I>(self, name: I, rename: Option<I>) -> P<Item>
     where
         I: Make<Ident>,
@@ -1929,6 +2016,28 @@ impl Builder {
         )
     }
 
+    pub fn fn_impl_item<I, S, B>(self, name: I, sig: S, block: B) -> ImplItem
+    where
+        I: Make<Ident>,
+        S: Make<FnSig>,
+        B: Make<P<Block>>,
+    {
+        let name = name.make(&self);
+        let sig = sig.make(&self);
+        let block = block.make(&self);
+        let kind = ImplItemKind::Method(sig, block);
+        Self::impl_item_(
+            name,
+            self.attrs,
+            self.vis,
+            Defaultness::Final,
+            self.generics,
+            self.span,
+            self.id,
+            kind,
+        )
+    }
+
     // Trait Items
 
     /// Called `trait_item_` because `trait_item` is already used for "Item, of ItemKind::Trait".
@@ -2309,6 +2418,19 @@ impl Builder {
         })
     }
 
+    pub fn try_expr<E>(self, e: E) -> P<Expr>
+    where
+        E: Make<P<Expr>>,
+    {
+        let e = e.make(&self);
+        P(Expr {
+            id: self.id,
+            kind: ExprKind::Try(e),
+            span: self.span,
+            attrs: self.attrs.into(),
+        })
+    }
+
     pub fn continue_expr<I>(self, label: Option<I>) -> P<Expr>
     where
         I: Make<Ident>,
@@ -123,6 +123,17 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
         cbor_encoder_close_container(encoder, &local);
     }
 
+    // Name of a calling convention other than the default C one, e.g.
+    // "stdcall", which the importer maps to a Rust ABI, or null
+    static void encodeCallConv(CborEncoder *local, CallingConv cc) {
+        if (cc == CC_C) {
+            cbor_encode_null(local);
+        } else {
+            auto name = FunctionType::getNameForCallConv(cc);
+            cbor_encode_text_stringz(local, name.str().c_str());
+        }
+    }
+
   public:
     uintptr_t encodeQualType(QualType t) {
         auto s = t.split();
@@ -286,6 +297,22 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
         VisitQualType(t);
     }
 
+#if CLANG_VERSION_MAJOR >= 14
+    void VisitBitIntType(const BitIntType *T) {
+        encodeType(T, TagBitIntType, [T](CborEncoder *local) {
+            cbor_encode_boolean(local, T->isSigned());
+            cbor_encode_uint(local, T->getNumBits());
+        });
+    }
+#elif CLANG_VERSION_MAJOR >= 11
+    void VisitExtIntType(const ExtIntType *T) {
+        encodeType(T, TagBitIntType, [T](CborEncoder *local) {
+            cbor_encode_boolean(local, T->isSigned());
+            cbor_encode_uint(local, T->getNumBits());
+        });
+    }
+#endif // CLANG_VERSION_MAJOR
+
     void VisitBuiltinType(const BuiltinType *T) {
         TypeTag tag;
         auto kind = T->getKind();
@@ -305,6 +332,7 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
         case BuiltinType::ULong:      tag = TagULong;       break;
         case BuiltinType::ULongLong:  tag = TagULongLong;   break;
         case BuiltinType::Half:       tag = TagHalf;        break;
+        case BuiltinType::Float16:    tag = TagHalf;        break;
         case BuiltinType::Float:      tag = TagFloat;       break;
         case BuiltinType::Double:     tag = TagDouble;      break;
         case BuiltinType::LongDouble: tag = TagLongDouble;  break;
@@ -316,10 +344,20 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
         case BuiltinType::Bool:       tag = TagBool;        break;
         case BuiltinType::WChar_S:    tag = TagSWChar;      break;
         case BuiltinType::WChar_U:    tag = TagUWChar;      break;
+        case BuiltinType::Char16:     tag = TagChar16;      break;
+        case BuiltinType::Char32:     tag = TagChar32;      break;
         }
         // clang-format on
 
-        encodeType(T, tag);
+        if (tag == TagTypeUnknown) {
+            // Named so the transpiler can report it, and `--type-map` replace it
+            auto name = T->getName(Context->getPrintingPolicy()).str();
+            encodeType(T, tag, [&name](CborEncoder *local) {
+                cbor_encode_text_stringz(local, name.c_str());
+            });
+        } else {
+            encodeType(T, tag);
+        }
     }
 
     // Clang represents function declarations with parameters as
@@ -350,6 +388,7 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
             cbor_encode_boolean(local, T->getExtProtoInfo().Variadic);
             cbor_encode_boolean(local, T->getNoReturnAttr());
             cbor_encode_boolean(local, true); // has arguments
+            encodeCallConv(local, T->getCallConv());
         });
 
         VisitQualType(T->getReturnType());
@@ -373,6 +412,7 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
             cbor_encode_boolean(local, false); // Variable argument function
             cbor_encode_boolean(local, T->getNoReturnAttr());
             cbor_encode_boolean(local, false); // has arguments
+            encodeCallConv(local, T->getCallConv());
         });
 
         VisitQualType(T->getReturnType());
@@ -418,6 +458,16 @@ class TypeEncoder final : public TypeVisitor<TypeEncoder> {
         VisitQualType(t);
     }
 
+    // `__auto_type` (GCC extension), encoded as the type deduced from the
+    // initializer
+    void VisitAutoType(const AutoType *T) {
+        auto t = T->getDeducedType();
+        auto qt = encodeQualType(t);
+        encodeType(T, TagAutoType,
+                   [qt](CborEncoder *local) { cbor_encode_uint(local, qt); });
+        VisitQualType(t);
+    }
+
     void VisitElaboratedType(const ElaboratedType *T) {
         auto t = T->desugar();
         auto qt = encodeQualType(t);
@@ -459,6 +509,11 @@ class TranslateASTVisitor final
     std::unordered_set<unsigned> macroCallSites;
     SmallVector<MacroInfo*, 1> curMacroExpansionStack;
     StringRef curMacroExpansionSource;
+    // Function-like macro parameter name and argument spelling for the
+    // expression currently being visited, if it is a whole macro argument.
+    std::pair<StringRef, StringRef> curMacroArgument;
+    // C++ method whose body is being visited, which `this` refers to
+    CXXMethodDecl *currentMethod = nullptr;
 
     // Returns true when a new entry is added to exportedTags
     bool markForExport(void *ptr, ASTEntryTag tag) {
@@ -549,7 +604,18 @@ class TranslateASTVisitor final
             cbor_encode_null(&local);
         }
 
-        // 12.. - Extra entries
+        // 12 - Macro parameter this expression was substituted for, if
+        // applicable, as a [name, argument spelling] pair.
+        if (!curMacroArgument.first.empty()) {
+            cbor_encoder_create_array(&local, &childEnc, 2);
+            cbor_encode_string(&childEnc, curMacroArgument.first.str());
+            cbor_encode_string(&childEnc, curMacroArgument.second.str());
+            cbor_encoder_close_container(&local, &childEnc);
+        } else {
+            cbor_encode_null(&local);
+        }
+
+        // 13.. - Extra entries
         extra(&local);
 
         cbor_encoder_close_container(encoder, &local);
@@ -692,6 +758,13 @@ class TranslateASTVisitor final
     // Override the default behavior of the RecursiveASTVisitor
     bool shouldVisitImplicitCode() const { return true; }
 
+    // Returns true when any entry was exported for this AST node
+    bool hasEntry(void *ptr) {
+        auto search =
+            exportedTags.lower_bound(std::make_pair(ptr, ASTEntryTag(0)));
+        return search != std::end(exportedTags) && search->first == ptr;
+    }
+
     // Return the filenames as a vector. Indices correspond to file IDs.
     const std::vector<std::pair<string, SourceLocation>> &getFiles() {
         // Iterate file include locations until fix point
@@ -729,8 +802,29 @@ class TranslateASTVisitor final
             std::vector<void *> childIds;
             auto range = SourceRange(Mac->getDefinitionLoc(), Mac->getDefinitionEndLoc());
             encode_entry_raw(Mac, tag, range, QualType(), false,
-                             false, false, childIds, [Name](CborEncoder *local) {
+                             false, false, childIds, [Name, Mac](CborEncoder *local) {
                                  cbor_encode_string(local, Name.str());
+                                 if (!Mac->isFunctionLike())
+                                     return;
+
+                                 // Parameter names, without the trailing
+                                 // __VA_ARGS__ of variadic macros
+                                 CborEncoder paramEnc;
+                                 auto numParams = Mac->getNumParams();
+                                 if (Mac->isC99Varargs())
+                                     numParams--;
+                                 cbor_encoder_create_array(local, &paramEnc, numParams);
+#if CLANG_VERSION_MAJOR < 7
+                                 auto params = llvm::makeArrayRef(Mac->arg_begin(), Mac->arg_end());
+#else
+                                 auto params = Mac->params();
+#endif
+                                 for (unsigned i = 0; i < numParams; i++) {
+                                     cbor_encode_string(&paramEnc, params[i]->getName().str());
+                                 }
+                                 cbor_encoder_close_container(local, &paramEnc);
+
+                                 cbor_encode_boolean(local, Mac->isVariadic());
                              });
 
         }
@@ -1071,6 +1165,7 @@ class TranslateASTVisitor final
     bool VisitExpr(Expr *E) {
         curMacroExpansionStack.clear();
         curMacroExpansionSource = StringRef();
+        curMacroArgument = {};
 
         // We only translate constant macro objects to Rust consts, so this
         // expression must be constant.
@@ -1087,6 +1182,38 @@ class TranslateASTVisitor final
         auto Begin = Range.getBegin();
         auto End = Range.getEnd();
 
+        // Check whether this expression is an entire macro argument, i.e. it
+        // was substituted for a single use of a macro parameter.
+        if (Begin.isMacroID() && End.isMacroID() &&
+            Mgr.isMacroArgExpansion(Begin) && Mgr.isMacroArgExpansion(End)) {
+#if CLANG_VERSION_MAJOR < 7
+            auto ParamBegin = Mgr.getImmediateExpansionRange(Begin).first;
+            auto ParamEnd = Mgr.getImmediateExpansionRange(End).first;
+#else // CLANG_VERSION_MAJOR >= 7
+            auto ParamBegin = Mgr.getImmediateExpansionRange(Begin).getBegin();
+            auto ParamEnd = Mgr.getImmediateExpansionRange(End).getBegin();
+#endif
+            Token Tok;
+            if (ParamBegin == ParamEnd &&
+                !Lexer::getRawToken(Mgr.getSpellingLoc(ParamBegin), Tok, Mgr,
+                                    Context->getLangOpts()) &&
+                Tok.is(tok::raw_identifier)) {
+                // Spell the argument as written in the macro invocation, so
+                // that arguments which are macro invocations themselves
+                // are spelled unexpanded.
+                auto ArgBegin = Mgr.getExpansionLoc(Mgr.getImmediateSpellingLoc(Begin));
+#if CLANG_VERSION_MAJOR < 7
+                auto ArgEnd = Mgr.getExpansionRange(Mgr.getImmediateSpellingLoc(End)).second;
+#else // CLANG_VERSION_MAJOR >= 7
+                auto ArgEnd = Mgr.getExpansionRange(Mgr.getImmediateSpellingLoc(End)).getEnd();
+#endif
+                auto ArgRange = CharSourceRange::getTokenRange(ArgBegin, ArgEnd);
+                curMacroArgument = std::make_pair(
+                    Tok.getRawIdentifier(),
+                    Lexer::getSourceText(ArgRange, Mgr, Context->getLangOpts()));
+            }
+        }
+
         // Check that we are only expanding a single macro call.
         if (!Begin.isMacroID() || !End.isMacroID() ||
             Mgr.getImmediateMacroCallerLoc(Begin) != Mgr.getImmediateMacroCallerLoc(End))
@@ -1150,8 +1277,43 @@ class TranslateASTVisitor final
         return true;
     }
 
+    // Block literals of Apple's blocks extension can't be translated. They are
+    // exported as unsupported expressions, so they only fail the functions
+    // using them rather than the import of the whole file.
+    bool VisitBlockExpr(BlockExpr *E) {
+        std::vector<void *> childIds;
+        encode_entry(E, TagUnsupportedExpr, childIds, [](CborEncoder *extras) {
+            cbor_encode_string(extras, "block literals");
+        });
+        return true;
+    }
+
     bool VisitGenericSelectionExpr(GenericSelectionExpr *E) {
-        printWarning("Encountered unsupported generic selection expression", E);
+        if (E->isResultDependent()) {
+            printWarning("Encountered dependent generic selection expression", E);
+            return true;
+        }
+
+        // Only the controlling expression and the association clang selected
+        // are exported; the other associations are never evaluated.
+        std::vector<void *> childIds{E->getControllingExpr(),
+                                     E->getResultExpr()};
+#if CLANG_VERSION_MAJOR >= 9
+        auto assocType = E->getAssociation(E->getResultIndex()).getType();
+#else
+        auto assocType = E->getAssocType(E->getResultIndex());
+#endif // CLANG_VERSION_MAJOR
+        encode_entry(E, TagGenericSelectionExpr, childIds,
+                     [this, assocType](CborEncoder *extras) {
+                         // The `default` association has no type
+                         if (assocType.isNull())
+                             cbor_encode_null(extras);
+                         else
+                             cbor_encode_uint(
+                                 extras, typeEncoder.encodeQualType(assocType));
+                     });
+        if (!assocType.isNull())
+            typeEncoder.VisitQualType(assocType);
         return true;
     }
 
@@ -1160,9 +1322,16 @@ class TranslateASTVisitor final
             E->isArgumentType() ? nullptr : E->getArgumentExpr()};
         auto t = E->getTypeOfArgument();
         auto qt = typeEncoder.encodeQualType(t);
+
+        // Clang folds the operator to a constant unless the operand is a
+        // variable-length array.
+        APSInt value;
+        bool is_constant = !E->isValueDependent() &&
+                           E->isIntegerConstantExpr(value, *this->Context);
+
         encode_entry(
             E, TagUnaryExprOrTypeTraitExpr, childIds,
-            [E, t, qt, this](CborEncoder *extras) {
+            [E, t, qt, value, is_constant, this](CborEncoder *extras) {
                 switch (E->getKind()) {
                 case UETT_SizeOf:
                     cbor_encode_text_stringz(extras, "sizeof");
@@ -1211,6 +1380,10 @@ class TranslateASTVisitor final
                     abort();
                 }
                 cbor_encode_uint(extras, qt);
+                if (is_constant)
+                    cbor_encode_uint(extras, value.getZExtValue());
+                else
+                    cbor_encode_null(extras);
             });
         typeEncoder.VisitQualType(t);
         return true;
@@ -1286,6 +1459,11 @@ class TranslateASTVisitor final
      - true: is arrow; false: is dot
      */
     bool VisitMemberExpr(MemberExpr *E) {
+        // Method calls are encoded as calls of free functions, see
+        // encodeMethodCall
+        if (isa<CXXMethodDecl>(E->getMemberDecl()))
+            return true;
+
         std::vector<void *> childIds{E->getBase(),
                                      E->getMemberDecl()->getCanonicalDecl()};
         encode_entry(E, TagMemberExpr, childIds, [E](CborEncoder *extras) {
@@ -1568,6 +1746,11 @@ class TranslateASTVisitor final
     }
 
     bool VisitCallExpr(CallExpr *CE) {
+        if (auto MCE = dyn_cast<CXXMemberCallExpr>(CE)) {
+            encodeMethodCall(MCE);
+            return true;
+        }
+
         std::vector<void *> childIds = {CE->getCallee()};
         for (auto x : CE->arguments()) {
             childIds.push_back(x);
@@ -1603,6 +1786,12 @@ class TranslateASTVisitor final
 
         APSInt value;
         bool hasValue = evaluateConstantInt(E, value);
+        // Values of 128-bit constants don't fit in the encoding, so those are
+        // translated from their subexpression instead
+        if (hasValue) {
+            hasValue = value.isSigned() ? value.getMinSignedBits() <= 64
+                                        : value.getActiveBits() <= 64;
+        }
 
         encode_entry(E, TagConstantExpr, childIds,
                      [hasValue, value](CborEncoder *extra) {
@@ -1668,6 +1857,13 @@ class TranslateASTVisitor final
     // Some function declarations are also function definitions.
     // This method handles both types of declarations.
     bool VisitFunctionDecl(FunctionDecl *FD) {
+        auto MD = dyn_cast<CXXMethodDecl>(FD);
+        if (MD && (MD->isImplicit() || isUnsupportedMethod(MD))) {
+            if (!MD->isImplicit() && MD->isCanonicalDecl())
+                printWarning("Encountered unsupported C++ method", MD);
+            return true;
+        }
+
         if (!FD->isCanonicalDecl()) {
             // Emit non-canonical decl so we have a placeholder to attach comments to
             std::vector<void *> childIds = {FD->getCanonicalDecl()};
@@ -1691,6 +1887,10 @@ class TranslateASTVisitor final
             FD->getBody(paramsFD); // replaces its argument if body exists
 
         std::vector<void *> childIds;
+        if (MD && MD->isInstance()) {
+            encodeThisParam(MD);
+            childIds.push_back(thisParamId(MD));
+        }
         for (auto x : paramsFD->parameters()) {
             auto cd = x->getCanonicalDecl();
             childIds.push_back(cd);
@@ -1699,15 +1899,20 @@ class TranslateASTVisitor final
 
         childIds.push_back(body);
 
-        auto functionType = FD->getType();
+        auto functionType = MD ? methodFunctionType(MD) : FD->getType();
         auto span = paramsFD->getSourceRange();
         encode_entry(
             FD, TagFunctionDecl, span, childIds, functionType,
-            [this, FD](CborEncoder *array) {
+            [this, FD, MD](CborEncoder *array) {
                 auto name = FD->getNameAsString();
+                if (MD)
+                    name = MD->getParent()->getNameAsString() + "_" + name;
                 cbor_encode_string(array, name);
 
-                auto is_global = FD->isGlobal();
+                // Static methods have external linkage unlike static
+                // functions
+                auto is_global =
+                    MD ? MD->isExternallyVisible() : FD->isGlobal();
                 cbor_encode_boolean(array, is_global);
 
                 auto def = FD->getDefinition();
@@ -1741,6 +1946,9 @@ class TranslateASTVisitor final
                         if (auto *aa = dyn_cast<AliasAttr>(attr)) {
                             cbor_encode_text_stringz(
                                 &attr_info, aa->getAliasee().str().c_str());
+                        } else if (auto *sa = dyn_cast<SectionAttr>(attr)) {
+                            cbor_encode_text_stringz(
+                                &attr_info, sa->getName().str().c_str());
                         } else if (auto *va = dyn_cast<VisibilityAttr>(attr)) {
                             const char *vis = VisibilityAttr::ConvertVisibilityTypeToStr(va->getVisibility());
                             cbor_encode_text_stringz(&attr_info, vis);
@@ -1804,7 +2012,15 @@ class TranslateASTVisitor final
         // Non static (externally visible) non definitions shouldn't receive an initializer,
         // otherwise get one
         if (!(is_externally_visible && !is_defn)) {
-            childIds.push_back((void *)VD->getAnyInitializer());
+            auto init = VD->getAnyInitializer();
+            // C++ objects constructed trivially are left uninitialized, like
+            // C objects without an initializer
+            if (auto CE = dyn_cast_or_null<CXXConstructExpr>(init)) {
+                auto ctor = CE->getConstructor();
+                if (ctor->isTrivial() && ctor->isDefaultConstructor())
+                    init = nullptr;
+            }
+            childIds.push_back((void *)init);
         }
 
         // Use the type from the definition in case the extern was an incomplete
@@ -1844,7 +2060,7 @@ class TranslateASTVisitor final
                 if (has_attrs) {
                     auto attrs = def ? def->getAttrs() : VD->getAttrs();
 
-                    for (auto attr : def->attrs()) {
+                    for (auto attr : attrs) {
                         cbor_encode_text_stringz(&attr_info,
                                                  attr->getSpelling());
 
@@ -1874,6 +2090,12 @@ class TranslateASTVisitor final
      - name as string
      */
     bool VisitRecordDecl(RecordDecl *D) {
+        // Every C++ class declares its own name inside of it
+        if (auto CRD = dyn_cast<CXXRecordDecl>(D)) {
+            if (CRD->isInjectedClassName())
+                return true;
+        }
+
         if (!D->isCanonicalDecl()) {
             // Emit non-canonical decl so we have a placeholder to attach comments to
             std::vector<void *> childIds = {D->getCanonicalDecl()};
@@ -1908,7 +2130,8 @@ class TranslateASTVisitor final
             byteSize = layout.getSize().getQuantity();
         }
 
-        auto tag = D->isStruct() ? TagStructDecl : TagUnionDecl;
+        // C++ classes are translated like structs
+        auto tag = D->isUnion() ? TagUnionDecl : TagStructDecl;
 
         encode_entry(
             D, tag, loc, childIds, QualType(),
@@ -2127,6 +2350,33 @@ class TranslateASTVisitor final
         return true;
     }
 
+    // C++ `true` and `false` are encoded as integer literals of type `bool`
+    bool VisitCXXBoolLiteralExpr(CXXBoolLiteralExpr *E) {
+        std::vector<void *> childIds;
+        encode_entry(E, TagIntegerLiteral, childIds, [E](CborEncoder *array) {
+            cbor_encode_uint(array, E->getValue());
+            cbor_encode_uint(array, 10);
+        });
+        return true;
+    }
+
+    // C++ `nullptr` is encoded as the null pointer constant `0`, which is
+    // converted to pointers with the same implicit casts
+    bool VisitCXXNullPtrLiteralExpr(CXXNullPtrLiteralExpr *E) {
+        std::vector<void *> childIds;
+        auto isVaList = false;
+        auto encodeMacroExpansions = true;
+        encode_entry_raw(E, TagIntegerLiteral, E->getSourceRange(),
+                         Context->IntTy, E->isRValue(), isVaList,
+                         encodeMacroExpansions, childIds,
+                         [](CborEncoder *array) {
+                             cbor_encode_uint(array, 0);
+                             cbor_encode_uint(array, 10);
+                         });
+        typeEncoder.VisitQualType(Context->IntTy);
+        return true;
+    }
+
     bool VisitCharacterLiteral(CharacterLiteral *L) {
         std::vector<void *> childIds;
         encode_entry(L, TagCharacterLiteral, childIds, [L](CborEncoder *array) {
@@ -2218,6 +2468,177 @@ class TranslateASTVisitor final
         return false;
     }
 
+    //
+    // C++
+    //
+
+    // We accept the subset of C++ that reads like C with classes. Namespaces
+    // are flattened, classes are translated like structs of their fields and
+    // their methods like free functions named `Class_method`. Instance
+    // methods take a pointer to their object as an explicit first parameter,
+    // `this`. Templates and the methods C has no equivalent of, like
+    // constructors and virtual methods, are skipped with a warning.
+
+    bool TraverseClassTemplateDecl(ClassTemplateDecl *D) {
+        printWarning("Encountered unsupported C++ class template", D);
+        return true;
+    }
+
+    bool TraverseFunctionTemplateDecl(FunctionTemplateDecl *D) {
+        printWarning("Encountered unsupported C++ function template", D);
+        return true;
+    }
+
+    bool TraverseCXXMethodDecl(CXXMethodDecl *MD) {
+        auto outerMethod = currentMethod;
+        currentMethod = MD;
+        auto result =
+            RecursiveASTVisitor<TranslateASTVisitor>::TraverseCXXMethodDecl(MD);
+        currentMethod = outerMethod;
+        return result;
+    }
+
+    bool VisitCXXRecordDecl(CXXRecordDecl *D) {
+        if (D->hasDefinition() && D == D->getDefinition() &&
+            (D->getNumBases() > 0 || D->isPolymorphic()))
+            printWarning("Encountered unsupported C++ class with base classes "
+                         "or virtual methods; only its fields are translated",
+                         D);
+        return true;
+    }
+
+    // `this` refers to the parameter synthesized for the current method
+    bool VisitCXXThisExpr(CXXThisExpr *E) {
+        if (!currentMethod) {
+            printWarning("Encountered unsupported use of `this` outside of a "
+                         "method", E);
+            return true;
+        }
+        std::vector<void *> childIds{thisParamId(currentMethod)};
+        encode_entry(E, TagDeclRefExpr, childIds);
+        return true;
+    }
+
+    // Whether a method has no equivalent free function
+    bool isUnsupportedMethod(CXXMethodDecl *MD) {
+        return isa<CXXConstructorDecl>(MD) || isa<CXXDestructorDecl>(MD) ||
+               isa<CXXConversionDecl>(MD) || MD->isVirtual() ||
+               MD->isOverloadedOperator();
+    }
+
+    // ID of the `this` parameter synthesized for an instance method. Decls
+    // are aligned, so this can't be the ID of another node.
+    void *thisParamId(CXXMethodDecl *MD) {
+        return (char *)MD->getCanonicalDecl() + 1;
+    }
+
+    QualType thisParamType(CXXMethodDecl *MD) {
+        auto recordType = Context->getRecordType(MD->getParent());
+        if (MD->isConst())
+            recordType.addConst();
+        return Context->getPointerType(recordType);
+    }
+
+    // The type of a method as a free function, which takes `this` first
+    QualType methodFunctionType(CXXMethodDecl *MD) {
+        auto FPT = MD->getType()->getAs<FunctionProtoType>();
+        std::vector<QualType> paramTypes;
+        if (MD->isInstance())
+            paramTypes.push_back(thisParamType(MD));
+        for (auto T : FPT->param_types())
+            paramTypes.push_back(T);
+
+        FunctionProtoType::ExtProtoInfo EPI;
+        EPI.Variadic = FPT->isVariadic();
+        return Context->getFunctionType(FPT->getReturnType(), paramTypes, EPI);
+    }
+
+    void encodeThisParam(CXXMethodDecl *MD) {
+        auto T = thisParamType(MD);
+        // Parameters have no initializer
+        std::vector<void *> childIds{nullptr};
+        auto rvalue = false;
+        auto isVaList = false;
+        auto encodeMacroExpansions = false;
+        encode_entry_raw(thisParamId(MD), TagVarDecl, MD->getSourceRange(), T,
+                         rvalue, isVaList, encodeMacroExpansions, childIds,
+                         [](CborEncoder *array) {
+                             cbor_encode_string(array, "this");
+                             // static duration, thread duration, externally
+                             // visible, definition
+                             cbor_encode_boolean(array, false);
+                             cbor_encode_boolean(array, false);
+                             cbor_encode_boolean(array, false);
+                             cbor_encode_boolean(array, true);
+
+                             CborEncoder attr_info;
+                             cbor_encoder_create_array(array, &attr_info, 0);
+                             cbor_encoder_close_container(array, &attr_info);
+                         });
+        typeEncoder.VisitQualType(T);
+    }
+
+    // Encode `obj.method(args)` as the call `Class_method(&obj, args)`. The
+    // nodes of the callee and of `&obj` don't exist in the clang AST, so they
+    // get IDs right after that of the call, which can't be IDs of other nodes.
+    void encodeMethodCall(CXXMemberCallExpr *CE) {
+        auto ME = dyn_cast<MemberExpr>(CE->getCallee()->IgnoreParens());
+        auto MD = CE->getMethodDecl();
+        if (!ME || !MD) {
+            printWarning("Encountered unsupported C++ method call", CE);
+            return;
+        }
+        MD = MD->getCanonicalDecl();
+
+        auto range = CE->getSourceRange();
+        auto isVaList = false;
+        auto encodeMacroExpansions = false;
+
+        auto fnType = methodFunctionType(MD);
+        void *declRefId = (char *)CE + 1;
+        std::vector<void *> declRefChildIds{MD};
+        encode_entry_raw(declRefId, TagDeclRefExpr, range, fnType, false,
+                         isVaList, encodeMacroExpansions, declRefChildIds,
+                         [](CborEncoder *) {});
+        typeEncoder.VisitQualType(fnType);
+
+        auto fnPtrType = Context->getPointerType(fnType);
+        void *calleeId = (char *)CE + 2;
+        std::vector<void *> calleeChildIds{declRefId};
+        encode_entry_raw(calleeId, TagImplicitCastExpr, range, fnPtrType, true,
+                         isVaList, encodeMacroExpansions, calleeChildIds,
+                         [](CborEncoder *array) {
+                             cbor_encode_text_stringz(array,
+                                                      "FunctionToPointerDecay");
+                         });
+        typeEncoder.VisitQualType(fnPtrType);
+
+        std::vector<void *> childIds{calleeId};
+        if (MD->isInstance()) {
+            void *objId = ME->getBase();
+            if (!ME->isArrow()) {
+                auto objType = Context->getPointerType(ME->getBase()->getType());
+                objId = (char *)CE + 3;
+                std::vector<void *> objChildIds{ME->getBase()};
+                encode_entry_raw(objId, TagUnaryOperator, range, objType, true,
+                                 isVaList, encodeMacroExpansions, objChildIds,
+                                 [](CborEncoder *array) {
+                                     cbor_encode_string(array, "&");
+                                     cbor_encode_boolean(array, true);
+                                 });
+                typeEncoder.VisitQualType(objType);
+            }
+            childIds.push_back(objId);
+        }
+        for (auto x : CE->arguments()) {
+            childIds.push_back(x);
+        }
+        encode_entry(CE, TagCallExpr, childIds);
+
+        // Methods only called might never be traversed otherwise
+        TraverseDecl(MD);
+    }
+
     DiagnosticBuilder getDiagBuilder(SourceLocation Loc,
                                      DiagnosticsEngine::Level Lvl) {
         auto &DiagEngine = Context->getDiagnostics();
@@ -2278,7 +2699,7 @@ void TypeEncoder::VisitRecordType(const RecordType *T) {
         VisitQualType(qt);
     }
 
-    auto tag = T->isStructureType() ? TagStructType : TagUnionType;
+    auto tag = T->isUnionType() ? TagUnionType : TagStructType;
 
     encodeType(T, tag, [T](CborEncoder *local) {
         cbor_encode_uint(local, uintptr_t(T->getDecl()->getCanonicalDecl()));
@@ -2334,6 +2755,25 @@ void TypeEncoder::VisitVariableArrayType(const VariableArrayType *T) {
 //    abort();
 //}
 
+// Collect the top-level declarations of a translation unit. Declarations in
+// namespaces and `extern "C"` blocks are flattened, and the methods of C++
+// classes follow their class, since they are translated as free functions.
+static void collectTopLevelDecls(DeclContext *DC, std::vector<Decl *> &decls) {
+    for (auto d : DC->decls()) {
+        if (isa<NamespaceDecl>(d) || isa<LinkageSpecDecl>(d)) {
+            collectTopLevelDecls(cast<DeclContext>(d), decls);
+            continue;
+        }
+        decls.push_back(d);
+        if (auto RD = dyn_cast<CXXRecordDecl>(d)) {
+            for (auto m : RD->decls()) {
+                if (isa<CXXMethodDecl>(m))
+                    decls.push_back(m);
+            }
+        }
+    }
+}
+
 class TranslateConsumer : public clang::ASTConsumer {
     Outputs *outputs;
     const std::string outfile;
@@ -2358,7 +2798,7 @@ class TranslateConsumer : public clang::ASTConsumer {
             cbor_encoder_init(&encoder, buffer, len, 0);
 
             CborEncoder outer;
-            cbor_encoder_create_array(&encoder, &outer, 5);
+            cbor_encoder_create_array(&encoder, &outer, 6);
 
             CborEncoder array;
 
@@ -2372,7 +2812,9 @@ class TranslateConsumer : public clang::ASTConsumer {
 
             // 2. Track all of the top-level declarations
             cbor_encoder_create_array(&outer, &array, CborIndefiniteLength);
-            for (auto d : translation_unit->decls()) {
+            std::vector<Decl *> decls;
+            collectTopLevelDecls(translation_unit, decls);
+            for (auto d : decls) {
                 if(!d->isCanonicalDecl() && isa<VarDecl>(d)) {
                     auto canonical_decl = d->getCanonicalDecl();
                     auto var_decl = cast<VarDecl>(canonical_decl);
@@ -2389,6 +2831,12 @@ class TranslateConsumer : public clang::ASTConsumer {
                     continue;
                 }
 
+                // Neither are the C++ declarations we don't support, like
+                // templates and `using` declarations
+                if (Context.getLangOpts().CPlusPlus && !visitor.hasEntry(d)) {
+                    continue;
+                }
+
                 cbor_encode_uint(&array, reinterpret_cast<std::uintptr_t>(d));
             }
             cbor_encoder_close_container(&outer, &array);
@@ -2438,6 +2886,22 @@ class TranslateConsumer : public clang::ASTConsumer {
             // 5. Target VaList type as BuiltiVaListKind
             cbor_encode_uint(&outer, static_cast<std::uintptr_t>(Context.getTargetInfo().getBuiltinVaListKind()));
 
+            // 6. Target triple and the target-dependent properties of the
+            // builtin types: pointer, short, int, long and long long widths,
+            // the signedness of char, and the width and signedness of wchar_t
+            auto &TI = Context.getTargetInfo();
+            cbor_encoder_create_array(&outer, &array, 9);
+            cbor_encode_string(&array, TI.getTriple().str());
+            cbor_encode_uint(&array, TI.getPointerWidth(0));
+            cbor_encode_uint(&array, TI.getShortWidth());
+            cbor_encode_uint(&array, TI.getIntWidth());
+            cbor_encode_uint(&array, TI.getLongWidth());
+            cbor_encode_uint(&array, TI.getLongLongWidth());
+            cbor_encode_boolean(&array, Context.CharTy->isSignedIntegerType());
+            cbor_encode_uint(&array, Context.getTypeSize(Context.getWideCharType()));
+            cbor_encode_boolean(&array, Context.getWideCharType()->isSignedIntegerType());
+            cbor_encoder_close_container(&outer, &array);
+
             cbor_encoder_close_container(&encoder, &outer);
         };
 
@@ -2467,7 +2931,9 @@ class TranslateAction : public clang::ASTFrontendAction {
     virtual std::unique_ptr<clang::ASTConsumer>
     CreateASTConsumer(clang::CompilerInstance &Compiler,
                       llvm::StringRef InFile) {
-        if(this->getCurrentFileKind().getLanguage() != InputKind::Language::C) {
+        auto language = this->getCurrentFileKind().getLanguage();
+        if (language != InputKind::Language::C &&
+            language != InputKind::Language::CXX) {
             return nullptr;
         }
 
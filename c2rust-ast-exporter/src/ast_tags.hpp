@@ -83,6 +83,11 @@ enum ASTEntryTag {
 
     TagAtomicExpr,
 
+    TagGenericSelectionExpr,
+
+    // Expressions of extensions we can't translate, like block literals
+    TagUnsupportedExpr,
+
     TagIntegerLiteral = 300,
     TagStringLiteral,
     TagCharacterLiteral,
@@ -139,6 +144,10 @@ enum TypeTag {
     TagBlockPointer,
     TagComplexType,
     TagHalf,
+    TagBitIntType,
+    TagChar16,
+    TagChar32,
+    TagAutoType,
 };
 
 enum StringTypeTag {
@@ -82,6 +82,9 @@ pub struct AstNode {
     // macro definitions.
     pub macro_expansions: Vec<u64>,
     pub macro_expansion_text: Option<String>,
+    // Name of the function-like macro parameter this node was substituted
+    // for, along with the spelling of the macro argument.
+    pub macro_argument: Option<(String, String)>,
     pub extras: Vec<Value>,
 }
 
@@ -119,6 +122,38 @@ pub struct AstContext {
     pub comments: Vec<CommentNode>,
     pub files: Vec<SrcFile>,
     pub va_list_kind: BuiltinVaListKind,
+    pub target_info: TargetInfo,
+}
+
+/// Properties of the target clang compiled the input for
+#[derive(Debug, Clone)]
+pub struct TargetInfo {
+    pub triple: String,
+    pub pointer_width: u64,
+    pub short_width: u64,
+    pub int_width: u64,
+    pub long_width: u64,
+    pub long_long_width: u64,
+    pub char_is_signed: bool,
+    pub wchar_width: u64,
+    pub wchar_is_signed: bool,
+}
+
+impl Default for TargetInfo {
+    /// The LP64 layout of x86_64 Linux, for contexts not built from a clang AST
+    fn default() -> Self {
+        TargetInfo {
+            triple: "x86_64-unknown-linux-gnu".to_string(),
+            pointer_width: 64,
+            short_width: 16,
+            int_width: 32,
+            long_width: 64,
+            long_long_width: 64,
+            char_is_signed: true,
+            wchar_width: 32,
+            wchar_is_signed: true,
+        }
+    }
 }
 
 pub fn expect_opt_str(val: &Value) -> Option<Option<&str>> {
@@ -160,16 +195,40 @@ pub fn process(items: Value) -> error::Result<AstContext> {
     let mut types: HashMap<u64, TypeNode> = HashMap::new();
     let mut comments: Vec<CommentNode> = vec![];
 
-    let (all_nodes, top_nodes, files, raw_comments, va_list_kind): (
+    let (all_nodes, top_nodes, files, raw_comments, va_list_kind, target_info): (
         Vec<VecDeque<Value>>,
         Vec<u64>,
         Vec<(String, Option<(u64, u64, u64)>)>,
         Vec<(u64, u64, u64, ByteBuf)>,
         u64,
+        (String, u64, u64, u64, u64, u64, bool, u64, bool),
     ) = from_value(items)?;
 
     let va_list_kind = import_va_list_kind(va_list_kind);
 
+    let (
+        triple,
+        pointer_width,
+        short_width,
+        int_width,
+        long_width,
+        long_long_width,
+        char_is_signed,
+        wchar_width,
+        wchar_is_signed,
+    ) = target_info;
+    let target_info = TargetInfo {
+        triple,
+        pointer_width,
+        short_width,
+        int_width,
+        long_width,
+        long_long_width,
+        char_is_signed,
+        wchar_width,
+        wchar_is_signed,
+    };
+
     for (fileid, line, column, bytes) in raw_comments {
         comments.push(CommentNode {
             loc: SrcLoc { fileid, line, column },
@@ -225,6 +284,9 @@ pub fn process(items: Value) -> error::Result<AstContext> {
             let macro_expansion_text = expect_opt_str(&entry.pop_front().unwrap()).unwrap()
                 .map(|s| s.to_string());
 
+            let macro_argument =
+                from_value::<Option<(String, String)>>(entry.pop_front().unwrap()).unwrap();
+
             let node = AstNode {
                 tag: import_ast_tag(tag),
                 children,
@@ -239,6 +301,7 @@ pub fn process(items: Value) -> error::Result<AstContext> {
                 rvalue,
                 macro_expansions,
                 macro_expansion_text,
+                macro_argument,
                 extras: entry.into_iter().collect(),
             };
 
@@ -259,5 +322,6 @@ pub fn process(items: Value) -> error::Result<AstContext> {
         comments,
         files,
         va_list_kind,
+        target_info,
     })
 }
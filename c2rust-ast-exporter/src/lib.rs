@@ -30,7 +30,7 @@ pub fn get_untyped_ast(
     debug: bool,
 ) -> Result<clang_ast::AstContext, Error> {
     let cbors = get_ast_cbors(file_path, cc_db, extra_args, debug);
-    let buffer = cbors.values().next().ok_or(Error::new(
+    let buffer = cbors.into_iter().next().map(|(_, buffer)| buffer).ok_or(Error::new(
         ErrorKind::InvalidData,
         "Could not parse input file",
     ))?;
@@ -40,7 +40,9 @@ pub fn get_untyped_ast(
     // cbor_file.write_all(&buffer[..])?;
     // eprintln!("Dumped CBOR to {}", cbor_path.to_string_lossy());
 
+    // Free the encoded AST as soon as it has been decoded
     let items: Value = from_slice(&buffer[..]).unwrap();
+    drop(buffer);
 
     match clang_ast::process(items) {
         Ok(cxt) => Ok(cxt),
@@ -365,9 +365,9 @@ def do_recursive_body(se, target1, target2):
             else:
                 outer_span_expr = f.attrs.get('seq_rewrite_outer_span')
                 if outer_span_expr is not None:
-                    # Replace `self.foo` with `foo2`, since we want the *old*
+                    # Replace `self.foo` with `foo1`, since we want the *old*
                     # outer span.
-                    outer_span_expr = rewrite_field_expr(outer_span_expr, '%s2')
+                    outer_span_expr = rewrite_field_expr(outer_span_expr, '%s1')
                 else:
                     outer_span_expr = 'DUMMY_SP'
                 mk_rewrite = lambda old, new: \
@@ -707,3 +707,71 @@ pub fn dump_results(dcx: &RefactorCtxt, results: &AnalysisResult) {
         }
     }
 }
+
+/// Format the analysis results as a report for the user.  Each static, field, and function
+/// signature is listed with the permission inferred for each of its pointers, along with the
+/// location of its definition.  Functions with several monomorphizations have one signature for
+/// each.
+pub fn report(dcx: &RefactorCtxt, results: &AnalysisResult) -> String {
+    use std::fmt::Write;
+
+    let arena = SyncDroplessArena::default();
+    let new_lcx = LabeledTyCtxt::new(&arena);
+    let format_sig = |sig: VFnSig, assign: &IndexVec<Var, ConcretePerm>| {
+        let mut func = |p: &Option<_>| p.as_ref().map(|&v| assign[v]);
+
+        let inputs = new_lcx.relabel_slice(sig.inputs, &mut func);
+        let output = new_lcx.relabel(sig.output, &mut func);
+        format!("{:?} -> {:?}", pretty_slice(inputs), Pretty(output))
+    };
+
+    let tcx = dcx.ty_ctxt();
+    let path_str = |def_id| tcx.def_path(def_id).to_string_no_crate();
+    let span_str = |def_id| dcx.session().source_map().span_to_string(tcx.def_span(def_id));
+    let sorted_ids = |ids: Vec<DefId>| {
+        let mut ids = ids.into_iter().map(|id| (path_str(id), id)).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    };
+
+    let mut out = String::new();
+    writeln!(out, "# Ownership of pointers: READ is `&T`, WRITE is `&mut T`, \
+                   MOVE is `Box<T>`").unwrap();
+
+    fn has_ptrs(ty: PTy) -> bool {
+        ty.label.is_some() || ty.args.iter().any(|&arg| has_ptrs(arg))
+    }
+
+    let statics = results.statics.iter()
+        .filter(|&(_, &ty)| has_ptrs(ty))
+        .map(|(&id, _)| id)
+        .collect();
+    for (path, id) in sorted_ids(statics) {
+        writeln!(out, "\n{}  // {}", path, span_str(id)).unwrap();
+        writeln!(out, "  {:?}", Pretty(results.statics[&id])).unwrap();
+    }
+
+    let funcs = results.funcs.iter()
+        .filter(|&(_, fr)| fr.num_sig_vars > 0)
+        .map(|(&id, _)| id)
+        .collect();
+    for (path, id) in sorted_ids(funcs) {
+        let fr = &results.funcs[&id];
+        writeln!(out, "\nfn {}  // {}", path, span_str(id)).unwrap();
+        for i in 0..fr.num_monos {
+            let mr = &results.monos[&(id, i)];
+            match fr.variants {
+                Some(ref var_ids) => {
+                    write!(out, "  variant {}: ", path_str(var_ids[i])).unwrap()
+                }
+                None if fr.num_monos > 1 => {
+                    write!(out, "  mono {:?}: ", mr.suffix).unwrap()
+                }
+                None => write!(out, "  ").unwrap(),
+            }
+            writeln!(out, "{}", format_sig(fr.sig, &mr.assign)).unwrap();
+        }
+    }
+
+    out
+}
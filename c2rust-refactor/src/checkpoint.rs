@@ -0,0 +1,370 @@
+//! Checkpoints for resuming a command batch after a failure.
+//!
+//! With `--checkpoint DIR`, the driver saves its progress after each command: the rewritten text
+//! of every source file changed so far, the current marks, and the number of commands completed.
+//! Marks are saved by node kind and source range within the rewritten text, since node IDs don't
+//! survive reparsing.  If a later command fails, rerunning the batch with the same `DIR` reads the
+//! saved text in place of the original sources, restores the marks, and continues at the first
+//! command that didn't complete.  Analysis results aren't saved - commands recompute them from the
+//! restored crate as needed.
+//!
+//! A checkpoint is only used if the commands it completed match the start of the new batch, so
+//! the failing command (and anything after it) can be edited before resuming.  The checkpoint is
+//! deleted once the whole batch succeeds.
+use json::{self, JsonValue};
+use rustc_interface::interface;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use syntax::ast::*;
+use syntax::source_map::Span;
+use syntax::symbol::Symbol;
+use syntax::visit::{self, FnKind, Visitor};
+use syntax_pos::FileName;
+
+use crate::command::RefactorState;
+use crate::driver::Phase;
+use crate::file_io::{FileIO, RealFileIO};
+use crate::rewrite::files;
+use crate::Command;
+
+/// A mark, located by the span of the marked node in the checkpointed text.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct SavedMark {
+    file: String,
+    lo: usize,
+    hi: usize,
+    kind: String,
+    label: String,
+}
+
+impl SavedMark {
+    fn encode(&self) -> JsonValue {
+        object! {
+            "file" => self.file.as_str(),
+            "lo" => self.lo,
+            "hi" => self.hi,
+            "kind" => self.kind.as_str(),
+            "label" => self.label.as_str(),
+        }
+    }
+
+    fn decode(j: &JsonValue) -> Option<SavedMark> {
+        Some(SavedMark {
+            file: j["file"].as_str()?.to_owned(),
+            lo: j["lo"].as_usize()?,
+            hi: j["hi"].as_usize()?,
+            kind: j["kind"].as_str()?.to_owned(),
+            label: j["label"].as_str()?.to_owned(),
+        })
+    }
+}
+
+pub struct Checkpoint {
+    path: PathBuf,
+    /// The commands of the batch, each as its name followed by its arguments.
+    commands: Vec<Vec<String>>,
+    completed: usize,
+    files: BTreeMap<PathBuf, String>,
+    marks: Vec<SavedMark>,
+}
+
+impl Checkpoint {
+    /// Open the checkpoint for running `commands` on target number `index`, loading any progress
+    /// saved by a previous run of the same batch.  `dir` must already exist.
+    pub fn open(dir: &Path, index: usize, commands: &[Command]) -> io::Result<Checkpoint> {
+        let mut cp = Checkpoint {
+            path: dir.join(format!("checkpoint.{}.json", index)),
+            commands: commands
+                .iter()
+                .map(|c| iter::once(&c.name).chain(&c.args).cloned().collect())
+                .collect(),
+            completed: 0,
+            files: BTreeMap::new(),
+            marks: Vec::new(),
+        };
+        if cp.path.exists() {
+            cp.load()?;
+        }
+        Ok(cp)
+    }
+
+    /// The number of commands that already ran to completion.
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    fn load(&mut self) -> io::Result<()> {
+        let s = fs::read_to_string(&self.path)?;
+        let j = json::parse(&s)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        let saved = j["commands"]
+            .members()
+            .map(|c| {
+                c.members()
+                    .filter_map(|arg| arg.as_str())
+                    .map(|arg| arg.to_owned())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let completed = j["completed"].as_usize().unwrap_or(0);
+        if completed > saved.len()
+            || completed > self.commands.len()
+            || saved[..completed] != self.commands[..completed]
+        {
+            warn!(
+                "ignoring checkpoint {:?}: its completed commands don't match this batch",
+                self.path
+            );
+            return Ok(());
+        }
+
+        self.completed = completed;
+        self.files = j["files"]
+            .members()
+            .filter_map(|f| Some((PathBuf::from(f["path"].as_str()?), f["text"].as_str()?.to_owned())))
+            .collect();
+        self.marks = j["marks"].members().filter_map(SavedMark::decode).collect();
+        Ok(())
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let j = object! {
+            "commands" => JsonValue::Array(self.commands.iter().map(|c| {
+                JsonValue::Array(c.iter().map(|arg| arg.as_str().into()).collect())
+            }).collect()),
+            "completed" => self.completed,
+            "files" => JsonValue::Array(self.files.iter().map(|(path, text)| object! {
+                "path" => path.to_string_lossy().into_owned(),
+                "text" => text.as_str(),
+            }).collect()),
+            "marks" => JsonValue::Array(self.marks.iter().map(|m| m.encode()).collect()),
+        };
+
+        // Write the new checkpoint alongside the old one and then move it into place, so a crash
+        // partway through doesn't leave a truncated checkpoint behind.
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json::stringify_pretty(j, 2))?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    /// Make `io` read the checkpointed text in place of the original sources.
+    pub fn preload(&self, io: &RealFileIO) -> io::Result<()> {
+        for (path, text) in &self.files {
+            io.preload_file(path, text)?;
+        }
+        Ok(())
+    }
+
+    /// Re-establish the checkpointed marks on the crate loaded from the checkpointed text.
+    pub fn restore_marks(&self, state: &mut RefactorState) -> interface::Result<()> {
+        if self.marks.is_empty() {
+            return Ok(());
+        }
+
+        let marks = &self.marks;
+        state.transform_crate(Phase::Phase1, |st, cx| {
+            let sm = cx.session().source_map();
+            let mut by_span = HashMap::new();
+            for (id, (kind, span)) in node_spans(&st.krate()) {
+                if span.is_dummy() || span.from_expansion() {
+                    continue;
+                }
+                let lo = sm.lookup_byte_offset(span.lo());
+                let hi = sm.lookup_byte_offset(span.hi());
+                if let FileName::Real(ref path) = lo.sf.name {
+                    let file = path.to_string_lossy().into_owned();
+                    by_span.insert((file, lo.pos.0 as usize, hi.pos.0 as usize, kind), id);
+                }
+            }
+
+            for m in marks {
+                let label = Symbol::intern(&m.label);
+                if m.kind == "crate" {
+                    st.add_mark(CRATE_NODE_ID, label);
+                    continue;
+                }
+                match by_span.get(&(m.file.clone(), m.lo, m.hi, &m.kind as &str)) {
+                    Some(&id) => st.add_mark(id, label),
+                    None => warn!(
+                        "checkpoint: no {} at {}:{}-{} to mark {:?}",
+                        m.kind, m.file, m.lo, m.hi, m.label
+                    ),
+                }
+            }
+        })
+    }
+
+    /// Record that the first `completed` commands have finished, saving the current state of the
+    /// crate.  If no crate is loaded (for example, right after a `commit`), there is nothing new
+    /// to save, and a resumed run will start over from the last checkpoint that had one.
+    pub fn record(&mut self, state: &RefactorState, completed: usize) -> io::Result<()> {
+        let (krate, rw) = match state.pending_rewrite() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        let sm = state.source_map();
+
+        // The rewrite is relative to the sources the crate was loaded from, so a previously saved
+        // file that the rewrite doesn't touch now matches those sources.
+        for (path, text) in self.files.iter_mut() {
+            if let Some(sf) = sm.get_source_file(&FileName::Real(path.clone())) {
+                if let Some(ref src) = sf.src {
+                    *text = (**src).clone();
+                }
+            }
+        }
+        let capture = CaptureFileIO::default();
+        files::rewrite_files_with(sm, &rw, &capture)?;
+        self.files.extend(capture.files.into_inner().unwrap());
+
+        let spans = node_spans(krate);
+        self.marks.clear();
+        for &(id, label) in state.marks() {
+            let label = label.as_str().to_string();
+            if id == CRATE_NODE_ID {
+                self.marks.push(SavedMark {
+                    file: String::new(),
+                    lo: 0,
+                    hi: 0,
+                    kind: "crate".to_owned(),
+                    label,
+                });
+                continue;
+            }
+
+            let saved = spans.get(&id).and_then(|&(kind, span)| {
+                if span.is_dummy() || span.from_expansion() {
+                    return None;
+                }
+                let file = match sm.lookup_byte_offset(span.lo()).sf.name {
+                    FileName::Real(ref path) => path.to_string_lossy().into_owned(),
+                    _ => return None,
+                };
+                let (lo, hi) = files::rewritten_range(sm, &rw, span)?;
+                Some(SavedMark {
+                    file,
+                    lo,
+                    hi,
+                    kind: kind.to_owned(),
+                    label: label.clone(),
+                })
+            });
+            match saved {
+                Some(m) => self.marks.push(m),
+                None => warn!(
+                    "checkpoint: can't locate node {:?} in the rewritten text; \
+                     its mark {:?} won't be restored on resume",
+                    id, label
+                ),
+            }
+        }
+        self.marks.sort();
+
+        self.completed = completed;
+        self.save()
+    }
+
+    /// Discard the checkpoint once the batch has finished.
+    pub fn finish(self) -> io::Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `FileIO` that collects the rewritten files instead of writing them anywhere.
+#[derive(Default)]
+struct CaptureFileIO {
+    files: Mutex<Vec<(PathBuf, String)>>,
+}
+
+impl FileIO for CaptureFileIO {
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write_file(&self, path: &Path, s: &str) -> io::Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .push((path.to_owned(), s.to_owned()));
+        Ok(())
+    }
+}
+
+/// Collect the kind and span of every node that can carry a mark.
+fn node_spans(krate: &Crate) -> HashMap<NodeId, (&'static str, Span)> {
+    let mut v = NodeSpanVisitor {
+        spans: HashMap::new(),
+    };
+    visit::walk_crate(&mut v, krate);
+    v.spans
+}
+
+struct NodeSpanVisitor {
+    spans: HashMap<NodeId, (&'static str, Span)>,
+}
+
+impl<'ast> Visitor<'ast> for NodeSpanVisitor {
+    fn visit_item(&mut self, x: &'ast Item) {
+        self.spans.insert(x.id, ("item", x.span));
+        visit::walk_item(self, x);
+    }
+
+    fn visit_impl_item(&mut self, x: &'ast ImplItem) {
+        self.spans.insert(x.id, ("impl_item", x.span));
+        visit::walk_impl_item(self, x);
+    }
+
+    fn visit_trait_item(&mut self, x: &'ast TraitItem) {
+        self.spans.insert(x.id, ("trait_item", x.span));
+        visit::walk_trait_item(self, x);
+    }
+
+    fn visit_foreign_item(&mut self, x: &'ast ForeignItem) {
+        self.spans.insert(x.id, ("foreign_item", x.span));
+        visit::walk_foreign_item(self, x);
+    }
+
+    fn visit_stmt(&mut self, x: &'ast Stmt) {
+        self.spans.insert(x.id, ("stmt", x.span));
+        visit::walk_stmt(self, x);
+    }
+
+    fn visit_expr(&mut self, x: &'ast Expr) {
+        self.spans.insert(x.id, ("expr", x.span));
+        visit::walk_expr(self, x);
+    }
+
+    fn visit_pat(&mut self, x: &'ast Pat) {
+        self.spans.insert(x.id, ("pat", x.span));
+        visit::walk_pat(self, x);
+    }
+
+    fn visit_ty(&mut self, x: &'ast Ty) {
+        self.spans.insert(x.id, ("ty", x.span));
+        visit::walk_ty(self, x);
+    }
+
+    fn visit_fn(&mut self, kind: FnKind<'ast>, fd: &'ast FnDecl, span: Span, _id: NodeId) {
+        for arg in &fd.inputs {
+            self.spans.insert(arg.id, ("arg", arg.span));
+        }
+        visit::walk_fn(self, kind, fd, span);
+    }
+
+    fn visit_struct_field(&mut self, x: &'ast StructField) {
+        self.spans.insert(x.id, ("field", x.span));
+        visit::walk_struct_field(self, x);
+    }
+
+    fn visit_mac(&mut self, x: &'ast Mac) {
+        visit::walk_mac(self, x);
+    }
+}
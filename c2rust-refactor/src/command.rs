@@ -34,7 +34,7 @@ use crate::driver::{self, Phase};
 use crate::file_io::FileIO;
 use crate::node_map::NodeMap;
 use crate::rewrite;
-use crate::rewrite::files;
+use crate::rewrite::{files, TextRewrite};
 use crate::span_fix;
 use crate::RefactorCtxt;
 use c2rust_ast_builder::IntoSymbol;
@@ -260,13 +260,10 @@ impl RefactorState {
     /// matches the text on disk) as the basis for rewriting.
     #[cfg_attr(feature = "profile", flame)]
     pub fn save_crate(&mut self) {
-        if let None = self.krate {
-            return;
-        }
-
-        let disk_state = self.disk_state.as_ref().unwrap();
-        let old = &disk_state.orig_krate;
-        let new = self.krate.as_ref().unwrap();
+        let (new, rw) = match self.pending_rewrite() {
+            Some(x) => x,
+            None => return,
+        };
         let node_id_map = self.node_map.clone().into_inner();
 
         self.file_io
@@ -278,14 +275,25 @@ impl RefactorState {
             )
             .unwrap();
 
-        let rw = rewrite::rewrite(self.session(), old, new, &disk_state.comment_map, node_id_map, |map| {
-            map_ast_into(&self.parsed_nodes, map);
-        });
         // Note that `rewrite_files_with` does not read any files from disk - it uses the
         // `SourceMap` to get files' original source text.
         files::rewrite_files_with(self.source_map(), &rw, &*self.file_io).unwrap();
     }
 
+    /// Compute the rewrite that `save_crate` would apply, without writing anything out.  Returns
+    /// the current crate along with the rewrite, or `None` if no crate is loaded.
+    pub fn pending_rewrite(&self) -> Option<(&Crate, TextRewrite)> {
+        let new = self.krate.as_ref()?;
+        let disk_state = self.disk_state.as_ref().unwrap();
+        let old = &disk_state.orig_krate;
+        let node_id_map = self.node_map.clone().into_inner();
+
+        let rw = rewrite::rewrite(self.session(), old, new, &disk_state.comment_map, node_id_map, |map| {
+            map_ast_into(&self.parsed_nodes, map);
+        });
+        Some((new, rw))
+    }
+
     #[cfg_attr(feature = "profile", flame)]
     pub fn transform_crate<F, R>(&mut self, phase: Phase, f: F) -> interface::Result<R>
     where
@@ -711,6 +719,15 @@ impl CommandState {
         is
     }
 
+    pub fn parse_stmts(&self, cx: &RefactorCtxt, src: &str) -> Vec<Stmt> {
+        let mut ss = driver::parse_stmts(cx.session(), src);
+        for s in &mut ss {
+            self.process_parsed(s);
+            self.parsed_nodes.borrow_mut().stmts.push(s.clone());
+        }
+        ss
+    }
+
     // TODO: similar methods for other node types
     // TODO: check that parsed_node reuse works for expr and other non-seqitems
 
@@ -113,6 +113,28 @@ impl RealFileIO {
             state: Mutex::new(RealState::new()),
         }
     }
+
+    /// Make later reads of `path` return `s` instead of the file's contents on disk.  Nothing is
+    /// written out.  This is used to pick up the rewritten sources saved in a checkpoint.
+    pub fn preload_file(&self, path: &Path, s: &str) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.file_state.insert(state_path(path)?, s.to_owned());
+        Ok(())
+    }
+}
+
+/// Get the key used for `path` in `RealState::file_state`.  If we're creating a new module, we
+/// can't canonicalize the filename itself (since it doesn't exist), so canonicalize its path and
+/// append the filename.
+fn state_path(path: &Path) -> io::Result<PathBuf> {
+    if path.is_relative() {
+        let parent_dir = Path::new(".").join(path.parent().unwrap());
+        let mut abs_path = fs::canonicalize(parent_dir)?;
+        abs_path.push(path.file_name().unwrap());
+        Ok(abs_path)
+    } else {
+        Ok(path.to_owned())
+    }
 }
 
 impl FileIO for RealFileIO {
@@ -178,19 +200,8 @@ impl FileIO for RealFileIO {
 
             if !self.output_modes.iter().any(|&mode| mode.overwrites()) {
                 // None of the modes actually updated the original file, so we
-                // need to record the new content internally. If we're creating
-                // a new module, we can't canonicalize the filename itself
-                // (since it doesn't exist), so canonicalize its path and append
-                // the filename.
-                let abs_path = if path.is_relative() {
-                    let parent_dir = Path::new(".").join(path.parent().unwrap());
-                    let mut abs_path = fs::canonicalize(parent_dir)?;
-                    abs_path.push(path.file_name().unwrap());
-                    abs_path
-                } else {
-                    path.to_owned()
-                };
-                state.file_state.insert(abs_path, s.to_owned());
+                // need to record the new content internally.
+                state.file_state.insert(state_path(path)?, s.to_owned());
             }
         }
 
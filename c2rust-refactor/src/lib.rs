@@ -73,6 +73,7 @@ pub mod collapse;
 pub mod driver;
 pub mod node_map;
 
+pub mod checkpoint;
 pub mod command;
 pub mod file_io;
 pub mod interact;
@@ -91,6 +92,7 @@ use cargo::util::paths;
 use rustc_interface::interface;
 use std::collections::HashSet;
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::{self, FromStr};
 use std::sync::Arc;
@@ -175,6 +177,9 @@ pub struct Options {
 
     pub plugins: Vec<String>,
     pub plugin_dirs: Vec<String>,
+
+    /// Directory for saving progress after each command, so a failed batch can be resumed.
+    pub checkpoint_dir: Option<PathBuf>,
 }
 
 /// Try to find the rustup installation that provides the rustc at the given path.  The input path
@@ -410,7 +415,15 @@ fn main_impl(opts: Options) -> interface::Result<()> {
         warn!("Could not derive any rustc invocations for refactoring");
     }
     let multiple_refactorings = target_args.len() > 1;
-    for rustc_args in target_args {
+
+    // Resolve the checkpoint directory up front, since each target may change the working
+    // directory.
+    let checkpoint_dir = opts.checkpoint_dir.as_ref().map(|dir| {
+        fs::create_dir_all(dir).expect("Error creating checkpoint directory");
+        fs::canonicalize(dir).expect("Error resolving checkpoint directory")
+    });
+
+    for (index, rustc_args) in target_args.into_iter().enumerate() {
         let mut marks = HashSet::new();
         for m in &opts.marks {
             let label = m.label.as_ref().map_or("target", |s| s).into_symbol();
@@ -496,8 +509,34 @@ fn main_impl(opts: Options) -> interface::Result<()> {
             ).expect("Error loading user script");
         } else {
             let file_io = Arc::new(file_io::RealFileIO::new(opts.rewrite_modes.clone()));
+
+            let mut checkpoint = checkpoint_dir.as_ref().map(|dir| {
+                checkpoint::Checkpoint::open(dir, index, &opts.commands)
+                    .expect("Error loading checkpoint")
+            });
+            let start = checkpoint.as_ref().map_or(0, |cp| cp.completed());
+            if start > 0 {
+                info!("resuming from checkpoint after {} commands", start);
+                checkpoint
+                    .as_ref()
+                    .unwrap()
+                    .preload(&file_io)
+                    .expect("Error loading checkpointed sources");
+                // Marks given on the command line refer to the original sources.  The
+                // checkpoint's own marks are restored below instead.
+                marks.clear();
+            }
+
             driver::run_refactoring(config, cmd_reg, file_io, marks, |mut state| {
-                for cmd in opts.commands.clone() {
+                if start > 0 {
+                    checkpoint
+                        .as_ref()
+                        .unwrap()
+                        .restore_marks(&mut state)
+                        .expect("Error restoring checkpointed marks");
+                }
+
+                for (i, cmd) in opts.commands.iter().enumerate().skip(start) {
                     if &cmd.name == "interact" {
                         panic!("`interact` must be the only command");
                     } else {
@@ -509,9 +548,17 @@ fn main_impl(opts: Options) -> interface::Result<()> {
                             }
                         }
                     }
+
+                    if let Some(ref mut cp) = checkpoint {
+                        cp.record(&state, i + 1).expect("Error saving checkpoint");
+                    }
                 }
 
                 state.save_crate();
+
+                if let Some(cp) = checkpoint {
+                    cp.finish().expect("Error removing checkpoint");
+                }
             });
         }
 
@@ -26,6 +26,7 @@ use rustc::session::Session;
 use std::fmt::Debug;
 use std::iter::Sum;
 use std::rc::Rc;
+use rustc_data_structures::sync::Lrc;
 use syntax::ptr::P;
 use syntax::source_map::{Spanned, DUMMY_SP};
 use syntax::util::parser::{AssocOp, Fixity};
@@ -292,6 +293,110 @@ pub fn calc_outer_span<T: GetSpan>(seq: &[T], default: Span) -> Span {
     sp
 }
 
+/// Compute an `outer_span` for rewriting the items of a module.  `Mod::inner` starts *before*
+/// the module's inner attributes and `//!` doc comments, so using it directly as the insertion
+/// point for new leading items would splice them in front of those attributes.  Instead, the
+/// default insertion point is moved past any shebang line, inner attributes, and inner doc
+/// comments at the start of `inner`.
+pub fn mod_items_outer_span<T: GetSpan>(seq: &[T], inner: Span, rcx: &RewriteCtxt) -> Span {
+    if !is_rewritable(inner) {
+        return calc_outer_span(seq, inner);
+    }
+
+    let lo = rcx.session().source_map().lookup_byte_offset(inner.lo());
+    let hi = rcx.session().source_map().lookup_byte_offset(inner.hi());
+    let skip = |src: &str| {
+        let end = if Lrc::ptr_eq(&lo.sf, &hi.sf) { hi.pos.to_usize() } else { src.len() };
+        let at_file_start = lo.pos.to_usize() == 0;
+        let skipped = skip_inner_attrs(&src[lo.pos.to_usize()..end], at_file_start);
+        inner.lo() + BytePos::from_usize(skipped)
+    };
+    let start = if let Some(ref src) = lo.sf.src {
+        skip(src)
+    } else if let Some(src) = lo.sf.external_src.borrow().get_source() {
+        skip(src)
+    } else {
+        inner.lo()
+    };
+
+    calc_outer_span(seq, inner.with_lo(start).with_hi(start))
+}
+
+/// Return the length of the prefix of `src` consisting of a shebang line (only if
+/// `at_file_start`), inner attributes, inner doc comments, and any comments and whitespace
+/// between them.  Comments following the last inner attribute are not included, since they
+/// belong to the first item.
+fn skip_inner_attrs(src: &str, at_file_start: bool) -> usize {
+    let mut pos = 0;
+    let mut end = 0;
+
+    if at_file_start && src.starts_with("#!") && !src[2..].trim_start().starts_with('[') {
+        pos = src.find('\n').map_or(src.len(), |i| i + 1);
+        end = pos;
+    }
+
+    loop {
+        let rest = &src[pos..];
+        pos += rest.len() - rest.trim_start().len();
+        let rest = &src[pos..];
+
+        if rest.starts_with("//!") {
+            pos += rest.find('\n').unwrap_or(rest.len());
+            end = pos;
+        } else if rest.starts_with("/*!") {
+            pos += rest.find("*/").map_or(rest.len(), |i| i + 2);
+            end = pos;
+        } else if rest.starts_with("//") {
+            pos += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            pos += rest.find("*/").map_or(rest.len(), |i| i + 2);
+        } else if rest.starts_with("#!") && rest[2..].trim_start().starts_with('[') {
+            match skip_delimited(rest) {
+                Some(len) => {
+                    pos += len;
+                    end = pos;
+                }
+                None => break,
+            }
+        } else {
+            break;
+        }
+    }
+
+    end
+}
+
+/// Return the length of `src` up to and including the `]` that closes its first `[`, skipping
+/// over string literals.
+fn skip_delimited(src: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = src.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            '"' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 /// Like normal sequence rewriting, but on a list of comma-separated items.  Also requires a span
 /// for each `old` item that covers the item itself along with its trailing comma (if any), and a
 /// span covering the entire sequence (for cases where `old` is empty).
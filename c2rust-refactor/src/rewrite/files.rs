@@ -2,7 +2,7 @@
 use diff;
 use std::collections::{HashMap, VecDeque};
 use std::io;
-use syntax::source_map::{SourceFile, SourceMap};
+use syntax::source_map::{SourceFile, SourceMap, Span};
 use syntax_pos::{BytePos, FileName};
 
 use crate::file_io::FileIO;
@@ -58,6 +58,81 @@ pub fn rewrite_files_with(cm: &SourceMap, rw: &TextRewrite, io: &dyn FileIO) ->
     Ok(())
 }
 
+/// Find where the source text under `span` ends up after applying `rw` to its file.  Returns the
+/// byte range of that text within the rewritten file, or `None` if either end of `span` falls
+/// strictly inside a rewritten region.
+pub fn rewritten_range(cm: &SourceMap, rw: &TextRewrite, span: Span) -> Option<(usize, usize)> {
+    let sf = cm.lookup_byte_offset(span.lo()).sf;
+    let rewrites = rw
+        .rewrites
+        .iter()
+        .filter(|rw| cm.lookup_byte_offset(rw.old_span.lo()).sf.start_pos == sf.start_pos)
+        .cloned()
+        .collect();
+    let rewrites = cleanup_rewrites(cm, rewrites);
+
+    let mut cur = sf.start_pos;
+    let mut out = 0;
+    let mut lo = None;
+    let mut hi = None;
+
+    for rw in &rewrites {
+        let (rw_lo, rw_hi) = (rw.old_span.lo(), rw.old_span.hi());
+        if lo.is_none() && span.lo() < rw_lo {
+            lo = Some(out + (span.lo() - cur).0 as usize);
+        }
+        if hi.is_none() && span.hi() <= rw_lo {
+            hi = Some(out + (span.hi() - cur).0 as usize);
+        }
+        if lo.is_some() && hi.is_some() {
+            break;
+        }
+
+        out += (rw_lo - cur).0 as usize;
+        let paren = match rw.adjust {
+            TextAdjust::None => 0,
+            TextAdjust::Parenthesize => 1,
+        };
+        let mut len = 0;
+        if rw.rewrites.is_empty() {
+            len = (rw.new_span.hi() - rw.new_span.lo()).0 as usize;
+        } else {
+            rewrite_range(
+                cm,
+                rw.new_span.lo(),
+                rw.new_span.hi(),
+                &rw.rewrites,
+                &mut |s| len += s.len(),
+            );
+        }
+
+        // A node that was replaced wholesale maps to its replacement text, without any
+        // parentheses added around it.
+        let exact = span.lo() == rw_lo && span.hi() == rw_hi;
+        if lo.is_none() {
+            if span.lo() == rw_lo && rw_lo != rw_hi {
+                lo = Some(if exact { out + paren } else { out });
+            } else if span.lo() < rw_hi {
+                return None;
+            }
+        }
+        if hi.is_none() {
+            if span.hi() == rw_hi {
+                hi = Some(if exact { out + paren + len } else { out + len + 2 * paren });
+            } else if span.hi() < rw_hi {
+                return None;
+            }
+        }
+
+        out += len + 2 * paren;
+        cur = rw_hi;
+    }
+
+    let lo = lo.unwrap_or_else(|| out + (span.lo() - cur).0 as usize);
+    let hi = hi.unwrap_or_else(|| out + (span.hi() - cur).0 as usize);
+    Some((lo, hi))
+}
+
 #[allow(dead_code)] // Helper function for debugging
 fn print_rewrite(rw: &TextRewrite, depth: usize) {
     for _ in 0..depth {
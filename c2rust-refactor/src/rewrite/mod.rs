@@ -73,7 +73,7 @@ pub mod json;
 mod base;
 mod strategy;
 
-pub use self::base::Rewrite;
+pub use self::base::{binop_left_prec, binop_right_prec, Rewrite};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum TextAdjust {
@@ -159,6 +159,27 @@ pub enum ExprPrec {
     LeftLess(i8),
 }
 
+/// Check whether `expr` must be parenthesized to keep its meaning in a context with
+/// precedence `prec`.
+pub fn needs_parens(expr: &Expr, prec: ExprPrec) -> bool {
+    // Check for cases where we can safely omit parentheses.
+    let expr_prec = expr.precedence();
+    match prec {
+        ExprPrec::Normal(min_prec) => expr_prec.order() < min_prec,
+        ExprPrec::Cond(min_prec) => {
+            expr_prec.order() < min_prec || parser::contains_exterior_struct_lit(expr)
+        }
+        ExprPrec::Callee(min_prec) => match expr.kind {
+            ExprKind::Field(..) => true,
+            _ => expr_prec.order() < min_prec,
+        },
+        ExprPrec::LeftLess(min_prec) => match expr.kind {
+            ExprKind::Cast(..) | ExprKind::Type(..) => true,
+            _ => expr_prec.order() < min_prec,
+        },
+    }
+}
+
 pub struct RewriteCtxt<'s> {
     sess: &'s Session,
     old_nodes: AstMap<'s>,
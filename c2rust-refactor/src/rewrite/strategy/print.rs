@@ -26,7 +26,6 @@ use syntax::ptr::P;
 use syntax::source_map::{BytePos, FileName, SourceFile, Span, Spanned};
 use syntax::symbol::Symbol;
 use syntax::tokenstream::{DelimSpan, TokenStream, TokenTree};
-use syntax::util::parser;
 use syntax::ThinVec;
 use syntax_pos::DUMMY_SP;
 
@@ -37,7 +36,7 @@ use crate::ast_manip::{AstDeref, GetSpan, MaybeGetNodeId};
 use crate::driver;
 use crate::rewrite::base::{binop_left_prec, binop_right_prec};
 use crate::rewrite::base::{describe, extend_span_comments, extend_span_comments_strict, is_rewritable, rewind_span_over_whitespace};
-use crate::rewrite::{ExprPrec, Rewrite, RewriteCtxt, RewriteCtxtRef, TextAdjust, TextRewrite};
+use crate::rewrite::{needs_parens, Rewrite, RewriteCtxt, RewriteCtxtRef, TextAdjust, TextRewrite};
 use crate::util::Lone;
 
 
@@ -201,24 +200,7 @@ impl Splice for Expr {
     }
 
     fn get_adjustment(&self, rcx: &RewriteCtxt) -> TextAdjust {
-        // Check for cases where we can safely omit parentheses.
-        let prec = self.precedence();
-        let need_parens = match rcx.expr_prec() {
-            ExprPrec::Normal(min_prec) => prec.order() < min_prec,
-            ExprPrec::Cond(min_prec) => {
-                prec.order() < min_prec || parser::contains_exterior_struct_lit(self)
-            }
-            ExprPrec::Callee(min_prec) => match self.kind {
-                ExprKind::Field(..) => true,
-                _ => prec.order() < min_prec,
-            },
-            ExprPrec::LeftLess(min_prec) => match self.kind {
-                ExprKind::Cast(..) | ExprKind::Type(..) => true,
-                _ => prec.order() < min_prec,
-            },
-        };
-
-        if need_parens {
+        if needs_parens(self, rcx.expr_prec()) {
             TextAdjust::Parenthesize
         } else {
             TextAdjust::None
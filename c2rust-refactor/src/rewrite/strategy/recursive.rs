@@ -5,7 +5,9 @@ use syntax::tokenstream::{DelimSpan, TokenTree};
 use syntax::util::parser;
 
 use crate::ast_manip::{GetNodeId, GetSpan};
-use crate::rewrite::base::{binop_left_prec, binop_right_prec, calc_outer_span, rewrite_seq};
+use crate::rewrite::base::{
+    binop_left_prec, binop_right_prec, calc_outer_span, mod_items_outer_span, rewrite_seq,
+};
 use crate::rewrite::{ExprPrec, Rewrite, RewriteCtxtRef};
 
 /// Try rewriting every child of `old` into the corresponding child of `new`.  Fails if `old` and
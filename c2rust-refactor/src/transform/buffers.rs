@@ -0,0 +1,475 @@
+//! Transformations on code that parses byte buffers through raw pointers.
+use std::collections::{BTreeSet, HashSet};
+use std::mem;
+
+use rustc::hir::def::Res;
+use rustc::hir::HirId;
+use rustc::ty;
+use syntax::ast::*;
+use syntax::mut_visit::{self, MutVisitor};
+use syntax::ptr::P;
+use syntax::symbol::Symbol;
+
+use crate::ast_manip::{visit_nodes, MutVisit};
+use crate::command::{CommandState, Registry};
+use crate::driver::{self, Phase};
+use crate::transform::Transform;
+use crate::RefactorCtxt;
+use c2rust_ast_builder::mk;
+use c2rust_ast_printer::pprust;
+
+/// Name of the generated reader type.
+const CURSOR_TYPE: &str = "ByteCursor";
+
+/// # `byte_buffer_to_cursor` Command
+///
+/// Usage: `byte_buffer_to_cursor`
+///
+/// Rewrites sequential reads from a byte buffer through pointer casts into reads from a
+/// bounds-checked `ByteCursor` over a `&[u8]`.  A run of statements like this:
+///
+/// ```ignore
+///     let len: uint32_t = ntohl(*(p as *const uint32_t));
+///     p = p.offset(4 as libc::c_int as isize);
+///     let kind: uint16_t = *(p as *const uint16_t);
+///     p = p.offset(2 as libc::c_int as isize);
+/// ```
+///
+/// becomes:
+///
+/// ```ignore
+///     let mut p_cursor = crate::ByteCursor::new(::std::slice::from_raw_parts(p as *const u8, 6));
+///     let len: uint32_t = p_cursor.read_u32_be();
+///     let kind: uint16_t = p_cursor.read_u16_ne();
+///     p = p.offset(6 as isize);
+/// ```
+///
+/// Each read must dereference a cast of a local `u8`/`i8` pointer to an integer pointer type and
+/// be immediately followed by advancing the same pointer by the size of the integer.  The byte
+/// order of each read follows the conversion applied to it in the original code: `ntohl`,
+/// `be32toh` and friends read big-endian values, `le32toh` and friends read little-endian ones,
+/// `__bswap_32` and friends read byte-swapped native values, and bare reads use the native byte
+/// order.  The `ByteCursor` type, with only the read methods that are used, is added to the crate
+/// root, or extended if a previous run already added it.
+pub struct ByteBufferToCursor;
+
+impl Transform for ByteBufferToCursor {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        let mut folder = CursorFolder {
+            cx,
+            reads: BTreeSet::new(),
+        };
+        krate.visit(&mut folder);
+
+        if folder.reads.is_empty() {
+            return;
+        }
+
+        let existing_methods = cursor_methods(krate);
+        let methods = folder
+            .reads
+            .iter()
+            .filter(|read| !existing_methods.contains(&read.method_name()))
+            .map(ReadOp::method_src)
+            .collect::<String>();
+
+        let src = if existing_methods.is_empty() {
+            format!(
+                "/// Bounds-checked reader over a byte buffer.\n\
+                 pub struct {name}<'a> {{\n\
+                 \x20   buf: &'a [u8],\n\
+                 \x20   pos: usize,\n\
+                 }}\n\
+                 \n\
+                 impl<'a> {name}<'a> {{\n\
+                 \x20   pub fn new(buf: &'a [u8]) -> {name}<'a> {{\n\
+                 \x20       {name} {{ buf, pos: 0 }}\n\
+                 \x20   }}\n\
+                 \n\
+                 \x20   fn take(&mut self, n: usize) -> &'a [u8] {{\n\
+                 \x20       let bytes = &self.buf[self.pos..self.pos + n];\n\
+                 \x20       self.pos += n;\n\
+                 \x20       bytes\n\
+                 \x20   }}\n\
+                 {methods}\
+                 }}\n",
+                name = CURSOR_TYPE,
+                methods = methods,
+            )
+        } else if methods.is_empty() {
+            return;
+        } else {
+            format!("impl<'a> {}<'a> {{\n{}}}\n", CURSOR_TYPE, methods)
+        };
+        krate.module.items.extend(st.parse_items(cx, &src));
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// Byte order of a single read, as evident from the conversion applied to the loaded value.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Endian {
+    Little,
+    Big,
+    Native,
+    Swapped,
+}
+
+impl Endian {
+    /// Detect the byte order implied by a call to the function named `name`.
+    fn from_conversion_fn(name: &str) -> Option<Endian> {
+        match name {
+            "ntohs" | "ntohl" | "be16toh" | "be32toh" | "be64toh" | "from_be" => Some(Endian::Big),
+            "le16toh" | "le32toh" | "le64toh" | "from_le" => Some(Endian::Little),
+            "__bswap_16" | "__bswap_32" | "__bswap_64" | "bswap_16" | "bswap_32" | "bswap_64"
+            | "__builtin_bswap16" | "__builtin_bswap32" | "__builtin_bswap64" | "swap_bytes" => {
+                Some(Endian::Swapped)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A read of one integer from the buffer.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct ReadOp {
+    ty: &'static str,
+    size: u64,
+    endian: Endian,
+}
+
+impl ReadOp {
+    fn method_name(&self) -> String {
+        if self.size == 1 {
+            return format!("read_{}", self.ty);
+        }
+        let suffix = match self.endian {
+            Endian::Little => "le",
+            Endian::Big => "be",
+            Endian::Native => "ne",
+            Endian::Swapped => "swapped",
+        };
+        format!("read_{}_{}", self.ty, suffix)
+    }
+
+    /// Source of the method, indented for the inside of an `impl` block.
+    fn method_src(&self) -> String {
+        let body = match (self.size, self.endian) {
+            (1, _) => format!("self.take(1)[0] as {}", self.ty),
+            (_, Endian::Little) => format!("{}::from_le_bytes(bytes)", self.ty),
+            (_, Endian::Big) => format!("{}::from_be_bytes(bytes)", self.ty),
+            (_, Endian::Native) => format!("{}::from_ne_bytes(bytes)", self.ty),
+            (_, Endian::Swapped) => format!("{}::from_ne_bytes(bytes).swap_bytes()", self.ty),
+        };
+        let load = if self.size == 1 {
+            String::new()
+        } else {
+            format!(
+                "        let mut bytes = [0; {size}];\n\
+                 \x20       bytes.copy_from_slice(self.take({size}));\n",
+                size = self.size,
+            )
+        };
+        format!(
+            "\n    pub fn {name}(&mut self) -> {ty} {{\n{load}        {body}\n    }}\n",
+            name = self.method_name(),
+            ty = self.ty,
+            load = load,
+            body = body,
+        )
+    }
+}
+
+/// A statement reading an integer through a cast of the buffer pointer, together with the
+/// statement advancing the pointer past it.
+struct ReadStep {
+    ptr: HirId,
+    /// The read expression to replace: either the dereference itself or the byte order
+    /// conversion wrapped around it.
+    read_expr: NodeId,
+    op: ReadOp,
+}
+
+struct CursorFolder<'a, 'tcx: 'a> {
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+    reads: BTreeSet<ReadOp>,
+}
+
+impl<'a, 'tcx> CursorFolder<'a, 'tcx> {
+    /// Resolve `e` to a local variable holding a `u8` or `i8` pointer.
+    fn byte_ptr_local(&self, e: &Expr) -> Option<HirId> {
+        let id = match_or!([self.cx.try_resolve_expr_hir(e)] Some(Res::Local(id)) => id;
+                           return None);
+        let ptr_ty = self.cx.opt_node_type(e.id)?;
+        match ptr_ty.kind {
+            ty::TyKind::RawPtr(ty::TypeAndMut { ty: pointee, .. }) => match pointee.kind {
+                ty::TyKind::Uint(UintTy::U8) | ty::TyKind::Int(IntTy::I8) => Some(id),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Get the name and size of the integer type produced by `e`.
+    fn int_type(&self, id: NodeId) -> Option<(&'static str, u64)> {
+        let int_ty = self.cx.opt_node_type(id)?;
+        Some(match int_ty.kind {
+            ty::TyKind::Uint(UintTy::U8) => ("u8", 1),
+            ty::TyKind::Uint(UintTy::U16) => ("u16", 2),
+            ty::TyKind::Uint(UintTy::U32) => ("u32", 4),
+            ty::TyKind::Uint(UintTy::U64) => ("u64", 8),
+            ty::TyKind::Int(IntTy::I8) => ("i8", 1),
+            ty::TyKind::Int(IntTy::I16) => ("i16", 2),
+            ty::TyKind::Int(IntTy::I32) => ("i32", 4),
+            ty::TyKind::Int(IntTy::I64) => ("i64", 8),
+            _ => return None,
+        })
+    }
+
+    /// Evaluate the argument of `p.offset(n)`: an integer literal, possibly cast, or a call to
+    /// `size_of::<T>()` for an integer type `T`.
+    fn offset_amount(&self, e: &Expr) -> Option<u64> {
+        match e.kind {
+            ExprKind::Lit(ref lit) => match lit.kind {
+                LitKind::Int(i, _) => Some(i as u64),
+                _ => None,
+            },
+            ExprKind::Cast(ref e, _) | ExprKind::Paren(ref e) => self.offset_amount(e),
+            ExprKind::Call(ref f, ref args) if args.is_empty() => {
+                let path = match_or!([f.kind] ExprKind::Path(None, ref p) => p; return None);
+                let seg = path.segments.last()?;
+                if seg.ident.name != Symbol::intern("size_of") {
+                    return None;
+                }
+                let args = seg.args.as_ref()?;
+                let args = match_or!([**args] GenericArgs::AngleBracketed(ref a) => a;
+                                     return None);
+                match args.args[..] {
+                    [GenericArg::Type(ref arg_ty)] => {
+                        self.int_type(arg_ty.id).map(|(_, size)| size)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Match `p = p.offset(n)`, returning the pointer and the amount.
+    fn match_advance(&self, s: &Stmt) -> Option<(HirId, u64)> {
+        let e = match_or!([s.kind] StmtKind::Semi(ref e) => e; return None);
+        let (lhs, rhs) = match_or!([e.kind] ExprKind::Assign(ref lhs, ref rhs) => (lhs, rhs);
+                                   return None);
+        let (seg, args) = match_or!([rhs.kind]
+                                    ExprKind::MethodCall(ref seg, ref args) => (seg, args);
+                                    return None);
+        if seg.ident.name != Symbol::intern("offset") || args.len() != 2 {
+            return None;
+        }
+        let ptr = self.byte_ptr_local(lhs)?;
+        if self.byte_ptr_local(&args[0])? != ptr {
+            return None;
+        }
+        Some((ptr, self.offset_amount(&args[1])?))
+    }
+
+    /// Match a dereference `*(p as *const T)` of the byte pointer `p`, or `*p` itself.
+    fn match_deref(&self, e: &Expr) -> Option<HirId> {
+        let inner = match_or!([e.kind] ExprKind::Unary(UnOp::Deref, ref inner) => inner;
+                              return None);
+        let inner = strip_parens(inner);
+        match inner.kind {
+            ExprKind::Cast(ref ptr, ref cast_ty) => match cast_ty.kind {
+                TyKind::Ptr(_) => self.byte_ptr_local(strip_parens(ptr)),
+                _ => None,
+            },
+            _ => self.byte_ptr_local(inner),
+        }
+    }
+
+    /// Match a statement containing exactly one read from `ptr` and no other use of it,
+    /// followed by the statement advancing `ptr` past the read value.
+    fn match_step(&self, read: &Stmt, advance: &Stmt) -> Option<ReadStep> {
+        let (ptr, amount) = self.match_advance(advance)?;
+
+        let mut derefs = Vec::new();
+        let mut uses = 0;
+        visit_nodes(read, |e: &Expr| {
+            if let Some(id) = self.match_deref(e) {
+                if id == ptr {
+                    derefs.push(e.id);
+                }
+            }
+            if let ExprKind::Path(..) = e.kind {
+                if self.cx.try_resolve_expr_hir(e) == Some(Res::Local(ptr)) {
+                    uses += 1;
+                }
+            }
+        });
+        if derefs.len() != 1 || uses != 1 {
+            return None;
+        }
+        let deref = derefs[0];
+        let (ty, size) = self.int_type(deref)?;
+        if size != amount {
+            return None;
+        }
+
+        // Look for a byte order conversion applied directly to the loaded value
+        let mut conversion = None;
+        visit_nodes(read, |e: &Expr| {
+            let endian = match e.kind {
+                ExprKind::Call(ref f, ref args) if args.len() == 1
+                    && strip_parens(&args[0]).id == deref =>
+                {
+                    match_or!([f.kind] ExprKind::Path(None, ref p) => p; return)
+                        .segments
+                        .last()
+                        .and_then(|seg| Endian::from_conversion_fn(&seg.ident.as_str()))
+                }
+                ExprKind::MethodCall(ref seg, ref args) if args.len() == 1
+                    && strip_parens(&args[0]).id == deref =>
+                {
+                    Endian::from_conversion_fn(&seg.ident.as_str())
+                }
+                _ => None,
+            };
+            if let Some(endian) = endian {
+                conversion = Some((e.id, endian));
+            }
+        });
+        let (read_expr, endian) = conversion.unwrap_or((deref, Endian::Native));
+
+        Some(ReadStep {
+            ptr,
+            read_expr,
+            op: ReadOp { ty, size, endian },
+        })
+    }
+
+    /// Rewrite every run of read steps in `stmts`.
+    fn rewrite_stmts(&mut self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+        let mut new_stmts = Vec::with_capacity(stmts.len());
+        let mut i = 0;
+        while i < stmts.len() {
+            let first = match stmts.get(i + 1).and_then(|s| self.match_step(&stmts[i], s)) {
+                Some(step) => step,
+                None => {
+                    new_stmts.push(stmts[i].clone());
+                    i += 1;
+                    continue;
+                }
+            };
+
+            // Extend the run with further reads from the same pointer
+            let mut steps = vec![(i, first)];
+            let mut next = i + 2;
+            while next + 1 < stmts.len() {
+                match self.match_step(&stmts[next], &stmts[next + 1]) {
+                    Some(ref step) if step.ptr != steps[0].1.ptr => break,
+                    Some(step) => steps.push((next, step)),
+                    None => break,
+                }
+                next += 2;
+            }
+
+            let advance = expect!([stmts[i + 1].kind] StmtKind::Semi(ref e) => e);
+            let ptr_expr = expect!([advance.kind] ExprKind::Assign(ref lhs, _) => lhs);
+            let ptr_src = pprust::expr_to_string(ptr_expr);
+            let cursor = format!("{}_cursor", ptr_src);
+            let total: u64 = steps.iter().map(|(_, step)| step.op.size).sum();
+
+            new_stmts.push(driver::parse_stmts(
+                self.cx.session(),
+                &format!(
+                    "let mut {} = crate::{}::new(::std::slice::from_raw_parts({} as *const u8, {}));",
+                    cursor, CURSOR_TYPE, ptr_src, total,
+                ),
+            ).remove(0));
+            for (idx, step) in steps {
+                let mut stmt = stmts[idx].clone();
+                let read = mk().method_call_expr(
+                    mk().ident_expr(cursor.as_str()),
+                    step.op.method_name(),
+                    vec![] as Vec<P<Expr>>,
+                );
+                stmt.visit(&mut ReplaceExpr { id: step.read_expr, new: read });
+                new_stmts.push(stmt);
+                self.reads.insert(step.op);
+            }
+            new_stmts.extend(driver::parse_stmts(
+                self.cx.session(),
+                &format!("{p} = {p}.offset({} as isize);", total, p = ptr_src),
+            ));
+
+            i = next;
+        }
+        new_stmts
+    }
+}
+
+impl<'a, 'tcx> MutVisitor for CursorFolder<'a, 'tcx> {
+    fn visit_block(&mut self, b: &mut P<Block>) {
+        let stmts = mem::replace(&mut b.stmts, vec![]);
+        b.stmts = self.rewrite_stmts(stmts);
+        mut_visit::noop_visit_block(b, self)
+    }
+}
+
+/// Replace the expression with the given `NodeId`.
+struct ReplaceExpr {
+    id: NodeId,
+    new: P<Expr>,
+}
+
+impl MutVisitor for ReplaceExpr {
+    fn visit_expr(&mut self, e: &mut P<Expr>) {
+        if e.id == self.id {
+            *e = self.new.clone();
+            return;
+        }
+        mut_visit::noop_visit_expr(e, self)
+    }
+}
+
+fn strip_parens(e: &Expr) -> &Expr {
+    match e.kind {
+        ExprKind::Paren(ref inner) => strip_parens(inner),
+        _ => e,
+    }
+}
+
+/// Collect the names of the methods of the `ByteCursor` type at the crate root, if it exists.
+fn cursor_methods(krate: &Crate) -> HashSet<String> {
+    let mut methods = HashSet::new();
+    for item in &krate.module.items {
+        match item.kind {
+            ItemKind::Struct(..) if item.ident.name == Symbol::intern(CURSOR_TYPE) => {
+                methods.insert("new".to_owned());
+            }
+            ItemKind::Impl(_, _, _, _, None, ref self_ty, ref impl_items) => {
+                let is_cursor = match self_ty.kind {
+                    TyKind::Path(None, ref path) => path
+                        .segments
+                        .last()
+                        .map_or(false, |seg| seg.ident.name == Symbol::intern(CURSOR_TYPE)),
+                    _ => false,
+                };
+                if is_cursor {
+                    methods.extend(impl_items.iter().map(|ii| ii.ident.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    methods
+}
+
+pub fn register_commands(reg: &mut Registry) {
+    use super::mk;
+
+    reg.register("byte_buffer_to_cursor", |_args| mk(ByteBufferToCursor));
+}
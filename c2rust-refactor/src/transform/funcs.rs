@@ -1,21 +1,26 @@
 use std::collections::{HashMap, HashSet};
+use std::mem;
+use rustc::hir::HirId;
+use rustc::hir::def::Res;
 use rustc::hir::def_id::DefId;
-use rustc::ty::TyKind;
+use rustc::ty::{self, TyKind};
 use syntax::ast;
 use syntax::ast::*;
 use syntax::attr;
 use syntax::mut_visit::{self, MutVisitor};
 use syntax::ptr::P;
+use syntax::visit::{self, Visitor};
 use syntax_pos::sym;
 use smallvec::{smallvec, SmallVec};
 
 use c2rust_ast_builder::{mk, IntoSymbol};
-use crate::ast_manip::{FlatMapNodes, MutVisitNodes, fold_modules, visit_nodes, MutVisit};
-use crate::command::{CommandState, Registry};
+use crate::ast_manip::{AstEquiv, FlatMapNodes, MutVisitNodes, fold_modules, visit_nodes, MutVisit};
+use crate::command::{Command, CommandState, RefactorState, Registry, TypeckLoopResult};
 use crate::driver::{Phase, parse_expr};
 use crate::matcher::{BindingType, MatchCtxt, Subst, mut_visit_match_with};
 use crate::path_edit::{fold_resolved_paths, fold_resolved_paths_with_id};
 use crate::transform::Transform;
+use crate::transform::retype::fn_values;
 use crate::util::Lone;
 use crate::RefactorCtxt;
 
@@ -274,13 +279,7 @@ impl Transform for FixUnusedUnsafe {
     fn transform(&self, krate: &mut Crate, _st: &CommandState, cx: &RefactorCtxt) {
         MutVisitNodes::visit(krate, |b: &mut P<Block>| {
             if let BlockCheckMode::Unsafe(UnsafeSource::UserProvided) = b.rules {
-                let hir_id = cx.hir_map().node_to_hir_id(b.id);
-                let parent = cx.hir_map().get_parent_did(hir_id);
-                let result = cx.ty_ctxt().unsafety_check_result(parent);
-                let unused = result.unsafe_blocks.iter().any(|&(id, used)| {
-                    id == cx.hir_map().node_to_hir_id(b.id) && !used
-                });
-                if unused {
+                if is_unused_unsafe_block(b, cx) {
                     b.rules = BlockCheckMode::Default;
                 }
             }
@@ -292,6 +291,14 @@ impl Transform for FixUnusedUnsafe {
     }
 }
 
+/// Whether the compiler found the `unsafe` block `b` to be unused.
+fn is_unused_unsafe_block(b: &Block, cx: &RefactorCtxt) -> bool {
+    let hir_id = cx.hir_map().node_to_hir_id(b.id);
+    let parent = cx.hir_map().get_parent_did(hir_id);
+    let result = cx.ty_ctxt().unsafety_check_result(parent);
+    result.unsafe_blocks.iter().any(|&(id, used)| id == hir_id && !used)
+}
+
 
 /// # `sink_unsafe` Command
 ///
@@ -358,6 +365,231 @@ impl Transform for SinkUnsafe {
 }
 
 
+/// # `purge_unsafe` Command
+///
+/// Usage: `purge_unsafe`
+///
+/// Remove `unsafe` qualifiers that aren't needed.  Each `unsafe fn` whose
+/// body performs no unsafe operations becomes a safe `fn`, and then unused
+/// `unsafe` blocks become ordinary blocks, as with `fix_unused_unsafe`.
+///
+/// To find out whether a function needs to be unsafe, its body is moved into
+/// an `unsafe` block in a safe function, as with `sink_unsafe`, and the crate
+/// is typechecked again to see whether the block is used.  Functions that
+/// need it get their qualifier back, which may make their callers need
+/// theirs, so this repeats until no more functions change.  Since safe
+/// functions can be called wherever unsafe ones can, the crate typechecks
+/// throughout.
+///
+/// Trait methods and methods of trait impls keep their qualifier, since it
+/// must match the trait, as do functions used other than by calling them,
+/// e.g. stored as function pointers of an `unsafe fn` type.
+///
+/// A function whose unsafe operations are all inside `unsafe` blocks of its
+/// own becomes safe and keeps those blocks.  The compiler counts operations in
+/// blocks nested in the one the body was moved into as uses of the outer
+/// block, so for such functions the operations outside the nested blocks are
+/// checked instead: calls of unsafe functions, dereferences of raw pointers,
+/// accesses to mutable or foreign statics and union fields, and inline
+/// assembly.
+pub struct PurgeUnsafe;
+
+impl Command for PurgeUnsafe {
+    fn run(&mut self, state: &mut RefactorState) {
+        // Functions we made safe, whose bodies are in an `unsafe` block until we know whether
+        // they need it, are marked `purge_unsafe`.  Unlike their node IDs, marks carry over to
+        // the crate typechecked in the next iteration.
+        let mut sunk = false;
+        let mut unwrapped = false;
+
+        state.run_typeck_loop(|krate, st, cx| {
+            if unwrapped {
+                // (4) With the final qualifiers typechecked, remove the unused `unsafe` blocks.
+                FixUnusedUnsafe.transform(krate, st, cx);
+                return TypeckLoopResult::Finished;
+            }
+
+            if !sunk {
+                // (1) Make each unsafe fn safe, with its body in an `unsafe` block.
+                let fn_values = fn_values(krate, cx);
+                visit_unsafe_fns(krate, |id, unsafety, block| {
+                    if *unsafety == Unsafety::Unsafe && !fn_values.contains(&cx.node_def_id(id)) {
+                        sink_unsafe(unsafety, block);
+                        st.add_mark(id, "purge_unsafe");
+                    }
+                });
+                sunk = true;
+                return TypeckLoopResult::Iterate;
+            }
+
+            // (2) Restore the qualifier of the functions whose bodies use their `unsafe` block.
+            // Their callers may need theirs now, so check again until no more are restored.
+            let mut restored = false;
+            visit_unsafe_fns(krate, |id, unsafety, block| {
+                if !st.marked(id, "purge_unsafe") {
+                    return;
+                }
+                let needed = sunk_unsafe_block(block).map_or(true, |b| {
+                    !is_unused_unsafe_block(b, cx) &&
+                        (!has_nested_unsafe_blocks(b) || has_unsafe_ops_outside_blocks(b, cx))
+                });
+                if needed {
+                    unsink_unsafe(block);
+                    *unsafety = Unsafety::Unsafe;
+                    st.remove_mark(id, "purge_unsafe");
+                    restored = true;
+                }
+            });
+            if restored {
+                return TypeckLoopResult::Iterate;
+            }
+
+            // (3) The remaining functions don't need to be unsafe.
+            visit_unsafe_fns(krate, |id, _unsafety, block| {
+                if st.marked(id, "purge_unsafe") {
+                    unsink_unsafe(block);
+                    st.remove_mark(id, "purge_unsafe");
+                }
+            });
+            unwrapped = true;
+            TypeckLoopResult::Iterate
+        }).unwrap();
+    }
+}
+
+/// Call `callback` with the ID, unsafety and body of each `fn` item and inherent method.
+fn visit_unsafe_fns<F>(krate: &mut Crate, callback: F)
+where
+    F: FnMut(NodeId, &mut Unsafety, &mut P<Block>),
+{
+    struct UnsafeFnFolder<F> {
+        callback: F,
+    }
+
+    impl<F> MutVisitor for UnsafeFnFolder<F>
+    where
+        F: FnMut(NodeId, &mut Unsafety, &mut P<Block>),
+    {
+        fn flat_map_item(&mut self, i: P<Item>) -> SmallVec<[P<Item>; 1]> {
+            // The unsafety of trait impl methods must match the trait.
+            if matches!([i.kind] ItemKind::Impl(_, _, _, _, Some(_), _, _)) {
+                return smallvec![i];
+            }
+            let i = i.map(|mut i| {
+                if let ItemKind::Fn(ref mut sig, _, ref mut block) = i.kind {
+                    (self.callback)(i.id, &mut sig.header.unsafety, block);
+                }
+                i
+            });
+            mut_visit::noop_flat_map_item(i, self)
+        }
+
+        fn flat_map_impl_item(&mut self, mut i: ImplItem) -> SmallVec<[ImplItem; 1]> {
+            if let ImplItemKind::Method(ref mut sig, ref mut block) = i.kind {
+                (self.callback)(i.id, &mut sig.header.unsafety, block);
+            }
+            mut_visit::noop_flat_map_impl_item(i, self)
+        }
+    }
+
+    krate.visit(&mut UnsafeFnFolder { callback })
+}
+
+/// The `unsafe` block `sink_unsafe` moved the function body `block` into.
+fn sunk_unsafe_block(block: &Block) -> Option<&P<Block>> {
+    let e = match &block.stmts[..] {
+        [stmt] => match_or!([stmt.kind] StmtKind::Expr(ref e) => e; return None),
+        _ => return None,
+    };
+    match e.kind {
+        ExprKind::Block(ref b, None) => match b.rules {
+            BlockCheckMode::Unsafe(UnsafeSource::UserProvided) => Some(b),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Move a function body back out of the `unsafe` block `sink_unsafe` moved it into.
+fn unsink_unsafe(block: &mut P<Block>) {
+    if let Some(stmts) = sunk_unsafe_block(block).map(|b| b.stmts.clone()) {
+        block.stmts = stmts;
+    }
+}
+
+/// Whether `block` contains `unsafe` blocks the user wrote.
+fn has_nested_unsafe_blocks(block: &Block) -> bool {
+    let mut found = false;
+    for stmt in &block.stmts {
+        visit_nodes(stmt, |b: &Block| {
+            found |= b.rules == BlockCheckMode::Unsafe(UnsafeSource::UserProvided);
+        });
+    }
+    found
+}
+
+/// Whether `block` performs unsafe operations outside the `unsafe` blocks nested in it.
+/// Operations whose safety we can't determine count as unsafe.
+fn has_unsafe_ops_outside_blocks(block: &Block, cx: &RefactorCtxt) -> bool {
+    struct UnsafeOpFinder<'a, 'tcx: 'a> {
+        cx: &'a RefactorCtxt<'a, 'tcx>,
+        found: bool,
+    }
+
+    impl<'a, 'tcx> UnsafeOpFinder<'a, 'tcx> {
+        fn is_unsafe_call(&self, e: &Expr) -> bool {
+            self.cx.opt_callee_fn_sig(e)
+                .map_or(true, |sig| sig.unsafety == rustc::hir::Unsafety::Unsafe)
+        }
+
+        fn is_unsafe_path(&self, e: &Expr) -> bool {
+            let tcx = self.cx.ty_ctxt();
+            self.cx.try_resolve_expr(e).map_or(false, |def_id| {
+                tcx.is_mutable_static(def_id) ||
+                    (tcx.is_foreign_item(def_id) && tcx.static_mutability(def_id).is_some())
+            })
+        }
+
+        fn is_raw_ptr(&self, e: &Expr) -> bool {
+            self.cx.opt_adjusted_node_type(e.id)
+                .map_or(true, |ty| matches!([ty.kind] TyKind::RawPtr(_)))
+        }
+    }
+
+    impl<'a, 'tcx, 'ast> Visitor<'ast> for UnsafeOpFinder<'a, 'tcx> {
+        fn visit_expr(&mut self, e: &'ast Expr) {
+            self.found |= match e.kind {
+                ExprKind::Call(..) | ExprKind::MethodCall(..) => self.is_unsafe_call(e),
+                ExprKind::Unary(UnOp::Deref, ref ptr) => self.is_raw_ptr(ptr),
+                ExprKind::Field(ref base, _) => {
+                    self.cx.opt_adjusted_node_type(base.id).map_or(true, |ty| ty.is_union())
+                }
+                ExprKind::Path(..) => self.is_unsafe_path(e),
+                ExprKind::InlineAsm(..) => true,
+                _ => false,
+            };
+            visit::walk_expr(self, e);
+        }
+
+        fn visit_block(&mut self, b: &'ast Block) {
+            if b.rules != BlockCheckMode::Unsafe(UnsafeSource::UserProvided) {
+                visit::walk_block(self, b);
+            }
+        }
+
+        fn visit_item(&mut self, _i: &'ast Item) {}
+
+        fn visit_mac(&mut self, mac: &'ast Mac) {
+            visit::walk_mac(self, mac);
+        }
+    }
+
+    let mut finder = UnsafeOpFinder { cx, found: false };
+    visit::walk_block(&mut finder, block);
+    finder.found
+}
+
+
 /// # `wrap_extern` Command
 ///
 /// Usage: `wrap_extern`
@@ -695,6 +927,899 @@ impl Transform for WrapApi {
 }
 
 
+/// # `last_error_to_result` Command
+///
+/// Usage: `last_error_to_result`
+///
+/// Marks: `target`, `error`
+///
+/// Convert functions marked `target`, which report failures by storing an error code in the
+/// `static` marked `error` (as read back by a `foo_last_error()`-style API), into functions
+/// returning `Result`.  For each marked function `foo`:
+///
+///  1. Rename `foo` to `foo_result`, reset its ABI to `"Rust"`, remove any `#[no_mangle]` or
+///     `#[export_name]` attributes, and change its return type from `T` to `Result<T, E>`,
+///     where `E` is the type of the error static.
+///  2. Inside `foo_result`, replace each `ERROR = e; return v;` sequence with `return Err(e);`
+///     and wrap all other returned values in `Ok`.  All such sequences must return the same
+///     sentinel value `v`; functions where they do not are left unchanged.
+///  3. Add a wrapper with `foo`'s original name, ABI, and attributes that calls `foo_result`,
+///     stores any error into the error static, and returns the sentinel, so that external
+///     callers keep observing the old behavior.
+///  4. Inside other marked functions, rewrite `if foo(args) == v { return w; }`, where `v` is
+///     `foo`'s sentinel and `w` is the calling function's sentinel, into `foo_result(args)?;`.
+///     Marked functions that never set the error static themselves, but only return `w` this
+///     way when a converted function fails, are converted too, with `w` as their sentinel.
+///
+/// Example:
+///
+/// ```ignore
+///     static mut LAST_ERROR: i32 = 0;
+///
+///     #[no_mangle]
+///     pub unsafe extern "C" fn half(x: i32) -> i32 {
+///         if x % 2 != 0 {
+///             LAST_ERROR = 1;
+///             return -1;
+///         }
+///         x / 2
+///     }
+/// ```
+///
+/// After running `last_error_to_result`, with `fn half` marked `target` and `LAST_ERROR`
+/// marked `error`:
+///
+/// ```ignore
+///     static mut LAST_ERROR: i32 = 0;
+///
+///     pub unsafe fn half_result(x: i32) -> Result<i32, i32> {
+///         if x % 2 != 0 {
+///             return Err(1);
+///         }
+///         Ok(x / 2)
+///     }
+///
+///     #[no_mangle]
+///     pub unsafe extern "C" fn half(x: i32) -> i32 {
+///         match half_result(x) {
+///             Ok(v) => v,
+///             Err(e) => {
+///                 LAST_ERROR = e;
+///                 -1
+///             }
+///         }
+///     }
+/// ```
+pub struct LastErrorToResult;
+
+/// Information about a function being converted by `last_error_to_result`.
+struct LastErrorFn {
+    /// Name of the new `Result`-returning function.
+    result_ident: Ident,
+    /// The value returned after setting the error static, or `None` for a bare `return`.
+    sentinel: Option<P<Expr>>,
+}
+
+impl Transform for LastErrorToResult {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the error static.
+        let mut error = None;
+        visit_nodes(krate, |i: &Item| {
+            if !st.marked(i.id, "error") {
+                return;
+            }
+            if let ItemKind::Static(ref ty, _, _) = i.kind {
+                if error.is_some() {
+                    info!("warning: found multiple \"error\" marks");
+                    return;
+                }
+                error = Some((cx.node_def_id(i.id), ty.clone()));
+            }
+        });
+        let (error_def_id, error_ty) = match_or!([error] Some(x) => x; {
+            info!("warning: found no \"error\" static");
+            return;
+        });
+        let error_path = cx.def_path(error_def_id);
+
+        // (2) Collect the marked functions along with the sentinel value each one returns on
+        // error.
+        let mut candidates = Vec::new();
+        visit_nodes(krate, |i: &Item| {
+            if !st.marked(i.id, "target") {
+                return;
+            }
+            let block = match_or!([i.kind] ItemKind::Fn(_, _, ref block) => block; return);
+            candidates.push((cx.node_def_id(i.id), i.ident, block.clone()));
+        });
+
+        let mut fns = HashMap::new();
+        let mut propagate_only = Vec::new();
+        for (def_id, ident, block) in candidates {
+            let mut sentinels = Vec::new();
+            visit_nodes(&*block, |b: &Block| {
+                for (assign, ret) in b.stmts.iter().zip(b.stmts.iter().skip(1)) {
+                    if error_assign(assign, cx, error_def_id).is_none() {
+                        continue;
+                    }
+                    if let Some(val) = stmt_return(ret) {
+                        sentinels.push(val.clone());
+                    }
+                }
+            });
+            if sentinels.is_empty() {
+                propagate_only.push((def_id, ident, block));
+                continue;
+            }
+            if let Some(sentinel) = common_sentinel(&sentinels, ident) {
+                fns.insert(def_id, LastErrorFn {
+                    result_ident: mk().ident(format!("{}_result", ident)),
+                    sentinel,
+                });
+            }
+        }
+
+        // Functions that never set the error static themselves, but return early when a
+        // converted function fails, are converted as well, returning what they return then.
+        // Since they may in turn call each other, repeat until none is left.
+        while let Some(idx) = propagate_only.iter().position(|&(_, _, ref block)| {
+            !propagated_sentinels(block, cx, &fns).is_empty()
+        }) {
+            let (def_id, ident, block) = propagate_only.swap_remove(idx);
+            let sentinels = propagated_sentinels(&block, cx, &fns);
+            if let Some(sentinel) = common_sentinel(&sentinels, ident) {
+                fns.insert(def_id, LastErrorFn {
+                    result_ident: mk().ident(format!("{}_result", ident)),
+                    sentinel,
+                });
+            }
+        }
+        for (_, ident, _) in propagate_only {
+            warn!("`{}` never sets the error static; skipping it", ident);
+        }
+
+        // (3) Rewrite each function and add its compatibility wrapper.
+        FlatMapNodes::visit(krate, |i: P<Item>| {
+            if !st.marked(i.id, "target") {
+                return smallvec![i];
+            }
+            let info = match_or!([fns.get(&cx.node_def_id(i.id))] Some(x) => x;
+                                 return smallvec![i]);
+
+            let (sig, generics, mut block) = expect!([i.kind]
+                ItemKind::Fn(ref sig, ref generics, ref block) =>
+                    (sig.clone(), generics.clone(), block.clone()));
+
+            // Propagate errors from other converted functions with `?`.
+            MutVisitNodes::visit(&mut block, |b: &mut P<Block>| {
+                for stmt in &mut b.stmts {
+                    let call = match_or!([checked_call(stmt, cx, &fns, &info.sentinel)]
+                                         Some(x) => x; continue);
+                    *stmt = mk().semi_stmt(mk().try_expr(call));
+                }
+            });
+
+            // Wrap all returned values in `Ok`.
+            block.visit(&mut WrapOkReturns);
+            let is_unit = match sig.decl.output {
+                FunctionRetTy::Default(_) => true,
+                FunctionRetTy::Ty(ref ty) => match ty.kind {
+                    ast::TyKind::Tup(ref elems) => elems.is_empty(),
+                    _ => false,
+                },
+            };
+            let tail = block.stmts.last_mut().and_then(|s| match s.kind {
+                StmtKind::Expr(ref mut e) if !matches!([e.kind] ExprKind::Ret(..)) => Some(e),
+                _ => None,
+            });
+            match tail {
+                Some(e) => *e = mk_ok(e.clone()),
+                None if is_unit => block.stmts.push(mk().expr_stmt(mk_ok(unit_expr()))),
+                None => {}
+            }
+
+            // Turn `ERROR = e; return Ok(v);` into `return Err(e);`.
+            MutVisitNodes::visit(&mut block, |b: &mut P<Block>| {
+                let old_stmts = mem::replace(&mut b.stmts, Vec::new());
+                let mut stmts = Vec::with_capacity(old_stmts.len());
+                let mut old_stmts = old_stmts.into_iter().peekable();
+                while let Some(stmt) = old_stmts.next() {
+                    let err = error_assign(&stmt, cx, error_def_id).cloned();
+                    let is_ok_return = old_stmts.peek().map_or(false, |next| {
+                        match stmt_return(next) {
+                            Some(Some(val)) => matches!([val.kind] ExprKind::Call(..)),
+                            _ => false,
+                        }
+                    });
+                    match err {
+                        Some(err) if is_ok_return => {
+                            old_stmts.next();
+                            let err = mk().call_expr(mk().path_expr(vec!["Err"]), vec![err]);
+                            stmts.push(mk().semi_stmt(mk().return_expr(Some(err))));
+                        }
+                        _ => stmts.push(stmt),
+                    }
+                }
+                b.stmts = stmts;
+            });
+
+            let ret_ty = match sig.decl.output {
+                FunctionRetTy::Ty(ref ty) => ty.clone(),
+                FunctionRetTy::Default(_) => mk().tuple_ty(Vec::<P<Ty>>::new()),
+            };
+            let result_ty = mk().path_ty(vec![mk().path_segment_with_args(
+                "Result",
+                mk().angle_bracketed_args(vec![ret_ty, error_ty.clone()]),
+            )]);
+
+            let mut result_sig = sig.clone();
+            result_sig.header.ext = Extern::None;
+            result_sig.decl = sig.decl.clone().map(|decl| FnDecl {
+                output: FunctionRetTy::Ty(result_ty),
+                .. decl
+            });
+
+            // The wrapper takes over the original name and attributes, and gets a fresh
+            // `NodeId` since the original body moves to the `Result`-returning function.
+            let mut wrapper = P(Item {
+                id: DUMMY_NODE_ID,
+                .. (*i).clone()
+            });
+
+            let result_fn = i.map(|mut i| {
+                i.ident = info.result_ident;
+                i.attrs.retain(|attr| {
+                    let attr = attr.name_or_empty();
+                    attr != sym::no_mangle && attr != sym::export_name
+                });
+                i.kind = ItemKind::Fn(result_sig, generics.clone(), block);
+                i
+            });
+
+            // The wrapper keeps the original signature, so it can forward its arguments by name,
+            // falling back on `arg0`, `arg1`, ... for non-ident patterns.
+            let arg_names = sig.decl.inputs.iter().enumerate().map(|(idx, arg)| {
+                match arg.pat.kind {
+                    PatKind::Ident(_, ident, None) => ident,
+                    _ => mk().ident(format!("arg{}", idx)),
+                }
+            }).collect::<Vec<_>>();
+            let wrapper_decl = sig.decl.clone().map(|decl| {
+                let inputs = decl.inputs.iter().zip(arg_names.iter()).map(|(arg, name)| {
+                    mk().arg(&arg.ty, mk().ident_pat(name))
+                }).collect();
+                FnDecl { inputs, .. decl }
+            });
+
+            let call = mk().call_expr(
+                mk().path_expr(vec![info.result_ident]),
+                arg_names.iter().map(|name| mk().ident_expr(name)).collect(),
+            );
+            let ok_arm = mk().arm(
+                mk().tuple_struct_pat(vec!["Ok"], vec![mk().ident_pat("v")]),
+                None,
+                mk().ident_expr("v"),
+            );
+            let mut err_stmts = vec![mk().semi_stmt(
+                mk().assign_expr(mk().path_expr(error_path.clone()), mk().ident_expr("e")),
+            )];
+            if let Some(ref sentinel) = info.sentinel {
+                err_stmts.push(mk().expr_stmt(sentinel.clone()));
+            }
+            let err_arm = mk().arm(
+                mk().tuple_struct_pat(vec!["Err"], vec![mk().ident_pat("e")]),
+                None,
+                mk().block_expr(mk().block(err_stmts)),
+            );
+            let wrapper_body = mk().block(vec![
+                mk().expr_stmt(mk().match_expr(call, vec![ok_arm, err_arm])),
+            ]);
+
+            let wrapper_sig = FnSig { decl: wrapper_decl, .. sig };
+            wrapper.kind = ItemKind::Fn(wrapper_sig, generics, wrapper_body);
+
+            smallvec![result_fn, wrapper]
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// Visitor that wraps the value of every `return` in `Ok`, without descending into closures or
+/// nested items, whose `return`s belong to a different function.
+struct WrapOkReturns;
+
+impl MutVisitor for WrapOkReturns {
+    fn visit_expr(&mut self, e: &mut P<Expr>) {
+        if let ExprKind::Closure(..) = e.kind {
+            return;
+        }
+        mut_visit::noop_visit_expr(e, self);
+        if let ExprKind::Ret(ref mut val) = e.kind {
+            let inner = val.take().unwrap_or_else(unit_expr);
+            *val = Some(mk_ok(inner));
+        }
+    }
+
+    fn flat_map_item(&mut self, i: P<Item>) -> SmallVec<[P<Item>; 1]> {
+        smallvec![i]
+    }
+}
+
+fn mk_ok(e: P<Expr>) -> P<Expr> {
+    mk().call_expr(mk().path_expr(vec!["Ok"]), vec![e])
+}
+
+fn unit_expr() -> P<Expr> {
+    mk().tuple_expr(Vec::<P<Expr>>::new())
+}
+
+/// If `stmt` assigns to the error static, as in `ERROR = e;`, return `e`.
+fn error_assign<'a>(stmt: &'a Stmt, cx: &RefactorCtxt, error: DefId) -> Option<&'a P<Expr>> {
+    let e = match stmt.kind {
+        StmtKind::Semi(ref e) | StmtKind::Expr(ref e) => e,
+        _ => return None,
+    };
+    match e.kind {
+        ExprKind::Assign(ref lhs, ref rhs) if cx.try_resolve_expr(lhs) == Some(error) => Some(rhs),
+        _ => None,
+    }
+}
+
+/// If `stmt` is a `return`, return its (optional) value.
+fn stmt_return(stmt: &Stmt) -> Option<&Option<P<Expr>>> {
+    match stmt.kind {
+        StmtKind::Semi(ref e) | StmtKind::Expr(ref e) => match e.kind {
+            ExprKind::Ret(ref val) => Some(val),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Return the sentinel that all of `sentinels` are equal to, or warn that function `ident`
+/// returns different values on error.
+fn common_sentinel(sentinels: &[Option<P<Expr>>], ident: Ident) -> Option<Option<P<Expr>>> {
+    match sentinels.split_first() {
+        Some((first, rest)) if rest.iter().all(|s| s.ast_equiv(first)) => Some(first.clone()),
+        Some(_) => {
+            warn!("`{}` returns different values on error; skipping it", ident);
+            None
+        }
+        None => None,
+    }
+}
+
+/// Collect the values `w` of the `if foo(args) == v { return w; }` statements in `block`,
+/// which return early when a converted function fails.
+fn propagated_sentinels(
+    block: &Block,
+    cx: &RefactorCtxt,
+    fns: &HashMap<DefId, LastErrorFn>,
+) -> Vec<Option<P<Expr>>> {
+    let mut sentinels = Vec::new();
+    visit_nodes(block, |b: &Block| {
+        for stmt in &b.stmts {
+            if let Some((_, ret)) = propagating_call(stmt, cx, fns) {
+                sentinels.push(ret.clone());
+            }
+        }
+    });
+    sentinels
+}
+
+/// Match `if foo(args) == v { return w; }`, where `foo` is being converted and returns `v` on
+/// error, and `w` is the error sentinel of the current function.  Returns the replacement call
+/// `foo_result(args)`.
+fn checked_call(
+    stmt: &Stmt,
+    cx: &RefactorCtxt,
+    fns: &HashMap<DefId, LastErrorFn>,
+    sentinel: &Option<P<Expr>>,
+) -> Option<P<Expr>> {
+    let (call, ret) = propagating_call(stmt, cx, fns)?;
+    if !ret.ast_equiv(sentinel) {
+        return None;
+    }
+    Some(call)
+}
+
+/// Match `if foo(args) == v { return w; }`, where `foo` is being converted and returns `v` on
+/// error.  Returns the replacement call `foo_result(args)` along with `w`.
+fn propagating_call<'a>(
+    stmt: &'a Stmt,
+    cx: &RefactorCtxt,
+    fns: &HashMap<DefId, LastErrorFn>,
+) -> Option<(P<Expr>, &'a Option<P<Expr>>)> {
+    let e = match stmt.kind {
+        StmtKind::Semi(ref e) | StmtKind::Expr(ref e) => e,
+        _ => return None,
+    };
+    let (cond, then) = match e.kind {
+        ExprKind::If(ref cond, ref then, None) => (cond, then),
+        _ => return None,
+    };
+    let ret = match &then.stmts[..] {
+        [ret] => stmt_return(ret)?,
+        _ => return None,
+    };
+
+    let (lhs, rhs) = match cond.kind {
+        ExprKind::Binary(op, ref lhs, ref rhs) if op.node == BinOpKind::Eq => (lhs, rhs),
+        _ => return None,
+    };
+    let (func, args) = match lhs.kind {
+        ExprKind::Call(ref func, ref args) => (func, args),
+        _ => return None,
+    };
+    let callee = fns.get(&cx.try_resolve_expr(func)?)?;
+    match callee.sentinel {
+        Some(ref callee_sentinel) if callee_sentinel.ast_equiv(rhs) => {}
+        _ => return None,
+    }
+
+    let mut path = match func.kind {
+        ExprKind::Path(None, ref path) => path.clone(),
+        _ => return None,
+    };
+    path.segments.pop();
+    path.segments.push(mk().path_segment(callee.result_ident));
+    Some((mk().call_expr(mk().path_expr(path), args.clone()), ret))
+}
+
+
+/// # `collapse_forwarding_fns` Command
+///
+/// Usage: `collapse_forwarding_fns [aliases]`
+///
+/// Find functions whose body consists of a single call to another function that forwards the
+/// function's own arguments, and merge the ones that have the same signature and forward to
+/// the same callee in the same way.  The first function of each such group is kept; the others
+/// are removed and all references to them are rewritten to refer to the kept function.  Only
+/// functions with the same visibility are merged.
+///
+/// If `aliases` is passed, each removed function is replaced by a `use` item that re-exports
+/// the kept function under the removed function's name and visibility, which is also the
+/// visibility of the kept function.
+///
+/// Functions with generics, or with `#[no_mangle]` or `#[export_name]` attributes, are never
+/// removed, since their symbols may be referenced from outside the crate.
+///
+/// Example:
+///
+/// ```ignore
+///     unsafe fn read_u8(p: *const u8) -> u8 {
+///         read(p)
+///     }
+///
+///     unsafe fn read_byte(q: *const u8) -> u8 {
+///         read(q)
+///     }
+///
+///     unsafe fn f(p: *const u8) -> u8 {
+///         read_byte(p)
+///     }
+/// ```
+///
+/// After running `collapse_forwarding_fns`:
+///
+/// ```ignore
+///     unsafe fn read_u8(p: *const u8) -> u8 {
+///         read(p)
+///     }
+///
+///     unsafe fn f(p: *const u8) -> u8 {
+///         read_u8(p)
+///     }
+/// ```
+pub struct CollapseForwardingFns {
+    aliases: bool,
+}
+
+/// The call made by a forwarding function: which function it calls, and which of its own
+/// arguments it passes in each position.
+#[derive(PartialEq, Eq, Hash)]
+struct ForwardingCall {
+    callee: DefId,
+    arg_indices: Vec<usize>,
+    /// Whether the call is followed by a `;`, discarding its result.
+    is_semi: bool,
+}
+
+impl Transform for CollapseForwardingFns {
+    fn transform(&self, krate: &mut Crate, _st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Group forwarding functions by their call, signature and visibility.  For every
+        // call, we keep the signatures and visibilities seen so far, along with the function to
+        // keep for each.  Only functions of the same visibility are merged, so that references
+        // to a removed function, and its alias, can see the kept one.
+        let mut kept: HashMap<ForwardingCall, Vec<(FnSig, Visibility, DefId)>> = HashMap::new();
+        let mut replacements = HashMap::new();
+
+        visit_nodes(krate, |i: &Item| {
+            let (sig, generics, block) = match_or!([i.kind]
+                ItemKind::Fn(ref sig, ref generics, ref block) => (sig, generics, block); return);
+            if !generics.params.is_empty() {
+                return;
+            }
+            let call = match_or!([forwarding_call(&sig.decl, block, cx)] Some(x) => x; return);
+            let def_id = cx.node_def_id(i.id);
+            if call.callee == def_id {
+                return;
+            }
+
+            let is_exported = attr::contains_name(&i.attrs, sym::no_mangle) ||
+                attr::contains_name(&i.attrs, sym::export_name);
+            let candidates = kept.entry(call).or_insert_with(Vec::new);
+            let same_sig = candidates.iter().find(|(other, vis, _)| {
+                same_signature(other, sig) && vis.ast_equiv(&i.vis)
+            });
+            match same_sig {
+                Some(&(_, _, kept_id)) if !is_exported => {
+                    replacements.insert(def_id, kept_id);
+                }
+                _ => candidates.push((sig.clone(), i.vis.clone(), def_id)),
+            }
+        });
+
+        if replacements.is_empty() {
+            return;
+        }
+
+        // (2) Rewrite references to the removed functions.
+        fold_resolved_paths(krate, cx, |qself, path, def| {
+            match def[0].opt_def_id().and_then(|def_id| replacements.get(&def_id)) {
+                Some(&kept_id) => (qself, cx.def_path(kept_id)),
+                None => (qself, path),
+            }
+        });
+
+        // (3) Remove the functions, or replace them with aliases of the kept ones.
+        FlatMapNodes::visit(krate, |i: P<Item>| {
+            if !matches!([i.kind] ItemKind::Fn(..)) {
+                return smallvec![i];
+            }
+            let kept_id = match_or!([replacements.get(&cx.node_def_id(i.id))] Some(&x) => x;
+                                    return smallvec![i]);
+            if !self.aliases {
+                return smallvec![];
+            }
+            let alias = mk().vis(i.vis.clone()).use_simple_item(cx.def_path(kept_id), Some(i.ident));
+            smallvec![alias]
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// If `block` consists only of a call to a function with arguments that are all parameters
+/// of `decl`, describe that call.
+fn forwarding_call(decl: &FnDecl, block: &Block, cx: &RefactorCtxt) -> Option<ForwardingCall> {
+    let (e, is_semi) = match &block.stmts[..] {
+        [stmt] => match stmt.kind {
+            StmtKind::Expr(ref e) => (e, false),
+            StmtKind::Semi(ref e) => (e, true),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let (func, args) = match e.kind {
+        ExprKind::Call(ref func, ref args) => (func, args),
+        _ => return None,
+    };
+    if !matches!([func.kind] ExprKind::Path(..)) {
+        return None;
+    }
+    let callee = cx.try_resolve_expr(func)?;
+
+    let params = decl.inputs.iter().map(|arg| {
+        match arg.pat.kind {
+            PatKind::Ident(_, ident, None) => Some(ident.name),
+            _ => None,
+        }
+    }).collect::<Vec<_>>();
+    let arg_indices = args.iter().map(|arg| {
+        let path = match_or!([arg.kind] ExprKind::Path(None, ref path) => path; return None);
+        match &path.segments[..] {
+            [seg] => params.iter().position(|&p| p == Some(seg.ident.name)),
+            _ => None,
+        }
+    }).collect::<Option<Vec<_>>>()?;
+
+    Some(ForwardingCall { callee, arg_indices, is_semi })
+}
+
+/// Check whether two functions have the same header and argument and return types, ignoring
+/// argument names.
+fn same_signature(a: &FnSig, b: &FnSig) -> bool {
+    a.header.ast_equiv(&b.header) &&
+        a.decl.output.ast_equiv(&b.decl.output) &&
+        a.decl.inputs.len() == b.decl.inputs.len() &&
+        a.decl.inputs.iter().zip(b.decl.inputs.iter()).all(|(x, y)| x.ty.ast_equiv(&y.ty))
+}
+
+
+
+/// # `inline_wrappers` Command
+///
+/// Usage: `inline_wrappers`
+///
+/// Inline calls to trivial wrapper functions, and remove the wrappers that are no longer used.
+/// A trivial wrapper is a non-generic function whose body is a single expression, made only of
+/// calls, operators, casts, field accesses, indexing and literals, that uses each of the
+/// function's parameters at most once and only by value.
+///
+/// A call is inlined only if its arguments have no side effects and are passed without
+/// coercions, so that evaluating them in a different order or in a different place doesn't
+/// change their values.  Calls from other modules are inlined only if everything the wrapper
+/// refers to is public.  Wrappers with `#[no_mangle]` or `#[export_name]` attributes are never
+/// removed, since their symbols may be referenced from outside the crate.
+///
+/// Example:
+///
+/// ```ignore
+///     unsafe fn get_len(s: *const buf) -> size_t {
+///         (*s).len
+///     }
+///
+///     unsafe fn f(s: *const buf) -> size_t {
+///         get_len(s) + 1
+///     }
+/// ```
+///
+/// After running `inline_wrappers`:
+///
+/// ```ignore
+///     unsafe fn f(s: *const buf) -> size_t {
+///         (*s).len + 1
+///     }
+/// ```
+pub struct InlineWrappers;
+
+/// A trivial wrapper function, ready to be inlined.
+struct Wrapper {
+    /// The body of the wrapper, with paths to items made absolute.
+    body: P<Expr>,
+    /// For the uses of parameters in `body`, the index of the parameter used.
+    param_uses: HashMap<NodeId, usize>,
+    num_params: usize,
+    /// The module containing the wrapper.
+    module: HirId,
+    /// Whether all the items the body refers to are public, so it can be inlined anywhere.
+    is_public: bool,
+}
+
+impl Transform for InlineWrappers {
+    fn transform(&self, krate: &mut Crate, _st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the trivial wrappers.
+        let mut wrappers = HashMap::new();
+        let mut exported = HashSet::new();
+
+        visit_nodes(krate, |i: &Item| {
+            let wrapper = match_or!([trivial_wrapper(i, cx)] Some(x) => x; return);
+            let def_id = cx.node_def_id(i.id);
+            if attr::contains_name(&i.attrs, sym::no_mangle) ||
+                attr::contains_name(&i.attrs, sym::export_name) {
+                exported.insert(def_id);
+            }
+            wrappers.insert(def_id, wrapper);
+        });
+
+        if wrappers.is_empty() {
+            return;
+        }
+
+        // (2) Replace calls to the wrappers with their bodies.
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            let (func, args) = match_or!([e.kind]
+                ExprKind::Call(ref func, ref args) => (func, args); return);
+            if !matches!([func.kind] ExprKind::Path(..)) {
+                return;
+            }
+            let wrapper = match_or!([cx.try_resolve_expr(func).and_then(|id| wrappers.get(&id))]
+                                    Some(x) => x; return);
+            if args.len() != wrapper.num_params ||
+                !args.iter().all(|arg| is_simple_arg(arg) && !is_coerced(arg, cx)) {
+                return;
+            }
+            if !wrapper.is_public &&
+                cx.hir_map().get_module_parent_node(cx.node_to_hir_id(e.id)) != wrapper.module {
+                return;
+            }
+
+            let args = args.clone();
+            let mut body = wrapper.body.clone();
+            MutVisitNodes::visit(&mut body, |e: &mut P<Expr>| {
+                if let Some(&index) = wrapper.param_uses.get(&e.id) {
+                    *e = args[index].clone();
+                }
+            });
+            *e = body;
+        });
+
+        // (3) Remove the wrappers that are no longer referenced.
+        let mut used = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            if matches!([e.kind] ExprKind::Path(..)) {
+                used.extend(cx.try_resolve_expr(e));
+            }
+        });
+        visit_nodes(krate, |i: &Item| {
+            if matches!([i.kind] ItemKind::Use(..)) {
+                if let Some(path) = cx.try_resolve_use_id(i.id) {
+                    used.extend(path.res.opt_def_id());
+                }
+            }
+        });
+
+        FlatMapNodes::visit(krate, |i: P<Item>| {
+            if !matches!([i.kind] ItemKind::Fn(..)) {
+                return smallvec![i];
+            }
+            let def_id = cx.node_def_id(i.id);
+            if wrappers.contains_key(&def_id) && !used.contains(&def_id) &&
+                !exported.contains(&def_id) {
+                return smallvec![];
+            }
+            smallvec![i]
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// If `i` is a trivial wrapper function, prepare its body for inlining.
+fn trivial_wrapper(i: &Item, cx: &RefactorCtxt) -> Option<Wrapper> {
+    let (sig, generics, block) = match_or!([i.kind]
+        ItemKind::Fn(ref sig, ref generics, ref block) => (sig, generics, block); return None);
+    if !generics.params.is_empty() {
+        return None;
+    }
+    let e = match &block.stmts[..] {
+        [stmt] => match stmt.kind {
+            StmtKind::Expr(ref e) => e,
+            // A wrapper returning `()` can only be inlined where its call is, if the expression
+            // it discards is `()` as well.
+            StmtKind::Semi(ref e) if cx.opt_node_type(e.id).map_or(false, |ty| ty.is_unit()) => e,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    // The body might be coerced to the return type, which it won't be at the call site.
+    if is_coerced(e, cx) {
+        return None;
+    }
+
+    let params = sig.decl.inputs.iter().map(|arg| {
+        match arg.pat.kind {
+            PatKind::Ident(BindingMode::ByValue(_), _, None) => Some(cx.node_to_hir_id(arg.pat.id)),
+            _ => None,
+        }
+    }).collect::<Option<Vec<_>>>()?;
+
+    let mut param_uses = HashMap::new();
+    let mut defs = Vec::new();
+    if !trivial_expr(e, false, &params, &mut param_uses, &mut defs, cx) {
+        return None;
+    }
+    let mut used = HashSet::new();
+    if !param_uses.values().all(|&index| used.insert(index)) ||
+        defs.contains(&cx.node_def_id(i.id)) {
+        return None;
+    }
+    let tcx = cx.ty_ctxt();
+    let is_public = defs.iter().all(|&id| tcx.visibility(id) == ty::Visibility::Public);
+
+    let mut body = e.clone();
+    fold_resolved_paths(&mut body, cx, |qself, path, def| {
+        if qself.is_some() {
+            return (qself, path);
+        }
+        match def.get(0).and_then(|def| def.opt_def_id()) {
+            Some(id) => {
+                let mut new_path = cx.def_path(id);
+                // Keep explicit generic arguments, as in `size_of::<T>()`
+                new_path.segments.last_mut().unwrap().args =
+                    path.segments.last().unwrap().args.clone();
+                (None, new_path)
+            }
+            None => (None, path),
+        }
+    });
+
+    Some(Wrapper {
+        body,
+        param_uses,
+        num_params: params.len(),
+        module: cx.hir_map().get_module_parent_node(cx.node_to_hir_id(i.id)),
+        is_public,
+    })
+}
+
+/// Check that `e` can be the body of a trivial wrapper, recording the parameters it uses and the
+/// items it refers to.  `is_place` is set when `e` is used as a place, which parameters can't be,
+/// since the place would become the caller's own argument.
+fn trivial_expr(
+    e: &Expr,
+    is_place: bool,
+    params: &[HirId],
+    param_uses: &mut HashMap<NodeId, usize>,
+    defs: &mut Vec<DefId>,
+    cx: &RefactorCtxt,
+) -> bool {
+    let mut sub = |e: &Expr, is_place: bool| trivial_expr(e, is_place, params, param_uses, defs, cx);
+    match e.kind {
+        ExprKind::Lit(..) => true,
+        ExprKind::Path(..) => match cx.try_resolve_expr_hir(e) {
+            Some(Res::Local(id)) => match params.iter().position(|&p| p == id) {
+                Some(index) if !is_place => {
+                    param_uses.insert(e.id, index);
+                    true
+                }
+                _ => false,
+            },
+            Some(res) => match res.opt_def_id() {
+                Some(id) => {
+                    defs.push(id);
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        },
+        ExprKind::Paren(ref x) => sub(x, is_place),
+        ExprKind::Call(ref func, ref args) => {
+            sub(func, false) && args.iter().all(|arg| sub(arg, false))
+        }
+        ExprKind::MethodCall(_, ref args) => {
+            // Trait methods might not be in scope at the call site
+            let callee = match_or!([cx.opt_callee(e)] Some(x) => x; return false);
+            if cx.ty_ctxt().trait_of_item(callee).is_some() {
+                return false;
+            }
+            sub(&args[0], true) && args[1..].iter().all(|arg| sub(arg, false))
+        }
+        ExprKind::Binary(_, ref a, ref b) => sub(a, false) && sub(b, false),
+        ExprKind::Unary(_, ref x) | ExprKind::Cast(ref x, _) => sub(x, false),
+        ExprKind::Field(ref x, _) | ExprKind::AddrOf(_, _, ref x) => sub(x, true),
+        ExprKind::Index(ref a, ref i) => sub(a, true) && sub(i, false),
+        ExprKind::Block(ref b, None) if b.rules == BlockCheckMode::Unsafe(UnsafeSource::UserProvided) => {
+            match &b.stmts[..] {
+                [stmt] => match stmt.kind {
+                    StmtKind::Expr(ref x) => sub(x, is_place),
+                    _ => false,
+                },
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Check whether evaluating `e` has no side effects.
+fn is_simple_arg(e: &Expr) -> bool {
+    match e.kind {
+        ExprKind::Lit(..) | ExprKind::Path(..) => true,
+        ExprKind::Paren(ref x) |
+        ExprKind::Field(ref x, _) |
+        ExprKind::Unary(_, ref x) |
+        ExprKind::Cast(ref x, _) |
+        ExprKind::AddrOf(_, _, ref x) => is_simple_arg(x),
+        _ => false,
+    }
+}
+
+/// Check whether `e` is implicitly coerced to another type where it is used.
+fn is_coerced(e: &Expr, cx: &RefactorCtxt) -> bool {
+    cx.opt_node_type(e.id) != cx.opt_adjusted_node_type(e.id)
+}
+
 /// # `abstract` Command
 ///
 /// Usage: `abstract SIG PAT [BODY]`
@@ -803,8 +1928,14 @@ pub fn register_commands(reg: &mut Registry) {
     reg.register("func_to_method", |_args| mk(ToMethod));
     reg.register("fix_unused_unsafe", |_args| mk(FixUnusedUnsafe));
     reg.register("sink_unsafe", |_args| mk(SinkUnsafe));
+    reg.register("purge_unsafe", |_args| Box::new(PurgeUnsafe));
     reg.register("wrap_extern", |_args| mk(WrapExtern));
     reg.register("wrap_api", |_args| mk(WrapApi));
+    reg.register("last_error_to_result", |_args| mk(LastErrorToResult));
+    reg.register("collapse_forwarding_fns", |args| mk(CollapseForwardingFns {
+        aliases: args.get(0).map_or(false, |x| x == "aliases"),
+    }));
+    reg.register("inline_wrappers", |_args| mk(InlineWrappers));
     reg.register("abstract", |args| mk(Abstract {
         sig: args[0].clone(),
         pat: args[1].clone(),
@@ -49,6 +49,7 @@ macro_rules! transform_modules {
 }
 
 transform_modules! {
+    buffers,
     canonicalize_refs,
     casts,
     char_literals,
@@ -65,6 +66,7 @@ transform_modules! {
     ownership,
     retype,
     rewrite,
+    safety,
     statics,
     structs,
     test,
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fs;
 
 use arena::SyncDroplessArena;
 use rustc::hir::def_id::DefId;
@@ -47,6 +48,14 @@ pub fn register_commands(reg: &mut Registry) {
             do_mark_pointers(st, cx);
         }))
     });
+
+    reg.register("ownership_report", |args| {
+        let path = args.get(0).cloned();
+
+        Box::new(DriverCommand::new(Phase::Phase3, move |st, cx| {
+            do_report(st, cx, path.as_ref().map(|x| x as &str));
+        }))
+    });
 }
 
 /// # `ownership_annotate` Command
@@ -498,11 +507,11 @@ fn callee_new_name(cx: &RefactorCtxt,
 ///
 /// Usage: `ownership_mark_pointers [MARK]`
 ///
-/// Marks: reads `MARK`/`target`; sets `ref`, `mut`, and `box`
+/// Marks: reads `MARK`/`target`; sets `ref`, `mut`, and `move`
 ///
 /// Run ownership analysis on functions bearing `MARK` (default: `target`),
 /// then for pointer type appearing in their argument and return types,
-/// apply one of the marks `ref`, `mut`, or `box`, reflecting the results
+/// apply one of the marks `ref`, `mut`, or `move`, reflecting the results
 /// of the ownership analysis.
 /// See `analysis/ownership/README.md` for details on ownership inference.
 fn do_mark_pointers(st: &CommandState, cx: &RefactorCtxt) {
@@ -600,3 +609,35 @@ fn do_mark_pointers(st: &CommandState, cx: &RefactorCtxt) {
         st.add_mark(ast_ty.id, label);
     });
 }
+
+
+/// # `ownership_report` Command
+///
+/// Usage: `ownership_report [FILE]`
+///
+/// Run ownership analysis on the crate, and write a report of its results to
+/// `FILE`, or to stdout if no file is given.  The report lists every static,
+/// struct field, and function signature containing pointers, with the
+/// location of its definition and the permission inferred for each pointer:
+/// `READ` for pointers that can become `&T`, `WRITE` for `&mut T`, and `MOVE`
+/// for `Box<T>`.  Functions with several monomorphizations list the
+/// signature of each.
+///
+/// Unlike `ownership_annotate`, this leaves the crate unchanged, so it can be
+/// used to review the results before applying `ownership_mark_pointers` and
+/// the transforms that consume its marks.
+/// See `analysis/ownership/README.md` for details on ownership inference.
+fn do_report(st: &CommandState, cx: &RefactorCtxt, path: Option<&str>) {
+    let arena = SyncDroplessArena::default();
+    let ana = ownership::analyze(&st, &cx, &arena);
+    let report = ownership::report(cx, &ana);
+
+    match path {
+        Some(path) => {
+            if let Err(e) = fs::write(path, report) {
+                cx.session().fatal(&format!("ownership_report: can't write {}: {}", path, e));
+            }
+        }
+        None => print!("{}", report),
+    }
+}
@@ -1,21 +1,23 @@
 use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut;
 use rustc::hir;
+use rustc::hir::def::Res;
 use rustc::hir::def_id::DefId;
 use rustc::ty::{self, TyKind, TyCtxt, ParamEnv};
 use syntax::ast::*;
+use syntax::attr;
 use syntax::mut_visit::{self, MutVisitor};
 use rustc_errors::PResult;
 use rustc_parse::parser::Parser;
 use syntax::token::{TokenKind, BinOpToken};
 use syntax::print::pprust;
 use syntax::ptr::P;
-use syntax_pos::Span;
+use syntax_pos::{sym, Span};
 use smallvec::{smallvec, SmallVec};
 
 use c2rust_ast_builder::{mk, IntoSymbol};
-use crate::ast_manip::{FlatMapNodes, MutVisit, MutVisitNodes, fold_output_exprs};
-use crate::ast_manip::fn_edit::{mut_visit_fns, visit_fns};
+use crate::ast_manip::{AstEquiv, FlatMapNodes, MutVisit, MutVisitNodes, fold_output_exprs, visit_nodes};
+use crate::ast_manip::fn_edit::{FnKind, FnLike, mut_visit_fns, visit_fns};
 use crate::ast_manip::lr_expr::{self, fold_expr_with_context, fold_exprs_with_context};
 use crate::command::{Command, CommandState, RefactorState, Registry, TypeckLoopResult};
 use crate::driver::{self, Phase, parse_ty, parse_expr};
@@ -190,6 +192,1384 @@ impl Transform for RetypeReturn {
 }
 
 
+/// # `ptr_to_ref` Command
+///
+/// Usage: `ptr_to_ref`
+///
+/// Marks: `target`
+///
+/// For each function marked `target`, change its raw pointer parameters of
+/// type `*const T` and `*mut T` to `&T` and `&mut T`.  Uses of a parameter in
+/// the function body must all dereference it, which works the same on the
+/// reference.  At call sites, a borrow cast to a pointer, as in
+/// `&mut x as *mut T`, is passed as the borrow itself, and other pointers are
+/// converted with `as_ref().unwrap()` or `as_mut().unwrap()`, which panic on
+/// null pointers.  Since these are unsafe methods, calls must be in unsafe
+/// code, as they are in translated code.
+///
+/// A parameter is left unchanged, with a warning, if the function stores it,
+/// offsets it or uses it in any other way than by dereferencing it, since the
+/// reference would not live long enough or would not point to the right
+/// object.  So is a parameter that a call passes the same pointer to as
+/// another argument, since the reference would alias it.  Exported
+/// functions, and functions used other than by calling them, are left
+/// unchanged with a warning as well, since their callers can't be updated.
+///
+/// Example:
+///
+/// ```ignore
+///     unsafe fn get(p: *const i32) -> i32 {   // p: target
+///         *p
+///     }
+///
+///     unsafe fn f(x: i32, q: *const i32) -> i32 {
+///         get(&x as *const i32) + get(q)
+///     }
+/// ```
+///
+/// After running `ptr_to_ref`:
+///
+/// ```ignore
+///     unsafe fn get(p: &i32) -> i32 {
+///         *p
+///     }
+///
+///     unsafe fn f(x: i32, q: *const i32) -> i32 {
+///         get(&x) + get(q.as_ref().unwrap())
+///     }
+/// ```
+pub struct PtrToRef;
+
+impl Transform for PtrToRef {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the functions that are used other than by calling them, whose callers we
+        // can't update.
+        let fn_values = fn_values(krate, cx);
+
+        // (2) Find the parameters that are only dereferenced.
+
+        // Functions to modify, by DefId.  For each one, we track the indices of the parameters to
+        // convert, with their names and the mutability of their references.
+        let mut mod_fns: HashMap<DefId, HashMap<usize, (Ident, Mutability)>> = HashMap::new();
+
+        visit_fns(krate, |fl| {
+            if !st.marked(fl.id, "target") ||
+                !has_known_callers(&fl, &fn_values, "ptr_to_ref", cx) {
+                return;
+            }
+            let block = match_or!([fl.block] Some(ref b) => b; return);
+
+            let mut converted = HashMap::new();
+            for (i, arg) in fl.decl.inputs.iter().enumerate() {
+                let mt = match_or!([arg.ty.kind]
+                    syntax::ast::TyKind::Ptr(ref mt) => mt; continue);
+                let ident = match_or!([arg.pat.kind]
+                    PatKind::Ident(BindingMode::ByValue(_), ident, None) => ident; continue);
+                let param = cx.hir_map().node_to_hir_id(arg.pat.id);
+                if let Err((span, reason)) = check_deref_only(block, param, cx) {
+                    cx.session().span_warn(span, &format!(
+                        "ptr_to_ref: can't convert parameter `{}` of `{}` to a reference: it {}",
+                        ident, fl.ident, reason));
+                    continue;
+                }
+                converted.insert(i, (ident, mt.mutbl));
+            }
+
+            if !converted.is_empty() {
+                mod_fns.insert(cx.node_def_id(fl.id), converted);
+            }
+        });
+
+        // (3) Keep the parameters that a call passes the same pointer to as another argument,
+        // since their references would alias it.
+        visit_nodes(krate, |e: &Expr| {
+            let args = match_or!([e.kind] ExprKind::Call(_, ref args) => args; return);
+            let callee = match_or!([cx.opt_callee(e)] Some(x) => x; return);
+            let converted = match_or!([mod_fns.get_mut(&callee)] Some(x) => x; return);
+            converted.retain(|&idx, &mut (ident, _)| {
+                let aliased = args.iter().enumerate()
+                    .any(|(i, arg)| i != idx && same_pointer(arg, &args[idx]));
+                if aliased {
+                    cx.session().span_warn(e.span, &format!(
+                        "ptr_to_ref: can't convert parameter `{}` to a reference: this call \
+                         passes the same pointer as another argument", ident));
+                }
+                !aliased
+            });
+        });
+        mod_fns.retain(|_, converted| !converted.is_empty());
+
+        // (4) Change the types of the parameters.
+        mut_visit_fns(krate, |fl| {
+            let converted = match_or!([mod_fns.get(&cx.node_def_id(fl.id))] Some(x) => x; return);
+            for (&i, &(_, mutbl)) in converted {
+                let arg = &mut fl.decl.inputs[i];
+                let pointee = expect!([arg.ty.kind]
+                    syntax::ast::TyKind::Ptr(ref mt) => mt.ty.clone());
+                arg.ty = mk().set_mutbl(mutbl).ref_ty(pointee);
+            }
+        });
+
+        // (5) Rewrite callsites of modified functions.
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            if !matches!([e.kind] ExprKind::Call(..)) {
+                return;
+            }
+            let callee = match_or!([cx.opt_callee(&e)] Some(x) => x; return);
+            let converted = match_or!([mod_fns.get(&callee)] Some(x) => x; return);
+            let args = expect!([e.kind] ExprKind::Call(_, ref mut args) => args);
+            for (&idx, &(_, mutbl)) in converted {
+                args[idx] = ptr_arg_to_ref(args[idx].clone(), mutbl, cx);
+            }
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// Find the functions that are used other than as the callee of a call, e.g. by taking their
+/// address.
+pub fn fn_values(krate: &Crate, cx: &RefactorCtxt) -> HashSet<DefId> {
+    let mut callees = HashSet::new();
+    visit_nodes(krate, |e: &Expr| {
+        if let ExprKind::Call(ref func, _) = e.kind {
+            callees.insert(func.id);
+        }
+    });
+    let mut fn_values = HashSet::new();
+    visit_nodes(krate, |e: &Expr| {
+        if matches!([e.kind] ExprKind::Path(..)) && !callees.contains(&e.id) {
+            fn_values.extend(cx.try_resolve_expr(e));
+        }
+    });
+    fn_values
+}
+
+/// Check that all callers of `fl` are in the crate and call it directly, so that changing its
+/// parameters can update them.  Otherwise, warn that command `cmd` leaves it unchanged.
+fn has_known_callers(
+    fl: &FnLike,
+    fn_values: &HashSet<DefId>,
+    cmd: &str,
+    cx: &RefactorCtxt,
+) -> bool {
+    if fl.kind != FnKind::Normal {
+        return false;
+    }
+    let reason = if attr::contains_name(&fl.attrs, sym::no_mangle) ||
+        attr::contains_name(&fl.attrs, sym::export_name) {
+        "it is exported"
+    } else if fn_values.contains(&cx.node_def_id(fl.id)) {
+        "it is used other than by calling it"
+    } else {
+        return true;
+    };
+    cx.session().span_warn(fl.span, &format!(
+        "{}: can't convert the parameters of `{}`: {}", cmd, fl.ident, reason));
+    false
+}
+
+/// Check that the only uses of the parameter `param` in `block` dereference it.  Otherwise,
+/// return the span of a use that doesn't and a description of it.
+fn check_deref_only(
+    block: &Block,
+    param: hir::HirId,
+    cx: &RefactorCtxt,
+) -> Result<(), (Span, &'static str)> {
+    // The uses of values in positions that dereference, offset or store them.
+    let mut derefs = HashSet::new();
+    let mut offsets = HashSet::new();
+    let mut stores = HashSet::new();
+    visit_nodes(block, |e: &Expr| {
+        match e.kind {
+            ExprKind::Unary(UnOp::Deref, ref x) => {
+                derefs.insert(strip_parens(x).id);
+            }
+            ExprKind::MethodCall(ref seg, ref args) => {
+                let name = seg.ident.as_str();
+                if ["offset", "add", "sub", "wrapping_offset", "wrapping_add", "wrapping_sub"]
+                    .contains(&&*name) {
+                    offsets.insert(strip_parens(&args[0]).id);
+                }
+            }
+            ExprKind::Binary(_, ref lhs, ref rhs) => {
+                offsets.insert(strip_parens(lhs).id);
+                offsets.insert(strip_parens(rhs).id);
+            }
+            ExprKind::Assign(_, ref rhs) | ExprKind::AssignOp(_, _, ref rhs) => {
+                stores.insert(strip_parens(rhs).id);
+            }
+            ExprKind::Struct(_, ref fields, _) => {
+                stores.extend(fields.iter().map(|f| strip_parens(&f.expr).id));
+            }
+            ExprKind::Array(ref elems) | ExprKind::Tup(ref elems) => {
+                stores.extend(elems.iter().map(|x| strip_parens(x).id));
+            }
+            _ => {}
+        }
+    });
+    visit_nodes(block, |l: &Local| {
+        if let Some(ref init) = l.init {
+            stores.insert(strip_parens(init).id);
+        }
+    });
+
+    let mut result = Ok(());
+    visit_nodes(block, |e: &Expr| {
+        if result.is_err() || !matches!([e.kind] ExprKind::Path(..)) {
+            return;
+        }
+        if cx.try_resolve_expr_hir(e) != Some(Res::Local(param)) || derefs.contains(&e.id) {
+            return;
+        }
+        let reason = if offsets.contains(&e.id) {
+            "is offset"
+        } else if stores.contains(&e.id) {
+            "is stored"
+        } else {
+            "is used other than by dereferencing it"
+        };
+        result = Err((e.span, reason));
+    });
+    result
+}
+
+/// Strip the parentheses around an expression.
+pub fn strip_parens(e: &Expr) -> &Expr {
+    match e.kind {
+        ExprKind::Paren(ref inner) => strip_parens(inner),
+        _ => e,
+    }
+}
+
+/// Whether two arguments of a call pass the same pointer, as far as we can tell from their
+/// expressions: casts, borrows and `as_ptr()` or `as_mut_ptr()` calls are looked through, so
+/// `&mut x as *mut T` and `&x as *const T` are the same pointer, as are `v.as_ptr()` and
+/// `v.as_mut_ptr()`.
+fn same_pointer(a: &Expr, b: &Expr) -> bool {
+    fn base(e: &Expr) -> &Expr {
+        match e.kind {
+            ExprKind::Paren(ref inner) |
+            ExprKind::Cast(ref inner, _) |
+            ExprKind::AddrOf(_, _, ref inner) => base(inner),
+            ExprKind::MethodCall(ref seg, ref args)
+                if seg.ident.as_str() == "as_ptr" || seg.ident.as_str() == "as_mut_ptr" =>
+                base(&args[0]),
+            _ => e,
+        }
+    }
+    let (a, b) = (base(a), base(b));
+    // Literals, like the `0` of a null pointer, don't point to anything.
+    !matches!([a.kind] ExprKind::Lit(..)) && a.ast_equiv(b)
+}
+
+/// Convert the argument `arg`, a pointer, to a reference of mutability `mutbl`.
+fn ptr_arg_to_ref(arg: P<Expr>, mutbl: Mutability, cx: &RefactorCtxt) -> P<Expr> {
+    // Arguments that are already references are coerced to the parameter type.
+    if let Some(ty) = cx.opt_node_type(arg.id) {
+        if let ty::TyKind::Ref(..) = ty.kind {
+            return arg;
+        }
+    }
+    // `&mut x as *mut T` becomes `&mut x`.
+    if let ExprKind::Cast(ref inner, _) = arg.kind {
+        if let ExprKind::AddrOf(BorrowKind::Ref, m, _) = inner.kind {
+            if m == mutbl {
+                return inner.clone();
+            }
+        }
+    }
+    let method = match mutbl {
+        Mutability::Immutable => "as_ref",
+        Mutability::Mutable => "as_mut",
+    };
+    let opt = mk().method_call_expr(arg, method, Vec::<P<Expr>>::new());
+    mk().method_call_expr(opt, "unwrap", Vec::<P<Expr>>::new())
+}
+
+
+/// # `ptr_len_to_slice` Command
+///
+/// Usage: `ptr_len_to_slice`
+///
+/// Marks: `target`
+///
+/// Replace pairs of a raw pointer parameter and a length parameter with a
+/// single slice parameter.  Pointer parameters marked `target` are converted
+/// together with the parameter following them.  In functions marked
+/// `target`, every `*const T` or `*mut T` parameter directly followed by a
+/// parameter of an integer type is converted, if the length counts
+/// elements: either `T` is a one-byte type, or the body bounds an index of
+/// the pointer by the length, as in `for i in 0..n`.  The pair becomes a
+/// parameter of type `&[T]` or `&mut [T]`, with the name of the pointer.
+///
+/// In the function body, `*p`, `*p.offset(i)` and `*p.add(i)` become `p[0]`
+/// and `p[i as usize]`, and uses of the length become `p.len()`.  At call
+/// sites, `x.as_ptr(), x.len()` is passed as `&x[..]`, and other pointers
+/// and lengths as `std::slice::from_raw_parts(ptr, len)` or
+/// `from_raw_parts_mut`, whose pointer must not be null, even for a length
+/// of zero.
+///
+/// A pair is left unchanged, with a warning, if the function uses the
+/// pointer in any other way, or assigns to the length.  So is a pair that a
+/// call passes the same pointer to as another argument, since the slice
+/// would alias it, or a length computed with `size_of`, which counts bytes
+/// rather than elements.  Exported functions, and functions used other than
+/// by calling them, are left unchanged with a warning as well, since their
+/// callers can't be updated.
+///
+/// Example:
+///
+/// ```ignore
+///     unsafe fn sum(p: *const i32, n: usize) -> i32 {   // sum: target
+///         let mut s = 0;
+///         for i in 0..n {
+///             s += *p.offset(i as isize);
+///         }
+///         s
+///     }
+///
+///     unsafe fn f(v: &Vec<i32>) -> i32 {
+///         sum(v.as_ptr(), v.len())
+///     }
+/// ```
+///
+/// After running `ptr_len_to_slice`:
+///
+/// ```ignore
+///     unsafe fn sum(p: &[i32]) -> i32 {
+///         let mut s = 0;
+///         for i in 0..p.len() {
+///             s += p[i as isize as usize];
+///         }
+///         s
+///     }
+///
+///     unsafe fn f(v: &Vec<i32>) -> i32 {
+///         sum(&v[..])
+///     }
+/// ```
+pub struct PtrLenToSlice;
+
+impl Transform for PtrLenToSlice {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the functions that are used other than by calling them, whose callers we
+        // can't update.
+        let fn_values = fn_values(krate, cx);
+
+        // (2) Find the pairs of parameters to replace with slices.
+
+        // Functions to modify, by DefId.  For each one, we track the indices of the pointer
+        // parameters to convert, which their length parameters follow, with the names of both and
+        // the mutability of their slices.
+        let mut mod_fns: HashMap<DefId, Vec<(usize, Ident, Ident, Mutability)>> = HashMap::new();
+
+        visit_fns(krate, |fl| {
+            let marked_args = fl.decl.inputs.iter().any(|arg| st.marked(arg.id, "target"));
+            if !marked_args && !st.marked(fl.id, "target") {
+                return;
+            }
+            if !has_known_callers(&fl, &fn_values, "ptr_len_to_slice", cx) {
+                return;
+            }
+            let block = match_or!([fl.block] Some(ref b) => b; return);
+
+            let mut converted = vec![];
+            let inputs = &fl.decl.inputs;
+            let mut i = 0;
+            while i + 1 < inputs.len() {
+                let (ptr, len) = (&inputs[i], &inputs[i + 1]);
+                let is_pair = if marked_args {
+                    st.marked(ptr.id, "target")
+                } else {
+                    is_int_ty(&len.ty)
+                };
+                let mt = match ptr.ty.kind {
+                    syntax::ast::TyKind::Ptr(ref mt) if is_pair => mt,
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                };
+                let (ptr_ident, len_ident) = match (&ptr.pat.kind, &len.pat.kind) {
+                    (&PatKind::Ident(BindingMode::ByValue(_), ptr_ident, None),
+                     &PatKind::Ident(BindingMode::ByValue(_), len_ident, None)) =>
+                        (ptr_ident, len_ident),
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                };
+                let ptr_id = cx.hir_map().node_to_hir_id(ptr.pat.id);
+                let len_id = cx.hir_map().node_to_hir_id(len.pat.id);
+                let mut result = check_slice_uses(block, ptr_id, len_id, cx);
+                // A length that only follows the pointer may count bytes rather than elements,
+                // unless the elements are bytes or the body bounds its indices by it.
+                if result.is_ok() && !marked_args && !is_byte_ty(&mt.ty) &&
+                    !bounds_indices(block, ptr_id, len_id, cx) {
+                    result = Err((len.pat.span, "nothing shows that the length counts elements"));
+                }
+                if let Err((span, reason)) = result {
+                    cx.session().span_warn(span, &format!(
+                        "ptr_len_to_slice: can't convert `{}` and `{}` of `{}` to a slice: \
+                         {}", ptr_ident, len_ident, fl.ident, reason));
+                    i += 1;
+                    continue;
+                }
+
+                converted.push((i, ptr_ident, len_ident, mt.mutbl));
+                i += 2;
+            }
+
+            if !converted.is_empty() {
+                mod_fns.insert(cx.node_def_id(fl.id), converted);
+            }
+        });
+
+        // (3) Keep the pairs that a call passes the same pointer to as another argument, since
+        // their slices would alias it, or a length computed from `size_of`, which counts bytes.
+        visit_nodes(krate, |e: &Expr| {
+            let args = match_or!([e.kind] ExprKind::Call(_, ref args) => args; return);
+            let callee = match_or!([cx.opt_callee(e)] Some(x) => x; return);
+            let converted = match_or!([mod_fns.get_mut(&callee)] Some(x) => x; return);
+            converted.retain(|&(idx, ptr_ident, len_ident, _)| {
+                let reason = if args.iter().enumerate()
+                    .any(|(i, arg)| i != idx && same_pointer(arg, &args[idx])) {
+                    "this call passes the same pointer as another argument"
+                } else if uses_size_of(&args[idx + 1]) {
+                    "this call passes a length computed from `size_of`, which counts bytes"
+                } else {
+                    return true;
+                };
+                cx.session().span_warn(e.span, &format!(
+                    "ptr_len_to_slice: can't convert `{}` and `{}` to a slice: {}",
+                    ptr_ident, len_ident, reason));
+                false
+            });
+        });
+        mod_fns.retain(|_, converted| !converted.is_empty());
+
+        // (4) Replace the pairs of parameters with slices, and rewrite function bodies.
+        mut_visit_fns(krate, |fl| {
+            let converted = match_or!([mod_fns.get(&cx.node_def_id(fl.id))] Some(x) => x; return);
+
+            // The pointer parameters, with the mutability of their slices, and the length
+            // parameters, with the uses that replace them.
+            let mut ptrs = HashMap::new();
+            let mut lens = HashMap::new();
+            for &(i, ptr_ident, _, mutbl) in converted {
+                let (ptr, len) = (&fl.decl.inputs[i], &fl.decl.inputs[i + 1]);
+                let mut len_expr = mk().method_call_expr(
+                    mk().ident_expr(ptr_ident), "len", Vec::<P<Expr>>::new());
+                if !is_usize_ty(&len.ty) {
+                    len_expr = mk().cast_expr(len_expr, len.ty.clone());
+                }
+                ptrs.insert(cx.hir_map().node_to_hir_id(ptr.pat.id), mutbl);
+                lens.insert(cx.hir_map().node_to_hir_id(len.pat.id), len_expr);
+            }
+
+            for &(i, _, _, mutbl) in converted.iter().rev() {
+                fl.decl.inputs.remove(i + 1);
+                let ptr = &mut fl.decl.inputs[i];
+                let elem_ty = expect!([ptr.ty.kind]
+                    syntax::ast::TyKind::Ptr(ref mt) => mt.ty.clone());
+                ptr.ty = mk().set_mutbl(mutbl).ref_ty(mk().slice_ty(elem_ty));
+            }
+
+            fl.block.as_mut().map(|b| MutVisitNodes::visit(b, |e: &mut P<Expr>| {
+                if let Some(new_e) = slice_index(e, &ptrs, cx) {
+                    *e = new_e;
+                } else if let Some(Res::Local(id)) = cx.try_resolve_expr_hir(e) {
+                    if let Some(len_expr) = lens.get(&id) {
+                        *e = len_expr.clone();
+                    }
+                }
+            }));
+        });
+
+        // (5) Rewrite callsites of modified functions.
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            if !matches!([e.kind] ExprKind::Call(..)) {
+                return;
+            }
+            let callee = match_or!([cx.opt_callee(&e)] Some(x) => x; return);
+            let converted = match_or!([mod_fns.get(&callee)] Some(x) => x; return);
+            let args = expect!([e.kind] ExprKind::Call(_, ref mut args) => args);
+            for &(idx, _, _, mutbl) in converted.iter().rev() {
+                let len = args.remove(idx + 1);
+                args[idx] = ptr_len_arg_to_slice(args[idx].clone(), len, mutbl, cx);
+            }
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// Whether `ty` names one of the integer types C lengths are translated to.
+fn is_int_ty(ty: &Ty) -> bool {
+    let path = match_or!([ty.kind] syntax::ast::TyKind::Path(None, ref path) => path; return false);
+    let name = path.segments.last().unwrap().ident.as_str();
+    ["usize", "isize", "u64", "i64", "u32", "i32", "u16", "i16",
+     "size_t", "ssize_t", "c_int", "c_uint", "c_long", "c_ulong"].contains(&&*name)
+}
+
+/// Whether `ty` names a one-byte type, whose lengths count bytes and elements alike.
+fn is_byte_ty(ty: &Ty) -> bool {
+    let path = match_or!([ty.kind] syntax::ast::TyKind::Path(None, ref path) => path; return false);
+    let name = path.segments.last().unwrap().ident.as_str();
+    ["u8", "i8", "c_char", "c_schar", "c_uchar"].contains(&&*name)
+}
+
+/// Strip the parentheses and casts around an expression.
+fn strip_casts(e: &Expr) -> &Expr {
+    match e.kind {
+        ExprKind::Paren(ref inner) | ExprKind::Cast(ref inner, _) => strip_casts(inner),
+        _ => e,
+    }
+}
+
+/// Check whether `block` bounds an index of the pointer parameter `ptr` by the length parameter
+/// `len`, as in `for i in 0..len { *ptr.offset(i) }` or `while i < len`, which shows that the
+/// length counts elements.
+fn bounds_indices(block: &Block, ptr: hir::HirId, len: hir::HirId, cx: &RefactorCtxt) -> bool {
+    fn is_local(e: &Expr, id: hir::HirId, cx: &RefactorCtxt) -> bool {
+        cx.try_resolve_expr_hir(strip_casts(e)) == Some(Res::Local(id))
+    }
+
+    // The local variables that index the pointer.
+    let mut indices = HashSet::new();
+    visit_nodes(block, |e: &Expr| {
+        let x = match_or!([e.kind] ExprKind::Unary(UnOp::Deref, ref x) => strip_parens(x); return);
+        match x.kind {
+            ExprKind::MethodCall(ref seg, ref args)
+                if (seg.ident.as_str() == "offset" || seg.ident.as_str() == "add") &&
+                    is_local(&args[0], ptr, cx) => {
+                if let Some(Res::Local(id)) = cx.try_resolve_expr_hir(strip_casts(&args[1])) {
+                    indices.insert(id);
+                }
+            }
+            _ => {}
+        }
+    });
+
+    let mut bounded = false;
+    visit_nodes(block, |e: &Expr| {
+        match e.kind {
+            ExprKind::Binary(op, ref lhs, ref rhs) if [
+                BinOpKind::Lt, BinOpKind::Le, BinOpKind::Gt, BinOpKind::Ge,
+            ].contains(&op.node) => {
+                bounded |= indices.iter().any(|&i| {
+                    (is_local(lhs, i, cx) && is_local(rhs, len, cx)) ||
+                        (is_local(lhs, len, cx) && is_local(rhs, i, cx))
+                });
+            }
+            ExprKind::ForLoop(ref pat, ref iter, _, _) => {
+                if let ExprKind::Range(_, Some(ref end), _) = strip_parens(iter).kind {
+                    let i = cx.hir_map().node_to_hir_id(pat.id);
+                    bounded |= indices.contains(&i) && is_local(end, len, cx);
+                }
+            }
+            _ => {}
+        }
+    });
+    bounded
+}
+
+/// Whether the expression `e` calls `size_of` or `size_of_val`, e.g. to compute a number of bytes.
+fn uses_size_of(e: &Expr) -> bool {
+    let mut found = false;
+    visit_nodes(e, |x: &Expr| {
+        if let ExprKind::Path(_, ref path) = x.kind {
+            let name = path.segments.last().unwrap().ident.as_str();
+            found |= name == "size_of" || name == "size_of_val";
+        }
+    });
+    found
+}
+
+/// Whether `ty` is written as `usize`.
+fn is_usize_ty(ty: &Ty) -> bool {
+    match ty.kind {
+        syntax::ast::TyKind::Path(None, ref path) => {
+            path.segments.len() == 1 && path.segments[0].ident.name == sym::usize
+        }
+        _ => false,
+    }
+}
+
+/// Check that the only uses of the pointer parameter `ptr` in `block` access an element of it,
+/// and that the length parameter `len` is not assigned to.  Otherwise, return the span of a use
+/// that violates this and a description of it.
+fn check_slice_uses(
+    block: &Block,
+    ptr: hir::HirId,
+    len: hir::HirId,
+    cx: &RefactorCtxt,
+) -> Result<(), (Span, &'static str)> {
+    // Uses of values that access the elements they point to, and uses as places that can be
+    // assigned to.
+    let mut accesses = HashSet::new();
+    let mut places = HashSet::new();
+    visit_nodes(block, |e: &Expr| {
+        match e.kind {
+            ExprKind::Unary(UnOp::Deref, ref x) => {
+                let x = strip_parens(x);
+                match x.kind {
+                    ExprKind::MethodCall(ref seg, ref args)
+                        if seg.ident.as_str() == "offset" || seg.ident.as_str() == "add" => {
+                        accesses.insert(strip_parens(&args[0]).id);
+                    }
+                    _ => {
+                        accesses.insert(x.id);
+                    }
+                }
+            }
+            ExprKind::Assign(ref lhs, _) | ExprKind::AssignOp(_, ref lhs, _) => {
+                places.insert(strip_parens(lhs).id);
+            }
+            ExprKind::AddrOf(_, Mutability::Mutable, ref x) => {
+                places.insert(strip_parens(x).id);
+            }
+            _ => {}
+        }
+    });
+
+    let mut result = Ok(());
+    visit_nodes(block, |e: &Expr| {
+        if result.is_err() || !matches!([e.kind] ExprKind::Path(..)) {
+            return;
+        }
+        match cx.try_resolve_expr_hir(e) {
+            Some(Res::Local(id)) if id == ptr && !accesses.contains(&e.id) => {
+                result = Err((e.span, "the pointer is used other than to access its elements"));
+            }
+            Some(Res::Local(id)) if id == len && places.contains(&e.id) => {
+                result = Err((e.span, "the length is assigned to"));
+            }
+            _ => {}
+        }
+    });
+    result
+}
+
+/// If `e` accesses an element of one of the pointers in `ptrs`, return the same access through
+/// the slice that replaces it.
+fn slice_index(
+    e: &Expr,
+    ptrs: &HashMap<hir::HirId, Mutability>,
+    cx: &RefactorCtxt,
+) -> Option<P<Expr>> {
+    let x = match_or!([e.kind] ExprKind::Unary(UnOp::Deref, ref x) => strip_parens(x);
+                      return None);
+    let (ptr, index) = match x.kind {
+        ExprKind::MethodCall(ref seg, ref args)
+            if seg.ident.as_str() == "offset" || seg.ident.as_str() == "add" => {
+            (strip_parens(&args[0]), to_usize(args[1].clone(), cx))
+        }
+        _ => (x, mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed))),
+    };
+    match cx.try_resolve_expr_hir(ptr) {
+        Some(Res::Local(id)) if ptrs.contains_key(&id) => {
+            Some(mk().index_expr(ptr.clone(), index))
+        }
+        _ => None,
+    }
+}
+
+/// Convert the arguments `ptr` and `len` of a call to a slice of mutability `mutbl`.
+fn ptr_len_arg_to_slice(
+    ptr: P<Expr>,
+    len: P<Expr>,
+    mutbl: Mutability,
+    cx: &RefactorCtxt,
+) -> P<Expr> {
+    // `x.as_ptr(), x.len()` becomes `&x[..]`.
+    let as_ptr = match mutbl {
+        Mutability::Immutable => "as_ptr",
+        Mutability::Mutable => "as_mut_ptr",
+    };
+    if let (ExprKind::MethodCall(ref ptr_seg, ref ptr_args),
+            ExprKind::MethodCall(ref len_seg, ref len_args)) = (&ptr.kind, &len.kind) {
+        if ptr_seg.ident.as_str() == as_ptr && len_seg.ident.as_str() == "len" &&
+            ptr_args.len() == 1 && len_args.len() == 1 && ptr_args[0].ast_equiv(&len_args[0]) {
+            let full = mk().range_expr(None::<P<Expr>>, None, RangeLimits::HalfOpen);
+            return mk().set_mutbl(mutbl).addr_of_expr(mk().index_expr(ptr_args[0].clone(), full));
+        }
+    }
+
+    let from_raw_parts = match mutbl {
+        Mutability::Immutable => "from_raw_parts",
+        Mutability::Mutable => "from_raw_parts_mut",
+    };
+    let len = to_usize(len, cx);
+    mk().call_expr(mk().path_expr(vec!["", "std", "slice", from_raw_parts]), vec![ptr, len])
+}
+
+/// Cast the integer `e` to `usize`, unless it already is one.
+fn to_usize(e: P<Expr>, cx: &RefactorCtxt) -> P<Expr> {
+    match cx.opt_node_type(e.id).map(|ty| &ty.kind) {
+        Some(&TyKind::Uint(syntax::ast::UintTy::Usize)) => e,
+        _ => mk().cast_expr(e, mk().path_ty(vec!["usize"])),
+    }
+}
+
+
+/// # `native_int_types` Command
+///
+/// Usage: `native_int_types`
+///
+/// Marks: `target`
+///
+/// In items marked `target`, and the items inside them, replace the C integer
+/// type aliases of `libc` and `std::os::raw`, like `c_int` and `c_ulong`,
+/// with the Rust integer types they stand for on the current target, like
+/// `i32` and `u64`.
+///
+/// The signatures of foreign functions and statics, and of functions exported
+/// with `#[no_mangle]` or `#[export_name]`, keep their C types, since those
+/// must match C on every target.  Instead, calls to these functions from the
+/// marked items cast their arguments to the C types and their results to the
+/// native ones.  The casts change nothing on the current target, where the
+/// types are the same, but mark the places to review when porting to
+/// another.  A note summarizes how many casts were added at calls to each
+/// function.
+///
+/// Example:
+///
+/// ```ignore
+///     extern "C" {
+///         fn abs(x: libc::c_int) -> libc::c_int;
+///     }
+///
+///     unsafe fn f(x: libc::c_int) -> libc::c_uint {   // f: target
+///         abs(x) as libc::c_uint
+///     }
+/// ```
+///
+/// After running `native_int_types`:
+///
+/// ```ignore
+///     extern "C" {
+///         fn abs(x: libc::c_int) -> libc::c_int;
+///     }
+///
+///     unsafe fn f(x: i32) -> u32 {
+///         abs(x as libc::c_int) as i32 as u32
+///     }
+/// ```
+pub struct NativeIntTypes;
+
+/// The C integer type aliases `native_int_types` replaces.
+const C_INT_TYPES: &[&str] = &[
+    "c_char", "c_schar", "c_uchar", "c_short", "c_ushort", "c_int", "c_uint",
+    "c_long", "c_ulong", "c_longlong", "c_ulonglong",
+];
+
+impl Transform for NativeIntTypes {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the signatures on the FFI boundary, which keep their C types.  For each
+        // function, record the C types of its parameters and return type, to cast to and from.
+        let mut boundary_tys = HashSet::new();
+        visit_nodes(krate, |fi: &ForeignItem| {
+            visit_nodes(fi, |t: &Ty| {
+                boundary_tys.insert(t.id);
+            });
+        });
+
+        let mut boundary_fns = HashMap::new();
+        visit_fns(krate, |fl| {
+            if fl.kind != FnKind::Foreign &&
+                !attr::contains_name(&fl.attrs, sym::no_mangle) &&
+                !attr::contains_name(&fl.attrs, sym::export_name) {
+                return;
+            }
+            for arg in &fl.decl.inputs {
+                visit_nodes(&*arg.ty, |t: &Ty| {
+                    boundary_tys.insert(t.id);
+                });
+            }
+            let c_ty = |t: &P<Ty>| c_int_alias(t, cx).map(|_| t.clone());
+            let params = fl.decl.inputs.iter().map(|arg| c_ty(&arg.ty)).collect::<Vec<_>>();
+            let ret = match fl.decl.output {
+                FunctionRetTy::Ty(ref t) => {
+                    visit_nodes(&**t, |t: &Ty| {
+                        boundary_tys.insert(t.id);
+                    });
+                    c_ty(t).map(|c_ty| {
+                        let native_ty = native_int_ty(&c_ty, cx);
+                        (c_ty, native_ty)
+                    })
+                }
+                FunctionRetTy::Default(_) => None,
+            };
+            boundary_fns.insert(cx.node_def_id(fl.id), (params, ret));
+        });
+
+        // (2) In the marked items, replace the C types and cast at calls across the boundary.
+        let mut cast_counts: HashMap<DefId, usize> = HashMap::new();
+        FlatMapNodes::visit(krate, |mut i: P<Item>| {
+            if !st.marked(i.id, "target") {
+                return smallvec![i];
+            }
+
+            // Calls whose results are discarded don't need them cast.
+            let mut discarded = HashSet::new();
+            visit_nodes(&*i, |s: &Stmt| {
+                if let StmtKind::Semi(ref e) = s.kind {
+                    discarded.insert(e.id);
+                }
+            });
+
+            MutVisitNodes::visit(&mut i, |e: &mut P<Expr>| {
+                if !matches!([e.kind] ExprKind::Call(..)) {
+                    return;
+                }
+                let callee = match_or!([cx.opt_callee(&e)] Some(x) => x; return);
+                let (params, ret) = match_or!([boundary_fns.get(&callee)] Some(x) => x; return);
+                let count = cast_counts.entry(callee).or_insert(0);
+                let is_discarded = discarded.contains(&e.id);
+
+                let args = expect!([e.kind] ExprKind::Call(_, ref mut args) => args);
+                // Variadic arguments have no declared type to cast to.
+                for (arg, c_ty) in args.iter_mut().zip(params) {
+                    if let Some(c_ty) = c_ty {
+                        if !is_unsuffixed_int_lit(arg) {
+                            *arg = mk().cast_expr(arg.clone(), c_ty.clone());
+                            *count += 1;
+                        }
+                    }
+                }
+                if let Some((_, native_ty)) = ret {
+                    if !is_discarded {
+                        *e = mk().cast_expr(e.clone(), native_ty.clone());
+                        *count += 1;
+                    }
+                }
+            });
+
+            MutVisitNodes::visit(&mut i, |t: &mut P<Ty>| {
+                if boundary_tys.contains(&t.id) || c_int_alias(t, cx).is_none() {
+                    return;
+                }
+                *t = native_int_ty(t, cx);
+            });
+
+            smallvec![i]
+        });
+
+        // (3) Summarize the casts left at the boundary.
+        let mut counts = cast_counts.into_iter()
+            .filter(|&(_, count)| count > 0)
+            .map(|(id, count)| (cx.ty_ctxt().def_path_str(id), count))
+            .collect::<Vec<_>>();
+        counts.sort();
+        for (path, count) in counts {
+            cx.session().note_without_error(&format!(
+                "native_int_types: {} casts remain at calls to `{}`", count, path));
+        }
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// If `t` names one of the C integer type aliases, return the `DefId` of the alias.
+fn c_int_alias(t: &Ty, cx: &RefactorCtxt) -> Option<DefId> {
+    let def_id = cx.try_resolve_ty(t)?;
+    let tcx = cx.ty_ctxt();
+    let crate_name = tcx.crate_name(def_id.krate).as_str();
+    let name = tcx.item_name(def_id).as_str();
+    if ["libc", "std", "core"].contains(&&*crate_name) && C_INT_TYPES.contains(&&*name) {
+        Some(def_id)
+    } else {
+        None
+    }
+}
+
+/// The Rust integer type that `t`, one of the C integer type aliases, stands for.
+fn native_int_ty(t: &Ty, cx: &RefactorCtxt) -> P<Ty> {
+    let def_id = c_int_alias(t, cx).expect("not a C integer type");
+    mk().path_ty(vec![cx.ty_ctxt().type_of(def_id).to_string()])
+}
+
+fn is_unsuffixed_int_lit(e: &Expr) -> bool {
+    match e.kind {
+        ExprKind::Lit(ref lit) => matches!([lit.kind] LitKind::Int(_, LitIntType::Unsuffixed)),
+        ExprKind::Unary(UnOp::Neg, ref e) => is_unsuffixed_int_lit(e),
+        _ => false,
+    }
+}
+
+
+/// # `char_ptr_to_cstr` Command
+///
+/// Usage: `char_ptr_to_cstr`
+///
+/// Marks: `target`
+///
+/// In functions marked `target`, change parameters and `let` bindings of
+/// type `*const c_char`, from `libc` or `std::os::raw`, to `&CStr`.  Each
+/// use of a converted string must be an argument of a call.  The string
+/// functions `strlen(s)` and `strcmp(s, t)` become `s.to_bytes().len()` and
+/// `s.cmp(t)`, and other functions, like foreign ones, get `s.as_ptr()`.
+///
+/// Locals initialized with a translated string literal, like
+/// `b"hi\x00" as *const u8 as *const c_char`, get the literal through
+/// `CStr::from_bytes_with_nul`, and other locals through `CStr::from_ptr`.
+/// A literal with a NUL before its end, like `"a\0b"`, goes through
+/// `CStr::from_ptr` too, which ends the string at the first NUL as C does.
+/// Callers of a function with converted parameters pass strings that are
+/// already `&CStr` as they are, and convert other pointers the same way as
+/// initializers.  `CStr::from_ptr` is unsafe and requires a non-null pointer,
+/// so calls must be in unsafe code, as they are in translated code, and each
+/// pointer it converts gets a warning that it must not be null.
+///
+/// A string is left unchanged, with a warning, if it is used other than as
+/// an argument of a call, or is mutable.  Locals initialized with a null
+/// pointer, and parameters some caller passes one to, like `0 as *const
+/// c_char`, are left unchanged with a warning too.  Parameters of exported
+/// functions, and of functions used other than by calling them, are left
+/// unchanged with a warning as well, since their callers can't be updated.
+///
+/// Locals of type `*mut c_char` initialized with `strdup(s)` own their
+/// string, and become a `CString` initialized with `s.to_owned()` if their
+/// only uses are as `*const` arguments of calls and in a call of `free`,
+/// which becomes a `drop`.  An owned string that is never freed is left
+/// unchanged with a warning, since a callee may have taken ownership of it.
+///
+/// `strcpy(dst, s)` with a converted source copies `s.to_bytes_with_nul()`
+/// into the destination buffer with `copy_from_slice`, and still evaluates to
+/// `dst`.  The destination stays a pointer: its buffer belongs to the C code,
+/// which must make it large enough, as for `strcpy`.
+///
+/// Example:
+///
+/// ```ignore
+///     unsafe fn f(s: *const libc::c_char) -> libc::c_ulong {   // f: target
+///         let t: *const libc::c_char = b"hi\x00" as *const u8 as *const libc::c_char;
+///         puts(s);
+///         strlen(s) + strlen(t)
+///     }
+/// ```
+///
+/// After running `char_ptr_to_cstr`:
+///
+/// ```ignore
+///     unsafe fn f(s: &::std::ffi::CStr) -> libc::c_ulong {
+///         let t: &::std::ffi::CStr = ::std::ffi::CStr::from_bytes_with_nul(b"hi\x00").unwrap();
+///         puts(s.as_ptr());
+///         s.to_bytes().len() as u64 + t.to_bytes().len() as u64
+///     }
+/// ```
+pub struct CharPtrToCStr;
+
+impl Transform for CharPtrToCStr {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Find the functions that are used other than by calling them, whose callers we
+        // can't update.
+        let fn_values = fn_values(krate, cx);
+
+        // Also find the parameters some caller passes a null pointer to, which can't be a `CStr`.
+        let mut null_args: HashMap<(DefId, usize), Span> = HashMap::new();
+        visit_nodes(krate, |e: &Expr| {
+            let callee = match_or!([cx.opt_callee(e)] Some(x) => x; return);
+            let args = match_or!([e.kind] ExprKind::Call(_, ref args) => args; return);
+            for (i, arg) in args.iter().enumerate() {
+                if is_null_ptr(arg) {
+                    null_args.entry((callee, i)).or_insert(arg.span);
+                }
+            }
+        });
+
+        // (2) Change the types of the strings used only as call arguments, and the initializers
+        // of the converted locals.
+
+        // The converted parameters and locals, the locals converted to `CString`s, and the
+        // modified functions, by DefId, with the indices of their converted parameters.
+        let mut cstrs = HashSet::new();
+        let mut owned = HashSet::new();
+        let mut mod_fns: HashMap<DefId, HashSet<usize>> = HashMap::new();
+
+        mut_visit_fns(krate, |fl| {
+            if !st.marked(fl.id, "target") {
+                return;
+            }
+
+            let mut converted_params = HashSet::new();
+            let mut converted_locals = HashSet::new();
+            let mut owned_locals = HashSet::new();
+            {
+                let block = match_or!([fl.block] Some(ref b) => b; return);
+                let check_uses = |pat: &Pat, what: &str| {
+                    let ident = match_or!([pat.kind]
+                        PatKind::Ident(BindingMode::ByValue(Mutability::Immutable), ident, None) =>
+                            ident;
+                        return false);
+                    let var = cx.hir_map().node_to_hir_id(pat.id);
+                    if let Err(span) = check_call_args_only(block, var, cx) {
+                        cx.session().span_warn(span, &format!(
+                            "char_ptr_to_cstr: can't convert {} `{}` of `{}` to a `CStr`: it is \
+                             used other than as an argument of a call", what, ident, fl.ident));
+                        return false;
+                    }
+                    true
+                };
+                let check_owned = |pat: &Pat| {
+                    let ident = match_or!([pat.kind]
+                        PatKind::Ident(BindingMode::ByValue(Mutability::Immutable), ident, None) =>
+                            ident;
+                        return false);
+                    let var = cx.hir_map().node_to_hir_id(pat.id);
+                    if let Err((span, reason)) = check_owned_uses(block, var, cx) {
+                        cx.session().span_warn(span, &format!(
+                            "char_ptr_to_cstr: can't convert local `{}` of `{}` to a `CString`: \
+                             it {}", ident, fl.ident, reason));
+                        return false;
+                    }
+                    true
+                };
+
+                let has_params = fl.decl.inputs.iter().any(|arg| is_c_str_ptr(&arg.ty, cx));
+                if has_params && has_known_callers(fl, &fn_values, "char_ptr_to_cstr", cx) {
+                    let def_id = cx.node_def_id(fl.id);
+                    for (i, arg) in fl.decl.inputs.iter().enumerate() {
+                        if !is_c_str_ptr(&arg.ty, cx) {
+                            continue;
+                        }
+                        if let Some(&span) = null_args.get(&(def_id, i)) {
+                            cx.session().span_warn(span, &format!(
+                                "char_ptr_to_cstr: can't convert parameter {} of `{}` to a \
+                                 `CStr`: it is passed a null pointer", i, fl.ident));
+                            continue;
+                        }
+                        if check_uses(&arg.pat, "parameter") {
+                            converted_params.insert(i);
+                            cstrs.insert(cx.hir_map().node_to_hir_id(arg.pat.id));
+                        }
+                    }
+                }
+
+                visit_nodes(&**block, |l: &Local| {
+                    let ty = match_or!([l.ty] Some(ref ty) => ty; return);
+                    let init = match_or!([l.init] Some(ref e) => e; return);
+                    if is_c_char_ptr(ty, Mutability::Mutable, cx) {
+                        let callee = cx.opt_callee(init).and_then(|f| string_fn_name(f, cx));
+                        if callee == Some("strdup") && check_owned(&l.pat) {
+                            owned_locals.insert(l.pat.id);
+                            owned.insert(cx.hir_map().node_to_hir_id(l.pat.id));
+                        }
+                        return;
+                    }
+                    if !is_c_str_ptr(ty, cx) {
+                        return;
+                    }
+                    if is_null_ptr(init) {
+                        cx.session().span_warn(init.span, &format!(
+                            "char_ptr_to_cstr: can't convert local `{}` of `{}` to a `CStr`: \
+                             it is initialized with a null pointer",
+                            pprust::pat_to_string(&l.pat), fl.ident));
+                        return;
+                    }
+                    if check_uses(&l.pat, "local") {
+                        converted_locals.insert(l.pat.id);
+                        cstrs.insert(cx.hir_map().node_to_hir_id(l.pat.id));
+                    }
+                });
+            }
+
+            for &i in &converted_params {
+                fl.decl.inputs[i].ty = mk().ref_ty(cstr_ty());
+            }
+            fl.block.as_mut().map(|b| MutVisitNodes::visit(b, |l: &mut P<Local>| {
+                if converted_locals.contains(&l.pat.id) {
+                    l.ty = Some(mk().ref_ty(cstr_ty()));
+                    l.init = l.init.take().map(|e| ptr_to_cstr(e, cx));
+                } else if owned_locals.contains(&l.pat.id) {
+                    // `strdup(s)` becomes `s.to_owned()`, with `s` converted to a `&CStr`.
+                    let s = expect!([l.init.as_ref().unwrap().kind]
+                        ExprKind::Call(_, ref args) => args[0].clone());
+                    let s = match cx.try_resolve_expr_hir(strip_parens(&s)) {
+                        Some(Res::Local(id)) if cstrs.contains(&id) => s,
+                        _ => ptr_to_cstr(s, cx),
+                    };
+                    l.ty = Some(cstring_ty());
+                    l.init = Some(mk().method_call_expr(s, "to_owned", Vec::<P<Expr>>::new()));
+                }
+            }));
+
+            if !converted_params.is_empty() {
+                mod_fns.insert(cx.node_def_id(fl.id), converted_params);
+            }
+        });
+
+        // (3) Rewrite the calls taking converted strings, and the callsites of modified
+        // functions.
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            if !matches!([e.kind] ExprKind::Call(..)) {
+                return;
+            }
+            let callee = cx.opt_callee(&e);
+            let is_in = |x: &Expr, vars: &HashSet<hir::HirId>| {
+                match cx.try_resolve_expr_hir(x) {
+                    Some(Res::Local(id)) => vars.contains(&id),
+                    _ => false,
+                }
+            };
+            let (args_cstr, args_owned) = expect!([e.kind] ExprKind::Call(_, ref args) => (
+                args.iter().map(|x| is_in(strip_parens(x), &cstrs)).collect::<Vec<_>>(),
+                args.iter().map(|x| is_in(strip_parens(x), &owned)).collect::<Vec<_>>(),
+            ));
+
+            if let Some(name) = callee.and_then(|callee| string_fn_name(callee, cx)) {
+                let args = expect!([e.kind] ExprKind::Call(_, ref args) => args.clone());
+                let is_str = |i: usize| args_cstr[i] || args_owned[i];
+                // Borrow owned strings as `&CStr`s.
+                let as_cstr = |i: usize| if args_owned[i] {
+                    mk().method_call_expr(args[i].clone(), "as_c_str", Vec::<P<Expr>>::new())
+                } else {
+                    args[i].clone()
+                };
+
+                match (name, args.len()) {
+                    ("free", 1) if is_in(strip_casts(&args[0]), &owned) => {
+                        let s = P(strip_casts(&args[0]).clone());
+                        *e = mk().call_expr(mk().path_expr(vec!["", "std", "mem", "drop"]), vec![s]);
+                        return;
+                    }
+                    ("strcpy", 2) if is_str(1) => {
+                        *e = strcpy_expr(args[0].clone(), as_cstr(1));
+                        return;
+                    }
+                    _ => {}
+                }
+
+                let ret_ty = cx.opt_node_type(e.id);
+                let new_e = match (name, args.len()) {
+                    ("strlen", 1) if is_str(0) => {
+                        let bytes = mk().method_call_expr(
+                            args[0].clone(), "to_bytes", Vec::<P<Expr>>::new());
+                        Some(mk().method_call_expr(bytes, "len", Vec::<P<Expr>>::new()))
+                    }
+                    ("strcmp", 2) if is_str(0) && is_str(1) => {
+                        Some(mk().method_call_expr(as_cstr(0), "cmp", vec![as_cstr(1)]))
+                    }
+                    _ => None,
+                };
+                if let (Some(new_e), Some(ret_ty)) = (new_e, ret_ty) {
+                    *e = mk().cast_expr(new_e, reflect_tcx_ty(cx.ty_ctxt(), ret_ty));
+                    return;
+                }
+            }
+
+            let converted = callee.and_then(|callee| mod_fns.get(&callee));
+            let args = expect!([e.kind] ExprKind::Call(_, ref mut args) => args);
+            for (i, arg) in args.iter_mut().enumerate() {
+                let param_converted = converted.map_or(false, |c| c.contains(&i));
+                if args_owned[i] {
+                    let method = if param_converted { "as_c_str" } else { "as_ptr" };
+                    *arg = mk().method_call_expr(arg.clone(), method, Vec::<P<Expr>>::new());
+                } else if args_cstr[i] && !param_converted {
+                    *arg = mk().method_call_expr(arg.clone(), "as_ptr", Vec::<P<Expr>>::new());
+                } else if !args_cstr[i] && param_converted {
+                    *arg = ptr_to_cstr(arg.clone(), cx);
+                }
+            }
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// Whether `ty` is `*const c_char`.
+fn is_c_str_ptr(ty: &Ty, cx: &RefactorCtxt) -> bool {
+    is_c_char_ptr(ty, Mutability::Immutable, cx)
+}
+
+/// Whether `ty` is a pointer to `c_char` with mutability `mutbl`.
+fn is_c_char_ptr(ty: &Ty, mutbl: Mutability, cx: &RefactorCtxt) -> bool {
+    let mt = match_or!([ty.kind] syntax::ast::TyKind::Ptr(ref mt) => mt; return false);
+    mt.mutbl == mutbl &&
+        c_int_alias(&mt.ty, cx).map_or(false, |alias| {
+            cx.ty_ctxt().item_name(alias).as_str() == "c_char"
+        })
+}
+
+/// Check that the only uses of the variable `var` in `block` are as arguments of calls.
+/// Otherwise, return the span of a use that isn't.
+fn check_call_args_only(block: &Block, var: hir::HirId, cx: &RefactorCtxt) -> Result<(), Span> {
+    let mut args = HashSet::new();
+    visit_nodes(block, |e: &Expr| {
+        if let ExprKind::Call(_, ref xs) = e.kind {
+            args.extend(xs.iter().map(|x| strip_parens(x).id));
+        }
+    });
+
+    let mut result = Ok(());
+    visit_nodes(block, |e: &Expr| {
+        if result.is_err() || !matches!([e.kind] ExprKind::Path(..)) {
+            return;
+        }
+        if cx.try_resolve_expr_hir(e) == Some(Res::Local(var)) && !args.contains(&e.id) {
+            result = Err(e.span);
+        }
+    });
+    result
+}
+
+/// Check that the only uses of the owned string `var` in `block` are as arguments of calls whose
+/// parameters are `*const`, and in a call of `free`, which must appear.  Otherwise, return the
+/// span of a use that isn't, or of `block`, with the reason.
+fn check_owned_uses(block: &Block, var: hir::HirId, cx: &RefactorCtxt)
+                    -> Result<(), (Span, &'static str)> {
+    let mut args = HashSet::new();
+    let mut freed = false;
+    visit_nodes(block, |e: &Expr| {
+        let xs = match_or!([e.kind] ExprKind::Call(_, ref xs) => xs; return);
+        if cx.opt_callee(e).and_then(|f| string_fn_name(f, cx)) == Some("free") {
+            // `free` takes a `*mut c_void`, so its argument is cast.
+            let x = match_or!([xs.get(0)] Some(x) => strip_casts(x); return);
+            if cx.try_resolve_expr_hir(x) == Some(Res::Local(var)) {
+                args.insert(x.id);
+                freed = true;
+            }
+            return;
+        }
+        let sig = match_or!([cx.opt_callee_fn_sig(e)] Some(sig) => sig; return);
+        for (x, ty) in xs.iter().zip(sig.inputs()) {
+            if matches!([ty.kind] TyKind::RawPtr(ty::TypeAndMut {
+                mutbl: hir::Mutability::Immutable, ..
+            })) {
+                args.insert(strip_parens(x).id);
+            }
+        }
+    });
+
+    let mut result = Ok(());
+    visit_nodes(block, |e: &Expr| {
+        if result.is_err() || !matches!([e.kind] ExprKind::Path(..)) {
+            return;
+        }
+        if cx.try_resolve_expr_hir(e) == Some(Res::Local(var)) && !args.contains(&e.id) {
+            result = Err((e.span, "is used other than as a `*const` argument of a call"));
+        }
+    });
+    if result.is_ok() && !freed {
+        result = Err((block.span, "is never freed"));
+    }
+    result
+}
+
+/// If `callee` is one of the foreign string functions `char_ptr_to_cstr` rewrites, return its
+/// name.
+fn string_fn_name(callee: DefId, cx: &RefactorCtxt) -> Option<&'static str> {
+    let tcx = cx.ty_ctxt();
+    if !tcx.is_foreign_item(callee) {
+        return None;
+    }
+    match &*tcx.item_name(callee).as_str() {
+        "strlen" => Some("strlen"),
+        "strcmp" => Some("strcmp"),
+        "strcpy" => Some("strcpy"),
+        "strdup" => Some("strdup"),
+        "free" => Some("free"),
+        _ => None,
+    }
+}
+
+/// The path of `CStr`.
+fn cstr_ty() -> P<Ty> {
+    mk().path_ty(vec!["", "std", "ffi", "CStr"])
+}
+
+/// The path of `CString`.
+fn cstring_ty() -> P<Ty> {
+    mk().path_ty(vec!["", "std", "ffi", "CString"])
+}
+
+/// Build the replacement of `strcpy(dst, src)`, with `src` a `&CStr`, which copies the bytes of
+/// `src` and its NUL into the buffer at `dst`, and evaluates to `dst`:
+///
+/// ```ignore
+///     {
+///         let (dst, src) = (dst, src);
+///         ::std::slice::from_raw_parts_mut(dst as *mut u8, src.to_bytes_with_nul().len())
+///             .copy_from_slice(src.to_bytes_with_nul());
+///         dst
+///     }
+/// ```
+///
+/// Both arguments are bound at once, so neither can shadow a variable the other uses.
+fn strcpy_expr(dst: P<Expr>, src: P<Expr>) -> P<Expr> {
+    let bytes = || mk().method_call_expr(
+        mk().ident_expr("src"), "to_bytes_with_nul", Vec::<P<Expr>>::new());
+    let buf = mk().call_expr(
+        mk().path_expr(vec!["", "std", "slice", "from_raw_parts_mut"]),
+        vec![
+            mk().cast_expr(mk().ident_expr("dst"), mk().mutbl().ptr_ty(mk().ident_ty("u8"))),
+            mk().method_call_expr(bytes(), "len", Vec::<P<Expr>>::new()),
+        ]);
+    let pat = mk().tuple_pat(vec![mk().ident_pat("dst"), mk().ident_pat("src")]);
+    let bind = mk().local(pat, None as Option<P<Ty>>, Some(mk().tuple_expr(vec![dst, src])));
+    mk().block_expr(mk().block(vec![
+        mk().local_stmt(P(bind)),
+        mk().semi_stmt(mk().method_call_expr(buf, "copy_from_slice", vec![bytes()])),
+        mk().expr_stmt(mk().ident_expr("dst")),
+    ]))
+}
+
+/// Convert the `*const c_char` expression `e` to a `&CStr`.  A translated string literal,
+/// `b"...\x00" as *const u8 as *const c_char`, whose only NUL is the last byte is converted
+/// safely.  Other pointers go through `CStr::from_ptr`, with a warning that they must not be
+/// null.
+fn ptr_to_cstr(e: P<Expr>, cx: &RefactorCtxt) -> P<Expr> {
+    let mut is_lit = false;
+    if let ExprKind::Cast(ref inner, _) = e.kind {
+        if let ExprKind::Cast(ref lit_e, _) = inner.kind {
+            if let ExprKind::Lit(ref lit) = lit_e.kind {
+                if let LitKind::ByteStr(ref bytes) = lit.kind {
+                    // `from_bytes_with_nul` fails unless the only NUL ends the string.
+                    if bytes.iter().position(|&b| b == 0).map_or(false, |i| i == bytes.len() - 1) {
+                        let from_bytes = mk().path_expr(
+                            vec!["", "std", "ffi", "CStr", "from_bytes_with_nul"]);
+                        let cstr = mk().call_expr(from_bytes, vec![lit_e.clone()]);
+                        return mk().method_call_expr(cstr, "unwrap", Vec::<P<Expr>>::new());
+                    }
+                    is_lit = bytes.contains(&0);
+                }
+            }
+        }
+    }
+    if !is_lit {
+        cx.session().span_warn(e.span, &format!(
+            "char_ptr_to_cstr: converting `{}` with `CStr::from_ptr`, which requires it to be \
+             non-null", pprust::expr_to_string(&e)));
+    }
+    mk().call_expr(mk().path_expr(vec!["", "std", "ffi", "CStr", "from_ptr"]), vec![e])
+}
+
+/// Whether `e` is a null pointer, like `0 as *const c_char` or `ptr::null()`.
+fn is_null_ptr(e: &Expr) -> bool {
+    match strip_parens(e).kind {
+        ExprKind::Cast(ref e, _) => is_null_ptr(e),
+        ExprKind::Lit(ref lit) => matches!([lit.kind] LitKind::Int(0, _)),
+        ExprKind::Call(ref f, ref args) if args.is_empty() => match f.kind {
+            ExprKind::Path(_, ref path) => path.segments.last().map_or(false, |seg| {
+                seg.ident.name.as_str() == "null" || seg.ident.name.as_str() == "null_mut"
+            }),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+
 /// # `retype_static` Command
 ///
 /// Usage: `retype_static NEW_TY REV_CONV_ASSIGN CONV_RVAL CONV_LVAL [CONV_LVAL_MUT]`
@@ -1414,6 +2794,11 @@ pub fn register_commands(reg: &mut Registry) {
         unwrap: args[2].clone(),
     }));
 
+    reg.register("ptr_to_ref", |_args| mk(PtrToRef));
+    reg.register("ptr_len_to_slice", |_args| mk(PtrLenToSlice));
+    reg.register("native_int_types", |_args| mk(NativeIntTypes));
+    reg.register("char_ptr_to_cstr", |_args| mk(CharPtrToCStr));
+
     reg.register("retype_static", |args| mk(RetypeStatic {
         new_ty: args[0].clone(),
         rev_conv_assign: args[1].clone(),
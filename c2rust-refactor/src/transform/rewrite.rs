@@ -1,12 +1,21 @@
-use syntax::ast::Crate;
+use syntax::ast::{Crate, Expr, ExprKind, Stmt, StmtKind};
+use syntax::mut_visit::{self, MutVisitor};
+use syntax::ptr::P;
+use syntax::source_map::{FileName, SourceMap, Span};
 use syntax::symbol::Symbol;
+use syntax::util::classify;
+use syntax::util::parser::{self, AssocOp};
 
+use c2rust_ast_printer::pprust;
+use smallvec::SmallVec;
+use crate::ast_manip::{remove_paren, AstEquiv, MutVisit};
 use crate::command::{CommandState, Registry};
 use crate::contains_mark::contains_mark;
-use crate::driver::Phase;
+use crate::driver::{self, Phase};
 use crate::matcher::{MatchCtxt, Subst, mut_visit_match_with};
+use crate::rewrite::{binop_left_prec, binop_right_prec, needs_parens, ExprPrec};
 use crate::transform::Transform;
-use c2rust_ast_builder::IntoSymbol;
+use c2rust_ast_builder::{mk, IntoSymbol};
 use crate::RefactorCtxt;
 
 
@@ -172,6 +181,191 @@ impl Transform for DebugMatchExpr {
 }
 
 
+/// # `fix_parens` Command
+///
+/// Usage: `fix_parens`
+///
+/// Audit the expressions built or modified by earlier commands in the current batch, i.e.
+/// those whose text will be produced by the rewriter rather than copied from the original
+/// source.  Wherever such an expression has an operand whose precedence is too low for its
+/// position, as with `a * b` having been spliced in as the operand `b` of `a + b` in reverse,
+/// the operand is wrapped in explicit parentheses.  The same goes for a block-like operand,
+/// such as a `match`, that starts an expression statement, as in `match x { .. } + 1;`, where
+/// it would end the statement early.  Then each expression is pretty-printed and re-parsed, and
+/// a warning is logged for every one whose re-parsed AST still differs.
+///
+/// This is a safety net for the rewriter's own parenthesization of spliced-in text: run it
+/// before `commit` or `write` after a complex series of transforms.
+pub struct FixParens;
+
+impl Transform for FixParens {
+    fn transform(&self, krate: &mut Crate, _st: &CommandState, cx: &RefactorCtxt) {
+        let mut folder = FixParensFolder {
+            cx,
+            source_map: cx.session().source_map(),
+            in_fresh: false,
+            fixed: 0,
+            audited: 0,
+        };
+        krate.visit(&mut folder);
+        info!(
+            "fix_parens: parenthesized {} operands in {} rewritten expressions",
+            folder.fixed, folder.audited,
+        );
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase1
+    }
+}
+
+struct FixParensFolder<'a, 'tcx: 'a> {
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+    source_map: &'a SourceMap,
+    /// Whether we are inside an expression that has already been audited.
+    in_fresh: bool,
+    fixed: usize,
+    audited: usize,
+}
+
+impl<'a, 'tcx> FixParensFolder<'a, 'tcx> {
+    /// Check whether the text for `sp` will come from the rewriter rather than from the
+    /// original source: the span is either missing or points into a snippet parsed by an
+    /// earlier command.
+    fn is_fresh(&self, sp: Span) -> bool {
+        sp.is_dummy() || match self.source_map.span_to_filename(sp) {
+            FileName::Anon(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Pretty-print and re-parse `e`, and check that this round-trips.
+    fn audit(&mut self, e: &Expr) {
+        self.audited += 1;
+
+        let src = pprust::expr_to_string(e);
+        let reparsed = driver::parse_expr(self.cx.session(), &src);
+        let mut expected = P(e.clone());
+        remove_paren(&mut expected);
+        if !expected.ast_equiv(&reparsed) {
+            warn!(
+                "fix_parens: {} does not re-parse to the rewritten expression at {}",
+                src,
+                self.source_map.span_to_string(e.span),
+            );
+        }
+    }
+}
+
+impl<'a, 'tcx> MutVisitor for FixParensFolder<'a, 'tcx> {
+    fn visit_expr(&mut self, e: &mut P<Expr>) {
+        let fresh = self.is_fresh(e.span);
+        for (operand, prec) in operands_mut(e) {
+            // Only operands spliced in by the rewriter can have lost their precedence
+            if (fresh || self.is_fresh(operand.span)) && needs_parens(operand, prec) {
+                let inner = operand.clone();
+                *operand = mk().paren_expr(inner);
+                self.fixed += 1;
+            }
+        }
+
+        if fresh && !self.in_fresh {
+            self.in_fresh = true;
+            mut_visit::noop_visit_expr(e, self);
+            self.in_fresh = false;
+            self.audit(e);
+        } else {
+            mut_visit::noop_visit_expr(e, self);
+        }
+    }
+
+    fn flat_map_stmt(&mut self, mut s: Stmt) -> SmallVec<[Stmt; 1]> {
+        if let StmtKind::Expr(ref mut e) | StmtKind::Semi(ref mut e) = s.kind {
+            let fresh = self.is_fresh(e.span);
+            if let Some(operand) = leading_block_like(e) {
+                if fresh || self.is_fresh(operand.span) {
+                    let inner = operand.clone();
+                    *operand = mk().paren_expr(inner);
+                    self.fixed += 1;
+                }
+            }
+        }
+        mut_visit::noop_flat_map_stmt(s, self)
+    }
+}
+
+/// Find the operand that starts the expression statement `e`, if it is a block-like expression
+/// like `match`, which the parser would take as a statement of its own.
+fn leading_block_like(e: &mut P<Expr>) -> Option<&mut P<Expr>> {
+    let lhs = match e.kind {
+        ExprKind::Binary(_, ref mut lhs, _)
+        | ExprKind::Cast(ref mut lhs, _)
+        | ExprKind::Type(ref mut lhs, _)
+        | ExprKind::Assign(ref mut lhs, _)
+        | ExprKind::AssignOp(_, ref mut lhs, _)
+        | ExprKind::Range(Some(ref mut lhs), _, _) => lhs,
+        _ => return None,
+    };
+    if classify::expr_requires_semi_to_be_stmt(lhs) {
+        leading_block_like(lhs)
+    } else {
+        Some(lhs)
+    }
+}
+
+/// List the operands of `e` that are subject to precedence, along with the precedence their
+/// position requires.  This mirrors the `prec` annotations in `gen/ast.txt` that drive the
+/// rewriter.
+fn operands_mut(e: &mut Expr) -> Vec<(&mut P<Expr>, ExprPrec)> {
+    let prefix = ExprPrec::Normal(parser::PREC_PREFIX);
+    let postfix = ExprPrec::Normal(parser::PREC_POSTFIX);
+    let jump = ExprPrec::Normal(parser::PREC_JUMP);
+    let cond = ExprPrec::Cond(parser::PREC_RESET);
+    let assign = AssocOp::Assign.precedence() as i8;
+    let range = ExprPrec::Normal(AssocOp::LOr.precedence() as i8);
+
+    match e.kind {
+        ExprKind::Box(ref mut x)
+        | ExprKind::Unary(_, ref mut x)
+        | ExprKind::AddrOf(_, _, ref mut x) => vec![(x, prefix)],
+        ExprKind::Call(ref mut f, _) => vec![(f, ExprPrec::Callee(parser::PREC_POSTFIX))],
+        ExprKind::MethodCall(_, ref mut args) => {
+            args.first_mut().into_iter().map(|x| (x, postfix)).collect()
+        }
+        ExprKind::Binary(op, ref mut a, ref mut b) => {
+            vec![(a, binop_left_prec(&op)), (b, binop_right_prec(&op))]
+        }
+        ExprKind::Cast(ref mut x, _) => {
+            vec![(x, ExprPrec::Normal(AssocOp::As.precedence() as i8))]
+        }
+        ExprKind::Type(ref mut x, _) => {
+            vec![(x, ExprPrec::Normal(AssocOp::Colon.precedence() as i8))]
+        }
+        ExprKind::Let(_, ref mut x)
+        | ExprKind::If(ref mut x, ..)
+        | ExprKind::While(ref mut x, ..)
+        | ExprKind::ForLoop(_, ref mut x, ..)
+        | ExprKind::Match(ref mut x, _) => vec![(x, cond)],
+        ExprKind::Assign(ref mut lhs, ref mut rhs)
+        | ExprKind::AssignOp(_, ref mut lhs, ref mut rhs) => vec![
+            (lhs, ExprPrec::Normal(assign + 1)),
+            (rhs, ExprPrec::Normal(assign)),
+        ],
+        ExprKind::Field(ref mut x, _)
+        | ExprKind::Index(ref mut x, _)
+        | ExprKind::Try(ref mut x) => vec![(x, postfix)],
+        ExprKind::Range(ref mut lo, ref mut hi, _) => lo
+            .iter_mut()
+            .chain(hi.iter_mut())
+            .map(|x| (x, range))
+            .collect(),
+        ExprKind::Break(_, Some(ref mut x))
+        | ExprKind::Ret(Some(ref mut x))
+        | ExprKind::Yield(Some(ref mut x)) => vec![(x, jump)],
+        _ => vec![],
+    }
+}
+
 
 pub fn register_commands(reg: &mut Registry) {
     use super::mk;
@@ -194,4 +388,6 @@ pub fn register_commands(reg: &mut Registry) {
     reg.register("debug_match_expr", |args| mk(DebugMatchExpr {
         pat: args[0].clone(),
     }));
+
+    reg.register("fix_parens", |_args| mk(FixParens));
 }
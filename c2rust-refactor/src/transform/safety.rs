@@ -0,0 +1,407 @@
+//! Transformations that document the remaining `unsafe` code in a crate.
+use std::collections::HashMap;
+
+use rustc::hir;
+use rustc::ty::TyKind;
+use syntax::ast::*;
+use syntax::source_map::SourceMap;
+use syntax::util::comments::{Comment, CommentStyle};
+use syntax::visit::{self, Visitor};
+use syntax_pos::{BytePos, Span};
+use smallvec::smallvec;
+
+use crate::ast_manip::{FlatMapNodes, Visit};
+use crate::command::{CommandState, Registry};
+use crate::driver::Phase;
+use crate::transform::Transform;
+use crate::util::Lone;
+use crate::RefactorCtxt;
+use c2rust_ast_printer::pprust;
+
+/// Marker that starts every generated comment, so remaining audit gaps can be found with `grep`.
+const SAFETY_MARKER: &str = "// SAFETY: unverified";
+
+/// # `annotate_unsafe_blocks` Command
+///
+/// Usage: `annotate_unsafe_blocks`
+///
+/// Adds a `// SAFETY:` comment template in front of every statement containing an `unsafe`
+/// block, listing the obligations the refactoring analyses could not discharge:
+///
+/// ```ignore
+///     // SAFETY: unverified
+///     // - ffi: calls foreign function `memcpy`
+///     // - aliasing: raw pointers `dst` and `src` are not known to be disjoint
+///     // - validity: raw pointer `dst` is not known to be non-null, aligned and live
+///     // - length: `src.offset(n as isize)` is not bounds-checked
+///     unsafe { ... }
+/// ```
+///
+/// Each line names one category (`ffi`, `unsafe-fn`, `aliasing`, `validity`, `length`, or
+/// `static-mut`), so the outstanding gaps of a hardening campaign can be queried by grepping for
+/// `SAFETY: unverified` and the category.  Statements already preceded by a `// SAFETY:` comment
+/// are left alone, which makes the command safe to re-run; nested `unsafe` blocks are covered by
+/// the comment on their outermost enclosing block.
+pub struct AnnotateUnsafeBlocks;
+
+impl Transform for AnnotateUnsafeBlocks {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        let source_map = cx.session().source_map();
+
+        let mut collector = UnsafeStmtCollector {
+            cx,
+            unsafe_depth: 0,
+            stmts: HashMap::new(),
+        };
+        krate.visit(&mut collector);
+        let mut stmts = collector.stmts;
+
+        FlatMapNodes::visit(krate, |s: Stmt| {
+            let facts = match stmts.remove(&s.id) {
+                Some(facts) => facts,
+                None => return smallvec![s],
+            };
+            if s.span.from_expansion() || has_safety_comment(source_map, s.span) {
+                return smallvec![s];
+            }
+            let src = match source_map.span_to_snippet(s.span) {
+                Ok(src) => src,
+                Err(_) => return smallvec![s],
+            };
+
+            // Reparse the statement from its own source text so it is treated as a new node and
+            // printed along with its comments.  This drops any comments attached to the old
+            // statement, so carry those over first.
+            let new_stmt = st.parse_stmts(cx, &src).lone();
+            let mut pos = 0;
+            if let Some(lines) = leading_comment(source_map, s.span) {
+                st.add_comment(new_stmt.id, Comment {
+                    style: CommentStyle::Isolated,
+                    lines,
+                    pos: BytePos(pos),
+                });
+                pos += 1;
+            }
+            st.add_comment(new_stmt.id, Comment {
+                style: CommentStyle::Isolated,
+                lines: safety_comment(&facts),
+                pos: BytePos(pos),
+            });
+            if let Some(line) = trailing_comment(source_map, s.span) {
+                st.add_comment(new_stmt.id, Comment {
+                    style: CommentStyle::Trailing,
+                    lines: vec![line],
+                    pos: BytePos(pos + 1),
+                });
+            }
+            smallvec![new_stmt]
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// Build the lines of the comment template for a block with the given unproven `facts`.
+fn safety_comment(facts: &[String]) -> Vec<String> {
+    let mut lines = vec![SAFETY_MARKER.to_owned()];
+    if facts.is_empty() {
+        lines.push("// - no specific obligation was identified".to_owned());
+    }
+    lines.extend(facts.iter().map(|f| format!("// - {}", f)));
+    lines
+}
+
+/// The run of line comments directly preceding `span`, if any.
+fn leading_comment(source_map: &SourceMap, span: Span) -> Option<Vec<String>> {
+    let prev = source_map.span_to_prev_source(span).ok()?;
+    let mut lines = prev
+        .trim_end()
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|l| l.starts_with("//"))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(lines)
+}
+
+/// The line comment following `span` on the same line, if any.
+fn trailing_comment(source_map: &SourceMap, span: Span) -> Option<String> {
+    let next = source_map.span_to_next_source(span).ok()?;
+    let line = next.lines().next()?.trim();
+    if line.starts_with("//") {
+        Some(format!(" {}", line))
+    } else {
+        None
+    }
+}
+
+fn has_safety_comment(source_map: &SourceMap, span: Span) -> bool {
+    leading_comment(source_map, span)
+        .map_or(false, |lines| lines.iter().any(|l| l.starts_with("// SAFETY:")))
+}
+
+fn is_unsafe_block(b: &Block) -> bool {
+    match b.rules {
+        BlockCheckMode::Unsafe(UnsafeSource::UserProvided) => true,
+        _ => false,
+    }
+}
+
+/// Finds the statements that own an `unsafe` block, that is, statements containing an `unsafe`
+/// block through nested expressions but not through a nested statement.  Such a statement is
+/// annotated with the facts of every `unsafe` block inside it, and the statements nested inside
+/// it are not annotated separately.
+struct UnsafeStmtCollector<'a, 'tcx: 'a> {
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+    /// Number of enclosing `unsafe` blocks or annotated statements.
+    unsafe_depth: usize,
+    stmts: HashMap<NodeId, Vec<String>>,
+}
+
+impl<'a, 'tcx> UnsafeStmtCollector<'a, 'tcx> {
+    fn finder(&self, nested: bool) -> UnsafeBlockFinder<'a, 'tcx> {
+        UnsafeBlockFinder {
+            facts: FactCollector {
+                cx: self.cx,
+                facts: Vec::new(),
+                pointers: Vec::new(),
+            },
+            found: false,
+            nested,
+        }
+    }
+}
+
+impl<'a, 'tcx, 'ast> Visitor<'ast> for UnsafeStmtCollector<'a, 'tcx> {
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        if self.unsafe_depth > 0 {
+            visit::walk_stmt(self, s);
+            return;
+        }
+
+        let mut direct = self.finder(false);
+        visit::walk_stmt(&mut direct, s);
+        if !direct.found {
+            visit::walk_stmt(self, s);
+            return;
+        }
+
+        let mut all = self.finder(true);
+        visit::walk_stmt(&mut all, s);
+        self.stmts.insert(s.id, all.facts.finish());
+
+        self.unsafe_depth += 1;
+        visit::walk_stmt(self, s);
+        self.unsafe_depth -= 1;
+    }
+
+    fn visit_block(&mut self, b: &'ast Block) {
+        let is_unsafe = is_unsafe_block(b);
+        if is_unsafe {
+            self.unsafe_depth += 1;
+        }
+        visit::walk_block(self, b);
+        if is_unsafe {
+            self.unsafe_depth -= 1;
+        }
+    }
+
+    fn visit_item(&mut self, i: &'ast Item) {
+        // Items nested inside an `unsafe` block are not covered by its comment.
+        let depth = self.unsafe_depth;
+        self.unsafe_depth = 0;
+        visit::walk_item(self, i);
+        self.unsafe_depth = depth;
+    }
+
+    fn visit_mac(&mut self, mac: &'ast Mac) {
+        visit::walk_mac(self, mac);
+    }
+}
+
+struct UnsafeBlockFinder<'a, 'tcx: 'a> {
+    facts: FactCollector<'a, 'tcx>,
+    found: bool,
+    /// Whether to look for `unsafe` blocks inside nested statements too.
+    nested: bool,
+}
+
+impl<'a, 'tcx, 'ast> Visitor<'ast> for UnsafeBlockFinder<'a, 'tcx> {
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        if self.nested {
+            visit::walk_stmt(self, s);
+        }
+    }
+
+    fn visit_item(&mut self, _i: &'ast Item) {}
+
+    fn visit_block(&mut self, b: &'ast Block) {
+        if is_unsafe_block(b) {
+            self.found = true;
+            self.facts.visit_block(b);
+        } else {
+            visit::walk_block(self, b);
+        }
+    }
+
+    fn visit_mac(&mut self, mac: &'ast Mac) {
+        visit::walk_mac(self, mac);
+    }
+}
+
+/// Collects the safety obligations of the code inside an `unsafe` block.
+struct FactCollector<'a, 'tcx: 'a> {
+    cx: &'a RefactorCtxt<'a, 'tcx>,
+    facts: Vec<String>,
+    /// Dereferenced raw pointers, and whether each was used mutably.
+    pointers: Vec<(String, bool)>,
+}
+
+impl<'a, 'tcx> FactCollector<'a, 'tcx> {
+    fn add(&mut self, fact: String) {
+        if !self.facts.contains(&fact) {
+            self.facts.push(fact);
+        }
+    }
+
+    fn is_raw_ptr(&self, e: &Expr) -> Option<bool> {
+        match self.cx.opt_node_type(e.id)?.kind {
+            TyKind::RawPtr(mt) => Some(mt.mutbl == hir::Mutability::Mutable),
+            _ => None,
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        if self.pointers.len() > 1 && self.pointers.iter().any(|&(_, mutbl)| mutbl) {
+            let names = self
+                .pointers
+                .iter()
+                .map(|(name, _)| format!("`{}`", name))
+                .collect::<Vec<_>>();
+            let (last, rest) = names.split_last().unwrap();
+            self.facts.push(format!(
+                "aliasing: raw pointers {} and {} are not known to be disjoint",
+                rest.join(", "),
+                last,
+            ));
+        }
+        for (name, _) in &self.pointers {
+            self.facts.push(format!(
+                "validity: raw pointer `{}` is not known to be non-null, aligned and live",
+                name,
+            ));
+        }
+        self.facts
+    }
+}
+
+/// Strip casts and parentheses to find the pointer an expression is derived from.
+fn pointer_base(mut e: &Expr) -> &Expr {
+    loop {
+        match e.kind {
+            ExprKind::Cast(ref inner, _) | ExprKind::Paren(ref inner) => e = inner,
+            _ => return e,
+        }
+    }
+}
+
+/// Methods and functions that read or write a caller-supplied range of memory without checking
+/// it.
+const UNCHECKED_METHODS: &[&str] = &["offset", "add", "sub", "get_unchecked", "get_unchecked_mut"];
+const UNCHECKED_FNS: &[&str] = &[
+    "from_raw_parts",
+    "from_raw_parts_mut",
+    "copy",
+    "copy_nonoverlapping",
+    "write_bytes",
+];
+
+impl<'a, 'tcx, 'ast> Visitor<'ast> for FactCollector<'a, 'tcx> {
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        match e.kind {
+            ExprKind::Unary(UnOp::Deref, ref ptr) => {
+                if let Some(mutbl) = self.is_raw_ptr(ptr) {
+                    let name = pprust::expr_to_string(pointer_base(ptr));
+                    match self.pointers.iter_mut().find(|(n, _)| *n == name) {
+                        Some(p) => p.1 |= mutbl,
+                        None => self.pointers.push((name, mutbl)),
+                    }
+                }
+            }
+
+            ExprKind::MethodCall(ref seg, ref args) => {
+                let name = seg.ident.as_str();
+                if UNCHECKED_METHODS.contains(&&*name) && self.is_raw_ptr(&args[0]).is_some() {
+                    self.add(format!(
+                        "length: `{}` is not bounds-checked",
+                        pprust::expr_to_string(e),
+                    ));
+                }
+                self.visit_callee(e);
+            }
+
+            ExprKind::Call(ref func, _) => {
+                if let ExprKind::Path(_, ref path) = func.kind {
+                    let last = path.segments.last().map(|s| s.ident.as_str());
+                    if last.map_or(false, |name| UNCHECKED_FNS.contains(&&*name)) {
+                        self.add(format!(
+                            "length: `{}` is given an unchecked length",
+                            pprust::path_to_string(path),
+                        ));
+                    }
+                }
+                self.visit_callee(e);
+            }
+
+            ExprKind::Path(..) => {
+                if let Some(def_id) = self.cx.try_resolve_expr(e) {
+                    if self.cx.ty_ctxt().is_mutable_static(def_id) {
+                        self.add(format!(
+                            "static-mut: accesses `{}` without synchronization",
+                            pprust::expr_to_string(e),
+                        ));
+                    }
+                }
+            }
+
+            _ => {}
+        }
+        visit::walk_expr(self, e);
+    }
+
+    fn visit_item(&mut self, _i: &'ast Item) {}
+
+    fn visit_mac(&mut self, mac: &'ast Mac) {
+        visit::walk_mac(self, mac);
+    }
+}
+
+impl<'a, 'tcx> FactCollector<'a, 'tcx> {
+    /// Record the obligations of calling an `unsafe` or foreign function.
+    fn visit_callee(&mut self, call: &Expr) {
+        let def_id = match self.cx.opt_callee(call) {
+            Some(def_id) => def_id,
+            None => return,
+        };
+        let tcx = self.cx.ty_ctxt();
+        let name = tcx.def_path_str(def_id);
+        if tcx.is_foreign_item(def_id) {
+            self.add(format!("ffi: calls foreign function `{}`", name));
+        } else if tcx.fn_sig(def_id).unsafety() == hir::Unsafety::Unsafe {
+            self.add(format!("unsafe-fn: preconditions of `{}` are not checked", name));
+        }
+    }
+}
+
+pub fn register_commands(reg: &mut Registry) {
+    use super::mk;
+
+    reg.register("annotate_unsafe_blocks", |_args| mk(AnnotateUnsafeBlocks));
+}
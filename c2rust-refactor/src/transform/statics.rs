@@ -1,18 +1,21 @@
 use std::collections::{HashMap, HashSet};
 use std::mem;
 use rustc::hir::def_id::DefId;
+use rustc::ty::{self, ParamEnv, TyKind};
 use syntax::ast::*;
+use syntax::attr;
 use syntax::ptr::P;
-use syntax::symbol::Symbol;
+use syntax::symbol::{sym, Symbol};
 use smallvec::smallvec;
 
-use crate::ast_manip::{FlatMapNodes, MutVisitNodes, fold_modules};
+use crate::ast_manip::{FlatMapNodes, MutVisitNodes, fold_modules, visit_nodes};
 use crate::ast_manip::fn_edit::mut_visit_fns;
 use crate::command::{CommandState, Registry};
-use crate::driver::{parse_expr};
+use crate::driver::{Phase, parse_expr};
 use crate::matcher::{Bindings, BindingType, MatchCtxt, Subst, mut_visit_match_with};
 use crate::path_edit::fold_resolved_paths;
 use crate::transform::Transform;
+use crate::transform::retype::strip_parens;
 use c2rust_ast_builder::{mk, IntoSymbol};
 use crate::util::dataflow;
 use crate::RefactorCtxt;
@@ -496,6 +499,246 @@ impl Transform for StaticToLocal {
 }
 
 
+/// # `static_to_safe` Command
+///
+/// Usage: `static_to_safe`
+///
+/// Marks: `target`
+///
+/// Replace each `static mut` marked `target` with an immutable `static` of a
+/// type that can be used without `unsafe`, and rewrite its uses to match:
+///
+///  * A static of an integer type or `bool` becomes the corresponding atomic
+///    type, like `AtomicI32`.  Reads become `X.load(SeqCst)` and assignments
+///    `X.store(e, SeqCst)`.  Compound assignments like `X += e` become a load
+///    and a store, so like the original code they aren't atomic as a whole.
+///  * A thread-local static (with `#[thread_local]`) of a `Copy` type becomes
+///    a `Cell`, read with `X.get()` and assigned with `X.set(e)`.
+///
+/// Other statics are left unchanged with a warning: `Mutex::new` can't
+/// initialize a `static`, and a `Cell` can't be shared between threads.  A
+/// static is also left unchanged if it is used in a way that can't be
+/// converted, like borrowing it or accessing one of its fields or elements,
+/// with a warning at each such use.
+///
+/// Example:
+///
+/// ```ignore
+///     static mut COUNT: i32 = 0;  // COUNT: target
+///
+///     unsafe fn bump() -> i32 {
+///         COUNT += 1;
+///         COUNT
+///     }
+/// ```
+///
+/// After running `static_to_safe`:
+///
+/// ```ignore
+///     static COUNT: ::std::sync::atomic::AtomicI32 = ::std::sync::atomic::AtomicI32::new(0);
+///
+///     unsafe fn bump() -> i32 {
+///         COUNT.store(COUNT.load(::std::sync::atomic::Ordering::SeqCst) + 1,
+///                     ::std::sync::atomic::Ordering::SeqCst);
+///         COUNT.load(::std::sync::atomic::Ordering::SeqCst)
+///     }
+/// ```
+struct StaticToSafe;
+
+/// The type a `static mut` is wrapped in by `static_to_safe`.
+#[derive(Clone, Copy)]
+enum SafeWrapper {
+    /// An atomic type, like `AtomicI32`
+    Atomic(&'static str),
+    Cell,
+}
+
+impl Transform for StaticToSafe {
+    fn transform(&self, krate: &mut Crate, st: &CommandState, cx: &RefactorCtxt) {
+        // (1) Choose the wrapper of each marked static.
+        let mut statics = HashMap::new();
+
+        visit_nodes(krate, |i: &Item| {
+            if !st.marked(i.id, "target") ||
+                !matches!([i.kind] ItemKind::Static(_, Mutability::Mutable, _)) {
+                return;
+            }
+            let def_id = cx.node_def_id(i.id);
+            let ty = cx.def_type(def_id);
+            let is_thread_local = attr::contains_name(&i.attrs, sym::thread_local);
+            let wrapper = if is_thread_local &&
+                ty.is_copy_modulo_regions(cx.ty_ctxt(), ParamEnv::empty(), i.span) {
+                SafeWrapper::Cell
+            } else if let Some(name) = atomic_type(ty) {
+                SafeWrapper::Atomic(name)
+            } else {
+                cx.session().span_warn(i.span, &format!(
+                    "static_to_safe: can't convert `{}`: it has no atomic type and isn't a \
+                     thread-local of a `Copy` type", i.ident));
+                return;
+            };
+            statics.insert(def_id, wrapper);
+        });
+
+        // (2) Leave out the statics with uses we can't convert.  Only reads and assignments
+        // can be.
+        let mut writes = HashSet::new();
+        let mut places = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            match e.kind {
+                ExprKind::Assign(ref lhs, _) | ExprKind::AssignOp(_, ref lhs, _) => {
+                    writes.insert(strip_parens(lhs).id);
+                }
+                ExprKind::AddrOf(_, _, ref x) |
+                ExprKind::Field(ref x, _) |
+                ExprKind::Index(ref x, _) => {
+                    places.insert(strip_parens(x).id);
+                }
+                _ => {}
+            }
+        });
+
+        let mut unconvertible = HashSet::new();
+        visit_nodes(krate, |e: &Expr| {
+            if !matches!([e.kind] ExprKind::Path(..)) || writes.contains(&e.id) {
+                return;
+            }
+            let def_id = match_or!([cx.try_resolve_expr(e)] Some(x) => x; return);
+            if !statics.contains_key(&def_id) {
+                return;
+            }
+            // A read the compiler borrows, like the receiver of a `&self` method, uses the
+            // static as a place as well.
+            if places.contains(&e.id) ||
+                cx.opt_adjusted_node_type(e.id) != cx.opt_node_type(e.id) {
+                cx.session().span_warn(e.span,
+                    "static_to_safe: can't convert this use, which borrows the static or \
+                     accesses part of it");
+                unconvertible.insert(def_id);
+            }
+        });
+        statics.retain(|id, _| !unconvertible.contains(id));
+
+        if statics.is_empty() {
+            return;
+        }
+
+        // (3) Change the types of the statics.
+        FlatMapNodes::visit(krate, |i: P<Item>| {
+            if !matches!([i.kind] ItemKind::Static(..)) {
+                return smallvec![i];
+            }
+            let wrapper = match_or!([statics.get(&cx.node_def_id(i.id))] Some(&x) => x;
+                                    return smallvec![i]);
+            smallvec![i.map(|mut i| {
+                if let ItemKind::Static(ref mut ty, ref mut mutbl, ref mut init) = i.kind {
+                    let (new_ty, ctor) = match wrapper {
+                        SafeWrapper::Atomic(name) => (
+                            mk().path_ty(vec!["", "std", "sync", "atomic", name]),
+                            mk().path_expr(vec!["", "std", "sync", "atomic", name, "new"]),
+                        ),
+                        SafeWrapper::Cell => (
+                            mk().path_ty(vec![
+                                mk().path_segment(""),
+                                mk().path_segment("std"),
+                                mk().path_segment("cell"),
+                                mk().path_segment_with_args(
+                                    "Cell", mk().angle_bracketed_args(vec![ty.clone()])),
+                            ]),
+                            mk().path_expr(vec!["", "std", "cell", "Cell", "new"]),
+                        ),
+                    };
+                    *ty = new_ty;
+                    *mutbl = Mutability::Immutable;
+                    *init = mk().call_expr(ctor, vec![init.clone()]);
+                }
+                i
+            })]
+        });
+
+        // The static a use refers to, as a fresh path expression to build the new use from.
+        let static_use = |e: &Expr| -> Option<(P<Expr>, SafeWrapper)> {
+            let e = strip_parens(e);
+            if !matches!([e.kind] ExprKind::Path(..)) {
+                return None;
+            }
+            let wrapper = *statics.get(&cx.try_resolve_expr(e)?)?;
+            let mut path = P(e.clone());
+            path.id = DUMMY_NODE_ID;
+            Some((path, wrapper))
+        };
+
+        // (4) Rewrite assignments to the statics.
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            let new_e = match e.kind {
+                ExprKind::Assign(ref lhs, ref rhs) => {
+                    let (x, wrapper) = match_or!([static_use(lhs)] Some(x) => x; return);
+                    write_safe_static(x, rhs.clone(), wrapper)
+                }
+                ExprKind::AssignOp(op, ref lhs, ref rhs) => {
+                    let (x, wrapper) = match_or!([static_use(lhs)] Some(x) => x; return);
+                    let value = mk().binary_expr(
+                        op.node, read_safe_static(x.clone(), wrapper), rhs.clone());
+                    write_safe_static(x, value, wrapper)
+                }
+                _ => return,
+            };
+            *e = new_e;
+        });
+
+        // (5) Rewrite the remaining uses, which all read the statics.
+        MutVisitNodes::visit(krate, |e: &mut P<Expr>| {
+            if !matches!([e.kind] ExprKind::Path(..)) {
+                return;
+            }
+            if let Some((x, wrapper)) = static_use(e) {
+                *e = read_safe_static(x, wrapper);
+            }
+        });
+    }
+
+    fn min_phase(&self) -> Phase {
+        Phase::Phase3
+    }
+}
+
+/// The atomic type with the same values as `ty`, if there is one.
+fn atomic_type(ty: ty::Ty) -> Option<&'static str> {
+    Some(match ty.kind {
+        TyKind::Bool => "AtomicBool",
+        TyKind::Int(IntTy::I8) => "AtomicI8",
+        TyKind::Int(IntTy::I16) => "AtomicI16",
+        TyKind::Int(IntTy::I32) => "AtomicI32",
+        TyKind::Int(IntTy::I64) => "AtomicI64",
+        TyKind::Int(IntTy::Isize) => "AtomicIsize",
+        TyKind::Uint(UintTy::U8) => "AtomicU8",
+        TyKind::Uint(UintTy::U16) => "AtomicU16",
+        TyKind::Uint(UintTy::U32) => "AtomicU32",
+        TyKind::Uint(UintTy::U64) => "AtomicU64",
+        TyKind::Uint(UintTy::Usize) => "AtomicUsize",
+        _ => return None,
+    })
+}
+
+fn seq_cst() -> P<Expr> {
+    mk().path_expr(vec!["", "std", "sync", "atomic", "Ordering", "SeqCst"])
+}
+
+fn read_safe_static(x: P<Expr>, wrapper: SafeWrapper) -> P<Expr> {
+    match wrapper {
+        SafeWrapper::Atomic(_) => mk().method_call_expr(x, "load", vec![seq_cst()]),
+        SafeWrapper::Cell => mk().method_call_expr(x, "get", Vec::<P<Expr>>::new()),
+    }
+}
+
+fn write_safe_static(x: P<Expr>, value: P<Expr>, wrapper: SafeWrapper) -> P<Expr> {
+    match wrapper {
+        SafeWrapper::Atomic(_) => mk().method_call_expr(x, "store", vec![value, seq_cst()]),
+        SafeWrapper::Cell => mk().method_call_expr(x, "set", vec![value]),
+    }
+}
+
+
 
 
 pub fn register_commands(reg: &mut Registry) {
@@ -507,4 +750,5 @@ pub fn register_commands(reg: &mut Registry) {
     }));
     reg.register("static_to_local_ref", |_args| mk(Localize));
     reg.register("static_to_local", |_args| mk(StaticToLocal));
+    reg.register("static_to_safe", |_args| mk(StaticToSafe));
 }
@@ -0,0 +1,45 @@
+extern "C" {
+    fn abs(x: i32) -> i32;
+}
+
+static mut COUNTER: i32 = 0;
+
+pub unsafe fn load(p: *const i32) -> i32 {
+    *p
+}
+
+pub fn copy(dst: *mut i32, src: *const i32, n: isize) {
+    // Copy a single element.
+    // SAFETY: unverified
+    // - length: `dst.offset(n)` is not bounds-checked
+    // - aliasing: raw pointers `dst.offset(n)` and `src` are not known to be disjoint
+    // - validity: raw pointer `dst.offset(n)` is not known to be non-null, aligned and live
+    // - validity: raw pointer `src` is not known to be non-null, aligned and live
+    unsafe {
+        *dst.offset(n) = *src;
+    }
+}
+
+pub fn magnitude(x: i32) -> i32 {
+    // SAFETY: unverified
+    // - ffi: calls foreign function `abs`
+    let m = unsafe { abs(x) };
+    m
+}
+
+pub fn bump(p: *const i32) -> i32 {
+    // SAFETY: unverified
+    // - static-mut: accesses `COUNTER` without synchronization
+    // - unsafe-fn: preconditions of `load` are not checked
+    unsafe {
+        COUNTER += 1;
+        load(p)
+    }
+}
+
+pub fn audited(x: i32) -> i32 {
+    // SAFETY: `abs` is defined for all inputs except `i32::MIN`, which callers exclude.
+    unsafe { abs(x) }
+}
+
+fn main() {}
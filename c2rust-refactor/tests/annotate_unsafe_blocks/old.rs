@@ -0,0 +1,35 @@
+extern "C" {
+    fn abs(x: i32) -> i32;
+}
+
+static mut COUNTER: i32 = 0;
+
+pub unsafe fn load(p: *const i32) -> i32 {
+    *p
+}
+
+pub fn copy(dst: *mut i32, src: *const i32, n: isize) {
+    // Copy a single element.
+    unsafe {
+        *dst.offset(n) = *src;
+    }
+}
+
+pub fn magnitude(x: i32) -> i32 {
+    let m = unsafe { abs(x) };
+    m
+}
+
+pub fn bump(p: *const i32) -> i32 {
+    unsafe {
+        COUNTER += 1;
+        load(p)
+    }
+}
+
+pub fn audited(x: i32) -> i32 {
+    // SAFETY: `abs` is defined for all inputs except `i32::MIN`, which callers exclude.
+    unsafe { abs(x) }
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor annotate_unsafe_blocks -- old.rs $rustflags
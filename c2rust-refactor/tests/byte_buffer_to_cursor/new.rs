@@ -0,0 +1,63 @@
+#![allow(non_camel_case_types)]
+
+pub type uint16_t = u16;
+pub type uint32_t = u32;
+
+extern "C" {
+    fn ntohl(netlong: uint32_t) -> uint32_t;
+}
+
+#[repr(C)]
+pub struct header {
+    pub len: uint32_t,
+    pub kind: uint16_t,
+    pub flags: u8,
+}
+
+pub unsafe fn parse_header(mut p: *const u8, h: *mut header) -> *const u8 {
+    let mut p_cursor = crate::ByteCursor::new(::std::slice::from_raw_parts(p as *const u8, 6));
+    (*h).len = p_cursor.read_u32_be();
+    let kind: uint16_t = p_cursor.read_u16_ne();
+    p = p.offset(6 as isize);
+    (*h).kind = kind;
+    let mut p_cursor = crate::ByteCursor::new(::std::slice::from_raw_parts(p as *const u8, 1));
+    (*h).flags = p_cursor.read_u8();
+    p = p.offset(1 as isize);
+    return p;
+}
+
+fn main() {}
+
+/// Bounds-checked reader over a byte buffer.
+pub struct ByteCursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteCursor<'a> {
+    pub fn new(buf: &'a [u8]) -> ByteCursor<'a> {
+        ByteCursor { buf, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> &'a [u8] {
+        let bytes = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        bytes
+    }
+
+    pub fn read_u16_ne(&mut self) -> u16 {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.take(2));
+        u16::from_ne_bytes(bytes)
+    }
+
+    pub fn read_u32_be(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4));
+        u32::from_be_bytes(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> u8 {
+        self.take(1)[0] as u8
+    }
+}
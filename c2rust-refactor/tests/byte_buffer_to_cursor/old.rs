@@ -0,0 +1,28 @@
+#![allow(non_camel_case_types)]
+
+pub type uint16_t = u16;
+pub type uint32_t = u32;
+
+extern "C" {
+    fn ntohl(netlong: uint32_t) -> uint32_t;
+}
+
+#[repr(C)]
+pub struct header {
+    pub len: uint32_t,
+    pub kind: uint16_t,
+    pub flags: u8,
+}
+
+pub unsafe fn parse_header(mut p: *const u8, h: *mut header) -> *const u8 {
+    (*h).len = ntohl(*(p as *const uint32_t));
+    p = p.offset(4 as i32 as isize);
+    let kind: uint16_t = *(p as *const uint16_t);
+    p = p.offset(::std::mem::size_of::<uint16_t>() as isize);
+    (*h).kind = kind;
+    (*h).flags = *p;
+    p = p.offset(1 as i32 as isize);
+    return p;
+}
+
+fn main() {}
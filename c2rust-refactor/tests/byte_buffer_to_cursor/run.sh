@@ -0,0 +1,8 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor byte_buffer_to_cursor -- old.rs $rustflags
@@ -0,0 +1,57 @@
+#![feature(libc)]
+extern crate libc;
+
+extern "C" {
+    fn strlen(s: *const libc::c_char) -> libc::c_ulong;
+    fn strcmp(a: *const libc::c_char, b: *const libc::c_char) -> libc::c_int;
+    fn puts(s: *const libc::c_char) -> libc::c_int;
+    fn strdup(s: *const libc::c_char) -> *mut libc::c_char;
+    fn strcpy(dst: *mut libc::c_char, src: *const libc::c_char) -> *mut libc::c_char;
+    fn free(p: *mut libc::c_void);
+}
+
+unsafe fn compare(a: &::std::ffi::CStr, b: &::std::ffi::CStr) -> libc::c_int {
+    if a.to_bytes().len() as u64 == b.to_bytes().len() as u64 {
+        a.cmp(b) as i32
+    } else {
+        1
+    }
+}
+
+unsafe fn greet(name: &::std::ffi::CStr) {
+    let hello: &::std::ffi::CStr = ::std::ffi::CStr::from_bytes_with_nul(b"hello\x00").unwrap();
+    puts(hello.as_ptr());
+    let pair: &::std::ffi::CStr =
+        ::std::ffi::CStr::from_ptr(b"a\x00b\x00" as *const u8 as *const libc::c_char);
+    puts(pair.as_ptr());
+    if compare(name, hello) != 0 {
+        puts(name.as_ptr());
+    }
+}
+
+unsafe fn show(s: *const libc::c_char) {
+    puts(s);
+}
+
+unsafe fn dup(dst: *mut libc::c_char, name: &::std::ffi::CStr) -> *mut libc::c_char {
+    let copy: ::std::ffi::CString = name.to_owned();
+    if copy.to_bytes().len() as u64 != 0 {
+        puts(copy.as_ptr());
+    }
+    ::std::mem::drop(copy);
+    {
+        let (dst, src) = (dst, name);
+        ::std::slice::from_raw_parts_mut(dst as *mut u8, src.to_bytes_with_nul().len())
+            .copy_from_slice(src.to_bytes_with_nul());
+        dst
+    }
+}
+
+fn main() {
+    let mut buf = [0 as libc::c_char; 8];
+    unsafe {
+        greet(::std::ffi::CStr::from_bytes_with_nul(b"world\x00").unwrap());
+        show(0 as *const libc::c_char);
+        dup(buf.as_mut_ptr(), ::std::ffi::CStr::from_bytes_with_nul(b"x\x00").unwrap());
+    }
+}
@@ -0,0 +1,51 @@
+#![feature(libc)]
+extern crate libc;
+
+extern "C" {
+    fn strlen(s: *const libc::c_char) -> libc::c_ulong;
+    fn strcmp(a: *const libc::c_char, b: *const libc::c_char) -> libc::c_int;
+    fn puts(s: *const libc::c_char) -> libc::c_int;
+    fn strdup(s: *const libc::c_char) -> *mut libc::c_char;
+    fn strcpy(dst: *mut libc::c_char, src: *const libc::c_char) -> *mut libc::c_char;
+    fn free(p: *mut libc::c_void);
+}
+
+unsafe fn compare(a: *const libc::c_char, b: *const libc::c_char) -> libc::c_int {
+    if strlen(a) == strlen(b) {
+        strcmp(a, b)
+    } else {
+        1
+    }
+}
+
+unsafe fn greet(name: *const libc::c_char) {
+    let hello: *const libc::c_char = b"hello\x00" as *const u8 as *const libc::c_char;
+    puts(hello);
+    let pair: *const libc::c_char = b"a\x00b\x00" as *const u8 as *const libc::c_char;
+    puts(pair);
+    if compare(name, hello) != 0 {
+        puts(name);
+    }
+}
+
+unsafe fn show(s: *const libc::c_char) {
+    puts(s);
+}
+
+unsafe fn dup(dst: *mut libc::c_char, name: *const libc::c_char) -> *mut libc::c_char {
+    let copy: *mut libc::c_char = strdup(name);
+    if strlen(copy) != 0 {
+        puts(copy);
+    }
+    free(copy as *mut libc::c_void);
+    strcpy(dst, name)
+}
+
+fn main() {
+    let mut buf = [0 as libc::c_char; 8];
+    unsafe {
+        greet(b"world\x00" as *const u8 as *const libc::c_char);
+        show(0 as *const libc::c_char);
+        dup(buf.as_mut_ptr(), b"x\x00" as *const u8 as *const libc::c_char);
+    }
+}
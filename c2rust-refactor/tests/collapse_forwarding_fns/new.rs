@@ -0,0 +1,53 @@
+unsafe fn read(p: *const u8) -> u8 {
+    *p
+}
+
+pub unsafe fn read_u8(p: *const u8) -> u8 {
+    read(p)
+}
+
+pub use crate::read_u8 as read_byte;
+
+// Private, unlike `read_u8`, so this one is kept
+unsafe fn read_private(p: *const u8) -> u8 {
+    read(p)
+}
+
+// Different signature, so this one is kept
+pub unsafe fn read_i8(p: *const i8) -> u8 {
+    read(p as *const u8)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn read_exported(p: *const u8) -> u8 {
+    read(p)
+}
+
+unsafe fn sum(a: u8, b: u8) -> u8 {
+    a + b
+}
+
+unsafe fn add(a: u8, b: u8) -> u8 {
+    sum(a, b)
+}
+
+// Arguments are passed in a different order
+unsafe fn add_swapped(a: u8, b: u8) -> u8 {
+    sum(b, a)
+}
+
+use crate::add as plus;
+
+fn main() {
+    let x = 1;
+    unsafe {
+        read_u8(&x);
+        crate::read_u8(&x);
+        read_private(&x);
+        read_i8(&x as *const u8 as *const i8);
+        read_exported(&x);
+        add(1, 2);
+        add_swapped(1, 2);
+        crate::add(1, 2);
+    }
+}
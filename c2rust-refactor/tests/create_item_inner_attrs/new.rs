@@ -0,0 +1,10 @@
+//! Crate-level docs.
+#![allow(dead_code)]
+
+mod test {
+    //! Module docs.
+    #![allow(unused)]
+    fn new() {}
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+//! Crate-level docs.
+#![allow(dead_code)]
+
+mod test {
+    //! Module docs.
+    #![allow(unused)]
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+fn scale(x: i32, y: i32) -> i32 {
+    (x + y) * 2
+}
+
+fn scale_cast(x: u8) -> u32 {
+    x as u32 * 2
+}
+
+fn negate_sum(a: i32, b: i32) -> i32 {
+    -(a + b)
+}
+
+fn bump(x: i32) -> i32 {
+    x
+}
+
+fn count(x: Option<i32>) {
+    bump(match x {
+        Some(y) => y,
+        None => 0,
+    });
+}
+
+fn main() {}
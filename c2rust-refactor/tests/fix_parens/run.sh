@@ -0,0 +1,12 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor \
+    rewrite_expr '$e * 2' '$e << 1' \; \
+    rewrite_expr 'bump($e)' '$e + 1' \; \
+    fix_parens \
+    -- old.rs $rustflags
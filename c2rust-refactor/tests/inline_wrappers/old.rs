@@ -0,0 +1,49 @@
+pub struct Buf {
+    pub len: usize,
+    pub data: *mut u8,
+}
+
+unsafe fn len(b: *const Buf) -> usize {
+    (*b).len
+}
+
+unsafe fn data_at(b: *const Buf, i: usize) -> *mut u8 {
+    (*b).data.offset(i as isize)
+}
+
+fn sum(a: u32, b: u32) -> u32 {
+    a.wrapping_add(b)
+}
+
+fn add_one(x: u32) -> u32 {
+    sum(x, 1)
+}
+
+// Uses its parameter twice, so it is kept
+fn square(x: u32) -> u32 {
+    x * x
+}
+
+// Takes the address of its parameter, so it is kept
+fn addr(x: u32) -> *const u32 {
+    &x as *const u32
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn buf_len(b: *const Buf) -> usize {
+    (*b).len
+}
+
+fn main() {
+    let mut bytes = [1u8, 2, 3];
+    let buf = Buf { len: 3, data: bytes.as_mut_ptr() };
+    unsafe {
+        let p = &buf as *const Buf;
+        let n = len(p) + buf_len(p);
+        *data_at(p, 2) = 0;
+        add_one(n as u32);
+        // The argument has side effects, so this call is kept
+        add_one(square(2));
+        addr(1);
+    }
+}
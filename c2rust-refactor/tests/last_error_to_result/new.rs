@@ -0,0 +1,45 @@
+static mut LAST_ERROR: i32 = 0;
+
+pub unsafe fn half_result(x: i32) -> Result<i32, i32> {
+    if x % 2 != 0 {
+        return Err(1);
+    }
+    Ok(x / 2)
+}
+#[no_mangle]
+pub unsafe extern "C" fn half(x: i32) -> i32 {
+    match half_result(x) {
+        Ok(v) => v,
+        Err(e) => {
+            LAST_ERROR = e;
+            -1
+        }
+    }
+}
+
+pub unsafe fn quarter_result(x: i32) -> Result<i32, i32> {
+    half_result(x)?;
+    return Ok(half(half(x)));
+}
+#[no_mangle]
+pub unsafe extern "C" fn quarter(x: i32) -> i32 {
+    match quarter_result(x) {
+        Ok(v) => v,
+        Err(e) => {
+            LAST_ERROR = e;
+            -1
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn last_error() -> i32 {
+    LAST_ERROR
+}
+
+fn main() {
+    unsafe {
+        half(4);
+        quarter(8);
+    }
+}
@@ -0,0 +1,30 @@
+static mut LAST_ERROR: i32 = 0;
+
+#[no_mangle]
+pub unsafe extern "C" fn half(x: i32) -> i32 {
+    if x % 2 != 0 {
+        LAST_ERROR = 1;
+        return -1;
+    }
+    x / 2
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn quarter(x: i32) -> i32 {
+    if half(x) == -1 {
+        return -1;
+    }
+    return half(half(x));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn last_error() -> i32 {
+    LAST_ERROR
+}
+
+fn main() {
+    unsafe {
+        half(4);
+        quarter(8);
+    }
+}
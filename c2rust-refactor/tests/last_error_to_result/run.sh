@@ -0,0 +1,11 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor \
+    select target 'crate; child(fn && name("half|quarter"));' \; \
+    select error 'item(LAST_ERROR);' \; \
+    last_error_to_result -- old.rs $rustflags
@@ -0,0 +1,30 @@
+#![feature(libc)]
+extern crate libc;
+
+extern "C" {
+    fn abs(x: libc::c_int) -> libc::c_int;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn clamp(x: libc::c_int, max: libc::c_uint) -> libc::c_uint {
+    let y: u32 = abs(x as libc::c_int) as i32 as u32;
+    if y > max { max } else { y }
+}
+
+unsafe fn sum(xs: *const i32, n: i32) -> i32 {
+    let mut total: i32 = 0;
+    let mut i: i32 = 0;
+    while i < n {
+        total += abs(*xs.offset(i as isize) as libc::c_int) as i32;
+        i += 1;
+    }
+    clamp(total as libc::c_int, 100);
+    total
+}
+
+fn main() {
+    let xs: [libc::c_int; 3] = [1, -2, 3];
+    unsafe {
+        sum(xs.as_ptr(), 3);
+    }
+}
@@ -0,0 +1,30 @@
+#![feature(libc)]
+extern crate libc;
+
+extern "C" {
+    fn abs(x: libc::c_int) -> libc::c_int;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn clamp(x: libc::c_int, max: libc::c_uint) -> libc::c_uint {
+    let y: libc::c_uint = abs(x) as libc::c_uint;
+    if y > max { max } else { y }
+}
+
+unsafe fn sum(xs: *const libc::c_int, n: libc::c_int) -> libc::c_int {
+    let mut total: libc::c_int = 0;
+    let mut i: libc::c_int = 0;
+    while i < n {
+        total += abs(*xs.offset(i as isize));
+        i += 1;
+    }
+    clamp(total, 100);
+    total
+}
+
+fn main() {
+    let xs: [libc::c_int; 3] = [1, -2, 3];
+    unsafe {
+        sum(xs.as_ptr(), 3);
+    }
+}
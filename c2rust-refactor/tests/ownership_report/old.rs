@@ -0,0 +1,32 @@
+struct Pair {
+    first: *mut i32,
+    second: *const i32,
+}
+
+unsafe fn get(p: *const i32) -> i32 {
+    *p
+}
+
+unsafe fn set(p: *mut i32, v: i32) {
+    *p = v;
+}
+
+unsafe fn swap_first(pair: *mut Pair, q: *mut i32) {
+    let t = *(*pair).first;
+    *(*pair).first = *q;
+    *q = t;
+}
+
+fn main() {
+    let mut x = 1;
+    let mut y = 2;
+    let mut pair = Pair {
+        first: &mut x,
+        second: &y,
+    };
+    unsafe {
+        set(&mut y, get(pair.second) + 1);
+        swap_first(&mut pair, &mut y);
+        println!("{} {}", get(pair.first), get(pair.second));
+    }
+}
@@ -0,0 +1,15 @@
+#!/bin/sh
+set -e
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+rm -f report.txt old.new
+$refactor ownership_report report.txt -- old.rs $rustflags
+
+# The report lists the signatures and fields with pointers, with the permission of each pointer.
+# Path disambiguators and the line and column of each definition are left out.
+sed -e 's/\[[0-9][0-9]*\]//g' -e 's|  // old\.rs:.*|  // old.rs|' report.txt | diff -u expected.txt -
+rm report.txt
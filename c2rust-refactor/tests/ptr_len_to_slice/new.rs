@@ -0,0 +1,66 @@
+unsafe fn sum(p: &[i32]) -> i32 {
+    let mut s = 0;
+    for i in 0..p.len() {
+        s += p[i as isize as usize];
+    }
+    s
+}
+
+unsafe fn fill(dst: &mut [u8], c: u8) {
+    let mut i = 0;
+    while i < dst.len() as i32 {
+        dst[i as usize] = c;
+        i += 1;
+    }
+    dst[0] = c;
+}
+
+// Nothing shows that `size` counts elements rather than bytes, so it is kept
+unsafe fn first(p: *const i32, size: usize) -> i32 {
+    if size < 4 {
+        return 0;
+    }
+    *p
+}
+
+// Called with the same pointer as another argument, so `src` and `n` are kept
+unsafe fn copy(dst: *mut i32, src: *const i32, n: usize) {
+    for i in 0..n {
+        *dst.offset(i as isize) = *src.offset(i as isize);
+    }
+}
+
+// Called with a length computed from `size_of`, so it is kept
+unsafe fn total(p: *const i32, n: usize) -> i32 {
+    let mut s = 0;
+    for i in 0..n {
+        s += *p.offset(i as isize);
+    }
+    s
+}
+
+// Advances its pointer, so it is kept
+unsafe fn advance(mut p: *const i32, n: usize) -> i32 {
+    let mut s = 0;
+    for _ in 0..n {
+        s += *p;
+        p = p.offset(1);
+    }
+    s
+}
+
+unsafe fn f(v: &mut Vec<u8>, a: &[i32; 4]) -> i32 {
+    fill(::std::slice::from_raw_parts_mut(v.as_mut_ptr(), v.len() as i32 as usize), 1);
+    let n = 4;
+    let mut b = [1, 2, 3, 4];
+    copy(b.as_mut_ptr(), b.as_ptr(), 2);
+    sum(&a[..]) + sum(::std::slice::from_raw_parts(a.as_ptr(), n)) + advance(a.as_ptr(), 4) +
+        first(a.as_ptr(), 16) + total(a.as_ptr(), ::std::mem::size_of::<i32>() / 4)
+}
+
+fn main() {
+    let mut v = vec![0; 4];
+    unsafe {
+        println!("{}", f(&mut v, &[1, 2, 3, 4]));
+    }
+}
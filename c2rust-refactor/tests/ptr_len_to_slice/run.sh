@@ -0,0 +1,10 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor \
+    select target 'crate; desc(fn && name("^(sum|fill|first|copy|total|advance)$"));' \; \
+    ptr_len_to_slice -- old.rs $rustflags
@@ -0,0 +1,46 @@
+pub struct S {
+    pub x: i32,
+}
+
+unsafe fn get(p: &S) -> i32 {
+    (*p).x
+}
+
+unsafe fn set(p: &mut S, q: &i32) {
+    (*p).x = *q;
+}
+
+// Offsets its pointer, so it is kept
+unsafe fn advance(p: *const i32) -> i32 {
+    *p.offset(1)
+}
+
+// A call passes the same pointer for both parameters, so they are kept
+unsafe fn swap(p: *mut i32, q: *mut i32) {
+    let t = *p;
+    *p = *q;
+    *q = t;
+}
+
+// Its address is taken, so it is kept
+unsafe fn kept(p: *const i32) -> i32 {
+    *p
+}
+
+unsafe fn f(s: *mut S, arr: &[i32; 2]) -> i32 {
+    let mut t = S { x: 1 };
+    let y = 2;
+    set(&mut t, &y);
+    set(s.as_mut().unwrap(), &arr[0]);
+    let mut z = 3;
+    swap(&mut z as *mut i32, &mut z as *mut i32);
+    let g: unsafe fn(*const i32) -> i32 = kept;
+    get(&t) + get(s.as_ref().unwrap()) + advance(arr.as_ptr()) + g(&y)
+}
+
+fn main() {
+    let mut s = S { x: 0 };
+    unsafe {
+        println!("{}", f(&mut s, &[1, 2]));
+    }
+}
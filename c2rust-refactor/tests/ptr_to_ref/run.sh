@@ -0,0 +1,10 @@
+#!/bin/sh
+
+# work around System Integrity Protection on macOS
+if [ `uname` = 'Darwin' ]; then
+    export LD_LIBRARY_PATH=$not_LD_LIBRARY_PATH
+fi
+
+$refactor \
+    select target 'crate; desc(fn && name("^(get|set|swap|advance|kept)$"));' \; \
+    ptr_to_ref -- old.rs $rustflags
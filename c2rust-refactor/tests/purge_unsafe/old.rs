@@ -0,0 +1,49 @@
+unsafe fn get(p: *const i32) -> i32 {
+    *p
+}
+
+unsafe fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+unsafe fn twice(p: *const i32) -> i32 {
+    add(get(p), get(p))
+}
+
+unsafe fn four() -> i32 {
+    add(2, 2)
+}
+
+unsafe fn checked(p: *const i32) -> i32 {
+    if p.is_null() {
+        return 0;
+    }
+    unsafe { *p }
+}
+
+unsafe fn both(p: *const i32) -> i32 {
+    let x = *p;
+    x + unsafe { *p }
+}
+
+trait Get {
+    unsafe fn get(&self) -> i32;
+}
+
+struct S(i32);
+
+// The trait requires this to be unsafe
+impl Get for S {
+    unsafe fn get(&self) -> i32 {
+        self.0
+    }
+}
+
+fn main() {
+    let x = 1;
+    unsafe {
+        println!("{}", twice(&x) + four() + checked(&x) + both(&x) + S(1).get());
+    }
+    let y = unsafe { add(1, 2) };
+    println!("{}", y);
+}
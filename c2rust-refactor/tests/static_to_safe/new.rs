@@ -0,0 +1,29 @@
+#![feature(thread_local)]
+
+static COUNT: ::std::sync::atomic::AtomicI32 = ::std::sync::atomic::AtomicI32::new(0);
+static READY: ::std::sync::atomic::AtomicBool = ::std::sync::atomic::AtomicBool::new(false);
+
+#[thread_local]
+static DEPTH: ::std::cell::Cell<u32> = ::std::cell::Cell::new(0);
+
+// Its elements are accessed, so it is kept
+static mut BUF: [u8; 4] = [0; 4];
+
+unsafe fn bump() -> i32 {
+    COUNT.store(
+        COUNT.load(::std::sync::atomic::Ordering::SeqCst) + 1,
+        ::std::sync::atomic::Ordering::SeqCst,
+    );
+    DEPTH.set(DEPTH.get().wrapping_add(1));
+    BUF[0] = 1;
+    if !READY.load(::std::sync::atomic::Ordering::SeqCst) {
+        READY.store(true, ::std::sync::atomic::Ordering::SeqCst);
+    }
+    COUNT.load(::std::sync::atomic::Ordering::SeqCst) + (DEPTH.get() as i32)
+}
+
+fn main() {
+    unsafe {
+        println!("{}", bump());
+    }
+}
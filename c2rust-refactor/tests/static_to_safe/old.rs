@@ -0,0 +1,26 @@
+#![feature(thread_local)]
+
+static mut COUNT: i32 = 0;
+static mut READY: bool = false;
+
+#[thread_local]
+static mut DEPTH: u32 = 0;
+
+// Its elements are accessed, so it is kept
+static mut BUF: [u8; 4] = [0; 4];
+
+unsafe fn bump() -> i32 {
+    COUNT += 1;
+    DEPTH = DEPTH.wrapping_add(1);
+    BUF[0] = 1;
+    if !READY {
+        READY = true;
+    }
+    COUNT + (DEPTH as i32)
+}
+
+fn main() {
+    unsafe {
+        println!("{}", bump());
+    }
+}
@@ -12,7 +12,8 @@ use self::pathdiff::diff_paths;
 use serde_json::json;
 
 use super::TranspilerConfig;
-use super::compile_cmds::LinkCmd;
+use super::compile_cmds::{LinkCmd, LinkType};
+use crate::translator::HarnessFn;
 use crate::CrateSet;
 use crate::PragmaSet;
 use crate::get_module_name;
@@ -65,6 +66,22 @@ pub struct CrateConfig<'lcmd> {
     pub pragmas: PragmaSet,
     pub crates: CrateSet,
     pub link_cmd: &'lcmd LinkCmd,
+    /// The functions each module exports that the differential tests call
+    pub harness_fns: Vec<(PathBuf, Vec<HarnessFn>)>,
+}
+
+fn templates() -> Handlebars {
+    let mut reg = Handlebars::new();
+
+    reg.register_template_string("Cargo.toml", include_str!("Cargo.toml.hbs"))
+        .unwrap();
+    reg.register_template_string("lib.rs", include_str!("lib.rs.hbs"))
+        .unwrap();
+    reg.register_template_string("build.rs", include_str!("build.rs.hbs"))
+        .unwrap();
+    reg.register_template_string("differential.rs", include_str!("differential.rs.hbs"))
+        .unwrap();
+    reg
 }
 
 /// Emit `Cargo.toml` and `lib.rs` for a library or `main.rs` for a binary.
@@ -76,14 +93,7 @@ pub fn emit_build_files<'lcmd>(
     crate_cfg: Option<CrateConfig<'lcmd>>,
     workspace_members: Option<Vec<String>>,
 ) -> Option<PathBuf> {
-    let mut reg = Handlebars::new();
-
-    reg.register_template_string("Cargo.toml", include_str!("Cargo.toml.hbs"))
-        .unwrap();
-    reg.register_template_string("lib.rs", include_str!("lib.rs.hbs"))
-        .unwrap();
-    reg.register_template_string("build.rs", include_str!("build.rs.hbs"))
-        .unwrap();
+    let reg = templates();
 
     if !build_dir.exists() {
         fs::create_dir_all(&build_dir).expect(&format!(
@@ -97,7 +107,11 @@ pub fn emit_build_files<'lcmd>(
         emit_rust_toolchain(tcfg, &build_dir);
     }
     crate_cfg.and_then(|ccfg| {
-        emit_build_rs(tcfg, &reg, &build_dir, ccfg.link_cmd);
+        let differential_harness = has_differential_harness(tcfg, &ccfg);
+        emit_build_rs(tcfg, &reg, &build_dir, ccfg.link_cmd, differential_harness);
+        if differential_harness {
+            emit_differential_harness(tcfg, &reg, &build_dir, &ccfg);
+        }
         emit_lib_rs(tcfg, &reg, &build_dir, ccfg.modules, ccfg.pragmas, &ccfg.crates)
     })
 }
@@ -147,6 +161,10 @@ fn convert_module_list(
     module_subset: ModuleSubset,
 ) -> Vec<Module> {
     modules.retain(|m| {
+        // Integration tests are found by cargo itself
+        if is_test_module(build_dir, m) {
+            return false;
+        }
         let is_binary = tcfg.is_binary(&m);
         if is_binary && module_subset == ModuleSubset::Libraries {
             // Don't add binary modules to lib.rs, these are emitted to
@@ -184,6 +202,27 @@ fn convert_module_list(
     res
 }
 
+/// The path of a translated module within the library crate, or `None` if it
+/// is emitted as a binary or test
+pub fn module_path(tcfg: &TranspilerConfig, build_dir: &Path, module: &Path) -> Option<Vec<String>> {
+    if tcfg.is_binary(module) || is_test_module(build_dir, module) {
+        return None;
+    }
+    let names = match module.strip_prefix(build_dir) {
+        Ok(relpath) => relpath
+            .iter()
+            .map(|sm| get_module_name(Path::new(sm), true, false, false).unwrap())
+            .collect(),
+        Err(_) => vec![get_module_name(module, true, false, false).unwrap()],
+    };
+    Some(names)
+}
+
+/// Whether a translated module is a test driver emitted to `tests/`
+fn is_test_module(build_dir: &Path, module: &Path) -> bool {
+    module.starts_with(build_dir.join("tests"))
+}
+
 fn convert_dependencies_list(crates: CrateSet) -> Vec<ExternCrateDetails> {
     crates.into_iter().map(|dep| dep.into()).collect()
 }
@@ -196,15 +235,49 @@ fn get_lib_rs_file_name(tcfg: &TranspilerConfig) -> &str {
     }
 }
 
-/// Emit `build.rs` to make it easier to link in native libraries
+/// Differential tests are emitted for library crates integration tests can
+/// link to, if they export functions the tests can call
+fn has_differential_harness(tcfg: &TranspilerConfig, ccfg: &CrateConfig) -> bool {
+    tcfg.differential_harness
+        && ccfg.link_cmd.r#type == LinkType::Static
+        && ccfg.harness_fns.iter().any(|(_, fns)| !fns.is_empty())
+}
+
+/// Emit `build.rs` to make it easier to link in native libraries, and to
+/// compile the C sources for the differential tests
 fn emit_build_rs(
     tcfg: &TranspilerConfig,
     reg: &Handlebars,
     build_dir: &Path,
     link_cmd: &LinkCmd,
+    differential_harness: bool,
 ) -> Option<PathBuf> {
+    let reference = if differential_harness {
+        // The sources are compiled together, so with the flags of all of them
+        let mut cmd_flags = indexmap::IndexSet::new();
+        for cmd in &link_cmd.cmd_inputs {
+            cmd_flags.insert(cmd.c_flags());
+        }
+        let flags = cmd_flags.into_iter().flatten().collect::<Vec<_>>();
+        Some(json!({
+            "sources": link_cmd
+                .cmd_inputs
+                .iter()
+                .map(|cmd| cmd.abs_file().to_string_lossy().into_owned())
+                .collect::<Vec<_>>(),
+            "flags": flags,
+            "lib_dirs": link_cmd
+                .lib_dirs
+                .iter()
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .collect::<Vec<_>>(),
+        }))
+    } else {
+        None
+    };
     let json = json!({
         "libraries": link_cmd.libs,
+        "reference": reference,
     });
     let output = reg.render("build.rs", &json).unwrap();
     let output_path = build_dir.join("build.rs");
@@ -236,6 +309,7 @@ fn emit_lib_rs(
         "lib_rs_file": file_name,
         "reorganize_definitions": tcfg.reorganize_definitions,
         "translate_valist": tcfg.translate_valist,
+        "no_std": tcfg.emit_no_std_crate,
         "cross_checks": tcfg.cross_checks,
         "cross_check_backend": rs_xcheck_backend,
         "plugin_args": plugin_args,
@@ -250,6 +324,49 @@ fn emit_lib_rs(
     maybe_write_to_file(&output_path, output, tcfg.overwrite_existing)
 }
 
+/// Emit `tests/differential.rs`, which calls every exported function the
+/// tests support with generated arguments, both in the crate and in the C
+/// sources compiled by `build.rs`, and reports where the two diverge
+fn emit_differential_harness(
+    tcfg: &TranspilerConfig,
+    reg: &Handlebars,
+    build_dir: &Path,
+    ccfg: &CrateConfig,
+) -> Option<PathBuf> {
+    let mut functions = vec![];
+    for (module, fns) in &ccfg.harness_fns {
+        let mod_path = match module_path(tcfg, build_dir, module) {
+            Some(mod_path) => mod_path,
+            None => continue,
+        };
+        for f in fns {
+            let mut rust_path = mod_path.clone();
+            rust_path.push(f.rust_name.clone());
+            functions.push(json!({
+                "symbol": f.symbol,
+                "rust_path": rust_path.join("::"),
+                "params": f.params,
+                "ret": f.ret,
+            }));
+        }
+    }
+    let json = json!({
+        "crate_rust_name": ccfg.crate_name.replace('-', "_"),
+        "functions": functions,
+    });
+
+    let tests_dir = build_dir.join("tests");
+    if !tests_dir.exists() {
+        fs::create_dir_all(&tests_dir).expect(&format!(
+            "couldn't create tests directory: {}",
+            tests_dir.display()
+        ));
+    }
+    let output = reg.render("differential.rs", &json).unwrap();
+    let output_path = tests_dir.join("differential.rs");
+    maybe_write_to_file(&output_path, output, tcfg.overwrite_existing)
+}
+
 /// If we translate variadic functions, the output will only compile
 /// on a nightly toolchain until the `c_variadics` feature is stable.
 fn emit_rust_toolchain(tcfg: &TranspilerConfig, build_dir: &Path) {
@@ -285,6 +402,12 @@ fn emit_cargo_toml<'lcmd>(
             "cross_checks": tcfg.cross_checks,
             "cross_check_backend": tcfg.cross_check_backend,
             "dependencies": dependencies,
+            "sys_crate": tcfg.sys_crate.as_ref().map(|sys_crate| json!({
+                "name": sys_crate.name,
+                "version": sys_crate.version,
+            })),
+            "features": tcfg.cfg_configurations.iter().map(|c| &c.name).collect::<Vec<_>>(),
+            "differential_harness": has_differential_harness(tcfg, ccfg),
         });
         json.as_object_mut()
             .unwrap()
@@ -318,3 +441,37 @@ fn maybe_write_to_file(output_path: &Path, output: String, overwrite: bool) -> O
 
     Some(PathBuf::from(output_path))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_differential_harness() {
+        let json = json!({
+            "crate_rust_name": "fill",
+            "functions": [{
+                "symbol": "fill",
+                "rust_path": "src::fill::fill",
+                "params": [
+                    { "ty": "libc::c_int", "pointer": true, "mutable": true, "length": false },
+                    { "ty": "libc::c_uint", "pointer": false, "mutable": false, "length": true },
+                    { "ty": "libc::c_int", "pointer": false, "mutable": false, "length": false },
+                ],
+                "ret": "libc::c_int",
+            }],
+        });
+        let output = templates().render("differential.rs", &json).unwrap();
+        assert!(output.contains("extern crate fill;"));
+        assert!(output.contains("fn differential_fill() {"));
+        assert!(output.contains(
+            "let c_fn: unsafe extern \"C\" fn(*mut libc::c_int, libc::c_uint, libc::c_int, ) -> libc::c_int"
+        ));
+        assert!(output.contains("let arg0: Vec<libc::c_int> = fuzz_buffer(&mut rng);"));
+        assert!(output.contains("let arg1 = buffer_len(arg1 as u64) as libc::c_uint;"));
+        assert!(!output.contains("buffer_len(arg2"));
+        assert!(output.contains(
+            "fill::src::fill::fill(rust_arg0.as_mut_ptr(), arg1, arg2, )"
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+//! Evaluation of integer constant expressions (C11 6.6), used to fold static
+//! initializers whose translation wouldn't be valid in a Rust constant, e.g.
+//! because it uses wrapping unsigned arithmetic.
+
+use crate::c_ast::*;
+
+impl TypedAstContext {
+    /// The value of an integer constant expression of at most 64 bits, or
+    /// `None` if the expression isn't one
+    pub fn eval_int_const(&self, expr_id: CExprId) -> Option<ConstIntExpr> {
+        let ty = self[expr_id].kind.get_type()?;
+        let val = self.eval_int(expr_id)?;
+        let (_, signed) = self.int_repr(ty)?;
+        if signed {
+            Some(ConstIntExpr::I(val as i64))
+        } else {
+            Some(ConstIntExpr::U(val as u64))
+        }
+    }
+
+    /// Width and signedness of an integral type of at most 64 bits
+    fn int_repr(&self, ty: CTypeId) -> Option<(u64, bool)> {
+        let kind = &self.resolve_type(ty).kind;
+        let kind = match *kind {
+            CTypeKind::Enum(decl_id) => match self[decl_id].kind {
+                CDeclKind::Enum {
+                    integral_type: Some(integral_type),
+                    ..
+                } => &self.resolve_type(integral_type.ctype).kind,
+                _ => return None,
+            },
+            ref kind => kind,
+        };
+        match *kind {
+            CTypeKind::BitInt(..) => None,
+            _ => {
+                let width = self.integral_type_width(kind).filter(|&width| width <= 64)?;
+                Some((width, self.is_signed_integral(kind)))
+            }
+        }
+    }
+
+    /// Wrap a value around to the range of the given type
+    fn wrap_int(&self, val: i128, ty: CTypeId) -> Option<i128> {
+        if let CTypeKind::Bool = self.resolve_type(ty).kind {
+            return Some((val != 0) as i128);
+        }
+        let (width, signed) = self.int_repr(ty)?;
+        let shift = 128 - width;
+        if signed {
+            Some((val << shift) >> shift)
+        } else {
+            Some(((val as u128) << shift >> shift) as i128)
+        }
+    }
+
+    fn eval_int(&self, expr_id: CExprId) -> Option<i128> {
+        let ty = self[expr_id].kind.get_type()?;
+        let val = match self[expr_id].kind {
+            CExprKind::Literal(_, CLiteral::Integer(val, _))
+            | CExprKind::Literal(_, CLiteral::Character(val)) => val as i128,
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::U(val))) => val as i128,
+            CExprKind::ConstantExpr(_, _, Some(ConstIntExpr::I(val))) => val as i128,
+            CExprKind::ConstantExpr(_, expr, None) | CExprKind::Paren(_, expr) => {
+                self.eval_int(expr)?
+            }
+            CExprKind::DeclRef(_, decl_id, _) => match self[decl_id].kind {
+                CDeclKind::EnumConstant {
+                    value: ConstIntExpr::U(val),
+                    ..
+                } => val as i128,
+                CDeclKind::EnumConstant {
+                    value: ConstIntExpr::I(val),
+                    ..
+                } => val as i128,
+                _ => return None,
+            },
+            CExprKind::UnaryType(_, _, _, _, Some(val)) => val as i128,
+            CExprKind::ImplicitCast(_, expr, kind, _, _)
+            | CExprKind::ExplicitCast(_, expr, kind, _, _) => match kind {
+                CastKind::IntegralCast | CastKind::NoOp | CastKind::IntegralToBoolean => {
+                    self.eval_int(expr)?
+                }
+                _ => return None,
+            },
+            CExprKind::Unary(_, op, expr, _) => {
+                let val = self.eval_int(expr)?;
+                match op {
+                    UnOp::Plus => val,
+                    UnOp::Negate => val.wrapping_neg(),
+                    UnOp::Complement => !val,
+                    UnOp::Not => (val == 0) as i128,
+                    _ => return None,
+                }
+            }
+            CExprKind::Binary(_, op, lhs, rhs, _, _) => {
+                let l = self.eval_int(lhs)?;
+                let r = self.eval_int(rhs)?;
+                match op {
+                    BinOp::Add => l.wrapping_add(r),
+                    BinOp::Subtract => l.wrapping_sub(r),
+                    BinOp::Multiply => l.wrapping_mul(r),
+                    BinOp::Divide => l.checked_div(r)?,
+                    BinOp::Modulus => l.checked_rem(r)?,
+                    BinOp::ShiftLeft => l.checked_shl(self.shift_amount(lhs, r)?)?,
+                    BinOp::ShiftRight => l.checked_shr(self.shift_amount(lhs, r)?)?,
+                    BinOp::BitAnd => l & r,
+                    BinOp::BitOr => l | r,
+                    BinOp::BitXor => l ^ r,
+                    BinOp::Less => (l < r) as i128,
+                    BinOp::Greater => (l > r) as i128,
+                    BinOp::LessEqual => (l <= r) as i128,
+                    BinOp::GreaterEqual => (l >= r) as i128,
+                    BinOp::EqualEqual => (l == r) as i128,
+                    BinOp::NotEqual => (l != r) as i128,
+                    BinOp::And => (l != 0 && r != 0) as i128,
+                    BinOp::Or => (l != 0 || r != 0) as i128,
+                    _ => return None,
+                }
+            }
+            CExprKind::Conditional(_, cond, lhs, rhs) => {
+                if self.eval_int(cond)? != 0 {
+                    self.eval_int(lhs)?
+                } else {
+                    self.eval_int(rhs)?
+                }
+            }
+            _ => return None,
+        };
+        self.wrap_int(val, ty)
+    }
+
+    /// A shift amount that is valid for the left operand's type
+    fn shift_amount(&self, lhs: CExprId, amount: i128) -> Option<u32> {
+        let (width, _) = self.int_repr(self[lhs].kind.get_type()?)?;
+        if amount < 0 || amount >= width as i128 {
+            return None;
+        }
+        Some(amount as u32)
+    }
+}
@@ -159,6 +159,8 @@ fn parse_cast_kind(kind: &str) -> CastKind {
         "BuiltinFnToFnPtr" => CastKind::BuiltinFnToFnPtr,
         "ConstCast" => CastKind::ConstCast,
         "VectorSplat" => CastKind::VectorSplat,
+        // Block pointers are translated as raw pointers
+        "AnyPointerToBlockPointerCast" => CastKind::BitCast,
         k => panic!("Unsupported implicit cast: {}", k),
     }
 }
@@ -181,16 +183,34 @@ fn parse_attributes(attributes: Vec<Value>) -> IndexSet<Attribute> {
             "cold" => {
                 attrs.insert(Attribute::Cold);
             }
+            "dllexport" => {
+                attrs.insert(Attribute::DllExport);
+            }
+            "dllimport" => {
+                attrs.insert(Attribute::DllImport);
+            }
+            "constructor" => {
+                attrs.insert(Attribute::Constructor);
+            }
+            "destructor" => {
+                attrs.insert(Attribute::Destructor);
+            }
             "gnu_inline" => {
                 attrs.insert(Attribute::GnuInline);
             }
             "noinline" => {
                 attrs.insert(Attribute::NoInline);
             }
+            "noreturn" | "_Noreturn" => {
+                attrs.insert(Attribute::NoReturn);
+            }
             "used" => {
                 attrs.insert(Attribute::Used);
             },
             "visibility" => expect_visibility_value = true,
+            "weak" => {
+                attrs.insert(Attribute::Weak);
+            }
             "section" => expect_section_value = true,
             s if expect_section_value => {
                 attrs.insert(Attribute::Section(s.into()));
@@ -315,6 +335,7 @@ impl ConversionContext {
             typed_context: TypedAstContext::new(&untyped_context.files),
             invalid_clang_ast,
         };
+        ctx.typed_context.target_info = untyped_context.target_info.clone();
 
         ctx.convert(untyped_context);
         ctx
@@ -507,6 +528,34 @@ impl ConversionContext {
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
                 }
 
+                // `wchar_t` is only a builtin type in C++; its representation depends on the target
+                TypeTag::TagSWChar | TypeTag::TagUWChar if expected_ty & OTHER_TYPE != 0 => {
+                    let signed = ty_node.tag == TypeTag::TagSWChar;
+                    let kind = match (self.typed_context.target_info.wchar_width, signed) {
+                        (8, true) => CTypeKind::SChar,
+                        (8, false) => CTypeKind::UChar,
+                        (16, true) => CTypeKind::Short,
+                        (16, false) => CTypeKind::UShort,
+                        (32, true) => CTypeKind::Int,
+                        (32, false) => CTypeKind::UInt,
+                        (width, _) => CTypeKind::Unsupported(format!("{}-bit wchar_t", width)),
+                    };
+                    self.add_type(new_id, not_located(kind));
+                    self.processed_nodes.insert(new_id, OTHER_TYPE);
+                }
+
+                // `char16_t` and `char32_t` are builtin in C++ and typedefs of the
+                // `uint_least*_t` types in C, which are exactly 16 and 32 bits wide
+                TypeTag::TagChar16 if expected_ty & OTHER_TYPE != 0 => {
+                    self.add_type(new_id, not_located(CTypeKind::UShort));
+                    self.processed_nodes.insert(new_id, OTHER_TYPE);
+                }
+
+                TypeTag::TagChar32 if expected_ty & OTHER_TYPE != 0 => {
+                    self.add_type(new_id, not_located(CTypeKind::UInt));
+                    self.processed_nodes.insert(new_id, OTHER_TYPE);
+                }
+
                 TypeTag::TagInt if expected_ty & OTHER_TYPE != 0 => {
                     self.add_type(new_id, not_located(CTypeKind::Int));
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
@@ -577,6 +626,14 @@ impl ConversionContext {
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
                 }
 
+                TypeTag::TagBitIntType if expected_ty & OTHER_TYPE != 0 => {
+                    let signed = from_value(ty_node.extras[0].clone()).expect("Expected signedness");
+                    let width = from_value(ty_node.extras[1].clone()).expect("Expected bit width");
+
+                    self.add_type(new_id, not_located(CTypeKind::BitInt(signed, width)));
+                    self.processed_nodes.insert(new_id, OTHER_TYPE);
+                }
+
                 TypeTag::TagInt128 if expected_ty & OTHER_TYPE != 0 => {
                     self.add_type(new_id, not_located(CTypeKind::Int128));
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
@@ -660,8 +717,27 @@ impl ConversionContext {
                         .expect("NoReturn of function type not found");
                     let has_proto = from_value(ty_node.extras[3].clone())
                         .expect("HasProto of function type not found");
-                    let function_ty =
-                        CTypeKind::Function(ret, arguments, is_variadic, is_noreturn, has_proto);
+                    let calling_conv = match expect_opt_str(&ty_node.extras[4])
+                        .expect("Calling convention of function type not found")
+                    {
+                        None => None,
+                        Some("stdcall") => Some(CallingConv::StdCall),
+                        Some("fastcall") => Some(CallingConv::FastCall),
+                        Some("thiscall") => Some(CallingConv::ThisCall),
+                        Some("vectorcall") => Some(CallingConv::VectorCall),
+                        Some("ms_abi") => Some(CallingConv::Win64),
+                        Some("sysv_abi") => Some(CallingConv::SysV64),
+                        Some("aapcs") => Some(CallingConv::Aapcs),
+                        Some(other) => Some(CallingConv::Other(other.to_string())),
+                    };
+                    let function_ty = CTypeKind::Function(
+                        ret,
+                        arguments,
+                        is_variadic,
+                        is_noreturn,
+                        has_proto,
+                        calling_conv,
+                    );
                     self.add_type(new_id, not_located(function_ty));
                     self.processed_nodes.insert(new_id, FUNC_TYPE);
                 }
@@ -676,6 +752,15 @@ impl ConversionContext {
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
                 }
 
+                TypeTag::TagAutoType if expected_ty & TYPE != 0 => {
+                    let deduced_old = from_value(ty_node.extras[0].clone())
+                        .expect("Deduced type of __auto_type not found");
+                    let deduced = self.visit_type(deduced_old);
+
+                    self.add_type(new_id, not_located(CTypeKind::Auto(deduced)));
+                    self.processed_nodes.insert(new_id, OTHER_TYPE);
+                }
+
                 TypeTag::TagTypedefType => {
                     let decl = from_value(ty_node.extras[0].clone()).expect("Typedef decl not found");
                     let decl_new = CDeclId(self.visit_node_type(decl, TYPDEF_DECL));
@@ -776,6 +861,15 @@ impl ConversionContext {
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
                 }
 
+                // Builtin types we have no translation for, like `__bf16`, are
+                // reported by name when a declaration uses them
+                TypeTag::TagTypeUnknown => {
+                    let name = from_value(ty_node.extras[0].clone())
+                        .expect("Unknown type name not found");
+                    self.add_type(new_id, not_located(CTypeKind::Unsupported(name)));
+                    self.processed_nodes.insert(new_id, OTHER_TYPE);
+                }
+
                 TypeTag::TagBuiltinFn => {
                     let ty = CTypeKind::BuiltinFn;
                     self.add_type(new_id, not_located(ty));
@@ -817,6 +911,10 @@ impl ConversionContext {
                 self.typed_context.macro_expansion_text.insert(CExprId(new_id), text.clone());
             }
 
+            if let Some(arg) = &node.macro_argument {
+                self.typed_context.macro_arguments.insert(CExprId(new_id), arg.clone());
+            }
+
             match node.tag {
                 // Statements
                 ASTEntryTag::TagBreakStmt if expected_ty & OTHER_STMT != 0 => {
@@ -1439,7 +1537,9 @@ impl ConversionContext {
                     let arg_ty = from_value(node.extras[1].clone()).expect("expected type id");
                     let arg_ty = self.visit_qualified_type(arg_ty);
 
-                    let operator = CExprKind::UnaryType(ty, kind, expr, arg_ty);
+                    let value = from_value(node.extras[2].clone()).ok();
+
+                    let operator = CExprKind::UnaryType(ty, kind, expr, arg_ty, value);
 
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, operator);
                 }
@@ -1670,6 +1770,27 @@ impl ConversionContext {
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
                 }
 
+                ASTEntryTag::TagGenericSelectionExpr => {
+                    let controlling = node.children[0]
+                        .expect("GenericSelectionExpr controlling expression not found");
+                    let controlling = self.visit_expr(controlling);
+
+                    let result = node.children[1]
+                        .expect("GenericSelectionExpr result expression not found");
+                    let result = self.visit_expr(result);
+
+                    let ty = node.type_id.expect("Expected expression to have type");
+                    let ty = self.visit_qualified_type(ty);
+
+                    let assoc_ty = from_value::<Option<ClangId>>(node.extras[0].clone())
+                        .expect("Expected association type")
+                        .map(|assoc_ty| self.visit_qualified_type(assoc_ty));
+
+                    let e = CExprKind::GenericSelection(ty, controlling, assoc_ty, result);
+
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
+                }
+
                 ASTEntryTag::TagAtomicExpr => {
                     let name = from_value::<String>(node.extras[0].clone())
                         .expect("Expected to find builtin operator name");
@@ -1705,6 +1826,18 @@ impl ConversionContext {
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
                 }
 
+                ASTEntryTag::TagUnsupportedExpr => {
+                    let what = from_value::<String>(node.extras[0].clone())
+                        .expect("Expected to find unsupported construct");
+
+                    let ty = node.type_id.expect("Expected expression to have type");
+                    let ty = self.visit_qualified_type(ty);
+
+                    let e = CExprKind::Unsupported(ty, what);
+
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
+                }
+
                 // Declarations
                 ASTEntryTag::TagFunctionDecl if expected_ty & OTHER_DECL != 0 => {
                     let name = from_value::<String>(node.extras[0].clone())
@@ -1959,7 +2092,19 @@ impl ConversionContext {
                         None
                     };
 
-                    let record = CDeclKind::Union { name, fields };
+                    let manual_alignment =
+                        expect_opt_u64(&node.extras[3]).expect("Expected union alignment");
+                    let platform_byte_size = from_value(node.extras[5].clone()).expect("Expected union size");
+                    let platform_alignment =
+                        from_value(node.extras[6].clone()).expect("Expected union alignment");
+
+                    let record = CDeclKind::Union {
+                        name,
+                        fields,
+                        manual_alignment,
+                        platform_byte_size,
+                        platform_alignment,
+                    };
 
                     self.add_decl(new_id, located(node, record));
                     self.processed_nodes.insert(new_id, RECORD_DECL);
@@ -1996,7 +2141,13 @@ impl ConversionContext {
 
                     let mac_object = match node.tag {
                         ASTEntryTag::TagMacroObjectDef => CDeclKind::MacroObject { name },
-                        ASTEntryTag::TagMacroFunctionDef => CDeclKind::MacroFunction { name },
+                        ASTEntryTag::TagMacroFunctionDef => {
+                            let params = from_value::<Vec<String>>(node.extras[1].clone())
+                                .expect("Function-like macros must have parameters");
+                            let is_variadic = from_value::<bool>(node.extras[2].clone())
+                                .expect("Expected macro variadic flag");
+                            CDeclKind::MacroFunction { name, params, is_variadic }
+                        }
                         _ => unreachable!("Unexpected tag for macro"),
                     };
 
@@ -2009,20 +2160,6 @@ impl ConversionContext {
                     self.typed_context.c_decls_top.push(CDeclId(new_id));
                 }
 
-                ASTEntryTag::TagMacroFunctionDef if expected_ty & MACRO_DECL != 0 => {
-                    let name = from_value::<String>(node.extras[0].clone())
-                        .expect("Macros must have a name");
-
-                    let mac_object = CDeclKind::MacroFunction { name };
-                    self.add_decl(new_id, located(node, mac_object));
-                    self.processed_nodes.insert(new_id, MACRO_DECL);
-
-                    // Macros aren't technically top-level decls, so clang
-                    // doesn't put them in top_nodes, but we do need to process
-                    // them early.
-                    self.typed_context.c_decls_top.push(CDeclId(new_id));
-                }
-
                 ASTEntryTag::TagNonCanonicalDecl if expected_ty & DECL != 0 => {
                     let canonical_decl = node.children[0]
                         .expect("NonCanonicalDecl must point to a canonical decl");
@@ -39,13 +39,13 @@ macro_rules! intos {
 fn immediate_expr_children(kind: &CExprKind) -> Vec<SomeId> {
     use crate::c_ast::CExprKind::*;
     match *kind {
-        BadExpr => vec![],
+        BadExpr | Unsupported(..) => vec![],
         DesignatedInitExpr(..) => vec![], // the relevant information will be found in the semantic initializer
         ShuffleVector(..) | ConvertVector(..) => vec![],
         OffsetOf(..) | Literal(..) | ImplicitValueInit(..) => vec![],
         DeclRef(..) => vec![], // don't follow references back!
         Unary(_, _, subexpr, _) | ConstantExpr(_, subexpr, _) => intos![subexpr],
-        UnaryType(_ty, _op, opt_expr_id, _) => opt_expr_id.iter().map(|&x| x.into()).collect(),
+        UnaryType(_ty, _op, opt_expr_id, _, _) => opt_expr_id.iter().map(|&x| x.into()).collect(),
         Binary(_ty, _op, lhs, rhs, _, _) => intos![lhs, rhs],
         Call(_, f, ref args) => {
             let mut res = intos![f];
@@ -57,6 +57,7 @@ fn immediate_expr_children(kind: &CExprKind) -> Vec<SomeId> {
         ArraySubscript(_, l, r, _) => intos![l, r],
         Conditional(_, c, t, e)
         | Choose(_, c, t, e, _) => intos![c, t, e],
+        GenericSelection(_, c, _, e) => intos![c, e],
         BinaryConditional(_, c, t) => intos![c, t],
         InitList(_, ref xs, _, _) => xs.iter().map(|&x| x.into()).collect(),
         Atomic { ptr, order, val1, order_fail, val2, weak, ..} => {
@@ -80,7 +81,7 @@ fn immediate_expr_children(kind: &CExprKind) -> Vec<SomeId> {
 fn immediate_expr_children_all_types(kind: &CExprKind) -> Vec<SomeId> {
     use crate::c_ast::CExprKind::*;
     match *kind {
-        BadExpr => vec![],
+        BadExpr | Unsupported(..) => vec![],
         DesignatedInitExpr(..) => vec![], // the relevant information will be found in the semantic initializer
         ShuffleVector(_, ref kids) | ConvertVector(_, ref kids) => {
             kids.iter().map(|&x| x.into()).collect()
@@ -91,7 +92,7 @@ fn immediate_expr_children_all_types(kind: &CExprKind) -> Vec<SomeId> {
         OffsetOf(..) | Literal(..) | ImplicitValueInit(..) => vec![],
         DeclRef(..) => vec![], // don't follow references back!
         Unary(_, _, subexpr, _) | ConstantExpr(_, subexpr, _) => intos![subexpr],
-        UnaryType(_ty, _op, opt_expr_id, qty) => {
+        UnaryType(_ty, _op, opt_expr_id, qty, _) => {
             let mut res = intos![qty.ctype];
             if let Some(expr_id) = opt_expr_id {
                 res.push(expr_id.into());
@@ -109,6 +110,7 @@ fn immediate_expr_children_all_types(kind: &CExprKind) -> Vec<SomeId> {
         ArraySubscript(_, l, r, _) => intos![l, r],
         Conditional(_, c, t, e)
         | Choose(_, c, t, e, _) => intos![c, t, e],
+        GenericSelection(_, c, _, e) => intos![c, e],
         BinaryConditional(_, c, t) => intos![c, t],
         InitList(_, ref xs, _, _) => xs.iter().map(|&x| x.into()).collect(),
         Atomic { ptr, order, val1, order_fail, val2, weak, ..} => {
@@ -260,7 +262,8 @@ fn immediate_type_children(kind: &CTypeKind) -> Vec<SomeId> {
         Elaborated(_) => vec![], // These are references to previous definitions
         TypeOfExpr(e) => intos![e],
         Void | Bool | Short | Int | Long | LongLong | UShort | UInt | ULong | ULongLong | SChar
-        | UChar | Char | Double | LongDouble | Float | Int128 | UInt128 | BuiltinFn | Half => {
+        | UChar | Char | Double | LongDouble | Float | Int128 | UInt128 | BuiltinFn | Half
+        | Unsupported(..) | BitInt(..) => {
             vec![]
         }
 
@@ -271,6 +274,7 @@ fn immediate_type_children(kind: &CTypeKind) -> Vec<SomeId> {
         Decayed(ctype)
         | Paren(ctype)
         | TypeOf(ctype)
+        | Auto(ctype)
         | Complex(ctype)
         | ConstantArray(ctype, _)
         | IncompleteArray(ctype) => intos![ctype],
@@ -284,7 +288,7 @@ fn immediate_type_children(kind: &CTypeKind) -> Vec<SomeId> {
             }
             res
         }
-        Function(ret, ref params, _, _, _) => {
+        Function(ret, ref params, ..) => {
             let mut res = intos![ret.ctype];
             for p in params {
                 res.push(p.ctype.into())
@@ -294,7 +298,7 @@ fn immediate_type_children(kind: &CTypeKind) -> Vec<SomeId> {
     }
 }
 
-fn immediate_children(context: &TypedAstContext, s_or_e: SomeId) -> Vec<SomeId> {
+pub fn immediate_children(context: &TypedAstContext, s_or_e: SomeId) -> Vec<SomeId> {
     match s_or_e {
         SomeId::Stmt(stmt_id) => immediate_stmt_children(&context[stmt_id].kind),
         SomeId::Expr(expr_id) => immediate_expr_children(&context[expr_id].kind),
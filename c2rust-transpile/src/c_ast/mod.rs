@@ -4,11 +4,13 @@ use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::cmp::Ordering;
 use std::fmt::{self, Debug, Display};
+use std::fs;
+use std::iter;
 use std::mem;
 use std::ops::Index;
 use std::path::{Path, PathBuf};
 
-pub use c2rust_ast_exporter::clang_ast::{SrcFile, SrcLoc, SrcSpan, BuiltinVaListKind};
+pub use c2rust_ast_exporter::clang_ast::{SrcFile, SrcLoc, SrcSpan, BuiltinVaListKind, TargetInfo};
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone)]
 pub struct CTypeId(pub u64);
@@ -35,6 +37,7 @@ pub type CEnumConstantId = CDeclId; // Enum's need to point to child 'DeclKind::
 pub use self::conversion::*;
 pub use self::print::Printer;
 
+mod const_eval;
 mod conversion;
 pub mod iterators;
 mod print;
@@ -44,9 +47,9 @@ use iterators::{DFNodes, SomeId};
 /// AST context containing all of the nodes in the Clang AST
 #[derive(Debug, Clone)]
 pub struct TypedAstContext {
-    c_types: HashMap<CTypeId, CType>,
-    c_exprs: HashMap<CExprId, CExpr>,
-    c_stmts: HashMap<CStmtId, CStmt>,
+    c_types: IndexMap<CTypeId, CType>,
+    c_exprs: IndexMap<CExprId, CExpr>,
+    c_stmts: IndexMap<CStmtId, CStmt>,
 
     // Decls require a stable iteration order as this map will be
     // iterated over export all defined types during translation.
@@ -54,7 +57,7 @@ pub struct TypedAstContext {
 
     pub c_decls_top: Vec<CDeclId>,
     pub c_main: Option<CDeclId>,
-    pub parents: HashMap<CDeclId, CDeclId>, // record fields and enum constants
+    pub parents: IndexMap<CDeclId, CDeclId>, // record fields and enum constants
 
     // Mapping from FileId to SrcFile. Deduplicated by file path.
     files: Vec<SrcFile>,
@@ -67,14 +70,18 @@ pub struct TypedAstContext {
     include_map: Vec<Vec<SrcLoc>>,
 
     // map expressions to the stack of macros they were expanded from
-    pub macro_invocations: HashMap<CExprId, Vec<CDeclId>>,
+    pub macro_invocations: IndexMap<CExprId, Vec<CDeclId>>,
 
     // map macro decls to the expressions they expand to
-    pub macro_expansions: HashMap<CDeclId, Vec<CExprId>>,
+    pub macro_expansions: IndexMap<CDeclId, Vec<CExprId>>,
 
     // map expressions to the text of the macro invocation they expanded from,
     // if any
-    pub macro_expansion_text: HashMap<CExprId, String>,
+    pub macro_expansion_text: IndexMap<CExprId, String>,
+
+    // map expressions that were substituted for a function-like macro
+    // parameter to the parameter name and the spelling of the argument
+    pub macro_arguments: IndexMap<CExprId, (String, String)>,
 
     pub comments: Vec<Located<String>>,
 
@@ -83,12 +90,20 @@ pub struct TypedAstContext {
     pub prenamed_decls: IndexMap<CDeclId, CDeclId>,
 
     pub va_list_kind: BuiltinVaListKind,
+
+    // the target the C sources were compiled for, which determines the
+    // widths of the builtin integral types
+    pub target_info: TargetInfo,
+
+    // Expressions that types refer to, which are kept when function bodies are
+    // released. Indexed once, by the first release.
+    type_exprs: Option<IndexSet<CExprId>>,
 }
 
 /// Comments associated with a typed AST context
 #[derive(Debug, Clone)]
 pub struct CommentContext {
-    comments_by_file: HashMap<FileId, RefCell<Vec<Located<String>>>>,
+    comments_by_file: IndexMap<FileId, RefCell<Vec<Located<String>>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -97,6 +112,16 @@ pub struct DisplaySrcSpan {
     loc: SrcSpan,
 }
 
+impl DisplaySrcSpan {
+    /// The line the span begins on, its number and the column the span begins
+    /// at, if the source file is still readable
+    pub fn source_line(&self) -> Option<(u64, u64, String)> {
+        let contents = fs::read_to_string(self.file.as_ref()?).ok()?;
+        let line = contents.lines().nth(self.loc.begin_line.checked_sub(1)? as usize)?;
+        Some((self.loc.begin_line, self.loc.begin_column, line.to_string()))
+    }
+}
+
 impl Display for DisplaySrcSpan {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Some(ref file) = self.file {
@@ -109,6 +134,9 @@ impl Display for DisplaySrcSpan {
 
 pub type FileId = usize;
 
+/// File extensions that indicate a C source file rather than a header
+const SOURCE_FILE_EXTENSIONS: &[&str] = &["c", "i"];
+
 /// Represents some AST node possibly with source location information bundled with it
 #[derive(Debug, Clone)]
 pub struct Located<T> {
@@ -157,24 +185,27 @@ impl TypedAstContext {
         }
 
         TypedAstContext {
-            c_types: HashMap::new(),
-            c_exprs: HashMap::new(),
+            c_types: IndexMap::new(),
+            c_exprs: IndexMap::new(),
             c_decls: IndexMap::new(),
-            c_stmts: HashMap::new(),
+            c_stmts: IndexMap::new(),
 
             c_decls_top: Vec::new(),
             c_main: None,
             files,
             file_map,
             include_map,
-            parents: HashMap::new(),
-            macro_invocations: HashMap::new(),
-            macro_expansions: HashMap::new(),
-            macro_expansion_text: HashMap::new(),
+            parents: IndexMap::new(),
+            macro_invocations: IndexMap::new(),
+            macro_expansions: IndexMap::new(),
+            macro_expansion_text: IndexMap::new(),
+            macro_arguments: IndexMap::new(),
 
             comments: vec![],
             prenamed_decls: IndexMap::new(),
             va_list_kind: BuiltinVaListKind::CharPtrBuiltinVaList,
+            target_info: TargetInfo::default(),
+            type_exprs: None,
         }
     }
 
@@ -195,6 +226,13 @@ impl TypedAstContext {
         self.files[id].path.as_ref().map(|p| p.as_path())
     }
 
+    /// Path of the file a source location is in
+    pub fn get_span_path<'a>(&'a self, loc: &SrcSpan) -> Option<&'a Path> {
+        self.file_map
+            .get(loc.fileid as usize)
+            .and_then(|&id| self.get_file_path(id))
+    }
+
 
     pub fn compare_src_locs(&self, a: &SrcLoc, b: &SrcLoc) -> Ordering {
         /// Compare `self` with `other`, without regard to file id
@@ -223,6 +261,17 @@ impl TypedAstContext {
         }
     }
 
+    /// Is this file a non-header source (e.g. a `.c` file) that was textually
+    /// `#include`d into the translation unit, as happens in unity builds?
+    pub fn is_included_source(&self, file: FileId) -> bool {
+        let is_source = self
+            .get_file_path(file)
+            .and_then(|path| path.extension())
+            .and_then(|ext| ext.to_str())
+            .map_or(false, |ext| SOURCE_FILE_EXTENSIONS.contains(&ext));
+        is_source && !self.include_map[file].is_empty()
+    }
+
     pub fn get_file_include_line_number(&self, file: FileId) -> Option<u64> {
         self.include_map[file].first().map(|loc| loc.line)
     }
@@ -333,6 +382,36 @@ impl TypedAstContext {
         }
     }
 
+    /// Whether the translation target follows the MSVC ABI, e.g. `x86_64-pc-windows-msvc`
+    pub fn is_msvc_target(&self) -> bool {
+        self.target_info.triple.contains("-msvc")
+    }
+
+    /// Width in bits of a builtin integral type on the translation target
+    pub fn integral_type_width(&self, kind: &CTypeKind) -> Option<u64> {
+        let target = &self.target_info;
+        match *kind {
+            CTypeKind::Bool | CTypeKind::Char | CTypeKind::SChar | CTypeKind::UChar => Some(8),
+            CTypeKind::Short | CTypeKind::UShort => Some(target.short_width),
+            CTypeKind::Int | CTypeKind::UInt => Some(target.int_width),
+            CTypeKind::Long | CTypeKind::ULong => Some(target.long_width),
+            CTypeKind::LongLong | CTypeKind::ULongLong => Some(target.long_long_width),
+            CTypeKind::Int128 | CTypeKind::UInt128 => Some(128),
+            ref kind @ CTypeKind::BitInt(..) => kind.bit_int_storage_width(),
+            _ => None,
+        }
+    }
+
+    /// Signedness of an integral type on the translation target. Unlike
+    /// `CTypeKind::is_signed_integral_type`, this knows whether plain `char`
+    /// is signed.
+    pub fn is_signed_integral(&self, kind: &CTypeKind) -> bool {
+        match *kind {
+            CTypeKind::Char => self.target_info.char_is_signed,
+            ref kind => kind.is_signed_integral_type(),
+        }
+    }
+
     /// Predicate for pointers to types that are used to implement C's `va_list`.
     pub fn is_va_list(&self, typ: CTypeId) -> bool {
         match self.va_list_kind {
@@ -438,6 +517,7 @@ impl TypedAstContext {
             CTypeKind::Elaborated(ty) => self.resolve_type_id(ty),
             CTypeKind::Decayed(ty) => self.resolve_type_id(ty),
             CTypeKind::TypeOf(ty) => self.resolve_type_id(ty),
+            CTypeKind::Auto(ty) => self.resolve_type_id(ty),
             CTypeKind::Paren(ty) => self.resolve_type_id(ty),
             CTypeKind::Typedef(decl) => match self.index(decl).kind {
                 CDeclKind::Typedef { typ: ty, .. } => self.resolve_type_id(ty.ctype),
@@ -447,6 +527,81 @@ impl TypedAstContext {
         }
     }
 
+    /// Collect the qualifiers of a type, including those hidden behind typedefs, `typeof`, and
+    /// other sugar (e.g. the `const` in `typedef const int cint; cint *p;`).
+    pub fn resolve_qualifiers(&self, typ: CQualTypeId) -> Qualifiers {
+        let inner = match self.index(typ.ctype).kind {
+            CTypeKind::Attributed(ty, _) => self.resolve_qualifiers(ty),
+            CTypeKind::Elaborated(ty)
+            | CTypeKind::TypeOf(ty)
+            | CTypeKind::Auto(ty)
+            | CTypeKind::Paren(ty) => self.resolve_qualifiers(CQualTypeId::new(ty)),
+            CTypeKind::Typedef(decl) => match self.index(decl).kind {
+                CDeclKind::Typedef { typ: ty, .. } => self.resolve_qualifiers(ty),
+                _ => panic!("Typedef decl did not point to a typedef"),
+            },
+            _ => Qualifiers::default(),
+        };
+        typ.qualifiers.and(inner)
+    }
+
+    /// Collect the enums that some type refers to, as opposed to the enums only being
+    /// declared for the sake of their constants (as in `enum { FOO = 1, BAR = 2 };`).
+    pub fn enums_used_as_types(&self) -> HashSet<CEnumId> {
+        let mut enums = HashSet::new();
+        let mut add = |ty: CTypeId| {
+            if let CTypeKind::Enum(id) = self.index(ty).kind {
+                enums.insert(id);
+            }
+        };
+        for ty in self.c_types.values() {
+            match ty.kind {
+                CTypeKind::Elaborated(ty)
+                | CTypeKind::Decayed(ty)
+                | CTypeKind::Paren(ty)
+                | CTypeKind::TypeOf(ty)
+                | CTypeKind::Auto(ty)
+                | CTypeKind::ConstantArray(ty, _)
+                | CTypeKind::IncompleteArray(ty)
+                | CTypeKind::VariableArray(ty, _) => add(ty),
+                CTypeKind::Pointer(ty)
+                | CTypeKind::Attributed(ty, _)
+                | CTypeKind::BlockPointer(ty)
+                | CTypeKind::Vector(ty, _) => add(ty.ctype),
+                CTypeKind::Function(ret, ref params, ..) => {
+                    add(ret.ctype);
+                    params.iter().for_each(|param| add(param.ctype));
+                }
+                _ => {}
+            }
+        }
+        for decl in self.c_decls.values() {
+            match decl.kind {
+                CDeclKind::Typedef { typ, .. }
+                | CDeclKind::Variable { typ, .. }
+                | CDeclKind::Field { typ, .. } => add(typ.ctype),
+                _ => {}
+            }
+        }
+        enums
+    }
+
+    /// Collect the structs that are passed to or returned from a function by value, either
+    /// by a function declaration or through a function pointer type.
+    pub fn structs_passed_by_value(&self) -> HashSet<CRecordId> {
+        let mut records = HashSet::new();
+        for ty in self.c_types.values() {
+            if let CTypeKind::Function(ret, ref params, ..) = ty.kind {
+                for qty in iter::once(&ret).chain(params) {
+                    if let CTypeKind::Struct(record_id) = self.resolve_type(qty.ctype).kind {
+                        records.insert(record_id);
+                    }
+                }
+            }
+        }
+        records
+    }
+
     pub fn resolve_type(&self, typ: CTypeId) -> &CType {
         let resolved_typ_id = self.resolve_type_id(typ);
         self.index(resolved_typ_id)
@@ -457,6 +612,7 @@ impl TypedAstContext {
     pub fn is_expr_pure(&self, expr: CExprId) -> bool {
         match self.index(expr).kind {
             CExprKind::BadExpr |
+            CExprKind::Unsupported(..) |
             CExprKind::ShuffleVector(..) |
             CExprKind::ConvertVector(..) |
             CExprKind::Call(..) |
@@ -474,10 +630,15 @@ impl TypedAstContext {
 
             CExprKind::Literal(_, _) |
             CExprKind::DeclRef(_, _, _) |
-            CExprKind::UnaryType(_, _, _, _) |
+            CExprKind::UnaryType(_, _, _, _, _) |
             CExprKind::OffsetOf(..) |
             CExprKind::ConstantExpr(..) => true,
 
+            // Reading a volatile lvalue is a side effect
+            CExprKind::ImplicitCast(_, e, CastKind::LValueToRValue, _, _)
+                if self.index(e).kind.get_qual_type()
+                    .map_or(false, |qty| self.resolve_qualifiers(qty).is_volatile) => false,
+
             CExprKind::DesignatedInitExpr(_,_,e) |
             CExprKind::ImplicitCast(_, e, _, _, _) |
             CExprKind::ExplicitCast(_, e, _, _, _) |
@@ -493,33 +654,67 @@ impl TypedAstContext {
             CExprKind::Conditional(_, c, lhs, rhs) => self.is_expr_pure(c) && self.is_expr_pure(lhs) && self.is_expr_pure(rhs),
             CExprKind::BinaryConditional(_, c, rhs) => self.is_expr_pure(c) && self.is_expr_pure(rhs),
             CExprKind::Choose(_, c, lhs, rhs, _) => self.is_expr_pure(c) && self.is_expr_pure(lhs) && self.is_expr_pure(rhs),
+            // The controlling expression is never evaluated
+            CExprKind::GenericSelection(_, _, _, e) => self.is_expr_pure(e),
         }
     }
 
     // Pessimistically try to check if an expression doesn't return. If it does, or we can't tell
     /// that it doesn't, return `false`.
     pub fn expr_diverges(&self, expr_id: CExprId) -> bool {
-        let func_id = match self.index(expr_id).kind {
-            CExprKind::Call(_, func_id, _) => func_id,
-            _ => return false,
-        };
+        match self.index(expr_id).kind {
+            CExprKind::Paren(_, e)
+            | CExprKind::ExplicitCast(_, e, CastKind::ToVoid, _, _)
+            | CExprKind::ImplicitCast(_, e, CastKind::ToVoid, _, _) => self.expr_diverges(e),
+            CExprKind::Binary(_, BinOp::Comma, lhs, rhs, _, _) => {
+                self.expr_diverges(lhs) || self.expr_diverges(rhs)
+            }
+            CExprKind::Conditional(_, _, lhs, rhs) => {
+                self.expr_diverges(lhs) && self.expr_diverges(rhs)
+            }
+            CExprKind::Call(_, func_id, _) => self.calls_noreturn(func_id),
+            _ => false,
+        }
+    }
+
+    /// Whether a callee is declared `_Noreturn`, has a `noreturn` function type or is a
+    /// builtin that never returns, like `__builtin_unreachable`
+    fn calls_noreturn(&self, func_id: CExprId) -> bool {
+        if let CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _)
+        | CExprKind::ImplicitCast(_, fexp, CastKind::BuiltinFnToFnPtr, _, _) =
+            self.index(func_id).kind
+        {
+            if let CExprKind::DeclRef(_, decl_id, _) = self.index(fexp).kind {
+                if let CDeclKind::Function { ref attrs, ref name, .. } = self.index(decl_id).kind {
+                    if attrs.contains(&Attribute::NoReturn)
+                        || name == "__builtin_unreachable"
+                        || name == "__builtin_trap"
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
 
         let type_id = match self[func_id].kind.get_type() {
             None => return false,
             Some(t) => t,
         };
-        let pointed_id = match self.index(type_id).kind {
+        let pointed_id = match self.resolve_type(type_id).kind {
             CTypeKind::Pointer(pointer_qualtype) => pointer_qualtype.ctype,
             _ => return false,
         };
 
-        match self.index(pointed_id).kind {
-            CTypeKind::Function(_, _, _, no_return, _) => no_return,
+        match self.resolve_type(pointed_id).kind {
+            CTypeKind::Function(_, _, _, no_return, _, _) => no_return,
             _ => false,
         }
     }
 
-    pub fn prune_unused_decls(&mut self) {
+    /// Omit the declarations that no root uses. Besides the declarations that
+    /// might be visible from another compilation unit, the declarations whose
+    /// names `is_root` accepts are roots.
+    pub fn prune_unused_decls<F: Fn(&str) -> bool>(&mut self, is_root: F) {
         // Starting from a set of root declarations, walk each one to find declarations it
         // depends on. Then walk each of those, recursively.
 
@@ -550,12 +745,26 @@ impl TypedAstContext {
                     to_walk.push(decl_id);
                     used.insert(decl_id);
                 }
+                // Functions and variables exported from a DLL are used by its clients
                 CDeclKind::Variable { ref attrs, .. } | CDeclKind::Function { ref attrs, .. }
-                    if attrs.contains(&Attribute::Used) =>
+                    if attrs.contains(&Attribute::Used) || attrs.contains(&Attribute::DllExport) =>
                 {
                     to_walk.push(decl_id);
                     used.insert(decl_id);
                 }
+                // Constructors and destructors are invoked by the loader, so
+                // they are live even if nothing references them.
+                CDeclKind::Function { ref attrs, .. }
+                    if attrs.contains(&Attribute::Constructor)
+                        || attrs.contains(&Attribute::Destructor) =>
+                {
+                    to_walk.push(decl_id);
+                    used.insert(decl_id);
+                }
+                _ if decl.kind.get_name().map_or(false, |name| is_root(name)) => {
+                    to_walk.push(decl_id);
+                    used.insert(decl_id);
+                }
                 _ => {}
             }
         }
@@ -643,10 +852,135 @@ impl TypedAstContext {
         self.c_decls_top.retain(|x| used.contains(x));
     }
 
+    /// Map each object-like macro whose expansions are all implicitly
+    /// converted to the same numeric type to those conversions. Translating
+    /// such a macro at the type of its uses avoids a cast at every use.
+    pub fn macro_use_sites(&self) -> HashMap<CDeclId, Vec<CExprId>> {
+        // Expansion to the conversion of it and the type converted to
+        let mut conversions = HashMap::new();
+        for (&expr_id, expr) in &self.c_exprs {
+            if let CExprKind::ImplicitCast(qty, child, kind, _, _) = expr.kind {
+                match kind {
+                    CastKind::IntegralCast
+                    | CastKind::IntegralToFloating
+                    | CastKind::FloatingCast
+                    | CastKind::FloatingToIntegral => {}
+                    _ => continue,
+                }
+                if self.macro_invocations.contains_key(&child) {
+                    conversions.insert(child, (expr_id, self.resolve_type_id(qty.ctype)));
+                }
+            }
+        }
+
+        self.macro_expansions
+            .iter()
+            .filter(|(mac_id, _)| match self.index(**mac_id).kind {
+                CDeclKind::MacroObject { .. } => true,
+                _ => false,
+            })
+            .filter_map(|(&mac_id, expansions)| {
+                let mut use_ty = None;
+                let sites = expansions
+                    .iter()
+                    .map(|expansion| {
+                        let (site, ty) = conversions.get(expansion)?;
+                        if *use_ty.get_or_insert(*ty) == *ty {
+                            Some(*site)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some((mac_id, sites))
+            })
+            .collect()
+    }
+
+    /// Drop the statements and expressions in the body of a function once it has been
+    /// translated, so that large translation units don't hold on to the C AST of every
+    /// function until the end. Expressions that types refer to, such as the operand of
+    /// `typeof`, are kept since types are shared between functions.
+    pub fn release_function_body(&mut self, decl_id: CDeclId) {
+        let body = match self.c_decls.get(&decl_id).map(|decl| &decl.kind) {
+            Some(&CDeclKind::Function { body: Some(body), .. }) => body,
+            _ => return,
+        };
+
+        if self.type_exprs.is_none() {
+            let type_exprs = self
+                .c_types
+                .values()
+                .filter_map(|ty| match ty.kind {
+                    CTypeKind::TypeOfExpr(expr_id) => Some(expr_id),
+                    CTypeKind::VariableArray(_, Some(expr_id)) => Some(expr_id),
+                    _ => None,
+                })
+                .collect();
+            self.type_exprs = Some(type_exprs);
+        }
+        let type_exprs = self.type_exprs.as_ref().unwrap();
+
+        let mut stmts = vec![];
+        let mut exprs = vec![];
+        let mut to_walk = vec![SomeId::Stmt(body)];
+        while let Some(id) = to_walk.pop() {
+            match id {
+                SomeId::Stmt(stmt_id) if self.c_stmts.contains_key(&stmt_id) => {
+                    stmts.push(stmt_id)
+                }
+                SomeId::Expr(expr_id)
+                    if self.c_exprs.contains_key(&expr_id) && !type_exprs.contains(&expr_id) =>
+                {
+                    exprs.push(expr_id)
+                }
+                // Walk through local declarations to reach their initializers,
+                // but keep the declarations themselves
+                SomeId::Decl(_) => {}
+                _ => continue,
+            }
+            to_walk.extend(
+                iterators::immediate_children(self, id)
+                    .into_iter()
+                    .filter(|child| match (id, child) {
+                        (_, SomeId::Type(_)) => false,
+                        // Local declarations of functions can lead to the body of another
+                        // function, so only follow initializers
+                        (SomeId::Decl(_), SomeId::Expr(_)) => true,
+                        (SomeId::Decl(_), _) => false,
+                        _ => true,
+                    }),
+            );
+        }
+
+        for stmt_id in stmts {
+            self.c_stmts.swap_remove(&stmt_id);
+        }
+        for expr_id in exprs {
+            self.c_exprs.swap_remove(&expr_id);
+            self.macro_expansion_text.swap_remove(&expr_id);
+            self.macro_arguments.swap_remove(&expr_id);
+        }
+    }
+
+    /// Treat every `long double` as a `double`. Returns whether any `long double` types
+    /// were present.
+    pub fn demote_long_double(&mut self) -> bool {
+        let mut demoted = false;
+        for ty in self.c_types.values_mut() {
+            if let CTypeKind::LongDouble = ty.kind {
+                ty.kind = CTypeKind::Double;
+                demoted = true;
+            }
+        }
+        demoted
+    }
+
     pub fn sort_top_decls(&mut self) {
-        // Group and sort declarations by file and by position
+        // Group and sort declarations by file and by position. Declarations at
+        // the same position keep the order clang emitted them in.
         let mut decls_top = mem::replace(&mut self.c_decls_top, vec![]);
-        decls_top.sort_unstable_by(|a, b| {
+        decls_top.sort_by(|a, b| {
             let a = self.index(*a);
             let b = self.index(*b);
             match (&a.loc, &b.loc) {
@@ -694,13 +1028,13 @@ impl TypedAstContext {
 impl CommentContext {
     pub fn empty() -> CommentContext {
         CommentContext {
-            comments_by_file: HashMap::new(),
+            comments_by_file: IndexMap::new(),
         }
     }
 
     /// Build a CommentContext from the comments in this `ast_context`
     pub fn new(ast_context: &mut TypedAstContext) -> CommentContext {
-        let mut comments_by_file: HashMap<FileId, Vec<Located<String>>> = HashMap::new();
+        let mut comments_by_file: IndexMap<FileId, Vec<Located<String>>> = IndexMap::new();
 
         // Group comments by their file
         for comment in &ast_context.comments {
@@ -791,7 +1125,7 @@ impl CommentContext {
     }
 
     pub fn get_remaining_comments(&mut self, file_id: FileId) -> Vec<String> {
-        match self.comments_by_file.remove(&file_id) {
+        match self.comments_by_file.swap_remove(&file_id) {
             Some(comments) => comments.into_inner().into_iter().map(|c| c.kind).collect(),
             None => vec![],
         }
@@ -920,6 +1254,9 @@ pub enum CDeclKind {
     Union {
         name: Option<String>,
         fields: Option<Vec<CFieldId>>,
+        manual_alignment: Option<u64>,
+        platform_byte_size: u64,
+        platform_alignment: u64,
     },
 
     // Field
@@ -938,7 +1275,8 @@ pub enum CDeclKind {
 
     MacroFunction {
         name: String,
-        // replacements: Vec<CExprId>,
+        params: Vec<String>,
+        is_variadic: bool,
     },
 
     NonCanonicalDecl {
@@ -996,7 +1334,9 @@ pub enum CExprKind {
     Unary(CQualTypeId, UnOp, CExprId, LRValue),
 
     // Unary type operator.
-    UnaryType(CQualTypeId, UnTypeOp, Option<CExprId>, CQualTypeId),
+    // Fields: result type, operator, expression operand, operand type, clang-computed value
+    // (absent for variable-length array operands)
+    UnaryType(CQualTypeId, UnTypeOp, Option<CExprId>, CQualTypeId, Option<u64>),
 
     // Offsetof expression.
     OffsetOf(CQualTypeId, OffsetOfKind),
@@ -1071,6 +1411,10 @@ pub enum CExprKind {
     // GNU choose expr. Condition, true expr, false expr, was condition true?
     Choose(CQualTypeId, CExprId, CExprId, CExprId, bool),
 
+    // C11 generic selection. Controlling expr, type of the selected
+    // association (`None` for `default`), selected expr
+    GenericSelection(CQualTypeId, CExprId, Option<CQualTypeId>, CExprId),
+
     // GNU/C11 atomic expr
     Atomic {
         typ: CQualTypeId,
@@ -1083,6 +1427,10 @@ pub enum CExprKind {
         weak: Option<CExprId>,
     },
 
+    // Expression of an extension we can't translate, like a block literal,
+    // with a description of the construct for error messages
+    Unsupported(CQualTypeId, String),
+
     BadExpr,
 }
 
@@ -1111,7 +1459,7 @@ impl CExprKind {
             CExprKind::Literal(ty, _)
             | CExprKind::OffsetOf(ty, _)
             | CExprKind::Unary(ty, _, _, _)
-            | CExprKind::UnaryType(ty, _, _, _)
+            | CExprKind::UnaryType(ty, _, _, _, _)
             | CExprKind::Binary(ty, _, _, _, _, _)
             | CExprKind::ImplicitCast(ty, _, _, _, _)
             | CExprKind::ExplicitCast(ty, _, _, _, _)
@@ -1133,6 +1481,8 @@ impl CExprKind {
             | CExprKind::DesignatedInitExpr(ty, _, _)
             | CExprKind::ConstantExpr(ty, _, _) => Some(ty),
             | CExprKind::Choose(ty, _, _, _, _)
+            | CExprKind::GenericSelection(ty, _, _, _)
+            | CExprKind::Unsupported(ty, _)
             | CExprKind::Atomic{typ: ty, ..} => Some(ty),
         }
     }
@@ -1521,12 +1871,16 @@ pub enum CTypeKind {
     TypeOf(CTypeId),
     TypeOfExpr(CExprId),
 
+    // Type of a variable declared `__auto_type` (GCC extension), deduced from its initializer
+    Auto(CTypeId),
+
     // Function type (6.7.5.3)
     //
     // Note a function taking no arguments should have one `void` argument. Functions without any
     // arguments and in K&R format.
-    // Flags: is_variable_argument, is_noreturn, has prototype
-    Function(CQualTypeId, Vec<CQualTypeId>, bool, bool, bool),
+    // Flags: is_variable_argument, is_noreturn, has prototype, followed by the calling
+    // convention if it isn't the default C one
+    Function(CQualTypeId, Vec<CQualTypeId>, bool, bool, bool, Option<CallingConv>),
 
     // Type definition type (6.7.7)
     Typedef(CTypedefId),
@@ -1555,7 +1909,15 @@ pub enum CTypeKind {
 
     Vector(CQualTypeId, usize),
 
+    // 16-bit floating point type, `__fp16` or `_Float16`
     Half,
+
+    // Builtin type we have no translation for, by its C name, e.g. `__bf16`
+    Unsupported(String),
+
+    // Bit-precise integer type `_BitInt(N)` (C2x) / `_ExtInt(N)` (Clang extension)
+    // Fields: is_signed, bit width
+    BitInt(bool, u64),
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -1565,6 +1927,28 @@ pub enum Designator {
     Field(CFieldId),
 }
 
+/// Calling conventions functions can be declared with, e.g. with `__stdcall`,
+/// other than the default C one
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum CallingConv {
+    /// __stdcall, the convention of the Win32 API
+    StdCall,
+    /// __fastcall
+    FastCall,
+    /// __thiscall
+    ThisCall,
+    /// __vectorcall
+    VectorCall,
+    /// __attribute__((ms_abi))
+    Win64,
+    /// __attribute__((sysv_abi))
+    SysV64,
+    /// __attribute__((pcs("aapcs")))
+    Aapcs,
+    /// Conventions Rust has no ABI for, e.g. `__regcall`, by their clang name
+    Other(String),
+}
+
 /// Enumeration of supported attributes for Declarations
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Attribute {
@@ -1574,10 +1958,19 @@ pub enum Attribute {
     AlwaysInline,
     /// __attribute__((cold, __cold__))
     Cold,
+    /// __attribute__((constructor, __constructor__))
+    Constructor,
+    /// __attribute__((destructor, __destructor__))
+    Destructor,
+    /// __declspec(dllexport)
+    DllExport,
+    /// __declspec(dllimport)
+    DllImport,
     /// __attribute__((gnu_inline, __gnu_inline__))
     GnuInline,
     /// __attribute__((no_inline, __no_inline__))
     NoInline,
+    /// __attribute__((noreturn)) or _Noreturn
     NoReturn,
     NotNull,
     Nullable,
@@ -1587,6 +1980,8 @@ pub enum Attribute {
     Used,
     /// __attribute((visibility("hidden")))
     Visibility(String),
+    /// __attribute__((weak, __weak__))
+    Weak,
 }
 
 impl CTypeKind {
@@ -1624,6 +2019,7 @@ impl CTypeKind {
             CTypeKind::ULong => true,
             CTypeKind::ULongLong => true,
             CTypeKind::UInt128 => true,
+            CTypeKind::BitInt(signed, _) => !signed,
             _ => false,
         }
     }
@@ -1637,12 +2033,45 @@ impl CTypeKind {
             CTypeKind::Long => true,
             CTypeKind::LongLong => true,
             CTypeKind::Int128 => true,
+            CTypeKind::BitInt(signed, _) => signed,
             _ => false,
         }
     }
 
+    /// Integer conversion rank of a standard integer type (C11 6.3.1.1), which
+    /// orders the types by width independent of the target platform
+    pub fn integer_conversion_rank(&self) -> Option<u32> {
+        match *self {
+            CTypeKind::Bool => Some(1),
+            CTypeKind::Char | CTypeKind::SChar | CTypeKind::UChar => Some(2),
+            CTypeKind::Short | CTypeKind::UShort => Some(3),
+            CTypeKind::Int | CTypeKind::UInt => Some(4),
+            CTypeKind::Long | CTypeKind::ULong => Some(5),
+            CTypeKind::LongLong | CTypeKind::ULongLong => Some(6),
+            CTypeKind::Int128 | CTypeKind::UInt128 => Some(7),
+            _ => None,
+        }
+    }
+
+    /// Width in bits of the smallest Rust integer able to hold a `_BitInt(N)`,
+    /// or `None` if this is not a bit-precise integer of a supported width.
+    pub fn bit_int_storage_width(&self) -> Option<u64> {
+        match *self {
+            CTypeKind::BitInt(_, width) => match width {
+                0..=8 => Some(8),
+                9..=16 => Some(16),
+                17..=32 => Some(32),
+                33..=64 => Some(64),
+                65..=128 => Some(128),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     pub fn is_floating_type(&self) -> bool {
         match *self {
+            CTypeKind::Half => true,
             CTypeKind::Float => true,
             CTypeKind::Double => true,
             CTypeKind::LongDouble => true,
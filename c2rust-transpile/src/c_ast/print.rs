@@ -75,6 +75,7 @@ impl<W: Write> Printer<W> {
     ) -> Result<()> {
         match context.c_exprs.get(&expr_id).map(|l| &l.kind) {
             Some(&CExprKind::BadExpr) => self.writer.write_all(b"BAD"),
+            Some(&CExprKind::Unsupported(..)) => self.writer.write_all(b"UNSUPPORTED"),
             Some(&CExprKind::DesignatedInitExpr(..)) => {
                 self.writer.write_all(b"DESIGNATED INIT EXPR")
             }
@@ -88,7 +89,7 @@ impl<W: Write> Printer<W> {
                 Ok(())
             }
 
-            Some(&CExprKind::UnaryType(_, kind, opt_expr, arg_ty)) => {
+            Some(&CExprKind::UnaryType(_, kind, opt_expr, arg_ty, _)) => {
                 let kind_str = match kind {
                     UnTypeOp::SizeOf => b"sizeof(".as_ref(),
                     UnTypeOp::AlignOf => b"alignof(".as_ref(),
@@ -227,6 +228,19 @@ impl<W: Write> Printer<W> {
                 self.writer.write_all(b")")
             }
 
+            Some(&CExprKind::GenericSelection(_, controlling, assoc_ty, expr)) => {
+                self.writer.write_all(b"_Generic(")?;
+                self.print_expr(controlling, context)?;
+                self.writer.write_all(b", ")?;
+                match assoc_ty {
+                    Some(assoc_ty) => self.print_qtype(assoc_ty, None, context)?,
+                    None => self.writer.write_all(b"default")?,
+                }
+                self.writer.write_all(b": ")?;
+                self.print_expr(expr, context)?;
+                self.writer.write_all(b")")
+            }
+
             Some(&CExprKind::Atomic{ref name, ptr, order, val1, order_fail, val2, weak, ..}) => {
                 self.writer.write_fmt(format_args!("{}(", name))?;
 
@@ -726,9 +740,14 @@ impl<W: Write> Printer<W> {
 
             Some(&CDeclKind::MacroFunction {
                 ref name,
-                ..
+                ref params,
+                is_variadic,
             }) => {
-                self.writer.write_fmt(format_args!("#define {}() ", name))?;
+                let mut params = params.clone();
+                if is_variadic {
+                    params.push("...".to_string());
+                }
+                self.writer.write_fmt(format_args!("#define {}({}) ", name, params.join(", ")))?;
 
                 Ok(())
             }
@@ -829,6 +848,11 @@ impl<W: Write> Printer<W> {
                     &CTypeKind::LongDouble => self.writer.write_all(b"long double"),
                     &CTypeKind::Int128 => self.writer.write_all(b"__int128"),
                     &CTypeKind::UInt128 => self.writer.write_all(b"unsigned __int128"),
+                    &CTypeKind::BitInt(signed, width) => self.writer.write_fmt(format_args!(
+                        "{}_BitInt({})",
+                        if signed { "" } else { "unsigned " },
+                        width
+                    )),
                     _ => unimplemented!("Printer::print_type({:?})", ty),
                 }?;
 
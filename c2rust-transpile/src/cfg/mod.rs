@@ -553,6 +553,11 @@ pub enum ImplicitReturnType {
     ///
     /// TODO: document
     StmtExpr(ExprContext, CExprId, Label),
+
+    /// A block nested in a statement translated directly, like the body of a
+    /// Rust `for` loop or a branch of an `if let`, which falls through at its
+    /// end
+    NestedBlock,
 }
 
 /// A complete control-flow graph
@@ -570,7 +575,10 @@ impl Cfg<Label, StmtOrDecl> {
             .flat_map(|&stmt_id| DFExpr::new(&translator.ast_context, stmt_id.into()))
             .flat_map(SomeId::stmt)
             .flat_map(|x| match translator.ast_context[x].kind {
-                CStmtKind::Goto(target) => Some((target, x)),
+                // `goto`s to a cleanup label are replaced with the cleanup
+                CStmtKind::Goto(target) if !translator.is_cleanup_label(target) => {
+                    Some((target, x))
+                }
                 _ => None,
             })
         {
@@ -625,6 +633,7 @@ impl Cfg<Label, StmtOrDecl> {
                             mk().break_expr_value(Some(brk_label.pretty_print()), Some(val)),
                         )));
                     }
+                    ImplicitReturnType::NestedBlock => {}
                 };
 
                 cfg_builder.add_wip_block(wip, End);
@@ -1414,6 +1423,9 @@ impl CfgBuilder {
                     };
 
                     let (stmts, ret_val) = WithStmts::with_stmts_opt(val).discard_unsafe();
+                    let ret_val = ret_val.map(|val| {
+                        translator.convert_option_return(translator.convert_mapped_return(val))
+                    });
                     wip.extend(stmts);
                     wip.push_stmt(mk().expr_stmt(mk().return_expr(ret_val)));
 
@@ -1422,6 +1434,12 @@ impl CfgBuilder {
                     Ok(None)
                 }
 
+                CStmtKind::If { .. } if translator.is_null_check(stmt_id) => {
+                    let stmts = translator.convert_null_check(ctx, stmt_id)?;
+                    wip.extend(stmts);
+                    Ok(Some(wip))
+                }
+
                 CStmtKind::If {
                     scrutinee,
                     true_variant,
@@ -1577,6 +1595,12 @@ impl CfgBuilder {
                     Ok(Some(self.new_wip_block(next_entry)))
                 }
 
+                CStmtKind::ForLoop { .. } if translator.is_range_loop(stmt_id) => {
+                    let stmts = translator.convert_range_loop(ctx, stmt_id)?;
+                    wip.extend(stmts);
+                    Ok(Some(wip))
+                }
+
                 CStmtKind::ForLoop {
                     init,
                     condition,
@@ -1667,7 +1691,9 @@ impl CfgBuilder {
                 CStmtKind::Label(sub_stmt) => {
                     let this_label = Label::FromC(stmt_id);
                     self.add_wip_block(wip, Jump(this_label));
-                    self.last_per_stmt_mut().c_labels_defined.insert(stmt_id);
+                    if !translator.is_cleanup_label(stmt_id) {
+                        self.last_per_stmt_mut().c_labels_defined.insert(stmt_id);
+                    }
 
                     // Sub stmt
                     let sub_stmt_next =
@@ -1675,7 +1701,25 @@ impl CfgBuilder {
                     Ok(sub_stmt_next.map(|l| self.new_wip_block(l)))
                 }
 
+                CStmtKind::Goto(label_id) if translator.is_cleanup_label(label_id) => {
+                    let cleanup_entry = self.fresh_label();
+                    self.add_wip_block(wip, Jump(cleanup_entry));
+                    let cleanup = translator.cleanup_stmts(label_id);
+                    let cleanup_exit =
+                        self.convert_stmts_help(translator, ctx, &cleanup, None, cleanup_entry)?;
+
+                    // The cleanup of a `void` function may end without a `return`
+                    if let Some(cleanup_exit) = cleanup_exit {
+                        let mut wip = self.new_wip_block(cleanup_exit);
+                        wip.push_stmt(mk().semi_stmt(mk().return_expr(None as Option<P<Expr>>)));
+                        self.add_wip_block(wip, End);
+                    }
+
+                    Ok(None)
+                }
+
                 CStmtKind::Goto(label_id) => {
+                    translator.record_goto();
                     let tgt_label = Label::FromC(label_id);
                     self.add_wip_block(wip, Jump(tgt_label));
                     self.last_per_stmt_mut()
@@ -1701,6 +1745,12 @@ impl CfgBuilder {
                     Ok(next_lbl.map(|l| self.new_wip_block(l)))
                 }
 
+                CStmtKind::Expr(expr) if translator.is_assertion(expr) => {
+                    let stmts = translator.convert_assertion(ctx, expr)?;
+                    wip.extend(stmts);
+                    Ok(Some(wip))
+                }
+
                 CStmtKind::Expr(expr) => 'case_blk: {
                     // This case typically happens in macros from system headers.
                     // We simply inline the common statement at this point rather
@@ -1,12 +1,41 @@
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
+use std::fs::{self, File};
+use std::io;
 use std::path::{Path, PathBuf};
+use std::process;
 use std::rc::Rc;
 
 use failure::Error;
-use regex::Regex;
+use regex::{self, Regex};
 
-#[derive(Deserialize, Debug, Default, Clone)]
+/// Response files can include other response files, up to this depth
+const MAX_RESPONSE_FILE_DEPTH: usize = 16;
+
+/// Which entries of `compile_commands.json` to translate, and changes to
+/// their clang arguments, so the database doesn't have to be edited by hand
+#[derive(Debug, Default, Clone)]
+pub struct CompileCmdOptions {
+    /// Only translate the files matching one of these globs, if any are given
+    pub include_files: Vec<String>,
+    /// Don't translate the files matching one of these globs
+    pub exclude_files: Vec<String>,
+    /// Macros to define after the definitions of the database, as `NAME` or
+    /// `NAME=VALUE`
+    pub defines: Vec<String>,
+    /// Directories to search for headers before those of the database
+    pub include_dirs: Vec<PathBuf>,
+    /// Replaces the `--sysroot` of every entry
+    pub sysroot: Option<PathBuf>,
+}
+
+impl CompileCmdOptions {
+    /// Whether the arguments of the entries have to be changed
+    fn changes_args(&self) -> bool {
+        !self.defines.is_empty() || !self.include_dirs.is_empty() || self.sysroot.is_some()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct CompileCmd {
     /// The working directory of the compilation. All paths specified in the command
     /// or file fields must be either absolute or relative to this directory.
@@ -20,12 +49,14 @@ pub struct CompileCmd {
     /// to rerun the exact compilation step for the translation unit in the environment
     /// the build system uses. Parameters use shell quoting and shell escaping of quotes,
     /// with ‘"’ and ‘\’ being the only special characters. Shell expansion is not supported.
+    #[serde(skip_serializing_if = "Option::is_none")]
     command: Option<String>,
     /// The compile command executed as list of strings. Either arguments or command is required.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     arguments: Vec<String>,
     /// The name of the output created by this compilation step. This field is optional. It can
     /// be used to distinguish different processing modes of the same input file.
+    #[serde(skip_serializing_if = "Option::is_none")]
     output: Option<String>,
 }
 
@@ -40,6 +71,198 @@ impl CompileCmd {
             },
         }
     }
+
+    /// Whether the entry is for a file the options select
+    fn is_selected(&self, include: &[Regex], exclude: &[Regex]) -> bool {
+        let path = self.directory.join(&self.file);
+        let path = path.to_string_lossy();
+        (include.is_empty() || include.iter().any(|re| re.is_match(&path)))
+            && !exclude.iter().any(|re| re.is_match(&path))
+    }
+
+    /// The arguments of the compile command, starting with the compiler
+    fn args(&self) -> Vec<String> {
+        if self.arguments.is_empty() {
+            self.command
+                .as_ref()
+                .map(|cmd| split_command_line(cmd))
+                .unwrap_or_default()
+        } else {
+            self.arguments.clone()
+        }
+    }
+
+    /// Replace the `@file` arguments of the command by the arguments the
+    /// response files hold, which clang doesn't read from the database.
+    /// Returns whether there were any.
+    fn expand_response_files(&mut self) -> Result<bool, Error> {
+        let args = self.args();
+        if !args.iter().any(|arg| arg.starts_with('@')) {
+            return Ok(false);
+        }
+        let mut expanded = vec![];
+        self.expand_args(args, &mut expanded, 0)?;
+        self.command = None;
+        self.arguments = expanded;
+        Ok(true)
+    }
+
+    fn expand_args(
+        &self,
+        args: Vec<String>,
+        expanded: &mut Vec<String>,
+        depth: usize,
+    ) -> Result<(), Error> {
+        for arg in args {
+            if !arg.starts_with('@') || arg.len() == 1 {
+                expanded.push(arg);
+                continue;
+            }
+            if depth == MAX_RESPONSE_FILE_DEPTH {
+                return Err(format_err!(
+                    "response files of {} are nested too deeply",
+                    self.file.display()
+                ));
+            }
+            let path = self.directory.join(&arg[1..]);
+            let contents = fs::read_to_string(&path).map_err(|e| {
+                format_err!("could not read response file {}: {}", path.display(), e)
+            })?;
+            self.expand_args(split_command_line(&contents), expanded, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Change the arguments of the command as the options ask: include
+    /// directories go first, so they are searched before the others, and
+    /// definitions last, so they override the others
+    fn apply_options(&mut self, options: &CompileCmdOptions) {
+        let mut args = self.args().into_iter();
+        let mut new_args = vec![];
+        // The compiler itself
+        new_args.extend(args.next());
+        for dir in &options.include_dirs {
+            new_args.push(format!("-I{}", dir.display()));
+        }
+        while let Some(arg) = args.next() {
+            if options.sysroot.is_some() {
+                if arg == "--sysroot" {
+                    args.next();
+                    continue;
+                }
+                if arg.starts_with("--sysroot=") {
+                    continue;
+                }
+            }
+            new_args.push(arg);
+        }
+        if let Some(ref sysroot) = options.sysroot {
+            new_args.push(format!("--sysroot={}", sysroot.display()));
+        }
+        for define in &options.defines {
+            new_args.push(format!("-D{}", define));
+        }
+        self.command = None;
+        self.arguments = new_args;
+    }
+
+    /// The preprocessor and language standard flags of the compilation, with
+    /// the paths they name made absolute
+    pub fn c_flags(&self) -> Vec<String> {
+        let mut flags = vec![];
+        // Skip the compiler itself
+        let mut args = self.args().into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-D" | "-U" => {
+                    if let Some(value) = args.next() {
+                        flags.push(format!("{}{}", arg, value));
+                    }
+                }
+                "-I" | "-isystem" | "-include" => {
+                    if let Some(path) = args.next() {
+                        flags.push(arg);
+                        flags.push(self.directory.join(path).to_string_lossy().into_owned());
+                    }
+                }
+                _ if arg.starts_with("-I") => {
+                    let path = self.directory.join(&arg[2..]);
+                    flags.push(format!("-I{}", path.display()));
+                }
+                _ if arg.starts_with("-D") || arg.starts_with("-U") || arg.starts_with("-std=") => {
+                    flags.push(arg)
+                }
+                _ => {}
+            }
+        }
+        flags
+    }
+}
+
+/// Split a command line into its arguments like a shell does, by whitespace
+/// outside of quotes. A backslash escapes the next character.
+fn split_command_line(cmd: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    let mut chars = cmd.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => arg.push(c),
+            ('\\', _) => {
+                arg.extend(chars.next());
+                in_arg = true;
+            }
+            (_, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => arg.push(c),
+            ('"', None) | ('\'', None) => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (_, None) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::replace(&mut arg, String::new()));
+                    in_arg = false;
+                }
+            }
+            (_, None) => {
+                arg.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+    args
+}
+
+/// Translate a glob over paths into a regex. `*` and `?` match within a path
+/// component and `**` across them. A relative glob matches the end of a path,
+/// so `src/*.c` matches `/project/src/main.c`.
+fn glob_regex(glob: &str) -> Result<Regex, Error> {
+    let mut re = String::from(if glob.starts_with('/') { "^" } else { "(^|/)" });
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Ok(Regex::new(&re)?)
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq)]
@@ -162,26 +385,60 @@ fn filter_duplicate_cmds(v: Vec<Rc<CompileCmd>>) -> Vec<Rc<CompileCmd>> {
     cmds
 }
 
-/// Read `compile_commands` file and optionally ignore any entries not matching `filter`.
+/// Read `compile_commands` file and optionally ignore any entries not matching `filter`,
+/// or not selected by the globs of `options`. The response files of the entries are
+/// expanded and their arguments changed as `options` asks. Also returns whether any
+/// entry changed, in which case clang has to be given a `RewrittenCompileCmds`.
 pub fn get_compile_commands(
     compile_commands: &Path,
     filter: &Option<Regex>,
-) -> Result<Vec<LinkCmd>, Error> {
+    options: &CompileCmdOptions,
+) -> Result<(Vec<LinkCmd>, bool), Error> {
     let f = File::open(compile_commands)?; // open read-only
 
     // Read the JSON contents of the file as an instance of `Value`
-    let v: Vec<Rc<CompileCmd>> = serde_json::from_reader(f)?;
+    let v: Vec<CompileCmd> = serde_json::from_reader(f)?;
 
     // apply the filter argument, if any
     let v = if let &Some(ref re) = filter {
         v.into_iter()
             .filter(|c| re.is_match(c.file.to_str().unwrap()))
-            .collect::<Vec<Rc<CompileCmd>>>()
+            .collect::<Vec<CompileCmd>>()
     } else {
         v
     };
 
-    let mut lcmds = build_link_commands(v)?;
+    let include = options
+        .include_files
+        .iter()
+        .map(|glob| glob_regex(glob))
+        .collect::<Result<Vec<_>, _>>()?;
+    let exclude = options
+        .exclude_files
+        .iter()
+        .map(|glob| glob_regex(glob))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut changed = false;
+    let mut cmds = vec![];
+    for mut cmd in v {
+        // Link commands are encoded as entries for pseudo-files
+        if cmd.file.starts_with("/c2rust/link/") {
+            cmds.push(Rc::new(cmd));
+            continue;
+        }
+        if !cmd.is_selected(&include, &exclude) {
+            continue;
+        }
+        changed |= cmd.expand_response_files()?;
+        if options.changes_args() {
+            cmd.apply_options(options);
+            changed = true;
+        }
+        cmds.push(Rc::new(cmd));
+    }
+
+    let mut lcmds = build_link_commands(cmds)?;
 
     for lcmd in &mut lcmds {
         let inputs = std::mem::replace(&mut lcmd.cmd_inputs, vec![]);
@@ -189,5 +446,115 @@ pub fn get_compile_commands(
         lcmd.cmd_inputs = inputs;
     }
 
-    Ok(lcmds)
+    Ok((lcmds, changed))
+}
+
+/// A compilation database of the entries `get_compile_commands` changed, for
+/// clang to read. It is written to a temporary directory, which is removed
+/// when this is dropped.
+pub struct RewrittenCompileCmds {
+    dir: PathBuf,
+}
+
+impl RewrittenCompileCmds {
+    pub fn write(lcmds: &[LinkCmd]) -> io::Result<Self> {
+        let dir = std::env::temp_dir().join(format!("c2rust-compile-commands-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        let rewritten = RewrittenCompileCmds { dir };
+
+        let cmds = lcmds
+            .iter()
+            .flat_map(|lcmd| lcmd.cmd_inputs.iter())
+            .collect::<Vec<_>>();
+        let json = serde_json::to_string_pretty(&cmds)
+            .expect("Unable to serialize compile commands");
+        fs::write(rewritten.path(), json)?;
+        Ok(rewritten)
+    }
+
+    /// Path of the database, which clang finds only under this file name
+    pub fn path(&self) -> PathBuf {
+        self.dir.join("compile_commands.json")
+    }
+}
+
+impl Drop for RewrittenCompileCmds {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        let re = glob_regex("src/*.c").unwrap();
+        assert!(re.is_match("/project/src/main.c"));
+        assert!(re.is_match("src/main.c"));
+        assert!(!re.is_match("/project/src/sub/main.c"));
+        assert!(!re.is_match("/project/mysrc/main.c"));
+
+        let re = glob_regex("src/**/*.c").unwrap();
+        assert!(re.is_match("/project/src/main.c"));
+        assert!(re.is_match("/project/src/sub/dir/main.c"));
+        assert!(!re.is_match("/project/lib/main.c"));
+
+        let re = glob_regex("/project/?.c").unwrap();
+        assert!(re.is_match("/project/a.c"));
+        assert!(!re.is_match("/project/ab.c"));
+        assert!(!re.is_match("/other/project/a.c"));
+
+        let re = glob_regex("a+b.c").unwrap();
+        assert!(re.is_match("/a+b.c"));
+        assert!(!re.is_match("/aab.c"));
+    }
+
+    #[test]
+    fn command_line_splitting() {
+        assert_eq!(
+            split_command_line("  cc -c\tmain.c  -o main.o "),
+            vec!["cc", "-c", "main.c", "-o", "main.o"],
+        );
+        assert_eq!(
+            split_command_line(r#"cc "-DNAME=\"a b\"" -I'my dir' x\ y.c"#),
+            vec!["cc", r#"-DNAME="a b""#, "-Imy dir", "x y.c"],
+        );
+        assert_eq!(split_command_line(r"cc '-DA=\n' """), vec!["cc", r"-DA=\n", ""]);
+        assert!(split_command_line(" \n ").is_empty());
+    }
+
+    #[test]
+    fn response_files() {
+        let dir = std::env::temp_dir()
+            .join(format!("c2rust-response-files-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("flags.rsp"), "-DA=1 @nested.rsp\n-Iinclude").unwrap();
+        fs::write(dir.join("nested.rsp"), "'-DB=two words'").unwrap();
+        fs::write(dir.join("loop.rsp"), "@loop.rsp").unwrap();
+
+        let cmd = CompileCmd {
+            directory: dir.clone(),
+            file: PathBuf::from("main.c"),
+            ..Default::default()
+        };
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        let mut expanded = vec![];
+        cmd.expand_args(args(&["cc", "@flags.rsp", "@", "main.c"]), &mut expanded, 0)
+            .unwrap();
+        assert_eq!(
+            expanded,
+            args(&["cc", "-DA=1", "-DB=two words", "-Iinclude", "@", "main.c"]),
+        );
+
+        let missing = cmd.expand_args(args(&["@missing.rsp"]), &mut vec![], 0);
+        assert!(missing.unwrap_err().to_string().contains("could not read response file"));
+
+        let nested = cmd.expand_args(args(&["@loop.rsp"]), &mut vec![], 0);
+        assert!(nested.unwrap_err().to_string().contains("nested too deeply"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
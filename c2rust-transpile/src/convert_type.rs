@@ -1,9 +1,13 @@
 use crate::c_ast::CDeclId;
 use crate::c_ast::*;
 use crate::renamer::*;
-use crate::diagnostics::TranslationError;
+use crate::diagnostics::{TranslationError, TranslationErrorKind};
+use crate::hooks::TranslationHooks;
+use crate::type_map::TypeMap;
 use c2rust_ast_builder::mk;
-use std::collections::{HashMap, HashSet};
+use failure::err_msg;
+use indexmap::IndexSet;
+use std::collections::HashMap;
 use std::ops::Index;
 use syntax::ast::*;
 use syntax::ptr::P;
@@ -16,11 +20,17 @@ enum FieldKey {
 
 pub struct TypeConverter {
     pub translate_valist: bool,
+    pub translate_simd: bool,
+    pub hooks: TranslationHooks,
+    pub type_map: TypeMap,
     renamer: Renamer<CDeclId>,
     fields: HashMap<CDeclId, Renamer<FieldKey>>,
     suffix_names: HashMap<(CDeclId, &'static str), String>,
-    features: HashSet<&'static str>,
+    features: IndexSet<&'static str>,
+    uses_half: bool,
     emit_no_std: bool,
+    emit_no_libc: bool,
+    naming: NamingPolicy,
 }
 
 pub const RESERVED_NAMES: [&str; 103] = [
@@ -133,24 +143,81 @@ pub const RESERVED_NAMES: [&str; 103] = [
 ];
 
 impl TypeConverter {
-    pub fn new(emit_no_std: bool) -> TypeConverter {
+    pub fn new(emit_no_std: bool, emit_no_libc: bool, naming: NamingPolicy) -> TypeConverter {
         TypeConverter {
             translate_valist: false,
-            renamer: Renamer::new(&RESERVED_NAMES),
+            translate_simd: false,
+            hooks: TranslationHooks::new(),
+            type_map: TypeMap::default(),
+            renamer: Renamer::with_policy(&RESERVED_NAMES, naming.clone()),
             fields: HashMap::new(),
             suffix_names: HashMap::new(),
-            features: HashSet::new(),
+            features: IndexSet::new(),
+            uses_half: false,
             emit_no_std,
+            emit_no_libc,
+            naming,
         }
     }
 
-    pub fn features_used(&self) -> &HashSet<&'static str> {
+    /// The C FFI type `name`, e.g. `c_int` or `size_t`. These come from `libc` unless we
+    /// emit libc-free code, in which case we use `core::ffi` and the primitive integer types.
+    pub fn ffi_ty(&self, name: &str) -> P<Ty> {
+        if !self.emit_no_libc {
+            return mk().path_ty(vec!["libc", name]);
+        }
+        match name {
+            "size_t" | "uintptr_t" => mk().path_ty(vec!["usize"]),
+            "ssize_t" | "intptr_t" | "ptrdiff_t" => mk().path_ty(vec!["isize"]),
+            _ => mk().path_ty(vec!["", "core", "ffi", name]),
+        }
+    }
+
+    pub fn features_used(&self) -> &IndexSet<&'static str> {
         &self.features
     }
 
+    /// Whether a type was translated as `half::f16`, which needs the `half` crate
+    pub fn uses_half(&self) -> bool {
+        self.uses_half
+    }
+
+    /// The Rust ABI of functions with the given calling convention, `"C"` for the default one,
+    /// which `__cdecl` also selects. `__stdcall` functions on Windows, like those of the Win32
+    /// API, use the `"system"` ABI, which is `stdcall` on 32-bit x86 and the C one on other
+    /// architectures.
+    pub fn calling_conv_abi(
+        &mut self,
+        ctxt: &TypedAstContext,
+        calling_conv: Option<&CallingConv>,
+    ) -> Result<&'static str, TranslationError> {
+        Ok(match calling_conv {
+            None => "C",
+            Some(CallingConv::StdCall) if ctxt.target_info.triple.contains("windows") => "system",
+            Some(CallingConv::StdCall) => "stdcall",
+            Some(CallingConv::FastCall) => "fastcall",
+            Some(CallingConv::ThisCall) => {
+                self.features.insert("abi_thiscall");
+                "thiscall"
+            }
+            Some(CallingConv::VectorCall) => {
+                self.features.insert("abi_vectorcall");
+                "vectorcall"
+            }
+            Some(CallingConv::Win64) => "win64",
+            Some(CallingConv::SysV64) => "sysv64",
+            Some(CallingConv::Aapcs) => "aapcs",
+            // Calling these with the C ABI would silently pass arguments in the wrong places
+            Some(CallingConv::Other(name)) => {
+                return Err(format_err!("Unsupported calling convention {}", name).into())
+            }
+        })
+    }
+
     pub fn declare_decl_name(&mut self, decl_id: CDeclId, name: &str) -> String {
+        let name = self.naming.type_case.apply(name);
         self.renamer
-            .insert(decl_id, name)
+            .insert(decl_id, &name)
             .expect("Name already assigned")
     }
 
@@ -184,7 +251,8 @@ impl TypeConverter {
         let name = if name.is_empty() { "c2rust_unnamed" } else { name };
 
         if !self.fields.contains_key(&record_id) {
-            self.fields.insert(record_id, Renamer::new(&RESERVED_NAMES));
+            let renamer = Renamer::with_policy(&RESERVED_NAMES, self.naming.clone());
+            self.fields.insert(record_id, renamer);
         }
 
         self.fields
@@ -200,7 +268,8 @@ impl TypeConverter {
         padding_idx: usize,
     ) -> String {
         if !self.fields.contains_key(&record_id) {
-            self.fields.insert(record_id, Renamer::new(&RESERVED_NAMES));
+            let renamer = Renamer::with_policy(&RESERVED_NAMES, self.naming.clone());
+            self.fields.insert(record_id, renamer);
         }
 
         let key = FieldKey::Padding(padding_idx);
@@ -238,6 +307,7 @@ impl TypeConverter {
         ret: Option<CQualTypeId>,
         params: &Vec<CQualTypeId>,
         is_variadic: bool,
+        calling_conv: Option<&CallingConv>,
     ) -> Result<P<Ty>, TranslationError> {
         let mut inputs = params
             .iter()
@@ -255,7 +325,8 @@ impl TypeConverter {
         };
 
         let fn_ty = mk().fn_decl(inputs, FunctionRetTy::Ty(output));
-        return Ok(mk().unsafe_().extern_("C").barefn_ty(fn_ty));
+        let abi = self.calling_conv_abi(ctxt, calling_conv)?;
+        return Ok(mk().unsafe_().extern_(abi).barefn_ty(fn_ty));
     }
 
     pub fn convert_pointer(
@@ -263,7 +334,9 @@ impl TypeConverter {
         ctxt: &TypedAstContext,
         qtype: CQualTypeId,
     ) -> Result<P<Ty>, TranslationError> {
-        let mutbl = if qtype.qualifiers.is_const {
+        // Look through typedefs so that e.g. `typedef const int cint; cint *p;` still becomes
+        // `*const`
+        let mutbl = if ctxt.resolve_qualifiers(qtype).is_const {
             Mutability::Immutable
         } else {
             Mutability::Mutable
@@ -275,7 +348,7 @@ impl TypeConverter {
             CTypeKind::Void => {
                 Ok(mk()
                     .set_mutbl(mutbl)
-                    .ptr_ty(mk().path_ty(vec!["libc", "c_void"])))
+                    .ptr_ty(self.ffi_ty("c_void")))
             }
 
             CTypeKind::VariableArray(mut elt, _len) => {
@@ -308,6 +381,22 @@ impl TypeConverter {
         ctxt: &TypedAstContext,
         ctype: CTypeId,
     ) -> Result<P<Ty>, TranslationError> {
+        if let Some(ty) = self.hooks.before_type(ctxt, ctype) {
+            return Ok(ty);
+        }
+        let ty = self.convert_unhooked(ctxt, ctype)?;
+        Ok(self.hooks.after_type(ctxt, ctype, ty))
+    }
+
+    fn convert_unhooked(
+        &mut self,
+        ctxt: &TypedAstContext,
+        ctype: CTypeId,
+    ) -> Result<P<Ty>, TranslationError> {
+        if let Some(ty) = self.type_map.lookup(ctxt, ctype) {
+            return Ok(ty);
+        }
+
         if self.translate_valist && ctxt.is_va_list(ctype) {
             let std_or_core = if self.emit_no_std { "core" } else { "std" };
             let path = vec!["", std_or_core, "ffi", "VaList"];
@@ -318,25 +407,46 @@ impl TypeConverter {
         match ctxt.index(ctype).kind {
             CTypeKind::Void => Ok(mk().tuple_ty(vec![] as Vec<P<Ty>>)),
             CTypeKind::Bool => Ok(mk().path_ty(mk().path(vec!["bool"]))),
-            CTypeKind::Short => Ok(mk().path_ty(mk().path(vec!["libc", "c_short"]))),
-            CTypeKind::Int => Ok(mk().path_ty(mk().path(vec!["libc", "c_int"]))),
-            CTypeKind::Long => Ok(mk().path_ty(mk().path(vec!["libc", "c_long"]))),
-            CTypeKind::LongLong => Ok(mk().path_ty(mk().path(vec!["libc", "c_longlong"]))),
-            CTypeKind::UShort => Ok(mk().path_ty(mk().path(vec!["libc", "c_ushort"]))),
-            CTypeKind::UInt => Ok(mk().path_ty(mk().path(vec!["libc", "c_uint"]))),
-            CTypeKind::ULong => Ok(mk().path_ty(mk().path(vec!["libc", "c_ulong"]))),
-            CTypeKind::ULongLong => Ok(mk().path_ty(mk().path(vec!["libc", "c_ulonglong"]))),
-            CTypeKind::SChar => Ok(mk().path_ty(mk().path(vec!["libc", "c_schar"]))),
-            CTypeKind::UChar => Ok(mk().path_ty(mk().path(vec!["libc", "c_uchar"]))),
-            CTypeKind::Char => Ok(mk().path_ty(mk().path(vec!["libc", "c_char"]))),
-            CTypeKind::Double => Ok(mk().path_ty(mk().path(vec!["libc", "c_double"]))),
+            CTypeKind::Short => Ok(self.ffi_ty("c_short")),
+            CTypeKind::Int => Ok(self.ffi_ty("c_int")),
+            CTypeKind::Long => Ok(self.ffi_ty("c_long")),
+            CTypeKind::LongLong => Ok(self.ffi_ty("c_longlong")),
+            CTypeKind::UShort => Ok(self.ffi_ty("c_ushort")),
+            CTypeKind::UInt => Ok(self.ffi_ty("c_uint")),
+            CTypeKind::ULong => Ok(self.ffi_ty("c_ulong")),
+            CTypeKind::ULongLong => Ok(self.ffi_ty("c_ulonglong")),
+            CTypeKind::SChar => Ok(self.ffi_ty("c_schar")),
+            CTypeKind::UChar => Ok(self.ffi_ty("c_uchar")),
+            CTypeKind::Char => Ok(self.ffi_ty("c_char")),
+            CTypeKind::Double => Ok(self.ffi_ty("c_double")),
             CTypeKind::LongDouble => Ok(mk().path_ty(mk().path(vec!["f128", "f128"]))),
-            CTypeKind::Float => Ok(mk().path_ty(mk().path(vec!["libc", "c_float"]))),
+            CTypeKind::Float => Ok(self.ffi_ty("c_float")),
+            CTypeKind::Half => {
+                self.uses_half = true;
+                Ok(mk().path_ty(mk().path(vec!["half", "f16"])))
+            }
             CTypeKind::Int128 => Ok(mk().path_ty(mk().path(vec!["i128"]))),
             CTypeKind::UInt128 => Ok(mk().path_ty(mk().path(vec!["u128"]))),
 
+            // Bit-precise integers are stored in the nearest native integer; arithmetic on them
+            // is masked back to the declared width in the operator translation.
+            ref kind @ CTypeKind::BitInt(signed, width) => match kind.bit_int_storage_width() {
+                Some(storage) => {
+                    let prefix = if signed { "i" } else { "u" };
+                    Ok(mk().path_ty(mk().path(vec![format!("{}{}", prefix, storage)])))
+                }
+                None => Err(format_err!(
+                    "Unsupported _BitInt width {}, at most 128 bits are supported",
+                    width
+                )
+                .into()),
+            },
+
             CTypeKind::Pointer(qtype) => self.convert_pointer(ctxt, qtype),
 
+            // Blocks can only be passed around as opaque pointers
+            CTypeKind::BlockPointer(_) => Ok(mk().mutbl().ptr_ty(self.ffi_ty("c_void"))),
+
             CTypeKind::Elaborated(ref ctype) => self.convert(ctxt, *ctype),
             CTypeKind::Decayed(ref ctype) => self.convert(ctxt, *ctype),
             CTypeKind::Paren(ref ctype) => self.convert(ctxt, *ctype),
@@ -389,22 +499,62 @@ impl TypeConverter {
             CTypeKind::Attributed(ty, _) => self.convert(ctxt, ty.ctype),
 
             // ANSI/ISO C-style function
-            CTypeKind::Function(ret, ref params, is_var, is_noreturn, true) => {
+            CTypeKind::Function(ret, ref params, is_var, is_noreturn, true, ref calling_conv) => {
                 let opt_ret = if is_noreturn { None } else { Some(ret) };
-                let fn_ty = self.convert_function(ctxt, opt_ret, params, is_var)?;
+                let fn_ty = self.convert_function(ctxt, opt_ret, params, is_var, calling_conv.as_ref())?;
                 Ok(fn_ty)
             }
 
             // K&R-style function
-            CTypeKind::Function(ret, _, is_var, is_noreturn, false) => {
+            CTypeKind::Function(ret, _, is_var, is_noreturn, false, ref calling_conv) => {
                 let opt_ret = if is_noreturn { None } else { Some(ret) };
-                let fn_ty = self.convert_function(ctxt, opt_ret, &vec![], is_var)?;
+                let fn_ty =
+                    self.convert_function(ctxt, opt_ret, &vec![], is_var, calling_conv.as_ref())?;
                 Ok(fn_ty)
             }
 
-            CTypeKind::TypeOf(ty) => self.convert(ctxt, ty),
+            CTypeKind::TypeOf(ty) | CTypeKind::Auto(ty) => self.convert(ctxt, ty),
+
+            CTypeKind::TypeOfExpr(expr) => match ctxt[expr].kind.get_type() {
+                Some(ty) => self.convert(ctxt, ty),
+                None => Err(format_err!("Expression of `typeof` has no type").into()),
+            },
+
+            CTypeKind::Vector(elt, len) if self.translate_simd => {
+                match simd_vector_type_name(ctxt, elt.ctype, len) {
+                    Some(name) => Ok(mk().path_ty(mk().path(vec![name]))),
+                    None => Err(format_err!(
+                        "Vector of {} {:?} elements has no core::arch counterpart",
+                        len,
+                        ctxt.resolve_type(elt.ctype).kind,
+                    ).into()),
+                }
+            }
 
-            ref t => Err(format_err!("Unsupported type {:?}", t).into()),
+            CTypeKind::Vector(..) => Err(unsupported_type(
+                "Vector types are only translated with `--translate-simd`".to_string(),
+                "Pass `--translate-simd` to translate them as `core::arch` vector types.",
+            )),
+
+            CTypeKind::Unsupported(ref name) => Err(unsupported_type(
+                format!("Rust has no counterpart of the builtin type `{}`", name),
+                "Replace it with a Rust type with `--type-map`.",
+            )),
+
+            CTypeKind::Complex(_) => Err(unsupported_type(
+                "Rust has no counterpart of complex types".to_string(),
+                "Replace it with a Rust type like `num_complex::Complex<f64>` with `--type-map`.",
+            )),
+
+            CTypeKind::Reference(_) => Err(unsupported_type(
+                "C++ references are not supported".to_string(),
+                "Declare it as a pointer instead.",
+            )),
+
+            CTypeKind::BuiltinFn => Err(unsupported_type(
+                "Builtin functions can only be called, not used as values".to_string(),
+                "Wrap the builtin in a function and use that instead.",
+            )),
         }
     }
 
@@ -418,10 +568,10 @@ impl TypeConverter {
     ) -> Result<Option<P<Ty>>, TranslationError> {
         match ctxt.index(ctype).kind {
             // ANSI/ISO C-style function
-            CTypeKind::Function(.., true) => Ok(None),
+            CTypeKind::Function(_, _, _, _, true, _) => Ok(None),
 
             // K&R-style function
-            CTypeKind::Function(ret, ref _params, is_var, is_noreturn, false) => {
+            CTypeKind::Function(ret, ref _params, is_var, is_noreturn, false, ref calling_conv) => {
                 // _params is empty here -> get params from function definition instead
                 let params = params
                     .iter()
@@ -435,14 +585,16 @@ impl TypeConverter {
                     .collect();
 
                 let opt_ret = if is_noreturn { None } else { Some(ret) };
-                let fn_ty = self.convert_function(ctxt, opt_ret, &params, is_var)?;
+                let fn_ty = self.convert_function(ctxt, opt_ret, &params, is_var, calling_conv.as_ref())?;
                 Ok(Some(fn_ty))
             }
 
             CTypeKind::Elaborated(ref ctype) => self.knr_function_type_with_parameters(ctxt, *ctype, params),
             CTypeKind::Decayed(ref ctype) => self.knr_function_type_with_parameters(ctxt, *ctype, params),
             CTypeKind::Paren(ref ctype) => self.knr_function_type_with_parameters(ctxt, *ctype, params),
-            CTypeKind::TypeOf(ty) => self.knr_function_type_with_parameters(ctxt, ty, params),
+            CTypeKind::TypeOf(ty) | CTypeKind::Auto(ty) => {
+                self.knr_function_type_with_parameters(ctxt, ty, params)
+            }
 
             CTypeKind::Typedef(decl) => match &ctxt.index(decl).kind {
                 CDeclKind::Typedef { typ, .. } => self.knr_function_type_with_parameters(ctxt, typ.ctype, params),
@@ -453,3 +605,50 @@ impl TypeConverter {
         }
     }
 }
+
+/// Name of the `core::arch` type with the same layout as a C vector of `len` elements of
+/// type `elt`, as declared with `__attribute__((vector_size(N)))`.
+pub fn simd_vector_type_name(
+    ctxt: &TypedAstContext,
+    elt: CTypeId,
+    len: usize,
+) -> Option<&'static str> {
+    let elt_size = match ctxt.resolve_type(elt).kind {
+        CTypeKind::Float => return match len {
+            4 => Some("__m128"),
+            8 => Some("__m256"),
+            _ => None,
+        },
+        CTypeKind::Double => return match len {
+            2 => Some("__m128d"),
+            4 => Some("__m256d"),
+            _ => None,
+        },
+        ref kind @ CTypeKind::Char
+        | ref kind @ CTypeKind::SChar
+        | ref kind @ CTypeKind::UChar
+        | ref kind @ CTypeKind::Short
+        | ref kind @ CTypeKind::UShort
+        | ref kind @ CTypeKind::Int
+        | ref kind @ CTypeKind::UInt
+        | ref kind @ CTypeKind::Long
+        | ref kind @ CTypeKind::ULong
+        | ref kind @ CTypeKind::LongLong
+        | ref kind @ CTypeKind::ULongLong => ctxt.integral_type_width(kind)? / 8,
+        _ => return None,
+    };
+
+    match elt_size * len {
+        8 => Some("__m64"),
+        16 => Some("__m128i"),
+        32 => Some("__m256i"),
+        _ => None,
+    }
+}
+
+/// Error for a C type we can't translate, with a suggestion of how to work around it
+fn unsupported_type(what: String, workaround: &'static str) -> TranslationError {
+    err_msg(what)
+        .context(TranslationErrorKind::UnsupportedType(workaround))
+        .into()
+}
@@ -86,6 +86,12 @@ pub enum TranslationErrorKind {
 
     // Clang AST exported by AST-exporter was not valid
     InvalidClangAst(ClangAstParseErrorKind),
+
+    // C extensions we can't translate, like Apple's block literals
+    UnsupportedExtension,
+
+    // C types we can't translate, with a suggestion of how to work around them
+    UnsupportedType(&'static str),
 }
 
 /// Constructs a `TranslationError` using the standard string interpolation syntax.
@@ -100,6 +106,21 @@ macro_rules! format_translation_err {
     }
 }
 
+impl TranslationErrorKind {
+    /// Category of the error in reports
+    pub fn name(&self) -> &'static str {
+        use self::TranslationErrorKind::*;
+        match self {
+            Generic => "generic",
+            OldLLVMSimd => "old-llvm-simd",
+            VaCopyNotImplemented => "va-copy-not-implemented",
+            InvalidClangAst(_) => "invalid-clang-ast",
+            UnsupportedExtension => "unsupported-extension",
+            UnsupportedType(_) => "unsupported-type",
+        }
+    }
+}
+
 impl Display for TranslationErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::TranslationErrorKind::*;
@@ -121,6 +142,14 @@ impl Display for TranslationErrorKind {
             InvalidClangAst(_) => {
                 return write!(f, "Exported Clang AST was invalid. Check warnings above for unimplemented features.");
             }
+
+            UnsupportedExtension => {
+                return write!(f, "This C extension cannot be translated to Rust.");
+            }
+
+            UnsupportedType(workaround) => {
+                return write!(f, "{}", workaround);
+            }
         }
         Ok(())
     }
@@ -148,6 +177,18 @@ impl Display for TranslationError {
         for loc in &self.loc {
             writeln!(f, "{} {}", "-->".blue(), loc)?;
         }
+        // Show the C source where the error occurred
+        if let Some((line_no, column, line)) = self.loc.first().and_then(|loc| loc.source_line()) {
+            let gutter = " ".repeat(line_no.to_string().len());
+            writeln!(f, "{} {}", gutter, "|".blue())?;
+            writeln!(f, "{} {} {}", line_no.to_string().blue(), "|".blue(), line)?;
+            let indent: String = line
+                .chars()
+                .take(column.saturating_sub(1) as usize)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            writeln!(f, "{} {} {}{}", gutter, "|".blue(), indent, "^".red())?;
+        }
         Ok(())
     }
 }
@@ -157,6 +198,19 @@ impl TranslationError {
         self.inner.get_context().clone()
     }
 
+    /// What went wrong, without the locations
+    pub fn message(&self) -> String {
+        let mut lines = vec![];
+        if let Some(cause) = self.cause() {
+            lines.push(cause.to_string());
+        }
+        match self.inner.get_context() {
+            TranslationErrorKind::Generic => {}
+            kind => lines.push(kind.to_string()),
+        }
+        lines.join("\n")
+    }
+
     pub fn new(loc: Option<DisplaySrcSpan>, inner: Context<TranslationErrorKind>) -> Self {
         let mut loc_stack = vec![];
         if let Some(loc) = loc {
@@ -0,0 +1,70 @@
+//! Errors that stop a transpiler run, or keep one from starting. Input files
+//! that fail to translate are not errors of the run; they are reported in
+//! `TranspileResult::report`, in `RunReport::failed_files`, instead.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use failure::Fail;
+
+#[derive(Debug)]
+pub enum TranspileError {
+    /// An input file of the run could not be read
+    Read {
+        /// What the file holds, e.g. "type map"
+        what: &'static str,
+        path: PathBuf,
+        cause: io::Error,
+    },
+    /// An input file of the run was read, but is malformed
+    Parse {
+        what: &'static str,
+        path: PathBuf,
+        message: String,
+    },
+    /// An option of the run has an invalid value
+    InvalidOption {
+        option: &'static str,
+        message: String,
+    },
+    /// A translation or build file could not be written
+    Write { path: PathBuf, cause: io::Error },
+    /// The threads to translate on could not be started
+    ThreadPool(String),
+}
+
+impl fmt::Display for TranspileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TranspileError::Read { what, path, cause } => {
+                write!(f, "Could not read {} {}: {}", what, path.display(), cause)
+            }
+            TranspileError::Parse {
+                what,
+                path,
+                message,
+            } => write!(f, "Could not parse {} {}: {}", what, path.display(), message),
+            TranspileError::InvalidOption { option, message } => {
+                write!(f, "Invalid {}: {}", option, message)
+            }
+            TranspileError::Write { path, cause } => {
+                write!(f, "Unable to write {}: {}", path.display(), cause)
+            }
+            TranspileError::ThreadPool(message) => {
+                write!(f, "Unable to create translation thread pool: {}", message)
+            }
+        }
+    }
+}
+
+impl Fail for TranspileError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        match self {
+            TranspileError::Read { cause, .. } | TranspileError::Write { cause, .. } => {
+                Some(cause)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! The symbols a translated crate exports, loaded from the file given to
+//! `--export-list`. The file either lists one C name per line, or is a linker
+//! version script like
+//!
+//! ```text
+//! LIBFOO_1.0 {
+//!     global:
+//!         foo_*;
+//!         bar;
+//!     local:
+//!         *;
+//! };
+//! ```
+//!
+//! whose `global` patterns are the exported symbols. Functions and variables
+//! that aren't exported lose their `#[no_mangle]` and are only visible within
+//! the crate.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::error::TranspileError;
+
+#[derive(Debug, Clone)]
+pub struct ExportList {
+    /// Exported C names
+    symbols: HashSet<String>,
+    /// Exported C names given by glob patterns
+    patterns: Vec<Regex>,
+}
+
+impl ExportList {
+    /// Load an export list. Files containing a `{` are read as version
+    /// scripts; in other files, empty lines and lines starting with `#` are
+    /// skipped.
+    pub fn load(path: &Path) -> Result<Self, TranspileError> {
+        let contents = fs::read_to_string(path).map_err(|cause| TranspileError::Read {
+            what: "export list",
+            path: path.to_path_buf(),
+            cause,
+        })?;
+        let parse_error = |message| TranspileError::Parse {
+            what: "export list",
+            path: path.to_path_buf(),
+            message,
+        };
+        let entries = if contents.contains('{') {
+            parse_version_script(&contents).map_err(parse_error)?
+        } else {
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from)
+                .collect()
+        };
+
+        let mut symbols = HashSet::new();
+        let mut patterns = vec![];
+        for entry in entries {
+            if entry.contains(|c| c == '*' || c == '?' || c == '[') {
+                patterns.push(glob_to_regex(&entry).map_err(parse_error)?);
+            } else {
+                symbols.insert(entry);
+            }
+        }
+        Ok(ExportList { symbols, patterns })
+    }
+
+    pub fn exports(&self, c_name: &str) -> bool {
+        self.symbols.contains(c_name) || self.patterns.iter().any(|p| p.is_match(c_name))
+    }
+}
+
+/// The `global` patterns of the C symbols in a version script
+fn parse_version_script(contents: &str) -> Result<Vec<String>, String> {
+    let mut exported = vec![];
+    let mut depth = 0;
+    let mut global = true;
+    let mut tokens = tokenize(&strip_comments(contents)).into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token.as_str() {
+            "{" => {
+                depth += 1;
+                // A version node starts out global
+                if depth == 1 {
+                    global = true;
+                }
+            }
+            "}" => {
+                if depth == 0 {
+                    return Err("Unbalanced `}` in version script".to_string());
+                }
+                depth -= 1;
+            }
+            ";" | ":" => {}
+            // Node names and dependencies, and C++ symbols in `extern "C++" { ... }`
+            _ if depth != 1 => {}
+            _ if tokens.peek().map(String::as_str) == Some(":") => match token.as_str() {
+                "global" => global = true,
+                "local" => global = false,
+                _ => return Err(format!("Unknown version script section `{}`", token)),
+            },
+            "extern" => {
+                // The language, as in `extern "C" { ... }`
+                tokens.next();
+            }
+            _ if global => exported.push(token.trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err("Unbalanced `{` in version script".to_string());
+    }
+    Ok(exported)
+}
+
+fn strip_comments(contents: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = contents;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start..].find("*/") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped
+        .lines()
+        .map(|line| line.split('#').next().unwrap())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn tokenize(contents: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    for c in contents.chars() {
+        match c {
+            '{' | '}' | ';' | ':' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::replace(&mut token, String::new()));
+                }
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::replace(&mut token, String::new()));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+fn glob_to_regex(glob: &str) -> Result<Regex, String> {
+    let mut pattern = String::from("^");
+    let mut in_class = false;
+    for c in glob.chars() {
+        match c {
+            '*' if !in_class => pattern.push_str(".*"),
+            '?' if !in_class => pattern.push('.'),
+            '[' if !in_class => {
+                in_class = true;
+                pattern.push('[');
+            }
+            ']' if in_class => {
+                in_class = false;
+                pattern.push(']');
+            }
+            c if in_class => pattern.push(c),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).map_err(|e| format!("Invalid export pattern `{}`: {}", glob, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_script_globals() {
+        let script = r#"
+            /* The stable API */
+            LIBFOO_1.0 {
+                global:
+                    foo_*;
+                    bar; # the only bar
+                    extern "C++" {
+                        ns::baz;
+                    };
+                local:
+                    *;
+            };
+            LIBFOO_2.0 {
+                qux;
+            } LIBFOO_1.0;
+        "#;
+        assert_eq!(parse_version_script(script).unwrap(), vec!["foo_*", "bar", "qux"]);
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let re = glob_to_regex("foo_?[ab]*").unwrap();
+        assert!(re.is_match("foo_1a"));
+        assert!(re.is_match("foo_2bcd"));
+        assert!(!re.is_match("foo_1c"));
+        assert!(!re.is_match("xfoo_1a"));
+    }
+}
@@ -0,0 +1,160 @@
+//! Callbacks that let library users customize the translation without forking
+//! the translator, e.g. to substitute their own Rust types for specific C
+//! structs or to instrument translated expressions.
+
+use std::fmt;
+use std::sync::Arc;
+
+use syntax::ast::{Expr, Ty};
+use syntax::ptr::P;
+
+use crate::c_ast::{CDeclId, CExprId, CTypeId, TypedAstContext};
+use crate::translator::ConvertedDecl;
+
+/// Customizes the conversion of declarations, expressions and types.
+///
+/// The `before_*` callbacks run before the translator converts a node and can
+/// replace its translation entirely by returning `Some`. The `after_*`
+/// callbacks receive the translation of a node and return the one to use
+/// instead. Every callback defaults to leaving the translation unchanged.
+///
+/// Files are translated in parallel, so hooks must be `Send` and `Sync`.
+pub trait TranslationHook: Send + Sync {
+    fn before_decl(&self, _ctx: &TypedAstContext, _decl_id: CDeclId) -> Option<ConvertedDecl> {
+        None
+    }
+
+    fn after_decl(
+        &self,
+        _ctx: &TypedAstContext,
+        _decl_id: CDeclId,
+        decl: ConvertedDecl,
+    ) -> ConvertedDecl {
+        decl
+    }
+
+    fn before_expr(&self, _ctx: &TypedAstContext, _expr_id: CExprId) -> Option<P<Expr>> {
+        None
+    }
+
+    fn after_expr(&self, _ctx: &TypedAstContext, _expr_id: CExprId, expr: P<Expr>) -> P<Expr> {
+        expr
+    }
+
+    fn before_type(&self, _ctx: &TypedAstContext, _type_id: CTypeId) -> Option<P<Ty>> {
+        None
+    }
+
+    fn after_type(&self, _ctx: &TypedAstContext, _type_id: CTypeId, ty: P<Ty>) -> P<Ty> {
+        ty
+    }
+}
+
+/// The hooks to run during translation, in order
+#[derive(Clone, Default)]
+pub struct TranslationHooks(Vec<Arc<dyn TranslationHook>>);
+
+impl TranslationHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add<H: TranslationHook + 'static>(&mut self, hook: H) {
+        self.0.push(Arc::new(hook));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The first translation a `before_decl` hook provides, if any
+    pub fn before_decl(&self, ctx: &TypedAstContext, decl_id: CDeclId) -> Option<ConvertedDecl> {
+        self.0.iter().filter_map(|hook| hook.before_decl(ctx, decl_id)).next()
+    }
+
+    pub fn after_decl(
+        &self,
+        ctx: &TypedAstContext,
+        decl_id: CDeclId,
+        decl: ConvertedDecl,
+    ) -> ConvertedDecl {
+        self.0
+            .iter()
+            .fold(decl, |decl, hook| hook.after_decl(ctx, decl_id, decl))
+    }
+
+    /// The first translation a `before_expr` hook provides, if any
+    pub fn before_expr(&self, ctx: &TypedAstContext, expr_id: CExprId) -> Option<P<Expr>> {
+        self.0.iter().filter_map(|hook| hook.before_expr(ctx, expr_id)).next()
+    }
+
+    pub fn after_expr(&self, ctx: &TypedAstContext, expr_id: CExprId, expr: P<Expr>) -> P<Expr> {
+        self.0
+            .iter()
+            .fold(expr, |expr, hook| hook.after_expr(ctx, expr_id, expr))
+    }
+
+    /// The first translation a `before_type` hook provides, if any
+    pub fn before_type(&self, ctx: &TypedAstContext, type_id: CTypeId) -> Option<P<Ty>> {
+        self.0.iter().filter_map(|hook| hook.before_type(ctx, type_id)).next()
+    }
+
+    pub fn after_type(&self, ctx: &TypedAstContext, type_id: CTypeId, ty: P<Ty>) -> P<Ty> {
+        self.0
+            .iter()
+            .fold(ty, |ty, hook| hook.after_type(ctx, type_id, ty))
+    }
+}
+
+impl fmt::Debug for TranslationHooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TranslationHooks({} hooks)", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use c2rust_ast_builder::mk;
+    use c2rust_ast_printer::pprust;
+    use syntax_pos::edition::Edition;
+
+    /// Translates the C type 1 to `$name` and wraps every type in `$wrapper<..>`
+    struct TypeHook(&'static str, &'static str);
+
+    impl TranslationHook for TypeHook {
+        fn before_type(&self, _ctx: &TypedAstContext, type_id: CTypeId) -> Option<P<Ty>> {
+            if type_id == CTypeId(1) {
+                Some(mk().path_ty(vec![self.0]))
+            } else {
+                None
+            }
+        }
+
+        fn after_type(&self, _ctx: &TypedAstContext, _type_id: CTypeId, ty: P<Ty>) -> P<Ty> {
+            let args = mk().angle_bracketed_args(vec![ty]);
+            mk().path_ty(vec![mk().path_segment_with_args(self.1, args)])
+        }
+    }
+
+    #[test]
+    fn hooks_run_in_order() {
+        syntax::with_globals(Edition::Edition2018, || {
+            let ctx = TypedAstContext::new(&[]);
+            let mut hooks = TranslationHooks::new();
+            hooks.add(TypeHook("u8", "Option"));
+            hooks.add(TypeHook("u16", "Box"));
+
+            let before = |type_id| {
+                hooks
+                    .before_type(&ctx, type_id)
+                    .map(|ty| pprust::ty_to_string(&ty))
+            };
+            assert_eq!(before(CTypeId(1)), Some("u8".to_string()));
+            assert_eq!(before(CTypeId(2)), None);
+
+            let ty = hooks.after_type(&ctx, CTypeId(2), mk().path_ty(vec!["i32"]));
+            assert_eq!(pprust::ty_to_string(&ty), "Box<Option<i32>>");
+        })
+    }
+}
@@ -17,12 +17,14 @@ extern crate c2rust_ast_exporter;
 extern crate clap;
 extern crate itertools;
 extern crate libc;
+extern crate rayon;
 extern crate regex;
 extern crate serde_json;
 #[macro_use]
 extern crate log;
 extern crate fern;
 extern crate strum;
+extern crate toml;
 #[macro_use]
 extern crate strum_macros;
 #[macro_use]
@@ -30,43 +32,92 @@ extern crate failure;
 
 #[macro_use]
 mod diagnostics;
+mod error;
 
 pub mod build_files;
 pub mod c_ast;
 pub mod cfg;
 mod compile_cmds;
 pub mod convert_type;
+pub mod export_list;
+pub mod hooks;
+mod multi_config;
 pub mod renamer;
+pub mod report;
 pub mod rust_ast;
+pub mod sys_crate;
 pub mod translator;
+pub mod type_map;
 pub mod with_stmts;
 
-use std::collections::HashSet;
-use std::fs::{self, File};
-use std::io;
-use std::io::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Mutex;
+use std::thread;
 
 use failure::Error;
+use indexmap::IndexMap;
+use rayon::prelude::*;
 use regex::Regex;
 
 use crate::c_ast::Printer;
 use crate::c_ast::*;
+pub use crate::compile_cmds::CompileCmdOptions;
 pub use crate::diagnostics::Diagnostic;
+pub use crate::error::TranspileError;
 use c2rust_ast_exporter as ast_exporter;
 
-use crate::build_files::{emit_build_files, get_build_dir, CrateConfig};
-use crate::compile_cmds::get_compile_commands;
+use crate::build_files::{emit_build_files, get_build_dir, module_path, CrateConfig};
+use crate::compile_cmds::{get_compile_commands, RewrittenCompileCmds};
 use crate::convert_type::RESERVED_NAMES;
-pub use crate::translator::ReplaceMode;
+pub use crate::export_list::ExportList;
+pub use crate::hooks::{TranslationHook, TranslationHooks};
+pub use crate::multi_config::CfgConfiguration;
+pub use crate::renamer::{NameCase, NamingPolicy, ReservedStyle};
+pub use crate::sys_crate::SysCrate;
+pub use crate::translator::{
+    AllocaMode, Derive, IntegerConversionMode, LintAllowMode, LongDoubleMode, ReplaceMode,
+    SignedArithmeticMode, ThreadLocalMode,
+};
+pub use crate::type_map::TypeMap;
+use crate::report::{FileReport, RunReport};
+use crate::translator::{
+    HarnessFn, LinkedStatics, SharedInlineFn, SourceMapEntry, XCheckSourceEntry,
+};
 use std::prelude::v1::Vec;
 use syntax_pos::edition::Edition;
 
 type PragmaVec = Vec<(&'static str, Vec<&'static str>)>;
 type PragmaSet = indexmap::IndexSet<(&'static str, &'static str)>;
 type CrateSet = indexmap::IndexSet<ExternCrate>;
-type TranspileResult = Result<(PathBuf, PragmaVec, CrateSet), ()>;
+/// A written translation
+type EmittedFile = (
+    PathBuf,
+    PragmaVec,
+    CrateSet,
+    FileReport,
+    Vec<SharedInlineFn>,
+    LinkedStatics,
+    Vec<HarnessFn>,
+);
+
+/// The translation of a single input file, ready to be written out
+struct TranslatedFile {
+    input_path: PathBuf,
+    output_path: PathBuf,
+    translation: String,
+    pragmas: PragmaVec,
+    crates: CrateSet,
+    xcheck_source_map: Vec<XCheckSourceEntry>,
+    source_map: Vec<SourceMapEntry>,
+    report: FileReport,
+    shared_inline_fns: Vec<SharedInlineFn>,
+    linked_statics: LinkedStatics,
+    harness_fns: Vec<HarnessFn>,
+}
 
 /// Configuration settings for the translation process
 #[derive(Debug)]
@@ -86,6 +137,15 @@ pub struct TranspilerConfig {
     pub incremental_relooper: bool,
     pub fail_on_multiple: bool,
     pub filter: Option<Regex>,
+    /// Which entries of the compilation database to translate, and changes
+    /// to their clang arguments
+    pub compile_cmd_options: CompileCmdOptions,
+    /// Only translate the bodies of functions whose names match, and declare
+    /// all other non-static functions as `extern`
+    pub function_filter: Option<Regex>,
+    /// Only translate the bodies of functions defined in files whose paths
+    /// match, and declare all other non-static functions as `extern`
+    pub file_filter: Option<Regex>,
     pub debug_relooper_labels: bool,
     pub cross_checks: bool,
     pub cross_check_backend: String,
@@ -98,18 +158,116 @@ pub struct TranspilerConfig {
     pub panic_on_translator_failure: bool,
     pub emit_modules: bool,
     pub fail_on_error: bool,
+    /// Replace functions whose bodies fail to translate with stubs, and report
+    /// every skipped item in a `.skipped.json` file next to the translation
+    pub best_effort: bool,
     pub replace_unsupported_decls: ReplaceMode,
+    pub thread_local_mode: ThreadLocalMode,
+    pub long_double_mode: LongDoubleMode,
+    pub integer_conversions: IntegerConversionMode,
+    pub signed_arithmetic: SignedArithmeticMode,
+    pub naming: NamingPolicy,
+    /// Where to allow the lints translated code triggers
+    pub lint_allow_mode: LintAllowMode,
+    /// Attributes to add to every translated function, without their `#[]`
+    pub fn_attributes: Vec<String>,
+    /// Where to allocate the memory `alloca` returns
+    pub alloca_mode: AllocaMode,
+    /// Traits to implement for translated types where possible
+    pub derives: HashSet<Derive>,
     pub translate_valist: bool,
+    pub translate_simd: bool,
+    pub check_abi: bool,
+    pub emit_layout_assertions: bool,
+    pub union_accessors: bool,
     pub overwrite_existing: bool,
     pub reduce_type_annotations: bool,
+    /// Translate simple counting `for` loops as loops over a range
+    pub range_loops: bool,
+    /// Translate local pointers that own a `malloc`ed allocation as `Box` or
+    /// `Vec`
+    pub owned_allocations: bool,
+    /// Translate local pointers that are checked against null before every
+    /// use as `Option`s
+    pub option_pointers: bool,
+    /// Replace `goto`s to a cleanup label at the end of a function with a
+    /// copy of the cleanup
+    pub structured_cleanup: bool,
+    /// Translate subscripts of arrays of known size as Rust indexing, which
+    /// panics out of bounds, even where the element's address is taken
+    pub checked_indexing: bool,
+    /// Translate `__builtin_unreachable()` as `unreachable!()`, which panics,
+    /// instead of `unreachable_unchecked()`
+    pub checked_unreachable: bool,
     pub reorganize_definitions: bool,
+    pub split_included_sources: bool,
     pub enabled_warnings: HashSet<Diagnostic>,
     pub emit_no_std: bool,
+    /// Use `core::ffi` types and our own C library declarations instead of `libc`
+    pub emit_no_libc: bool,
+    /// Emit a `#![no_std]` crate without a Rust `main`, since there is no
+    /// runtime to start one from. `emit_no_std` alone only avoids `std` paths.
+    pub emit_no_std_crate: bool,
+    /// Rust path of a module providing `malloc`, `calloc`, `realloc` and `free`,
+    /// which calls to the C allocation functions are redirected to
+    pub alloc_shim: Option<String>,
     pub output_dir: Option<PathBuf>,
     pub translate_const_macros: bool,
     pub translate_fn_macros: bool,
+    /// Replace calls to some C library functions with their Rust equivalents
+    pub lower_libc_calls: bool,
+    /// Translate `assert`s as `debug_assert!` instead of `assert!`
+    pub debug_asserts: bool,
+    /// Read `errno` through `std::io::Error::last_os_error` instead of the
+    /// function of the C library returning its location
+    pub portable_errno: bool,
+    /// Translate under each of these macro configurations and guard the
+    /// items that differ between them with `#[cfg]`
+    pub cfg_configurations: Vec<CfgConfiguration>,
+    /// Target triple to compile the C sources for, if not the host
+    pub target: Option<String>,
+    /// Write a JSON report summarizing the translation of every file to this path
+    pub report_file: Option<PathBuf>,
+    /// Write a JSON map from the C names of renamed items to their Rust names
+    /// to this path
+    pub rename_map: Option<PathBuf>,
+    /// Write a JSON source map from the lines of each translated module back
+    /// to the C declarations and statements they were translated from
+    pub source_map: bool,
+    /// Add the C source of every translated function and global variable as a
+    /// comment above its translation
+    pub annotate_with_c_source: bool,
+    /// Format every translated module with `rustfmt` before writing it, unless
+    /// a source map refers to its unformatted lines
+    pub rustfmt: bool,
     pub disable_refactoring: bool,
     pub log_level: log::LevelFilter,
+    /// Number of threads to translate independent files on, or one per CPU if not given
+    pub jobs: Option<usize>,
+    /// Callbacks that customize the translation, for library users
+    pub hooks: TranslationHooks,
+    /// Existing Rust types to use instead of translating some C types
+    pub type_map: TypeMap,
+    /// A `-sys` crate to import declarations from instead of translating them
+    pub sys_crate: Option<SysCrate>,
+    /// Only the functions and variables listed keep their C symbols and are
+    /// public, if given
+    pub export_list: Option<ExportList>,
+    /// Only declare the functions and variables other translation units can
+    /// link to, and translate the types they use, like bindgen
+    pub emit_bindings_only: bool,
+    /// Translate `static inline` functions from headers once, into a shared
+    /// `inline_fns` module, instead of into every module that includes them
+    pub share_inline_fns: bool,
+    /// Translate the declarations of a translation unit that none of its
+    /// non-static definitions use, instead of omitting them
+    pub keep_unused: bool,
+    /// Declarations to keep, along with everything they use, when omitting
+    /// unused declarations
+    pub root_decls: Vec<Regex>,
+    /// Order the items of each module by their dependencies instead of by
+    /// the location of their C declarations
+    pub order_items: bool,
 
     // Options that control build files
     /// Emit `Cargo.toml` and `lib.rs`
@@ -117,6 +275,12 @@ pub struct TranspilerConfig {
     /// Names of translation units containing main functions that we should make
     /// into binaries
     pub binaries: Vec<String>,
+    /// Translation units whose paths match are test drivers, emitted as
+    /// integration tests in `tests/`
+    pub test_files: Option<Regex>,
+    /// Emit tests comparing the exported functions of the crate with the C
+    /// they were translated from
+    pub differential_harness: bool,
 }
 
 impl TranspilerConfig {
@@ -126,11 +290,254 @@ impl TranspilerConfig {
         self.binaries.contains(&name)
     }
 
+    /// Test drivers need a crate directory to put their `tests/` in
+    fn is_test_file(&self, input_path: &Path) -> bool {
+        self.output_dir.is_some()
+            && self.test_files.as_ref().map_or(false, |pattern| {
+                pattern.is_match(&input_path.to_string_lossy())
+            })
+    }
+
+    /// Inline functions can only be shared between the modules of a crate
+    fn shares_inline_fns(&self) -> bool {
+        self.share_inline_fns && self.emit_build_files
+    }
+
+    /// Definitions and `extern` declarations of variables are resolved across
+    /// the modules of a crate, unless the refactorer reorganizes them
+    fn resolves_linked_statics(&self) -> bool {
+        self.emit_build_files && !self.reorganize_definitions
+    }
+
     fn crate_name(&self) -> String {
         self.output_dir.as_ref().and_then(
             |x| x.file_name().map(|x| x.to_string_lossy().into_owned())
         ).unwrap_or_else(|| "c2rust_out".into())
     }
+
+    /// Start from the defaults of the command line
+    pub fn builder() -> TranspilerConfigBuilder {
+        TranspilerConfigBuilder {
+            config: TranspilerConfig::default(),
+        }
+    }
+
+    /// Turn on the options that others need: binaries, tests and the
+    /// differential harness are emitted with the build files, which need
+    /// modules
+    pub fn apply_implied_options(&mut self) {
+        if !self.binaries.is_empty() || self.test_files.is_some() || self.differential_harness {
+            self.emit_build_files = true;
+        }
+        if self.emit_build_files {
+            self.emit_modules = true;
+        }
+    }
+}
+
+/// The defaults of the options, which the command line also falls back to for
+/// the options it isn't given
+impl Default for TranspilerConfig {
+    fn default() -> Self {
+        TranspilerConfig {
+            dump_untyped_context: false,
+            dump_typed_context: false,
+            pretty_typed_context: false,
+            dump_function_cfgs: false,
+            json_function_cfgs: false,
+            dump_cfg_liveness: false,
+            dump_structures: false,
+            verbose: false,
+            debug_ast_exporter: false,
+
+            incremental_relooper: true,
+            fail_on_multiple: false,
+            filter: None,
+            compile_cmd_options: CompileCmdOptions::default(),
+            function_filter: None,
+            file_filter: None,
+            debug_relooper_labels: false,
+            cross_checks: false,
+            cross_check_backend: "zstd-logging".to_string(),
+            cross_check_configs: vec![],
+            prefix_function_names: None,
+            translate_asm: true,
+            use_c_loop_info: true,
+            use_c_multiple_info: true,
+            simplify_structures: true,
+            panic_on_translator_failure: false,
+            emit_modules: false,
+            fail_on_error: false,
+            best_effort: false,
+            replace_unsupported_decls: ReplaceMode::Extern,
+            thread_local_mode: ThreadLocalMode::Attribute,
+            long_double_mode: LongDoubleMode::F128,
+            integer_conversions: IntegerConversionMode::Default,
+            signed_arithmetic: SignedArithmeticMode::Plain,
+            naming: NamingPolicy::default(),
+            lint_allow_mode: LintAllowMode::Crate,
+            fn_attributes: vec![],
+            alloca_mode: AllocaMode::Vec,
+            derives: HashSet::new(),
+            translate_valist: true,
+            translate_simd: false,
+            check_abi: false,
+            emit_layout_assertions: false,
+            union_accessors: false,
+            overwrite_existing: false,
+            reduce_type_annotations: false,
+            range_loops: false,
+            owned_allocations: false,
+            option_pointers: false,
+            structured_cleanup: false,
+            checked_indexing: false,
+            checked_unreachable: false,
+            reorganize_definitions: false,
+            split_included_sources: false,
+            enabled_warnings: HashSet::new(),
+            emit_no_std: false,
+            emit_no_libc: false,
+            emit_no_std_crate: false,
+            alloc_shim: None,
+            output_dir: None,
+            translate_const_macros: false,
+            translate_fn_macros: false,
+            lower_libc_calls: false,
+            debug_asserts: false,
+            portable_errno: false,
+            cfg_configurations: vec![],
+            target: None,
+            report_file: None,
+            rename_map: None,
+            source_map: false,
+            annotate_with_c_source: false,
+            rustfmt: false,
+            disable_refactoring: false,
+            log_level: log::LevelFilter::Warn,
+            jobs: None,
+            hooks: TranslationHooks::new(),
+            type_map: TypeMap::default(),
+            sys_crate: None,
+            export_list: None,
+            emit_bindings_only: false,
+            share_inline_fns: false,
+            keep_unused: false,
+            root_decls: vec![],
+            order_items: false,
+
+            emit_build_files: false,
+            binaries: vec![],
+            test_files: None,
+            differential_harness: false,
+        }
+    }
+}
+
+/// Builds a `TranspilerConfig` for library users. The setters cover the
+/// options most tools need; `with` sets any other field of the config.
+#[derive(Debug)]
+pub struct TranspilerConfigBuilder {
+    config: TranspilerConfig,
+}
+
+impl TranspilerConfigBuilder {
+    /// Write the translation to this directory instead of next to the C
+    /// sources
+    pub fn output_dir<P: Into<PathBuf>>(mut self, output_dir: P) -> Self {
+        self.config.output_dir = Some(output_dir.into());
+        self
+    }
+
+    /// Emit `Cargo.toml` and `lib.rs` for the translated crate
+    pub fn emit_build_files(mut self, emit_build_files: bool) -> Self {
+        self.config.emit_build_files = emit_build_files;
+        self
+    }
+
+    /// Make the translation units with these names into binaries
+    pub fn binaries<I: IntoIterator<Item = String>>(mut self, binaries: I) -> Self {
+        self.config.binaries = binaries.into_iter().collect();
+        self
+    }
+
+    pub fn overwrite_existing(mut self, overwrite_existing: bool) -> Self {
+        self.config.overwrite_existing = overwrite_existing;
+        self
+    }
+
+    /// Only translate the input files whose paths match
+    pub fn filter(mut self, filter: Regex) -> Self {
+        self.config.filter = Some(filter);
+        self
+    }
+
+    pub fn reorganize_definitions(mut self, reorganize_definitions: bool) -> Self {
+        self.config.reorganize_definitions = reorganize_definitions;
+        self
+    }
+
+    pub fn translate_const_macros(mut self, translate_const_macros: bool) -> Self {
+        self.config.translate_const_macros = translate_const_macros;
+        self
+    }
+
+    pub fn translate_fn_macros(mut self, translate_fn_macros: bool) -> Self {
+        self.config.translate_fn_macros = translate_fn_macros;
+        self
+    }
+
+    pub fn emit_no_std(mut self, emit_no_std: bool) -> Self {
+        self.config.emit_no_std = emit_no_std;
+        self
+    }
+
+    pub fn naming(mut self, naming: NamingPolicy) -> Self {
+        self.config.naming = naming;
+        self
+    }
+
+    pub fn enabled_warnings<I: IntoIterator<Item = Diagnostic>>(mut self, warnings: I) -> Self {
+        self.config.enabled_warnings = warnings.into_iter().collect();
+        self
+    }
+
+    pub fn log_level(mut self, log_level: log::LevelFilter) -> Self {
+        self.config.log_level = log_level;
+        self
+    }
+
+    /// Translate on this many threads instead of one per CPU
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.config.jobs = Some(jobs);
+        self
+    }
+
+    /// Run a hook during translation, after the ones already added
+    pub fn hook<H: TranslationHook + 'static>(mut self, hook: H) -> Self {
+        self.config.hooks.add(hook);
+        self
+    }
+
+    pub fn type_map(mut self, type_map: TypeMap) -> Self {
+        self.config.type_map = type_map;
+        self
+    }
+
+    pub fn export_list(mut self, export_list: ExportList) -> Self {
+        self.config.export_list = Some(export_list);
+        self
+    }
+
+    /// Set any other options of the config
+    pub fn with<F: FnOnce(&mut TranspilerConfig)>(mut self, f: F) -> Self {
+        f(&mut self.config);
+        self
+    }
+
+    pub fn build(mut self) -> TranspilerConfig {
+        self.config.apply_implied_options();
+        self.config
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -138,9 +545,11 @@ pub enum ExternCrate {
     C2RustBitfields,
     C2RustAsmCasts,
     F128,
+    Half,
     NumTraits,
     Memoffset,
     Libc,
+    Alloca,
 }
 
 #[derive(Serialize)]
@@ -168,9 +577,11 @@ impl From<ExternCrate> for ExternCrateDetails {
             ExternCrate::C2RustBitfields => Self::new("c2rust-bitfields", "0.3", true),
             ExternCrate::C2RustAsmCasts => Self::new("c2rust-asm-casts", "0.1", true),
             ExternCrate::F128 => Self::new("f128", "0.2", false),
+            ExternCrate::Half => Self::new("half", "1.4", false),
             ExternCrate::NumTraits => Self::new("num-traits", "0.2", true),
             ExternCrate::Memoffset => Self::new("memoffset", "0.5", true),
             ExternCrate::Libc => Self::new("libc", "0.2", false),
+            ExternCrate::Alloca => Self::new("alloca", "0.3", false),
         }
     }
 }
@@ -223,25 +634,96 @@ fn get_module_name(
     file.to_str().map(String::from)
 }
 
-/// Main entry point to transpiler. Called from CLI tools with the result of
-/// clap::App::get_matches().
-pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]) {
+/// Summary of a transpiler run
+#[derive(Debug, Default)]
+pub struct TranspileResult {
+    /// The Rust modules written, including the ones shared by the modules of
+    /// a crate
+    pub modules: Vec<PathBuf>,
+    /// Directory the modules and build files were written to
+    pub build_dir: PathBuf,
+    /// What was translated, and the input files that failed to translate
+    pub report: RunReport,
+}
+
+/// Main entry point to transpiler. Translates every input file of the
+/// compilation database `cc_db`, passing `extra_clang_args` to clang. Input
+/// files that fail to translate are skipped and listed in the report of the
+/// result.
+pub fn transpile(
+    tcfg: TranspilerConfig,
+    cc_db: &Path,
+    extra_clang_args: &[&str],
+) -> Result<TranspileResult, TranspileError> {
     diagnostics::init(tcfg.enabled_warnings.clone(), tcfg.log_level);
 
-    let lcmds = get_compile_commands(cc_db, &tcfg.filter).expect(&format!(
-        "Could not parse compile commands from {}",
-        cc_db.to_string_lossy()
-    ));
+    // Report malformed attributes now rather than in the middle of a translation
+    syntax::with_globals(Edition::Edition2018, || {
+        for attr in &tcfg.fn_attributes {
+            translator::parse_attribute(attr)?;
+        }
+        Ok(())
+    })
+    .map_err(|message| TranspileError::InvalidOption {
+        option: "function attribute",
+        message,
+    })?;
+
+    let (lcmds, changed) = get_compile_commands(cc_db, &tcfg.filter, &tcfg.compile_cmd_options)
+        .map_err(|e| TranspileError::Parse {
+            what: "compile commands",
+            path: cc_db.to_path_buf(),
+            message: e.to_string(),
+        })?;
+
+    // Clang reads the compilation database itself, so it has to be given the
+    // entries as we changed them
+    let rewritten_cc_db = if changed {
+        let rewritten = RewrittenCompileCmds::write(&lcmds).map_err(|cause| {
+            TranspileError::Write {
+                path: std::env::temp_dir(),
+                cause,
+            }
+        })?;
+        Some(rewritten)
+    } else {
+        None
+    };
+    let exporter_cc_db = rewritten_cc_db
+        .as_ref()
+        .map_or_else(|| cc_db.to_path_buf(), RewrittenCompileCmds::path);
 
     // Specify path to system include dir on macOS 10.14 and later. Disable the blocks extension.
     let clang_args: Vec<String> = get_extra_args_macos();
+    let target_arg = tcfg.target.as_ref().map(|triple| format!("--target={}", triple));
     let mut clang_args: Vec<&str> = clang_args.iter().map(AsRef::as_ref).collect();
     clang_args.extend_from_slice(extra_clang_args);
+    clang_args.extend(target_arg.as_ref().map(String::as_str));
+
+    if tcfg.export_list.is_some() && !tcfg.resolves_linked_statics() {
+        warn!(
+            "--export-list only applies to the modules of a crate; it is ignored without \
+             --emit-build-files and with --reorganize-definitions"
+        );
+    }
 
     let mut top_level_ccfg = None;
     let mut workspace_members = vec![];
+    let mut run_report = RunReport::default();
+    let mut all_modules = vec![];
     let mut num_transpiled_files = 0;
     let build_dir = get_build_dir(&tcfg, cc_db);
+
+    // Translation units are imported and translated in parallel, but the AST exporter keeps
+    // global clang state, so only one of them can be exported at a time.
+    let mut pool = rayon::ThreadPoolBuilder::new();
+    if let Some(jobs) = tcfg.jobs {
+        pool = pool.num_threads(jobs);
+    }
+    let pool = pool
+        .build()
+        .map_err(|e| TranspileError::ThreadPool(e.to_string()))?;
+    let exporter_lock = Mutex::new(());
     for lcmd in &lcmds {
         let cmds = &lcmd.cmd_inputs;
         let lcmd_name = lcmd.output
@@ -279,23 +761,45 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
             }
         }
 
-        let results = cmds
-            .iter()
-            .map(|cmd| transpile_single(&tcfg, cmd.abs_file(),
-                                        &ancestor_path,
-                                        &build_dir,
-                                        cc_db,
-                                        &clang_args))
-            .collect::<Vec<TranspileResult>>();
+        let input_paths = cmds.iter().map(|cmd| cmd.abs_file()).collect::<Vec<_>>();
+        let translations = pool.install(|| {
+            input_paths
+                .par_iter()
+                .map(|input_path| translate_single(&tcfg, input_path.clone(),
+                                            &ancestor_path,
+                                            &build_dir,
+                                            &exporter_cc_db,
+                                            &clang_args,
+                                            &exporter_lock))
+                .collect::<Vec<Result<TranslatedFile, ()>>>()
+        });
+        // Write out the translations in order, on this thread
+        let results = translations
+            .into_iter()
+            .map(|translated| match translated {
+                Ok(translated) => emit_single(&tcfg, translated, &ancestor_path).map(Ok),
+                Err(()) => Ok(Err(())),
+            })
+            .collect::<Result<Vec<Result<EmittedFile, ()>>, TranspileError>>()?;
         let mut modules = vec![];
         let mut modules_skipped = false;
         let mut pragmas = PragmaSet::new();
         let mut crates = CrateSet::new();
-        for res in results {
+        let mut inline_fns = IndexMap::new();
+        let mut linked_statics = vec![];
+        let mut harness_fns = vec![];
+        for (input_path, res) in input_paths.iter().zip(results) {
             match res {
-                Ok((module, pragma_vec, crate_set)) => {
+                Ok((module, pragma_vec, crate_set, file_report, shared_inline_fns, statics, fns)) => {
+                    for shared in shared_inline_fns {
+                        inline_fns.entry(shared.module).or_insert(shared.source);
+                    }
+                    linked_statics.push((module.clone(), statics));
+                    harness_fns.push((module.clone(), fns));
+                    all_modules.push(module.clone());
                     modules.push(module);
                     crates.extend(crate_set);
+                    run_report.files.push(file_report);
 
                     num_transpiled_files += 1;
                     for (key, vals) in pragma_vec {
@@ -306,6 +810,7 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
                 },
                 Err(_) => {
                     modules_skipped = true;
+                    run_report.failed_files.push(input_path.clone());
                 }
             }
         }
@@ -316,15 +821,40 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
             if modules_skipped {
                 // If we skipped a file, we may not have collected all required pragmas
                 warn!("Can't emit build files after incremental transpiler run; skipped.");
-                return;
+                if let Some(ref report_file) = tcfg.report_file {
+                    run_report.write(report_file)?;
+                }
+                if let Some(ref rename_map) = tcfg.rename_map {
+                    run_report.write_rename_map(rename_map)?;
+                }
+                return Ok(TranspileResult {
+                    modules: all_modules,
+                    build_dir,
+                    report: run_report,
+                });
+            }
+
+            // Modules shared by the modules of the crate
+            let mut shared_modules = vec![];
+            if !inline_fns.is_empty() {
+                shared_modules.push(emit_inline_fns(&build_dir, &inline_fns)?);
+            }
+            if linked_statics.iter().any(|(_, statics)| !statics.tentative.is_empty()) {
+                shared_modules.push(emit_tentative_defs(&build_dir, &linked_statics)?);
+            }
+            if linked_statics.iter().any(|(_, statics)| !statics.externs.is_empty()) {
+                shared_modules.push(emit_extern_statics(&tcfg, &build_dir, &linked_statics)?);
             }
+            all_modules.extend(shared_modules.iter().cloned());
+            modules.extend(shared_modules);
 
             let ccfg = CrateConfig {
                 crate_name: lcmd_name.clone(),
                 modules,
                 pragmas,
                 crates,
-                link_cmd: lcmd
+                link_cmd: lcmd,
+                harness_fns,
             };
             if lcmd.top_level {
                 top_level_ccfg = Some(ccfg);
@@ -337,16 +867,26 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
         }
     }
 
-    if num_transpiled_files == 0 {
-        warn!("No C files found in compile_commands.json; nothing to do.");
-        return;
+    if let Some(ref report_file) = tcfg.report_file {
+        run_report.write(report_file)?;
+    }
+    if let Some(ref rename_map) = tcfg.rename_map {
+        run_report.write_rename_map(rename_map)?;
     }
 
-    if tcfg.emit_build_files {
+    if num_transpiled_files == 0 {
+        warn!("No C files found in compile_commands.json; nothing to do.");
+    } else if tcfg.emit_build_files {
         let crate_file = emit_build_files(&tcfg, &build_dir, top_level_ccfg, Some(workspace_members));
         reorganize_definitions(&tcfg, &build_dir, crate_file)
             .unwrap_or_else(|e| warn!("Reorganizing definitions failed: {}", e));
     }
+
+    Ok(TranspileResult {
+        modules: all_modules,
+        build_dir,
+        report: run_report,
+    })
 }
 
 /// Ensure that clang can locate the system headers on macOS 10.14+.
@@ -442,21 +982,23 @@ fn reorganize_definitions(
     Ok(())
 }
 
-fn transpile_single(
+/// Import and translate a single input file. This runs concurrently for
+/// independent files, so it must not write any output.
+fn translate_single(
     tcfg: &TranspilerConfig,
     input_path: PathBuf,
     ancestor_path: &Path,
     build_dir: &Path,
     cc_db: &Path,
     extra_clang_args: &[&str],
-) -> TranspileResult {
+    exporter_lock: &Mutex<()>,
+) -> Result<TranslatedFile, ()> {
     let output_path = get_output_path(tcfg, &input_path, ancestor_path, build_dir);
     if output_path.exists() && !tcfg.overwrite_existing {
         warn!("Skipping existing file {}", output_path.display());
         return Err(());
     }
 
-    let file = input_path.file_name().unwrap().to_str().unwrap();
     if !input_path.exists() {
         warn!(
             "Input C file {} does not exist, skipping!",
@@ -465,17 +1007,328 @@ fn transpile_single(
         return Err(());
     }
 
+    let (
+        translation,
+        pragmas,
+        crates,
+        xcheck_source_map,
+        source_map,
+        report,
+        shared_inline_fns,
+        linked_statics,
+        harness_fns,
+    ) = if tcfg.cfg_configurations.is_empty() {
+        translate_input(tcfg, &input_path, ancestor_path, cc_db, extra_clang_args, exporter_lock)?
+    } else {
+        translate_configurations(
+            tcfg,
+            &input_path,
+            ancestor_path,
+            cc_db,
+            extra_clang_args,
+            exporter_lock,
+        )?
+    };
+
+    Ok(TranslatedFile {
+        input_path,
+        output_path,
+        translation,
+        pragmas,
+        crates,
+        xcheck_source_map,
+        source_map,
+        report,
+        shared_inline_fns,
+        linked_statics,
+        harness_fns,
+    })
+}
+
+/// Write a translated file and its side files
+fn emit_single(
+    tcfg: &TranspilerConfig,
+    translated: TranslatedFile,
+    ancestor_path: &Path,
+) -> Result<EmittedFile, TranspileError> {
+    let TranslatedFile {
+        input_path,
+        output_path,
+        translation,
+        pragmas,
+        crates,
+        xcheck_source_map,
+        source_map,
+        mut report,
+        shared_inline_fns,
+        linked_statics,
+        harness_fns,
+    } = translated;
+
+    let translation = if tcfg.rustfmt && !tcfg.source_map {
+        rustfmt(translation, &output_path)
+    } else {
+        translation
+    };
+    write_file(&output_path, translation)?;
+
+    if tcfg.cross_checks && !xcheck_source_map.is_empty() {
+        let map_path = output_path.with_extension("xcheck_map.json");
+        let map_json = serde_json::to_string_pretty(&xcheck_source_map)
+            .expect("Unable to serialize cross-check source map");
+        write_file(&map_path, map_json)?;
+
+        let config_path = output_path.with_extension("xcheck.c2r");
+        write_file(&config_path, translator::clang_xcheck_config(&xcheck_source_map, ancestor_path))?;
+    }
+
+    if tcfg.source_map {
+        let map_path = output_path.with_extension("source_map.json");
+        let map_json =
+            serde_json::to_string_pretty(&source_map).expect("Unable to serialize source map");
+        write_file(&map_path, map_json)?;
+    }
+
+    if tcfg.best_effort {
+        let report_path = output_path.with_extension("skipped.json");
+        let report_json = serde_json::to_string_pretty(&report.skipped)
+            .expect("Unable to serialize best-effort report");
+        write_file(&report_path, report_json)?;
+        if !report.skipped.is_empty() {
+            warn!(
+                "Skipped or stubbed out {} items in {}, see {}",
+                report.skipped.len(),
+                input_path.display(),
+                report_path.display(),
+            );
+        }
+    }
+
+    report.rs_file = Some(output_path.clone());
+    Ok((
+        output_path,
+        pragmas,
+        crates,
+        report,
+        shared_inline_fns,
+        linked_statics,
+        harness_fns,
+    ))
+}
+
+/// Format a translation with `rustfmt`, or return it unchanged if that fails.
+/// `$RUSTFMT` overrides the `rustfmt` on the `PATH`, like it does for `cargo fmt`.
+fn rustfmt(translation: String, output_path: &Path) -> String {
+    let rustfmt = std::env::var_os("RUSTFMT").unwrap_or_else(|| "rustfmt".into());
+    let child = process::Command::new(rustfmt)
+        .args(&["--edition", "2018"])
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Could not run rustfmt on {}: {}", output_path.display(), e);
+            return translation;
+        }
+    };
+
+    // Write the input on its own thread so rustfmt can't block on a full pipe
+    let mut stdin = child.stdin.take().expect("rustfmt stdin is piped");
+    let input = translation.clone();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output();
+    let written = writer.join().expect("rustfmt input thread panicked");
+
+    match (output, written) {
+        (Ok(ref output), Ok(())) if output.status.success() => {
+            match String::from_utf8(output.stdout.clone()) {
+                Ok(formatted) => formatted,
+                Err(_) => translation,
+            }
+        }
+        (Ok(output), _) => {
+            warn!(
+                "rustfmt failed on {}, writing it unformatted:\n{}",
+                output_path.display(),
+                String::from_utf8_lossy(&output.stderr),
+            );
+            translation
+        }
+        (Err(e), _) => {
+            warn!("Could not run rustfmt on {}: {}", output_path.display(), e);
+            translation
+        }
+    }
+}
+
+fn write_file<C: AsRef<[u8]>>(path: &Path, contents: C) -> Result<(), TranspileError> {
+    fs::write(path, contents).map_err(|cause| TranspileError::Write {
+        path: path.to_path_buf(),
+        cause,
+    })
+}
+
+/// Write the `inline_fns` module holding the inline functions shared by the
+/// modules of a crate, keyed by submodule name
+fn emit_inline_fns(
+    build_dir: &Path,
+    inline_fns: &IndexMap<String, String>,
+) -> Result<PathBuf, TranspileError> {
+    let mut contents = String::new();
+    for (module, source) in inline_fns {
+        contents.push_str(&format!("pub mod {} {{
+{}}}
+
+", module, source));
+    }
+    let output_path = build_dir.join("inline_fns.rs");
+    write_file(&output_path, contents)?;
+    Ok(output_path)
+}
+
+/// Write the `tentative_defs` module defining each variable that only has
+/// tentative definitions once for all modules of a crate
+fn emit_tentative_defs(
+    build_dir: &Path,
+    linked_statics: &[(PathBuf, LinkedStatics)],
+) -> Result<PathBuf, TranspileError> {
+    let defined: HashSet<&str> = linked_statics
+        .iter()
+        .flat_map(|(_, statics)| statics.defined.iter().map(|def| def.symbol.as_str()))
+        .collect();
+    // The Rust name each variable is defined under here, and all names used
+    let mut names: IndexMap<&str, &str> = IndexMap::new();
+    let mut used_names = HashSet::new();
+    let mut contents = String::new();
+    for def in linked_statics.iter().flat_map(|(_, statics)| &statics.tentative) {
+        if !used_names.insert(def.rust_name.as_str()) {
+            continue;
+        }
+        match names.get(def.symbol.as_str()) {
+            // Modules may have renamed the variable differently
+            Some(&name) => {
+                contents.push_str(&format!("pub use self::{} as {};\n", name, def.rust_name));
+            }
+            None => {
+                names.insert(&def.symbol, &def.rust_name);
+                // Defined with an initializer by some module
+                let source = if defined.contains(def.symbol.as_str()) {
+                    &def.declaration
+                } else {
+                    &def.definition
+                };
+                contents.push_str(source);
+                contents.push_str("\n");
+            }
+        }
+    }
+    let output_path = build_dir.join("tentative_defs.rs");
+    write_file(&output_path, contents)?;
+    Ok(output_path)
+}
+
+/// Write the `extern_statics` module resolving each variable declared `extern`
+/// to the module defining it, or declaring it `extern` if no module does
+fn emit_extern_statics(
+    tcfg: &TranspilerConfig,
+    build_dir: &Path,
+    linked_statics: &[(PathBuf, LinkedStatics)],
+) -> Result<PathBuf, TranspileError> {
+    let mut definitions: HashMap<&str, Vec<String>> = HashMap::new();
+    for (module, statics) in linked_statics {
+        let mod_path = match module_path(tcfg, build_dir, module) {
+            Some(mod_path) => mod_path,
+            None => continue,
+        };
+        for def in &statics.defined {
+            if let Some(ref rust_name) = def.rust_name {
+                let mut path = vec!["crate".to_string()];
+                path.extend(mod_path.iter().cloned());
+                path.push(rust_name.clone());
+                definitions.entry(def.symbol.as_str()).or_insert(path);
+            }
+        }
+    }
+    for (_, statics) in linked_statics {
+        for def in &statics.tentative {
+            let path = vec!["crate".to_string(), "tentative_defs".to_string(), def.rust_name.clone()];
+            definitions.entry(def.symbol.as_str()).or_insert(path);
+        }
+    }
+
+    // The Rust name each undefined variable is declared under here, and all
+    // names used
+    let mut names: IndexMap<&str, &str> = IndexMap::new();
+    let mut used_names = HashSet::new();
+    let mut contents = String::new();
+    for decl in linked_statics.iter().flat_map(|(_, statics)| &statics.externs) {
+        if !used_names.insert(decl.rust_name.as_str()) {
+            continue;
+        }
+        if let Some(path) = definitions.get(decl.symbol.as_str()) {
+            let import = path.join("::");
+            if path.last() == Some(&decl.rust_name) {
+                contents.push_str(&format!("pub use {};\n", import));
+            } else {
+                contents.push_str(&format!("pub use {} as {};\n", import, decl.rust_name));
+            }
+            continue;
+        }
+        match names.get(decl.symbol.as_str()) {
+            // Modules may have renamed the variable differently
+            Some(&name) => {
+                contents.push_str(&format!("pub use self::{} as {};\n", name, decl.rust_name));
+            }
+            None => {
+                names.insert(&decl.symbol, &decl.rust_name);
+                contents.push_str(&decl.declaration);
+                contents.push_str("\n");
+            }
+        }
+    }
+    let output_path = build_dir.join("extern_statics.rs");
+    write_file(&output_path, contents)?;
+    Ok(output_path)
+}
+
+type TranslateResult = Result<
+    (
+        String,
+        PragmaVec,
+        CrateSet,
+        Vec<XCheckSourceEntry>,
+        Vec<SourceMapEntry>,
+        FileReport,
+        Vec<SharedInlineFn>,
+        LinkedStatics,
+        Vec<HarnessFn>,
+    ),
+    (),
+>;
+
+/// Export the AST of a single input file and translate it
+fn translate_input(
+    tcfg: &TranspilerConfig,
+    input_path: &Path,
+    ancestor_path: &Path,
+    cc_db: &Path,
+    extra_clang_args: &[&str],
+    exporter_lock: &Mutex<()>,
+) -> TranslateResult {
+    let file = input_path.file_name().unwrap().to_str().unwrap();
+
     if tcfg.verbose {
         println!("Additional Clang arguments: {}", extra_clang_args.join(" "));
     }
 
     // Extract the untyped AST from the CBOR file
-    let untyped_context = match ast_exporter::get_untyped_ast(
-        input_path.as_path(),
-        cc_db,
-        extra_clang_args,
-        tcfg.debug_ast_exporter,
-    ) {
+    let exported = {
+        let _guard = exporter_lock.lock().unwrap_or_else(|e| e.into_inner());
+        ast_exporter::get_untyped_ast(input_path, cc_db, extra_clang_args, tcfg.debug_ast_exporter)
+    };
+    let untyped_context = match exported {
         Err(e) => {
             warn!(
                 "Error: {}. Skipping {}; is it well-formed C?",
@@ -494,7 +1347,7 @@ fn transpile_single(
         println!("{:#?}", untyped_context);
     }
 
-    // Convert this into a typed AST
+    // Convert this into a typed AST, and free the untyped one before translating
     let typed_context = {
         let conv = ConversionContext::new(&untyped_context);
         if conv.invalid_clang_ast && tcfg.fail_on_error {
@@ -502,6 +1355,7 @@ fn transpile_single(
         }
         conv.typed_context
     };
+    drop(untyped_context);
 
     if tcfg.dump_typed_context {
         println!("Clang AST");
@@ -514,22 +1368,99 @@ fn transpile_single(
     }
 
     // Perform the translation
-    let (translated_string, pragmas, crates) =
-        syntax::with_globals(Edition::Edition2018, move || {
-            translator::translate(typed_context, &tcfg, input_path)
-        });
+    let translation = syntax::with_globals(Edition::Edition2018, move || {
+        translator::translate(typed_context, &tcfg, input_path.to_path_buf(), ancestor_path)
+    });
 
-    let mut file = match File::create(&output_path) {
-        Ok(file) => file,
-        Err(e) => panic!("Unable to open file {} for writing: {}", output_path.display(), e),
-    };
+    Ok(translation)
+}
 
-    match file.write_all(translated_string.as_bytes()) {
-        Ok(()) => (),
-        Err(e) => panic!("Unable to write translation to file {}: {}", output_path.display(), e),
-    };
+/// Translate a single input file under every configuration of
+/// `tcfg.cfg_configurations` and merge the results
+fn translate_configurations(
+    tcfg: &TranspilerConfig,
+    input_path: &Path,
+    ancestor_path: &Path,
+    cc_db: &Path,
+    extra_clang_args: &[&str],
+    exporter_lock: &Mutex<()>,
+) -> TranslateResult {
+    let mut translations = vec![];
+    let mut pragmas = PragmaVec::new();
+    let mut crates = CrateSet::new();
+    let mut report: Option<FileReport> = None;
+    let mut shared_inline_fns: Vec<SharedInlineFn> = vec![];
+    let mut linked_statics = LinkedStatics::default();
+    let mut harness_fns: Vec<HarnessFn> = vec![];
+    for config in &tcfg.cfg_configurations {
+        let config_args = config.clang_args();
+        let mut clang_args = extra_clang_args.to_vec();
+        clang_args.extend(config_args.iter().map(String::as_str));
 
-    Ok((output_path, pragmas, crates))
+        let (
+            translation,
+            config_pragmas,
+            config_crates,
+            _,
+            _,
+            config_report,
+            config_inline_fns,
+            config_statics,
+            config_harness_fns,
+        ) = translate_input(tcfg, input_path, ancestor_path, cc_db, &clang_args, exporter_lock)?;
+        translations.push((config.name.as_str(), translation));
+        for (key, vals) in config_pragmas {
+            match pragmas.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, all_vals)) => {
+                    for val in vals {
+                        if !all_vals.contains(&val) {
+                            all_vals.push(val);
+                        }
+                    }
+                }
+                None => pragmas.push((key, vals)),
+            }
+        }
+        crates.extend(config_crates);
+        for shared in config_inline_fns {
+            if !shared_inline_fns.iter().any(|s| s.module == shared.module) {
+                shared_inline_fns.push(shared);
+            }
+        }
+        linked_statics.merge(config_statics);
+        // Test each function as translated in the first configuration
+        for harness_fn in config_harness_fns {
+            if !harness_fns.iter().any(|f| f.symbol == harness_fn.symbol) {
+                harness_fns.push(harness_fn);
+            }
+        }
+        match report {
+            Some(ref mut report) => report.merge(config_report),
+            None => report = Some(config_report),
+        }
+    }
+
+    let merged = syntax::with_globals(Edition::Edition2018, || {
+        multi_config::merge_translations(&translations)
+    });
+    match merged {
+        // Source maps refer to positions in a single translation
+        Ok(merged) => Ok((
+            merged,
+            pragmas,
+            crates,
+            vec![],
+            vec![],
+            report.unwrap_or_default(),
+            shared_inline_fns,
+            linked_statics,
+            harness_fns,
+        )),
+        Err(e) => {
+            warn!("Error: {}. Skipping {}", e, input_path.display());
+            Err(())
+        }
+    }
 }
 
 fn get_output_path(
@@ -552,7 +1483,17 @@ fn get_output_path(
     path_buf.set_file_name(file_name);
     path_buf.set_extension("rs");
 
-    if tcfg.output_dir.is_some() {
+    if tcfg.is_test_file(input_path) {
+        // Test drivers are integration tests of the crate
+        let tests_dir = build_dir.join("tests");
+        if !tests_dir.exists() {
+            fs::create_dir_all(&tests_dir).expect(&format!(
+                "couldn't create tests directory: {}",
+                tests_dir.display()
+            ));
+        }
+        tests_dir.join(get_module_name(&path_buf, false, true, false).unwrap())
+    } else if tcfg.output_dir.is_some() {
         let path_buf = path_buf.strip_prefix(ancestor_path)
             .expect("Couldn't strip common ancestor path");
 
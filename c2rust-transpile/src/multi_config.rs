@@ -0,0 +1,266 @@
+//! Preservation of conditionally compiled code. Each input is translated once
+//! per configuration of predefined macros and the translations are merged
+//! item by item. Items that differ between configurations are emitted once
+//! per variant, guarded by `#[cfg(feature = ...)]` on the configurations
+//! they were translated under.
+
+use indexmap::IndexMap;
+use rustc_parse::parse_crate_from_source_str;
+use syntax::ast::{AttrStyle, Item, ItemKind};
+use syntax::sess::ParseSess;
+use syntax::source_map::FilePathMapping;
+use syntax_pos::FileName;
+
+use crate::error::TranspileError;
+
+/// A named set of macros to predefine when exporting the C AST. The name is
+/// used as the cargo feature enabling the items of this configuration.
+#[derive(Debug, Clone)]
+pub struct CfgConfiguration {
+    pub name: String,
+    /// Macro definitions in `NAME` or `NAME=VALUE` form
+    pub defines: Vec<String>,
+}
+
+impl CfgConfiguration {
+    /// Parse a configuration given as `NAME:DEFINE,DEFINE,...`
+    pub fn parse(s: &str) -> Result<Self, TranspileError> {
+        let mut parts = s.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        if name.is_empty() {
+            return Err(TranspileError::InvalidOption {
+                option: "configuration",
+                message: format!("Missing configuration name in {}", s),
+            });
+        }
+        let defines = parts
+            .next()
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(String::from)
+            .collect();
+        Ok(CfgConfiguration {
+            name: name.to_string(),
+            defines,
+        })
+    }
+
+    /// Clang arguments predefining the macros of this configuration
+    pub fn clang_args(&self) -> Vec<String> {
+        self.defines.iter().map(|d| format!("-D{}", d)).collect()
+    }
+}
+
+/// Source text of a single top-level item, including the comments before it
+struct SourceItem {
+    key: String,
+    text: String,
+}
+
+/// A translated file split into top-level items
+struct SourceFile {
+    header: String,
+    items: Vec<SourceItem>,
+    footer: String,
+}
+
+/// Items are matched up across configurations by namespace and name. Items
+/// without a name of their own, such as `use` declarations and `extern`
+/// blocks, only match identical items.
+fn item_key(item: &Item, text: &str) -> String {
+    let namespace = match item.kind {
+        ItemKind::ExternCrate(..) => "extern crate",
+        ItemKind::Fn(..) | ItemKind::Static(..) | ItemKind::Const(..) => "value",
+        ItemKind::Mod(..) => "mod",
+        ItemKind::TyAlias(..)
+        | ItemKind::Enum(..)
+        | ItemKind::Struct(..)
+        | ItemKind::Union(..)
+        | ItemKind::Trait(..) => "type",
+        _ => return text.trim().to_string(),
+    };
+    format!("{} {}", namespace, item.ident)
+}
+
+fn split_items(name: &str, source: &str) -> Result<SourceFile, String> {
+    let sess = ParseSess::new(FilePathMapping::empty());
+    let krate = parse_crate_from_source_str(
+        FileName::Custom(name.to_string()),
+        source.to_string(),
+        &sess,
+    )
+    .map_err(|mut e| {
+        e.cancel();
+        format!("Could not parse translation for configuration {}", name)
+    })?;
+
+    let sm = sess.source_map();
+    let offset = |pos| sm.lookup_byte_offset(pos).pos.0 as usize;
+
+    let mut header = String::new();
+    let mut items = vec![];
+    let mut prev_hi = 0;
+    for (i, item) in krate.module.items.iter().enumerate() {
+        // Item spans don't include their outer attributes
+        let lo = item
+            .attrs
+            .iter()
+            .filter(|attr| attr.style == AttrStyle::Outer)
+            .map(|attr| offset(attr.span.lo()))
+            .chain(Some(offset(item.span.lo())))
+            .min()
+            .unwrap();
+        let hi = offset(item.span.hi());
+
+        let text = if i == 0 {
+            header = source[..lo].to_string();
+            source[lo..hi].to_string()
+        } else {
+            // Keep the comments between items with the following item
+            let leading = source[prev_hi..lo].trim_start();
+            format!("{}{}", leading, &source[lo..hi])
+        };
+        items.push(SourceItem {
+            key: item_key(item, &source[lo..hi]),
+            text,
+        });
+        prev_hi = hi;
+    }
+
+    Ok(SourceFile {
+        header,
+        items,
+        footer: source[prev_hi..].trim_start().to_string(),
+    })
+}
+
+fn cfg_attr(configs: &[&str]) -> String {
+    let features: Vec<String> = configs
+        .iter()
+        .map(|name| format!("feature = \"{}\"", name))
+        .collect();
+    if features.len() == 1 {
+        format!("#[cfg({})]", features[0])
+    } else {
+        format!("#[cfg(any({}))]", features.join(", "))
+    }
+}
+
+/// Merge the translations of a file under each named configuration. The
+/// header of the result, with crate attributes and the like, is that of the
+/// first configuration.
+pub fn merge_translations(translations: &[(&str, String)]) -> Result<String, String> {
+    // Item key to the distinct variants of the item and the configurations
+    // that produced each of them, in the order of first appearance
+    let mut merged: IndexMap<String, Vec<(String, Vec<&str>)>> = IndexMap::new();
+    let mut order: Vec<String> = vec![];
+    let mut first = None;
+
+    for &(name, ref source) in translations {
+        let file = split_items(name, source)?;
+
+        // Items only in this configuration go right after the last item we
+        // already knew about, to keep related definitions together
+        let mut cursor = 0;
+        for item in file.items {
+            match merged.get_mut(&item.key) {
+                Some(variants) => {
+                    match variants.iter_mut().find(|(text, _)| *text == item.text) {
+                        Some((_, configs)) => configs.push(name),
+                        None => variants.push((item.text, vec![name])),
+                    }
+                    cursor = order.iter().position(|k| *k == item.key).unwrap() + 1;
+                }
+                None => {
+                    order.insert(cursor, item.key.clone());
+                    cursor += 1;
+                    merged.insert(item.key, vec![(item.text, vec![name])]);
+                }
+            }
+        }
+
+        if first.is_none() {
+            first = Some((file.header, file.footer));
+        }
+    }
+
+    let (header, footer) = first.ok_or_else(|| "No configurations to merge".to_string())?;
+    let mut out = header;
+    for key in &order {
+        for (text, configs) in &merged[key] {
+            if configs.len() < translations.len() {
+                // Attach the guard to the item itself, after its comments
+                let (comments, item) = split_leading_comments(text);
+                out.push_str(comments);
+                out.push_str(&cfg_attr(configs));
+                out.push('\n');
+                out.push_str(item);
+            } else {
+                out.push_str(text);
+            }
+            out.push('\n');
+        }
+    }
+    out.push_str(&footer);
+    Ok(out)
+}
+
+/// Split the text of an item into its leading comments and the rest
+fn split_leading_comments(text: &str) -> (&str, &str) {
+    let mut idx = 0;
+    for line in text.split('\n') {
+        if !line.trim_start().starts_with("//") {
+            break;
+        }
+        idx += line.len() + 1;
+    }
+    text.split_at(idx.min(text.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntax::with_globals;
+    use syntax_pos::edition::Edition;
+
+    #[test]
+    fn parse_configuration() {
+        let config = CfgConfiguration::parse("linux:__linux__, HAVE_EPOLL=1").unwrap();
+        assert_eq!(config.name, "linux");
+        assert_eq!(config.clang_args(), vec!["-D__linux__", "-DHAVE_EPOLL=1"]);
+
+        assert!(CfgConfiguration::parse(":FOO").is_err());
+    }
+
+    #[test]
+    fn merge() {
+        let a = "#![allow(dead_code)]\n\
+                 pub static x: i32 = 1;\n\
+                 // Only with A\n\
+                 pub fn only_a() {}\n\
+                 pub fn common() {}\n";
+        let b = "#![allow(dead_code)]\n\
+                 pub static x: i32 = 2;\n\
+                 pub fn common() {}\n";
+
+        let merged = with_globals(Edition::Edition2018, || {
+            merge_translations(&[("a", a.to_string()), ("b", b.to_string())])
+        })
+        .unwrap();
+
+        assert_eq!(
+            merged,
+            "#![allow(dead_code)]\n\
+             #[cfg(feature = \"a\")]\n\
+             pub static x: i32 = 1;\n\
+             #[cfg(feature = \"b\")]\n\
+             pub static x: i32 = 2;\n\
+             // Only with A\n\
+             #[cfg(feature = \"a\")]\n\
+             pub fn only_a() {}\n\
+             pub fn common() {}\n"
+        );
+    }
+}
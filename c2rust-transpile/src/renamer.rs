@@ -1,21 +1,20 @@
-use std::collections::HashMap;
-use std::collections::HashSet;
+use indexmap::{IndexMap, IndexSet};
 use std::hash::Hash;
 use std::iter::FromIterator;
 
 struct Scope<T> {
-    name_map: HashMap<T, String>,
-    used: HashSet<String>,
+    name_map: IndexMap<T, String>,
+    used: IndexSet<String>,
 }
 
 impl<T: Clone + Eq + Hash> Scope<T> {
     pub fn new() -> Self {
-        Self::new_with_reserved(HashSet::new())
+        Self::new_with_reserved(IndexSet::new())
     }
 
-    pub fn new_with_reserved(reserved: HashSet<String>) -> Self {
+    pub fn new_with_reserved(reserved: IndexSet<String>) -> Self {
         Scope {
-            name_map: HashMap::new(),
+            name_map: IndexMap::new(),
             used: reserved,
         }
     }
@@ -37,9 +36,149 @@ impl<T: Clone + Eq + Hash> Scope<T> {
     }
 }
 
+/// Keywords that can still be used as identifiers by spelling them as raw identifiers, e.g.
+/// `r#type`. The printer adds the `r#` to any identifier that needs it.
+const RAW_IDENTIFIER_KEYWORDS: [&str; 46] = [
+    "as", "break", "const", "continue", "else", "enum", "extern", "false", "fn", "for", "if",
+    "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "type", "unsafe", "use", "where", "while", "dyn", "abstract",
+    "become", "box", "do", "final", "macro", "override", "priv", "typeof", "unsized", "virtual",
+    "yield", "async", "try",
+];
+
+/// Case conventions that generated names can be converted to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NameCase {
+    /// Keep the C spelling
+    Preserve,
+    /// `snake_case`
+    Snake,
+    /// `UPPER_SNAKE_CASE`
+    UpperSnake,
+    /// `UpperCamelCase`
+    UpperCamel,
+}
+
+impl NameCase {
+    /// Converts `name` to this case. Word boundaries are underscores and lower-to-upper case
+    /// transitions; leading underscores are kept as they are.
+    pub fn apply(self, name: &str) -> String {
+        if self == NameCase::Preserve {
+            return name.to_owned();
+        }
+
+        let trimmed = name.trim_start_matches('_');
+        let prefix = &name[..name.len() - trimmed.len()];
+        let words = split_words(trimmed);
+        if words.is_empty() {
+            return name.to_owned();
+        }
+
+        let converted = match self {
+            NameCase::Preserve => unreachable!(),
+            NameCase::Snake => words
+                .iter()
+                .map(|w| w.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            NameCase::UpperSnake => words
+                .iter()
+                .map(|w| w.to_uppercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            NameCase::UpperCamel => words
+                .iter()
+                .map(|w| {
+                    let mut chars = w.chars();
+                    let first = chars.next().unwrap();
+                    first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect()
+                })
+                .collect::<Vec<String>>()
+                .concat(),
+        };
+        format!("{}{}", prefix, converted)
+    }
+}
+
+/// Splits an identifier into its words, so that `HTTPServer_state` becomes `HTTP`, `Server`
+/// and `state`.
+fn split_words(name: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = name.char_indices().collect();
+    let mut words = vec![];
+    let mut start = None;
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        if c == '_' {
+            if let Some(start) = start.take() {
+                words.push(&name[start..pos]);
+            }
+            continue;
+        }
+
+        if let Some(word_start) = start {
+            let prev = chars[i - 1].1;
+            let next_is_lower = chars.get(i + 1).map_or(false, |&(_, c)| c.is_lowercase());
+            let boundary = c.is_uppercase()
+                && (prev.is_lowercase() || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next_is_lower));
+            if boundary {
+                words.push(&name[word_start..pos]);
+                start = Some(pos);
+            }
+        } else {
+            start = Some(pos);
+        }
+    }
+    if let Some(start) = start {
+        words.push(&name[start..]);
+    }
+    words
+}
+
+/// How reserved names are escaped when a C identifier uses one of them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReservedStyle {
+    /// Treat the reserved name like any other collision, e.g. `type_0`
+    Numbered,
+    /// Append an underscore, e.g. `type_`
+    TrailingUnderscore,
+    /// Use a raw identifier where Rust allows one, e.g. `r#type`, and number the rest
+    Raw,
+}
+
+/// Naming conventions applied to the identifiers generated by a `Renamer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamingPolicy {
+    /// Prepended to a name that collides with one already in use
+    pub collision_prefix: String,
+    /// Inserted between a colliding name and the number that disambiguates it
+    pub collision_suffix: String,
+    /// Case of struct, union, enum and typedef names
+    pub type_case: NameCase,
+    /// Case of enum constants and constants translated from macros
+    pub const_case: NameCase,
+    /// Case of function, variable and parameter names
+    pub value_case: NameCase,
+    pub reserved_style: ReservedStyle,
+}
+
+impl Default for NamingPolicy {
+    fn default() -> Self {
+        NamingPolicy {
+            collision_prefix: String::new(),
+            collision_suffix: "_".to_owned(),
+            type_case: NameCase::Preserve,
+            const_case: NameCase::Preserve,
+            value_case: NameCase::Preserve,
+            reserved_style: ReservedStyle::Numbered,
+        }
+    }
+}
+
 pub struct Renamer<T> {
     scopes: Vec<Scope<T>>,
     next_fresh: u64,
+    reserved: IndexSet<String>,
+    policy: NamingPolicy,
 }
 
 impl<T: Clone + Eq + Hash> Renamer<T> {
@@ -47,10 +186,27 @@ impl<T: Clone + Eq + Hash> Renamer<T> {
     /// reserved names will exclude those names from being chosen as the mangled names from
     /// the insert method.
     pub fn new(reserved_names: &[&str]) -> Self {
-        let set: HashSet<String> = HashSet::from_iter(reserved_names.iter().map(|&x| x.to_owned()));
+        Self::with_policy(reserved_names, NamingPolicy::default())
+    }
+
+    /// Creates a new renaming environment like `new`, which names colliding and reserved
+    /// names according to `policy`.
+    pub fn with_policy(reserved_names: &[&str], policy: NamingPolicy) -> Self {
+        let reserved: IndexSet<String> =
+            IndexSet::from_iter(reserved_names.iter().map(|&x| x.to_owned()));
+        let used = reserved
+            .iter()
+            .filter(|name| {
+                policy.reserved_style != ReservedStyle::Raw
+                    || !RAW_IDENTIFIER_KEYWORDS.contains(&name.as_str())
+            })
+            .cloned()
+            .collect();
         Renamer {
-            scopes: vec![Scope::new_with_reserved(set)],
+            scopes: vec![Scope::new_with_reserved(used)],
             next_fresh: 0,
+            reserved,
+            policy,
         }
     }
 
@@ -68,6 +224,17 @@ impl<T: Clone + Eq + Hash> Renamer<T> {
         self.scopes.pop();
     }
 
+    /// The number of name binding scopes, including the outermost one
+    pub fn scope_depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Drops scopes until only `depth` are left, e.g. after recovering from a
+    /// failure that skipped the matching calls to `drop_scope`
+    pub fn truncate_scopes(&mut self, depth: usize) {
+        self.scopes.truncate(depth.max(1));
+    }
+
     fn current_scope(&self) -> &Scope<T> {
         self.scopes.last().expect("Expected a scope")
     }
@@ -86,11 +253,21 @@ impl<T: Clone + Eq + Hash> Renamer<T> {
     /// Assigns a name that doesn't collide with anything in the context of a particular
     /// scope, defaulting to the current scope if None is provided
     fn pick_name_in_scope(&mut self, basename: &str, scope: Option<usize>) -> String {
-        let mut target = basename.to_string();
+        let basename = if self.policy.reserved_style == ReservedStyle::TrailingUnderscore
+            && self.reserved.contains(basename)
+        {
+            format!("{}_", basename)
+        } else {
+            basename.to_string()
+        };
+        let mut target = basename.clone();
 
         for i in 0.. {
             if self.is_target_used(&target) {
-                target = format!("{}_{}", basename, i);
+                target = format!(
+                    "{}{}{}{}",
+                    self.policy.collision_prefix, basename, self.policy.collision_suffix, i
+                );
             } else {
                 break;
             }
@@ -217,6 +394,59 @@ mod tests {
         assert_eq!(one5, one2);
     }
 
+    #[test]
+    fn truncated() {
+        let mut renamer = Renamer::new(&[]);
+        renamer.insert(10, "one").unwrap();
+        let depth = renamer.scope_depth();
+
+        renamer.add_scope();
+        renamer.insert(20, "two").unwrap();
+        renamer.add_scope();
+        renamer.truncate_scopes(depth);
+
+        assert_eq!(renamer.scope_depth(), depth);
+        assert_eq!(renamer.get(&10).unwrap(), "one");
+        assert_eq!(renamer.get(&20), None);
+        assert_eq!(renamer.insert(30, "two").unwrap(), "two");
+    }
+
+    #[test]
+    fn policy() {
+        let policy = NamingPolicy {
+            collision_prefix: "c_".to_owned(),
+            collision_suffix: "".to_owned(),
+            reserved_style: ReservedStyle::TrailingUnderscore,
+            ..NamingPolicy::default()
+        };
+        let mut renamer = Renamer::with_policy(&["type"], policy);
+        assert_eq!(renamer.insert(1, "type").unwrap(), "type_");
+        assert_eq!(renamer.insert(2, "type").unwrap(), "c_type_0");
+        assert_eq!(renamer.insert(3, "one").unwrap(), "one");
+        assert_eq!(renamer.insert(4, "one").unwrap(), "c_one0");
+
+        let policy = NamingPolicy {
+            reserved_style: ReservedStyle::Raw,
+            ..NamingPolicy::default()
+        };
+        let mut renamer = Renamer::with_policy(&["type", "self", "main"], policy);
+        assert_eq!(renamer.insert(1, "type").unwrap(), "type");
+        assert_eq!(renamer.insert(2, "type").unwrap(), "type_0");
+        assert_eq!(renamer.insert(3, "self").unwrap(), "self_0");
+        assert_eq!(renamer.insert(4, "main").unwrap(), "main_0");
+    }
+
+    #[test]
+    fn cases() {
+        assert_eq!(NameCase::UpperCamel.apply("uint32_t"), "Uint32T");
+        assert_eq!(NameCase::UpperCamel.apply("HTTPServer_state"), "HttpServerState");
+        assert_eq!(NameCase::UpperCamel.apply("__va_list_tag"), "__VaListTag");
+        assert_eq!(NameCase::UpperSnake.apply("maxValue"), "MAX_VALUE");
+        assert_eq!(NameCase::Snake.apply("MaxValue2D"), "max_value2_d");
+        assert_eq!(NameCase::Preserve.apply("Mixed_case"), "Mixed_case");
+        assert_eq!(NameCase::Snake.apply("__"), "__");
+    }
+
     #[test]
     fn forgets() {
         let mut renamer = Renamer::new(&[]);
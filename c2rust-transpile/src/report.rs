@@ -0,0 +1,177 @@
+//! Machine-readable summary of a transpiler run, written with `--report-file`.
+//! For every translated file it lists the items we translated or skipped, the
+//! unsafe C features the translation had to emulate, and the C names that had
+//! to be renamed in Rust, so migration progress can be tracked over time.
+//! `--rename-map` writes just the renames.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::c_ast::CDeclKind;
+use crate::error::TranspileError;
+
+/// Report for a whole run, covering all translation units
+#[derive(Serialize, Debug, Default)]
+pub struct RunReport {
+    pub files: Vec<FileReport>,
+    /// Input files that could not be translated at all, e.g. because clang
+    /// failed to parse them
+    pub failed_files: Vec<PathBuf>,
+}
+
+impl RunReport {
+    pub fn write(&self, path: &Path) -> Result<(), TranspileError> {
+        let json = serde_json::to_string_pretty(self).expect("Unable to serialize report");
+        write_json(path, json)
+    }
+
+    /// Write the renames of every file as a JSON object mapping the path of
+    /// each C file to an object mapping C names to Rust names
+    pub fn write_rename_map(&self, path: &Path) -> Result<(), TranspileError> {
+        let map = self
+            .files
+            .iter()
+            .map(|file| {
+                let renames = file
+                    .renames
+                    .iter()
+                    .map(|r| (r.c_name.clone(), r.rust_name.clone()))
+                    .collect::<BTreeMap<_, _>>();
+                (file.c_file.clone(), renames)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let json = serde_json::to_string_pretty(&map).expect("Unable to serialize rename map");
+        write_json(path, json)
+    }
+}
+
+fn write_json(path: &Path, json: String) -> Result<(), TranspileError> {
+    fs::write(path, json).map_err(|cause| TranspileError::Write {
+        path: path.to_path_buf(),
+        cause,
+    })
+}
+
+/// Report for a single translation unit
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct FileReport {
+    pub c_file: PathBuf,
+    pub rs_file: Option<PathBuf>,
+    pub translated: Vec<TranslatedItem>,
+    pub skipped: Vec<SkippedItem>,
+    pub features: FeatureCounts,
+    pub renames: Vec<Rename>,
+    /// Allocations `--owned-allocations` couldn't translate as a `Box` or `Vec`
+    pub raw_allocations: Vec<RawAllocation>,
+}
+
+impl FileReport {
+    /// Merge the report of another translation of the same file, such as the one
+    /// for another `--cfg-config` configuration
+    pub fn merge(&mut self, other: FileReport) {
+        for item in other.translated {
+            if !self.translated.contains(&item) {
+                self.translated.push(item);
+            }
+        }
+        self.skipped.extend(other.skipped);
+        self.features.pointer_casts += other.features.pointer_casts;
+        self.features.unions += other.features.unions;
+        self.features.gotos += other.features.gotos;
+        self.features.current_block_functions += other.features.current_block_functions;
+        self.features.weak_references += other.features.weak_references;
+        for rename in other.renames {
+            if !self.renames.contains(&rename) {
+                self.renames.push(rename);
+            }
+        }
+        for alloc in other.raw_allocations {
+            if !self.raw_allocations.contains(&alloc) {
+                self.raw_allocations.push(alloc);
+            }
+        }
+    }
+}
+
+/// A top-level C declaration we emitted Rust for
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TranslatedItem {
+    pub kind: &'static str,
+    pub c_name: Option<String>,
+    pub rust_name: Option<String>,
+}
+
+/// An item that failed to translate, as listed in the report and, with
+/// `--best-effort`, in the `.skipped.json` file next to the translation
+#[derive(Serialize, Debug, Clone)]
+pub struct SkippedItem {
+    /// Name of the C declaration, if it has one
+    pub name: Option<String>,
+    /// Category of the failure, e.g. `generic` or `invalid-clang-ast`, or
+    /// `panic` if the translator panicked
+    pub kind: &'static str,
+    /// Why the translation failed
+    pub reason: String,
+    /// Location of the declaration in the C source
+    pub c_file: Option<PathBuf>,
+    pub c_line: Option<u64>,
+    pub c_column: Option<u64>,
+    /// Whether the item was replaced by a stub rather than left out
+    pub stubbed: bool,
+}
+
+/// How often the translation had to fall back on unsafe Rust to emulate C
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct FeatureCounts {
+    /// Casts from pointers to pointers of another type, or between pointers and integers
+    pub pointer_casts: usize,
+    /// Union declarations, whose fields can only be read in unsafe code
+    pub unions: usize,
+    /// `goto` statements, which have no direct Rust equivalent
+    pub gotos: usize,
+    /// Functions whose control flow could only be translated with a
+    /// `current_block` state variable
+    pub current_block_functions: usize,
+    /// Declarations of weak external symbols, which Rust can only link as strong
+    /// references, so the symbol has to be defined at link time
+    pub weak_references: usize,
+}
+
+/// A C name that had to be changed in Rust, e.g. because it is a Rust keyword
+/// or collides with another name
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Rename {
+    pub c_name: String,
+    pub rust_name: String,
+}
+
+/// A `malloc` of values of a local pointer's pointee type that is still
+/// translated with raw pointers, because the pointer couldn't be shown to own
+/// the allocation
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RawAllocation {
+    /// Function declaring the pointer
+    pub function: String,
+    /// Name of the pointer
+    pub variable: String,
+    pub c_line: Option<u64>,
+    /// Why the allocation isn't owned
+    pub reason: String,
+}
+
+/// The kind of a declaration, as reported
+pub fn decl_kind_name(kind: &CDeclKind) -> &'static str {
+    match kind {
+        CDeclKind::Function { .. } => "function",
+        CDeclKind::Variable { .. } => "variable",
+        CDeclKind::Struct { .. } => "struct",
+        CDeclKind::Union { .. } => "union",
+        CDeclKind::Enum { .. } => "enum",
+        CDeclKind::EnumConstant { .. } => "enum constant",
+        CDeclKind::Typedef { .. } => "typedef",
+        CDeclKind::MacroObject { .. } => "macro",
+        CDeclKind::MacroFunction { .. } => "function-like macro",
+        _ => "other",
+    }
+}
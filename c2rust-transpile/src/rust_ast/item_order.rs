@@ -0,0 +1,159 @@
+//! Dependency ordering of the items of a translated module, for
+//! `--order-items`. Rust doesn't need items to be declared before they are
+//! used, but output in which every type comes after the types it refers to
+//! is easier to read and to combine with other passes, and doesn't depend on
+//! the order the C declarations happened to be in.
+
+use std::collections::{HashMap, HashSet};
+
+use syntax::ast::*;
+use syntax::print::pprust;
+use syntax::ptr::P;
+use syntax::visit::{self, Visitor};
+use syntax_pos::symbol::Symbol;
+
+/// The groups items are placed in, in output order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ItemGroup {
+    /// Imports and macros, which later items may rely on textually
+    Imports,
+    Types,
+    ExternBlocks,
+    /// Constants and statics, whose types are defined above them
+    Values,
+    /// Functions, impls and everything else, in their original order
+    Other,
+}
+
+fn item_group(item: &Item) -> ItemGroup {
+    match item.kind {
+        ItemKind::Use(..) | ItemKind::ExternCrate(..) | ItemKind::Mac(..) | ItemKind::MacroDef(..) => {
+            ItemGroup::Imports
+        }
+        ItemKind::Struct(..) | ItemKind::Union(..) | ItemKind::Enum(..) | ItemKind::TyAlias(..) => {
+            ItemGroup::Types
+        }
+        ItemKind::ForeignMod(..) => ItemGroup::ExternBlocks,
+        ItemKind::Const(..) | ItemKind::Static(..) => ItemGroup::Values,
+        _ => ItemGroup::Other,
+    }
+}
+
+/// Collects the names that the paths in an item refer to
+struct PathNames(HashSet<Symbol>);
+
+impl<'ast> Visitor<'ast> for PathNames {
+    fn visit_path(&mut self, path: &'ast Path, _id: NodeId) {
+        self.0.extend(path.segments.iter().map(|seg| seg.ident.name));
+        visit::walk_path(self, path);
+    }
+
+    fn visit_mac(&mut self, _mac: &'ast Mac) {}
+}
+
+/// Order the items of a module: imports, then type definitions, each after
+/// the types it refers to, then all extern blocks, with blocks of the same
+/// ABI merged, then constants and statics, again after the ones they refer
+/// to, and then everything else. Items otherwise keep their relative order,
+/// so the output is deterministic.
+pub fn order_items(items: Vec<P<Item>>) -> Vec<P<Item>> {
+    let mut groups: Vec<(ItemGroup, Vec<P<Item>>)> = vec![];
+    for item in items {
+        let group = item_group(&item);
+        match groups.iter_mut().find(|(g, _)| *g == group) {
+            Some((_, group_items)) => group_items.push(item),
+            None => groups.push((group, vec![item])),
+        }
+    }
+    groups.sort_by_key(|&(group, _)| group);
+
+    groups
+        .into_iter()
+        .flat_map(|(group, items)| match group {
+            ItemGroup::Types | ItemGroup::Values => sort_by_dependencies(items),
+            ItemGroup::ExternBlocks => merge_extern_blocks(items),
+            ItemGroup::Imports | ItemGroup::Other => items,
+        })
+        .collect()
+}
+
+/// Sort items so that each comes after the items it refers to by name.
+/// Items that refer to each other, like recursive types, are kept in their
+/// original order.
+fn sort_by_dependencies(items: Vec<P<Item>>) -> Vec<P<Item>> {
+    let index: HashMap<Symbol, usize> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (item.ident.name, i))
+        .collect();
+    let deps: Vec<Vec<usize>> = items
+        .iter()
+        .map(|item| {
+            let mut names = PathNames(HashSet::new());
+            names.visit_item(item);
+            let mut deps: Vec<usize> = names
+                .0
+                .iter()
+                .filter_map(|name| index.get(name).cloned())
+                .collect();
+            deps.sort();
+            deps
+        })
+        .collect();
+
+    fn visit(i: usize, deps: &[Vec<usize>], visited: &mut Vec<bool>, order: &mut Vec<usize>) {
+        if visited[i] {
+            return;
+        }
+        visited[i] = true;
+        for &dep in &deps[i] {
+            visit(dep, deps, visited, order);
+        }
+        order.push(i);
+    }
+
+    let mut visited = vec![false; items.len()];
+    let mut order = vec![];
+    for i in 0..items.len() {
+        visit(i, &deps, &mut visited, &mut order);
+    }
+
+    let mut items: Vec<Option<P<Item>>> = items.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|i| items[i].take()).collect()
+}
+
+/// Merge extern blocks of the same ABI and attributes into the first of them
+fn merge_extern_blocks(items: Vec<P<Item>>) -> Vec<P<Item>> {
+    let mut merged: Vec<P<Item>> = vec![];
+    for item in items {
+        let same_block = merged.iter().position(|block| same_extern_block(block, &item));
+        match same_block {
+            Some(i) => {
+                let foreign_items = match item.into_inner().kind {
+                    ItemKind::ForeignMod(fm) => fm.items,
+                    _ => unreachable!("Found item other than an extern block"),
+                };
+                if let ItemKind::ForeignMod(ref mut fm) = merged[i].kind {
+                    fm.items.extend(foreign_items);
+                }
+            }
+            None => merged.push(item),
+        }
+    }
+    merged
+}
+
+/// Whether two extern blocks have the same ABI and attributes
+fn same_extern_block(a: &Item, b: &Item) -> bool {
+    let abi = |item: &Item| match item.kind {
+        ItemKind::ForeignMod(ref fm) => fm.abi.as_ref().map(|abi| abi.symbol_unescaped),
+        _ => unreachable!("Found item other than an extern block"),
+    };
+    let attrs = |item: &Item| {
+        item.attrs
+            .iter()
+            .map(pprust::attribute_to_string)
+            .collect::<Vec<_>>()
+    };
+    abi(a) == abi(b) && attrs(a) == attrs(b)
+}
@@ -1,4 +1,5 @@
 pub mod comment_store;
+pub mod item_order;
 pub mod item_store;
 pub mod traverse;
 
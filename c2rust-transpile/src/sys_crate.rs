@@ -0,0 +1,53 @@
+//! Reuse of the bindings in an existing `-sys` crate. Declarations of C items
+//! that the crate already provides are not translated; the translation imports
+//! the crate's item instead, so that translated code and other users of the
+//! crate agree on a single definition of each type.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::error::TranspileError;
+
+#[derive(Debug, Clone)]
+pub struct SysCrate {
+    /// Name of the crate as it appears in `Cargo.toml`
+    pub name: String,
+    /// Version requirement on the crate in the emitted `Cargo.toml`. The
+    /// symbols are those of this version, so it is pinned.
+    pub version: String,
+    /// C names of the functions, variables and types the crate provides
+    symbols: HashSet<String>,
+}
+
+impl SysCrate {
+    /// Load the symbols a crate provides from a file listing one C name per
+    /// line. Empty lines and lines starting with `#` are skipped.
+    pub fn load(name: &str, version: &str, symbols_path: &Path) -> Result<Self, TranspileError> {
+        let contents = fs::read_to_string(symbols_path).map_err(|cause| TranspileError::Read {
+            what: "symbol list",
+            path: symbols_path.to_path_buf(),
+            cause,
+        })?;
+        let symbols = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+        Ok(SysCrate {
+            name: name.to_string(),
+            version: version.to_string(),
+            symbols,
+        })
+    }
+
+    pub fn provides(&self, c_name: &str) -> bool {
+        self.symbols.contains(c_name)
+    }
+
+    /// The name to refer to the crate by in Rust code
+    pub fn ident(&self) -> String {
+        self.name.replace('-', "_")
+    }
+}
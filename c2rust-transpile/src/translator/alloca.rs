@@ -0,0 +1,273 @@
+#![deny(missing_docs)]
+//! Translation of `alloca`. Rust can't grow the stack frame of a function, so
+//! by default every call allocates a zeroed buffer on the heap instead. The
+//! buffers are kept in a vector declared at the start of the function, so like
+//! the memory `alloca` returns, they are only freed when the function returns.
+//! They are made of 16-byte chunks of a type the function declares with that
+//! alignment.
+//!
+//! With `--alloca crate`, a function whose `alloca` calls all have constant
+//! sizes and can only run once per call of the function, because they aren't
+//! in loops and the function has no labels to jump back to, runs its body in a
+//! closure passed to `alloca::with_alloca_zeroed` instead. Variadic functions
+//! can't, because the closure can't use their `va_list`. That reserves the
+//! memory of all its calls on the stack when the function is entered. The
+//! calls of other functions still allocate on the heap.
+
+use super::*;
+use crate::c_ast::iterators::DFNodes;
+
+/// `alloca`, and the builtins clang expands it into
+const ALLOCA_FNS: &[&str] = &[
+    "alloca",
+    "__builtin_alloca",
+    "__builtin_alloca_uninitialized",
+    "__builtin_alloca_with_align",
+    "__builtin_alloca_with_align_uninitialized",
+];
+
+/// `alloca` returns memory aligned for any type
+const ALLOCA_ALIGN: u64 = 16;
+
+/// Where the `alloca` calls of the function being translated get their memory
+pub enum AllocaScratch {
+    /// Buffers of chunks of `ALLOCA_ALIGN` bytes that each call pushes onto a
+    /// `Vec`
+    Heap {
+        /// Name of the `Vec` of buffers
+        buffers: String,
+        /// Name of the chunk type, which the function declares
+        chunk: String,
+    },
+    /// A block of stack memory of this name, of which each call takes the
+    /// part at its offset
+    Stack {
+        name: String,
+        size: u64,
+        offsets: HashMap<CExprId, u64>,
+    },
+}
+
+impl<'c> Translation<'c> {
+    /// Decide where the `alloca` calls in a function body get their memory
+    pub fn find_allocas(&self, body: CStmtId, is_variadic: bool) {
+        let calls: Vec<CExprId> = DFNodes::new(&self.ast_context, SomeId::Stmt(body))
+            .filter_map(|node| match node {
+                SomeId::Expr(expr_id) if self.alloca_size(expr_id).is_some() => Some(expr_id),
+                _ => None,
+            })
+            .collect();
+        let scratch = if calls.is_empty() {
+            None
+        } else if self.tcfg.alloca_mode == AllocaMode::Crate && !is_variadic {
+            let stack = self.stack_allocas(body, &calls);
+            if stack.is_none() {
+                let name = self.function_context.borrow().get_name().to_string();
+                warn!("Allocating the alloca calls of {} on the heap", name);
+            }
+            stack.or_else(|| Some(self.heap_allocas()))
+        } else {
+            Some(self.heap_allocas())
+        };
+        *self.alloca_scratch.borrow_mut() = scratch;
+    }
+
+    fn heap_allocas(&self) -> AllocaScratch {
+        let mut renamer = self.renamer.borrow_mut();
+        AllocaScratch::Heap {
+            buffers: renamer.pick_name("alloca_buffers"),
+            chunk: renamer.pick_name("AllocaChunk"),
+        }
+    }
+
+    /// Lay out the `alloca` calls of a function on the stack, if they all run
+    /// at most once with a constant size
+    fn stack_allocas(&self, body: CStmtId, calls: &[CExprId]) -> Option<AllocaScratch> {
+        if self.tcfg.emit_no_std {
+            return None;
+        }
+        for node in DFNodes::new(&self.ast_context, SomeId::Stmt(body)) {
+            if let SomeId::Stmt(stmt_id) = node {
+                match self.ast_context[stmt_id].kind {
+                    CStmtKind::Label(..) => return None,
+                    CStmtKind::While { .. }
+                    | CStmtKind::DoWhile { .. }
+                    | CStmtKind::ForLoop { .. } => {
+                        let in_loop = DFNodes::new(&self.ast_context, SomeId::Stmt(stmt_id))
+                            .any(|node| match node {
+                                SomeId::Expr(expr_id) => calls.contains(&expr_id),
+                                _ => false,
+                            });
+                        if in_loop {
+                            return None;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut size = 0;
+        let mut offsets = HashMap::new();
+        for &call in calls {
+            let call_size = match self.ast_context.eval_int_const(self.alloca_size(call)?)? {
+                ConstIntExpr::U(n) => n,
+                ConstIntExpr::I(n) if n >= 0 => n as u64,
+                ConstIntExpr::I(_) => return None,
+            };
+            offsets.insert(call, size);
+            size += (call_size + ALLOCA_ALIGN - 1) / ALLOCA_ALIGN * ALLOCA_ALIGN;
+        }
+        let name = self.renamer.borrow_mut().pick_name("alloca_stack");
+        Some(AllocaScratch::Stack { name, size, offsets })
+    }
+
+    /// The size argument of a call to `alloca`
+    fn alloca_size(&self, expr_id: CExprId) -> Option<CExprId> {
+        let ctx = &self.ast_context;
+        let (func, args) = match ctx[expr_id].kind {
+            CExprKind::Call(_, func, ref args) if !args.is_empty() => (func, args),
+            _ => return None,
+        };
+        let func = match ctx[func].kind {
+            CExprKind::ImplicitCast(_, func, CastKind::FunctionToPointerDecay, _, _)
+            | CExprKind::ImplicitCast(_, func, CastKind::BuiltinFnToFnPtr, _, _) => func,
+            _ => return None,
+        };
+        match ctx[func].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match ctx[decl_id].kind {
+                CDeclKind::Function { ref name, body: None, .. }
+                    if ALLOCA_FNS.contains(&name.as_str()) =>
+                {
+                    Some(args[0])
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Translate a call to `alloca` as taking memory from the scratch space
+    /// of the function
+    pub fn convert_alloca(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        let size = match self.alloca_size(expr_id) {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+        let void_ptr = mk().mutbl().ptr_ty(self.ffi_ty("c_void"));
+        match *self.alloca_scratch.borrow() {
+            Some(AllocaScratch::Stack { ref name, ref offsets, .. }) => {
+                let offset = mk().lit_expr(mk().int_lit(offsets[&expr_id] as u128, ""));
+                let ptr = mk().method_call_expr(mk().ident_expr(name), "add", vec![offset]);
+                let mut ptr = WithStmts::new_val(mk().cast_expr(ptr, void_ptr));
+                ptr.set_unsafe();
+                Ok(Some(ptr))
+            }
+            Some(AllocaScratch::Heap { ref buffers, ref chunk }) => {
+                self.check_heap_available("Calls to alloca")?;
+                let size = self.convert_expr(ctx.used(), size)?;
+                size.and_then(|size| {
+                    // Round the size up to whole chunks
+                    let size = cast_int(size, "usize", false);
+                    let mask = mk().lit_expr(mk().int_lit((ALLOCA_ALIGN - 1) as u128, ""));
+                    let align = mk().lit_expr(mk().int_lit(ALLOCA_ALIGN as u128, ""));
+                    let size = mk().paren_expr(mk().binary_expr(BinOpKind::Add, size, mask));
+                    let chunks = mk().binary_expr(BinOpKind::Div, size, align);
+                    let buffer = vec_expr(self.alloca_chunk_expr(chunk), chunks);
+                    let push =
+                        mk().method_call_expr(mk().ident_expr(buffers), "push", vec![buffer]);
+                    let last = mk().method_call_expr(
+                        mk().ident_expr(buffers),
+                        "last_mut",
+                        vec![] as Vec<P<Expr>>,
+                    );
+                    let last = mk().method_call_expr(last, "unwrap", vec![] as Vec<P<Expr>>);
+                    let ptr = mk().method_call_expr(last, "as_mut_ptr", vec![] as Vec<P<Expr>>);
+                    let ptr = mk().cast_expr(ptr, void_ptr);
+                    Ok(WithStmts::new(vec![mk().semi_stmt(push)], ptr))
+                })
+                .map(Some)
+            }
+            None => Err(TranslationError::generic("alloca call outside of a function body")),
+        }
+    }
+
+    /// A zeroed chunk of a heap buffer
+    fn alloca_chunk_expr(&self, chunk: &str) -> P<Expr> {
+        let zero = mk().lit_expr(mk().int_lit(0, "u8"));
+        let bytes = mk().lit_expr(mk().int_lit(ALLOCA_ALIGN as u128, ""));
+        mk().call_expr(mk().path_expr(vec![chunk]), vec![mk().repeat_expr(zero, bytes)])
+    }
+
+    /// Set up the scratch space of the `alloca` calls of a function around
+    /// its translated body
+    pub fn wrap_alloca_scratch(&self, body: Vec<Stmt>) -> Vec<Stmt> {
+        match self.alloca_scratch.borrow_mut().take() {
+            None => body,
+            Some(AllocaScratch::Heap { buffers, chunk }) => {
+                // `u64` and `u128` are only 8-byte aligned on some targets
+                let reprs = vec![
+                    simple_metaitem("C"),
+                    int_arg_metaitem("align", ALLOCA_ALIGN as u128),
+                ];
+                let repr_attr = mk().meta_item(vec!["repr"], MetaItemKind::List(reprs));
+                let bytes = mk().lit_expr(mk().int_lit(ALLOCA_ALIGN as u128, ""));
+                let chunk_item = mk()
+                    .call_attr("derive", vec!["Copy", "Clone"])
+                    .meta_item_attr(AttrStyle::Outer, repr_attr)
+                    .struct_item(
+                        &chunk,
+                        vec![mk().enum_field(mk().array_ty(mk().path_ty(vec!["u8"]), bytes))],
+                        true,
+                    );
+
+                let vec_of = |ty: P<Ty>| {
+                    mk().path_ty(vec![
+                        mk().path_segment_with_args("Vec", mk().angle_bracketed_args(vec![ty]))
+                    ])
+                };
+                let vec_ty = vec_of(vec_of(mk().path_ty(vec![chunk])));
+                let new = mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![] as Vec<P<Expr>>);
+                let local = mk().local(mk().mutbl().ident_pat(&buffers), Some(vec_ty), Some(new));
+                let mut stmts = vec![mk().item_stmt(chunk_item), mk().local_stmt(P(local))];
+                stmts.extend(body);
+                stmts
+            }
+            Some(AllocaScratch::Stack { name, size, .. }) => {
+                self.use_crate(ExternCrate::Alloca);
+
+                // The stack memory isn't aligned, so we reserve room to align it
+                let base = mk().method_call_expr(
+                    mk().ident_expr(&name),
+                    "as_mut_ptr",
+                    vec![] as Vec<P<Expr>>,
+                );
+                let align = mk().lit_expr(mk().int_lit(ALLOCA_ALIGN as u128, ""));
+                let align_offset =
+                    mk().method_call_expr(mk().ident_expr(&name), "align_offset", vec![align]);
+                let aligned =
+                    mk().method_call_expr(mk().ident_expr(&name), "add", vec![align_offset]);
+                let mut stmts = vec![];
+                for init in vec![base, aligned] {
+                    let local = mk().local(mk().ident_pat(&name), None as Option<P<Ty>>, Some(init));
+                    stmts.push(mk().local_stmt(P(local)));
+                }
+                stmts.extend(body);
+
+                let arg = mk().arg(mk().infer_ty(), mk().ident_pat(&name));
+                let decl = mk().fn_decl(vec![arg], FunctionRetTy::Default(DUMMY_SP));
+                let closure_body = mk().block_expr(stmts_block(stmts));
+                let closure =
+                    mk().closure_expr(CaptureBy::Ref, Movability::Movable, decl, closure_body);
+                let reserved = size + ALLOCA_ALIGN - 1;
+                let reserved = mk().lit_expr(mk().int_lit(reserved as u128, ""));
+                let with_alloca = mk().path_expr(vec!["alloca", "with_alloca_zeroed"]);
+                vec![mk().expr_stmt(mk().call_expr(with_alloca, vec![reserved, closure]))]
+            }
+        }
+    }
+}
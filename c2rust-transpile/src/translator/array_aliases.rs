@@ -0,0 +1,141 @@
+//! Local pointers that only alias a local array. In
+//!
+//! ```c
+//! int buf[16];
+//! int *p = buf;
+//! ... p[i] ...
+//! ```
+//!
+//! `p` always points to the start of `buf` and is only ever indexed, so `p[i]`
+//! is translated as `buf[i as usize]`, which is bounds-checked and needs no raw
+//! pointer, and `p` itself is not emitted.
+
+use super::*;
+use crate::c_ast::iterators::DFNodes;
+
+impl<'c> Translation<'c> {
+    /// Find the pointers in a function body that only alias a local array and
+    /// remember the array each one is initialized with
+    pub fn find_array_aliases(&self, body: CStmtId) {
+        let ctx = &self.ast_context;
+        let mut candidates = HashMap::new();
+        let mut local_names = HashMap::new();
+        for node in DFNodes::new(ctx, SomeId::Stmt(body)) {
+            if let SomeId::Decl(decl_id) = node {
+                if let CDeclKind::Variable {
+                    ref ident,
+                    has_static_duration: false,
+                    has_thread_duration: false,
+                    initializer,
+                    typ,
+                    ..
+                } = ctx[decl_id].kind
+                {
+                    *local_names.entry(ident.as_str()).or_insert(0) += 1;
+                    if let Some(arr) = initializer.and_then(|init| self.local_array_decay(init, typ)) {
+                        candidates.insert(decl_id, arr);
+                    }
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return;
+        }
+
+        // A pointer qualifies if every reference to it is the base of a
+        // subscript whose address isn't taken, since `&p[n]` may point one past
+        // the end of the array
+        let mut uses = HashMap::new();
+        let mut indexed = HashMap::new();
+        for node in DFNodes::new(ctx, SomeId::Stmt(body)) {
+            let expr_id = match node {
+                SomeId::Expr(expr_id) => expr_id,
+                _ => continue,
+            };
+            match ctx[expr_id].kind {
+                CExprKind::DeclRef(_, decl_id, _) if candidates.contains_key(&decl_id) => {
+                    *uses.entry(decl_id).or_insert(0) += 1;
+                }
+                CExprKind::ArraySubscript(_, base, _, _) => {
+                    if let Some(decl_id) = self.indexed_pointer(base) {
+                        *indexed.entry(decl_id).or_insert(0) += 1;
+                    }
+                }
+                CExprKind::Unary(_, c_ast::UnOp::AddressOf, arg, _) => {
+                    if let CExprKind::ArraySubscript(_, base, _, _) = ctx[arg].kind {
+                        if let Some(decl_id) = self.indexed_pointer(base) {
+                            candidates.remove(&decl_id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut aliases = self.array_aliases.borrow_mut();
+        for (decl_id, (arr, arr_decl)) in candidates {
+            let arr_name = ctx[arr_decl].kind.get_name().map(String::as_str);
+            // The array must not be shadowed where the pointer is used
+            let unique_name = arr_name.map_or(false, |name| local_names.get(name) == Some(&1));
+            if unique_name && uses.get(&decl_id) == indexed.get(&decl_id) {
+                aliases.insert(decl_id, arr);
+            }
+        }
+    }
+
+    /// The array a subscript base refers to, if the base is a pointer that
+    /// only aliases a local array
+    pub fn aliased_array(&self, base: CExprId) -> Option<CExprId> {
+        let decl_id = self.indexed_pointer(base)?;
+        self.array_aliases.borrow().get(&decl_id).cloned()
+    }
+
+    /// The variable read by a subscript base of the form `p`
+    fn indexed_pointer(&self, base: CExprId) -> Option<CDeclId> {
+        match self.ast_context[base].kind {
+            CExprKind::ImplicitCast(_, var, CastKind::LValueToRValue, _, _) => {
+                match self.ast_context[var].kind {
+                    CExprKind::DeclRef(_, decl_id, _) => Some(decl_id),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// If an initializer of a variable with the given type is a local
+    /// fixed-size array of the pointee type decaying to a pointer, the
+    /// reference to the array and its declaration
+    fn local_array_decay(&self, init: CExprId, typ: CQualTypeId) -> Option<(CExprId, CDeclId)> {
+        let ctx = &self.ast_context;
+        let pointee = match ctx.resolve_type(typ.ctype).kind {
+            CTypeKind::Pointer(pointee) => pointee,
+            _ => return None,
+        };
+        let arr = match ctx[init].kind {
+            CExprKind::ImplicitCast(_, arr, CastKind::ArrayToPointerDecay, _, _) => arr,
+            _ => return None,
+        };
+        let arr_decl = match ctx[arr].kind {
+            CExprKind::DeclRef(_, arr_decl, _) => arr_decl,
+            _ => return None,
+        };
+        let arr_typ = match ctx[arr_decl].kind {
+            CDeclKind::Variable {
+                has_static_duration: false,
+                has_thread_duration: false,
+                typ,
+                ..
+            } => typ,
+            _ => return None,
+        };
+        match ctx.resolve_type(arr_typ.ctype).kind {
+            CTypeKind::ConstantArray(elt, _)
+                if ctx.resolve_type_id(elt) == ctx.resolve_type_id(pointee.ctype) =>
+            {
+                Some((arr, arr_decl))
+            }
+            _ => None,
+        }
+    }
+}
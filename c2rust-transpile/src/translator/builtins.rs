@@ -71,6 +71,9 @@ impl<'c> Translation<'c> {
                 "f64",
                 "NAN",
             ]))),
+            "__builtin_nanl" if self.tcfg.long_double_mode == LongDoubleMode::F64 => {
+                Ok(WithStmts::new_val(mk().path_expr(vec!["", std_or_core, "f64", "NAN"])))
+            }
             "__builtin_nanl" => {
                 self.use_crate(ExternCrate::F128);
 
@@ -82,7 +85,9 @@ impl<'c> Translation<'c> {
             },
             "__builtin_signbit" | "__builtin_signbitf" | "__builtin_signbitl" => {
                 // Long doubles require the Float trait from num_traits to call this method
-                if builtin_name == "__builtin_signbitl" {
+                if builtin_name == "__builtin_signbitl"
+                    && self.tcfg.long_double_mode == LongDoubleMode::F128
+                {
                     self.with_cur_file_item_store(|item_store| {
                         item_store.add_use(vec!["num_traits".into()], "Float");
                     });
@@ -93,7 +98,7 @@ impl<'c> Translation<'c> {
                 Ok(val.map(|v| {
                     let val = mk().method_call_expr(v, "is_sign_negative", vec![] as Vec<P<Expr>>);
 
-                    mk().cast_expr(val, mk().path_ty(vec!["libc", "c_int"]))
+                    mk().cast_expr(val, self.ffi_ty("c_int"))
                 }))
             },
             "__builtin_ffs" | "__builtin_ffsl" | "__builtin_ffsll" => {
@@ -127,10 +132,72 @@ impl<'c> Translation<'c> {
                     mk().cast_expr(zeros, mk().path_ty(vec!["i32"]))
                 }))
             }
+            "__builtin_clrsb" | "__builtin_clrsbl" | "__builtin_clrsbll" => {
+                // The number of leading redundant sign bits:
+                // `(if x < 0 { !x } else { x }).leading_zeros() as i32 - 1`
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                Ok(val.map(|x| {
+                    let zero = mk().lit_expr(mk().int_lit(0, ""));
+                    let cond = mk().binary_expr(BinOpKind::Lt, x.clone(), zero);
+                    let inverted = mk().block(vec![mk().expr_stmt(mk().unary_expr(UnOp::Not, x.clone()))]);
+                    let magnitude = mk().paren_expr(mk().ifte_expr(cond, inverted, Some(x)));
+                    let zeros = mk().method_call_expr(magnitude, "leading_zeros", vec![] as Vec<P<Expr>>);
+                    let zeros = mk().cast_expr(zeros, mk().path_ty(vec!["i32"]));
+                    mk().binary_expr(BinOpKind::Sub, zeros, mk().lit_expr(mk().int_lit(1, "")))
+                }))
+            }
+            "__builtin_parity" | "__builtin_parityl" | "__builtin_parityll" => {
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                Ok(val.map(|x| {
+                    let ones = mk().method_call_expr(x, "count_ones", vec![] as Vec<P<Expr>>);
+                    let parity = mk().binary_expr(BinOpKind::BitAnd, ones, mk().lit_expr(mk().int_lit(1, "")));
+                    mk().cast_expr(parity, mk().path_ty(vec!["i32"]))
+                }))
+            }
+            "__builtin_rotateleft8" | "__builtin_rotateleft16" | "__builtin_rotateleft32"
+            | "__builtin_rotateleft64" | "__builtin_rotateright8" | "__builtin_rotateright16"
+            | "__builtin_rotateright32" | "__builtin_rotateright64" => {
+                let method = if builtin_name.starts_with("__builtin_rotateleft") {
+                    "rotate_left"
+                } else {
+                    "rotate_right"
+                };
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                let amount = self.convert_expr(ctx.used(), args[1])?;
+                val.and_then(|x| {
+                    Ok(amount.map(|n| {
+                        let n = mk().cast_expr(n, mk().path_ty(vec!["u32"]));
+                        mk().method_call_expr(x, method, vec![n])
+                    }))
+                })
+            }
+            "__builtin_abs" | "__builtin_labs" | "__builtin_llabs" => {
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                Ok(val.map(|x| mk().method_call_expr(x, "wrapping_abs", vec![] as Vec<P<Expr>>)))
+            }
             "__builtin_bswap16" | "__builtin_bswap32" | "__builtin_bswap64" => {
                 let val = self.convert_expr(ctx.used(), args[0])?;
                 Ok(val.map(|x| mk().method_call_expr(x, "swap_bytes", vec![] as Vec<P<Expr>>)))
             }
+            // `abs` on floats comes from std, so without it we call the C library instead
+            "__builtin_fabs" | "__builtin_fabsf" if self.tcfg.emit_no_std => {
+                let func = self.declare_c_fn(&builtin_name["__builtin_".len()..], decl_id)?;
+                let args = self.convert_exprs(ctx.used(), args)?;
+                Ok(args.map(|args| mk().call_expr(func, args)))
+            }
+            // The C `fabsl` takes an x87 `long double`, not the `f64` we translate it as, so we
+            // clear the sign bit ourselves
+            "__builtin_fabsl"
+                if self.tcfg.emit_no_std && self.tcfg.long_double_mode == LongDoubleMode::F64 =>
+            {
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                Ok(val.map(|x| {
+                    let bits = mk().method_call_expr(x, "to_bits", vec![] as Vec<P<Expr>>);
+                    let mask = mk().lit_expr(mk().int_lit(i64::max_value() as u128, "u64"));
+                    let bits = mk().binary_expr(BinOpKind::BitAnd, bits, mask);
+                    mk().call_expr(mk().path_expr(vec!["f64", "from_bits"]), vec![bits])
+                }))
+            }
             "__builtin_fabs" | "__builtin_fabsf" | "__builtin_fabsl" => {
                 let val = self.convert_expr(ctx.used(), args[0])?;
                 Ok(val.map(|x| mk().method_call_expr(x, "abs", vec![] as Vec<P<Expr>>)))
@@ -169,7 +236,10 @@ impl<'c> Translation<'c> {
                 // https://github.com/llvm-mirror/llvm/blob/master/lib/CodeGen/IntrinsicLowering.cpp#L470
                 Ok(WithStmts::new_val(mk().lit_expr(mk().int_lit(1, "i32"))))
             }
-            "__builtin_expect" => self.convert_expr(ctx.used(), args[0]),
+            // Rust has no stable branch prediction hints, so we only keep the expression
+            "__builtin_expect" | "__builtin_expect_with_probability" => {
+                self.convert_expr(ctx.used(), args[0])
+            }
 
             "__builtin_popcount" | "__builtin_popcountl" | "__builtin_popcountll" => {
                 let val = self.convert_expr(ctx.used(), args[0])?;
@@ -181,7 +251,7 @@ impl<'c> Translation<'c> {
             "__builtin_bzero" => {
                 let ptr_stmts = self.convert_expr(ctx.used(), args[0])?;
                 let n_stmts = self.convert_expr(ctx.used(), args[1])?;
-                let write_bytes = mk().path_expr(vec!["", "std", "ptr", "write_bytes"]);
+                let write_bytes = mk().path_expr(vec!["", std_or_core, "ptr", "write_bytes"]);
                 let zero = mk().lit_expr(mk().int_lit(0, "u8"));
                 ptr_stmts.and_then(|ptr| {
                     Ok(n_stmts.map(|n| mk().call_expr(write_bytes, vec![ptr, zero, n])))
@@ -197,7 +267,7 @@ impl<'c> Translation<'c> {
             | "__builtin_memchr"
             | "__builtin_memcmp"
             | "__builtin_memmove"
-            | "__builtin_memset" => self.convert_mem_fns(builtin_name, ctx, args),
+            | "__builtin_memset" => self.convert_mem_fns(builtin_name, decl_id, ctx, args),
 
             "__builtin_add_overflow"
             | "__builtin_sadd_overflow"
@@ -250,7 +320,7 @@ impl<'c> Translation<'c> {
                         let if_expr = mk().ifte_expr(if_cond,
                                        mk().block(vec![mk().expr_stmt(minus_one)]),
                                        Some(mk().lit_expr(mk().int_lit(0, "isize"))));
-                        let size_t = mk().path_ty(vec!["libc", "size_t"]);
+                        let size_t = self.ffi_ty("size_t");
                         mk().cast_expr(if_expr, size_t)
                     }))
                 })
@@ -303,26 +373,6 @@ impl<'c> Translation<'c> {
                 Err(TranslationError::generic("Unsupported va_end"))
             }
 
-            "__builtin_alloca" => {
-                let count = self.convert_expr(ctx.used(), args[0])?;
-                count.and_then(|count| {
-                    let alloca_name = self.renamer.borrow_mut().fresh();
-                    let zero_elem = mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed));
-                    Ok(WithStmts::new(
-                        vec![mk().local_stmt(P(mk().local(
-                            mk().mutbl().ident_pat(&alloca_name),
-                            None as Option<P<Ty>>,
-                            Some(vec_expr(zero_elem, cast_int(count, "usize", false))),
-                        )))],
-                        mk().method_call_expr(
-                            mk().ident_expr(&alloca_name),
-                            "as_mut_ptr",
-                            vec![] as Vec<P<Expr>>,
-                        ),
-                    ))
-                })
-            }
-
             // SIMD builtins:
             "__builtin_ia32_aeskeygenassist128" => {
                 self.convert_simd_builtin(ctx, "_mm_aeskeygenassist_si128", args)
@@ -412,6 +462,12 @@ impl<'c> Translation<'c> {
             "__builtin_ia32_pcmpestrio128" => self.convert_simd_builtin(ctx, "_mm_cmpestro", args),
             "__builtin_ia32_pcmpestris128" => self.convert_simd_builtin(ctx, "_mm_cmpestrs", args),
             "__builtin_ia32_pcmpestriz128" => self.convert_simd_builtin(ctx, "_mm_cmpestrz", args),
+            "__builtin_neon_vld1_v"
+            | "__builtin_neon_vld1q_v"
+            | "__builtin_neon_vst1_v"
+            | "__builtin_neon_vst1q_v" if self.tcfg.translate_simd => {
+                self.convert_neon_builtin(ctx, builtin_name, args)
+            }
 
             "__sync_val_compare_and_swap_1"
             | "__sync_val_compare_and_swap_2"
@@ -596,14 +652,45 @@ impl<'c> Translation<'c> {
             "__builtin_assume_aligned" => Ok(self.convert_expr(ctx.used(), args[0])?),
             // Skip over, there's no way to implement it in Rust
             "__builtin_unwind_init" => Ok(WithStmts::new_val(self.panic_or_err("no value"))),
+            "__builtin_trap" => {
+                let abort = mk().call_expr(self.libc_fn_path("abort", decl_id)?, vec![] as Vec<P<Expr>>);
+                Ok(WithStmts::new_unsafe_val(abort))
+            }
+
+            // Builtins that are just the corresponding C library function
+            "__builtin_abort" | "__builtin_exit" | "__builtin_malloc" | "__builtin_calloc"
+            | "__builtin_realloc" | "__builtin_free" | "__builtin_strlen" | "__builtin_strcmp"
+            | "__builtin_strncmp" | "__builtin_strcpy" | "__builtin_strncpy" | "__builtin_strcat"
+            | "__builtin_strncat" | "__builtin_strchr" | "__builtin_strrchr" | "__builtin_strstr"
+            | "__builtin_puts" | "__builtin_putchar" | "__builtin_printf" | "__builtin_sprintf"
+            | "__builtin_snprintf" | "__builtin_wcslen" | "__builtin_wmemchr" => {
+                let name = &builtin_name["__builtin_".len()..];
+                let func = self.libc_fn_path(name, decl_id)?;
+                let args = self.convert_exprs(ctx.used(), args)?;
+                Ok(args.map(|args| mk().call_expr(func, args)))
+            }
+
+            // Both have type `!`, so code after them type checks like
+            // code after a diverging call
+            "__builtin_unreachable" if self.tcfg.checked_unreachable => {
+                Ok(WithStmts::new_val(mk().mac_expr(mk().mac(
+                    vec!["unreachable"],
+                    vec![],
+                    MacDelimiter::Parenthesis,
+                ))))
+            }
             "__builtin_unreachable" => {
-                Ok(WithStmts::new(
-                    vec![mk().semi_stmt(mk().mac_expr(mk().mac(
-                        vec!["unreachable"],
-                        vec![],
-                        MacDelimiter::Parenthesis,
-                    )))],
-                    self.panic_or_err("unreachable stub"),
+                let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+                let path = mk().path_expr(vec!["", std_or_core, "hint", "unreachable_unchecked"]);
+                Ok(WithStmts::new_unsafe_val(mk().call_expr(path, vec![] as Vec<P<Expr>>)))
+            }
+
+            // Only valid in fortified wrappers, which we declare instead of translating
+            "__builtin_va_arg_pack" | "__builtin_va_arg_pack_len" => {
+                Err(format_translation_err!(
+                    self.ast_context.display_loc(src_loc),
+                    "{} can only be used in gnu_inline wrapper functions",
+                    builtin_name,
                 ))
             }
 
@@ -652,22 +739,30 @@ impl<'c> Translation<'c> {
         })
     }
 
-    /// Converts a __buitlin_mem* use by calling into libc's mem* directly.
+    /// Converts a __buitlin_mem* use by calling into libc's mem* directly. When the result of
+    /// `memcpy`, `memmove` or `memset` is unused, we use the equivalent `ptr` functions instead.
     fn convert_mem_fns(
         &self,
         builtin_name: &str,
+        builtin: CDeclId,
         ctx: ExprContext,
         args: &[CExprId],
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
         let name = &builtin_name[10..];
-        let mem = mk().path_expr(vec!["libc", name]);
+        if ctx.is_unused() {
+            match name {
+                "memcpy" | "memmove" | "memset" => return self.convert_mem_fn_to_ptr(name, ctx, args),
+                _ => {}
+            }
+        }
+        let mem = self.libc_fn_path(name, builtin)?;
         let args = self.convert_exprs(ctx.used(), args)?;
         args.and_then(|args| {
             let mut args = args.into_iter();
             let dst = args.next().ok_or("Missing dst argument to convert_mem_fns")?;
             let c = args.next().ok_or("Missing c argument to convert_mem_fns")?;
             let len = args.next().ok_or("Missing len argument to convert_mem_fns")?;
-            let size_t = mk().path_ty(vec!["libc", "size_t"]);
+            let size_t = self.ffi_ty("size_t");
             let len1 = mk().cast_expr(len, size_t);
             let mem_expr = mk().call_expr(mem, vec![dst, c, len1]);
 
@@ -681,4 +776,44 @@ impl<'c> Translation<'c> {
             }
         })
     }
+    /// Converts an unused `memcpy`, `memmove` or `memset` into `ptr::copy_nonoverlapping`,
+    /// `ptr::copy` or `ptr::write_bytes` respectively, operating on bytes.
+    pub fn convert_mem_fn_to_ptr(
+        &self,
+        name: &str,
+        ctx: ExprContext,
+        args: &[CExprId],
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let u8_ptr = |mutbl| mk().set_mutbl(mutbl).ptr_ty(mk().path_ty(vec!["u8"]));
+        let args = self.convert_exprs(ctx.used(), args)?;
+        args.and_then(|args| {
+            let mut args = args.into_iter();
+            let dst = args.next().ok_or("Missing dst argument to convert_mem_fn_to_ptr")?;
+            let src = args.next().ok_or("Missing src argument to convert_mem_fn_to_ptr")?;
+            let len = args.next().ok_or("Missing len argument to convert_mem_fn_to_ptr")?;
+            let dst = mk().cast_expr(dst, u8_ptr(Mutability::Mutable));
+            let len = mk().cast_expr(len, mk().path_ty(vec!["usize"]));
+
+            // Note that `ptr::copy*` take the source before the destination
+            let call = match name {
+                "memset" => {
+                    let byte = mk().cast_expr(src, mk().path_ty(vec!["u8"]));
+                    let write_bytes = mk().path_expr(vec!["", std_or_core, "ptr", "write_bytes"]);
+                    mk().call_expr(write_bytes, vec![dst, byte, len])
+                }
+                _ => {
+                    let func = if name == "memcpy" { "copy_nonoverlapping" } else { "copy" };
+                    let src = mk().cast_expr(src, u8_ptr(Mutability::Immutable));
+                    let copy = mk().path_expr(vec!["", std_or_core, "ptr", func]);
+                    mk().call_expr(copy, vec![src, dst, len])
+                }
+            };
+
+            Ok(WithStmts::new(
+                vec![mk().semi_stmt(call)],
+                self.panic_or_err(&format!("__builtin_{} not used", name)),
+            ))
+        })
+    }
 }
@@ -0,0 +1,125 @@
+//! Cleanup labels that every error path jumps to. With
+//! `--structured-cleanup`, in
+//!
+//! ```c
+//! int load(const char *path) {
+//!     int rc = -1;
+//!     char *buf = malloc(SIZE);
+//!     if (!buf)
+//!         goto out;
+//!     if (read_all(path, buf) < 0)
+//!         goto out;
+//!     rc = parse(buf);
+//! out:
+//!     free(buf);
+//!     return rc;
+//! }
+//! ```
+//!
+//! every `goto out` is replaced with a copy of the statements following `out`,
+//! so the error paths become early returns instead of a `current_block` state
+//! machine. A label qualifies if it is a statement of the function body, the
+//! statements from it to the end of the body end with a `return` (or the
+//! function returns `void`) and have no labels, `goto`s, `switch`es or
+//! declarations of their own, and every local variable they use is declared
+//! before the first `goto` to the label.
+
+use super::*;
+use crate::c_ast::iterators::DFNodes;
+
+impl<'c> Translation<'c> {
+    /// Find the cleanup label of a function body, if it has one
+    pub fn find_cleanup_label(&self, body: CStmtId, is_void: bool) {
+        let ctx = &self.ast_context;
+        let body_stmts = match ctx[body].kind {
+            CStmtKind::Compound(ref stmts) => stmts,
+            _ => return,
+        };
+        let label_idx = match body_stmts
+            .iter()
+            .rposition(|&stmt_id| match ctx[stmt_id].kind {
+                CStmtKind::Label(_) => true,
+                _ => false,
+            }) {
+            Some(idx) => idx,
+            None => return,
+        };
+        let label_id = body_stmts[label_idx];
+        let mut cleanup = match ctx[label_id].kind {
+            CStmtKind::Label(sub_stmt) => vec![sub_stmt],
+            _ => return,
+        };
+        cleanup.extend_from_slice(&body_stmts[label_idx + 1..]);
+
+        let ends_with_return = match cleanup.last().map(|&stmt_id| &ctx[stmt_id].kind) {
+            Some(CStmtKind::Return(_)) => true,
+            _ => false,
+        };
+        if !ends_with_return && !is_void {
+            return;
+        }
+
+        let mut cleanup_refs = HashSet::new();
+        for &stmt_id in &cleanup {
+            for node in DFNodes::new(ctx, SomeId::Stmt(stmt_id)) {
+                match node {
+                    SomeId::Stmt(stmt_id) => match ctx[stmt_id].kind {
+                        CStmtKind::Label(_)
+                        | CStmtKind::Goto(_)
+                        | CStmtKind::Switch { .. }
+                        | CStmtKind::Case(..)
+                        | CStmtKind::Default(_)
+                        | CStmtKind::Decls(_) => return,
+                        _ => {}
+                    },
+                    SomeId::Expr(expr_id) => {
+                        if let CExprKind::DeclRef(_, decl_id, _) = ctx[expr_id].kind {
+                            cleanup_refs.insert(decl_id);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // The cleanup may only use the locals already declared where we copy it
+        let first_goto = body_stmts[..label_idx].iter().position(|&stmt_id| {
+            DFNodes::new(ctx, SomeId::Stmt(stmt_id)).any(|node| match node {
+                SomeId::Stmt(goto_id) => match ctx[goto_id].kind {
+                    CStmtKind::Goto(target) => target == label_id,
+                    _ => false,
+                },
+                _ => false,
+            })
+        });
+        let first_goto = match first_goto {
+            Some(idx) => idx,
+            None => return,
+        };
+        let mut declared_before = HashSet::new();
+        for &stmt_id in &body_stmts[..first_goto] {
+            if let CStmtKind::Decls(ref decls) = ctx[stmt_id].kind {
+                declared_before.extend(decls.iter().copied());
+            }
+        }
+        for node in DFNodes::new(ctx, SomeId::Stmt(body)) {
+            if let SomeId::Decl(decl_id) = node {
+                if cleanup_refs.contains(&decl_id) && !declared_before.contains(&decl_id) {
+                    return;
+                }
+            }
+        }
+
+        self.cleanup_labels.borrow_mut().insert(label_id, cleanup);
+    }
+
+    /// Whether `goto`s to a label are replaced with the cleanup following it
+    pub fn is_cleanup_label(&self, label_id: CLabelId) -> bool {
+        self.cleanup_labels.borrow().contains_key(&label_id)
+    }
+
+    /// The statements to copy in place of a `goto` to a cleanup label
+    pub fn cleanup_stmts(&self, label_id: CLabelId) -> Vec<CStmtId> {
+        self.cleanup_labels.borrow()[&label_id].clone()
+    }
+}
@@ -1,11 +1,16 @@
 use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
 use syntax::util::comments::CommentStyle;
 use syntax::source_map::{DUMMY_SP, Span};
-use crate::c_ast::{CDeclId, CDeclKind, CommentContext, SrcLoc, TypedAstContext};
+use crate::c_ast::{
+    CDeclId, CDeclKind, CExprId, CExprKind, CQualTypeId, CommentContext, Printer, SrcLoc, SrcSpan,
+    TypedAstContext,
+};
 use crate::c_ast::iterators::{NodeVisitor, SomeId};
 use crate::rust_ast::pos_to_span;
 use crate::rust_ast::comment_store::CommentStore;
-use super::Translation;
+use super::{source_map, Translation};
 
 struct CommentLocator<'c> {
     ast_context: &'c TypedAstContext,
@@ -14,6 +19,15 @@ struct CommentLocator<'c> {
     spans: &'c mut HashMap<SomeId, Span>,
     top_decls: &'c HashSet<CDeclId>,
     last_id: Option<SomeId>,
+    /// Locations of the `--source-map` markers, if we're adding them
+    source_locs: Option<&'c mut Vec<SrcSpan>>,
+    /// The top-level declaration to annotate with its C source, for
+    /// `--annotate-with-c-source`
+    annotated_decl: Option<CDeclId>,
+    /// Lines of the C files we've read annotations from
+    c_sources: &'c mut HashMap<PathBuf, Option<Vec<String>>>,
+    /// The statements and declarations we're inside of, innermost last
+    enclosing: Vec<SomeId>,
 }
 
 impl<'c> CommentLocator<'c> {
@@ -58,6 +72,91 @@ impl<'c> CommentLocator<'c> {
             }
         }
     }
+
+    /// The C source of a declaration as comment lines, headed by its location
+    fn c_source_comments(&mut self, loc: SrcSpan) -> Vec<String> {
+        let ast_context = self.ast_context;
+        let path = match ast_context.get_span_path(&loc) {
+            Some(path) => path,
+            None => return vec![],
+        };
+        let lines = self.c_sources
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                fs::read_to_string(path)
+                    .map(|source| source.lines().map(String::from).collect())
+                    .map_err(|e| warn!("Could not read C source {}: {}", path.display(), e))
+                    .ok()
+            });
+        let lines = match lines {
+            Some(lines) => lines,
+            None => return vec![],
+        };
+
+        let begin = loc.begin_line as usize;
+        let end = (loc.end_line as usize).min(lines.len());
+        if begin == 0 || begin > end {
+            return vec![];
+        }
+        let mut comments = vec![format!("// {}:{}", path.display(), begin)];
+        comments.extend(lines[begin - 1..end].iter().map(|line| {
+            let line = line.trim_end();
+            if line.is_empty() {
+                "//".to_string()
+            } else {
+                format!("// {}", line)
+            }
+        }));
+        comments
+    }
+
+    /// Note a resolved `_Generic` selection before the statement containing
+    /// it, or the declaration outside of functions, since expressions can't
+    /// have comments
+    fn note_generic_selection(&mut self, controlling: CExprId, assoc_ty: Option<CQualTypeId>) {
+        let stmt = self.enclosing.iter().rev().find(|id| match id {
+            SomeId::Stmt(_) => true,
+            _ => false,
+        });
+        let id = match stmt.or_else(|| self.enclosing.last()) {
+            Some(&id) => id,
+            None => return,
+        };
+        let mut controlling_src = vec![];
+        let mut assoc_src = vec![];
+        if Printer::new(&mut controlling_src)
+            .print_expr(controlling, self.ast_context)
+            .is_err()
+        {
+            return;
+        }
+        match assoc_ty {
+            Some(assoc_ty) => {
+                if Printer::new(&mut assoc_src)
+                    .print_qtype(assoc_ty, None, self.ast_context)
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            None => assoc_src.extend_from_slice(b"default"),
+        }
+        let comment = format!(
+            "// _Generic selection on `{}` resolved to its `{}` association",
+            String::from_utf8_lossy(&controlling_src),
+            String::from_utf8_lossy(&assoc_src),
+        );
+
+        if let Some(existing) = self.spans.get(&id) {
+            self.comment_store.extend_existing_comments(
+                &[comment],
+                Some(existing.lo()),
+                CommentStyle::Isolated,
+            );
+        } else if let Some(pos) = self.comment_store.add_comments(&[comment]) {
+            self.spans.insert(id, pos_to_span(pos));
+        }
+    }
 }
 
 impl<'c> NodeVisitor for CommentLocator<'c> {
@@ -69,14 +168,36 @@ impl<'c> NodeVisitor for CommentLocator<'c> {
                 return false;
             }
         }
+        match id {
+            SomeId::Stmt(_) | SomeId::Decl(_) => self.enclosing.push(id),
+            SomeId::Expr(expr_id) => {
+                if let CExprKind::GenericSelection(_, controlling, assoc_ty, _) =
+                    self.ast_context[expr_id].kind
+                {
+                    self.note_generic_selection(controlling, assoc_ty);
+                }
+            }
+            SomeId::Type(_) => {}
+        }
 
         if let Some(loc) = self.ast_context.get_src_loc(id) {
             // Check if we have a comment before this node that we need to
             // attach to the end of the last node.
             self.check_last_for_trailing(loc.begin());
 
-            let comments = self.comment_context
+            let mut comments = self.comment_context
                 .get_comments_before(loc.begin(), &self.ast_context);
+            if let SomeId::Decl(decl_id) = id {
+                if Some(decl_id) == self.annotated_decl {
+                    comments.extend(self.c_source_comments(loc));
+                }
+            }
+            if let Some(ref mut source_locs) = self.source_locs {
+                if source_map::has_source_marker(self.ast_context, id) {
+                    comments.push(source_map::source_marker(source_locs.len()));
+                    source_locs.push(loc);
+                }
+            }
             if let SomeId::Decl(decl_id) = id {
                 let decl_kind = &self.ast_context[decl_id].kind;
                 if let CDeclKind::NonCanonicalDecl { canonical_decl } = decl_kind {
@@ -118,6 +239,9 @@ impl<'c> NodeVisitor for CommentLocator<'c> {
                 return;
             }
         }
+        if let SomeId::Stmt(_) | SomeId::Decl(_) = id {
+            self.enclosing.pop();
+        }
         if let Some(loc) = self.ast_context.get_src_loc(id) {
             let comments = self.comment_context
                 .get_comments_before(loc.end(), &self.ast_context);
@@ -146,8 +270,19 @@ impl<'c> Translation<'c> {
             .copied()
             .collect();
         let mut spans: HashMap<SomeId, Span> = HashMap::new();
+        let mut source_locs = vec![];
+        let mut c_sources = HashMap::new();
         for decl_id in &self.ast_context.c_decls_top {
             top_decls.remove(decl_id);
+            let annotated_decl = match self.ast_context[*decl_id].kind {
+                CDeclKind::Function { body: Some(_), .. }
+                | CDeclKind::Variable { is_defn: true, .. }
+                    if self.tcfg.annotate_with_c_source =>
+                {
+                    Some(*decl_id)
+                }
+                _ => None,
+            };
             let mut visitor = CommentLocator {
                 ast_context: &self.ast_context,
                 comment_context: &self.comment_context,
@@ -155,10 +290,19 @@ impl<'c> Translation<'c> {
                 spans: &mut spans,
                 top_decls: &top_decls,
                 last_id: None,
+                source_locs: if self.tcfg.source_map {
+                    Some(&mut source_locs)
+                } else {
+                    None
+                },
+                annotated_decl,
+                c_sources: &mut c_sources,
+                enclosing: vec![],
             };
             visitor.visit_tree(&self.ast_context, SomeId::Decl(*decl_id));
         }
         self.spans = spans;
+        self.source_locs = source_locs;
     }
 
     pub fn get_span(&self, id: SomeId) -> Option<Span> {
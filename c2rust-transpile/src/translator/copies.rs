@@ -0,0 +1,84 @@
+#![deny(missing_docs)]
+//! Copies of struct values. C copies a struct when it is assigned, passed or
+//! returned by value, so translated structs derive `Copy` and `Clone`
+//! whenever their fields allow it. A field whose type `--type-map` replaces
+//! with a Rust type that isn't `Copy`, like `Vec<u8>`, keeps the struct from
+//! deriving `Copy`. The copies of such a struct are translated as `.clone()`
+//! calls instead. If a field isn't even `Clone`, copying the struct is an
+//! error: copying its bits would leave two owners of the same resources.
+
+use super::*;
+use crate::type_map::{self, Copyability};
+
+impl<'c> Translation<'c> {
+    /// How the values of a C type can be copied in its translation
+    pub fn type_copyability(&self, ctype: CTypeId) -> Copyability {
+        if let Some(ty) = self.tcfg.type_map.lookup(&self.ast_context, ctype) {
+            return type_map::copyability(&ty);
+        }
+        match self.ast_context[ctype].kind {
+            CTypeKind::Struct(decl_id) => self.record_copyability(decl_id),
+            CTypeKind::ConstantArray(ty, _)
+            | CTypeKind::IncompleteArray(ty)
+            | CTypeKind::Elaborated(ty)
+            | CTypeKind::Paren(ty)
+            | CTypeKind::TypeOf(ty)
+            | CTypeKind::Auto(ty) => self.type_copyability(ty),
+            CTypeKind::Attributed(ty, _) => self.type_copyability(ty.ctype),
+            CTypeKind::Typedef(decl_id) => match self.ast_context[decl_id].kind {
+                CDeclKind::Typedef { typ, .. } => self.type_copyability(typ.ctype),
+                _ => Copyability::Copy,
+            },
+            _ => Copyability::Copy,
+        }
+    }
+
+    /// How the values of a struct can be copied, which is how its least
+    /// capable field can be
+    pub fn record_copyability(&self, decl_id: CRecordId) -> Copyability {
+        if let Some(&copyability) = self.record_copyabilities.borrow().get(&decl_id) {
+            return copyability;
+        }
+        let copyability = match self.ast_context[decl_id].kind {
+            CDeclKind::Struct { fields: Some(ref fields), .. } => fields
+                .iter()
+                .filter_map(|&field_id| match self.ast_context[field_id].kind {
+                    CDeclKind::Field { typ, .. } => Some(self.type_copyability(typ.ctype)),
+                    _ => None,
+                })
+                .min()
+                .unwrap_or(Copyability::Copy),
+            _ => Copyability::Copy,
+        };
+        self.record_copyabilities.borrow_mut().insert(decl_id, copyability);
+        copyability
+    }
+
+    /// The traits a translated struct derives to be copied
+    pub fn record_derives(&self, decl_id: CRecordId) -> Vec<&'static str> {
+        match self.record_copyability(decl_id) {
+            Copyability::Copy => vec!["Copy", "Clone"],
+            Copyability::Clone => vec!["Clone"],
+            Copyability::NotClone => vec![],
+        }
+    }
+
+    /// Copy the value of an lvalue of the given type that can't be copied
+    /// implicitly
+    pub fn copy_value(
+        &self,
+        ctype: CTypeId,
+        val: WithStmts<P<Expr>>,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        match self.type_copyability(ctype) {
+            Copyability::Copy => Ok(val),
+            Copyability::Clone => {
+                Ok(val.map(|val| mk().method_call_expr(val, "clone", vec![] as Vec<P<Expr>>)))
+            }
+            Copyability::NotClone => Err(format_err!(
+                "Cannot copy a value whose translation is neither Copy nor Clone"
+            )
+            .into()),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+#![deny(missing_docs)]
+//! The traits `--derive` adds to translated types. `Debug` and `PartialEq` are
+//! derived by plain structs, those without bitfields or packing, whose fields
+//! all implement the trait. `Default` is implemented, rather than derived, by
+//! structs and unions as the all-zero value, which is what C initializes
+//! objects of static duration and `= {0}` initializers to. Types with fields
+//! of a type from `--type-map` get none of them, since we can't tell what
+//! that type implements or whether zero is a valid value of it.
+
+use super::*;
+
+/// Std only implements traits for arrays of up to this many elements
+const MAX_TRAIT_ARRAY_LEN: usize = 32;
+
+/// Std only implements traits for function pointers with up to this many
+/// parameters
+const MAX_TRAIT_FN_PARAMS: usize = 12;
+
+impl<'c> Translation<'c> {
+    /// The traits a translated struct derives because of `--derive`
+    pub fn policy_derives(&self, decl_id: CRecordId) -> Vec<&'static str> {
+        let mut derives = vec![];
+        if !self.is_plain_struct(decl_id) {
+            return derives;
+        }
+        if self.tcfg.derives.contains(&Derive::Debug) {
+            derives.push("Debug");
+        }
+        if self.tcfg.derives.contains(&Derive::PartialEq) {
+            derives.push("PartialEq");
+        }
+        derives
+    }
+
+    /// Implement `Default` for a translated struct or union as its all-zero
+    /// value, if `--derive default` asks for it and zero is a valid value of
+    /// all its fields
+    pub fn convert_default_impl(&self, decl_id: CRecordId, name: &str) -> Option<P<Item>> {
+        if !self.tcfg.derives.contains(&Derive::Default) || !self.is_zeroable_record(decl_id) {
+            return None;
+        }
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let zeroed = mk().path_expr(vec!["", std_or_core, "mem", "MaybeUninit", "zeroed"]);
+        let zeroed = mk().call_expr(zeroed, vec![] as Vec<P<Expr>>);
+        let value = mk().method_call_expr(zeroed, "assume_init", vec![] as Vec<P<Expr>>);
+        let body = mk().block(vec![mk().expr_stmt(mk().block_expr(
+            mk().unsafe_().block(vec![mk().expr_stmt(value)]),
+        ))]);
+        let decl = mk().fn_decl(vec![], FunctionRetTy::Ty(mk().path_ty(vec!["Self"])));
+        let default_fn = mk().fn_impl_item("default", decl, body);
+        Some(mk().trait_impl_item(
+            vec!["Default"],
+            mk().path_ty(vec![name]),
+            vec![default_fn],
+        ))
+    }
+
+    /// Whether a struct is laid out field by field, so that deriving `Debug`
+    /// and `PartialEq` compares and prints its C fields, and all those fields
+    /// implement them
+    fn is_plain_struct(&self, decl_id: CRecordId) -> bool {
+        match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                is_packed: false,
+                max_field_alignment: None,
+                ..
+            } => fields.iter().all(|&field_id| match self.ast_context[field_id].kind {
+                CDeclKind::Field {
+                    typ,
+                    bitfield_width: None,
+                    ..
+                } => self.implements_std_traits(typ.ctype),
+                _ => false,
+            }),
+            _ => false,
+        }
+    }
+
+    /// Whether the translation of a type implements `Debug` and `PartialEq`
+    fn implements_std_traits(&self, ctype: CTypeId) -> bool {
+        if self.tcfg.type_map.lookup(&self.ast_context, ctype).is_some() {
+            return false;
+        }
+        let kind = &self.ast_context[ctype].kind;
+        if kind.is_integral_type() || kind.is_bool() {
+            return true;
+        }
+        match *kind {
+            CTypeKind::Half | CTypeKind::Float | CTypeKind::Double => true,
+            // `f128` implements neither
+            CTypeKind::LongDouble => self.tcfg.long_double_mode == LongDoubleMode::F64,
+            CTypeKind::Pointer(pointee) => match self.ast_context.resolve_type(pointee.ctype).kind {
+                CTypeKind::Function(_, ref params, ..) => params.len() <= MAX_TRAIT_FN_PARAMS,
+                _ => true,
+            },
+            CTypeKind::ConstantArray(elt, len) => {
+                len <= MAX_TRAIT_ARRAY_LEN && self.implements_std_traits(elt)
+            }
+            // Flexible array members are translated as empty arrays
+            CTypeKind::IncompleteArray(elt) => self.implements_std_traits(elt),
+            CTypeKind::Elaborated(ty)
+            | CTypeKind::Paren(ty)
+            | CTypeKind::TypeOf(ty)
+            | CTypeKind::Auto(ty) => self.implements_std_traits(ty),
+            CTypeKind::Attributed(ty, _) => self.implements_std_traits(ty.ctype),
+            CTypeKind::Typedef(decl_id) => match self.ast_context[decl_id].kind {
+                CDeclKind::Typedef { typ, .. } => self.implements_std_traits(typ.ctype),
+                _ => false,
+            },
+            CTypeKind::Struct(decl_id) => self.is_plain_struct(decl_id),
+            CTypeKind::Enum(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether all bits zero is a valid value of all fields of a struct or
+    /// union, so that it can implement `Default` by zeroing its memory
+    fn is_zeroable_record(&self, decl_id: CRecordId) -> bool {
+        match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                ..
+            }
+            | CDeclKind::Union {
+                fields: Some(ref fields),
+                ..
+            } => fields.iter().all(|&field_id| match self.ast_context[field_id].kind {
+                CDeclKind::Field { typ, .. } => self.is_zeroable(typ.ctype),
+                _ => false,
+            }),
+            _ => false,
+        }
+    }
+
+    /// Whether all bits zero is a valid value of the translation of a type.
+    /// It is for all the types we translate C types to; pointers become raw
+    /// pointers or `Option`s of function pointers.
+    fn is_zeroable(&self, ctype: CTypeId) -> bool {
+        if self.tcfg.type_map.lookup(&self.ast_context, ctype).is_some() {
+            return false;
+        }
+        match self.ast_context[ctype].kind {
+            CTypeKind::ConstantArray(elt, _)
+            | CTypeKind::IncompleteArray(elt)
+            | CTypeKind::Elaborated(elt)
+            | CTypeKind::Paren(elt)
+            | CTypeKind::TypeOf(elt)
+            | CTypeKind::Auto(elt) => self.is_zeroable(elt),
+            CTypeKind::Attributed(ty, _) => self.is_zeroable(ty.ctype),
+            CTypeKind::Typedef(decl_id) => match self.ast_context[decl_id].kind {
+                CDeclKind::Typedef { typ, .. } => self.is_zeroable(typ.ctype),
+                _ => false,
+            },
+            CTypeKind::Struct(decl_id) | CTypeKind::Union(decl_id) => {
+                self.is_zeroable_record(decl_id)
+            }
+            _ => true,
+        }
+    }
+}
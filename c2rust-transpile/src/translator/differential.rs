@@ -0,0 +1,127 @@
+//! The exported functions the differential tests of `--differential-harness`
+//! call. A function is tested if it is defined with external linkage at the
+//! top level of its module and only takes integer, floating point and `_Bool`
+//! arguments or pointers to them, and returns one of those types or nothing.
+//! The tests themselves are emitted with the build files, see
+//! `build_files::emit_differential_harness`.
+
+use super::*;
+
+/// An exported function the differential tests call
+#[derive(Serialize, Debug, Clone)]
+pub struct HarnessFn {
+    /// C name of the function
+    pub symbol: String,
+    /// Name of the function in the translated module
+    pub rust_name: String,
+    pub params: Vec<HarnessParam>,
+    /// Rust type of the result, if it isn't `void`
+    pub ret: Option<String>,
+}
+
+/// An argument the differential tests generate
+#[derive(Serialize, Debug, Clone)]
+pub struct HarnessParam {
+    /// Rust type of the argument, or of the buffer it points to
+    pub ty: String,
+    /// Whether the argument points to a buffer
+    pub pointer: bool,
+    /// Whether the buffer the argument points to is mutable
+    pub mutable: bool,
+    /// Whether the argument is an integer following a pointer, which is
+    /// taken to be the length of its buffer and kept within it
+    pub length: bool,
+}
+
+impl<'c> Translation<'c> {
+    /// Record the translation of a function for the differential tests, if
+    /// they can call it
+    pub fn record_harness_fn(&self, decl_id: CDeclId, item: &Item) {
+        if !self.tcfg.differential_harness
+            || self.is_test_harness
+            || self.cur_file.borrow().is_some()
+        {
+            return;
+        }
+        let (name, typ) = match self.ast_context[decl_id].kind {
+            CDeclKind::Function {
+                is_global: true,
+                is_inline: false,
+                ref name,
+                typ,
+                body: Some(_),
+                ..
+            } if name != "main" && self.is_exported(name) => (name, typ),
+            _ => return,
+        };
+        let (ret, params) = match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Function(ret, ref params, false, _, true, _) => (ret, params),
+            _ => return,
+        };
+
+        let ret = match self.ast_context.resolve_type(ret.ctype).kind {
+            CTypeKind::Void => None,
+            _ => match self.harness_scalar_type(ret.ctype) {
+                Some(ty) => Some(ty),
+                None => return,
+            },
+        };
+        let mut harness_params: Vec<HarnessParam> = vec![];
+        for param in params {
+            let follows_pointer = harness_params.last().map_or(false, |prev| prev.pointer);
+            let param = match self.ast_context.resolve_type(param.ctype).kind {
+                CTypeKind::Pointer(pointee) => HarnessParam {
+                    ty: match self.harness_scalar_type(pointee.ctype) {
+                        Some(ty) => ty,
+                        None => return,
+                    },
+                    pointer: true,
+                    mutable: !self.ast_context.resolve_qualifiers(pointee).is_const,
+                    length: false,
+                },
+                ref kind => HarnessParam {
+                    ty: match self.harness_scalar_type(param.ctype) {
+                        Some(ty) => ty,
+                        None => return,
+                    },
+                    pointer: false,
+                    mutable: false,
+                    length: follows_pointer
+                        && kind.is_integral_type()
+                        && *kind != CTypeKind::Bool,
+                },
+            };
+            harness_params.push(param);
+        }
+
+        self.harness_fns.borrow_mut().push(HarnessFn {
+            symbol: name.clone(),
+            rust_name: item.ident.to_string(),
+            params: harness_params,
+            ret,
+        });
+    }
+
+    /// The `libc` type of a scalar C type the differential tests can
+    /// generate values of
+    fn harness_scalar_type(&self, ctype: CTypeId) -> Option<String> {
+        let ty = match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Bool => return Some("bool".to_string()),
+            CTypeKind::Char => "c_char",
+            CTypeKind::SChar => "c_schar",
+            CTypeKind::UChar => "c_uchar",
+            CTypeKind::Short => "c_short",
+            CTypeKind::UShort => "c_ushort",
+            CTypeKind::Int => "c_int",
+            CTypeKind::UInt => "c_uint",
+            CTypeKind::Long => "c_long",
+            CTypeKind::ULong => "c_ulong",
+            CTypeKind::LongLong => "c_longlong",
+            CTypeKind::ULongLong => "c_ulonglong",
+            CTypeKind::Float => "c_float",
+            CTypeKind::Double => "c_double",
+            _ => return None,
+        };
+        Some(format!("libc::{}", ty))
+    }
+}
@@ -0,0 +1,124 @@
+#![deny(missing_docs)]
+//! Lowering of `errno`. C libraries define `errno` as a macro dereferencing the
+//! result of a function returning the thread's error number, like
+//! `(*__errno_location ())`, which translates as is. Code declaring
+//! `extern int errno;` itself instead refers to a variable no C library
+//! defines, so we translate its uses with the function of the target's C
+//! library. With `--portable-errno`, reads of `errno` are translated as
+//! `std::io::Error::last_os_error().raw_os_error()`, which works on every
+//! platform Rust supports.
+
+use super::*;
+
+/// Functions C libraries expand `errno` into a dereference of the result of
+const ERRNO_LOCATION_FNS: &[&str] = &[
+    "__errno_location", // glibc, musl
+    "__error",          // macOS, FreeBSD, DragonFly
+    "__errno",          // OpenBSD, NetBSD, Bionic
+    "___errno",         // Solaris, illumos
+    "_errno",           // MSVC, MinGW
+];
+
+impl<'c> Translation<'c> {
+    /// Translate an `errno` expression, or a read of one with
+    /// `--portable-errno`. Other expressions are left to the caller.
+    pub fn convert_errno(
+        &self,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        if let CExprKind::ImplicitCast(_, lvalue, CastKind::LValueToRValue, _, _) =
+            self.ast_context[expr_id].kind
+        {
+            // `last_os_error` is only available with std
+            if self.tcfg.portable_errno && !self.tcfg.emit_no_std && self.is_errno(lvalue) {
+                let last_os_error =
+                    mk().path_expr(vec!["", "std", "io", "Error", "last_os_error"]);
+                let error = mk().call_expr(last_os_error, vec![] as Vec<P<Expr>>);
+                let raw = mk().method_call_expr(error, "raw_os_error", vec![] as Vec<P<Expr>>);
+                let zero = mk().lit_expr(mk().int_lit(0, ""));
+                let errno = mk().method_call_expr(raw, "unwrap_or", vec![zero]);
+                return Ok(Some(WithStmts::new_val(errno)));
+            }
+            return Ok(None);
+        }
+
+        // The `errno` macros of C libraries already call the right function
+        if self.tcfg.emit_no_libc || !self.is_errno_variable(expr_id) {
+            return Ok(None);
+        }
+        let location = mk().path_expr(vec!["libc", self.errno_location_fn()]);
+        let location = mk().call_expr(location, vec![] as Vec<P<Expr>>);
+        let mut errno = WithStmts::new_val(mk().unary_expr(ast::UnOp::Deref, location));
+        errno.set_unsafe();
+        Ok(Some(errno))
+    }
+
+    /// The function returning the location of `errno` in the C library of the
+    /// translation target
+    fn errno_location_fn(&self) -> &'static str {
+        let triple = &self.ast_context.target_info.triple;
+        if triple.contains("android") {
+            "__errno"
+        } else if triple.contains("linux") {
+            "__errno_location"
+        } else if triple.contains("apple")
+            || triple.contains("freebsd")
+            || triple.contains("dragonfly")
+        {
+            "__error"
+        } else if triple.contains("openbsd") || triple.contains("netbsd") {
+            "__errno"
+        } else if triple.contains("solaris") || triple.contains("illumos") {
+            "___errno"
+        } else if triple.contains("windows") {
+            "_errno"
+        } else {
+            "__errno_location"
+        }
+    }
+
+    /// Whether an expression is `errno`, either as expanded from the macro of
+    /// a C library or as a variable declared `extern int errno;`
+    fn is_errno(&self, expr_id: CExprId) -> bool {
+        let ctx = &self.ast_context;
+        match ctx[expr_id].kind {
+            CExprKind::Paren(_, expr) => self.is_errno(expr),
+            CExprKind::Unary(_, c_ast::UnOp::Deref, arg, _) => match ctx[arg].kind {
+                CExprKind::Call(_, func, ref args) if args.is_empty() => match ctx[func].kind {
+                    CExprKind::ImplicitCast(_, func, CastKind::FunctionToPointerDecay, _, _) => {
+                        match ctx[func].kind {
+                            CExprKind::DeclRef(_, decl_id, _) => match ctx[decl_id].kind {
+                                CDeclKind::Function { ref name, body: None, .. } => {
+                                    ERRNO_LOCATION_FNS.contains(&name.as_str())
+                                }
+                                _ => false,
+                            },
+                            _ => false,
+                        }
+                    }
+                    _ => false,
+                },
+                _ => false,
+            },
+            _ => self.is_errno_variable(expr_id),
+        }
+    }
+
+    /// Whether an expression refers to a variable declared `extern int errno;`
+    fn is_errno_variable(&self, expr_id: CExprId) -> bool {
+        let ctx = &self.ast_context;
+        match ctx[expr_id].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match ctx[decl_id].kind {
+                CDeclKind::Variable {
+                    ref ident,
+                    is_defn: false,
+                    has_static_duration,
+                    has_thread_duration,
+                    ..
+                } => ident == "errno" && (has_static_duration || has_thread_duration),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
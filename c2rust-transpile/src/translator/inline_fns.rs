@@ -0,0 +1,127 @@
+//! Sharing of `static inline` functions defined in headers. Every module whose C
+//! source includes such a header would otherwise get its own copy of the
+//! function. With `--share-inline-fns`, the function is instead translated into
+//! a submodule of the crate's `inline_fns` module, named after the function and
+//! a hash of its translation so that identical definitions from different
+//! translation units end up in the same submodule, and each module imports it
+//! from there.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use syntax::source_map::dummy_spanned;
+
+use super::*;
+use crate::c_ast::iterators::DFNodes;
+
+/// A function translated into a submodule of `inline_fns`
+#[derive(Debug, Clone)]
+pub struct SharedInlineFn {
+    /// Name of the submodule, unique per definition of the function
+    pub module: String,
+    /// Source of the function and the type aliases it uses
+    pub source: String,
+}
+
+impl<'c> Translation<'c> {
+    /// If `item` is the translation of a `static inline` function that can be
+    /// shared, move it to the `inline_fns` module and return an import of it
+    /// instead. Otherwise return `item` itself.
+    pub fn share_inline_fn(&self, ctx: ExprContext, decl_id: CDeclId, item: P<Item>) -> P<Item> {
+        // Integration tests can't import from the library's private modules
+        if !self.tcfg.shares_inline_fns() || self.is_test_harness {
+            return item;
+        }
+        let typedefs = match self.shareable_inline_fn_typedefs(decl_id) {
+            Some(typedefs) => typedefs,
+            None => return item,
+        };
+
+        let mut source = String::new();
+        for typedef_id in typedefs {
+            match self.convert_decl(ctx, typedef_id) {
+                Ok(ConvertedDecl::Item(typedef)) => {
+                    source.push_str(&pprust::item_to_string(&typedef));
+                    source.push('\n');
+                }
+                _ => return item,
+            }
+        }
+        let rust_name = item.ident.to_string();
+        let shared = item.map(|mut item| {
+            item.vis = dummy_spanned(VisibilityKind::Public);
+            item
+        });
+        source.push_str(&pprust::item_to_string(&shared));
+        source.push('\n');
+
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        let c_name = self.ast_context[decl_id].kind.get_name().unwrap();
+        let module = format!("{}_{:016x}", c_name, hasher.finish());
+
+        let path = vec!["crate".to_string(), "inline_fns".to_string(), module.clone(), rust_name];
+        self.shared_inline_fns
+            .borrow_mut()
+            .push(SharedInlineFn { module, source });
+
+        let mk_ = if self.cur_file.borrow().is_some() {
+            mk().pub_()
+        } else {
+            mk()
+        };
+        mk_.use_simple_item(path, None::<String>)
+    }
+
+    /// The typedefs a function uses, if it is a `static inline` function
+    /// defined in a header that can be moved out of its module: it may only
+    /// refer to its own parameters and non-static locals, and to no struct,
+    /// union or enum types, since those are defined separately in every module.
+    fn shareable_inline_fn_typedefs(&self, decl_id: CDeclId) -> Option<IndexSet<CDeclId>> {
+        let decl = &self.ast_context[decl_id];
+        match decl.kind {
+            CDeclKind::Function {
+                is_global: false,
+                is_inline: true,
+                body: Some(_),
+                ..
+            } => {}
+            _ => return None,
+        }
+        match self.ast_context.file_id(decl) {
+            Some(file_id) if file_id != self.main_file => {}
+            _ => return None,
+        }
+
+        let mut typedefs = IndexSet::new();
+        for node in DFNodes::new(&self.ast_context, SomeId::Decl(decl_id)) {
+            match node {
+                SomeId::Type(type_id) => match self.ast_context[type_id].kind {
+                    CTypeKind::Typedef(typedef_id) => {
+                        typedefs.insert(typedef_id);
+                    }
+                    CTypeKind::Struct(_) | CTypeKind::Union(_) | CTypeKind::Enum(_) => {
+                        return None
+                    }
+                    _ => {}
+                },
+                SomeId::Expr(expr_id) => {
+                    if let CExprKind::DeclRef(_, target, _) = self.ast_context[expr_id].kind {
+                        match self.ast_context[target].kind {
+                            CDeclKind::Variable { .. }
+                                if !self.ast_context.c_decls_top.contains(&target) => {}
+                            _ => return None,
+                        }
+                    }
+                }
+                // Statics may need module-level initialization
+                SomeId::Decl(local_id) => match self.ast_context[local_id].kind {
+                    CDeclKind::Variable { has_static_duration: true, .. } => return None,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        Some(typedefs)
+    }
+}
@@ -0,0 +1,105 @@
+#![deny(missing_docs)]
+//! Support for `__int128` beyond its mapping to `i128`. Calls to the libgcc
+//! and compiler-rt helpers implementing 128-bit arithmetic, which some code
+//! calls directly, are translated as the Rust operators they implement.
+//! Rust also aligns 128-bit integers to 8 bytes where C aligns them to 16 on
+//! most 64-bit targets, so records with such fields get their C alignment
+//! explicitly, and padding up to the C offsets of those fields.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Translate a call to a 128-bit arithmetic helper, e.g. `__divti3(a, b)`,
+    /// as the operation it performs. Other expressions are left to the caller.
+    pub fn convert_int128_helper(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        let (func, args) = match self.ast_context[expr_id].kind {
+            CExprKind::Call(_, func, ref args) if args.len() == 2 => (func, args),
+            _ => return Ok(None),
+        };
+        let name = match self.ast_context[func].kind {
+            CExprKind::ImplicitCast(_, func, CastKind::FunctionToPointerDecay, _, _) => {
+                match self.ast_context[func].kind {
+                    CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                        CDeclKind::Function { ref name, body: None, .. } => name.as_str(),
+                        _ => return Ok(None),
+                    },
+                    _ => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+        let op = match name {
+            "__multi3" => None,
+            "__divti3" | "__udivti3" => Some(BinOpKind::Div),
+            "__modti3" | "__umodti3" => Some(BinOpKind::Rem),
+            "__ashlti3" => Some(BinOpKind::Shl),
+            "__ashrti3" | "__lshrti3" => Some(BinOpKind::Shr),
+            _ => return Ok(None),
+        };
+
+        let is_logical_shift = name == "__lshrti3";
+        let args = self.convert_exprs(ctx.used(), args)?;
+        Ok(Some(args.map(|args| {
+            let mut args = args.into_iter();
+            let (lhs, rhs) = (args.next().unwrap(), args.next().unwrap());
+            match op {
+                // Multiplication wraps around like the helper does
+                None => mk().method_call_expr(lhs, "wrapping_mul", vec![rhs]),
+                // A logical shift of the bits of a signed integer
+                Some(BinOpKind::Shr) if is_logical_shift => {
+                    let bits = mk().cast_expr(lhs, mk().path_ty(vec!["u128"]));
+                    let shifted = mk().binary_expr(BinOpKind::Shr, mk().paren_expr(bits), rhs);
+                    mk().cast_expr(mk().paren_expr(shifted), mk().path_ty(vec!["i128"]))
+                }
+                Some(op) => mk().binary_expr(op, lhs, rhs),
+            }
+        })))
+    }
+
+    /// Whether a field type is a 128-bit integer, or an array of them, which
+    /// Rust aligns less than C does
+    pub fn is_int128_field_type(&self, ctype: CTypeId) -> bool {
+        match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Int128 | CTypeKind::UInt128 => true,
+            CTypeKind::ConstantArray(elt, _) | CTypeKind::IncompleteArray(elt) => {
+                self.is_int128_field_type(elt)
+            }
+            _ => false,
+        }
+    }
+
+    /// The C alignment a struct or union needs to be given explicitly
+    /// because Rust aligns its 128-bit integer fields less
+    pub fn int128_record_alignment(&self, decl_id: CRecordId) -> Option<u64> {
+        let (fields, platform_alignment) = match self.ast_context[decl_id].kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                is_packed: false,
+                manual_alignment: None,
+                max_field_alignment: None,
+                platform_alignment,
+                ..
+            }
+            | CDeclKind::Union {
+                fields: Some(ref fields),
+                manual_alignment: None,
+                platform_alignment,
+                ..
+            } => (fields, platform_alignment),
+            _ => return None,
+        };
+        let has_int128_field = fields.iter().any(|&field_id| match self.ast_context[field_id].kind {
+            CDeclKind::Field { typ, .. } => self.is_int128_field_type(typ.ctype),
+            _ => false,
+        });
+        if has_int128_field && platform_alignment > 8 {
+            Some(platform_alignment)
+        } else {
+            None
+        }
+    }
+}
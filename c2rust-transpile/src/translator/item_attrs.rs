@@ -0,0 +1,55 @@
+//! Attributes added to the items of a translation: the `allow`s for the lints
+//! that translated code triggers, placed according to `--allow-lints`, and the
+//! custom attributes `--fn-attribute` adds to every translated function.
+
+use rustc_parse::parse_crate_from_source_str;
+use syntax::sess::ParseSess;
+
+use super::*;
+
+/// Lints that translated code triggers as a matter of course
+pub const ALLOWED_LINTS: &[&str] = &[
+    "non_upper_case_globals",
+    "non_camel_case_types",
+    "non_snake_case",
+    "dead_code",
+    "mutable_transmutes",
+    "unused_mut",
+    "unused_assignments",
+];
+
+/// Parse the text of an outer attribute without its `#[]`, like
+/// `tracing::instrument(skip_all)`
+pub fn parse_attribute(src: &str) -> Result<Attribute, String> {
+    let sess = ParseSess::new(FilePathMapping::empty());
+    let error = || format!("Could not parse function attribute `{}`", src);
+    let krate = parse_crate_from_source_str(
+        FileName::Custom("function attribute".to_string()),
+        format!("#[{}] fn f() {{}}", src),
+        &sess,
+    )
+    .map_err(|mut e| {
+        e.cancel();
+        error()
+    })?;
+    match krate.module.items.into_iter().next() {
+        Some(item) if item.attrs.len() == 1 => Ok(item.attrs[0].clone()),
+        _ => Err(error()),
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Allow the lints of translated code on an item, with `--allow-lints item`
+    pub fn allow_item_lints(&self, mut item: P<Item>) -> P<Item> {
+        if self.tcfg.lint_allow_mode == LintAllowMode::Item {
+            item.attrs.extend(mk().call_attr("allow", ALLOWED_LINTS.to_vec()).into_attrs());
+        }
+        item
+    }
+
+    /// Add the attributes of `--fn-attribute` to a translated function
+    pub fn add_fn_attributes(&self, mut item: P<Item>) -> P<Item> {
+        item.attrs.extend(self.fn_attributes.iter().cloned());
+        item
+    }
+}
@@ -0,0 +1,209 @@
+#![deny(missing_docs)]
+//! Lowering of calls to C library functions to their Rust equivalents, enabled with
+//! `--lower-libc-calls`. Calls to functions not listed here, or whose Rust equivalent
+//! does not preserve the C semantics in the given context, remain calls to the extern
+//! declaration. Expanded `assert`s are translated as `assert!`s.
+
+use super::*;
+
+/// Functions C libraries expand a failing `assert` into a call of
+const ASSERT_FAIL_FNS: &[&str] = &[
+    "__assert_fail", // glibc, musl
+    "__assert_rtn",  // macOS
+    "__assert",      // BSDs
+    "__assert2",     // Bionic
+    "_assert",       // MSVC
+];
+
+impl<'c> Translation<'c> {
+    /// Convert a call to a C library function to its Rust equivalent, if it has one. Only
+    /// direct calls to functions declared but not defined in the translation unit are lowered.
+    pub fn convert_libc_call(
+        &self,
+        ctx: ExprContext,
+        call_expr_ty: CQualTypeId,
+        func: CExprId,
+        args: &[CExprId],
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        let decl_id = match self.ast_context[func].kind {
+            CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _) => {
+                match self.ast_context[fexp].kind {
+                    CExprKind::DeclRef(_, decl_id, _) => decl_id,
+                    _ => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+        let name = match self.ast_context[decl_id].kind {
+            CDeclKind::Function { ref name, body: None, .. } => name.as_str(),
+            _ => return Ok(None),
+        };
+        let lowered = match name {
+            // `CStr` is only available with std
+            "strlen" if !self.tcfg.emit_no_std => {
+                let ret_ty = self.convert_type(call_expr_ty.ctype)?;
+                let mut len = self.convert_expr(ctx.used(), args[0])?.map(|s| {
+                    let from_ptr = mk().path_expr(vec!["", "std", "ffi", "CStr", "from_ptr"]);
+                    let bytes = mk().method_call_expr(
+                        mk().call_expr(from_ptr, vec![s]),
+                        "to_bytes",
+                        vec![] as Vec<P<Expr>>,
+                    );
+                    let len = mk().method_call_expr(bytes, "len", vec![] as Vec<P<Expr>>);
+                    mk().cast_expr(len, ret_ty)
+                });
+                len.set_unsafe();
+                len
+            }
+
+            // The result, the destination pointer, is rarely used. When it is, we keep the call.
+            "memcpy" | "memmove" | "memset" if ctx.is_unused() => {
+                self.convert_mem_fn_to_ptr(name, ctx, args)?
+            }
+
+            // The number of bytes written is not available from the Rust macros
+            "printf" | "fprintf" if !self.tcfg.emit_no_std && ctx.is_unused() => {
+                return self.convert_printf_call(ctx, name, args);
+            }
+
+            "abs" | "labs" | "llabs" => {
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                val.map(|x| mk().method_call_expr(x, "wrapping_abs", vec![] as Vec<P<Expr>>))
+            }
+
+            // The inherent float methods other than these are only available with std
+            "fabs" | "fabsf" | "sqrt" | "sqrtf" | "floor" | "floorf" | "ceil" | "ceilf"
+            | "trunc" | "truncf" | "round" | "roundf"
+                if !self.tcfg.emit_no_std =>
+            {
+                let method = name.trim_end_matches('f');
+                let method = if method == "fabs" { "abs" } else { method };
+                let val = self.convert_expr(ctx.used(), args[0])?;
+                val.map(|x| mk().method_call_expr(x, method, vec![] as Vec<P<Expr>>))
+            }
+
+            "abort" if !self.tcfg.emit_no_std => {
+                let abort = mk().path_expr(vec!["", "std", "process", "abort"]);
+                WithStmts::new_val(mk().call_expr(abort, vec![] as Vec<P<Expr>>))
+            }
+
+            "exit" if !self.tcfg.emit_no_std => {
+                let exit = mk().path_expr(vec!["", "std", "process", "exit"]);
+                let status = self.convert_expr(ctx.used(), args[0])?;
+                status.map(|status| {
+                    let status = mk().cast_expr(status, mk().path_ty(vec!["i32"]));
+                    mk().call_expr(exit, vec![status])
+                })
+            }
+
+            _ => return Ok(None),
+        };
+        Ok(Some(lowered))
+    }
+
+    /// Whether an expression statement is an expanded `assert` to translate
+    /// as `assert!`. The assertions of test drivers are always translated.
+    pub fn is_assertion(&self, expr_id: CExprId) -> bool {
+        (self.tcfg.lower_libc_calls || self.is_test_harness)
+            && self.asserted_condition(expr_id).is_some()
+    }
+
+    /// Translate an expanded `assert(cond)` as `assert!(cond)`, or as
+    /// `debug_assert!(cond)` with `--debug-asserts`, which like `NDEBUG` skips
+    /// the check in release builds
+    pub fn convert_assertion(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Vec<Stmt>, TranslationError> {
+        let cond = self
+            .asserted_condition(expr_id)
+            .ok_or_else(|| format_err!("Expected an assertion"))?;
+        let mut cond = self.convert_condition(ctx, true, cond)?;
+        let mut stmts = vec![];
+        stmts.append(cond.stmts_mut());
+        let args = vec![TokenTree::token(
+            token::Interpolated(Rc::new(Nonterminal::NtExpr(cond.into_value()))),
+            DUMMY_SP,
+        )];
+        let name = if self.tcfg.debug_asserts && !self.is_test_harness {
+            "debug_assert"
+        } else {
+            "assert"
+        };
+        let assert = mk().mac_expr(mk().mac(vec![name], args, MacDelimiter::Parenthesis));
+        stmts.push(mk().semi_stmt(assert));
+        Ok(stmts)
+    }
+
+    /// The condition of an expanded `assert`. Each C library expands it
+    /// differently, e.g. to
+    ///
+    /// - `cond ? (void) 0 : __assert_fail(...)`
+    /// - `(void) ((cond) || (__assert_fail(...), 0))`
+    /// - `(void) sizeof(cond ? 1 : 0), ({ if (cond) ; else __assert_fail(...); })`
+    fn asserted_condition(&self, expr_id: CExprId) -> Option<CExprId> {
+        let ctx = &self.ast_context;
+        match ctx[expr_id].kind {
+            CExprKind::Paren(_, expr)
+            | CExprKind::ExplicitCast(_, expr, CastKind::ToVoid, _, _)
+            | CExprKind::Unary(_, UnOp::Extension, expr, _) => self.asserted_condition(expr),
+            CExprKind::Binary(_, BinOp::Comma, lhs, rhs, _, _) => match ctx[lhs].kind {
+                CExprKind::ExplicitCast(_, size_of, CastKind::ToVoid, _, _) => {
+                    match ctx[size_of].kind {
+                        CExprKind::UnaryType(..) => self.asserted_condition(rhs),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            CExprKind::Binary(_, BinOp::Or, cond, fail, _, _) if self.is_assert_fail(fail) => {
+                Some(cond)
+            }
+            CExprKind::Conditional(_, cond, _, fail) if self.is_assert_fail(fail) => Some(cond),
+            CExprKind::Statements(_, compound) => match ctx[compound].kind {
+                CStmtKind::Compound(ref stmts) if stmts.len() == 1 => match ctx[stmts[0]].kind {
+                    CStmtKind::If {
+                        scrutinee,
+                        true_variant,
+                        false_variant: Some(fail),
+                    } => match (&ctx[true_variant].kind, &ctx[fail].kind) {
+                        (CStmtKind::Empty, &CStmtKind::Expr(fail)) if self.is_assert_fail(fail) => {
+                            Some(scrutinee)
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether an expression calls the function a failing `assert` calls
+    fn is_assert_fail(&self, expr_id: CExprId) -> bool {
+        let ctx = &self.ast_context;
+        match ctx[expr_id].kind {
+            CExprKind::Paren(_, expr) | CExprKind::ExplicitCast(_, expr, CastKind::ToVoid, _, _) => {
+                self.is_assert_fail(expr)
+            }
+            CExprKind::Binary(_, BinOp::Comma, lhs, _, _, _) => self.is_assert_fail(lhs),
+            CExprKind::Call(_, func, _) => match ctx[func].kind {
+                CExprKind::ImplicitCast(_, func, CastKind::FunctionToPointerDecay, _, _) => {
+                    match ctx[func].kind {
+                        CExprKind::DeclRef(_, decl_id, _) => match ctx[decl_id].kind {
+                            CDeclKind::Function { ref name, .. } => {
+                                ASSERT_FAIL_FNS.contains(&name.as_str())
+                            }
+                            _ => false,
+                        },
+                        _ => false,
+                    }
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Resolution of externally visible variables across the translation units of
+//! a crate.
+//!
+//! A file-scope declaration like `int counter;` without an initializer is a
+//! tentative definition, and C linkers merge the tentative definitions of a
+//! variable in different translation units (and a real definition, if there
+//! is one) into a single common symbol. Translating every one of them to a
+//! `#[no_mangle]` static defines the symbol several times, so when emitting a
+//! crate, the variable is instead defined once in the crate's `tentative_defs`
+//! module and each module re-exports it from there. If some module defines the
+//! variable with an initializer, `tentative_defs` declares it `extern` instead.
+//!
+//! Likewise, `extern int counter;` is re-exported from the crate's
+//! `extern_statics` module, which imports the variable from the module
+//! defining it if that was translated, and declares it in an `extern "C"`
+//! block if it lives in untranslated C.
+//!
+//! With `--export-list`, functions and variables that aren't exported are
+//! hidden: they are defined without their C symbol, so the declarations of
+//! other modules are resolved through `extern_statics` too. Only functions
+//! declared in the crate's own sources can be defined by one of its modules,
+//! so those declared elsewhere, like the C library's, are left alone.
+
+use syntax::source_map::dummy_spanned;
+
+use super::*;
+
+/// A variable only tentatively defined in a translation unit
+#[derive(Debug, Clone)]
+pub struct TentativeDef {
+    /// C name of the variable
+    pub symbol: String,
+    /// Name of the variable in the translated module
+    pub rust_name: String,
+    /// Source of the zero-initialized static
+    pub definition: String,
+    /// Source of an `extern` declaration of the static
+    pub declaration: String,
+}
+
+/// A variable defined with an initializer in a translation unit
+#[derive(Debug, Clone)]
+pub struct DefinedStatic {
+    /// C name of the variable
+    pub symbol: String,
+    /// Name of the static, if it is defined at the top level of the
+    /// translated module rather than in a submodule
+    pub rust_name: Option<String>,
+}
+
+/// A variable declared `extern` in a translation unit
+#[derive(Debug, Clone)]
+pub struct ExternStatic {
+    /// C name of the variable
+    pub symbol: String,
+    /// Name of the variable in the translated module
+    pub rust_name: String,
+    /// Source of the `extern` declaration of the static
+    pub declaration: String,
+}
+
+/// The externally visible variables a translation unit defines or declares
+#[derive(Debug, Clone, Default)]
+pub struct LinkedStatics {
+    /// Variables that only have tentative definitions
+    pub tentative: Vec<TentativeDef>,
+    /// Variables defined with an initializer
+    pub defined: Vec<DefinedStatic>,
+    /// Variables declared `extern`
+    pub externs: Vec<ExternStatic>,
+}
+
+impl LinkedStatics {
+    pub fn merge(&mut self, other: LinkedStatics) {
+        for def in other.tentative {
+            if !self.tentative.iter().any(|d| d.symbol == def.symbol) {
+                self.tentative.push(def);
+            }
+        }
+        for def in other.defined {
+            if !self.defined.iter().any(|d| d.symbol == def.symbol) {
+                self.defined.push(def);
+            }
+        }
+        for decl in other.externs {
+            if !self.externs.iter().any(|d| d.symbol == decl.symbol) {
+                self.externs.push(decl);
+            }
+        }
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// If `item` is the translation of a tentative definition, move it to the
+    /// `tentative_defs` module and return a re-export of it instead. Otherwise
+    /// return `item` itself, recording it if it defines an externally visible
+    /// variable or a hidden function.
+    pub fn share_tentative_def(&self, ctx: ExprContext, decl_id: CDeclId, item: P<Item>) -> P<Item> {
+        if !self.tcfg.resolves_linked_statics() || self.is_test_harness {
+            return item;
+        }
+        let (ident, typ) = match self.ast_context[decl_id].kind {
+            CDeclKind::Function { ref name, body: Some(_), .. } => {
+                if self.is_hidden_symbol(decl_id) {
+                    self.linked_statics.borrow_mut().defined.push(DefinedStatic {
+                        symbol: name.clone(),
+                        rust_name: Some(item.ident.to_string()),
+                    });
+                }
+                return item;
+            }
+            CDeclKind::Variable {
+                has_static_duration: true,
+                has_thread_duration: false,
+                is_externally_visible: true,
+                is_defn: true,
+                ref ident,
+                initializer,
+                typ,
+                ref attrs,
+            } => {
+                if initializer.is_some() {
+                    let rust_name = if self.cur_file.borrow().is_none() {
+                        Some(item.ident.to_string())
+                    } else {
+                        None
+                    };
+                    self.linked_statics.borrow_mut().defined.push(DefinedStatic {
+                        symbol: ident.clone(),
+                        rust_name,
+                    });
+                    return item;
+                }
+                // Sections and weak linkage are specific to one definition
+                if !attrs.is_empty() || !self.is_module_independent_type(typ.ctype) {
+                    return item;
+                }
+                (ident, typ)
+            }
+            _ => return item,
+        };
+        let ty = match self.convert_variable(ctx.static_(), None, typ) {
+            Ok((ty, _, _)) => ty,
+            Err(_) => return item,
+        };
+
+        let rust_name = item.ident.to_string();
+        let declaration = mk()
+            .extern_("C")
+            .foreign_items(vec![mk_linkage(true, &rust_name, ident)
+                .pub_()
+                .mutbl()
+                .static_foreign_item(&rust_name, ty)]);
+        self.linked_statics.borrow_mut().tentative.push(TentativeDef {
+            symbol: ident.clone(),
+            rust_name: rust_name.clone(),
+            definition: pprust::item_to_string(&item),
+            declaration: pprust::item_to_string(&declaration),
+        });
+
+        let path = vec!["crate".to_string(), "tentative_defs".to_string(), rust_name];
+        mk().pub_().use_simple_item(path, None::<String>)
+    }
+
+    /// If `item` is the declaration of an `extern` variable, or of a function
+    /// of the crate that may be hidden, move it to the `extern_statics` module
+    /// and return a re-export of it instead
+    pub fn share_extern_static(&self, decl_id: CDeclId, item: &ForeignItem) -> Option<P<Item>> {
+        if !self.tcfg.resolves_linked_statics() || self.is_test_harness {
+            return None;
+        }
+        let ident = match self.ast_context[decl_id].kind {
+            CDeclKind::Function { ref name, typ, .. }
+                if !self.is_exported(name)
+                    && self.is_module_independent_fn_type(typ)
+                    && self.is_in_source_root(decl_id) =>
+            {
+                name
+            }
+            CDeclKind::Variable {
+                has_static_duration: true,
+                has_thread_duration: false,
+                is_externally_visible: true,
+                is_defn: false,
+                ref ident,
+                typ,
+                ref attrs,
+                ..
+            } if attrs.is_empty() && self.is_module_independent_type(typ.ctype) => ident,
+            _ => return None,
+        };
+
+        let rust_name = item.ident.to_string();
+        let mut declaration = item.clone();
+        declaration.vis = dummy_spanned(VisibilityKind::Public);
+        let declaration = mk().extern_("C").foreign_items(vec![declaration]);
+        self.linked_statics.borrow_mut().externs.push(ExternStatic {
+            symbol: ident.clone(),
+            rust_name: rust_name.clone(),
+            declaration: pprust::item_to_string(&declaration),
+        });
+
+        let path = vec!["crate".to_string(), "extern_statics".to_string(), rust_name];
+        Some(mk().pub_().use_simple_item(path, None::<String>))
+    }
+
+    /// Whether a function or variable with external linkage is defined without
+    /// its C symbol, since the export list doesn't list it. Only definitions
+    /// other modules can import instead are hidden: those at the top level of
+    /// the module, whose types are the same in every module. Tentative
+    /// definitions keep their symbol, since another module may define the
+    /// variable.
+    pub fn is_hidden_symbol(&self, decl_id: CDeclId) -> bool {
+        if !self.tcfg.resolves_linked_statics()
+            || self.is_test_harness
+            || self.tcfg.export_list.is_none()
+            || self.cur_file.borrow().is_some()
+        {
+            return false;
+        }
+        match self.ast_context[decl_id].kind {
+            CDeclKind::Function {
+                is_global: true,
+                ref name,
+                typ,
+                ..
+            } => !self.is_exported(name) && self.is_module_independent_fn_type(typ),
+            CDeclKind::Variable {
+                has_static_duration: true,
+                has_thread_duration: false,
+                is_externally_visible: true,
+                is_defn: true,
+                ref ident,
+                initializer: Some(_),
+                typ,
+                ref attrs,
+            } => {
+                attrs.is_empty()
+                    && !self.is_exported(ident)
+                    && self.is_module_independent_type(typ.ctype)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a declaration is in a file under the common ancestor directory
+    /// of the crate's C sources, rather than in a system header
+    fn is_in_source_root(&self, decl_id: CDeclId) -> bool {
+        self.ast_context
+            .get_source_path(&self.ast_context[decl_id])
+            .map_or(false, |path| path.starts_with(&self.source_root))
+    }
+
+    pub fn is_exported(&self, c_name: &str) -> bool {
+        self.tcfg
+            .export_list
+            .as_ref()
+            .map_or(true, |export_list| export_list.exports(self.c_function_name(c_name)))
+    }
+
+    fn is_module_independent_fn_type(&self, typ: CTypeId) -> bool {
+        match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Function(ret, ref params, ..) => {
+                self.is_module_independent_type(ret.ctype)
+                    && params.iter().all(|param| self.is_module_independent_type(param.ctype))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a type translates to the same Rust type in every module, which
+    /// isn't the case for struct, union, enum and typedef types since every
+    /// module defines its own
+    fn is_module_independent_type(&self, ctype: CTypeId) -> bool {
+        match self.ast_context[ctype].kind {
+            CTypeKind::Pointer(qty) | CTypeKind::Attributed(qty, _) => {
+                self.is_module_independent_type(qty.ctype)
+            }
+            CTypeKind::ConstantArray(ty, _)
+            | CTypeKind::IncompleteArray(ty)
+            | CTypeKind::Elaborated(ty)
+            | CTypeKind::Paren(ty)
+            | CTypeKind::TypeOf(ty)
+            | CTypeKind::Auto(ty)
+            | CTypeKind::Decayed(ty) => self.is_module_independent_type(ty),
+            CTypeKind::Void | CTypeKind::Float | CTypeKind::Double => true,
+            ref kind => kind.is_integral_type(),
+        }
+    }
+}
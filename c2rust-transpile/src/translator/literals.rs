@@ -8,6 +8,11 @@ use std::iter;
 impl<'c> Translation<'c> {
     /// Generate an integer literal corresponding to the given type, value, and base.
     pub fn mk_int_lit(&self, ty: CQualTypeId, val: u64, base: IntBase) -> Result<P<Expr>, TranslationError> {
+        // C++ `true` and `false` are exported as integer literals of type `bool`
+        if self.ast_context.resolve_type(ty.ctype).kind == CTypeKind::Bool {
+            return Ok(mk().lit_expr(mk().bool_lit(val != 0)));
+        }
+
         let lit = match base {
             IntBase::Dec => mk().int_lit(val.into(), LitIntType::Unsuffixed),
             IntBase::Hex => mk().float_unsuffixed_lit(format!("0x{:x}", val)),
@@ -55,11 +60,15 @@ impl<'c> Translation<'c> {
         let underlying_type_id =
             underlying_type_id.expect("Attempt to construct value of forward declared enum");
         let value = match self.ast_context.resolve_type(underlying_type_id.ctype).kind {
-            CTypeKind::UInt => {
-                mk().lit_expr(mk().int_lit((value as u32) as u128, LitIntType::Unsuffixed))
-            }
-            CTypeKind::ULong => {
-                mk().lit_expr(mk().int_lit((value as u64) as u128, LitIntType::Unsuffixed))
+            ref kind @ CTypeKind::UInt | ref kind @ CTypeKind::ULong => {
+                // Truncate to the width of the type on the translation target
+                let width = self.ast_context.integral_type_width(kind).unwrap_or(64);
+                let value = if width >= 64 {
+                    value as u64
+                } else {
+                    value as u64 & ((1 << width) - 1)
+                };
+                mk().lit_expr(mk().int_lit(value as u128, LitIntType::Unsuffixed))
             }
             _ => signed_int_expr(value),
         };
@@ -100,6 +109,12 @@ impl<'c> Translation<'c> {
                         }
                     }
                 };
+                // Plain character literals are `int`s, while wide and Unicode ones such as
+                // `u'x'` have the type of the corresponding character type
+                let expr = match self.ast_context.resolve_type(ty.ctype).kind {
+                    CTypeKind::Int => expr,
+                    _ => mk().cast_expr(expr, self.convert_type(ty.ctype)?),
+                };
                 Ok(WithStmts::new_val(expr))
             }
 
@@ -122,6 +137,7 @@ impl<'c> Translation<'c> {
                     }
                     CTypeKind::Double => mk().lit_expr(mk().float_lit(str, FloatTy::F64)),
                     CTypeKind::Float => mk().lit_expr(mk().float_lit(str, FloatTy::F32)),
+                    CTypeKind::Half => self.f64_to_half(mk().lit_expr(mk().float_lit(str, FloatTy::F64))),
                     ref k => panic!("Unsupported floating point literal type {:?}", k),
                 };
                 Ok(WithStmts::new_val(val))
@@ -150,7 +166,32 @@ impl<'c> Translation<'c> {
                         }
                     }
                 };
-                if ctx.is_static {
+                if ctx.is_static && width > 1 {
+                    // Wide strings are arrays of code units in the byte order of the host clang
+                    // ran on
+                    let signed = match self.ast_context.resolve_type(ty.ctype).kind {
+                        CTypeKind::ConstantArray(elem_ty, _) => self
+                            .ast_context
+                            .is_signed_integral(&self.ast_context.resolve_type(elem_ty).kind),
+                        _ => false,
+                    };
+                    let vals = val
+                        .chunks(width as usize)
+                        .map(|unit| {
+                            let unit = match *unit {
+                                [a, b] => u32::from(u16::from_ne_bytes([a, b])),
+                                [a, b, c, d] => u32::from_ne_bytes([a, b, c, d]),
+                                _ => return Err(format_err!("Unsupported string character width {}", width)),
+                            };
+                            Ok(if signed && (unit as i32) < 0 {
+                                signed_int_expr(i64::from(unit as i32))
+                            } else {
+                                mk().lit_expr(mk().int_lit(u128::from(unit), LitIntType::Unsuffixed))
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(WithStmts::new_val(mk().array_expr(vals)))
+                } else if ctx.is_static {
                     let mut vals: Vec<P<Expr>> = vec![];
                     for c in val {
                         // Emit negative literals if the expected type is not unsigned char. This
@@ -206,9 +247,15 @@ impl<'c> Translation<'c> {
                 // Need to check to see if the next item is a string literal,
                 // if it is need to treat it as a declaration, rather than
                 // an init list. https://github.com/GaloisInc/C2Rust/issues/40
+                // In an array of character arrays, like `char names[1][8] = {"abc"}`,
+                // the string initializes an element instead.
                 let mut is_string = false;
+                let elts_are_arrays = match self.ast_context.resolve_type(ty).kind {
+                    CTypeKind::ConstantArray(..) | CTypeKind::IncompleteArray(..) => true,
+                    _ => false,
+                };
 
-                if ids.len() == 1 {
+                if ids.len() == 1 && !elts_are_arrays {
                     let v = ids.first().unwrap();
                     if let CExprKind::Literal(_, CLiteral::String { .. }) =
                         self.ast_context.index(*v).kind
@@ -221,10 +268,10 @@ impl<'c> Translation<'c> {
                     let v = ids.first().unwrap();
                     self.convert_expr(ctx.used(), *v)
                 } else {
-                    Ok(ids
+                    let elements = ids
                         .iter()
                         .map(|id| {
-                            self.convert_expr(ctx.used(), *id)?
+                            let element = self.convert_expr(ctx.used(), *id)?
                                 .result_map(|x| {
                                     // Array literals require all of their elements to be
                                     // the correct type; they will not use implicit casts to
@@ -242,18 +289,19 @@ impl<'c> Translation<'c> {
                                     } else {
                                         Ok(x)
                                     }
-                                })
+                                })?;
+                            Ok((Some(*id), element))
                         })
                         .chain(
                             // Pad out the array literal with default values to the desired size
                             iter::repeat(
                                 self.implicit_default_expr(ty, ctx.is_static)
-                            ).take(n - ids.len())
+                            ).take(n - ids.len()).map(|init| Ok((None, init?)))
                         )
-                        .collect::<Result<WithStmts<Vec<P<Expr>>>, TranslationError>>()?
-                        .map(|vals| {
-                            mk().array_expr(vals)
-                        }))
+                        .collect::<Result<Vec<_>, TranslationError>>()?;
+                    Ok(self.sequence_operands(elements).map(|vals| {
+                        mk().array_expr(vals)
+                    }))
                 }
             }
             CTypeKind::Struct(struct_id) => {
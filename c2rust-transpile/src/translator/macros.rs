@@ -0,0 +1,537 @@
+//! This module provides recovery of C function-like macros. Clang expands
+//! macros away, so we reconstruct them from their expansions: macros whose
+//! expansions translate identically at every use are emitted once, as a
+//! `const` or an `#[inline]` function, and their uses are replaced.
+
+use super::*;
+use crate::c_ast::iterators::{immediate_children, DFNodes};
+
+/// Translation of a recovered function-like macro
+#[derive(Clone, Debug)]
+pub enum MacroFunctionExpansion {
+    /// Macro without parameters expanding to a constant expression
+    Const,
+    /// Macro translated to an `#[inline]` function
+    Function { is_unsafe: bool },
+}
+
+/// A single expansion of a function-like macro
+struct MacroInvocation {
+    /// Parameter index for each expression that was substituted for a
+    /// macro parameter
+    substitutions: HashMap<CExprId, usize>,
+    /// First argument expression substituted for each macro parameter
+    args: Vec<CExprId>,
+}
+
+/// Split the text of a macro invocation, e.g. `MAX(a, f(b, c))`, into its
+/// arguments. Like the preprocessor, only parentheses protect commas.
+fn split_macro_arguments(text: &str) -> Option<Vec<String>> {
+    let start = text.find('(')?;
+    let inner = text[start + 1..].trim_end();
+    if !inner.ends_with(')') {
+        return None;
+    }
+    let inner = &inner[..inner.len() - 1];
+
+    let mut args = vec![];
+    let mut cur = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in inner.chars() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    args.push(mem::replace(&mut cur, String::new()));
+                    continue;
+                }
+                _ => {}
+            },
+        }
+        cur.push(c);
+    }
+    if !args.is_empty() || !cur.trim().is_empty() {
+        args.push(cur);
+    }
+    Some(args)
+}
+
+fn strip_whitespace(s: &str) -> String {
+    s.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+impl<'c> Translation<'c> {
+    pub fn convert_macro_function(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+        span: Span,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        let (params, is_variadic) = match self.ast_context[decl_id].kind {
+            CDeclKind::MacroFunction { ref params, is_variadic, .. } => (params, is_variadic),
+            _ => panic!("Expected a function-like macro"),
+        };
+        let name = self
+            .renamer
+            .borrow_mut()
+            .get(&decl_id)
+            .expect("Macro function not named");
+
+        trace!("Recovering macro {:?}: {:?}", decl_id, self.ast_context[decl_id]);
+
+        match self.recover_macro_function(ctx, decl_id, &name, params, is_variadic, span) {
+            Ok((item, expansion)) => {
+                self.macro_function_expansions
+                    .borrow_mut()
+                    .insert(decl_id, Some(expansion));
+                Ok(ConvertedDecl::Item(item))
+            }
+            Err(e) => {
+                self.macro_function_expansions.borrow_mut().insert(decl_id, None);
+                info!("Could not recover macro {}: {}", name, e);
+                Ok(ConvertedDecl::NoItem)
+            }
+        }
+    }
+
+    fn recover_macro_function(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+        name: &str,
+        params: &[String],
+        is_variadic: bool,
+        span: Span,
+    ) -> Result<(P<Item>, MacroFunctionExpansion), TranslationError> {
+        if is_variadic {
+            return Err(TranslationError::generic("Variadic macros are not supported"));
+        }
+        let invocations = self
+            .ast_context
+            .macro_expansions
+            .get(&decl_id)
+            .ok_or_else(|| TranslationError::generic("Macro is never expanded"))?;
+        let ctx = ctx.set_expanding_macro(decl_id);
+
+        // A macro without parameters expanding to the same side-effect free
+        // expression everywhere is really a constant
+        if params.is_empty() && invocations.iter().all(|e| self.ast_context.is_expr_pure(*e)) {
+            if let Ok((replacement, ty)) =
+                self.canonical_macro_replacement(ctx.set_const(true), invocations)
+            {
+                let ty = self.convert_type(ty)?;
+                let item = mk().span(span).pub_().const_item(name, ty, replacement);
+                return Ok((item, MacroFunctionExpansion::Const));
+            }
+        }
+
+        self.renamer.borrow_mut().add_scope();
+        let param_names: Vec<String> = params
+            .iter()
+            .map(|param| self.renamer.borrow_mut().pick_name(param))
+            .collect();
+        let body = self.canonical_macro_body(
+            ctx.used().set_const(false).not_static(),
+            params,
+            &param_names,
+            invocations,
+        );
+        self.renamer.borrow_mut().drop_scope();
+        let (body, arg_tys, ret_ty) = body?;
+
+        let args = param_names
+            .iter()
+            .zip(&arg_tys)
+            .map(|(param, ty)| Ok(mk().arg(self.convert_type(*ty)?, mk().ident_pat(param))))
+            .collect::<Result<Vec<_>, TranslationError>>()?;
+        let ret = match self.ast_context.resolve_type(ret_ty).kind {
+            CTypeKind::Void => FunctionRetTy::Default(DUMMY_SP),
+            _ => FunctionRetTy::Ty(self.convert_type(ret_ty)?),
+        };
+        let decl = mk().fn_decl(args, ret);
+
+        let is_unsafe = body.is_unsafe();
+        let mut mk_ = mk().span(span).pub_().single_attr("inline");
+        if is_unsafe {
+            mk_ = mk_.unsafe_();
+        }
+        let item = mk_.fn_item(name, decl, body.to_block());
+        Ok((item, MacroFunctionExpansion::Function { is_unsafe }))
+    }
+
+    /// Translate every expansion of a macro with its arguments replaced by
+    /// the macro parameters and check that they all agree. Returns the body
+    /// along with the parameter and result types.
+    fn canonical_macro_body(
+        &self,
+        ctx: ExprContext,
+        params: &[String],
+        param_names: &[String],
+        invocations: &[CExprId],
+    ) -> Result<(WithStmts<P<Expr>>, Vec<CTypeId>, CTypeId), TranslationError> {
+        let expr_type = |expr_id: CExprId| {
+            self.ast_context[expr_id]
+                .kind
+                .get_type()
+                .ok_or_else(|| format_err!("Invalid expression type"))
+        };
+
+        let mut canonical: Option<(String, WithStmts<P<Expr>>, Vec<CTypeId>, CTypeId)> = None;
+        for &expr_id in invocations {
+            let invocation = self.resolve_macro_invocation(params, expr_id)?;
+            let arg_tys = invocation
+                .args
+                .iter()
+                .map(|arg| expr_type(*arg))
+                .collect::<Result<Vec<_>, _>>()?;
+            let ret_ty = expr_type(expr_id)?;
+
+            // Recovering a nested macro replaces the substitutions, so
+            // restore the outer ones afterwards
+            let substitutions = invocation
+                .substitutions
+                .iter()
+                .map(|(expr_id, idx)| (*expr_id, param_names[*idx].clone()))
+                .collect();
+            let outer = mem::replace(&mut *self.macro_arg_substitutions.borrow_mut(), substitutions);
+            let body = self.convert_expr(ctx, expr_id);
+            *self.macro_arg_substitutions.borrow_mut() = outer;
+            let body = body?;
+
+            let mut rendered = pprust::expr_to_string(&body.clone().to_expr());
+            if body.is_unsafe() {
+                rendered.insert_str(0, "unsafe ");
+            }
+
+            if let Some((ref canon_rendered, _, ref canon_arg_tys, canon_ret_ty)) = canonical {
+                let same_types = |a: CTypeId, b: CTypeId| {
+                    self.ast_context.resolve_type_id(a) == self.ast_context.resolve_type_id(b)
+                };
+                let equivalent = *canon_rendered == rendered
+                    && same_types(canon_ret_ty, ret_ty)
+                    && canon_arg_tys
+                        .iter()
+                        .zip(&arg_tys)
+                        .all(|(a, b)| same_types(*a, *b));
+                if !equivalent {
+                    return Err(TranslationError::generic(
+                        "Not all macro expansions are equivalent",
+                    ));
+                }
+            } else {
+                canonical = Some((rendered, body, arg_tys, ret_ty));
+            }
+        }
+
+        canonical
+            .map(|(_, body, arg_tys, ret_ty)| (body, arg_tys, ret_ty))
+            .ok_or_else(|| TranslationError::generic("Macro is never expanded"))
+    }
+
+    /// Find the expressions in a macro expansion that were substituted for
+    /// the macro parameters. Fails if the expansion can't be expressed as a
+    /// function of its arguments.
+    fn resolve_macro_invocation(
+        &self,
+        params: &[String],
+        invocation: CExprId,
+    ) -> Result<MacroInvocation, TranslationError> {
+        let text = self
+            .ast_context
+            .macro_expansion_text
+            .get(&invocation)
+            .ok_or_else(|| format_err!("Missing macro invocation text"))?;
+        let spellings = split_macro_arguments(text)
+            .filter(|args| args.len() == params.len())
+            .ok_or_else(|| format_err!("Could not split macro arguments of {}", text))?;
+        let spellings: Vec<String> = spellings.iter().map(|s| strip_whitespace(s)).collect();
+
+        let mut substitutions = HashMap::new();
+        let mut args: Vec<Option<CExprId>> = vec![None; params.len()];
+        // Variables declared by statement expressions in the expansion, which
+        // become locals of the translated macro
+        let mut locals = HashSet::new();
+        // Whether each parameter is used where C always evaluates it
+        let mut evaluated = vec![false; params.len()];
+        // Nodes are paired with whether C only evaluates them conditionally
+        let mut stack = vec![(SomeId::Expr(invocation), false)];
+        while let Some((id, conditional)) = stack.pop() {
+            if let SomeId::Expr(expr_id) = id {
+                let kind = &self.ast_context[expr_id].kind;
+                let param = self
+                    .ast_context
+                    .macro_arguments
+                    .get(&expr_id)
+                    .and_then(|(param, spelling)| {
+                        let idx = params.iter().position(|p| p == param)?;
+                        if strip_whitespace(spelling) == spellings[idx] {
+                            Some(idx)
+                        } else {
+                            None
+                        }
+                    });
+                if let Some(idx) = param {
+                    if kind.lrvalue() == LRValue::LValue {
+                        return Err(format_err!(
+                            "Macro parameter {} is used as an lvalue", params[idx]
+                        ).into());
+                    }
+                    match args[idx] {
+                        // C evaluates the argument at every use of the
+                        // parameter, so it must not have side effects.
+                        Some(first) => {
+                            if !self.ast_context.is_expr_pure(first) {
+                                return Err(format_err!(
+                                    "Macro argument for {} with side effects is expanded more than once",
+                                    params[idx]
+                                ).into());
+                            }
+                        }
+                        None => args[idx] = Some(expr_id),
+                    }
+                    evaluated[idx] |= !conditional;
+                    substitutions.insert(expr_id, idx);
+                    continue;
+                }
+
+                match *kind {
+                    CExprKind::Statements(_, compound_stmt_id) => {
+                        if self.stmt_expr_escapes(compound_stmt_id, false, false) {
+                            return Err(TranslationError::generic(
+                                "Statement expression in macro transfers control out of it",
+                            ));
+                        }
+                        locals.extend(self.stmt_expr_locals(compound_stmt_id)?);
+                    }
+                    CExprKind::DeclRef(_, decl_id, _) if locals.contains(&decl_id) => {}
+                    CExprKind::DeclRef(_, decl_id, _) => {
+                        if let CDeclKind::Variable {
+                            has_static_duration: false,
+                            has_thread_duration: false,
+                            ref ident,
+                            ..
+                        } = self.ast_context[decl_id].kind
+                        {
+                            return Err(format_err!(
+                                "Macro expansion refers to local variable {}", ident
+                            ).into());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let conditional_children = self.conditionally_evaluated_children(id);
+            stack.extend(
+                immediate_children(&self.ast_context, id)
+                    .into_iter()
+                    .map(|child| (child, conditional || conditional_children.contains(&child))),
+            );
+        }
+
+        let args = args
+            .into_iter()
+            .zip(params)
+            .map(|(arg, param)| {
+                arg.ok_or_else(|| format_err!("Macro parameter {} is never expanded", param))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // A function evaluates its arguments up front, whereas C may never
+        // evaluate an argument only used in `?:`, `&&` or `||` operands, as
+        // in `AND(p, p->x)`. Such an argument must be safe to evaluate early.
+        for ((&arg, param), evaluated) in args.iter().zip(params).zip(evaluated) {
+            if !evaluated && !self.is_expr_eager_safe(arg) {
+                return Err(format_err!(
+                    "Macro argument for {} is conditionally evaluated", param
+                ).into());
+            }
+        }
+
+        Ok(MacroInvocation { substitutions, args })
+    }
+
+    /// The children of a node that C evaluates only under some condition
+    fn conditionally_evaluated_children(&self, id: SomeId) -> Vec<SomeId> {
+        match id {
+            SomeId::Expr(expr_id) => match self.ast_context[expr_id].kind {
+                CExprKind::Conditional(_, _, lhs, rhs) => {
+                    vec![SomeId::Expr(lhs), SomeId::Expr(rhs)]
+                }
+                CExprKind::BinaryConditional(_, _, rhs)
+                | CExprKind::Binary(_, c_ast::BinOp::And, _, rhs, _, _)
+                | CExprKind::Binary(_, c_ast::BinOp::Or, _, rhs, _, _) => vec![SomeId::Expr(rhs)],
+                _ => vec![],
+            },
+            SomeId::Stmt(stmt_id) => match self.ast_context[stmt_id].kind {
+                CStmtKind::If { true_variant, false_variant, .. } => {
+                    let mut children = vec![SomeId::Stmt(true_variant)];
+                    children.extend(false_variant.map(SomeId::Stmt));
+                    children
+                }
+                CStmtKind::Switch { body, .. } | CStmtKind::While { body, .. } => {
+                    vec![SomeId::Stmt(body)]
+                }
+                CStmtKind::ForLoop { condition, increment, body, .. } => {
+                    let mut children = vec![SomeId::Stmt(body)];
+                    children.extend(condition.map(SomeId::Expr));
+                    children.extend(increment.map(SomeId::Expr));
+                    children
+                }
+                _ => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
+    /// Whether evaluating an expression before C would have can neither
+    /// have side effects nor trap, i.e. it doesn't call, dereference or divide.
+    fn is_expr_eager_safe(&self, expr_id: CExprId) -> bool {
+        self.ast_context.is_expr_pure(expr_id)
+            && DFNodes::new(&self.ast_context, SomeId::Expr(expr_id)).all(|node| match node {
+                SomeId::Expr(e) => match self.ast_context[e].kind {
+                    CExprKind::Call(..)
+                    | CExprKind::ArraySubscript(..)
+                    | CExprKind::Unary(_, c_ast::UnOp::Deref, _, _)
+                    | CExprKind::Member(_, _, _, MemberKind::Arrow, _)
+                    | CExprKind::Binary(_, c_ast::BinOp::Divide, _, _, _, _)
+                    | CExprKind::Binary(_, c_ast::BinOp::Modulus, _, _, _, _)
+                    | CExprKind::Statements(..) => false,
+                    _ => true,
+                },
+                _ => true,
+            })
+    }
+
+    /// The variables a statement expression in a macro declares. Statics are
+    /// rejected, since every expansion of the macro has its own.
+    fn stmt_expr_locals(&self, compound_stmt_id: CStmtId) -> Result<Vec<CDeclId>, TranslationError> {
+        let mut locals = vec![];
+        for node in DFNodes::new(&self.ast_context, SomeId::Stmt(compound_stmt_id)) {
+            if let SomeId::Stmt(stmt_id) = node {
+                if let CStmtKind::Decls(ref decls) = self.ast_context[stmt_id].kind {
+                    for &decl_id in decls {
+                        match self.ast_context[decl_id].kind {
+                            CDeclKind::Variable { has_static_duration: true, ref ident, .. } => {
+                                return Err(format_err!(
+                                    "Statement expression in macro declares static variable {}",
+                                    ident
+                                )
+                                .into())
+                            }
+                            _ => locals.push(decl_id),
+                        }
+                    }
+                }
+            }
+        }
+        Ok(locals)
+    }
+
+    /// Whether control can leave a statement expression other than by
+    /// evaluating to its value, e.g. through a `return` or `goto`. Such macros
+    /// can't be translated to functions.
+    fn stmt_expr_escapes(&self, stmt_id: CStmtId, in_loop: bool, in_switch: bool) -> bool {
+        let escapes = |stmt_id: CStmtId| self.stmt_expr_escapes(stmt_id, in_loop, in_switch);
+        let escapes_loop = |stmt_id: CStmtId| self.stmt_expr_escapes(stmt_id, true, in_switch);
+        match self.ast_context[stmt_id].kind {
+            CStmtKind::Return(_) | CStmtKind::Goto(_) | CStmtKind::Label(_) => true,
+            CStmtKind::Break => !in_loop && !in_switch,
+            CStmtKind::Continue => !in_loop,
+            CStmtKind::Case(_, body, _) | CStmtKind::Default(body) => escapes(body),
+            CStmtKind::Compound(ref stmts) => stmts.iter().any(|&stmt| escapes(stmt)),
+            CStmtKind::If { true_variant, false_variant, .. } => {
+                escapes(true_variant) || false_variant.map_or(false, escapes)
+            }
+            CStmtKind::Switch { body, .. } => self.stmt_expr_escapes(body, in_loop, true),
+            CStmtKind::While { body, .. } | CStmtKind::DoWhile { body, .. } => escapes_loop(body),
+            CStmtKind::ForLoop { init, body, .. } => {
+                init.map_or(false, escapes) || escapes_loop(body)
+            }
+            CStmtKind::Expr(_)
+            | CStmtKind::Empty
+            | CStmtKind::Decls(_)
+            | CStmtKind::Asm { .. } => false,
+        }
+    }
+
+    /// Translate an expansion of a recovered function-like macro as a use of
+    /// its Rust translation.
+    pub fn convert_macro_function_invocation(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        let macro_id = match self.ast_context.macro_invocations.get(&expr_id) {
+            // Find the first macro after the macro we're currently
+            // expanding, if any.
+            Some(macs) => match macs
+                .splitn(2, |macro_id| ctx.expanding_macro(macro_id))
+                .last()
+                .unwrap()
+                .first()
+            {
+                Some(macro_id) => *macro_id,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        let params = match self.ast_context[macro_id].kind {
+            CDeclKind::MacroFunction { ref params, .. } => params,
+            _ => return Ok(None),
+        };
+
+        let expansion = self.macro_function_expansions.borrow().get(&macro_id).cloned();
+        let expansion = match expansion {
+            Some(Some(expansion)) => expansion,
+            Some(None) => return Ok(None),
+            // We haven't tried to recover it yet
+            None => {
+                self.convert_decl(ctx, macro_id)?;
+                match self.macro_function_expansions.borrow().get(&macro_id) {
+                    Some(Some(expansion)) => expansion.clone(),
+                    _ => return Ok(None),
+                }
+            }
+        };
+
+        let rustname = self
+            .renamer
+            .borrow_mut()
+            .get(&macro_id)
+            .ok_or_else(|| format_err!("Macro name not declared"))?;
+
+        let val = match expansion {
+            MacroFunctionExpansion::Const => WithStmts::new_val(mk().path_expr(vec![rustname.clone()])),
+            MacroFunctionExpansion::Function { is_unsafe } => {
+                // Initializers of consts and statics can't call functions
+                if ctx.is_const || ctx.is_static {
+                    return Ok(None);
+                }
+
+                let invocation = self.resolve_macro_invocation(params, expr_id)?;
+                let mut call = self
+                    .convert_exprs(ctx.used(), &invocation.args)?
+                    .map(|args| mk().call_expr(mk().path_expr(vec![rustname.clone()]), args));
+                if is_unsafe {
+                    call.set_unsafe();
+                }
+                call
+            }
+        };
+
+        if let Some(cur_file) = self.cur_file.borrow().as_ref() {
+            self.add_import(*cur_file, macro_id, &rustname);
+        }
+
+        self.convert_side_effects_expr(ctx, val, "Macro invocation is not supposed to be used")
+            .map(Some)
+    }
+}
@@ -2,9 +2,46 @@
 //! Translating main requires us to wrap the C implementation to
 //! a helper that can be called from a generated main function in
 //! Rust.
+//!
+//! The generated `fn main()` passes the raw bytes of the arguments and
+//! environment to the C main on Unix, so they need not be valid UTF-8.
+//! Other targets have no raw bytes to pass, so they get the arguments and
+//! environment as UTF-8, with invalid sequences replaced.
 
 use super::*;
-use syntax::token::{self, TokenKind};
+
+/// Shadow the `OsString` `name` by its bytes:
+///
+/// ```ignore
+/// #[cfg(unix)]
+/// let name = ::std::os::unix::ffi::OsStringExt::into_vec(name);
+/// #[cfg(not(unix))]
+/// let name = name.to_string_lossy().into_owned().into_bytes();
+/// ```
+fn os_string_into_vec(name: &str) -> Vec<Stmt> {
+    let no_args: Vec<P<Expr>> = vec![];
+    let os_string = mk().ident_expr(name);
+    let into_vec = mk().path_expr(vec!["", "std", "os", "unix", "ffi", "OsStringExt", "into_vec"]);
+    let raw_bytes = mk().call_expr(into_vec, vec![os_string.clone()]);
+    let lossy = mk().method_call_expr(os_string, "to_string_lossy", no_args.clone());
+    let lossy_bytes = mk().method_call_expr(
+        mk().method_call_expr(lossy, "into_owned", no_args.clone()),
+        "into_bytes",
+        no_args,
+    );
+    vec![
+        mk().local_stmt(P(mk().call_attr("cfg", vec!["unix"]).local(
+            mk().ident_pat(name),
+            None as Option<P<Ty>>,
+            Some(raw_bytes),
+        ))),
+        mk().local_stmt(P(mk().call_attr("cfg", vec!["not(unix)"]).local(
+            mk().ident_pat(name),
+            None as Option<P<Ty>>,
+            Some(lossy_bytes),
+        ))),
+    ]
+}
 
 impl<'c> Translation<'c> {
     pub fn convert_main(&self, main_id: CDeclId) -> Result<P<Item>, TranslationError> {
@@ -15,7 +52,7 @@ impl<'c> Translation<'c> {
         } = self.ast_context.index(main_id).kind
         {
             let ret: CTypeKind = match self.ast_context.resolve_type(typ).kind {
-                CTypeKind::Function(ret, _, _, _, _) => {
+                CTypeKind::Function(ret, ..) => {
                     self.ast_context.resolve_type(ret.ctype).kind.clone()
                 }
                 ref k => Err(format_err!(
@@ -35,8 +72,8 @@ impl<'c> Translation<'c> {
             let main_fn = mk().path_expr(vec![main_fn_name]);
 
             let exit_fn = mk().path_expr(vec!["", "std", "process", "exit"]);
-            let args_fn = mk().path_expr(vec!["", "std", "env", "args"]);
-            let vars_fn = mk().path_expr(vec!["", "std", "env", "vars"]);
+            let args_fn = mk().path_expr(vec!["", "std", "env", "args_os"]);
+            let vars_fn = mk().path_expr(vec!["", "std", "env", "vars_os"]);
 
             let no_args: Vec<P<Expr>> = vec![];
 
@@ -53,34 +90,36 @@ impl<'c> Translation<'c> {
                     Some(mk().path_ty(vec![mk().path_segment_with_args(
                         "Vec",
                         mk().angle_bracketed_args(vec![
-                            mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_char"])),
+                            mk().mutbl().ptr_ty(self.ffi_ty("c_char")),
                         ]),
                     )])),
                     Some(
                         mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![] as Vec<P<Expr>>),
                     ),
                 ))));
+                let mut arg_stmts = os_string_into_vec("arg");
+                arg_stmts.push(mk().semi_stmt(mk().method_call_expr(
+                    mk().path_expr(vec!["args"]),
+                    "push",
+                    vec![mk().method_call_expr(
+                        mk().method_call_expr(
+                            mk().call_expr(
+                                mk().path_expr(vec!["", "std", "ffi", "CString", "new"]),
+                                vec![mk().path_expr(vec!["arg"])],
+                            ),
+                            "expect",
+                            vec![mk().lit_expr(
+                                "Failed to convert argument into CString.",
+                            )],
+                        ),
+                        "into_raw",
+                        vec![] as Vec<P<Expr>>,
+                    )],
+                )));
                 stmts.push(mk().semi_stmt(mk().for_expr(
                     mk().ident_pat("arg"),
                     mk().call_expr(args_fn, vec![] as Vec<P<Expr>>),
-                    mk().block(vec![mk().semi_stmt(mk().method_call_expr(
-                        mk().path_expr(vec!["args"]),
-                        "push",
-                        vec![mk().method_call_expr(
-                            mk().method_call_expr(
-                                mk().call_expr(
-                                    mk().path_expr(vec!["", "std", "ffi", "CString", "new"]),
-                                    vec![mk().path_expr(vec!["arg"])],
-                                ),
-                                "expect",
-                                vec![mk().lit_expr(
-                                    "Failed to convert argument into CString.",
-                                )],
-                            ),
-                            "into_raw",
-                            vec![] as Vec<P<Expr>>,
-                        )],
-                    ))]),
+                    mk().block(arg_stmts),
                     None as Option<Ident>,
                 )));
                 stmts.push(mk().semi_stmt(mk().method_call_expr(
@@ -125,57 +164,57 @@ impl<'c> Translation<'c> {
                     Some(mk().path_ty(vec![mk().path_segment_with_args(
                         "Vec",
                         mk().angle_bracketed_args(vec![
-                            mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_char"])),
+                            mk().mutbl().ptr_ty(self.ffi_ty("c_char")),
                         ]),
                     )])),
                     Some(
                         mk().call_expr(mk().path_expr(vec!["Vec", "new"]), vec![] as Vec<P<Expr>>),
                     ),
                 ))));
-                let var_name_ident = mk().ident("var_name");
-                let var_value_ident = mk().ident("var_value");
+                let mut var_stmts = os_string_into_vec("var_name");
+                var_stmts.extend(os_string_into_vec("var_value"));
+                var_stmts.extend(vec![
+                    // `NAME=value`
+                    mk().local_stmt(P(mk().local(
+                        mk().mutbl().ident_pat("var"),
+                        None as Option<P<Ty>>,
+                        Some(mk().path_expr(vec!["var_name"])),
+                    ))),
+                    mk().semi_stmt(mk().method_call_expr(
+                        mk().path_expr(vec!["var"]),
+                        "push",
+                        vec![mk().lit_expr(b'=')],
+                    )),
+                    mk().semi_stmt(mk().method_call_expr(
+                        mk().path_expr(vec!["var"]),
+                        "extend",
+                        vec![mk().path_expr(vec!["var_value"])],
+                    )),
+                    mk().semi_stmt(mk().method_call_expr(
+                        mk().path_expr(vec!["vars"]),
+                        "push",
+                        vec![
+                            mk().method_call_expr(
+                                mk().method_call_expr(
+                                    mk().call_expr(
+                                        mk().path_expr(vec!["","std","ffi","CString","new"]),
+                                        vec![mk().path_expr(vec!["var"])],
+                                    ),
+                                    "expect",
+                                    vec![mk().lit_expr(
+                                        "Failed to convert environment variable into CString."
+                                    )],
+                                ),
+                                "into_raw",
+                                vec![] as Vec<P<Expr>>,
+                            )
+                        ],
+                    ))
+                ]);
                 stmts.push(mk().semi_stmt(mk().for_expr(
                     mk().tuple_pat(vec![mk().ident_pat("var_name"), mk().ident_pat("var_value")]),
                     mk().call_expr(vars_fn, vec![] as Vec<P<Expr>>),
-                    mk().block(vec![
-                        mk().local_stmt(P(mk().local(
-                            mk().ident_pat("var"),
-                            Some(mk().path_ty(vec!["String"])),
-                            Some(mk().mac_expr(mk().mac(
-                                vec!["format"],
-                                vec![
-                                    token::Interpolated(Rc::new(Nonterminal::NtExpr(mk().lit_expr("{}={}")))),
-                                    token::Comma,
-                                    TokenKind::Ident(var_name_ident.name, var_name_ident.is_raw_guess()),
-                                    token::Comma,
-                                    TokenKind::Ident(var_value_ident.name, var_value_ident.is_raw_guess())
-                                ].into_iter()
-                                    .map(|tk| TokenTree::token(tk, DUMMY_SP))
-                                    .collect::<TokenStream>(),
-                                MacDelimiter::Parenthesis,
-                            )))
-                        ))),
-                        mk().semi_stmt(mk().method_call_expr(
-                            mk().path_expr(vec!["vars"]),
-                            "push",
-                            vec![
-                                mk().method_call_expr(
-                                    mk().method_call_expr(
-                                        mk().call_expr(
-                                            mk().path_expr(vec!["","std","ffi","CString","new"]),
-                                            vec![mk().path_expr(vec!["var"])],
-                                        ),
-                                        "expect",
-                                        vec![mk().lit_expr(
-                                            "Failed to convert environment variable into CString."
-                                        )],
-                                    ),
-                                    "into_raw",
-                                    vec![] as Vec<P<Expr>>,
-                                )
-                            ],
-                        ))
-                    ]),
+                    mk().block(var_stmts),
                     None as Option<Ident>,
                 )));
                 stmts.push(mk().semi_stmt(mk().method_call_expr(
@@ -231,8 +270,6 @@ impl<'c> Translation<'c> {
 
             let block = mk().block(stmts);
             let main_attributes = self.mk_cross_check(mk(), vec!["none"]);
-            let main_attributes = main_attributes.single_attr("main");
-            self.use_feature("main");
             Ok(main_attributes.pub_().fn_item("main", decl, block))
         } else {
             Err(TranslationError::generic(
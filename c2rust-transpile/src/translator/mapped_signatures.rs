@@ -0,0 +1,238 @@
+#![deny(missing_docs)]
+//! Function signatures that `--type-map` changes. An entry under
+//! `functions.NAME` replaces the types of the parameters and return value of a
+//! function defined in the translation unit, so the values crossing its
+//! signature are converted between the C type and the Rust type. The body
+//! shadows each replaced parameter with its value at the C type and converts
+//! the values it returns, and calls convert the other way around.
+//!
+//! A slice is passed on as its pointer, and built from the pointer argument
+//! and the integer argument after it, following the usual `(ptr, len)`
+//! convention. A reference is a dereferenced pointer. Any other type is
+//! assumed to have the layout of the C type it replaces, and is transmuted.
+
+use super::*;
+
+/// How values convert between a C type and the Rust type replacing it
+enum Conversion {
+    Slice(Mutability),
+    Ref(Mutability),
+    Transmute,
+}
+
+fn conversion(ty: &Ty) -> Conversion {
+    match ty.kind {
+        TyKind::Rptr(_, MutTy { ref ty, mutbl }) => match ty.kind {
+            TyKind::Slice(_) => Conversion::Slice(mutbl),
+            _ => Conversion::Ref(mutbl),
+        },
+        _ => Conversion::Transmute,
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// The Rust type the type map gives to a C type in the signature of a
+    /// function, if it replaces it
+    pub fn mapped_signature_ty(&self, function: &str, ctype: CTypeId) -> Option<P<Ty>> {
+        self.tcfg
+            .type_map
+            .lookup_in_function(&self.ast_context, self.c_function_name(function), ctype)
+    }
+
+    /// The definition of a called function, if the type map changes its signature
+    pub fn mapped_callee(&self, callee: CDeclId) -> Option<CDeclId> {
+        let name = match self.ast_context[callee].kind {
+            CDeclKind::Function { ref name, .. } => name,
+            _ => return None,
+        };
+        if !self.tcfg.type_map.has_function(self.c_function_name(name)) {
+            return None;
+        }
+        self.ast_context
+            .c_decls_top
+            .iter()
+            .cloned()
+            .find(|&decl_id| match self.ast_context[decl_id].kind {
+                CDeclKind::Function { name: ref def_name, body: Some(_), .. } => {
+                    def_name == name && self.is_selected_function(decl_id, def_name)
+                }
+                _ => false,
+            })
+    }
+
+    /// Check that the values crossing a changed signature can be converted:
+    /// a slice needs an integer length parameter after it, and a returned
+    /// slice has no length to be built with
+    pub fn check_mapped_signature(
+        &self,
+        function: &str,
+        arguments: &[(CDeclId, String, CQualTypeId)],
+        return_type: Option<CQualTypeId>,
+    ) -> Result<(), TranslationError> {
+        for (i, &(_, ref var, typ)) in arguments.iter().enumerate() {
+            let ty = match self.mapped_signature_ty(function, typ.ctype) {
+                Some(ty) => ty,
+                None => continue,
+            };
+            if let Conversion::Slice(_) = conversion(&ty) {
+                let has_len = arguments.get(i + 1).map_or(false, |&(_, _, len_typ)| {
+                    self.ast_context.resolve_type(len_typ.ctype).kind.is_integral_type()
+                });
+                if !has_len {
+                    return Err(format_err!(
+                        "Type map replaces parameter {} of {} with a slice, but no integer length \
+                         parameter follows it",
+                        var,
+                        function,
+                    )
+                    .into());
+                }
+            }
+        }
+        let ret_ty = return_type.and_then(|ret| self.mapped_signature_ty(function, ret.ctype));
+        if let Some(Conversion::Slice(_)) = ret_ty.as_ref().map(|ty| conversion(ty)) {
+            return Err(format_err!(
+                "Type map replaces the return type of {} with a slice, which has no length",
+                function,
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Shadow a replaced parameter by its value at the C type
+    pub fn convert_mapped_param(
+        &self,
+        var: &str,
+        ty: &Ty,
+        c_ty: P<Ty>,
+        mutbl: Mutability,
+    ) -> Stmt {
+        let val = self.mapped_to_c(ty, mk().ident_expr(var), c_ty.clone());
+        let pat = mk().set_mutbl(mutbl).ident_pat(var);
+        mk().local_stmt(P(mk().local(pat, Some(c_ty), Some(val))))
+    }
+
+    /// Convert a value the function being translated returns to the type
+    /// map's return type
+    pub fn convert_mapped_return(&self, val: P<Expr>) -> P<Expr> {
+        match *self.mapped_return_ty.borrow() {
+            Some(ref ty) => self.c_to_mapped(ty, val, None),
+            None => val,
+        }
+    }
+
+    /// Convert the arguments of a call to a function whose signature the type
+    /// map changes. The length argument of a slice is used twice, so it must
+    /// be pure.
+    pub fn convert_mapped_args(
+        &self,
+        callee: CDeclId,
+        arg_ids: &[CExprId],
+        args: Vec<P<Expr>>,
+    ) -> Result<Vec<P<Expr>>, TranslationError> {
+        let (name, params) = match self.ast_context[callee].kind {
+            CDeclKind::Function { ref name, ref parameters, .. } => (name, parameters),
+            _ => return Ok(args),
+        };
+        let mut converted = Vec::with_capacity(args.len());
+        for (i, arg) in args.iter().enumerate() {
+            let ty = params.get(i).and_then(|&param| match self.ast_context[param].kind {
+                CDeclKind::Variable { typ, .. } => self.mapped_signature_ty(name, typ.ctype),
+                _ => None,
+            });
+            let ty = match ty {
+                Some(ty) => ty,
+                None => {
+                    converted.push(arg.clone());
+                    continue;
+                }
+            };
+            let len = match conversion(&ty) {
+                Conversion::Slice(_) => match arg_ids.get(i + 1) {
+                    Some(&len_id) if self.ast_context.is_expr_pure(len_id) => args.get(i + 1).cloned(),
+                    _ => {
+                        return Err(format_err!(
+                            "The length argument of the slice passed to {} has side effects",
+                            name,
+                        )
+                        .into())
+                    }
+                },
+                _ => None,
+            };
+            converted.push(self.c_to_mapped(&ty, arg.clone(), len));
+        }
+        Ok(converted)
+    }
+
+    /// Convert the value a call to a function whose signature the type map
+    /// changes returns back to the C type
+    pub fn convert_mapped_result(
+        &self,
+        callee: CDeclId,
+        call_ty: CQualTypeId,
+        call: P<Expr>,
+    ) -> Result<P<Expr>, TranslationError> {
+        let ty = match self.ast_context[callee].kind {
+            CDeclKind::Function { ref name, typ, .. } => {
+                match self.ast_context.resolve_type(typ).kind {
+                    CTypeKind::Function(ret, ..) => self.mapped_signature_ty(name, ret.ctype),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match ty {
+            Some(ty) => Ok(self.mapped_to_c(&ty, call, self.convert_type(call_ty.ctype)?)),
+            None => Ok(call),
+        }
+    }
+
+    /// Convert a value of a C type to the type map's Rust type. Slices take
+    /// their length from `len`.
+    fn c_to_mapped(&self, ty: &Ty, val: P<Expr>, len: Option<P<Expr>>) -> P<Expr> {
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        match conversion(ty) {
+            Conversion::Slice(mutbl) => {
+                let (from_raw_parts, ptr_ty) = match mutbl {
+                    Mutability::Mutable => ("from_raw_parts_mut", mk().mutbl().ptr_ty(mk().infer_ty())),
+                    Mutability::Immutable => ("from_raw_parts", mk().ptr_ty(mk().infer_ty())),
+                };
+                let len = len.expect("Slices are built with a length");
+                mk().call_expr(
+                    mk().path_expr(vec!["", std_or_core, "slice", from_raw_parts]),
+                    vec![
+                        mk().cast_expr(val, ptr_ty),
+                        mk().cast_expr(len, mk().path_ty(vec!["usize"])),
+                    ],
+                )
+            }
+            Conversion::Ref(mutbl) => {
+                mk().set_mutbl(mutbl).addr_of_expr(mk().unary_expr(ast::UnOp::Deref, val))
+            }
+            Conversion::Transmute => {
+                transmute_expr(mk().infer_ty(), mk().infer_ty(), val, self.tcfg.emit_no_std)
+            }
+        }
+    }
+
+    /// Convert a value of the type map's Rust type back to the C type
+    fn mapped_to_c(&self, ty: &Ty, val: P<Expr>, c_ty: P<Ty>) -> P<Expr> {
+        match conversion(ty) {
+            Conversion::Slice(mutbl) => {
+                let as_ptr = match mutbl {
+                    Mutability::Mutable => "as_mut_ptr",
+                    Mutability::Immutable => "as_ptr",
+                };
+                mk().cast_expr(mk().method_call_expr(val, as_ptr, vec![] as Vec<P<Expr>>), c_ty)
+            }
+            Conversion::Ref(_) => {
+                mk().cast_expr(mk().cast_expr(val, mk().ptr_ty(mk().infer_ty())), c_ty)
+            }
+            Conversion::Transmute => {
+                transmute_expr(mk().infer_ty(), c_ty, val, self.tcfg.emit_no_std)
+            }
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use std::ops::Index;
 use std::path::{self, PathBuf};
 use std::rc::Rc;
+use std::slice;
 use std::char;
 
 use dtoa;
@@ -11,13 +12,11 @@ use dtoa;
 use failure::{err_msg, Fail};
 use indexmap::{IndexMap, IndexSet};
 
-use rustc_parse::parse_stream_from_source_str;
 use syntax::attr;
 use syntax::ast::*;
 use syntax::util::comments::CommentStyle;
 use syntax::token::{self, DelimToken, Nonterminal};
 use syntax::ptr::*;
-use syntax::sess::ParseSess;
 use syntax::source_map::{FilePathMapping, SourceMap};
 use syntax::tokenstream::{TokenStream, TokenTree};
 use syntax::{ast, with_globals};
@@ -26,6 +25,7 @@ use syntax_pos::edition::Edition;
 
 use crate::rust_ast::pos_to_span;
 use crate::rust_ast::comment_store::CommentStore;
+use crate::rust_ast::item_order;
 use crate::rust_ast::item_store::ItemStore;
 use crate::rust_ast::traverse::Traversal;
 use c2rust_ast_builder::{mk, Builder, IntoSymbol};
@@ -35,25 +35,54 @@ use crate::c_ast;
 use crate::c_ast::iterators::{DFExpr, SomeId};
 use crate::c_ast::*;
 use crate::cfg;
-use crate::convert_type::TypeConverter;
+use crate::convert_type::{simd_vector_type_name, TypeConverter};
 use crate::renamer::Renamer;
+use crate::report::{decl_kind_name, FileReport, Rename, TranslatedItem};
 use crate::with_stmts::WithStmts;
 use crate::{ExternCrate, ExternCrateDetails, TranspilerConfig};
 use c2rust_ast_exporter::clang_ast::LRValue;
 
+mod alloca;
+mod array_aliases;
 mod assembly;
 mod atomics;
 mod builtins;
+mod cleanup_gotos;
 mod comments;
+mod copies;
+mod derives;
+mod differential;
+mod errno;
+mod inline_fns;
+mod int128;
+mod item_attrs;
+mod libc_calls;
+mod linkage;
 mod literals;
+mod macros;
 mod main_function;
+mod mapped_signatures;
 mod named_references;
+mod null_checks;
 mod operators;
+mod owned_allocations;
+mod printf;
+mod range_loops;
+mod sequencing;
 mod simd;
+mod source_map;
 mod structs;
+mod stubs;
+mod test_harness;
 mod variadic;
+mod zero_init;
 
 pub use crate::diagnostics::{TranslationError, TranslationErrorKind};
+pub use self::differential::{HarnessFn, HarnessParam};
+pub use self::inline_fns::SharedInlineFn;
+pub use self::item_attrs::parse_attribute;
+pub use self::linkage::{DefinedStatic, ExternStatic, LinkedStatics, TentativeDef};
+pub use self::source_map::SourceMapEntry;
 use crate::CrateSet;
 use crate::PragmaVec;
 
@@ -99,12 +128,158 @@ impl From<bool> for DecayRef {
     }
 }
 
+/// Loader-run function pointer sections used for static initializers and
+/// `__attribute__((constructor))`/`__attribute__((destructor))` functions.
+#[derive(Debug, Copy, Clone)]
+enum InitSection {
+    Init,
+    Fini,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum ReplaceMode {
     None,
     Extern,
 }
 
+/// How definitions of `__thread`/`_Thread_local` variables are emitted.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ThreadLocalMode {
+    /// `#[thread_local] static mut` (requires the nightly `thread_local` feature)
+    Attribute,
+    /// `thread_local!` holding an `UnsafeCell`, with every use site rewritten
+    /// to go through `LocalKey::with`
+    Macro,
+}
+
+/// Where the function cross-checked under a given tag came from in the C source, so that a
+/// divergence reported in terms of tags can be traced back to the original C function.
+#[derive(Serialize, Debug, Clone)]
+pub struct XCheckSourceEntry {
+    /// The `djb2` hash of the C function name, used for its entry and exit cross-checks
+    pub tag: u32,
+    pub c_function: String,
+    pub c_file: Option<PathBuf>,
+    pub c_line: Option<u64>,
+    pub rust_function: String,
+}
+
+/// The configuration of the clang cross-check plugin that makes the C build
+/// check the same arguments and return values as the translated functions,
+/// passed to the plugin with `-Xclang -plugin-arg-crosschecks -Xclang -C<file>`.
+/// It is written as JSON, which the plugin parses as YAML. Files are named by
+/// their path relative to `source_root`, the common ancestor of the C sources.
+pub fn clang_xcheck_config(entries: &[XCheckSourceEntry], source_root: &path::Path) -> String {
+    let mut files: IndexMap<&path::Path, Vec<serde_json::Value>> = IndexMap::new();
+    for entry in entries {
+        let c_file = match entry.c_file {
+            Some(ref c_file) => c_file,
+            None => continue,
+        };
+        files.entry(c_file).or_default().push(serde_json::json!({
+            "item": "function",
+            "name": entry.c_function,
+            "entry": { "djb2": entry.c_function },
+            "exit": { "djb2": entry.c_function },
+            "all_args": "default",
+            "return": "default",
+        }));
+    }
+    let files: Vec<_> = files
+        .into_iter()
+        .map(|(c_file, items)| {
+            // The plugin matches the file name as it was passed to clang,
+            // which may be relative to any directory above the file, so the
+            // relative path is anchored with `**/`. Unlike the bare file name,
+            // it tells apart files with the same name in different directories.
+            let rel_path = c_file.strip_prefix(source_root).unwrap_or(c_file);
+            serde_json::json!({
+                "file": format!("**/{}", rel_path.to_string_lossy()),
+                "items": items,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&files).expect("Unable to serialize cross-check config")
+}
+
+/// The hash the cross-checking runtime and clang plugin use for function names.
+fn djb2_hash(s: &str) -> u32 {
+    s.bytes()
+        .fold(5381u32, |h, c| h.wrapping_mul(33).wrapping_add(c.into()))
+}
+
+/// How closely implicit integer conversions follow C. Clang makes the integer
+/// promotions and the usual arithmetic conversions explicit, so in every mode
+/// they are `as` casts, which convert like C does. The modes only differ in
+/// where Rust's arithmetic would not wrap around the way C's conversion of
+/// the result back to a narrower type does.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum IntegerConversionMode {
+    /// `as` casts wherever clang inserts an implicit conversion
+    Default,
+    /// Like `Default`, and `++`/`--` on signed types narrower than `int` wrap
+    /// around like the promoted C arithmetic does instead of overflowing
+    Exact,
+    /// Like `Exact`, but implicit conversions and narrow increments that would
+    /// change a value panic instead of wrapping, to find where a program
+    /// relies on them
+    Checked,
+}
+
+/// How arithmetic on signed integers is translated. Signed overflow is
+/// undefined in C, while Rust's plain operators panic on it in debug builds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SignedArithmeticMode {
+    /// Plain operators, which panic on overflow in debug builds and wrap
+    /// around in release builds
+    Plain,
+    /// `wrapping_add` and friends, which always wrap around
+    WrappingMethods,
+    /// Operators on `std::num::Wrapping` values, which always wrap around
+    WrappingType,
+}
+
+/// How `long double` values are represented.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LongDoubleMode {
+    /// `f128::f128` from the `f128` crate, which keeps the full precision
+    F128,
+    /// Plain `f64`, which loses precision but needs no extra crate
+    F64,
+}
+
+/// Where the lints that translated code triggers, like `non_camel_case_types`,
+/// are allowed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LintAllowMode {
+    /// `#![allow]` for the whole crate or module
+    Crate,
+    /// `#[allow]` on every top-level item
+    Item,
+    /// Not at all, to see what remains to clean up
+    None,
+}
+
+/// Where the memory that `alloca` returns is allocated
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AllocaMode {
+    /// In buffers on the heap that are freed when the function returns
+    Vec,
+    /// On the stack with the `alloca` crate, where possible
+    Crate,
+}
+
+/// A trait `--derive` has translated types implement where possible
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Derive {
+    /// Derived by plain structs whose fields are all `Debug`
+    Debug,
+    /// Implemented by structs and unions as their all-zero value
+    Default,
+    /// Derived by plain structs whose fields are all `PartialEq`
+    PartialEq,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct ExprContext {
     used: bool,
@@ -257,20 +432,51 @@ pub struct Translation<'c> {
     pub features: RefCell<IndexSet<&'static str>>,
     sectioned_static_initializers: RefCell<Vec<Stmt>>,
     extern_crates: RefCell<CrateSet>,
+    xcheck_source_map: RefCell<Vec<XCheckSourceEntry>>,
+    shared_inline_fns: RefCell<Vec<SharedInlineFn>>,
+    linked_statics: RefCell<LinkedStatics>,
+    harness_fns: RefCell<Vec<HarnessFn>>,
+    /// Parsed `TranspilerConfig::fn_attributes`
+    fn_attributes: Vec<Attribute>,
+    report: RefCell<FileReport>,
+    // C library functions we declared ourselves in each file
+    declared_c_fns: RefCell<HashSet<(FileId, String)>>,
 
     // Translation state and utilities
     type_converter: RefCell<TypeConverter>,
     renamer: RefCell<Renamer<CDeclId>>,
-    zero_inits: RefCell<IndexMap<CDeclId, WithStmts<P<Expr>>>>,
+    abi_checked_structs: HashSet<CRecordId>,
+    anonymous_constant_enums: HashSet<CEnumId>,
+    zero_inits: RefCell<IndexMap<(CDeclId, bool), WithStmts<P<Expr>>>>,
     function_context: RefCell<FunContext>,
     potential_flexible_array_members: RefCell<IndexSet<CDeclId>>,
+    // Local pointers that only alias a local array, to a reference to the array
+    array_aliases: RefCell<HashMap<CDeclId, CExprId>>,
+    range_loops: RefCell<HashMap<CStmtId, range_loops::RangeLoop>>,
+    owned_allocations: RefCell<HashMap<CDeclId, owned_allocations::OwnedAllocation>>,
+    option_pointers: RefCell<HashSet<CDeclId>>,
+    option_returns: RefCell<HashSet<String>>,
+    option_return_ty: RefCell<Option<P<Ty>>>,
+    alloca_scratch: RefCell<Option<alloca::AllocaScratch>>,
+    record_copyabilities: RefCell<HashMap<CRecordId, crate::type_map::Copyability>>,
+    // The type map's return type for the function whose body is being translated
+    mapped_return_ty: RefCell<Option<P<Ty>>>,
+    null_checks: RefCell<HashMap<CStmtId, null_checks::NullCheck>>,
+    /// Cleanup labels and the statements to copy in place of `goto`s to them
+    cleanup_labels: RefCell<HashMap<CLabelId, Vec<CStmtId>>>,
+    unwrapped_pointers: RefCell<HashSet<CDeclId>>,
     macro_expansions: RefCell<IndexMap<CDeclId, Option<MacroExpansion>>>,
+    macro_use_sites: HashMap<CDeclId, Vec<CExprId>>,
+    macro_function_expansions: RefCell<IndexMap<CDeclId, Option<macros::MacroFunctionExpansion>>>,
+    macro_arg_substitutions: RefCell<HashMap<CExprId, String>>,
 
     // Comment support
     pub comment_context: CommentContext, // Incoming comments
     pub comment_store: RefCell<CommentStore>,     // Outgoing comments
 
     spans: HashMap<SomeId, Span>,
+    /// Source locations named by the markers of `--source-map`
+    source_locs: Vec<SrcSpan>,
 
     // Items indexed by file id of the source
     items: RefCell<IndexMap<FileId, ItemStore>>,
@@ -281,6 +487,12 @@ pub struct Translation<'c> {
     // The main file id that the translator is operating on
     main_file: FileId,
 
+    // Whether the main file is a test driver, emitted as an integration test
+    is_test_harness: bool,
+
+    // The common ancestor of the paths of the crate's C sources
+    source_root: PathBuf,
+
     // While expanding an item, store the current file id that item is
     // expanded from. This is needed in order to note imports in items when
     // encountering DeclRefs.
@@ -319,27 +531,43 @@ fn cast_int(val: P<Expr>, name: &str, need_lit_suffix: bool) -> P<Expr> {
     }
 }
 
-/// Pointer offset that casts its argument to isize
+/// Pointer offset by `offset` elements, or by `offset * multiply_by` elements
+/// when stepping over the rows of a variable length array. A pointer that is
+/// dereferenced right away must be in bounds and uses `offset`, `add` or `sub`.
+/// Any other pointer may end up one past the end of its object or further, so
+/// it uses the wrapping methods, which are defined for any offset. Unsigned
+/// offsets use `add` and `sub` rather than a cast to `isize` and a negation.
 fn pointer_offset(
     ptr: P<Expr>,
     offset: P<Expr>,
+    unsigned_offset: bool,
     multiply_by: Option<P<Expr>>,
     neg: bool,
     mut deref: bool,
 ) -> P<Expr> {
-    let mut offset = cast_int(offset, "isize", false);
+    let in_bounds = deref;
+    let offset_ty = if unsigned_offset { "usize" } else { "isize" };
+    let mut offset = cast_int(offset, offset_ty, false);
 
     if let Some(mul) = multiply_by {
-        let mul = cast_int(mul, "isize", false);
+        let mul = cast_int(mul, offset_ty, false);
         offset = mk().binary_expr(BinOpKind::Mul, offset, mul);
         deref = false;
     }
 
-    if neg {
+    let method = match (unsigned_offset, neg, in_bounds) {
+        (true, false, true) => "add",
+        (true, true, true) => "sub",
+        (true, false, false) => "wrapping_add",
+        (true, true, false) => "wrapping_sub",
+        (false, _, true) => "offset",
+        (false, _, false) => "wrapping_offset",
+    };
+    if neg && !unsigned_offset {
         offset = mk().unary_expr(ast::UnOp::Neg, offset);
     }
 
-    let res = mk().method_call_expr(ptr, "offset", vec![offset]);
+    let res = mk().method_call_expr(ptr, method, vec![offset]);
     if deref {
         mk().unary_expr(ast::UnOp::Deref, res)
     } else {
@@ -376,6 +604,15 @@ fn transmute_expr(source_ty: P<Ty>, target_ty: P<Ty>, expr: P<Expr>, no_std: boo
     mk().call_expr(mk().path_expr(path), vec![expr])
 }
 
+/// Add `#[derive]` for the given traits, if there are any
+fn derive_attr(builder: Builder, traits: Vec<&str>) -> Builder {
+    if traits.is_empty() {
+        builder
+    } else {
+        builder.call_attr("derive", traits)
+    }
+}
+
 fn vec_expr(val: P<Expr>, count: P<Expr>) -> P<Expr> {
     let from_elem = mk().path_expr(vec!["", "std", "vec", "from_elem"]);
     mk().call_expr(from_elem, vec![val, count])
@@ -424,6 +661,27 @@ pub fn signed_int_expr(value: i64) -> P<Expr> {
 
 // This should only be used for tests
 fn prefix_names(translation: &mut Translation, prefix: &str) {
+    // Fortified wrappers are declared, so they keep the library's name
+    let wrappers: HashSet<CDeclId> = translation
+        .ast_context
+        .iter_decls()
+        .map(|(&decl_id, _)| decl_id)
+        .filter(|&decl_id| translation.is_fortified_wrapper(decl_id))
+        .collect();
+    // Functions left out by the filters are declared, so they keep their C name
+    // to link against
+    let filtered_out: HashSet<CDeclId> = translation
+        .ast_context
+        .iter_decls()
+        .filter(|&(&decl_id, decl)| match decl.kind {
+            CDeclKind::Function { ref name, body: Some(_), .. } => {
+                !translation.tcfg.emit_bindings_only
+                    && !translation.is_selected_function(decl_id, name)
+            }
+            _ => false,
+        })
+        .map(|(&decl_id, _)| decl_id)
+        .collect();
     for (&decl_id, ref mut decl) in translation.ast_context.iter_mut_decls() {
         match decl.kind {
             CDeclKind::Function {
@@ -432,12 +690,16 @@ fn prefix_names(translation: &mut Translation, prefix: &str) {
                 ..
             } if body.is_some() => {
                 // SIMD types are imported and do not need to be renamed
-                if name.starts_with("_mm") {
+                if name.starts_with("_mm")
+                    || wrappers.contains(&decl_id)
+                    || filtered_out.contains(&decl_id)
+                {
                     continue;
                 }
 
                 name.insert_str(0, prefix);
 
+                let name = translation.tcfg.naming.value_case.apply(name);
                 translation.renamer.borrow_mut().insert(decl_id, &name);
             }
             CDeclKind::Variable {
@@ -504,8 +766,19 @@ pub fn translate(
     ast_context: TypedAstContext,
     tcfg: &TranspilerConfig,
     main_file: PathBuf,
-) -> (String, PragmaVec, CrateSet) {
-    let mut t = Translation::new(ast_context, tcfg, main_file.as_path());
+    source_root: &path::Path,
+) -> (
+    String,
+    PragmaVec,
+    CrateSet,
+    Vec<XCheckSourceEntry>,
+    Vec<SourceMapEntry>,
+    FileReport,
+    Vec<SharedInlineFn>,
+    LinkedStatics,
+    Vec<HarnessFn>,
+) {
+    let mut t = Translation::new(ast_context, tcfg, main_file.as_path(), source_root);
     let ctx = ExprContext {
         used: true,
         is_static: false,
@@ -520,7 +793,9 @@ pub fn translate(
 
     // `with_globals` sets up a thread-local variable required by the syntax crate.
     with_globals(Edition::Edition2018, || {
-        t.use_crate(ExternCrate::Libc);
+        if !t.tcfg.emit_no_libc {
+            t.use_crate(ExternCrate::Libc);
+        }
 
         // Sort the top-level declarations by file and source location so that we
         // preserve the ordering of all declarations in each file.
@@ -530,10 +805,30 @@ pub fn translate(
 
         // Headers often pull in declarations that are unused;
         // we simplify the translator output by omitting those.
-        t.ast_context.prune_unused_decls();
+        if !tcfg.keep_unused {
+            t.ast_context
+                .prune_unused_decls(|name| tcfg.root_decls.iter().any(|re| re.is_match(name)));
+        }
+
+        if t.tcfg.long_double_mode == LongDoubleMode::F64 && t.ast_context.demote_long_double() {
+            warn!(
+                "Translating long double as f64 in {}; long double values will lose precision, \
+                 and external functions taking or returning long double will be called with f64",
+                main_file.display(),
+            );
+        }
+
+        if t.tcfg.check_abi {
+            t.abi_checked_structs = t.ast_context.structs_passed_by_value();
+        }
+
+        if t.tcfg.translate_const_macros {
+            t.macro_use_sites = t.ast_context.macro_use_sites();
+        }
 
         enum Name<'a> {
             VarName(&'a str),
+            ConstName(&'a str),
             TypeName(&'a str),
             AnonymousType,
             NoName,
@@ -551,6 +846,12 @@ pub fn translate(
             prefix_names(&mut t, prefix);
         }
 
+        // Callers decide whether a function returns an `Option`, so all
+        // functions are analyzed before any is translated
+        if t.tcfg.option_pointers {
+            t.find_option_pointers();
+        }
+
         // Identify typedefs that name unnamed types and collapse the two declarations
         // into a single name and declaration, eliminating the typedef altogether.
         let mut prenamed_decls: IndexMap<CDeclId, CDeclId> = IndexMap::new();
@@ -606,6 +907,7 @@ pub fn translate(
         }
 
         t.ast_context.prenamed_decls = prenamed_decls;
+        t.anonymous_constant_enums = t.anonymous_constant_enums();
 
         // Helper function that returns true if there is either a matching typedef or its
         // corresponding struct/union/enum
@@ -629,13 +931,14 @@ pub fn translate(
                 }
                 CDeclKind::Typedef { ref name, .. } => Name::TypeName(name),
                 CDeclKind::Function { ref name, .. } => Name::VarName(name),
-                CDeclKind::EnumConstant { ref name, .. } => Name::VarName(name),
+                CDeclKind::EnumConstant { ref name, .. } => Name::ConstName(name),
                 CDeclKind::Variable { ref ident, .. }
                     if t.ast_context.c_decls_top.contains(&decl_id) =>
                 {
                     Name::VarName(ident)
                 }
-                CDeclKind::MacroObject { ref name, .. } => Name::VarName(name),
+                CDeclKind::MacroObject { ref name, .. } => Name::ConstName(name),
+                CDeclKind::MacroFunction { ref name, .. } => Name::VarName(name),
                 _ => Name::NoName,
             };
             match decl_name {
@@ -651,6 +954,11 @@ pub fn translate(
                         .declare_decl_name(decl_id, name);
                 }
                 Name::VarName(name) => {
+                    let name = t.tcfg.naming.value_case.apply(name);
+                    t.renamer.borrow_mut().insert(decl_id, &name);
+                }
+                Name::ConstName(name) => {
+                    let name = t.tcfg.naming.const_case.apply(name);
                     t.renamer.borrow_mut().insert(decl_id, &name);
                 }
             }
@@ -659,33 +967,30 @@ pub fn translate(
         {
             let convert_type = |decl_id: CDeclId, decl: &CDecl| {
                 let decl_file_id = t.ast_context.file_id(decl);
-                if t.tcfg.reorganize_definitions {
+                if t.tcfg.reorganize_definitions || t.is_split_file(decl_file_id) {
                     *t.cur_file.borrow_mut() = decl_file_id;
                 }
                 match t.convert_decl(ctx, decl_id) {
                     Ok(ConvertedDecl::Item(item)) => {
                         t.insert_item(item, decl);
+                        t.record_translated(decl_id);
                     }
                     Ok(ConvertedDecl::ForeignItem(item)) => {
                         t.insert_foreign_item(item, decl);
+                        t.record_translated(decl_id);
                     }
                     Ok(ConvertedDecl::Items(items)) => {
                         for item in items {
                             t.insert_item(item, decl);
                         }
+                        t.record_translated(decl_id);
                     }
                     Ok(ConvertedDecl::NoItem) => {}
-                    Err(e) => {
-                        let ref k = t.ast_context.get_decl(&decl_id).map(|x| &x.kind);
-                        let msg = format!("Skipping declaration {:?} due to error: {}", k, e);
-                        translate_failure(&t.tcfg, &msg);
-                    }
+                    Err(e) => t.report_failed_decl(decl_id, e),
                 }
                 t.cur_file.borrow_mut().take();
 
-                if t.tcfg.reorganize_definitions
-                    && decl_file_id.map_or(false, |id| id != t.main_file)
-                {
+                if t.is_split_file(decl_file_id) {
                     t.generate_submodule_imports(decl_id, decl_file_id);
                 }
             };
@@ -704,18 +1009,27 @@ pub fn translate(
                         !t.ast_context.prenamed_decls.contains_key(&decl_id)
                     }
                     _ => false,
-                };
+                } && !tcfg.type_map.replaces_decl(&t.ast_context, decl_id);
                 if needs_export {
                     convert_type(decl_id, decl);
                 }
             }
         }
 
-        // Export top-level value declarations
-        for top_id in &t.ast_context.c_decls_top {
+        // Function bodies are not needed after their function is translated, unless macro
+        // translation still has to look at the expansions inside them.
+        let release_bodies = !tcfg.translate_const_macros && !tcfg.translate_fn_macros;
+
+        // Export top-level value declarations. Bindings only declare the ones other
+        // translation units can link to.
+        for top_id in &t.ast_context.c_decls_top.clone() {
             let needs_export = match t.ast_context[*top_id].kind {
-                CDeclKind::Function { is_implicit, .. } => !is_implicit,
-                CDeclKind::Variable { .. } => true,
+                CDeclKind::Function { is_implicit, is_global, .. } => {
+                    !is_implicit && (is_global || !tcfg.emit_bindings_only)
+                }
+                CDeclKind::Variable { is_externally_visible, .. } => {
+                    is_externally_visible || !tcfg.emit_bindings_only
+                }
                 CDeclKind::MacroObject { .. } => tcfg.translate_const_macros,
                 CDeclKind::MacroFunction { .. } => tcfg.translate_fn_macros,
                 _ => false,
@@ -725,63 +1039,71 @@ pub fn translate(
                 let decl = decl_opt.as_ref().unwrap();
                 let decl_file_id = t.ast_context.file_id(decl);
 
-                if t.tcfg.reorganize_definitions
-                    && decl_file_id.map_or(false, |id| id != t.main_file)
-                {
+                if t.is_split_file(decl_file_id) {
                     *t.cur_file.borrow_mut() = decl_file_id;
                 }
                 match t.convert_decl(ctx, *top_id) {
                     Ok(ConvertedDecl::Item(item)) => {
+                        t.record_harness_fn(*top_id, &item);
+                        let item = t.share_inline_fn(ctx, *top_id, item);
+                        let item = t.share_tentative_def(ctx, *top_id, item);
                         t.insert_item(item, decl);
+                        t.record_translated(*top_id);
                     }
                     Ok(ConvertedDecl::ForeignItem(item)) => {
-                        t.insert_foreign_item(item, decl);
+                        match t.share_extern_static(*top_id, &item) {
+                            Some(item) => t.insert_item(item, decl),
+                            None => t.insert_foreign_item(item, decl),
+                        }
+                        t.record_translated(*top_id);
                     }
                     Ok(ConvertedDecl::Items(items)) => {
                         for item in items {
                             t.insert_item(item, decl);
                         }
+                        t.record_translated(*top_id);
                     }
                     Ok(ConvertedDecl::NoItem) => {}
-                    Err(e) => {
-                        let ref decl = t.ast_context.get_decl(top_id);
-                        let msg = match decl {
-                            Some(decl) => {
-                                let decl_identifier = decl.kind.get_name().map_or_else(
-                                    || {
-                                        t.ast_context.display_loc(&decl.loc)
-                                            .map_or("Unknown".to_string(), |l| format!("at {}", l))
-                                    },
-                                    |name| name.clone(),
-                                );
-                                format!("Failed to translate {}: {}", decl_identifier, e)
-                            }
-                            _ => format!("Failed to translate declaration: {}", e,),
-                        };
-                        translate_failure(&t.tcfg, &msg);
-                    }
+                    Err(e) => t.report_failed_decl(*top_id, e),
                 }
                 t.cur_file.borrow_mut().take();
 
-                if t.tcfg.reorganize_definitions
-                    && decl_file_id.map_or(false, |id| id != t.main_file)
-                {
+                if t.is_split_file(decl_file_id) {
                     t.generate_submodule_imports(*top_id, decl_file_id);
                 }
+
+                if release_bodies {
+                    t.ast_context.release_function_body(*top_id);
+                }
             }
         }
 
-        // Add the main entry point
-        if let Some(main_id) = t.ast_context.c_main {
+        // Add the main entry point. There is no runtime to start a `no_std` crate from, so it
+        // has to call the translated C main itself. If the C main was filtered out, the C code
+        // provides the entry point instead.
+        let main_id = t.ast_context.c_main.filter(|&main_id| {
+            !t.tcfg.emit_no_std_crate
+                && !t.is_test_harness
+                && t.is_selected_function(main_id, "main")
+        });
+        if let Some(main_id) = main_id {
             match t.convert_main(main_id) {
                 Ok(item) => t.items.borrow_mut()[&t.main_file].add_item(item),
                 Err(e) => {
                     let msg = format!("Failed to translate main: {}", e);
-                    translate_failure(&t.tcfg, &msg)
+                    translate_failure(&t.tcfg, &msg);
+                    t.record_skipped(main_id, e.kind().name(), e.message(), false);
                 }
             }
         }
 
+        // The test harness provides the entry point of a test driver instead
+        if t.is_test_harness {
+            if let Some(tests) = t.convert_test_harness() {
+                t.items.borrow_mut()[&t.main_file].add_item(tests);
+            }
+        }
+
         // Initialize global statics when necessary
         if !t.sectioned_static_initializers.borrow().is_empty() {
             let (initializer_fn, initializer_static) = t.generate_global_static_init();
@@ -791,6 +1113,10 @@ pub fn translate(
             store.add_item(initializer_static);
         }
 
+        if t.type_converter.borrow().uses_half() {
+            t.use_crate(ExternCrate::Half);
+        }
+
         let pragmas = t.get_pragmas();
         let crates = t.extern_crates.borrow().clone();
 
@@ -808,6 +1134,7 @@ pub fn translate(
                     *file_id,
                     &mut new_uses,
                     &t.mod_names,
+                    t.tcfg.order_items,
                 );
                 let comments = t.comment_context.get_remaining_comments(*file_id);
                 submodule.span = match t
@@ -823,7 +1150,13 @@ pub fn translate(
         }
 
         // Main file item store
-        let (items, foreign_items, uses) = t.items.borrow_mut()[&t.main_file].drain();
+        let (mut items, mut foreign_items, uses) = t.items.borrow_mut()[&t.main_file].drain();
+        if t.tcfg.order_items {
+            if !foreign_items.is_empty() {
+                items.push(mk().extern_("C").foreign_items(mem::replace(&mut foreign_items, vec![])));
+            }
+            items = item_order::order_items(items);
+        }
 
         // Re-order comments
         // FIXME: We shouldn't have to replace with an empty comment store here,
@@ -835,7 +1168,7 @@ pub fn translate(
         // spans and uses them to decide when to emit comments.
         mod_items = mod_items
             .into_iter()
-            .map(|p_i| p_i.map(|i| traverser.traverse_item(i)))
+            .map(|p_i| t.allow_item_lints(p_i.map(|i| traverser.traverse_item(i))))
             .collect();
         let foreign_items: Vec<ForeignItem> = foreign_items
             .into_iter()
@@ -843,7 +1176,7 @@ pub fn translate(
             .collect();
         let items: Vec<P<Item>> = items
             .into_iter()
-            .map(|p_i| p_i.map(|i| traverser.traverse_item(i)))
+            .map(|p_i| t.allow_item_lints(p_i.map(|i| traverser.traverse_item(i))))
             .collect();
 
         let mut reordered_comment_store = traverser.into_comment_store();
@@ -862,7 +1195,7 @@ pub fn translate(
 
         // pass all converted items to the Rust pretty printer
         let translation = pprust::to_string_with_comments(comments, |s| {
-            print_header(s, &t, t.tcfg.is_binary(main_file.as_path()));
+            print_header(s, &t, t.tcfg.is_binary(main_file.as_path()) || t.is_test_harness);
 
             for mod_item in mod_items {
                 s.print_item(&*mod_item);
@@ -883,7 +1216,8 @@ pub fn translate(
             }
 
             if !foreign_items.is_empty() {
-                s.print_item(&mk().extern_("C").foreign_items(foreign_items))
+                let foreign_mod = mk().extern_("C").foreign_items(foreign_items);
+                s.print_item(&t.allow_item_lints(foreign_mod))
             }
 
             // Add the items accumulated
@@ -893,7 +1227,27 @@ pub fn translate(
 
             s.print_remaining_comments();
         });
-        (translation, pragmas, crates)
+        let (translation, source_map) = if t.tcfg.source_map {
+            t.extract_source_map(translation)
+        } else {
+            (translation, vec![])
+        };
+        let xcheck_source_map = t.xcheck_source_map.into_inner();
+        let shared_inline_fns = t.shared_inline_fns.into_inner();
+        let linked_statics = t.linked_statics.into_inner();
+        let harness_fns = t.harness_fns.into_inner();
+        let report = t.report.into_inner();
+        (
+            translation,
+            pragmas,
+            crates,
+            xcheck_source_map,
+            source_map,
+            report,
+            shared_inline_fns,
+            linked_statics,
+            harness_fns,
+        )
     })
 }
 
@@ -903,6 +1257,7 @@ fn make_submodule(
     file_id: FileId,
     use_item_store: &mut ItemStore,
     mod_names: &RefCell<IndexMap<String, PathBuf>>,
+    order_items: bool,
 ) -> P<Item> {
     let (mut items, foreign_items, uses) = item_store.drain();
     let file_path = ast_context.get_file_path(file_id);
@@ -936,6 +1291,10 @@ fn make_submodule(
         items.push(mk().extern_("C").foreign_items(foreign_items));
     }
 
+    if order_items {
+        items = item_order::order_items(items);
+    }
+
     let file_path_str = file_path.map_or(
         mod_name.as_str(),
         |path| path.to_str().expect("Found invalid unicode"),
@@ -1033,11 +1392,6 @@ fn print_header(s: &mut pprust::State, t: &Translation, is_binary: bool) {
     }
 }
 
-/// Convert a boolean expression to a c_int
-fn bool_to_int(val: P<Expr>) -> P<Expr> {
-    mk().cast_expr(val, mk().path_ty(vec!["libc", "c_int"]))
-}
-
 /// Add a src_loc = "line:col" attribute to an item/foreign_item
 fn add_src_loc_attr(attrs: &mut Vec<ast::Attribute>, src_loc: &Option<SrcLoc>) {
     if let Some(src_loc) = src_loc.as_ref() {
@@ -1049,6 +1403,31 @@ fn add_src_loc_attr(attrs: &mut Vec<ast::Attribute>, src_loc: &Option<SrcLoc>) {
     }
 }
 
+/// Add `#[c2rust::restrict(a, b)]` and `#[c2rust::volatile(c)]` attributes naming the
+/// arguments of a function that are `restrict` pointers or point to `volatile` data. Later
+/// analyses (such as ownership inference in `c2rust-refactor`) read these back since the Rust
+/// types alone no longer carry this information.
+fn add_aliasing_attrs(
+    mut mk_: Builder,
+    restrict_args: &[String],
+    volatile_args: &[String],
+) -> Builder {
+    for &(key, args) in &[("restrict", restrict_args), ("volatile", volatile_args)] {
+        if args.is_empty() {
+            continue;
+        }
+        let words = args
+            .iter()
+            .map(|arg| {
+                mk().nested_meta_item(mk().meta_item(vec![arg.as_str()], MetaItemKind::Word))
+            })
+            .collect::<Vec<_>>();
+        let item = mk().meta_item(vec!["c2rust", key], MetaItemKind::List(words));
+        mk_ = mk_.meta_item_attr(AttrStyle::Outer, item);
+    }
+    mk_
+}
+
 /// This represents all of the ways a C expression can be used in a C program. Making this
 /// distinction is important for:
 ///
@@ -1090,14 +1469,28 @@ impl<'c> Translation<'c> {
         mut ast_context: TypedAstContext,
         tcfg: &'c TranspilerConfig,
         main_file: &path::Path,
+        source_root: &path::Path,
     ) -> Self {
         let comment_context = CommentContext::new(&mut ast_context);
-        let mut type_converter = TypeConverter::new(tcfg.emit_no_std);
+        let mut type_converter =
+            TypeConverter::new(tcfg.emit_no_std, tcfg.emit_no_libc, tcfg.naming.clone());
 
         if tcfg.translate_valist {
             type_converter.translate_valist = true
         }
 
+        if tcfg.translate_simd {
+            type_converter.translate_simd = true
+        }
+
+        type_converter.hooks = tcfg.hooks.clone();
+        type_converter.type_map = tcfg.type_map.clone();
+
+        let report = FileReport {
+            c_file: main_file.to_path_buf(),
+            ..FileReport::default()
+        };
+        let is_test_harness = tcfg.is_test_file(main_file);
         let main_file = ast_context.find_file_id(main_file).unwrap_or(0);
         let items = indexmap!{main_file => ItemStore::new()};
 
@@ -1106,7 +1499,7 @@ impl<'c> Translation<'c> {
             type_converter: RefCell::new(type_converter),
             ast_context,
             tcfg,
-            renamer: RefCell::new(Renamer::new(&[
+            renamer: RefCell::new(Renamer::with_policy(&[
                 // Keywords currently in use
                 "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false",
                 "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
@@ -1118,18 +1511,49 @@ impl<'c> Translation<'c> {
                 "async", "try", "yield", // Prevent use for other reasons
                 "main",  // prelude names
                 "drop", "Some", "None", "Ok", "Err",
-            ])),
+            ], tcfg.naming.clone())),
+            abi_checked_structs: HashSet::new(),
+            anonymous_constant_enums: HashSet::new(),
             zero_inits: RefCell::new(IndexMap::new()),
             function_context: RefCell::new(FunContext::new()),
             potential_flexible_array_members: RefCell::new(IndexSet::new()),
+            array_aliases: RefCell::new(HashMap::new()),
+            range_loops: RefCell::new(HashMap::new()),
+            owned_allocations: RefCell::new(HashMap::new()),
+            option_pointers: RefCell::new(HashSet::new()),
+            option_returns: RefCell::new(HashSet::new()),
+            option_return_ty: RefCell::new(None),
+            alloca_scratch: RefCell::new(None),
+            record_copyabilities: RefCell::new(HashMap::new()),
+            mapped_return_ty: RefCell::new(None),
+            null_checks: RefCell::new(HashMap::new()),
+            cleanup_labels: RefCell::new(HashMap::new()),
+            unwrapped_pointers: RefCell::new(HashSet::new()),
             macro_expansions: RefCell::new(IndexMap::new()),
+            macro_use_sites: HashMap::new(),
+            macro_function_expansions: RefCell::new(IndexMap::new()),
+            macro_arg_substitutions: RefCell::new(HashMap::new()),
             comment_context,
             comment_store: RefCell::new(CommentStore::new()),
             spans: HashMap::new(),
+            source_locs: Vec::new(),
             sectioned_static_initializers: RefCell::new(Vec::new()),
+            xcheck_source_map: RefCell::new(Vec::new()),
+            shared_inline_fns: RefCell::new(Vec::new()),
+            linked_statics: RefCell::new(LinkedStatics::default()),
+            harness_fns: RefCell::new(vec![]),
+            fn_attributes: tcfg
+                .fn_attributes
+                .iter()
+                .map(|attr| item_attrs::parse_attribute(attr).unwrap_or_else(|e| panic!("{}", e)))
+                .collect(),
+            report: RefCell::new(report),
+            declared_c_fns: RefCell::new(HashSet::new()),
             items: RefCell::new(items),
             mod_names: RefCell::new(IndexMap::new()),
             main_file,
+            is_test_harness,
+            source_root: source_root.to_path_buf(),
             extern_crates: RefCell::new(IndexSet::new()),
             cur_file: RefCell::new(None),
         }
@@ -1139,6 +1563,108 @@ impl<'c> Translation<'c> {
         self.extern_crates.borrow_mut().insert(extern_crate);
     }
 
+    /// The C FFI type `name`, see `TypeConverter::ffi_ty`
+    fn ffi_ty(&self, name: &str) -> P<Ty> {
+        self.type_converter.borrow().ffi_ty(name)
+    }
+
+    /// Convert a boolean expression to a c_int
+    fn bool_to_int(&self, val: P<Expr>) -> P<Expr> {
+        mk().cast_expr(val, self.ffi_ty("c_int"))
+    }
+
+    /// Convert a boolean expression to the type of the C expression it comes
+    /// from, which is `int` for comparisons and logical operators in C but
+    /// `bool` in C++
+    fn bool_to_expr_type(&self, ctype: CTypeId, val: P<Expr>) -> P<Expr> {
+        match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Bool => val,
+            _ => self.bool_to_int(val),
+        }
+    }
+
+    /// Path to the C library function `name`, which clang provides as the builtin `builtin`.
+    /// Without `libc`, we declare the function ourselves with the signature of the builtin.
+    fn libc_fn_path(&self, name: &str, builtin: CDeclId) -> Result<P<Expr>, TranslationError> {
+        if let Some(shim_fn) = self.alloc_shim_fn(name) {
+            return Ok(shim_fn);
+        }
+        if !self.tcfg.emit_no_libc {
+            return Ok(mk().path_expr(vec!["libc", name]));
+        }
+        self.declare_c_fn(name, builtin)
+    }
+
+    /// Path to the C library function `name` after declaring it in an `extern "C"` block with
+    /// the signature of the builtin `builtin`
+    fn declare_c_fn(&self, name: &str, builtin: CDeclId) -> Result<P<Expr>, TranslationError> {
+        let cur_file = self.cur_file();
+        let file_id = if self.tcfg.reorganize_definitions || self.is_split_file(Some(cur_file)) {
+            cur_file
+        } else {
+            self.main_file
+        };
+        if self.declared_c_fns.borrow_mut().insert((file_id, name.to_string())) {
+            let decl = self.convert_builtin_signature(builtin)?;
+            let item = mk().pub_().fn_foreign_item(name, decl);
+            self.items
+                .borrow_mut()
+                .entry(file_id)
+                .or_insert(ItemStore::new())
+                .add_foreign_item(item);
+        }
+        Ok(mk().path_expr(vec![name]))
+    }
+
+    /// With an allocator shim, calls to the C allocation functions go to the functions of the
+    /// same name in the shim module instead
+    fn alloc_shim_fn(&self, name: &str) -> Option<P<Expr>> {
+        let shim = self.tcfg.alloc_shim.as_ref()?;
+        match name {
+            "malloc" | "calloc" | "realloc" | "free" => {
+                let mut path: Vec<&str> = shim.split("::").collect();
+                path.push(name);
+                Some(mk().path_expr(path))
+            }
+            _ => None,
+        }
+    }
+
+    /// Heap allocated temporaries need `std`
+    fn check_heap_available(&self, what: &str) -> Result<(), TranslationError> {
+        if self.tcfg.emit_no_std {
+            Err(format_err!("{} are not supported in no_std output", what).into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Signature of the function declared by a builtin, with anonymous parameters
+    fn convert_builtin_signature(&self, builtin: CDeclId) -> Result<P<FnDecl>, TranslationError> {
+        let typ = match self.ast_context[builtin].kind {
+            CDeclKind::Function { typ, .. } => typ,
+            _ => return Err(TranslationError::generic("Expected builtin function")),
+        };
+        let (ret, params, is_variadic) = match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Function(ret, ref params, is_variadic, ..) => (ret, params, is_variadic),
+            _ => return Err(TranslationError::generic("Expected builtin function type")),
+        };
+
+        let mut args = params
+            .iter()
+            .map(|param| Ok(mk().arg(self.convert_type(param.ctype)?, mk().wild_pat())))
+            .collect::<Result<Vec<_>, TranslationError>>()?;
+        if is_variadic {
+            args.push(mk().arg(mk().cvar_args_ty(), mk().wild_pat()));
+        }
+
+        let ret = match self.ast_context.resolve_type(ret.ctype).kind {
+            CTypeKind::Void => FunctionRetTy::Default(DUMMY_SP),
+            _ => FunctionRetTy::Ty(self.convert_type(ret.ctype)?),
+        };
+        Ok(mk().fn_decl(args, ret))
+    }
+
     pub fn cur_file(&self) -> FileId {
         if let Some(cur_file) = *self.cur_file.borrow() {
             cur_file
@@ -1147,6 +1673,58 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Are the items declared in this file emitted into a submodule of their
+    /// own? With `--reorganize-definitions` that holds for every file other
+    /// than the main one; with `--split-included-sources` it holds for `.c`
+    /// files pulled in through `#include`.
+    fn is_split_file(&self, file_id: Option<FileId>) -> bool {
+        let file_id = match file_id {
+            Some(file_id) if file_id != self.main_file => file_id,
+            _ => return false,
+        };
+        self.tcfg.reorganize_definitions
+            || (self.tcfg.split_included_sources && self.ast_context.is_included_source(file_id))
+    }
+
+    /// Whether the body of a function definition passes `--filter-function`
+    /// and `--filter-file`. Bindings declare every function. Static functions
+    /// have no symbol to link against, so the filters always keep them. The
+    /// filters match the C name, without `--prefix-function-names`.
+    fn is_selected_function(&self, decl_id: CDeclId, name: &str) -> bool {
+        if self.tcfg.emit_bindings_only {
+            return false;
+        }
+        if let CDeclKind::Function { is_global: false, .. } = self.ast_context[decl_id].kind {
+            return true;
+        }
+        let name = self.c_function_name(name);
+        if let Some(ref filter) = self.tcfg.function_filter {
+            if !filter.is_match(name) {
+                return false;
+            }
+        }
+        if let Some(ref filter) = self.tcfg.file_filter {
+            let decl = &self.ast_context[decl_id];
+            let path = self
+                .ast_context
+                .file_id(decl)
+                .and_then(|id| self.ast_context.get_file_path(id));
+            match path {
+                Some(path) if filter.is_match(&path.to_string_lossy()) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// The name of a function as written in C, without `--prefix-function-names`
+    fn c_function_name<'a>(&self, name: &'a str) -> &'a str {
+        match self.tcfg.prefix_function_names {
+            Some(ref prefix) if name.starts_with(prefix.as_str()) => &name[prefix.len()..],
+            _ => name,
+        }
+    }
+
     fn with_cur_file_item_store<F, T>(&self, f: F) -> T
         where F: FnOnce(&mut ItemStore) -> T
     {
@@ -1166,18 +1744,10 @@ impl<'c> Translation<'c> {
         let mut features = vec![];
         features.extend(self.features.borrow().iter());
         features.extend(self.type_converter.borrow().features_used());
-        let mut pragmas: PragmaVec = vec![(
-            "allow",
-            vec![
-                "non_upper_case_globals",
-                "non_camel_case_types",
-                "non_snake_case",
-                "dead_code",
-                "mutable_transmutes",
-                "unused_mut",
-                "unused_assignments",
-            ],
-        )];
+        let mut pragmas: PragmaVec = vec![];
+        if self.tcfg.lint_allow_mode == LintAllowMode::Crate {
+            pragmas.push(("allow", item_attrs::ALLOWED_LINTS.to_vec()));
+        }
         if self.tcfg.cross_checks {
             features.append(&mut vec!["plugin"]);
             pragmas.push(("cross_check", vec!["yes"]));
@@ -1211,6 +1781,79 @@ impl<'c> Translation<'c> {
         mk().mac_expr(mk().mac(vec![macro_name], macro_msg, MacDelimiter::Parenthesis))
     }
 
+    /// Import a declaration from the `-sys` crate given with `--sys-crate` instead
+    /// of translating it, if the crate provides it. Only declarations of items
+    /// defined elsewhere and types are imported, never function definitions.
+    fn import_from_sys_crate(&self, decl_id: CDeclId) -> Option<P<Item>> {
+        let sys_crate = self.tcfg.sys_crate.as_ref()?;
+        let kind = &self.ast_context[decl_id].kind;
+        let rust_name = match kind {
+            CDeclKind::Struct { .. }
+            | CDeclKind::Union { .. }
+            | CDeclKind::Enum { .. }
+            | CDeclKind::Typedef { .. } => {
+                self.type_converter.borrow().resolve_decl_name(decl_id)?
+            }
+            CDeclKind::Function { body: None, .. }
+            | CDeclKind::Variable { is_defn: false, .. } => self.renamer.borrow().get(&decl_id)?,
+            _ => return None,
+        };
+        // Anonymous types named by a typedef go by the typedef's name
+        let c_name = kind.get_name().cloned().unwrap_or_else(|| rust_name.clone());
+        if !sys_crate.provides(&c_name) {
+            return None;
+        }
+
+        let path = vec!["".to_string(), sys_crate.ident(), c_name.clone()];
+        let rename = if rust_name != c_name { Some(rust_name) } else { None };
+        Some(mk().pub_().use_simple_item(path, rename))
+    }
+
+    /// Add a declaration we emitted Rust for to the report, along with its
+    /// new name if it had to be renamed
+    fn record_translated(&self, decl_id: CDeclId) {
+        let kind = &self.ast_context[decl_id].kind;
+        let c_name = kind.get_name().cloned();
+        let rust_name = match kind {
+            CDeclKind::Struct { .. }
+            | CDeclKind::Union { .. }
+            | CDeclKind::Enum { .. }
+            | CDeclKind::Typedef { .. } => {
+                self.type_converter.borrow().resolve_decl_name(decl_id)
+            }
+            _ => self.renamer.borrow().get(&decl_id),
+        };
+
+        let mut report = self.report.borrow_mut();
+        if let CDeclKind::Union { .. } = kind {
+            report.features.unions += 1;
+        }
+        if let (Some(c_name), Some(rust_name)) = (&c_name, &rust_name) {
+            if c_name != rust_name {
+                report.renames.push(Rename {
+                    c_name: c_name.clone(),
+                    rust_name: rust_name.clone(),
+                });
+            }
+        }
+        report.translated.push(TranslatedItem {
+            kind: decl_kind_name(kind),
+            c_name,
+            rust_name,
+        });
+    }
+
+    /// Count a `goto` statement in the report
+    pub fn record_goto(&self) {
+        self.report.borrow_mut().features.gotos += 1;
+    }
+
+    /// `#[linkage = "extern_weak"]` only applies to pointer-typed statics, so
+    /// weak declarations are translated as ordinary ones and only reported
+    fn record_weak_reference(&self) {
+        self.report.borrow_mut().features.weak_references += 1;
+    }
+
     fn mk_cross_check(&self, mk: Builder, args: Vec<&str>) -> Builder {
         if self.tcfg.cross_checks {
             mk.call_attr("cross_check", args)
@@ -1264,6 +1907,41 @@ impl<'c> Translation<'c> {
         false
     }
 
+    /// Fold the initializer of an integer static that is a constant
+    /// expression into its value. The translation of operators, enum
+    /// constants and `sizeof` isn't always valid in a Rust constant. Plain
+    /// literals, and initializers that use translated macros and are valid
+    /// anyway, are left alone. Returns the value and the C expression.
+    fn fold_static_initializer(
+        &self,
+        initializer: Option<CExprId>,
+        qtype: CQualTypeId,
+    ) -> Option<(ConstIntExpr, String)> {
+        let expr_id = initializer?;
+        let kind = &self.ast_context.resolve_type(qtype.ctype).kind;
+        if !kind.is_integral_type() || *kind == CTypeKind::Bool {
+            return None;
+        }
+        if let CExprKind::Literal(..) = self.ast_context.resolve_expr(expr_id).1 {
+            return None;
+        }
+        let uses_macro = self.tcfg.translate_const_macros
+            && DFExpr::new(&self.ast_context, expr_id.into()).any(|node| match node {
+                SomeId::Expr(expr_id) => self.ast_context.macro_invocations.contains_key(&expr_id),
+                _ => false,
+            });
+        if uses_macro && !self.static_initializer_is_uncompilable(initializer, qtype) {
+            return None;
+        }
+
+        let value = self.ast_context.eval_int_const(expr_id)?;
+        let mut c_expr = vec![];
+        Printer::new(&mut c_expr)
+            .print_expr(expr_id, &self.ast_context)
+            .ok()?;
+        Some((value, String::from_utf8_lossy(&c_expr).into_owned()))
+    }
+
     /// The purpose of this function is to decide on whether or not a static initializer's
     /// translation is able to be compiled as a valid rust static initializer
     fn static_initializer_is_uncompilable(&self, expr_id: Option<CExprId>, qtype: CQualTypeId) -> bool {
@@ -1277,9 +1955,11 @@ impl<'c> Translation<'c> {
         };
 
         // The f128 crate doesn't currently provide a way to const initialize
-        // values, except for common mathematical constants
-        if let CTypeKind::LongDouble = self.ast_context[qtype.ctype].kind {
-            return true;
+        // values, except for common mathematical constants, and `half::f16`
+        // values are built with `half::f16::from_f64`, which isn't const either
+        match self.ast_context[qtype.ctype].kind {
+            CTypeKind::LongDouble | CTypeKind::Half => return true,
+            _ => {}
         }
 
         let iter = DFExpr::new(&self.ast_context, expr_id.into());
@@ -1417,32 +2097,96 @@ impl<'c> Translation<'c> {
             .extern_("C")
             .fn_item(&fn_name, fn_decl.clone(), fn_block);
 
+        let static_item = self.mk_init_section_static(
+            "INIT_ARRAY",
+            &fn_name,
+            fn_decl,
+            InitSection::Init,
+        );
+
+        (fn_item, static_item)
+    }
+
+    /// Build a static array holding a single function pointer, placed in the
+    /// platform-specific section that the loader walks at program startup
+    /// (`InitSection::Init`) or exit (`InitSection::Fini`).
+    fn mk_init_section_static(
+        &self,
+        static_name: &str,
+        fn_name: &str,
+        fn_decl: P<FnDecl>,
+        section: InitSection,
+    ) -> P<Item> {
+        let (linux, windows, macos) = match section {
+            InitSection::Init => (".init_array", ".CRT$XIB", "__DATA,__mod_init_func"),
+            InitSection::Fini => (".fini_array", ".CRT$XTU", "__DATA,__mod_term_func"),
+        };
+        let linux = format!("link_section = \"{}\"", linux);
+        let windows = format!("link_section = \"{}\"", windows);
+        let macos = format!("link_section = \"{}\"", macos);
         let static_attributes = mk()
             .single_attr("used")
-            .call_attr(
-                "cfg_attr",
-                vec!["target_os = \"linux\"", "link_section = \".init_array\""],
-            )
-            .call_attr(
-                "cfg_attr",
-                vec!["target_os = \"windows\"", "link_section = \".CRT$XIB\""],
-            )
-            .call_attr(
-                "cfg_attr",
-                vec![
-                    "target_os = \"macos\"",
-                    "link_section = \"__DATA,__mod_init_func\"",
-                ],
-            );
+            .call_attr("cfg_attr", vec!["target_os = \"linux\"", linux.as_str()])
+            .call_attr("cfg_attr", vec!["target_os = \"windows\"", windows.as_str()])
+            .call_attr("cfg_attr", vec!["target_os = \"macos\"", macos.as_str()]);
         let static_array_size = mk().lit_expr(mk().int_lit(1, LitIntType::Unsuffixed));
         let static_ty = mk().array_ty(
             mk().unsafe_().extern_("C").barefn_ty(fn_decl),
             static_array_size,
         );
         let static_val = mk().array_expr(vec![mk().path_expr(vec![fn_name])]);
-        let static_item = static_attributes.static_item("INIT_ARRAY", static_ty, static_val);
+        static_attributes.static_item(static_name, static_ty, static_val)
+    }
 
-        (fn_item, static_item)
+    /// Register a translated `__attribute__((constructor))` or
+    /// `__attribute__((destructor))` function with the loader so that it
+    /// keeps running before `main` or at exit, as it did in C.
+    fn convert_ctor_dtor(
+        &self,
+        converted: ConvertedDecl,
+        new_name: &str,
+        attrs: &IndexSet<c_ast::Attribute>,
+    ) -> ConvertedDecl {
+        let mut sections = vec![];
+        if attrs.contains(&c_ast::Attribute::Constructor) {
+            sections.push((InitSection::Init, "C2RUST_CTOR"));
+        }
+        if attrs.contains(&c_ast::Attribute::Destructor) {
+            sections.push((InitSection::Fini, "C2RUST_DTOR"));
+        }
+
+        let mut items = match converted {
+            _ if sections.is_empty() => return converted,
+            ConvertedDecl::Item(item) => vec![item],
+            ConvertedDecl::Items(items) => items,
+            // Declarations without a body are registered where they are defined
+            other => return other,
+        };
+
+        for (section, prefix) in sections {
+            let static_name = self
+                .renamer
+                .borrow_mut()
+                .pick_name(&format!("{}_{}", prefix, new_name));
+            let fn_decl = mk().fn_decl(vec![], FunctionRetTy::Default(DUMMY_SP));
+            info!("Registering {} in {:?} section", new_name, section);
+            items.push(self.mk_init_section_static(&static_name, new_name, fn_decl, section));
+        }
+
+        ConvertedDecl::Items(items)
+    }
+
+    /// Report a declaration that failed to translate by its name, pointing at
+    /// its C source after the location of the error itself
+    fn report_failed_decl(&self, decl_id: CDeclId, e: TranslationError) {
+        let decl = &self.ast_context[decl_id];
+        let e = e.add_loc(self.ast_context.display_loc(&decl.loc));
+        let msg = match decl.kind.get_name() {
+            Some(name) => format!("Failed to translate {}: {}", name, e),
+            None => format!("Failed to translate declaration: {}", e),
+        };
+        translate_failure(&self.tcfg, &msg);
+        self.record_skipped(decl_id, e.kind().name(), e.message(), false);
     }
 
     fn convert_decl(
@@ -1450,6 +2194,23 @@ impl<'c> Translation<'c> {
         ctx: ExprContext,
         decl_id: CDeclId,
     ) -> Result<ConvertedDecl, TranslationError> {
+        let hooks = &self.tcfg.hooks;
+        if let Some(converted) = hooks.before_decl(&self.ast_context, decl_id) {
+            return Ok(converted);
+        }
+        let converted = self.convert_decl_unhooked(ctx, decl_id)?;
+        Ok(hooks.after_decl(&self.ast_context, decl_id, converted))
+    }
+
+    fn convert_decl_unhooked(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        if let Some(item) = self.import_from_sys_crate(decl_id) {
+            return Ok(ConvertedDecl::Item(item));
+        }
+
         let decl = self
             .ast_context
             .get_decl(&decl_id)
@@ -1517,7 +2278,8 @@ impl<'c> Translation<'c> {
                 let field_entries =
                     self.convert_struct_fields(decl_id, fields, platform_byte_size)?;
 
-                let mut derives = vec!["Copy", "Clone"];
+                let mut derives = self.record_derives(decl_id);
+                derives.extend(self.policy_derives(decl_id));
                 let has_bitfields = fields
                     .iter()
                     .any(|field_id| match self.ast_context.index(*field_id).kind {
@@ -1542,8 +2304,12 @@ impl<'c> Translation<'c> {
                     Some(mf) if mf > 1 => reprs.push(int_arg_metaitem("packed", mf as u128)),
                     _ => { }
                 }
+                if let Some(alignment) = self.int128_record_alignment(decl_id) {
+                    reprs.push(int_arg_metaitem("align", alignment as u128));
+                }
 
-                if let Some(alignment) = manual_alignment {
+                let default_impl = self.convert_default_impl(decl_id, &name);
+                let mut structs = if let Some(alignment) = manual_alignment {
                     // This is the most complicated case: we have `align(N)` which
                     // might be mixed with or included into a `packed` structure,
                     // which Rust doesn't currently support; instead, we split
@@ -1562,9 +2328,9 @@ impl<'c> Translation<'c> {
                     let inner_name = self.resolve_decl_inner_name(decl_id);
                     let inner_ty = mk().path_ty(vec![inner_name.clone()]);
                     let inner_repr_attr = mk().meta_item(vec!["repr"], MetaItemKind::List(reprs));
-                    let inner_struct = mk().span(s)
-                        .pub_()
-                        .call_attr("derive", derives)
+                    let mut outer_derives = self.record_derives(decl_id);
+                    outer_derives.extend(self.policy_derives(decl_id));
+                    let inner_struct = derive_attr(mk().span(s).pub_(), derives)
                         .meta_item_attr(AttrStyle::Outer, inner_repr_attr)
                         .struct_item(inner_name.clone(), field_entries, false);
 
@@ -1577,9 +2343,7 @@ impl<'c> Translation<'c> {
                     ];
                     let repr_attr = mk().meta_item(vec!["repr"], MetaItemKind::List(outer_reprs));
                     let outer_field = mk().pub_().enum_field(mk().ident_ty(inner_name));
-                    let outer_struct = mk().span(s)
-                        .pub_()
-                        .call_attr("derive", vec!["Copy", "Clone"])
+                    let outer_struct = derive_attr(mk().span(s).pub_(), outer_derives)
                         .meta_item_attr(AttrStyle::Outer, repr_attr)
                         .struct_item(name, vec![outer_field], true);
 
@@ -1597,23 +2361,36 @@ impl<'c> Translation<'c> {
                         .call_attr("allow", vec!["dead_code", "non_upper_case_globals"])
                         .const_item(padding_name, padding_ty, padding_value);
 
-                    let structs = vec![outer_struct, inner_struct, padding_const];
-                    Ok(ConvertedDecl::Items(structs))
+                    vec![outer_struct, inner_struct, padding_const]
                 } else {
                     assert!(!self.ast_context.has_inner_struct_decl(decl_id));
                     let repr_attr = mk().meta_item(vec!["repr"], MetaItemKind::List(reprs));
-                    Ok(ConvertedDecl::Item(
-                        mk().span(s)
-                            .pub_()
-                            .call_attr("derive", derives)
-                            .meta_item_attr(AttrStyle::Outer, repr_attr)
-                            .struct_item(name, field_entries, false),
-                    ))
+                    vec![derive_attr(mk().span(s).pub_(), derives)
+                        .meta_item_attr(AttrStyle::Outer, repr_attr)
+                        .struct_item(name, field_entries, false)]
+                };
+                structs.extend(default_impl);
+
+                // Structs crossing the FFI boundary by value must match the C ABI exactly,
+                // down to their field offsets. So must all structs on MSVC targets, whose
+                // layout rules for aligned fields of packed structs and zero-sized fields
+                // differ from the ones our struct translation is modeled on.
+                let check_abi = self.abi_checked_structs.contains(&decl_id)
+                    || self.ast_context.is_msvc_target();
+                if check_abi || self.tcfg.emit_layout_assertions {
+                    structs.extend(self.convert_record_layout_assertions(decl_id, check_abi)?);
+                }
+
+                if structs.len() == 1 {
+                    Ok(ConvertedDecl::Item(structs.pop().unwrap()))
+                } else {
+                    Ok(ConvertedDecl::Items(structs))
                 }
             }
 
             CDeclKind::Union {
                 fields: Some(ref fields),
+                manual_alignment,
                 ..
             } => {
                 let name = self
@@ -1623,6 +2400,7 @@ impl<'c> Translation<'c> {
                     .unwrap();
 
                 let mut field_syns = vec![];
+                let mut accessor_fields = vec![];
                 for &x in fields {
                     let field_decl = self.ast_context.index(x);
                     match field_decl.kind {
@@ -1632,6 +2410,9 @@ impl<'c> Translation<'c> {
                                 .borrow_mut()
                                 .declare_field_name(decl_id, x, name);
                             let typ = self.convert_type(typ.ctype)?;
+                            if self.tcfg.union_accessors {
+                                accessor_fields.push((name.clone(), typ.clone()));
+                            }
                             field_syns.push(mk().pub_().struct_field(name, typ))
                         }
                         _ => {
@@ -1642,30 +2423,53 @@ impl<'c> Translation<'c> {
                     }
                 }
 
-                Ok(if field_syns.is_empty() {
+                let mut reprs = vec![simple_metaitem("C")];
+                if let Some(alignment) = manual_alignment.or(self.int128_record_alignment(decl_id)) {
+                    reprs.push(int_arg_metaitem("align", alignment as u128));
+                }
+                let repr_attr = mk().meta_item(vec!["repr"], MetaItemKind::List(reprs));
+
+                let is_empty = field_syns.is_empty();
+                let union_item = if is_empty {
                     // Empty unions are a GNU extension, but Rust doesn't allow empty unions.
-                    ConvertedDecl::Item(
-                        mk().span(s)
-                            .pub_()
-                            .call_attr("derive", vec!["Copy", "Clone"])
-                            .call_attr("repr", vec!["C"])
-                            .struct_item(name, vec![], false),
-                    )
+                    mk().span(s)
+                        .pub_()
+                        .call_attr("derive", vec!["Copy", "Clone"])
+                        .meta_item_attr(AttrStyle::Outer, repr_attr)
+                        .struct_item(&name, vec![], false)
                 } else {
-                    ConvertedDecl::Item(
-                        mk().span(s)
-                            .pub_()
-                            .call_attr("derive", vec!["Copy", "Clone"])
-                            .call_attr("repr", vec!["C"])
-                            .union_item(name, field_syns),
-                    )
-                })
+                    mk().span(s)
+                        .pub_()
+                        .call_attr("derive", vec!["Copy", "Clone"])
+                        .meta_item_attr(AttrStyle::Outer, repr_attr)
+                        .union_item(&name, field_syns)
+                };
+
+                let mut items = vec![union_item];
+                items.extend(self.convert_default_impl(decl_id, &name));
+                if self.tcfg.union_accessors && !is_empty {
+                    items.push(self.convert_union_accessors(&name, accessor_fields));
+                }
+                if self.tcfg.emit_layout_assertions || self.ast_context.is_msvc_target() {
+                    items.extend(self.convert_record_layout_assertions(decl_id, false)?);
+                }
+
+                if items.len() == 1 {
+                    Ok(ConvertedDecl::Item(items.pop().unwrap()))
+                } else {
+                    Ok(ConvertedDecl::Items(items))
+                }
             }
 
             CDeclKind::Field { .. } => Err(TranslationError::generic(
                 "Field declarations should be handled inside structs/unions",
             )),
 
+            // Anonymous enums that are only used for their constants don't need a type
+            CDeclKind::Enum { .. } if self.is_anonymous_constant_enum(decl_id) => {
+                Ok(ConvertedDecl::NoItem)
+            }
+
             CDeclKind::Enum {
                 integral_type: Some(integral_type),
                 ..
@@ -1693,10 +2497,6 @@ impl<'c> Translation<'c> {
                     .borrow()
                     .resolve_decl_name(enum_id)
                     .expect("Enums should already be renamed");
-                if let Some(cur_file) = *self.cur_file.borrow() {
-                    self.add_import(cur_file, enum_id, &enum_name);
-                }
-                let ty = mk().path_ty(mk().path(vec![enum_name]));
                 let val = match value {
                     ConstIntExpr::I(value) => signed_int_expr(value),
                     ConstIntExpr::U(value) => {
@@ -1704,6 +2504,29 @@ impl<'c> Translation<'c> {
                     }
                 };
 
+                if self.is_anonymous_constant_enum(enum_id) {
+                    // Use the underlying integral type directly, but keep track of which
+                    // constants were declared together so that later passes can regroup them
+                    let integral_type = match self.ast_context[enum_id].kind {
+                        CDeclKind::Enum { integral_type: Some(integral_type), .. } => {
+                            integral_type
+                        }
+                        _ => return Err(TranslationError::generic("Enum without integral type")),
+                    };
+                    let ty = self.convert_type(integral_type.ctype)?;
+                    return Ok(ConvertedDecl::Item(
+                        mk().span(s)
+                            .pub_()
+                            .str_attr(vec!["c2rust", "enum_group"], enum_name)
+                            .const_item(name, ty, val),
+                    ));
+                }
+
+                if let Some(cur_file) = *self.cur_file.borrow() {
+                    self.add_import(cur_file, enum_id, &enum_name);
+                }
+                let ty = mk().path_ty(mk().path(vec![enum_name]));
+
                 Ok(ConvertedDecl::Item(
                     mk().span(s).pub_().const_item(name, ty, val),
                 ))
@@ -1736,7 +2559,9 @@ impl<'c> Translation<'c> {
 
                 let (ret, is_var): (Option<CQualTypeId>, bool) =
                     match self.ast_context.resolve_type(typ).kind {
-                        CTypeKind::Function(ret, _, is_var, is_noreturn, _) => {
+                        CTypeKind::Function(ret, _, is_var, is_noreturn, ..) => {
+                            let is_noreturn =
+                                is_noreturn || attrs.contains(&c_ast::Attribute::NoReturn);
                             (if is_noreturn { None } else { Some(ret) }, is_var)
                         }
                         ref k => {
@@ -1764,18 +2589,54 @@ impl<'c> Translation<'c> {
 
                 let is_main = self.ast_context.c_main == Some(decl_id);
 
+                // Functions left out by the filters, and fortified wrappers, are
+                // declared, not defined
+                let body = body.filter(|_| {
+                    self.is_selected_function(decl_id, name) && !self.is_fortified_wrapper(decl_id)
+                });
+
                 let converted_function = self.convert_function(
-                    ctx, s, is_global, is_inline, is_main, is_var, is_extern,
+                    ctx, s, decl_id, is_global, is_inline, is_main, is_var, is_extern,
                     new_name, name, &args, ret, body, attrs,
                 );
 
-                converted_function.or_else(|e| match self.tcfg.replace_unsupported_decls {
-                    ReplaceMode::Extern if body.is_none() => self.convert_function(
-                        ctx, s, is_global, false, is_main, is_var, is_extern,
-                        new_name, name, &args, ret, None, attrs,
-                    ),
-                    _ => Err(e),
-                })
+                let converted_function = converted_function.or_else(|e| {
+                    match self.tcfg.replace_unsupported_decls {
+                        ReplaceMode::Extern if body.is_none() => self.convert_function(
+                            ctx, s, decl_id, is_global, false, is_main, is_var, is_extern,
+                            new_name, name, &args, ret, None, attrs,
+                        ),
+                        // Functions using extensions we can't translate, like block
+                        // literals, are declared so the C definition can still be linked
+                        ReplaceMode::Extern if e.kind() == TranslationErrorKind::UnsupportedExtension => {
+                            warn!("Declaring {} as extern: {}", name, e.message());
+                            self.record_skipped(decl_id, e.kind().name(), e.message(), true);
+                            self.convert_function(
+                                ctx, s, decl_id, is_global, false, is_main, is_var, is_extern,
+                                new_name, name, &args, ret, None, attrs,
+                            )
+                        }
+                        _ => Err(e),
+                    }
+                })?;
+
+                if self.tcfg.cross_checks && body.is_some() {
+                    let c_function = if is_main { "main" } else { name.as_str() };
+                    let decl = &self.ast_context[decl_id];
+                    self.xcheck_source_map.borrow_mut().push(XCheckSourceEntry {
+                        tag: djb2_hash(c_function),
+                        c_function: c_function.to_string(),
+                        c_file: self
+                            .ast_context
+                            .file_id(decl)
+                            .and_then(|id| self.ast_context.get_file_path(id))
+                            .map(path::Path::to_path_buf),
+                        c_line: decl.loc.map(|loc| loc.begin_line),
+                        rust_function: new_name.to_string(),
+                    });
+                }
+
+                Ok(self.convert_ctor_dtor(converted_function, new_name, attrs))
             }
 
             CDeclKind::Typedef { ref typ, .. } => {
@@ -1805,24 +2666,25 @@ impl<'c> Translation<'c> {
                 ))
             }
 
-            // Externally-visible variable without initializer (definition elsewhere)
+            // Externally-visible variable without initializer (definition elsewhere), or any
+            // externally-visible variable when only emitting bindings
             CDeclKind::Variable {
                 is_externally_visible: true,
                 has_static_duration,
                 has_thread_duration,
-                is_defn: false,
+                is_defn,
                 ref ident,
                 initializer,
                 typ,
                 ref attrs,
                 ..
-            } => {
+            } if !is_defn || self.tcfg.emit_bindings_only => {
                 assert!(
                     has_static_duration || has_thread_duration,
                     "An extern variable must be static or thread-local"
                 );
                 assert!(
-                    initializer.is_none(),
+                    is_defn || initializer.is_none(),
                     "An extern variable that isn't a definition can't have an initializer"
                 );
 
@@ -1836,8 +2698,12 @@ impl<'c> Translation<'c> {
                     .get(&decl_id)
                     .expect("Variables should already be renamed");
                 let (ty, mutbl, _) = self.convert_variable(ctx.static_(), None, typ)?;
-                // When putting extern statics into submodules, they need to be public to be accessible
-                let visibility = if self.tcfg.reorganize_definitions {
+                // When putting extern statics into submodules, they need to be public to be accessible,
+                // and bindings are the public API
+                let visibility = if self.tcfg.reorganize_definitions
+                    || self.tcfg.split_included_sources
+                    || self.tcfg.emit_bindings_only
+                {
                     "pub"
                 } else {
                     ""
@@ -1855,6 +2721,10 @@ impl<'c> Translation<'c> {
                         c_ast::Attribute::Alias(aliasee) => {
                             extern_item.str_attr("link_name", aliasee)
                         }
+                        c_ast::Attribute::Weak => {
+                            self.record_weak_reference();
+                            continue;
+                        }
                         _ => continue,
                     };
                 }
@@ -1875,19 +2745,51 @@ impl<'c> Translation<'c> {
                 ref attrs,
                 ..
             } if has_static_duration || has_thread_duration => {
-                if has_thread_duration {
-                    self.use_feature("thread_local");
-                }
-
                 let new_name = &self
                     .renamer
                     .borrow()
                     .get(&decl_id)
                     .expect("Variables should already be renamed");
 
+                if self.is_thread_local_macro_var(&decl.kind) {
+                    return self.convert_thread_local_macro(
+                        ctx,
+                        s,
+                        new_name,
+                        is_externally_visible,
+                        initializer,
+                        typ,
+                    );
+                }
+
+                if has_thread_duration {
+                    self.use_feature("thread_local");
+                }
+
                 // Collect problematic static initializers and offload them to sections for the linker
                 // to initialize for us
-                let (ty, init) = if self.static_initializer_is_uncompilable(initializer, typ) {
+                let (ty, init) = if let Some((value, c_expr)) =
+                    self.fold_static_initializer(initializer, typ)
+                {
+                    let comment = format!("// {}", c_expr);
+                    let comment_pos = if s.is_dummy() {
+                        None
+                    } else {
+                        Some(s.lo())
+                    };
+                    s = self
+                        .comment_store
+                        .borrow_mut()
+                        .extend_existing_comments(
+                            &[comment],
+                            comment_pos,
+                            CommentStyle::Isolated,
+                        )
+                        .map(pos_to_span)
+                        .unwrap_or(s);
+
+                    (self.convert_type(typ.ctype)?, self.convert_constant(value)?)
+                } else if self.static_initializer_is_uncompilable(initializer, typ) {
                     // Note: We don't pass has_static_duration through here. Extracted initializers
                     // are run outside of the static initializer.
                     let (ty, _, init) =
@@ -1931,7 +2833,10 @@ impl<'c> Translation<'c> {
                     (ty, init)
                 };
 
-                let static_def = if is_externally_visible {
+                let static_def = if is_externally_visible && self.is_hidden_symbol(decl_id) {
+                    // Other modules import it from this one
+                    mk().vis("pub(crate)")
+                } else if is_externally_visible {
                     mk_linkage(false, new_name, ident).pub_().extern_("C")
                 } else if self.cur_file.borrow().is_some() {
                     mk().pub_()
@@ -1953,6 +2858,10 @@ impl<'c> Translation<'c> {
                         c_ast::Attribute::Section(name) => {
                             static_def.str_attr("link_section", name)
                         }
+                        c_ast::Attribute::Weak => {
+                            self.use_feature("linkage");
+                            static_def.str_attr("linkage", "weak")
+                        }
                         _ => continue,
                     }
                 }
@@ -1975,10 +2884,14 @@ impl<'c> Translation<'c> {
 
                 trace!("Expanding macro {:?}: {:?}", decl_id, self.ast_context[decl_id]);
 
-                let maybe_replacement = self.canonical_macro_replacement(
-                    ctx.set_const(true).set_expanding_macro(decl_id),
-                    &self.ast_context.macro_expansions[&decl_id],
-                );
+                let ctx = ctx.set_const(true).set_expanding_macro(decl_id);
+                let maybe_replacement = match self.macro_use_sites.get(&decl_id) {
+                    Some(sites) => self.use_site_macro_replacement(ctx, sites),
+                    None => self.canonical_macro_replacement(
+                        ctx,
+                        &self.ast_context.macro_expansions[&decl_id],
+                    ),
+                };
 
                 match maybe_replacement {
                     Ok((replacement, ty)) => {
@@ -2002,8 +2915,9 @@ impl<'c> Translation<'c> {
                 }
             }
 
-            // We aren't doing anything with the definitions of function-like
-            // macros yet.
+            CDeclKind::MacroFunction { .. } if self.tcfg.translate_fn_macros => {
+                self.convert_macro_function(ctx, decl_id, s)
+            }
             CDeclKind::MacroFunction { .. } => Ok(ConvertedDecl::NoItem),
 
             // Do not translate non-canonical decls. They will be translated at
@@ -2012,6 +2926,84 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Is this a thread-local variable definition that we emit inside a
+    /// `thread_local!` block rather than as a `#[thread_local]` static?
+    /// Extern declarations always use the attribute since the macro does not
+    /// produce a linkable symbol, and `no_std` output has no `thread_local!`.
+    fn is_thread_local_macro_var(&self, decl: &CDeclKind) -> bool {
+        match decl {
+            CDeclKind::Variable {
+                has_thread_duration: true,
+                is_defn: true,
+                ..
+            } => self.tcfg.thread_local_mode == ThreadLocalMode::Macro && !self.tcfg.emit_no_std,
+            _ => false,
+        }
+    }
+
+    /// Emit `thread_local! { static NAME: UnsafeCell<T> = UnsafeCell::new(init); }`.
+    /// References to the variable go through `thread_local_access`.
+    fn convert_thread_local_macro(
+        &self,
+        ctx: ExprContext,
+        span: Span,
+        name: &str,
+        is_externally_visible: bool,
+        initializer: Option<CExprId>,
+        typ: CQualTypeId,
+    ) -> Result<ConvertedDecl, TranslationError> {
+        // The initializer of a `thread_local!` is evaluated lazily on first
+        // access in each thread, so it does not need to be a constant.
+        let (ty, _, init) = self.convert_variable(ctx.not_static(), initializer, typ)?;
+        let init = init?
+            .to_unsafe_pure_expr()
+            .ok_or_else(|| format_err!("Expected no side-effects in thread-local initializer"))?;
+
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let cell_ty = mk().path_ty(vec![
+            mk().path_segment(""),
+            mk().path_segment(std_or_core),
+            mk().path_segment("cell"),
+            mk().path_segment_with_args("UnsafeCell", mk().angle_bracketed_args(vec![ty])),
+        ]);
+        let cell_new = mk().path_expr(vec!["", std_or_core, "cell", "UnsafeCell", "new"]);
+        let cell_init = mk().call_expr(cell_new, vec![init]);
+
+        let static_def = if is_externally_visible || self.cur_file.borrow().is_some() {
+            mk().pub_()
+        } else {
+            mk()
+        };
+        let static_item = static_def.static_item(name, cell_ty, cell_init);
+
+        let tokens = vec![TokenTree::token(
+            token::Interpolated(Rc::new(Nonterminal::NtItem(static_item))),
+            DUMMY_SP,
+        )]
+        .into_iter()
+        .collect::<TokenStream>();
+        let mac = mk().mac(vec!["thread_local"], tokens, MacDelimiter::Brace);
+
+        // Keep the variable name on the item so that header reorganization
+        // can still generate imports for it.
+        let mut item = mk().span(span).mac_item(mac);
+        item.ident = Ident::from_str(name);
+
+        Ok(ConvertedDecl::Item(item))
+    }
+
+    /// Rewrite a reference to a `thread_local!` variable into the place
+    /// `*NAME.with(|v| v.get())`, which is usable both as an lvalue and an
+    /// rvalue for the lifetime of the current thread.
+    fn thread_local_access(&self, val: P<Expr>) -> P<Expr> {
+        let arg = mk().arg(mk().infer_ty(), mk().ident_pat("v"));
+        let decl = mk().fn_decl(vec![arg], FunctionRetTy::Default(DUMMY_SP));
+        let body = mk().method_call_expr(mk().ident_expr("v"), "get", vec![] as Vec<P<Expr>>);
+        let closure = mk().closure_expr(CaptureBy::Ref, Movability::Movable, decl, body);
+        let ptr = mk().method_call_expr(val, "with", vec![closure]);
+        mk().unary_expr(ast::UnOp::Deref, ptr)
+    }
+
     fn canonical_macro_replacement(
         &self,
         ctx: ExprContext,
@@ -2059,10 +3051,29 @@ impl<'c> Translation<'c> {
         // common type to minimize casts.
     }
 
+    /// Translate a macro at the type all of its uses convert it to, see
+    /// `TypedAstContext::macro_use_sites`.
+    fn use_site_macro_replacement(
+        &self,
+        ctx: ExprContext,
+        sites: &[CExprId],
+    ) -> Result<(P<Expr>, CTypeId), TranslationError> {
+        let site = *sites
+            .first()
+            .ok_or_else(|| format_err!("Could not find a valid type for macro"))?;
+        let ty = self.ast_context[site].kind.get_type()
+            .ok_or_else(|| format_err!("Invalid expression type"))?;
+        let val = self.convert_expr(ctx, site)?;
+
+        val.to_unsafe_pure_expr().map(|val| (val, ty))
+            .ok_or_else(|| TranslationError::generic("Macro expansion is not a pure expression"))
+    }
+
     fn convert_function(
         &self,
         ctx: ExprContext,
         span: Span,
+        decl_id: CDeclId,
         is_global: bool,
         is_inline: bool,
         is_main: bool,
@@ -2079,16 +3090,31 @@ impl<'c> Translation<'c> {
 
         self.with_scope(|| {
             let mut args: Vec<Param> = vec![];
+            let mut restrict_args = vec![];
+            let mut volatile_args = vec![];
+            let mut mapped_params = vec![];
+            let mut option_params = vec![];
+
+            // The type map only changes the signatures of the functions it
+            // translates, whose callers it can adapt
+            if body.is_some() {
+                self.check_mapped_signature(name, arguments, return_type)?;
+            }
+            let mapped_ty = |ctype| body.and_then(|_| self.mapped_signature_ty(name, ctype));
 
             // handle regular (non-variadic) arguments
             for &(decl_id, ref var, typ) in arguments {
-                let (ty, mutbl, _) = self.convert_variable(ctx, None, typ)?;
+                let (c_ty, mutbl, _) = self.convert_variable(ctx, None, typ)?;
+                let mapped = mapped_ty(typ.ctype);
+                let ty = mapped.clone().unwrap_or_else(|| c_ty.clone());
 
                 let pat = if var.is_empty() {
                     mk().wild_pat()
                 } else {
-                    // extern function declarations don't support/require mut patterns
-                    let mutbl = if body.is_none() {
+                    // extern function declarations don't support/require mut patterns,
+                    // and replaced parameters are shadowed by mutable locals
+                    let var_mutbl = mutbl;
+                    let mutbl = if body.is_none() || mapped.is_some() {
                         Mutability::Immutable
                     } else {
                         mutbl
@@ -2097,12 +3123,32 @@ impl<'c> Translation<'c> {
                     let new_var = self
                         .renamer
                         .borrow_mut()
-                        .insert(decl_id, var.as_str())
+                        .insert(decl_id, &self.tcfg.naming.value_case.apply(var))
                         .expect(&format!(
                             "Failed to insert argument '{}' while converting '{}'",
                             var, name
                         ));
 
+                    if self.ast_context.resolve_qualifiers(typ).is_restrict {
+                        restrict_args.push(new_var.clone());
+                    }
+                    let arg_kind = &self.ast_context.resolve_type(typ.ctype).kind;
+                    if let CTypeKind::Pointer(pointee) = *arg_kind {
+                        if self.ast_context.resolve_qualifiers(pointee).is_volatile {
+                            volatile_args.push(new_var.clone());
+                        }
+                    }
+                    if let Some(ref mapped) = mapped {
+                        mapped_params.push((new_var.clone(), mapped.clone(), c_ty, var_mutbl));
+                    }
+                    // Function pointers are `Option`s already
+                    if body.is_some()
+                        && self.is_option_pointer(decl_id)
+                        && !self.ast_context.is_function_pointer(typ.ctype)
+                    {
+                        option_params.push((new_var.clone(), typ));
+                    }
+
                     mk().set_mutbl(mutbl).ident_pat(new_var)
                 };
 
@@ -2123,8 +3169,22 @@ impl<'c> Translation<'c> {
             }
 
             // handle return type
+            let mapped_ret = return_type.and_then(|return_type| mapped_ty(return_type.ctype));
+            let option_ret = match return_type {
+                Some(return_type) if body.is_some() && self.is_option_return(name) => {
+                    match self.ast_context.resolve_type(return_type.ctype).kind {
+                        CTypeKind::Pointer(pointee) => Some(pointee.ctype),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
             let ret = match return_type {
-                Some(return_type) => self.convert_type(return_type.ctype)?,
+                Some(return_type) => match (&mapped_ret, option_ret) {
+                    (&Some(ref ty), _) => ty.clone(),
+                    (&None, Some(pointee)) => self.option_pointer_ty(pointee)?,
+                    (&None, None) => self.convert_type(return_type.ctype)?,
+                },
                 None => mk().never_ty(),
             };
             let is_void_ret = return_type
@@ -2141,6 +3201,18 @@ impl<'c> Translation<'c> {
 
             let decl = mk().fn_decl(args, ret);
 
+            // Functions declared with a calling convention like `__stdcall` use its Rust ABI
+            let abi = match self.ast_context[decl_id].kind {
+                CDeclKind::Function { typ, .. } => match self.ast_context.resolve_type(typ).kind {
+                    CTypeKind::Function(.., ref calling_conv) => self
+                        .type_converter
+                        .borrow_mut()
+                        .calling_conv_abi(&self.ast_context, calling_conv.as_ref())?,
+                    _ => "C",
+                },
+                _ => "C",
+            };
+
             if let Some(body) = body {
                 // Translating an actual function
 
@@ -2159,16 +3231,46 @@ impl<'c> Translation<'c> {
                     _ => cfg::ImplicitReturnType::Void,
                 };
 
-                let mut body_stmts = vec![];
-                for &(_, _, typ) in arguments {
-                    body_stmts.append(&mut self.compute_variable_array_sizes(ctx, typ.ctype)?);
+                self.find_array_aliases(body);
+                if self.tcfg.range_loops {
+                    self.find_range_loops(body);
                 }
-
-                let body_ids = match self.ast_context.index(body).kind {
-                    CStmtKind::Compound(ref stmts) => stmts,
-                    _ => panic!("function body expects to be a compound statement"),
+                // `Box` and `Vec` need an allocator
+                if self.tcfg.owned_allocations && !self.tcfg.emit_no_std {
+                    self.find_owned_allocations(name, body);
+                }
+                self.find_allocas(body, is_variadic);
+                if self.tcfg.structured_cleanup {
+                    let is_void = match ret {
+                        cfg::ImplicitReturnType::Void => true,
+                        _ => false,
+                    };
+                    self.find_cleanup_label(body, is_void);
+                }
+                *self.mapped_return_ty.borrow_mut() = mapped_ret;
+                *self.option_return_ty.borrow_mut() = match option_ret {
+                    Some(pointee) => Some(self.convert_type(pointee)?),
+                    None => None,
                 };
-                body_stmts.append(&mut self.convert_function_body(ctx, name, body_ids, ret)?);
+                let body_stmts = self.convert_body_or_stub(decl_id, || {
+                    let mut body_stmts = vec![];
+                    for (var, ty, c_ty, mutbl) in mapped_params {
+                        body_stmts.push(self.convert_mapped_param(&var, &ty, c_ty, mutbl));
+                    }
+                    for (var, typ) in option_params {
+                        body_stmts.push(self.convert_option_pointer_param(&var, typ)?);
+                    }
+                    for &(_, _, typ) in arguments {
+                        body_stmts.append(&mut self.compute_variable_array_sizes(ctx, typ.ctype)?);
+                    }
+
+                    let body_ids = match self.ast_context.index(body).kind {
+                        CStmtKind::Compound(ref stmts) => stmts,
+                        _ => panic!("function body expects to be a compound statement"),
+                    };
+                    body_stmts.append(&mut self.convert_function_body(ctx, name, body_ids, ret)?);
+                    Ok(self.wrap_alloca_scratch(body_stmts))
+                })?;
                 let mut block = stmts_block(body_stmts);
                 if let Some(span) = self.get_span(SomeId::Stmt(body)) {
                     block.span = span;
@@ -2179,18 +3281,24 @@ impl<'c> Translation<'c> {
                     // Cross-check this function as if it was called `main`
                     // FIXME: pass in a vector of NestedMetaItem elements,
                     // but strings have to do for now
-                    self.mk_cross_check(mk(), vec!["entry(djb2=\"main\")", "exit(djb2=\"main\")"])
+                    self.mk_cross_check(
+                        mk(),
+                        vec!["entry(djb2=\"main\")", "exit(djb2=\"main\")", "all_args", "ret"],
+                    )
+                } else if is_global && !is_inline && self.is_hidden_symbol(decl_id) {
+                    // Other modules import it from this one
+                    mk().extern_(abi).vis("pub(crate)")
                 } else if is_global && !is_inline {
-                    mk_linkage(false, new_name, name).extern_("C").pub_()
+                    mk_linkage(false, new_name, name).extern_(abi).pub_()
                 } else if is_inline && is_extern && !attrs.contains(&c_ast::Attribute::GnuInline) {
                     // c99 extern inline functions should be pub, but not gnu_inline attributed
                     // extern inlines, which become subject to their gnu89 visibility (private)
 
-                    mk_linkage(false, new_name, name).extern_("C").pub_()
+                    mk_linkage(false, new_name, name).extern_(abi).pub_()
                 } else if self.cur_file.borrow().is_some() {
-                    mk().extern_("C").pub_()
+                    mk().extern_(abi).pub_()
                 } else {
-                    mk().extern_("C")
+                    mk().extern_(abi)
                 };
 
                 for attr in attrs {
@@ -2198,6 +3306,11 @@ impl<'c> Translation<'c> {
                         c_ast::Attribute::AlwaysInline => mk_.single_attr("inline(always)"),
                         c_ast::Attribute::Cold => mk_.single_attr("cold"),
                         c_ast::Attribute::NoInline => mk_.single_attr("inline(never)"),
+                        c_ast::Attribute::Section(name) => mk_.str_attr("link_section", name),
+                        c_ast::Attribute::Weak => {
+                            self.use_feature("linkage");
+                            mk_.str_attr("linkage", "weak")
+                        }
                         _ => continue,
                     };
                 }
@@ -2228,14 +3341,31 @@ impl<'c> Translation<'c> {
                     // specifies internal linkage in all other cases due to name mangling by rustc.
                 }
 
-                Ok(ConvertedDecl::Item(
-                    mk_.span(span).unsafe_().fn_item(new_name, decl, block),
-                ))
+                // Tag the cross-checks with the C name rather than the Rust one, which may
+                // have been renamed or prefixed, so they line up with the C build, and check
+                // the arguments and return value like `clang_xcheck_config` makes the C build do
+                if !is_main && self.tcfg.cross_checks {
+                    let entry = format!("entry(djb2=\"{}\")", name);
+                    let exit = format!("exit(djb2=\"{}\")", name);
+                    mk_ = mk_.call_attr(
+                        "cross_check",
+                        vec![entry.as_str(), exit.as_str(), "all_args", "ret"],
+                    );
+                }
+
+                mk_ = add_aliasing_attrs(mk_, &restrict_args, &volatile_args);
+
+                let item = mk_.span(span).unsafe_().fn_item(new_name, decl, block);
+                Ok(ConvertedDecl::Item(self.add_fn_attributes(item)))
             } else {
                 // Translating an extern function declaration
 
-                // When putting extern fns into submodules, they need to be public to be accessible
-                let visibility = if self.tcfg.reorganize_definitions {
+                // When putting extern fns into submodules, they need to be public to be accessible,
+                // and bindings are the public API
+                let visibility = if self.tcfg.reorganize_definitions
+                    || self.tcfg.split_included_sources
+                    || self.tcfg.emit_bindings_only
+                {
                     "pub"
                 } else {
                     ""
@@ -2246,13 +3376,25 @@ impl<'c> Translation<'c> {
                 for attr in attrs {
                     mk_ = match attr {
                         c_ast::Attribute::Alias(aliasee) => mk_.str_attr("link_name", aliasee),
+                        c_ast::Attribute::Weak => {
+                            self.record_weak_reference();
+                            continue;
+                        }
                         _ => continue,
                     };
                 }
 
+                mk_ = add_aliasing_attrs(mk_, &restrict_args, &volatile_args);
+
                 let function_decl = mk_.fn_foreign_item(new_name, decl);
 
-                Ok(ConvertedDecl::ForeignItem(function_decl))
+                // Extern blocks share an ABI, so other ABIs get their own block
+                if abi == "C" {
+                    Ok(ConvertedDecl::ForeignItem(function_decl))
+                } else {
+                    let foreign_mod = mk().extern_(abi).foreign_items(vec![function_decl]);
+                    Ok(ConvertedDecl::Item(foreign_mod))
+                }
             }
         })
     }
@@ -2302,6 +3444,7 @@ impl<'c> Translation<'c> {
         let current_block = mk().ident_expr(&current_block_ident);
         let mut stmts: Vec<Stmt> = lifted_stmts;
         if cfg::structures::has_multiple(&relooped) {
+            self.report.borrow_mut().features.current_block_functions += 1;
             if self.tcfg.fail_on_multiple {
                 panic!("Uses of `current_block' are illegal with `--fail-on-multiple'.");
             }
@@ -2431,7 +3574,7 @@ impl<'c> Translation<'c> {
             match x {
                 SomeId::Expr(e) => match self.ast_context[e].kind {
                     CExprKind::DeclRef(_, d, _) if d == decl_id => return true,
-                    CExprKind::UnaryType(_, _, Some(_), _) => iter.prune(1),
+                    CExprKind::UnaryType(_, _, Some(_), _, _) => iter.prune(1),
                     _ => {}
                 },
                 SomeId::Type(t) => {
@@ -2450,6 +3593,21 @@ impl<'c> Translation<'c> {
         ctx: ExprContext,
         decl_id: CDeclId,
     ) -> Result<cfg::DeclStmtInfo, TranslationError> {
+        // Every use of the pointer is translated as a use of the array instead
+        if self.array_aliases.borrow().contains_key(&decl_id) {
+            return Ok(cfg::DeclStmtInfo::empty());
+        }
+        if let Some(alloc) = self.owned_allocation(decl_id) {
+            return self.convert_owned_allocation_decl(ctx, decl_id, alloc);
+        }
+        if self.is_option_pointer(decl_id) {
+            if let CDeclKind::Variable { typ, .. } = self.ast_context[decl_id].kind {
+                // Function pointers are `Option`s already
+                if !self.ast_context.is_function_pointer(typ.ctype) {
+                    return self.convert_option_pointer_decl(ctx, decl_id);
+                }
+            }
+        }
 
         match self.ast_context.index(decl_id).kind {
             CDeclKind::Variable {
@@ -2461,7 +3619,9 @@ impl<'c> Translation<'c> {
                 typ,
                 ..
             } => {
-                if self.static_initializer_is_uncompilable(initializer, typ) {
+                if self.static_initializer_is_uncompilable(initializer, typ)
+                    && self.fold_static_initializer(initializer, typ).is_none()
+                {
                     let ident2 = self
                         .renamer
                         .borrow_mut()
@@ -2516,7 +3676,7 @@ impl<'c> Translation<'c> {
                 let rust_name = self
                     .renamer
                     .borrow_mut()
-                    .insert(decl_id, &ident)
+                    .insert(decl_id, &self.tcfg.naming.value_case.apply(ident))
                     .expect(&format!("Failed to insert variable '{}'", ident));
 
                 if self.ast_context.is_va_list(typ.ctype) {
@@ -2576,7 +3736,14 @@ impl<'c> Translation<'c> {
                 } else {
                     let pat = mk().set_mutbl(mutbl).ident_pat(rust_name.clone());
 
-                    let type_annotation = if self.tcfg.reduce_type_annotations
+                    // `__auto_type` variables leave their type to inference, like in C
+                    let is_auto = match self.ast_context[typ.ctype].kind {
+                        CTypeKind::Auto(_) => true,
+                        _ => false,
+                    };
+                    let type_annotation = if is_auto && initializer.is_some() {
+                        None
+                    } else if self.tcfg.reduce_type_annotations
                         && !self.should_assign_type_annotation(typ.ctype, initializer)
                     {
                         None
@@ -2603,6 +3770,7 @@ impl<'c> Translation<'c> {
 
             ref decl => {
                 let inserted = if let Some(ident) = decl.get_name() {
+                    let ident = self.tcfg.naming.value_case.apply(ident);
                     self.renamer.borrow_mut().insert(decl_id, &ident).is_some()
                 } else {
                     false
@@ -2722,7 +3890,8 @@ impl<'c> Translation<'c> {
             | CTypeKind::ULongLong
             | CTypeKind::LongDouble
             | CTypeKind::Int128
-            | CTypeKind::UInt128 => initializer.is_none(),
+            | CTypeKind::UInt128
+            | CTypeKind::BitInt(..) => initializer.is_none(),
             CTypeKind::Float | CTypeKind::Double => initializer.is_none(),
             CTypeKind::Struct(_) | CTypeKind::Union(_) | CTypeKind::Enum(_) => false,
             CTypeKind::Function(..) => unreachable!("Can't have a function directly as a type"),
@@ -2781,6 +3950,27 @@ impl<'c> Translation<'c> {
             .convert(&self.ast_context, type_id)
     }
 
+    /// Anonymous enums which are neither typedef'd nor used as the type of anything, such as
+    /// `enum { FOO = 1, BAR = 2 };`, only serve to declare their constants.
+    fn anonymous_constant_enums(&self) -> HashSet<CEnumId> {
+        let used = self.ast_context.enums_used_as_types();
+        let typedefd: HashSet<CDeclId> = self.ast_context.prenamed_decls.values().cloned().collect();
+        self.ast_context
+            .iter_decls()
+            .filter(|&(id, decl)| match decl.kind {
+                CDeclKind::Enum { name: None, integral_type: Some(_), .. } => {
+                    !typedefd.contains(id) && !used.contains(id)
+                }
+                _ => false,
+            })
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    fn is_anonymous_constant_enum(&self, enum_id: CEnumId) -> bool {
+        self.anonymous_constant_enums.contains(&enum_id)
+    }
+
     /// Construct an expression for a NULL at any type, including forward declarations,
     /// function pointers, and normal pointers.
     fn null_ptr(&self, type_id: CTypeId, is_static: bool) -> Result<P<Expr>, TranslationError> {
@@ -2794,7 +3984,7 @@ impl<'c> Translation<'c> {
         };
         let ty = self.convert_type(type_id)?;
         let mut zero = mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed));
-        if is_static && !pointee.qualifiers.is_const {
+        if is_static && !self.ast_context.resolve_qualifiers(pointee).is_const {
             let mut qtype = pointee;
             qtype.qualifiers.is_const = true;
             self.use_feature("const_raw_ptr_to_usize_cast");
@@ -2966,6 +4156,22 @@ impl<'c> Translation<'c> {
         Ok(stmts)
     }
 
+    /// Fall back to the value clang computed for a `sizeof` or `_Alignof` when its operand type
+    /// has no Rust translation, e.g. because it is incomplete or otherwise unsupported.
+    fn or_computed_value(
+        &self,
+        computed: Result<WithStmts<P<Expr>>, TranslationError>,
+        value: Option<u64>,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        match (computed, value) {
+            (Ok(computed), _) => Ok(computed),
+            (Err(_), Some(value)) => Ok(WithStmts::new_val(
+                mk().lit_expr(mk().int_lit(value.into(), LitIntType::Unsuffixed)),
+            )),
+            (Err(e), None) => Err(e),
+        }
+    }
+
     // Compute the size of a type
     // Rust type: usize
     pub fn compute_size_of_type(
@@ -2985,6 +4191,16 @@ impl<'c> Translation<'c> {
                 }))
             });
         }
+        // A fixed-size array of variable-length arrays, e.g. `int a[3][n]`
+        if let CTypeKind::ConstantArray(elts, len) = self.ast_context.resolve_type(type_id).kind {
+            if self.variable_array_base_type(elts) != elts {
+                let elts = self.compute_size_of_type(ctx, elts)?;
+                return Ok(elts.map(|lhs| {
+                    let rhs = mk().lit_expr(mk().int_lit(len as u128, LitIntType::Unsuffixed));
+                    mk().binary_expr(BinOpKind::Mul, lhs, rhs)
+                }));
+            }
+        }
         let ty = self.convert_type(type_id)?;
         self.compute_size_of_ty(ty)
     }
@@ -3034,13 +4250,16 @@ impl<'c> Translation<'c> {
         Ok(WithStmts::new_val(call))
     }
 
+    /// Translate a list of expressions, such as the arguments of a call, evaluating
+    /// their side effects from left to right
     fn convert_exprs(&self, ctx: ExprContext, exprs: &[CExprId])
                      -> Result<WithStmts<Vec<P<Expr>>>, TranslationError>
     {
-        exprs
+        let operands = exprs
             .iter()
-            .map(|arg| self.convert_expr(ctx, *arg))
-            .collect()
+            .map(|arg| Ok((Some(*arg), self.convert_expr(ctx, *arg)?)))
+            .collect::<Result<Vec<_>, TranslationError>>()?;
+        Ok(self.sequence_operands(operands))
     }
 
     /// Translate a C expression into a Rust one, possibly collecting side-effecting statements
@@ -3053,6 +4272,19 @@ impl<'c> Translation<'c> {
     /// `stmts` field of the output and it is expected that the `val` field of the output will be
     /// ignored.
     pub fn convert_expr(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let hooks = &self.tcfg.hooks;
+        if let Some(expr) = hooks.before_expr(&self.ast_context, expr_id) {
+            return Ok(WithStmts::new_val(expr));
+        }
+        let converted = self.convert_expr_unhooked(ctx, expr_id)?;
+        Ok(converted.map(|expr| hooks.after_expr(&self.ast_context, expr_id, expr)))
+    }
+
+    fn convert_expr_unhooked(
         &self,
         mut ctx: ExprContext,
         expr_id: CExprId,
@@ -3064,6 +4296,12 @@ impl<'c> Translation<'c> {
 
         trace!("Converting expr {:?}: {:?}", expr_id, self.ast_context[expr_id]);
 
+        // Arguments of a function-like macro being recovered become its
+        // parameters
+        if let Some(param) = self.macro_arg_substitutions.borrow().get(&expr_id) {
+            return Ok(WithStmts::new_val(mk().path_expr(vec![param.clone()])));
+        }
+
         if self.tcfg.translate_const_macros {
             if let Some(converted) = self.convert_macro_expansion(ctx, expr_id)? {
                 return Ok(converted);
@@ -3071,12 +4309,15 @@ impl<'c> Translation<'c> {
         }
 
         if self.tcfg.translate_fn_macros {
-            let text = self.ast_context.macro_expansion_text.get(&expr_id);
-            if let Some(converted) = text.and_then(|text| self.convert_macro_invocation(ctx, &text)) {
+            if let Some(converted) = self.convert_macro_function_invocation(ctx, expr_id)? {
                 return Ok(converted);
             }
         }
 
+        if let Some(errno) = self.convert_errno(expr_id)? {
+            return Ok(errno);
+        }
+
         match *expr_kind {
             CExprKind::DesignatedInitExpr(..) => {
                 Err(TranslationError::generic("Unexpected designated init expr"))
@@ -3084,6 +4325,11 @@ impl<'c> Translation<'c> {
             CExprKind::BadExpr => Err(TranslationError::generic(
                 "convert_expr: expression kind not supported",
             )),
+            CExprKind::Unsupported(_, ref what) => Err(TranslationError::new(
+                self.ast_context.display_loc(src_loc),
+                err_msg(format!("{} are not supported", what))
+                    .context(TranslationErrorKind::UnsupportedExtension),
+            )),
             CExprKind::ShuffleVector(_, ref child_expr_ids) => self
                 .convert_shuffle_vector(ctx, child_expr_ids)
                 .map_err(|e| {
@@ -3093,10 +4339,28 @@ impl<'c> Translation<'c> {
                 Err(TranslationError::generic("convert vector not supported"))
             }
 
-            CExprKind::UnaryType(_ty, kind, opt_expr, arg_ty) => {
-                let result = match kind {
-                    UnTypeOp::SizeOf => match opt_expr {
-                        None => self.compute_size_of_type(ctx, arg_ty.ctype)?,
+            CExprKind::UnaryType(_ty, kind, opt_expr, arg_ty, value) => {
+                // Expression operands are never evaluated: only their type matters. The
+                // exception in C is a variable-length array operand, whose size is computed at
+                // runtime from the array's stored length.
+                let arg_kind = &self.ast_context.resolve_type(arg_ty.ctype).kind;
+                let is_vla = match arg_kind {
+                    CTypeKind::VariableArray(..) => true,
+                    _ => false,
+                };
+                let is_void_or_fn = match arg_kind {
+                    CTypeKind::Void | CTypeKind::Function(..) => true,
+                    _ => false,
+                };
+
+                let result = match (kind, value) {
+                    // GNU C gives `void` and function types a size and alignment of 1, which
+                    // has no Rust equivalent, so use the value clang computed.
+                    (_, Some(value)) if is_void_or_fn => {
+                        WithStmts::new_val(mk().lit_expr(mk().int_lit(value.into(), LitIntType::Unsuffixed)))
+                    }
+
+                    (UnTypeOp::SizeOf, _) if is_vla => match opt_expr {
                         Some(_) => {
                             let inner = self.variable_array_base_type(arg_ty.ctype);
                             let inner_size = self.compute_size_of_type(ctx, inner)?;
@@ -3104,16 +4368,23 @@ impl<'c> Translation<'c> {
                             if let Some(sz) = self.compute_size_of_expr(arg_ty.ctype) {
                                 inner_size.map(|x| mk().binary_expr(BinOpKind::Mul, sz, x))
                             } else {
-                                // Otherwise, use the pointer and make a deref of a pointer offset expression
-                                inner_size
+                                self.compute_size_of_type(ctx, arg_ty.ctype)?
                             }
                         }
+                        None => self.compute_size_of_type(ctx, arg_ty.ctype)?,
                     },
-                    UnTypeOp::AlignOf => self.compute_align_of_type(arg_ty.ctype, false)?,
-                    UnTypeOp::PreferredAlignOf => self.compute_align_of_type(arg_ty.ctype, true)?,
+                    (UnTypeOp::SizeOf, value) => {
+                        self.or_computed_value(self.compute_size_of_type(ctx, arg_ty.ctype), value)?
+                    }
+                    (UnTypeOp::AlignOf, value) => {
+                        self.or_computed_value(self.compute_align_of_type(arg_ty.ctype, false), value)?
+                    }
+                    (UnTypeOp::PreferredAlignOf, value) => {
+                        self.or_computed_value(self.compute_align_of_type(arg_ty.ctype, true), value)?
+                    }
                 };
 
-                Ok(result.map(|x| mk().cast_expr(x, mk().path_ty(vec!["libc", "c_ulong"]))))
+                Ok(result.map(|x| mk().cast_expr(x, self.ffi_ty("c_ulong"))))
             }
 
             CExprKind::ConstantExpr(_ty, child, value) => {
@@ -3147,7 +4418,7 @@ impl<'c> Translation<'c> {
                     .ok_or_else(|| format_err!("name not declared: '{}'", varname))?;
 
                 // Import the referenced global decl into our submodule
-                if self.tcfg.reorganize_definitions {
+                if self.tcfg.reorganize_definitions || self.tcfg.split_included_sources {
                     if let Some(cur_file) = self.cur_file.borrow().as_ref() {
                         self.add_import(*cur_file, decl_id, &rustname);
                         // match decl {
@@ -3159,6 +4430,17 @@ impl<'c> Translation<'c> {
 
                 let mut val = mk().path_expr(vec![rustname]);
 
+                // Bound to the `NonNull` pointer by a null check
+                if self.is_unwrapped_pointer(decl_id)
+                    && !self.ast_context.is_function_pointer(qual_ty.ctype)
+                {
+                    val = mk().method_call_expr(val, "as_ptr", Vec::<P<Expr>>::new());
+                }
+
+                if self.is_thread_local_macro_var(decl) {
+                    val = self.thread_local_access(val);
+                }
+
                 // If the variable is volatile and used as something that isn't an LValue, this
                 // constitutes a volatile read.
                 if lrvalue.is_rvalue() && qual_ty.qualifiers.is_volatile {
@@ -3221,8 +4503,6 @@ impl<'c> Translation<'c> {
                     Ok(WithStmts::new_val(self.mk_int_lit(ty, *val, IntBase::Dec)?))
                 }
                 OffsetOfKind::Variable(qty, field_id, expr_id) => {
-                    self.use_crate(ExternCrate::Memoffset);
-
                     // Struct Type
                     let decl_id = {
                         let kind = match self.ast_context[qty.ctype].kind {
@@ -3233,16 +4513,6 @@ impl<'c> Translation<'c> {
                         kind.as_decl_or_typedef()
                             .expect("Did not find decl_id for offsetof struct")
                     };
-                    let name = self.resolve_decl_inner_name(decl_id);
-                    let ty_ident = Nonterminal::NtIdent(mk().ident(name), false);
-
-                    // Field name
-                    let field_name = self
-                        .type_converter
-                        .borrow()
-                        .resolve_field_name(None, *field_id)
-                        .expect("Did not find name for offsetof struct field");
-                    let field_ident = Nonterminal::NtIdent(mk().ident(field_name), false);
 
                     // Index Expr
                     let expr = self.convert_expr(ctx, *expr_id)?
@@ -3251,19 +4521,9 @@ impl<'c> Translation<'c> {
                             format_err!("Expected Variable offsetof to be a side-effect free")
                         })?;
                     let expr = mk().cast_expr(expr, mk().ident_ty("usize"));
-                    let index_expr = Nonterminal::NtExpr(expr);
 
                     // offset_of!(Struct, field[expr as usize]) as ty
-                    let macro_body = vec![
-                        TokenTree::token(token::Interpolated(Rc::new(ty_ident)), DUMMY_SP),
-                        TokenTree::token(token::Comma, DUMMY_SP),
-                        TokenTree::token(token::Interpolated(Rc::new(field_ident)), DUMMY_SP),
-                        TokenTree::token(token::OpenDelim(DelimToken::Bracket), DUMMY_SP),
-                        TokenTree::token(token::Interpolated(Rc::new(index_expr)), DUMMY_SP),
-                        TokenTree::token(token::CloseDelim(DelimToken::Bracket), DUMMY_SP),
-                    ];
-                    let path = mk().path("offset_of");
-                    let mac = mk().mac_expr(mk().mac(path, macro_body, MacDelimiter::Parenthesis));
+                    let mac = self.offset_of_expr(decl_id, *field_id, Some(expr));
 
                     // Cast type
                     let cast_ty = self.convert_type(ty.ctype)?;
@@ -3278,6 +4538,15 @@ impl<'c> Translation<'c> {
             CExprKind::ImplicitCast(ty, expr, kind, opt_field_id, _)
             | CExprKind::ExplicitCast(ty, expr, kind, opt_field_id, _) => {
                 let is_explicit = if let CExprKind::ExplicitCast(..) = *expr_kind { true } else { false };
+
+                // `(size_t) &((struct S *)0)->field` is a hand-rolled `offsetof`
+                if let CastKind::PointerToIntegral = kind {
+                    if let Some(offset) = self.convert_null_member_offset(expr) {
+                        let target_ty = self.convert_type(ty.ctype)?;
+                        return Ok(WithStmts::new_val(mk().cast_expr(offset, target_ty)));
+                    }
+                }
+
                 // A reference must be decayed if a bitcast is required. Const casts in
                 // LLVM 8 are now NoOp casts, so we need to include it as well.
                 match kind {
@@ -3308,6 +4577,34 @@ impl<'c> Translation<'c> {
                 if self.casting_simd_builtin_call(expr, is_explicit, kind) {
                     return Ok(val);
                 }
+
+                if !is_explicit && kind == CastKind::IntegralCast {
+                    if let Some(checked) = self.checked_integral_conversion(ctx, source_ty, ty, &val)? {
+                        return Ok(checked);
+                    }
+                }
+
+                // Loading from a volatile lvalue must not become a plain read, otherwise it
+                // could be reordered or elided altogether. It happens even if the value
+                // is unused.
+                let val = match kind {
+                    CastKind::LValueToRValue
+                        if self.ast_context.resolve_qualifiers(source_ty).is_volatile =>
+                    {
+                        let val = val.result_map(|val| self.volatile_read(&val, source_ty))?;
+                        if ctx.is_unused() {
+                            return self.convert_side_effects_expr(
+                                ctx,
+                                val,
+                                "Volatile read is not supposed to be used",
+                            );
+                        }
+                        val
+                    }
+                    // Structs that aren't `Copy` are copied explicitly
+                    CastKind::LValueToRValue => self.copy_value(source_ty.ctype, val)?,
+                    _ => val,
+                };
                 self.convert_cast(ctx, source_ty, ty, val, Some(expr), Some(kind), opt_field_id)
             }
 
@@ -3375,16 +4672,53 @@ impl<'c> Translation<'c> {
                         ))
                     })
                 } else {
-                    self.name_reference_write_read(ctx, lhs)?
-                        .result_map(|(_, lhs_val)| {
-                            let cond = self.match_bool(true, ty.ctype, lhs_val.clone());
-                            let ite = mk().ifte_expr(
-                                cond,
-                                mk().block(vec![mk().expr_stmt(lhs_val)]),
-                                Some(self.convert_expr(ctx, rhs)?.to_expr()),
-                            );
+                    if ctx.is_const {
+                        return Err(format_translation_err!(
+                            self.ast_context.display_loc(src_loc),
+                            "Constants cannot contain ternary expressions in Rust",
+                        ));
+                    }
+                    let lhs_ty = self.ast_context[lhs]
+                        .kind
+                        .get_type()
+                        .ok_or_else(|| format_err!("bad binary conditional type"))?;
+                    // `a ?: b` evaluates `a` once, so bind it unless it is pure
+                    let lhs_is_pure = self.ast_context.is_expr_pure(lhs);
+                    let mut res = self.convert_expr(ctx.used(), lhs)?.and_then(|lhs_val| {
+                        if lhs_is_pure {
+                            return Ok(WithStmts::new_val(lhs_val));
+                        }
+                        let name = self.renamer.borrow_mut().fresh();
+                        let binding = mk().local_stmt(P(mk().local(
+                            mk().ident_pat(&name),
+                            None as Option<P<Ty>>,
+                            Some(lhs_val),
+                        )));
+                        Ok(WithStmts::new(vec![binding], mk().ident_expr(&name)))
+                    })?;
+                    let rhs = self.convert_expr(ctx, rhs)?;
+                    res.merge_unsafe(rhs.is_unsafe());
+
+                    res.result_map(|lhs_val| {
+                        let cond = self.match_bool(true, lhs_ty, lhs_val.clone());
+                        let lhs_val = if self.ast_context.resolve_type_id(lhs_ty)
+                            == self.ast_context.resolve_type_id(ty.ctype)
+                        {
+                            lhs_val
+                        } else {
+                            mk().cast_expr(lhs_val, self.convert_type(ty.ctype)?)
+                        };
+                        let ite = mk().ifte_expr(
+                            cond,
+                            mk().block(vec![mk().expr_stmt(lhs_val)]),
+                            Some(rhs.to_expr()),
+                        );
+                        if ctx.ternary_needs_parens {
+                            Ok(mk().paren_expr(ite))
+                        } else {
                             Ok(ite)
-                        })
+                        }
+                    })
                 }
             }
 
@@ -3425,9 +4759,24 @@ impl<'c> Translation<'c> {
                     ));
                 }
 
+                let unsigned_index = self.ast_context[*rhs]
+                    .kind
+                    .get_type()
+                    .map_or(false, |ty| self.ast_context.resolve_type(ty).kind.is_unsigned_integral_type());
                 let rhs = self.convert_expr(ctx.used(), *rhs)?;
                 rhs.and_then(|rhs| {
-                    let simple_index_array = if ctx.needs_address() {
+                    if self.owned_vec(*lhs).is_some() {
+                        let vec = self.convert_expr(ctx.used(), *lhs)?;
+                        return Ok(vec.map(|vec| mk().index_expr(vec, cast_int(rhs, "usize", false))));
+                    }
+
+                    // With `--checked-indexing`, taking the address of an
+                    // element slices the array instead of offsetting a pointer
+                    let checked_address =
+                        self.tcfg.checked_indexing && ctx.needs_address() && !ctx.is_static;
+                    let simple_index_array = if let Some(arr) = self.aliased_array(*lhs) {
+                        Some(arr)
+                    } else if ctx.needs_address() && !checked_address {
                         // We can't necessarily index into an array if we're using
                         // that element to compute an address.
                         None
@@ -3467,6 +4816,12 @@ impl<'c> Translation<'c> {
                             CTypeKind::VariableArray(elt, _) => Some(elt),
                             ref other => panic!("Unexpected array type {:?}", other),
                         };
+                        let elt_is_const = match self.ast_context.resolve_type(lhs_node_type).kind {
+                            CTypeKind::Pointer(pointee) => {
+                                self.ast_context.resolve_qualifiers(pointee).is_const
+                            }
+                            _ => false,
+                        };
 
                         let lhs = self.convert_expr(ctx.used(), arr)?;
                         Ok(lhs.map(|lhs| {
@@ -3476,7 +4831,21 @@ impl<'c> Translation<'c> {
                             // Don't dereference the offset if we're still within the variable portion
                             if let Some(elt_type_id) = var_elt_type_id {
                                 let mul = self.compute_size_of_expr(elt_type_id);
-                                pointer_offset(lhs, rhs, mul, false, true)
+                                pointer_offset(lhs, rhs, unsigned_index, mul, false, true)
+                            } else if checked_address {
+                                // C allows the address one past the end, and
+                                // so does slicing from it
+                                let tail = mk().index_expr(
+                                    lhs,
+                                    mk().range_expr(
+                                        Some(cast_int(rhs, "usize", false)),
+                                        None,
+                                        RangeLimits::HalfOpen,
+                                    ),
+                                );
+                                let method = if elt_is_const { "as_ptr" } else { "as_mut_ptr" };
+                                let ptr = mk().method_call_expr(tail, method, vec![] as Vec<P<Expr>>);
+                                mk().unary_expr(ast::UnOp::Deref, ptr)
                             } else {
                                 mk().index_expr(lhs, cast_int(rhs, "usize", false))
                             }
@@ -3504,21 +4873,48 @@ impl<'c> Translation<'c> {
                             };
 
                             let mul = self.compute_size_of_expr(pointee_type_id.ctype);
-                            Ok(pointer_offset(lhs, rhs, mul, false, true))
+                            Ok(pointer_offset(lhs, rhs, unsigned_index, mul, false, true))
                         })
                     }
                 })
             }
 
             CExprKind::Call(call_expr_ty, func, ref args) => {
+                if let Some(dropped) = self.convert_owned_free(expr_id)? {
+                    return Ok(dropped);
+                }
+
+                if let Some(alloca) = self.convert_alloca(ctx, expr_id)? {
+                    return Ok(alloca);
+                }
+
+                if let Some(int128) = self.convert_int128_helper(ctx, expr_id)? {
+                    return Ok(int128);
+                }
+
+                if self.tcfg.lower_libc_calls {
+                    if let Some(lowered) = self.convert_libc_call(ctx, call_expr_ty, func, args)? {
+                        return Ok(lowered);
+                    }
+                }
+
                 let fn_ty = self.ast_context.get_pointee_qual_type(
                     self.ast_context[func].kind.get_type()
                         .ok_or_else(|| format_err!("Invalid callee expression {:?}", func))?
                 ).map(|ty| &self.ast_context.resolve_type(ty.ctype).kind);
                 let is_variadic = match fn_ty {
-                    Some(CTypeKind::Function(_, _, is_variadic, _, _)) => *is_variadic,
+                    Some(CTypeKind::Function(_, _, is_variadic, ..)) => *is_variadic,
                     _ => false,
                 };
+                let mapped_callee = match self.ast_context[func].kind {
+                    CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _) => {
+                        match self.ast_context[fexp].kind {
+                            CExprKind::DeclRef(_, decl_id, _) => self.mapped_callee(decl_id),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
                 let func = match self.ast_context[func].kind {
                     // Direct function call
                     CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _)
@@ -3529,7 +4925,19 @@ impl<'c> Translation<'c> {
                             _ => false,
                         } =>
                     {
-                        self.convert_expr(ctx.used(), fexp)?
+                        let shim_fn = match self.ast_context[fexp].kind {
+                            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                                CDeclKind::Function { ref name, body: None, .. } => {
+                                    self.alloc_shim_fn(name)
+                                }
+                                _ => None,
+                            },
+                            _ => None,
+                        };
+                        match shim_fn {
+                            Some(shim_fn) => WithStmts::new_val(shim_fn),
+                            None => self.convert_expr(ctx.used(), fexp)?,
+                        }
                     }
 
                     // Builtin function call
@@ -3559,7 +4967,7 @@ impl<'c> Translation<'c> {
                             )
                         };
                         match fn_ty {
-                            Some(CTypeKind::Function(ret_ty, _, _, _, false)) => {
+                            Some(CTypeKind::Function(ret_ty, _, _, _, false, _)) => {
                                 // K&R function pointer without arguments
                                 if ctx.is_const { self.use_feature("const_transmute"); }
                                 let ret_ty = self.convert_type(ret_ty.ctype)?;
@@ -3578,6 +4986,8 @@ impl<'c> Translation<'c> {
                                     transmute_expr(mk().infer_ty(), target_ty, fn_ptr, self.tcfg.emit_no_std)
                                 })
                             }
+                            // Bound to the function by a null check
+                            Some(_) if self.calls_unwrapped_pointer(func) => callee,
                             Some(_) => {
                                 // Normal function pointer
                                 callee.map(unwrap_function_pointer)
@@ -3590,12 +5000,19 @@ impl<'c> Translation<'c> {
                     // We want to decay refs only when function is variadic
                     ctx.decay_ref = DecayRef::from(is_variadic);
 
+                    let arg_ids = args;
                     let args = self.convert_exprs(ctx.used(), args)?;
 
-                    let res: Result<_, TranslationError> = Ok(
-                        args.map(|args| mk().call_expr(func, args))
-                    );
-                    res
+                    match mapped_callee {
+                        Some(callee) => args.and_then(|args| -> Result<_, TranslationError> {
+                            let args = self.convert_mapped_args(callee, arg_ids, args)?;
+                            let call = mk().call_expr(func, args);
+                            Ok(WithStmts::new_val(
+                                self.convert_mapped_result(callee, call_expr_ty, call)?,
+                            ))
+                        }),
+                        None => Ok(args.map(|args| mk().call_expr(func, args))),
+                    }
                 })?;
 
                 self.convert_side_effects_expr(
@@ -3710,6 +5127,10 @@ impl<'c> Translation<'c> {
                 Ok(chosen_expr)
             }
 
+            // Clang already resolved the selection, and the controlling
+            // expression is never evaluated
+            CExprKind::GenericSelection(_, _, _, expr) => self.convert_expr(ctx, expr),
+
             CExprKind::Atomic{ref name, ptr, order, val1, order_fail, val2, weak, ..} => {
                 self.convert_atomic(ctx, name, ptr, order, val1, order_fail, val2, weak)
             }
@@ -3736,7 +5157,31 @@ impl<'c> Translation<'c> {
 
     fn convert_macro_expansion(&self, ctx: ExprContext, expr_id: CExprId)
                                -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
-        if let Some(macs) = self.ast_context.macro_invocations.get(&expr_id) {
+        // A macro translated at the type of its uses replaces the conversion
+        // of its expansion rather than the expansion itself.
+        let expansion_id = match self.ast_context[expr_id].kind {
+            CExprKind::ImplicitCast(_, child, ..) => Some(child),
+            _ => None,
+        };
+        let use_site_macro = expansion_id
+            .and_then(|child| self.ast_context.macro_invocations.get(&child))
+            .and_then(|macs| {
+                macs.splitn(2, |macro_id| ctx.expanding_macro(macro_id))
+                    .last()
+                    .unwrap()
+                    .first()
+            })
+            .filter(|macro_id| {
+                self.macro_use_sites
+                    .get(macro_id)
+                    .map_or(false, |sites| sites.contains(&expr_id))
+            });
+        let macs = match use_site_macro {
+            Some(macro_id) => Some(slice::from_ref(macro_id)),
+            None => self.ast_context.macro_invocations.get(&expr_id).map(Vec::as_slice),
+        };
+
+        if let Some(macs) = macs {
             // Find the first macro after the macro we're currently
             // expanding, if any.
             if let Some(macro_id) = macs
@@ -3794,22 +5239,6 @@ impl<'c> Translation<'c> {
         Ok(None)
     }
 
-    fn convert_macro_invocation(&self, _ctx: ExprContext, text: &str)
-                                -> Option<WithStmts<P<Expr>>> {
-        let mut split = text.splitn(2, '(');
-        let ident = split.next()?;
-        let args = split.next()?.trim_end_matches(')');
-
-        let parse_sess = ParseSess::new(FilePathMapping::empty());
-        let ts = parse_stream_from_source_str(
-            FileName::Anon(0),
-            args.to_string(),
-            &parse_sess,
-            None,
-        );
-        Some(WithStmts::new_val(mk().mac_expr(mk().mac(ident, ts, MacDelimiter::Parenthesis))))
-    }
-
     /// If `ctx` is unused, convert `expr` to a semi statement, otherwise return
     /// `expr`.
     fn convert_side_effects_expr(
@@ -3906,21 +5335,72 @@ impl<'c> Translation<'c> {
                     }
                 }
 
-                let block_body = mk().block(stmts.clone());
+                // The statements live inside the labelled block, so they must
+                // not be emitted again before it
+                let block_body = mk().block(stmts);
                 let val: P<Expr> = mk().labelled_block_expr(block_body, lbl.pretty_print());
+                let stmts = if ctx.is_unused() {
+                    vec![mk().expr_stmt(val.clone())]
+                } else {
+                    Vec::new()
+                };
 
                 Ok(WithStmts::new(stmts, val))
             }
-            _ => {
-                if ctx.is_unused() {
-                    let val =
-                        self.panic_or_err("Empty statement expression is not supposed to be used");
-                    Ok(WithStmts::new_val(val))
-                } else {
-                    Err(TranslationError::generic("Bad statement expression"))
-                }
+            // `({})` has type `void`
+            CStmtKind::Compound(_) => {
+                Ok(WithStmts::new_val(mk().tuple_expr(vec![] as Vec<P<Expr>>)))
+            }
+            _ => Err(TranslationError::generic("Bad statement expression")),
+        }
+    }
+
+    /// With `IntegerConversionMode::Checked`, translate an implicit integer
+    /// conversion that may not preserve its operand, e.g. from `int` to
+    /// `unsigned char`, to one that panics if it changes the value
+    fn checked_integral_conversion(
+        &self,
+        ctx: ExprContext,
+        source_ty: CQualTypeId,
+        ty: CQualTypeId,
+        val: &WithStmts<P<Expr>>,
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        if self.tcfg.integer_conversions != IntegerConversionMode::Checked
+            || ctx.is_static
+            || ctx.is_const
+        {
+            return Ok(None);
+        }
+        let source_kind = &self.ast_context.resolve_type(source_ty.ctype).kind;
+        let target_kind = &self.ast_context.resolve_type(ty.ctype).kind;
+        let may_change_value = match (
+            source_kind.integer_conversion_rank(),
+            target_kind.integer_conversion_rank(),
+        ) {
+            (Some(source_rank), Some(target_rank)) => {
+                target_rank < source_rank
+                    || source_kind.is_signed_integral_type() != target_kind.is_signed_integral_type()
             }
+            _ => false,
+        };
+        if !may_change_value {
+            return Ok(None);
         }
+
+        let target_ty = self.convert_type(ty.ctype)?;
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let try_into = mk().path_expr(vec![
+            mk().path_segment(""),
+            mk().path_segment(std_or_core),
+            mk().path_segment("convert"),
+            mk().path_segment_with_args("TryInto", mk().angle_bracketed_args(vec![target_ty])),
+            mk().path_segment("try_into"),
+        ]);
+        Ok(Some(val.clone().map(|val| {
+            let converted = mk().call_expr(try_into, vec![val]);
+            let msg = mk().lit_expr("implicit integer conversion changed the value");
+            mk().method_call_expr(converted, "expect", vec![msg])
+        })))
     }
 
     fn convert_cast(
@@ -3997,6 +5477,15 @@ impl<'c> Translation<'c> {
             }
         });
 
+        match (kind, source_ty_kind, target_ty_kind) {
+            (CastKind::BitCast, CTypeKind::Pointer(..), CTypeKind::Pointer(..))
+            | (CastKind::IntegralToPointer, ..)
+            | (CastKind::PointerToIntegral, ..) => {
+                self.report.borrow_mut().features.pointer_casts += 1;
+            }
+            _ => {}
+        }
+
         match kind {
             CastKind::BitCast | CastKind::NoOp => {
                 val.and_then(|x| {
@@ -4028,7 +5517,7 @@ impl<'c> Translation<'c> {
                 }
                 let target_ty = self.convert_type(ty.ctype)?;
                 val.and_then(|x| {
-                    let intptr_t = mk().path_ty(vec!["libc", "intptr_t"]);
+                    let intptr_t = self.ffi_ty("intptr_t");
                     let intptr = mk().cast_expr(x, intptr_t.clone());
                     Ok(WithStmts::new_unsafe_val(
                         transmute_expr(intptr_t, target_ty, intptr, self.tcfg.emit_no_std)
@@ -4048,7 +5537,17 @@ impl<'c> Translation<'c> {
                 let source_ty_ctype_id = source_ty.ctype;
 
                 let source_ty = self.convert_type(source_ty_ctype_id)?;
-                if let CTypeKind::LongDouble = target_ty_ctype {
+                // `half::f16` has no `as` casts, so it is converted through `f64`
+                let val = match *source_ty_kind {
+                    CTypeKind::Half => val.map(|val| self.half_to_f64(val)),
+                    _ => val,
+                };
+                if let CTypeKind::Half = target_ty_ctype {
+                    Ok(val.map(|val| match *source_ty_kind {
+                        CTypeKind::Double => self.f64_to_half(val),
+                        _ => self.f64_to_half(mk().cast_expr(val, mk().path_ty(vec!["f64"]))),
+                    }))
+                } else if let CTypeKind::LongDouble = target_ty_ctype {
                     self.use_crate(ExternCrate::F128);
 
                     let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
@@ -4069,7 +5568,8 @@ impl<'c> Translation<'c> {
                             }
                             Ok(WithStmts::new_unsafe_val(transmute_expr(source_ty, target_ty, x, self.tcfg.emit_no_std)))
                         } else {
-                            Ok(WithStmts::new_val(mk().cast_expr(x, target_ty)))
+                            let cast = mk().cast_expr(x, target_ty);
+                            Ok(WithStmts::new_val(self.wrap_bit_int(ty.ctype, cast)))
                         }
                     })
                 }
@@ -4096,7 +5596,7 @@ impl<'c> Translation<'c> {
                     _ => panic!("Dereferencing a non-pointer"),
                 };
 
-                let is_const = pointee.qualifiers.is_const;
+                let is_const = self.ast_context.resolve_qualifiers(pointee).is_const;
 
                 let expr_kind = expr.map(|e| &self.ast_context.index(e).kind);
                 match expr_kind {
@@ -4203,6 +5703,23 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Convert a `half::f16` to `f64`, which is exact
+    fn half_to_f64(&self, val: P<Expr>) -> P<Expr> {
+        mk().method_call_expr(val, "to_f64", vec![] as Vec<P<Expr>>)
+    }
+
+    /// Round a `f64` to a `half::f16`
+    fn f64_to_half(&self, val: P<Expr>) -> P<Expr> {
+        mk().call_expr(mk().path_expr(vec!["half", "f16", "from_f64"]), vec![val])
+    }
+
+    /// Arithmetic on `half::f16` values, done in `f64` and rounded back. `f64` is
+    /// precise enough for the result to round the same as in 16-bit arithmetic.
+    fn half_arith_expr(&self, op: BinOpKind, lhs: P<Expr>, rhs: P<Expr>) -> P<Expr> {
+        let val = mk().binary_expr(op, self.half_to_f64(lhs), self.half_to_f64(rhs));
+        self.f64_to_half(val)
+    }
+
     /// Cast a f128 to some other int or float type
     fn f128_cast_to(
         &self,
@@ -4217,18 +5734,32 @@ impl<'c> Translation<'c> {
         let to_method_name = match target_ty_ctype {
             CTypeKind::Float => "to_f32",
             CTypeKind::Double => "to_f64",
-            CTypeKind::Char => "to_i8",
-            CTypeKind::UChar => "to_u8",
-            CTypeKind::Short => "to_i16",
-            CTypeKind::UShort => "to_u16",
-            CTypeKind::Int => "to_i32",
-            CTypeKind::UInt => "to_u32",
-            CTypeKind::Long => "to_i64",
-            CTypeKind::ULong => "to_u64",
-            CTypeKind::LongLong => "to_i64",
-            CTypeKind::ULongLong => "to_u64",
-            CTypeKind::Int128 => "to_i128",
-            CTypeKind::UInt128 => "to_u128",
+            CTypeKind::Char
+            | CTypeKind::UChar
+            | CTypeKind::Short
+            | CTypeKind::UShort
+            | CTypeKind::Int
+            | CTypeKind::UInt
+            | CTypeKind::Long
+            | CTypeKind::ULong
+            | CTypeKind::LongLong
+            | CTypeKind::ULongLong
+            | CTypeKind::Int128
+            | CTypeKind::UInt128 => {
+                let signed = self.ast_context.is_signed_integral(target_ty_ctype);
+                match (signed, self.ast_context.integral_type_width(target_ty_ctype)) {
+                    (true, Some(8)) => "to_i8",
+                    (false, Some(8)) => "to_u8",
+                    (true, Some(16)) => "to_i16",
+                    (false, Some(16)) => "to_u16",
+                    (true, Some(32)) => "to_i32",
+                    (false, Some(32)) => "to_u32",
+                    (true, Some(64)) => "to_i64",
+                    (false, Some(64)) => "to_u64",
+                    (true, _) => "to_i128",
+                    (false, _) => "to_u128",
+                }
+            }
             _ => {
                 return Err(format_err!(
                     "Tried casting long double to unsupported type: {:?}",
@@ -4317,8 +5848,9 @@ impl<'c> Translation<'c> {
         } else if resolved_ty.is_integral_type() {
             Ok(WithStmts::new_val(mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed))))
         } else if resolved_ty.is_floating_type() {
-            match self.ast_context[ty_id].kind {
+            match *resolved_ty {
                 CTypeKind::LongDouble => Ok(WithStmts::new_val(mk().path_expr(vec!["f128", "f128", "ZERO"]))),
+                CTypeKind::Half => Ok(WithStmts::new_val(mk().path_expr(vec!["half", "f16", "ZERO"]))),
                 _ => Ok(WithStmts::new_val(mk().lit_expr(mk().float_unsuffixed_lit("0.")))),
             }
         } else if let &CTypeKind::Pointer(_) = resolved_ty {
@@ -4339,13 +5871,15 @@ impl<'c> Translation<'c> {
 
             // Find base element type of potentially nested arrays
             let inner = self.variable_array_base_type(elt);
+            self.check_heap_available("Variable-length arrays")?;
             let count = self.compute_size_of_expr(ty_id).unwrap();
             Ok(self.implicit_default_expr(inner, is_static)?
                .map(|val| vec_expr(val, count)))
         } else if let &CTypeKind::Vector(CQualTypeId { ctype, .. }, len) = resolved_ty {
             self.implicit_vector_default(ctype, len, is_static)
+                .or_else(|_| self.zeroed_value(ty_id, is_static))
         } else {
-            Err(format_err!("Unsupported default initializer: {:?}", resolved_ty).into())
+            self.zeroed_value(ty_id, is_static)
         }
     }
 
@@ -4361,7 +5895,7 @@ impl<'c> Translation<'c> {
         }
 
         // Look up the decl in the cache and return what we find (if we find anything)
-        if let Some(init) = self.zero_inits.borrow().get(&decl_id) {
+        if let Some(init) = self.zero_inits.borrow().get(&(decl_id, is_static)) {
             return Ok(init.clone());
         }
 
@@ -4388,7 +5922,7 @@ impl<'c> Translation<'c> {
                 ))
             },
 
-            // Zero initialize the first field
+            // Zero initialize a field covering the whole union
             CDeclKind::Union { ref fields, .. } => {
                 let name = self
                     .type_converter
@@ -4396,18 +5930,16 @@ impl<'c> Translation<'c> {
                     .resolve_decl_name(decl_id)
                     .unwrap();
 
-                let fields = match *fields {
-                    Some(ref fields) => fields,
-                    None => {
-                        return Err(TranslationError::generic(
-                            "Attempted to zero-initialize forward-declared struct",
-                        ))
-                    }
-                };
+                if fields.is_none() {
+                    return Err(TranslationError::generic(
+                        "Attempted to zero-initialize forward-declared struct",
+                    ));
+                }
 
-                let &field_id = fields
-                    .first()
-                    .ok_or(format_err!("A union should have a field"))?;
+                let field_id = match self.union_covering_field(decl_id) {
+                    Some(field_id) => field_id,
+                    None => return self.zeroed_value(type_id, is_static),
+                };
 
                 let field = match self.ast_context.index(field_id).kind {
                     CDeclKind::Field { typ, .. } => {
@@ -4452,13 +5984,107 @@ impl<'c> Translation<'c> {
 
         if init.is_pure() {
             // Insert the initializer into the cache, then return it
-            self.zero_inits.borrow_mut().insert(decl_id, init.clone());
+            self.zero_inits.borrow_mut().insert((decl_id, is_static), init.clone());
             Ok(init)
         } else {
             Err(TranslationError::generic("Expected no statements in zero initializer"))
         }
     }
 
+    /// Build a `memoffset` `offset_of!(Struct, field)` invocation, or
+    /// `offset_of!(Struct, field[index])` if an array index is given.
+    fn offset_of_expr(&self, decl_id: CDeclId, field_id: CDeclId, index: Option<P<Expr>>) -> P<Expr> {
+        self.use_crate(ExternCrate::Memoffset);
+
+        let name = self.resolve_decl_inner_name(decl_id);
+        let ty_ident = Nonterminal::NtIdent(mk().ident(name), false);
+
+        let field_name = self
+            .type_converter
+            .borrow()
+            .resolve_field_name(None, field_id)
+            .expect("Did not find name for offsetof struct field");
+        let field_ident = Nonterminal::NtIdent(mk().ident(field_name), false);
+
+        let mut macro_body = vec![
+            TokenTree::token(token::Interpolated(Rc::new(ty_ident)), DUMMY_SP),
+            TokenTree::token(token::Comma, DUMMY_SP),
+            TokenTree::token(token::Interpolated(Rc::new(field_ident)), DUMMY_SP),
+        ];
+        if let Some(index) = index {
+            let index_expr = Nonterminal::NtExpr(index);
+            macro_body.extend(vec![
+                TokenTree::token(token::OpenDelim(DelimToken::Bracket), DUMMY_SP),
+                TokenTree::token(token::Interpolated(Rc::new(index_expr)), DUMMY_SP),
+                TokenTree::token(token::CloseDelim(DelimToken::Bracket), DUMMY_SP),
+            ]);
+        }
+        let path = mk().path("offset_of");
+        mk().mac_expr(mk().mac(path, macro_body, MacDelimiter::Parenthesis))
+    }
+
+    /// Recognize the hand-written `offsetof` idiom `&((struct S *)0)->a.b`, which takes the
+    /// address of a field through a null pointer, and translate it into the sum of the
+    /// `offset_of!` of each field on the path.  Dereferencing null is undefined behavior in
+    /// Rust, so this must not be translated as written.  Returns `None` if `expr` does not match
+    /// the idiom.
+    fn convert_null_member_offset(&self, expr: CExprId) -> Option<P<Expr>> {
+        let strip = |mut e: CExprId| loop {
+            match self.ast_context[e].kind {
+                CExprKind::Paren(_, inner)
+                | CExprKind::ImplicitCast(_, inner, CastKind::BitCast, _, _)
+                | CExprKind::ExplicitCast(_, inner, CastKind::BitCast, _, _)
+                | CExprKind::ImplicitCast(_, inner, CastKind::NoOp, _, _)
+                | CExprKind::ExplicitCast(_, inner, CastKind::NoOp, _, _) => e = inner,
+                _ => return e,
+            }
+        };
+
+        let mut member = match self.ast_context[strip(expr)].kind {
+            CExprKind::Unary(_, c_ast::UnOp::AddressOf, arg, _) => strip(arg),
+            _ => return None,
+        };
+
+        // Walk the member path from the innermost field out to the null base pointer.
+        let mut fields = vec![];
+        loop {
+            match self.ast_context[member].kind {
+                CExprKind::Member(_, base, field_id, MemberKind::Dot, _) => {
+                    let base = strip(base);
+                    let record = self.ast_context[base].kind.get_type()?;
+                    fields.push((record, field_id));
+                    member = base;
+                }
+                CExprKind::Member(_, base, field_id, MemberKind::Arrow, _) => {
+                    let base = strip(base);
+                    if !self.ast_context.is_null_expr(base) {
+                        return None;
+                    }
+                    let pointer = self.ast_context[base].kind.get_type()?;
+                    let record = match self.ast_context.resolve_type(pointer).kind {
+                        CTypeKind::Pointer(pointee) => pointee.ctype,
+                        _ => return None,
+                    };
+                    fields.push((record, field_id));
+                    break;
+                }
+                _ => return None,
+            }
+        }
+
+        let mut offsets = fields
+            .into_iter()
+            .rev()
+            .map(|(record, field_id)| {
+                let decl_id = self.ast_context.resolve_type(record).kind.as_underlying_decl()?;
+                Some(self.offset_of_expr(decl_id, field_id, None))
+            })
+            .collect::<Option<Vec<_>>>()?
+            .into_iter();
+        let first = offsets.next()?;
+        Some(offsets.fold(first, |acc, offset| mk().binary_expr(BinOpKind::Add, acc, offset)))
+    }
+
     /// Resolve the inner name of a structure declaration
     /// if there is one (if the structure was split),
     /// otherwise just return the normal name
@@ -4533,7 +6159,9 @@ impl<'c> Translation<'c> {
             };
 
             // The backup is to just compare against zero
-            let zero = if ty.is_floating_type() {
+            let zero = if let CTypeKind::Half = *ty {
+                mk().path_expr(vec!["half", "f16", "ZERO"])
+            } else if ty.is_floating_type() {
                 mk().lit_expr(mk().float_unsuffixed_lit("0."))
             } else {
                 mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed))
@@ -4562,7 +6190,7 @@ impl<'c> Translation<'c> {
     fn insert_item(&self, mut item: P<Item>, decl: &CDecl) {
         let decl_file_id = self.ast_context.file_id(decl);
 
-        if self.tcfg.reorganize_definitions {
+        if self.tcfg.reorganize_definitions || self.is_split_file(decl_file_id) {
             add_src_loc_attr(&mut item.attrs, &decl.loc.as_ref().map(|x| x.begin()));
             let mut item_stores = self.items.borrow_mut();
             let items = item_stores
@@ -4580,7 +6208,7 @@ impl<'c> Translation<'c> {
     fn insert_foreign_item(&self, mut item: ForeignItem, decl: &CDecl) {
         let decl_file_id = self.ast_context.file_id(decl);
 
-        if self.tcfg.reorganize_definitions {
+        if self.tcfg.reorganize_definitions || self.is_split_file(decl_file_id) {
             add_src_loc_attr(&mut item.attrs, &decl.loc.as_ref().map(|x| x.begin()));
             let mut items = self.items.borrow_mut();
             let mod_block_items = items
@@ -4638,7 +6266,8 @@ impl<'c> Translation<'c> {
         match type_kind {
             // libc can be accessed from anywhere as of Rust 2019 by full path
             Void | Char | SChar | UChar | Short | UShort | Int | UInt | Long | ULong | LongLong
-            | ULongLong | Int128 | UInt128 | Half | Float | Double | LongDouble => {}
+            | ULongLong | Int128 | UInt128 | Half | Float | Double | LongDouble | BitInt(..)
+            | Unsupported(..) => {}
             // Bool uses the bool type, so no dependency on libc
             Bool => {}
             Paren(ctype)
@@ -4652,6 +6281,7 @@ impl<'c> Translation<'c> {
             | Reference(CQualTypeId { ctype, ..})
             | BlockPointer(CQualTypeId { ctype, .. })
             | TypeOf(ctype)
+            | Auto(ctype)
             | Complex(ctype) => {
                 self.import_type(*ctype, decl_file_id)
             }
@@ -4683,10 +6313,20 @@ impl<'c> Translation<'c> {
                     self.import_type(param_id.ctype, decl_file_id);
                 }
             }
-            Vector(..) => {
-                // Handled in `import_simd_typedef`
+            Vector(elt, len) => {
+                // Named vector typedefs are handled in `import_simd_typedef`
+                if self.tcfg.translate_simd {
+                    if let Some(name) = simd_vector_type_name(&self.ast_context, elt.ctype, *len) {
+                        self.import_simd_typedef(name);
+                    }
+                }
+            }
+            TypeOfExpr(expr) => {
+                if let Some(ctype) = self.ast_context[*expr].kind.get_type() {
+                    self.import_type(ctype, decl_file_id)
+                }
             }
-            TypeOfExpr(_) | BuiltinFn => {}
+            BuiltinFn => {}
         }
     }
 
@@ -4755,3 +6395,62 @@ impl<'c> Translation<'c> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clang_xcheck_config_checks_args_and_return() {
+        let entries = vec![
+            XCheckSourceEntry {
+                tag: djb2_hash("add"),
+                c_function: "add".to_string(),
+                c_file: Some(PathBuf::from("/src/int/math.c")),
+                c_line: Some(3),
+                rust_function: "rust_add".to_string(),
+            },
+            XCheckSourceEntry {
+                tag: djb2_hash("fadd"),
+                c_function: "fadd".to_string(),
+                c_file: Some(PathBuf::from("/src/float/math.c")),
+                c_line: Some(5),
+                rust_function: "fadd".to_string(),
+            },
+            XCheckSourceEntry {
+                tag: djb2_hash("main"),
+                c_function: "main".to_string(),
+                c_file: None,
+                c_line: None,
+                rust_function: "main_0".to_string(),
+            },
+        ];
+        let config: serde_json::Value =
+            serde_json::from_str(&clang_xcheck_config(&entries, path::Path::new("/src")))
+                .unwrap();
+        assert_eq!(
+            config,
+            serde_json::json!([{
+                "file": "**/int/math.c",
+                "items": [{
+                    "item": "function",
+                    "name": "add",
+                    "entry": { "djb2": "add" },
+                    "exit": { "djb2": "add" },
+                    "all_args": "default",
+                    "return": "default",
+                }],
+            }, {
+                "file": "**/float/math.c",
+                "items": [{
+                    "item": "function",
+                    "name": "fadd",
+                    "entry": { "djb2": "fadd" },
+                    "exit": { "djb2": "fadd" },
+                    "all_args": "default",
+                    "return": "default",
+                }],
+            }])
+        );
+    }
+}
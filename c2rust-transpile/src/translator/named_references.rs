@@ -78,7 +78,7 @@ impl<'c> Translation<'c> {
 
             // Given the LHS access to a variable, produce the RHS one
             let read = |write: P<Expr>| -> Result<P<Expr>, TranslationError> {
-                if reference_ty.qualifiers.is_volatile {
+                if self.ast_context.resolve_qualifiers(reference_ty).is_volatile {
                     self.volatile_read(&write, reference_ty)
                 } else {
                     Ok(write)
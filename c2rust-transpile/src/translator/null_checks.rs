@@ -0,0 +1,491 @@
+//! Pointers that are checked against `NULL` before every use. With
+//! `--option-pointers`, in
+//!
+//! ```c
+//! struct node *n = find(key);
+//! if (n != NULL) {
+//!     n->hits++;
+//! }
+//! ```
+//!
+//! `n` becomes an `Option<NonNull<node>>` initialized with `NonNull::new`, and
+//! the check becomes `if let Some(n) = n { ... }`, where `n.as_ptr()` is the
+//! raw pointer again. Function pointers are already `Option`s, so checked calls
+//! of them just skip the `expect`. This is only done if the pointer is never
+//! assigned after its declaration, every use is in a branch only taken when
+//! it isn't null, and the branches of those checks can be translated on their
+//! own, i.e. don't `break`, `continue` or `goto` out of them.
+//!
+//! Parameters qualify the same way, and are shadowed by their `Option` at the
+//! start of the body. A `static` function returning a pointer that only ever
+//! initializes such locals, like `find` above, returns the `Option` itself.
+
+use super::*;
+use crate::c_ast::iterators::DFNodes;
+
+/// An `if` statement checking whether a pointer is null
+#[derive(Debug, Clone, Copy)]
+pub struct NullCheck {
+    var: CDeclId,
+    /// The branch taken if the pointer isn't null
+    non_null: CStmtId,
+    /// The branch taken if it is
+    null: Option<CStmtId>,
+}
+
+impl<'c> Translation<'c> {
+    /// Find the pointers checked against null before every use in all
+    /// functions, and the functions returning pointers only their checked
+    /// locals are initialized with
+    pub fn find_option_pointers(&self) {
+        for &decl_id in &self.ast_context.c_decls_top {
+            if let CDeclKind::Function { ref parameters, body: Some(body), .. } =
+                self.ast_context[decl_id].kind
+            {
+                self.find_null_checks(parameters, body);
+            }
+        }
+        self.find_option_returns();
+    }
+
+    /// Find the parameters and local pointers of a function that are checked
+    /// against null before every use, and the checks to translate as `if let`
+    fn find_null_checks(&self, params: &[CDeclId], body: CStmtId) {
+        let ctx = &self.ast_context;
+        let mut candidates = HashSet::new();
+        let mut checks = vec![];
+        let params = params.iter().map(|&param| SomeId::Decl(param));
+        for node in params.chain(DFNodes::new(ctx, SomeId::Stmt(body))) {
+            match node {
+                SomeId::Decl(decl_id) => match ctx[decl_id].kind {
+                    CDeclKind::Variable {
+                        has_static_duration: false,
+                        has_thread_duration: false,
+                        typ,
+                        ..
+                    } if !typ.qualifiers.is_volatile => match ctx.resolve_type(typ.ctype).kind {
+                        CTypeKind::Pointer(pointee) => match ctx.resolve_type(pointee.ctype).kind {
+                            // K&R function pointers are called through a transmute
+                            CTypeKind::Function(_, _, _, _, false, _) => {}
+                            _ => {
+                                candidates.insert(decl_id);
+                            }
+                        },
+                        _ => {}
+                    },
+                    _ => {}
+                },
+                SomeId::Stmt(stmt_id) => {
+                    if let Some(check) = self.as_null_check(stmt_id) {
+                        checks.push((stmt_id, check));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Checks nested in a branch where the pointer is known not to be null
+        // are just uses of it
+        let mut nested = HashSet::new();
+        for &(_, outer) in &checks {
+            for node in DFNodes::new(ctx, SomeId::Stmt(outer.non_null)) {
+                if let SomeId::Stmt(stmt_id) = node {
+                    if checks.iter().any(|&(id, inner)| id == stmt_id && inner.var == outer.var) {
+                        nested.insert(stmt_id);
+                    }
+                }
+            }
+        }
+        checks.retain(|&(stmt_id, check)| {
+            candidates.contains(&check.var)
+                && !nested.contains(&stmt_id)
+                && !self.body_escapes(check.non_null, false, false)
+                && !check.null.map_or(false, |null| self.body_escapes(null, false, false))
+        });
+
+        let mut refs = HashMap::new();
+        let mut reads = HashMap::new();
+        let mut calls = HashMap::new();
+        for node in DFNodes::new(ctx, SomeId::Stmt(body)) {
+            let expr_id = match node {
+                SomeId::Expr(expr_id) => expr_id,
+                _ => continue,
+            };
+            match ctx[expr_id].kind {
+                CExprKind::DeclRef(_, decl_id, _) => *refs.entry(decl_id).or_insert(0) += 1,
+                CExprKind::ImplicitCast(_, _, CastKind::LValueToRValue, _, _) => {
+                    if let Some(decl_id) = self.read_var(expr_id) {
+                        *reads.entry(decl_id).or_insert(0) += 1;
+                    }
+                }
+                CExprKind::Call(_, func, _) => {
+                    if let Some(decl_id) = self.read_var(func) {
+                        *calls.entry(decl_id).or_insert(0) += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Every reference to the pointer must read it, either in a check or in
+        // a branch where it isn't null
+        let mut checked = HashMap::new();
+        for &(_, check) in &checks {
+            let in_branch = DFNodes::new(ctx, SomeId::Stmt(check.non_null))
+                .filter(|&node| match node {
+                    SomeId::Expr(expr_id) => match ctx[expr_id].kind {
+                        CExprKind::DeclRef(_, decl_id, _) => decl_id == check.var,
+                        _ => false,
+                    },
+                    _ => false,
+                })
+                .count();
+            let (num_checks, num_uses) = checked.entry(check.var).or_insert((0, 0));
+            *num_checks += 1;
+            *num_uses += in_branch;
+        }
+        let qualifies = |var: CDeclId| {
+            let (num_checks, num_uses) = match checked.get(&var) {
+                Some(&counts) => counts,
+                None => return false,
+            };
+            let num_refs = refs.get(&var).cloned().unwrap_or(0);
+            if reads.get(&var).cloned().unwrap_or(0) != num_refs || num_checks + num_uses != num_refs {
+                return false;
+            }
+            // A checked function pointer may only be called
+            !self.ast_context.is_function_pointer(self.var_type(var))
+                || calls.get(&var).cloned().unwrap_or(0) == num_uses
+        };
+
+        let mut option_pointers = self.option_pointers.borrow_mut();
+        let mut null_checks = self.null_checks.borrow_mut();
+        for (stmt_id, check) in checks {
+            if qualifies(check.var) {
+                option_pointers.insert(check.var);
+                null_checks.insert(stmt_id, check);
+            }
+        }
+    }
+
+    /// Find the `static` functions returning data pointers whose every
+    /// reference is a call initializing a local translated as an `Option`
+    fn find_option_returns(&self) {
+        let ctx = &self.ast_context;
+        let mut refs = HashMap::new();
+        let mut checked = HashMap::new();
+        for &top_id in &ctx.c_decls_top {
+            for node in DFNodes::new(ctx, SomeId::Decl(top_id)) {
+                match node {
+                    SomeId::Expr(expr_id) => {
+                        if let CExprKind::DeclRef(_, decl_id, _) = ctx[expr_id].kind {
+                            if let CDeclKind::Function { ref name, .. } = ctx[decl_id].kind {
+                                *refs.entry(name.as_str()).or_insert(0) += 1;
+                            }
+                        }
+                    }
+                    SomeId::Decl(decl_id) if self.is_option_pointer(decl_id) => {
+                        if let CDeclKind::Variable { initializer: Some(init), typ, .. } = ctx[decl_id].kind {
+                            if let Some((name, ret)) = self.direct_callee(init) {
+                                if ctx.resolve_type_id(ret) == ctx.resolve_type_id(typ.ctype) {
+                                    *checked.entry(name).or_insert(0) += 1;
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut option_returns = self.option_returns.borrow_mut();
+        for &decl_id in &ctx.c_decls_top {
+            let (name, typ) = match ctx[decl_id].kind {
+                CDeclKind::Function { is_global: false, ref name, typ, body: Some(_), .. } => (name, typ),
+                _ => continue,
+            };
+            // The type map changes the signature itself
+            if self.tcfg.type_map.has_function(self.c_function_name(name)) {
+                continue;
+            }
+            let returns_data_pointer = match ctx.resolve_type(typ).kind {
+                CTypeKind::Function(ret, ..) => match ctx.resolve_type(ret.ctype).kind {
+                    CTypeKind::Pointer(_) => !ctx.is_function_pointer(ret.ctype),
+                    _ => false,
+                },
+                _ => false,
+            };
+            let num_refs = refs.get(name.as_str()).cloned().unwrap_or(0);
+            if returns_data_pointer && num_refs > 0 && checked.get(name.as_str()) == Some(&num_refs) {
+                option_returns.insert(name.clone());
+            }
+        }
+    }
+
+    pub fn is_null_check(&self, stmt_id: CStmtId) -> bool {
+        self.null_checks.borrow().contains_key(&stmt_id)
+    }
+
+    /// Whether a local variable is translated as an `Option`, since it is
+    /// checked against null before every use
+    pub fn is_option_pointer(&self, decl_id: CDeclId) -> bool {
+        self.option_pointers.borrow().contains(&decl_id)
+    }
+
+    /// Whether a function returns an `Option`, since its callers check the
+    /// pointer it returns against null before every use
+    pub fn is_option_return(&self, name: &str) -> bool {
+        self.option_returns.borrow().contains(name)
+    }
+
+    /// The `Option<NonNull<T>>` type a data pointer to `pointee` is translated to
+    pub fn option_pointer_ty(&self, pointee: CTypeId) -> Result<P<Ty>, TranslationError> {
+        let pointee_ty = self.convert_type(pointee)?;
+        Ok(mk().path_ty(vec![mk().path_segment_with_args(
+            "Option",
+            mk().angle_bracketed_args(vec![mk().path_ty(self.non_null_path(Some(pointee_ty)))]),
+        )]))
+    }
+
+    /// Convert a value the function being translated returns to its `Option`
+    /// return type
+    pub fn convert_option_return(&self, val: P<Expr>) -> P<Expr> {
+        match *self.option_return_ty.borrow() {
+            Some(ref pointee_ty) => self.non_null_new(val, pointee_ty.clone()),
+            None => val,
+        }
+    }
+
+    /// Shadow a parameter checked against null by its `Option`
+    pub fn convert_option_pointer_param(
+        &self,
+        var: &str,
+        typ: CQualTypeId,
+    ) -> Result<Stmt, TranslationError> {
+        let pointee = match self.ast_context.resolve_type(typ.ctype).kind {
+            CTypeKind::Pointer(pointee) => pointee,
+            _ => return Err(TranslationError::generic("Expected a pointer parameter")),
+        };
+        let ty = self.option_pointer_ty(pointee.ctype)?;
+        let init = self.non_null_new(mk().ident_expr(var), self.convert_type(pointee.ctype)?);
+        let pat = mk().set_mutbl("mut").ident_pat(var);
+        Ok(mk().local_stmt(P(mk().local(pat, Some(ty), Some(init)))))
+    }
+
+    /// Whether a variable is a pointer bound by `if let Some(..)` where it is
+    /// referenced
+    pub fn is_unwrapped_pointer(&self, decl_id: CDeclId) -> bool {
+        self.unwrapped_pointers.borrow().contains(&decl_id)
+    }
+
+    /// Whether a callee is a function pointer bound by `if let Some(..)`
+    pub fn calls_unwrapped_pointer(&self, func: CExprId) -> bool {
+        self.read_var(func).map_or(false, |decl_id| self.is_unwrapped_pointer(decl_id))
+    }
+
+    /// Translate the declaration of a data pointer checked against null as an
+    /// `Option<NonNull<T>>`
+    pub fn convert_option_pointer_decl(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+    ) -> Result<cfg::DeclStmtInfo, TranslationError> {
+        let (ident, initializer, typ) = match self.ast_context[decl_id].kind {
+            CDeclKind::Variable { ref ident, initializer, typ, .. } => (ident, initializer, typ),
+            _ => return Err(TranslationError::generic("Expected a pointer variable")),
+        };
+        let pointee = match self.ast_context.resolve_type(typ.ctype).kind {
+            CTypeKind::Pointer(pointee) => pointee,
+            _ => return Err(TranslationError::generic("Expected a pointer variable")),
+        };
+        let rust_name = self
+            .renamer
+            .borrow_mut()
+            .insert(decl_id, &self.tcfg.naming.value_case.apply(ident))
+            .expect(&format!("Failed to insert variable '{}'", ident));
+
+        let ty = self.option_pointer_ty(pointee.ctype)?;
+        // Functions returning an `Option` are only called here
+        let returns_option = initializer
+            .and_then(|init| self.direct_callee(init))
+            .map_or(false, |(name, _)| self.is_option_return(name));
+
+        let (_, _, init) = self.convert_variable(ctx, initializer, typ)?;
+        let mut init = init?;
+        let mut stmts = vec![];
+        stmts.append(init.stmts_mut());
+        let init = if returns_option {
+            init.into_value()
+        } else {
+            self.non_null_new(init.into_value(), self.convert_type(pointee.ctype)?)
+        };
+
+        let pat = mk().set_mutbl("mut").ident_pat(rust_name.clone());
+        let decl = mk().local(pat.clone(), Some(ty.clone()), Some(mk().ident_expr("None")));
+        let local = mk().local(pat, Some(ty), Some(init.clone()));
+        let assign = mk().assign_expr(mk().ident_expr(rust_name), init);
+
+        let mut assign_stmts = stmts.clone();
+        assign_stmts.push(mk().semi_stmt(assign));
+        stmts.push(mk().local_stmt(P(local)));
+
+        Ok(cfg::DeclStmtInfo::new(
+            vec![mk().local_stmt(P(decl))],
+            assign_stmts,
+            stmts,
+        ))
+    }
+
+    /// Translate a null check of a pointer as `if let Some(p) = p`
+    pub fn convert_null_check(
+        &self,
+        ctx: ExprContext,
+        stmt_id: CStmtId,
+    ) -> Result<Vec<Stmt>, TranslationError> {
+        let check = self.null_checks.borrow()[&stmt_id];
+        let rust_name = self
+            .renamer
+            .borrow()
+            .get(&check.var)
+            .ok_or_else(|| format_err!("Null checked pointer not named"))?;
+        let fn_name = self.function_context.borrow().get_name().to_string();
+        let convert_branch = |branch: CStmtId| {
+            let stmt_ids = match self.ast_context[branch].kind {
+                CStmtKind::Compound(ref stmts) => stmts.clone(),
+                _ => vec![branch],
+            };
+            self.with_scope(|| {
+                self.convert_function_body(ctx, &fn_name, &stmt_ids, cfg::ImplicitReturnType::NestedBlock)
+            })
+        };
+
+        // Only the pointer bound by `if let` is unwrapped
+        let was_unwrapped = self.unwrapped_pointers.borrow_mut().insert(check.var);
+        let non_null = convert_branch(check.non_null);
+        if was_unwrapped {
+            self.unwrapped_pointers.borrow_mut().remove(&check.var);
+        }
+        let non_null = non_null?;
+        let null = match check.null {
+            Some(null) => Some(mk().block_expr(mk().block(convert_branch(null)?))),
+            None => None,
+        };
+
+        let pat = mk().tuple_struct_pat(vec!["Some"], vec![mk().ident_pat(&rust_name)]);
+        let cond = mk().let_expr(pat, mk().ident_expr(&rust_name));
+        let if_let = mk().ifte_expr(cond, mk().block(non_null), null);
+        Ok(vec![mk().expr_stmt(if_let)])
+    }
+
+    fn non_null_path(&self, args: Option<P<Ty>>) -> Vec<PathSegment> {
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let last = match args {
+            Some(ty) => mk().path_segment_with_args("NonNull", mk().angle_bracketed_args(vec![ty])),
+            None => mk().path_segment("NonNull"),
+        };
+        vec![mk().path_segment(""), mk().path_segment(std_or_core), mk().path_segment("ptr"), last]
+    }
+
+    /// `NonNull::new(ptr as *mut T)`
+    fn non_null_new(&self, ptr: P<Expr>, pointee_ty: P<Ty>) -> P<Expr> {
+        let ptr = mk().cast_expr(ptr, mk().mutbl().ptr_ty(pointee_ty));
+        let mut new_path = self.non_null_path(None);
+        new_path.push(mk().path_segment("new"));
+        mk().call_expr(mk().path_expr(new_path), vec![ptr])
+    }
+
+    /// The name and return type of the function an expression of the form
+    /// `f(...)` calls
+    fn direct_callee(&self, expr_id: CExprId) -> Option<(&str, CTypeId)> {
+        let ctx = &self.ast_context;
+        let func = match ctx[expr_id].kind {
+            CExprKind::Call(_, func, _) => func,
+            _ => return None,
+        };
+        let decl_id = match ctx[func].kind {
+            CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _) => match ctx[fexp].kind {
+                CExprKind::DeclRef(_, decl_id, _) => decl_id,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        match ctx[decl_id].kind {
+            CDeclKind::Function { ref name, typ, .. } => match ctx.resolve_type(typ).kind {
+                CTypeKind::Function(ret, ..) => Some((name.as_str(), ret.ctype)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The pointer a statement checks, if it is an `if` whose condition is
+    /// `p`, `!p`, `p != NULL` or `p == NULL`
+    fn as_null_check(&self, stmt_id: CStmtId) -> Option<NullCheck> {
+        let ctx = &self.ast_context;
+        let (scrutinee, true_variant, false_variant) = match ctx[stmt_id].kind {
+            CStmtKind::If { scrutinee, true_variant, false_variant } => {
+                (scrutinee, true_variant, false_variant)
+            }
+            _ => return None,
+        };
+        let (var, non_null_if_true) = self.null_check_condition(scrutinee)?;
+        if non_null_if_true {
+            Some(NullCheck { var, non_null: true_variant, null: false_variant })
+        } else {
+            Some(NullCheck { var, non_null: false_variant?, null: Some(true_variant) })
+        }
+    }
+
+    /// The pointer a condition checks, and whether it is true if the pointer
+    /// isn't null
+    fn null_check_condition(&self, cond: CExprId) -> Option<(CDeclId, bool)> {
+        let ctx = &self.ast_context;
+        if let Some(var) = self.read_var(cond) {
+            return Some((var, true));
+        }
+        match ctx[cond].kind {
+            CExprKind::ImplicitCast(_, expr, CastKind::PointerToBoolean, _, _) => {
+                Some((self.read_var(expr)?, true))
+            }
+            CExprKind::Unary(_, c_ast::UnOp::Not, expr, _) => {
+                self.read_var(expr).map(|var| (var, false))
+            }
+            CExprKind::Binary(_, op, lhs, rhs, _, _) => {
+                let non_null_if_true = match op {
+                    c_ast::BinOp::NotEqual => true,
+                    c_ast::BinOp::EqualEqual => false,
+                    _ => return None,
+                };
+                let var = if ctx.is_null_expr(rhs) {
+                    self.read_var(lhs)?
+                } else if ctx.is_null_expr(lhs) {
+                    self.read_var(rhs)?
+                } else {
+                    return None;
+                };
+                Some((var, non_null_if_true))
+            }
+            _ => None,
+        }
+    }
+
+    /// The variable an expression of the form `p` reads
+    fn read_var(&self, expr_id: CExprId) -> Option<CDeclId> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::ImplicitCast(_, var, CastKind::LValueToRValue, _, _) => {
+                match self.ast_context[var].kind {
+                    CExprKind::DeclRef(_, decl_id, _) => Some(decl_id),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn var_type(&self, decl_id: CDeclId) -> CTypeId {
+        match self.ast_context[decl_id].kind {
+            CDeclKind::Variable { typ, .. } => typ.ctype,
+            _ => panic!("Expected a variable"),
+        }
+    }
+}
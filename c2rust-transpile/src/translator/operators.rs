@@ -59,7 +59,13 @@ impl<'c> Translation<'c> {
         let rhs_loc = &self.ast_context[rhs].loc;
         match op {
             c_ast::BinOp::Comma => {
-                // The value of the LHS of a comma expression is always discarded
+                // The value of the LHS of a comma expression is always discarded,
+                // so there is nothing to emit for it unless it has side effects.
+                // Chains `a, b, c` nest to the left, so the statements for each
+                // operand end up in order before the value of the last one.
+                if self.ast_context.is_expr_pure(lhs) {
+                    return self.convert_expr(ctx, rhs);
+                }
                 self.convert_expr(ctx.unused(), lhs)?
                     .and_then(|_| self.convert_expr(ctx, rhs))
             }
@@ -68,7 +74,10 @@ impl<'c> Translation<'c> {
                 let lhs = self.convert_condition(ctx, true, lhs)?;
                 let rhs = self.convert_condition(ctx, true, rhs)?;
                 lhs
-                    .map(|x| bool_to_int(mk().binary_expr(BinOpKind::from(op), x, rhs.to_expr())))
+                    .map(|x| {
+                        let expr = mk().binary_expr(BinOpKind::from(op), x, rhs.to_expr());
+                        self.bool_to_expr_type(type_id.ctype, expr)
+                    })
                     .and_then(|out| {
                         if ctx.is_unused() {
                             Ok(WithStmts::new(
@@ -186,9 +195,15 @@ impl<'c> Translation<'c> {
         let compute_lhs_ty = compute_lhs_ty.unwrap();
         let compute_res_ty = compute_res_ty.unwrap();
 
+        let lhs_is_half = self.ast_context.resolve_type(lhs_ty.ctype).kind == CTypeKind::Half;
+
         if self.ast_context.resolve_type_id(compute_lhs_ty.ctype)
             == self.ast_context.resolve_type_id(lhs_ty.ctype)
         {
+            if lhs_is_half {
+                let val = self.half_arith_expr(bin_op_kind, read, rhs);
+                return Ok(WithStmts::new_val(mk().assign_expr(write, val)));
+            }
             Ok(WithStmts::new_val(mk().assign_op_expr(bin_op_kind, write, rhs)))
         } else {
             let resolved_computed_kind = &self.ast_context.resolve_type(compute_lhs_ty.ctype).kind;
@@ -202,6 +217,8 @@ impl<'c> Translation<'c> {
                 let args = vec![read];
 
                 mk().call_expr(fn_path, args)
+            } else if lhs_is_half {
+                mk().cast_expr(self.half_to_f64(read), lhs_type.clone())
             } else {
                 mk().cast_expr(read, lhs_type.clone())
             };
@@ -232,6 +249,8 @@ impl<'c> Translation<'c> {
                     let val = WithStmts::new_val(val);
 
                     self.f128_cast_to(val, resolved_lhs_kind)?
+                } else if lhs_is_half {
+                    WithStmts::new_val(self.f64_to_half(mk().cast_expr(val, mk().path_ty(vec!["f64"]))))
                 } else {
                     WithStmts::new_val(mk().cast_expr(val, result_type))
                 }
@@ -317,7 +336,7 @@ impl<'c> Translation<'c> {
             return self.convert_bitfield_assignment_op_with_rhs(ctx, op, lhs, rhs_expr, *field_id);
         }
 
-        let is_volatile = initial_lhs_type_id.qualifiers.is_volatile;
+        let is_volatile = self.ast_context.resolve_qualifiers(initial_lhs_type_id).is_volatile;
         let is_volatile_compound_assign = op.underlying_assignment().is_some() && is_volatile;
 
         let qtype_kind = &self.ast_context.resolve_type(qtype.ctype).kind;
@@ -331,20 +350,36 @@ impl<'c> Translation<'c> {
             _ => None,
         };
 
-        let is_unsigned_arith = match op {
+        let unsigned_rhs = self
+            .ast_context
+            .resolve_type(rhs_type_id.ctype)
+            .kind
+            .is_unsigned_integral_type();
+
+        // Wrapping arithmetic has no compound assignment form
+        let is_wrapping_arith = match op {
             c_ast::BinOp::AssignAdd
             | c_ast::BinOp::AssignSubtract
             | c_ast::BinOp::AssignMultiply
             | c_ast::BinOp::AssignDivide
-            | c_ast::BinOp::AssignModulus => compute_type_kind.is_unsigned_integral_type(),
+            | c_ast::BinOp::AssignModulus => {
+                compute_type_kind.is_unsigned_integral_type()
+                    || self.wraps_signed_arithmetic(ctx, compute_type_kind)
+            }
             _ => false,
         };
 
+        // Bit-precise integers must be masked after every update, which only happens on the
+        // desugared read/compute/write path.
+        let is_bit_int_arith =
+            op.underlying_assignment().is_some() && compute_type_kind.bit_int_storage_width().is_some();
+
         let lhs_translation = if initial_lhs_type_id.ctype != compute_lhs_type_id.ctype
             || ctx.is_used()
             || pointer_lhs.is_some()
             || is_volatile_compound_assign
-            || is_unsigned_arith
+            || is_wrapping_arith
+            || is_bit_int_arith
         {
             self.name_reference_write_read(ctx, lhs)?
         } else {
@@ -368,7 +403,7 @@ impl<'c> Translation<'c> {
                     }
 
                     // Anything volatile needs to be desugared into explicit reads and writes
-                    op if is_volatile || is_unsigned_arith => {
+                    op if is_volatile || is_wrapping_arith || is_bit_int_arith => {
                         let mut is_unsafe = false;
                         let op = op
                             .underlying_assignment()
@@ -433,12 +468,12 @@ impl<'c> Translation<'c> {
                     // Everything else
                     c_ast::BinOp::AssignAdd if pointer_lhs.is_some() => {
                         let mul = self.compute_size_of_expr(pointer_lhs.unwrap().ctype);
-                        let ptr = pointer_offset(write.clone(), rhs, mul, false, false);
+                        let ptr = pointer_offset(write.clone(), rhs, unsigned_rhs, mul, false, false);
                         WithStmts::new_val(mk().assign_expr(&write, ptr))
                     }
                     c_ast::BinOp::AssignSubtract if pointer_lhs.is_some() => {
                         let mul = self.compute_size_of_expr(pointer_lhs.unwrap().ctype);
-                        let ptr = pointer_offset(write.clone(), rhs, mul, true, false);
+                        let ptr = pointer_offset(write.clone(), rhs, unsigned_rhs, mul, true, false);
                         WithStmts::new_val(mk().assign_expr(&write, ptr))
                     }
 
@@ -596,7 +631,20 @@ impl<'c> Translation<'c> {
             .kind
             .is_unsigned_integral_type();
 
-        match op {
+        if let CTypeKind::Half = self.ast_context.resolve_type(ctype).kind {
+            let arith_op = match op {
+                c_ast::BinOp::Add => Some(BinOpKind::Add),
+                c_ast::BinOp::Subtract => Some(BinOpKind::Sub),
+                c_ast::BinOp::Multiply => Some(BinOpKind::Mul),
+                c_ast::BinOp::Divide => Some(BinOpKind::Div),
+                _ => None,
+            };
+            if let Some(arith_op) = arith_op {
+                return Ok(self.half_arith_expr(arith_op, lhs, rhs));
+            }
+        }
+
+        let val = match op {
             c_ast::BinOp::Add => self.convert_addition(ctx, lhs_type, rhs_type, lhs, rhs),
             c_ast::BinOp::Subtract => self.convert_subtraction(ctx, ty, lhs_type, rhs_type, lhs, rhs),
 
@@ -608,7 +656,7 @@ impl<'c> Translation<'c> {
                 }
                 Ok(mk().method_call_expr(lhs, mk().path_segment("wrapping_mul"), vec![rhs]))
             }
-            c_ast::BinOp::Multiply => Ok(mk().binary_expr(BinOpKind::Mul, lhs, rhs)),
+            c_ast::BinOp::Multiply => Ok(self.signed_arith_expr(ctx, ctype, BinOpKind::Mul, lhs, rhs)),
 
             c_ast::BinOp::Divide if is_unsigned_integral_type => {
                 if ctx.is_const {
@@ -618,7 +666,7 @@ impl<'c> Translation<'c> {
                 }
                 Ok(mk().method_call_expr(lhs, mk().path_segment("wrapping_div"), vec![rhs]))
             }
-            c_ast::BinOp::Divide => Ok(mk().binary_expr(BinOpKind::Div, lhs, rhs)),
+            c_ast::BinOp::Divide => Ok(self.signed_arith_expr(ctx, ctype, BinOpKind::Div, lhs, rhs)),
 
             c_ast::BinOp::Modulus if is_unsigned_integral_type => {
                 if ctx.is_const {
@@ -628,7 +676,7 @@ impl<'c> Translation<'c> {
                 }
                 Ok(mk().method_call_expr(lhs, mk().path_segment("wrapping_rem"), vec![rhs]))
             }
-            c_ast::BinOp::Modulus => Ok(mk().binary_expr(BinOpKind::Rem, lhs, rhs)),
+            c_ast::BinOp::Modulus => Ok(self.signed_arith_expr(ctx, ctype, BinOpKind::Rem, lhs, rhs)),
 
             c_ast::BinOp::BitXor => Ok(mk().binary_expr(BinOpKind::BitXor, lhs, rhs)),
 
@@ -655,7 +703,7 @@ impl<'c> Translation<'c> {
                     mk().binary_expr(BinOpKind::Eq, lhs, rhs)
                 };
 
-                Ok(bool_to_int(expr))
+                Ok(self.bool_to_expr_type(ctype, expr))
             }
             c_ast::BinOp::NotEqual => {
                 // Using is_some method for null comparison means we don't have to
@@ -677,17 +725,119 @@ impl<'c> Translation<'c> {
                     mk().binary_expr(BinOpKind::Ne, lhs, rhs)
                 };
 
-                Ok(bool_to_int(expr))
+                Ok(self.bool_to_expr_type(ctype, expr))
+            }
+            c_ast::BinOp::Less => {
+                Ok(self.bool_to_expr_type(ctype, mk().binary_expr(BinOpKind::Lt, lhs, rhs)))
+            }
+            c_ast::BinOp::Greater => {
+                Ok(self.bool_to_expr_type(ctype, mk().binary_expr(BinOpKind::Gt, lhs, rhs)))
+            }
+            c_ast::BinOp::GreaterEqual => {
+                Ok(self.bool_to_expr_type(ctype, mk().binary_expr(BinOpKind::Ge, lhs, rhs)))
+            }
+            c_ast::BinOp::LessEqual => {
+                Ok(self.bool_to_expr_type(ctype, mk().binary_expr(BinOpKind::Le, lhs, rhs)))
             }
-            c_ast::BinOp::Less => Ok(bool_to_int(mk().binary_expr(BinOpKind::Lt, lhs, rhs))),
-            c_ast::BinOp::Greater => Ok(bool_to_int(mk().binary_expr(BinOpKind::Gt, lhs, rhs))),
-            c_ast::BinOp::GreaterEqual => Ok(bool_to_int(mk().binary_expr(BinOpKind::Ge, lhs, rhs))),
-            c_ast::BinOp::LessEqual => Ok(bool_to_int(mk().binary_expr(BinOpKind::Le, lhs, rhs))),
 
             c_ast::BinOp::BitAnd => Ok(mk().binary_expr(BinOpKind::BitAnd, lhs, rhs)),
             c_ast::BinOp::BitOr => Ok(mk().binary_expr(BinOpKind::BitOr, lhs, rhs)),
 
             op => unimplemented!("Translation of binary operator {:?}", op),
+        }?;
+
+        match op {
+            c_ast::BinOp::Add
+            | c_ast::BinOp::Subtract
+            | c_ast::BinOp::Multiply
+            | c_ast::BinOp::Divide
+            | c_ast::BinOp::ShiftLeft => Ok(self.wrap_bit_int(ctype, val)),
+            _ => Ok(val),
+        }
+    }
+
+    /// Whether an expression names a struct or union field or a variable with
+    /// static storage, whose address is taken with `&raw`
+    fn is_field_or_static(&self, expr_id: CExprId) -> bool {
+        match self.ast_context[expr_id].kind {
+            CExprKind::Member(..) => true,
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Variable {
+                    has_static_duration: true,
+                    has_thread_duration: false,
+                    ..
+                } => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether arithmetic on values of this type must avoid Rust's plain
+    /// operators, as chosen by `--signed-arithmetic`. Wrapping methods and
+    /// operators on `Wrapping` can't be used in constants.
+    fn wraps_signed_arithmetic(&self, ctx: ExprContext, kind: &CTypeKind) -> bool {
+        self.tcfg.signed_arithmetic != SignedArithmeticMode::Plain
+            && !ctx.is_const
+            && kind.is_signed_integral_type()
+    }
+
+    /// Build `lhs op rhs` for operands of the given type, using wrapping
+    /// arithmetic for signed integers if `--signed-arithmetic` asks for it
+    fn signed_arith_expr(
+        &self,
+        ctx: ExprContext,
+        ctype: CTypeId,
+        op: BinOpKind,
+        lhs: P<Expr>,
+        rhs: P<Expr>,
+    ) -> P<Expr> {
+        let method = match op {
+            BinOpKind::Add => "wrapping_add",
+            BinOpKind::Sub => "wrapping_sub",
+            BinOpKind::Mul => "wrapping_mul",
+            BinOpKind::Div => "wrapping_div",
+            BinOpKind::Rem => "wrapping_rem",
+            _ => return mk().binary_expr(op, lhs, rhs),
+        };
+        if !self.wraps_signed_arithmetic(ctx, &self.ast_context.resolve_type(ctype).kind) {
+            return mk().binary_expr(op, lhs, rhs);
+        }
+        match self.tcfg.signed_arithmetic {
+            SignedArithmeticMode::WrappingType => {
+                let val = mk().binary_expr(op, self.wrapping_value(lhs), self.wrapping_value(rhs));
+                mk().field_expr(mk().paren_expr(val), "0")
+            }
+            _ => mk().method_call_expr(lhs, method, vec![rhs]),
+        }
+    }
+
+    /// `std::num::Wrapping(val)`
+    fn wrapping_value(&self, val: P<Expr>) -> P<Expr> {
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let path = mk().path_expr(vec!["", std_or_core, "num", "Wrapping"]);
+        mk().call_expr(path, vec![val])
+    }
+
+    /// Truncate the result of an arithmetic operation on a `_BitInt(N)` back to `N` bits.
+    /// Unsigned values are masked and signed values are sign-extended from bit `N - 1`. Values
+    /// of any other type, or whose width matches their storage type, are returned unchanged.
+    pub fn wrap_bit_int(&self, ctype: CTypeId, val: P<Expr>) -> P<Expr> {
+        let kind = &self.ast_context.resolve_type(ctype).kind;
+        let (signed, width, storage) = match (kind, kind.bit_int_storage_width()) {
+            (&CTypeKind::BitInt(signed, width), Some(storage)) if width < storage => {
+                (signed, width, storage)
+            }
+            _ => return val,
+        };
+
+        if signed {
+            let shift = mk().lit_expr(mk().int_lit((storage - width) as u128, LitIntType::Unsuffixed));
+            let shifted = mk().paren_expr(mk().binary_expr(BinOpKind::Shl, val, shift.clone()));
+            mk().paren_expr(mk().binary_expr(BinOpKind::Shr, shifted, shift))
+        } else {
+            let mask = mk().lit_expr(mk().int_lit((1u128 << width) - 1, LitIntType::Unsuffixed));
+            mk().paren_expr(mk().binary_expr(BinOpKind::BitAnd, val, mask))
         }
     }
 
@@ -704,10 +854,12 @@ impl<'c> Translation<'c> {
 
         if let &CTypeKind::Pointer(pointee) = lhs_type {
             let mul = self.compute_size_of_expr(pointee.ctype);
-            Ok(pointer_offset(lhs, rhs, mul, false, false))
+            let unsigned_offset = rhs_type.is_unsigned_integral_type();
+            Ok(pointer_offset(lhs, rhs, unsigned_offset, mul, false, false))
         } else if let &CTypeKind::Pointer(pointee) = rhs_type {
             let mul = self.compute_size_of_expr(pointee.ctype);
-            Ok(pointer_offset(rhs, lhs, mul, false, false))
+            let unsigned_offset = lhs_type.is_unsigned_integral_type();
+            Ok(pointer_offset(rhs, lhs, unsigned_offset, mul, false, false))
         } else if lhs_type.is_unsigned_integral_type() {
             if ctx.is_const {
                 return Err(TranslationError::generic(
@@ -716,7 +868,7 @@ impl<'c> Translation<'c> {
             }
             Ok(mk().method_call_expr(lhs, mk().path_segment("wrapping_add"), vec![rhs]))
         } else {
-            Ok(mk().binary_expr(BinOpKind::Add, lhs, rhs))
+            Ok(self.signed_arith_expr(ctx, lhs_type_id.ctype, BinOpKind::Add, lhs, rhs))
         }
     }
 
@@ -753,7 +905,8 @@ impl<'c> Translation<'c> {
             Ok(mk().cast_expr(offset, ty))
         } else if let &CTypeKind::Pointer(pointee) = lhs_type {
             let mul = self.compute_size_of_expr(pointee.ctype);
-            Ok(pointer_offset(lhs, rhs, mul, true, false))
+            let unsigned_offset = rhs_type.is_unsigned_integral_type();
+            Ok(pointer_offset(lhs, rhs, unsigned_offset, mul, true, false))
         } else if lhs_type.is_unsigned_integral_type() {
             if ctx.is_const {
                 return Err(TranslationError::generic(
@@ -762,7 +915,7 @@ impl<'c> Translation<'c> {
             }
             Ok(mk().method_call_expr(lhs, mk().path_segment("wrapping_sub"), vec![rhs]))
         } else {
-            Ok(mk().binary_expr(BinOpKind::Sub, lhs, rhs))
+            Ok(self.signed_arith_expr(ctx, lhs_type_id.ctype, BinOpKind::Sub, lhs, rhs))
         }
     }
 
@@ -779,8 +932,7 @@ impl<'c> Translation<'c> {
             c_ast::BinOp::AssignSubtract
         };
         let one = match self.ast_context.resolve_type(ty.ctype).kind {
-            // TODO: If rust gets f16 support:
-            // CTypeKind::Half |
+            CTypeKind::Half => mk().path_expr(vec!["half", "f16", "ONE"]),
             CTypeKind::Float | CTypeKind::Double => mk().lit_expr(mk().float_unsuffixed_lit("1.")),
             CTypeKind::LongDouble => {
                 self.use_crate(ExternCrate::F128);
@@ -796,6 +948,17 @@ impl<'c> Translation<'c> {
             .kind
             .get_qual_type()
             .ok_or_else(|| format_err!("bad arg type"))?;
+
+        if self.wraps_narrow_signed_step(ctx, arg_type) {
+            return self.name_reference_write_read(ctx, arg)?.and_then(|(write, read)| {
+                let val = self.narrow_signed_step(up, read.clone());
+                Ok(WithStmts::new(
+                    vec![mk().semi_stmt(mk().assign_expr(&write, val))],
+                    read,
+                ))
+            });
+        }
+
         self.convert_assignment_operator_with_rhs(
             ctx.used(),
             op,
@@ -836,9 +999,8 @@ impl<'c> Translation<'c> {
                     Some(read.clone()),
                 )));
 
-                let mut one = match self.ast_context[ty.ctype].kind {
-                    // TODO: If rust gets f16 support:
-                    // CTypeKind::Half |
+                let mut one = match self.ast_context.resolve_type(ty.ctype).kind {
+                    CTypeKind::Half => mk().path_expr(vec!["half", "f16", "ONE"]),
                     CTypeKind::Float | CTypeKind::Double => mk().lit_expr(mk().float_unsuffixed_lit("1.")),
                     CTypeKind::LongDouble => {
                         self.use_crate(ExternCrate::F128);
@@ -863,7 +1025,7 @@ impl<'c> Translation<'c> {
                         } else {
                             mk().unary_expr(ast::UnOp::Neg, one)
                         };
-                        mk().method_call_expr(read.clone(), "offset", vec![n])
+                        mk().method_call_expr(read.clone(), "wrapping_offset", vec![n])
                     } else {
                         if self
                             .ast_context
@@ -878,14 +1040,20 @@ impl<'c> Translation<'c> {
                             }
                             let m = if up { "wrapping_add" } else { "wrapping_sub" };
                             mk().method_call_expr(read.clone(), m, vec![one])
+                        } else if self.wraps_narrow_signed_step(ctx, ty) {
+                            self.narrow_signed_step(up, read.clone())
+                        } else if let CTypeKind::Half = self.ast_context.resolve_type(ty.ctype).kind {
+                            let k = if up { BinOpKind::Add } else { BinOpKind::Sub };
+                            self.half_arith_expr(k, read.clone(), one)
                         } else {
                             let k = if up { BinOpKind::Add } else { BinOpKind::Sub };
-                            mk().binary_expr(k, read.clone(), one)
+                            self.signed_arith_expr(ctx, ty.ctype, k, read.clone(), one)
                         }
                     };
+                let val = self.wrap_bit_int(ty.ctype, val);
 
                 // *p = *p + rhs
-                let assign_stmt = if ty.qualifiers.is_volatile {
+                let assign_stmt = if self.ast_context.resolve_qualifiers(ty).is_volatile {
                     self.volatile_write(&write, ty, val)?
                 } else {
                     mk().assign_expr(&write, val)
@@ -898,6 +1066,34 @@ impl<'c> Translation<'c> {
             })
     }
 
+    /// Whether `++`/`--` on a value of this type must be translated like C's
+    /// promoted arithmetic: C computes `c + 1` in `int` for a `signed char` or
+    /// `short` and converts the result back, which wraps around where the plain
+    /// Rust addition would overflow
+    fn wraps_narrow_signed_step(&self, ctx: ExprContext, ty: CQualTypeId) -> bool {
+        let kind = &self.ast_context.resolve_type(ty.ctype).kind;
+        self.tcfg.integer_conversions != IntegerConversionMode::Default
+            && !ctx.is_const
+            && !self.ast_context.resolve_qualifiers(ty).is_volatile
+            && kind.is_signed_integral_type()
+            && kind.integer_conversion_rank() < CTypeKind::Int.integer_conversion_rank()
+    }
+
+    /// Step a narrow signed value by one, wrapping around or, with
+    /// `IntegerConversionMode::Checked`, panicking where C's conversion back
+    /// from `int` would change the value
+    fn narrow_signed_step(&self, up: bool, val: P<Expr>) -> P<Expr> {
+        let one = mk().lit_expr(mk().int_lit(1, LitIntType::Unsuffixed));
+        if self.tcfg.integer_conversions == IntegerConversionMode::Checked {
+            let m = if up { "checked_add" } else { "checked_sub" };
+            let msg = mk().lit_expr("implicit integer conversion changed the value");
+            mk().method_call_expr(mk().method_call_expr(val, m, vec![one]), "expect", vec![msg])
+        } else {
+            let m = if up { "wrapping_add" } else { "wrapping_sub" };
+            mk().method_call_expr(val, m, vec![one])
+        }
+    }
+
     pub fn convert_unary_operator(
         &self,
         mut ctx: ExprContext,
@@ -913,6 +1109,13 @@ impl<'c> Translation<'c> {
         match name {
             c_ast::UnOp::AddressOf => {
                 let arg_kind = &self.ast_context[arg].kind;
+                let is_field_or_static = self.is_field_or_static(arg);
+                let is_checked_address = self.tcfg.checked_indexing
+                    && !ctx.is_static
+                    && match arg_kind {
+                        CExprKind::ArraySubscript(..) => true,
+                        _ => false,
+                    };
 
                 match arg_kind {
                     // C99 6.5.3.2 para 4
@@ -949,6 +1152,15 @@ impl<'c> Translation<'c> {
                     arg.result_map(|a| {
                         let mut addr_of_arg: P<Expr>;
 
+                        // With `--checked-indexing`, the element is read through
+                        // a pointer into the sliced array, which may point one
+                        // past its end, so the pointer is used without reborrowing
+                        if is_checked_address {
+                            if let ExprKind::Unary(ast::UnOp::Deref, ref ptr) = a.kind {
+                                return Ok(mk().cast_expr(ptr.clone(), ty));
+                            }
+                        }
+
                         if ctx.is_static {
                             // static variable initializers aren't able to use &mut,
                             // so we work around that by using & and an extra cast
@@ -963,6 +1175,12 @@ impl<'c> Translation<'c> {
                                     .convert_pointer(&self.ast_context, qtype)?;
                                 addr_of_arg = mk().cast_expr(addr_of_arg, ty_);
                             }
+                        } else if is_field_or_static {
+                            // A reference to a field may be unaligned and one to a
+                            // `static mut` may alias, so take a raw pointer without
+                            // creating a reference: `&raw mut place` or `&raw const place`
+                            self.use_feature("raw_ref_op");
+                            addr_of_arg = mk().set_mutbl(mutbl).raw_addr_of_expr(a);
                         } else {
                             // Normal case is allowed to use &mut if needed
                             addr_of_arg = mk().set_mutbl(mutbl).addr_of_expr(a);
@@ -1020,18 +1238,31 @@ impl<'c> Translation<'c> {
                             "Cannot use wrapping negate in a const expression",
                         ));
                     }
-                    Ok(val.map(wrapping_neg_expr))
+                    Ok(val.map(wrapping_neg_expr).map(|v| self.wrap_bit_int(ctype, v)))
+                } else if self.wraps_signed_arithmetic(ctx, &resolved_ctype.kind) {
+                    let val = if self.tcfg.signed_arithmetic == SignedArithmeticMode::WrappingType {
+                        val.map(|v| {
+                            let neg = neg_expr(self.wrapping_value(v));
+                            mk().field_expr(mk().paren_expr(neg), "0")
+                        })
+                    } else {
+                        val.map(wrapping_neg_expr)
+                    };
+                    Ok(val.map(|v| self.wrap_bit_int(ctype, v)))
+                } else if let CTypeKind::Half = resolved_ctype.kind {
+                    Ok(val.map(|v| self.f64_to_half(neg_expr(self.half_to_f64(v)))))
                 } else {
-                    Ok(val.map(neg_expr))
+                    Ok(val.map(neg_expr).map(|v| self.wrap_bit_int(ctype, v)))
                 }
             }
             c_ast::UnOp::Complement => Ok(self
                 .convert_expr(ctx.used(), arg)?
-                .map(|a| mk().unary_expr(ast::UnOp::Not, a))),
+                .map(|a| mk().unary_expr(ast::UnOp::Not, a))
+                .map(|v| self.wrap_bit_int(ctype, v))),
 
             c_ast::UnOp::Not => {
                 let val = self.convert_condition(ctx, false, arg)?;
-                Ok(val.map(|x| mk().cast_expr(x, mk().path_ty(vec!["libc", "c_int"]))))
+                Ok(val.map(|x| self.bool_to_expr_type(ctype, x)))
             }
             c_ast::UnOp::Extension => {
                 let arg = self.convert_expr(ctx, arg)?;
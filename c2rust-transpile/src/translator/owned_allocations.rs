@@ -0,0 +1,366 @@
+//! Heap allocations owned by a single local pointer. With
+//! `--owned-allocations`, in
+//!
+//! ```c
+//! struct node *n = malloc(sizeof(struct node));
+//! int *buf = malloc(len * sizeof(int));
+//! ...
+//! free(buf);
+//! free(n);
+//! ```
+//!
+//! `n` becomes a `Box<node>` and `buf` a `Vec<c_int>` of `len` elements, and
+//! the calls to `free` drop them. This is only done if every other use of the
+//! pointer goes through `*n` or `n->field`, or indexes `buf[i]`, and the
+//! allocation is freed exactly once, by a statement of the block declaring the
+//! pointer. Allocations that look like this but don't qualify are listed in
+//! the report.
+
+use super::*;
+use crate::c_ast::iterators::DFNodes;
+use crate::report::RawAllocation;
+
+/// How an allocation owned by a local pointer is translated
+#[derive(Debug, Clone, Copy)]
+pub enum OwnedAllocation {
+    /// `malloc(sizeof(T))`, as a `Box<T>`
+    Box,
+    /// `malloc(n * sizeof(T))`, as a `Vec<T>` of `n` elements
+    Vec(CExprId),
+}
+
+impl<'c> Translation<'c> {
+    /// Find the local pointers in a function body that own an allocation and
+    /// remember how to translate each one, reporting those that don't qualify
+    pub fn find_owned_allocations(&self, name: &str, body: CStmtId) {
+        let ctx = &self.ast_context;
+        // In the order they are declared, which the report lists them in
+        let mut candidates = IndexMap::new();
+        for node in DFNodes::new(ctx, SomeId::Stmt(body)) {
+            if let SomeId::Decl(decl_id) = node {
+                if let CDeclKind::Variable {
+                    has_static_duration: false,
+                    has_thread_duration: false,
+                    initializer: Some(init),
+                    typ,
+                    ..
+                } = ctx[decl_id].kind
+                {
+                    if let CTypeKind::Pointer(pointee) = ctx.resolve_type(typ.ctype).kind {
+                        if let Some(alloc) = self.malloc_allocation(init, pointee.ctype) {
+                            candidates.insert(decl_id, alloc);
+                        }
+                    }
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return;
+        }
+
+        // Every reference to the pointer must be one of these
+        let mut uses = HashMap::new();
+        let mut owned_uses = HashMap::new();
+        let mut escaping = HashSet::new();
+        // The blocks declaring each pointer and freeing it
+        let mut declared_in = HashMap::new();
+        let mut freed_in = HashMap::new();
+        for node in DFNodes::new(ctx, SomeId::Stmt(body)) {
+            match node {
+                SomeId::Expr(expr_id) => match ctx[expr_id].kind {
+                    CExprKind::DeclRef(_, decl_id, _) if candidates.contains_key(&decl_id) => {
+                        *uses.entry(decl_id).or_insert(0) += 1;
+                    }
+                    CExprKind::Unary(_, c_ast::UnOp::Deref, arg, _) => {
+                        if let Some(decl_id) = self.pointer_var(arg) {
+                            if let Some(OwnedAllocation::Box) = candidates.get(&decl_id) {
+                                *owned_uses.entry(decl_id).or_insert(0) += 1;
+                            }
+                        }
+                    }
+                    CExprKind::Member(_, base, _, MemberKind::Arrow, _) => {
+                        if let Some(decl_id) = self.pointer_var(base) {
+                            if let Some(OwnedAllocation::Box) = candidates.get(&decl_id) {
+                                *owned_uses.entry(decl_id).or_insert(0) += 1;
+                            }
+                        }
+                    }
+                    CExprKind::ArraySubscript(_, lhs, rhs, _) => {
+                        for &base in &[lhs, rhs] {
+                            if let Some(decl_id) = self.pointer_var(base) {
+                                if let Some(OwnedAllocation::Vec(_)) = candidates.get(&decl_id) {
+                                    *owned_uses.entry(decl_id).or_insert(0) += 1;
+                                }
+                            }
+                        }
+                    }
+                    // `&*p` is the pointer itself, and `&p[n]` may point one
+                    // past the end
+                    CExprKind::Unary(_, c_ast::UnOp::AddressOf, arg, _) => match ctx[arg].kind {
+                        CExprKind::Unary(_, c_ast::UnOp::Deref, base, _)
+                        | CExprKind::ArraySubscript(_, base, _, _) => {
+                            escaping.extend(self.pointer_var(base));
+                        }
+                        _ => {}
+                    },
+                    _ => {}
+                },
+                SomeId::Stmt(stmt_id) => {
+                    if let CStmtKind::Compound(ref stmts) = ctx[stmt_id].kind {
+                        for &stmt in stmts {
+                            match ctx[stmt].kind {
+                                CStmtKind::Decls(ref decls) => {
+                                    for &decl_id in decls {
+                                        declared_in.insert(decl_id, stmt_id);
+                                    }
+                                }
+                                CStmtKind::Expr(expr) => {
+                                    if let Some(decl_id) = self.freed_var(expr) {
+                                        *owned_uses.entry(decl_id).or_insert(0) += 1;
+                                        freed_in.entry(decl_id).or_insert_with(Vec::new).push(stmt_id);
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut owned = self.owned_allocations.borrow_mut();
+        let mut report = self.report.borrow_mut();
+        for (decl_id, alloc) in candidates {
+            let block = declared_in.get(&decl_id);
+            let reason = if escaping.contains(&decl_id) || uses.get(&decl_id) != owned_uses.get(&decl_id) {
+                match alloc {
+                    OwnedAllocation::Box => "the pointer is used other than through `*` or `->`",
+                    OwnedAllocation::Vec(_) => "the pointer is used other than by indexing it",
+                }
+            } else if block.is_none() || freed_in.get(&decl_id).map(Vec::as_slice) != block.map(slice::from_ref) {
+                "the allocation isn't freed exactly once, by the block declaring the pointer"
+            } else {
+                owned.insert(decl_id, alloc);
+                continue;
+            };
+            let decl = &ctx[decl_id];
+            report.raw_allocations.push(RawAllocation {
+                function: name.to_string(),
+                variable: decl.kind.get_name().cloned().unwrap_or_default(),
+                c_line: decl.loc.map(|loc| loc.begin_line),
+                reason: reason.to_string(),
+            });
+        }
+    }
+
+    /// How a local variable that owns its allocation is translated, if it does
+    pub fn owned_allocation(&self, decl_id: CDeclId) -> Option<OwnedAllocation> {
+        self.owned_allocations.borrow().get(&decl_id).cloned()
+    }
+
+    /// The `Vec` a subscript base refers to, if it is a pointer that owns its
+    /// allocation
+    pub fn owned_vec(&self, base: CExprId) -> Option<CDeclId> {
+        let decl_id = self.pointer_var(base)?;
+        match self.owned_allocation(decl_id)? {
+            OwnedAllocation::Vec(_) => Some(decl_id),
+            OwnedAllocation::Box => None,
+        }
+    }
+
+    /// Translate the declaration of a pointer that owns its allocation
+    pub fn convert_owned_allocation_decl(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+        alloc: OwnedAllocation,
+    ) -> Result<cfg::DeclStmtInfo, TranslationError> {
+        let (ident, typ) = match self.ast_context[decl_id].kind {
+            CDeclKind::Variable { ref ident, typ, .. } => (ident, typ),
+            _ => return Err(TranslationError::generic("Expected an owned allocation variable")),
+        };
+        let pointee = match self.ast_context.resolve_type(typ.ctype).kind {
+            CTypeKind::Pointer(pointee) => pointee,
+            _ => return Err(TranslationError::generic("Expected an owned allocation pointer")),
+        };
+        let rust_name = self
+            .renamer
+            .borrow_mut()
+            .insert(decl_id, &self.tcfg.naming.value_case.apply(ident))
+            .expect(&format!("Failed to insert variable '{}'", ident));
+
+        let elt_ty = self.convert_type(pointee.ctype)?;
+        let zeroed = self.implicit_default_expr(pointee.ctype, false)?;
+        let (ty, init) = match alloc {
+            OwnedAllocation::Box => {
+                let ty = mk().path_ty(vec![
+                    mk().path_segment_with_args("Box", mk().angle_bracketed_args(vec![elt_ty])),
+                ]);
+                let init = zeroed.map(|zeroed| {
+                    mk().call_expr(mk().path_expr(vec!["Box", "new"]), vec![zeroed])
+                });
+                (ty, init)
+            }
+            OwnedAllocation::Vec(count) => {
+                let ty = mk().path_ty(vec![
+                    mk().path_segment_with_args("Vec", mk().angle_bracketed_args(vec![elt_ty])),
+                ]);
+                let count = self.convert_expr(ctx.used(), count)?;
+                let init = zeroed.and_then(|zeroed| {
+                    count.map(|count| {
+                        let args = vec![
+                            TokenTree::token(
+                                token::Interpolated(Rc::new(Nonterminal::NtExpr(zeroed))),
+                                DUMMY_SP,
+                            ),
+                            TokenTree::token(token::Semi, DUMMY_SP),
+                            TokenTree::token(
+                                token::Interpolated(Rc::new(Nonterminal::NtExpr(
+                                    cast_int(count, "usize", false),
+                                ))),
+                                DUMMY_SP,
+                            ),
+                        ];
+                        mk().mac_expr(mk().mac(vec!["vec"], args, MacDelimiter::Bracket))
+                    })
+                });
+                (ty, init)
+            }
+        };
+        let mut init = init;
+        let mut stmts = vec![];
+        stmts.append(init.stmts_mut());
+        let init = init.into_value();
+
+        let pat = mk().set_mutbl("mut").ident_pat(rust_name.clone());
+        let decl = mk().local::<_, _, P<Expr>>(pat.clone(), Some(ty.clone()), None);
+        let local = mk().local(pat, Some(ty), Some(init.clone()));
+        let assign = mk().assign_expr(mk().ident_expr(rust_name), init);
+
+        let mut assign_stmts = stmts.clone();
+        assign_stmts.push(mk().semi_stmt(assign));
+        stmts.push(mk().local_stmt(P(local)));
+
+        Ok(cfg::DeclStmtInfo::new(
+            vec![mk().local_stmt(P(decl))],
+            assign_stmts,
+            stmts,
+        ))
+    }
+
+    /// Translate `free(p)` of a pointer that owns its allocation as dropping
+    /// the `Box` or `Vec`
+    pub fn convert_owned_free(
+        &self,
+        call: CExprId,
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        let decl_id = match self.freed_var(call) {
+            Some(decl_id) if self.owned_allocation(decl_id).is_some() => decl_id,
+            _ => return Ok(None),
+        };
+        let rust_name = self
+            .renamer
+            .borrow()
+            .get(&decl_id)
+            .ok_or_else(|| format_err!("Owned allocation not named"))?;
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let drop = mk().path_expr(vec!["", std_or_core, "mem", "drop"]);
+        let val = mk().call_expr(drop, vec![mk().ident_expr(rust_name)]);
+        Ok(Some(WithStmts::new_val(val)))
+    }
+
+    /// If an initializer allocates memory for values of the pointee type with
+    /// `malloc`, how it would be translated
+    fn malloc_allocation(&self, init: CExprId, pointee: CTypeId) -> Option<OwnedAllocation> {
+        let ctx = &self.ast_context;
+        let mut init = init;
+        while let CExprKind::ImplicitCast(_, expr, CastKind::BitCast, _, _)
+        | CExprKind::ExplicitCast(_, expr, CastKind::BitCast, _, _) = ctx[init].kind
+        {
+            init = expr;
+        }
+        let size = match ctx[init].kind {
+            CExprKind::Call(_, func, ref args) if args.len() == 1 && self.calls_named(func, "malloc") => {
+                args[0]
+            }
+            _ => return None,
+        };
+        if self.is_size_of(size, pointee) {
+            return Some(OwnedAllocation::Box);
+        }
+        match ctx[self.strip_implicit_casts(size)].kind {
+            CExprKind::Binary(_, c_ast::BinOp::Multiply, lhs, rhs, _, _) => {
+                if self.is_size_of(rhs, pointee) {
+                    Some(OwnedAllocation::Vec(lhs))
+                } else if self.is_size_of(lhs, pointee) {
+                    Some(OwnedAllocation::Vec(rhs))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether an expression is `sizeof(T)` of the given type
+    fn is_size_of(&self, expr_id: CExprId, ty: CTypeId) -> bool {
+        let ctx = &self.ast_context;
+        match ctx[self.strip_implicit_casts(expr_id)].kind {
+            CExprKind::UnaryType(_, UnTypeOp::SizeOf, _, arg_ty, _) => {
+                ctx.resolve_type_id(arg_ty.ctype) == ctx.resolve_type_id(ty)
+            }
+            _ => false,
+        }
+    }
+
+    fn strip_implicit_casts(&self, mut expr_id: CExprId) -> CExprId {
+        while let CExprKind::ImplicitCast(_, expr, _, _, _) = self.ast_context[expr_id].kind {
+            expr_id = expr;
+        }
+        expr_id
+    }
+
+    /// Whether a callee is the function with the given name
+    fn calls_named(&self, func: CExprId, name: &str) -> bool {
+        let ctx = &self.ast_context;
+        match ctx[func].kind {
+            CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _) => {
+                match ctx[fexp].kind {
+                    CExprKind::DeclRef(_, decl_id, _) => {
+                        ctx[decl_id].kind.get_name().map_or(false, |n| n == name)
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// The variable freed by a call of the form `free(p)`
+    fn freed_var(&self, expr_id: CExprId) -> Option<CDeclId> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::Call(_, func, ref args) if args.len() == 1 && self.calls_named(func, "free") => {
+                let arg = match self.ast_context[args[0]].kind {
+                    CExprKind::ImplicitCast(_, arg, CastKind::BitCast, _, _) => arg,
+                    _ => args[0],
+                };
+                self.pointer_var(arg)
+            }
+            _ => None,
+        }
+    }
+
+    /// The variable read by an expression of the form `p`
+    fn pointer_var(&self, expr_id: CExprId) -> Option<CDeclId> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::ImplicitCast(_, var, CastKind::LValueToRValue, _, _) => {
+                match self.ast_context[var].kind {
+                    CExprKind::DeclRef(_, decl_id, _) => Some(decl_id),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,378 @@
+#![deny(missing_docs)]
+//! Translation of `printf`-family calls with constant format strings into Rust
+//! formatting macros. A call is only rewritten when every conversion in the
+//! format string has an exact Rust counterpart for the type of its argument;
+//! anything else remains a call to the C library.
+
+use super::*;
+
+/// A parsed piece of a C format string
+#[derive(Debug, PartialEq)]
+enum Piece {
+    /// Literal text, with `%%` already unescaped
+    Text(String),
+    /// A conversion specification consuming one argument
+    Conversion(Conversion),
+}
+
+/// A conversion specification, e.g. `%-08lx`, restricted to the features we
+/// can express exactly with Rust formatting
+#[derive(Debug, PartialEq)]
+struct Conversion {
+    left_align: bool,
+    zero_pad: bool,
+    plus_sign: bool,
+    width: Option<usize>,
+    length: Length,
+    conversion: char,
+}
+
+/// Length modifier of an integer conversion
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Length {
+    Int,
+    Long,
+    LongLong,
+    Size,
+    /// C23's `wN`, for an integer of exactly N bits, e.g. `%w128d`
+    Width(u64),
+}
+
+/// Parse a format string into its pieces, or `None` if the format string contains
+/// anything without an exact Rust equivalent: non-UTF-8 text, conversions other
+/// than the integer ones, precisions, `*` widths, or the ` `, `#` and `'` flags.
+fn parse_format(fmt: &[u8]) -> Option<Vec<Piece>> {
+    let fmt = std::str::from_utf8(fmt).ok()?;
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            text.push('%');
+            continue;
+        }
+
+        let mut left_align = false;
+        let mut zero_pad = false;
+        let mut plus_sign = false;
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => left_align = true,
+                '0' => zero_pad = true,
+                '+' => plus_sign = true,
+                _ => break,
+            }
+            chars.next();
+        }
+
+        let mut width = String::new();
+        while let Some(&digit) = chars.peek() {
+            if !digit.is_ascii_digit() {
+                break;
+            }
+            width.push(digit);
+            chars.next();
+        }
+        let width = if width.is_empty() { None } else { Some(width.parse().ok()?) };
+
+        let length = match chars.peek() {
+            Some('l') => {
+                chars.next();
+                if chars.peek() == Some(&'l') {
+                    chars.next();
+                    Length::LongLong
+                } else {
+                    Length::Long
+                }
+            }
+            Some('z') => {
+                chars.next();
+                Length::Size
+            }
+            Some('w') => {
+                chars.next();
+                let mut bits = String::new();
+                while let Some(&digit) = chars.peek() {
+                    if !digit.is_ascii_digit() {
+                        break;
+                    }
+                    bits.push(digit);
+                    chars.next();
+                }
+                Length::Width(bits.parse().ok()?)
+            }
+            _ => Length::Int,
+        };
+
+        let conversion = chars.next()?;
+        match conversion {
+            'd' | 'i' => {}
+            // `+` only applies to signed conversions in C
+            'u' | 'x' | 'X' | 'o' if !plus_sign => {}
+            _ => return None,
+        }
+
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::replace(&mut text, String::new())));
+        }
+        pieces.push(Piece::Conversion(Conversion {
+            left_align,
+            // C ignores `0` when `-` is given
+            zero_pad: zero_pad && !left_align,
+            plus_sign,
+            width,
+            length,
+            conversion,
+        }));
+    }
+
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Some(pieces)
+}
+
+impl Conversion {
+    /// The Rust format specification, e.g. `{:<8x}`
+    fn rust_spec(&self) -> String {
+        let mut spec = String::new();
+        if self.left_align {
+            spec.push('<');
+        }
+        if self.plus_sign {
+            spec.push('+');
+        }
+        if self.zero_pad {
+            spec.push('0');
+        }
+        if let Some(width) = self.width {
+            spec.push_str(&width.to_string());
+        }
+        match self.conversion {
+            'x' => spec.push('x'),
+            'X' => spec.push('X'),
+            'o' => spec.push('o'),
+            _ => {}
+        }
+        if spec.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{:{}}}", spec)
+        }
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Convert a call to `printf`, or to `fprintf` on `stdout` or `stderr`, into a
+    /// write to Rust's `stdout()` or `stderr()` if its format string is a literal
+    /// that converts exactly. The call's result, the number of bytes written, is
+    /// not available, so the call must be unused.
+    ///
+    /// The C streams are flushed before and Rust's after the write, so output
+    /// stays in order when C code writes to the same stream. Write errors are
+    /// ignored, like C code ignoring the result would.
+    pub fn convert_printf_call(
+        &self,
+        ctx: ExprContext,
+        name: &str,
+        args: &[CExprId],
+    ) -> Result<Option<WithStmts<P<Expr>>>, TranslationError> {
+        let (stream_fn, args) = match name {
+            "printf" if !args.is_empty() => ("stdout", args),
+            "fprintf" if args.len() >= 2 => {
+                let stream = match self.ast_context.resolve_expr(args[0]).1 {
+                    CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[*decl_id].kind {
+                        CDeclKind::Variable { ref ident, .. } => ident.as_str(),
+                        _ => return Ok(None),
+                    },
+                    _ => return Ok(None),
+                };
+                match stream {
+                    "stdout" => ("stdout", &args[1..]),
+                    "stderr" => ("stderr", &args[1..]),
+                    _ => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        let fmt = match self.ast_context.resolve_expr(args[0]).1 {
+            CExprKind::Literal(_, CLiteral::String(bytes, 1)) => bytes,
+            _ => return Ok(None),
+        };
+        // Everything after an embedded NUL is ignored by C
+        let fmt = fmt.split(|&b| b == 0).next().unwrap_or(&[]);
+        let pieces = match parse_format(fmt) {
+            Some(pieces) => pieces,
+            None => return Ok(None),
+        };
+
+        let conversions: Vec<&Conversion> = pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Conversion(conv) => Some(conv),
+                Piece::Text(..) => None,
+            })
+            .collect();
+        let fmt_args = &args[1..];
+        if conversions.len() != fmt_args.len() {
+            return Ok(None);
+        }
+        for (conv, &arg) in conversions.iter().zip(fmt_args) {
+            if !self.printf_arg_matches(conv, arg) {
+                return Ok(None);
+            }
+        }
+
+        let mut rust_fmt = String::new();
+        for piece in &pieces {
+            match piece {
+                Piece::Text(text) => rust_fmt.push_str(&text.replace('{', "{{").replace('}', "}}")),
+                Piece::Conversion(conv) => rust_fmt.push_str(&conv.rust_spec()),
+            }
+        }
+
+        let fmt_args = self.convert_exprs(ctx.used(), fmt_args)?;
+        let write = fmt_args.and_then(|fmt_args| -> Result<_, TranslationError> {
+            let mut tokens = vec![TokenTree::token(
+                token::Interpolated(Rc::new(Nonterminal::NtExpr(mk().lit_expr(rust_fmt)))),
+                DUMMY_SP,
+            )];
+            for arg in fmt_args {
+                tokens.push(TokenTree::token(token::Comma, DUMMY_SP));
+                tokens.push(TokenTree::token(
+                    token::Interpolated(Rc::new(Nonterminal::NtExpr(arg))),
+                    DUMMY_SP,
+                ));
+            }
+            let fmt = mk().mac_expr(mk().mac(
+                vec!["format_args"],
+                tokens.into_iter().collect::<TokenStream>(),
+                MacDelimiter::Parenthesis,
+            ));
+
+            let fflush = mk().path_expr(vec!["libc", "fflush"]);
+            let all_streams = mk().cast_expr(
+                mk().lit_expr(mk().int_lit(0, "")),
+                mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "FILE"])),
+            );
+            let stream = || {
+                let stream = mk().path_expr(vec!["", "std", "io", stream_fn]);
+                mk().mutbl().addr_of_expr(mk().call_expr(stream, vec![] as Vec<P<Expr>>))
+            };
+            let write_fmt = mk().path_expr(vec!["", "std", "io", "Write", "write_fmt"]);
+            let flush = mk().path_expr(vec!["", "std", "io", "Write", "flush"]);
+            let mut stmts = WithStmts::new(
+                vec![
+                    mk().semi_stmt(mk().call_expr(fflush, vec![all_streams])),
+                    mk().local_stmt(P(mk().local(
+                        mk().wild_pat(),
+                        None as Option<P<Ty>>,
+                        Some(mk().call_expr(write_fmt, vec![stream(), fmt])),
+                    ))),
+                    mk().local_stmt(P(mk().local(
+                        mk().wild_pat(),
+                        None as Option<P<Ty>>,
+                        Some(mk().call_expr(flush, vec![stream()])),
+                    ))),
+                ],
+                self.panic_or_err(&format!("{} not used", name)),
+            );
+            stmts.set_unsafe();
+            Ok(stmts)
+        })?;
+        Ok(Some(write))
+    }
+
+    /// Check that the (promoted) type of a variadic argument is exactly the one
+    /// the conversion reads
+    fn printf_arg_matches(&self, conv: &Conversion, arg: CExprId) -> bool {
+        let ty = match self.ast_context[arg].kind.get_type() {
+            Some(ty) => ty,
+            None => return false,
+        };
+        let kind = &self.ast_context.resolve_type(ty).kind;
+        match kind {
+            CTypeKind::Int
+            | CTypeKind::UInt
+            | CTypeKind::Long
+            | CTypeKind::ULong
+            | CTypeKind::LongLong
+            | CTypeKind::ULongLong
+            | CTypeKind::Int128
+            | CTypeKind::UInt128 => {}
+            _ => return false,
+        }
+
+        let target = &self.ast_context.target_info;
+        let expected_width = match conv.length {
+            Length::Int => target.int_width,
+            Length::Long => target.long_width,
+            Length::LongLong => target.long_long_width,
+            Length::Size => target.pointer_width,
+            Length::Width(bits) => bits,
+        };
+        if self.ast_context.integral_type_width(kind) != Some(expected_width) {
+            return false;
+        }
+
+        // Hexadecimal and octal formatting of signed integers in Rust prints
+        // their two's complement, like C does
+        match conv.conversion {
+            'd' | 'i' => self.ast_context.is_signed_integral(kind),
+            'u' => !self.ast_context.is_signed_integral(kind),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let pieces = parse_format(b"{%d} %-5lu 100%% %08llX\n").unwrap();
+        let rust_fmt: Vec<String> = pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text.clone(),
+                Piece::Conversion(conv) => conv.rust_spec(),
+            })
+            .collect();
+        assert_eq!(rust_fmt, vec!["{", "{}", "} ", "{:<5}", " 100% ", "{:08X}", "\n"]);
+    }
+
+    #[test]
+    fn parse_exact_width() {
+        let pieces = parse_format(b"%w128d %w64x").unwrap();
+        let lengths: Vec<Length> = pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Conversion(conv) => Some(conv.length),
+                Piece::Text(..) => None,
+            })
+            .collect();
+        assert_eq!(lengths, vec![Length::Width(128), Length::Width(64)]);
+        assert_eq!(parse_format(b"%wd"), None);
+        assert_eq!(parse_format(b"%wf32d"), None);
+    }
+
+    #[test]
+    fn parse_inexact() {
+        assert_eq!(parse_format(b"%s"), None);
+        assert_eq!(parse_format(b"%f"), None);
+        assert_eq!(parse_format(b"%.3d"), None);
+        assert_eq!(parse_format(b"%*d"), None);
+        assert_eq!(parse_format(b"%#x"), None);
+        assert_eq!(parse_format(b"%+u"), None);
+        assert_eq!(parse_format(b"%"), None);
+        assert_eq!(parse_format(b"\xff"), None);
+    }
+}
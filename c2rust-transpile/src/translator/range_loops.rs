@@ -0,0 +1,339 @@
+//! Translation of simple counting loops like
+//!
+//! ```c
+//! for (int i = 0; i < n; i++) { ... }
+//! ```
+//!
+//! as Rust `for i in 0..n { ... }` loops, enabled with `--range-loops`. C
+//! evaluates the condition before every iteration while a range is computed
+//! once, and the Rust loop variable is immutable, so a loop only qualifies if
+//! its body can't change either the counter or the bound:
+//!
+//! - the counter is declared in the loop's initializer, has an integer type,
+//!   and is only read in the condition and body and incremented by one
+//! - the condition is `i < bound` or `i <= bound` with a bound of the
+//!   counter's type made of constants and local variables that are never
+//!   written in the loop and whose address is never taken in the function
+//! - the body has no `break`, `continue`, `goto` or label of its own, which a
+//!   separately translated body couldn't jump to
+
+use super::*;
+use crate::c_ast::iterators::DFNodes;
+
+/// A `for` loop that is translated as a loop over a range
+#[derive(Copy, Clone, Debug)]
+pub struct RangeLoop {
+    var: CDeclId,
+    start: CExprId,
+    end: CExprId,
+    inclusive: bool,
+    body: CStmtId,
+}
+
+/// How a variable is referenced inside some node
+#[derive(Default)]
+struct VarRefs {
+    reads: usize,
+    writes: usize,
+    total: usize,
+}
+
+impl VarRefs {
+    /// Whether the variable is referenced other than by reading or
+    /// assigning it, e.g. by taking its address
+    fn escapes(&self) -> bool {
+        self.total > self.reads + self.writes
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Find the `for` loops in a function body that can be translated as
+    /// loops over a range
+    pub fn find_range_loops(&self, body: CStmtId) {
+        let mut range_loops = self.range_loops.borrow_mut();
+        for node in DFNodes::new(&self.ast_context, SomeId::Stmt(body)) {
+            if let SomeId::Stmt(stmt_id) = node {
+                if let Some(range_loop) = self.as_range_loop(body, stmt_id) {
+                    range_loops.insert(stmt_id, range_loop);
+                }
+            }
+        }
+    }
+
+    pub fn is_range_loop(&self, stmt_id: CStmtId) -> bool {
+        self.range_loops.borrow().contains_key(&stmt_id)
+    }
+
+    /// Translate a loop found by `find_range_loops`
+    pub fn convert_range_loop(
+        &self,
+        ctx: ExprContext,
+        stmt_id: CStmtId,
+    ) -> Result<Vec<Stmt>, TranslationError> {
+        let range_loop = self.range_loops.borrow()[&stmt_id];
+        let ident = match self.ast_context[range_loop.var].kind {
+            CDeclKind::Variable { ref ident, .. } => ident,
+            _ => return Err(TranslationError::generic("Range loop counter is not a variable")),
+        };
+
+        let start = self.convert_expr(ctx.used(), range_loop.start)?;
+        let end = self.convert_expr(ctx.used(), range_loop.end)?;
+        let limits = if range_loop.inclusive {
+            RangeLimits::Closed
+        } else {
+            RangeLimits::HalfOpen
+        };
+        let range = start.and_then(|start| -> Result<_, TranslationError> {
+            Ok(end.map(|end| mk().range_expr(Some(start), Some(end), limits)))
+        })?;
+
+        self.with_scope(|| {
+            let var_name = self
+                .renamer
+                .borrow_mut()
+                .insert(range_loop.var, &self.tcfg.naming.value_case.apply(ident))
+                .expect(&format!("Failed to insert variable '{}'", ident));
+
+            let fn_name = self.function_context.borrow().get_name().to_string();
+            let body_ids = match self.ast_context[range_loop.body].kind {
+                CStmtKind::Compound(ref stmts) => stmts.clone(),
+                _ => vec![range_loop.body],
+            };
+            let body = self.convert_function_body(
+                ctx,
+                &fn_name,
+                &body_ids,
+                cfg::ImplicitReturnType::NestedBlock,
+            )?;
+
+            let (mut stmts, range) = range.discard_unsafe();
+            let for_loop = mk().for_expr(
+                mk().ident_pat(var_name),
+                range,
+                mk().block(body),
+                None as Option<Ident>,
+            );
+            stmts.push(mk().expr_stmt(for_loop));
+            Ok(stmts)
+        })
+    }
+
+    fn as_range_loop(&self, fn_body: CStmtId, stmt_id: CStmtId) -> Option<RangeLoop> {
+        let ctx = &self.ast_context;
+        let (init, condition, increment, body) = match ctx[stmt_id].kind {
+            CStmtKind::ForLoop {
+                init: Some(init),
+                condition: Some(condition),
+                increment: Some(increment),
+                body,
+            } => (init, condition, increment, body),
+            _ => return None,
+        };
+
+        let (var, start) = match ctx[init].kind {
+            CStmtKind::Decls(ref decls) if decls.len() == 1 => match ctx[decls[0]].kind {
+                CDeclKind::Variable {
+                    has_static_duration: false,
+                    has_thread_duration: false,
+                    initializer: Some(start),
+                    typ,
+                    ..
+                } if !typ.qualifiers.is_volatile => {
+                    let kind = &ctx.resolve_type(typ.ctype).kind;
+                    if !kind.is_integral_type()
+                        || *kind == CTypeKind::Bool
+                        || kind.bit_int_storage_width().is_some()
+                    {
+                        return None;
+                    }
+                    (decls[0], start)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let (end, inclusive) = match ctx[condition].kind {
+            CExprKind::Binary(_, op, lhs, rhs, _, _)
+                if self.reads_var(lhs) == Some(var)
+                    && ctx[rhs].kind.get_type().map(|ty| ctx.resolve_type_id(ty))
+                        == ctx[lhs].kind.get_type().map(|ty| ctx.resolve_type_id(ty)) =>
+            {
+                match op {
+                    c_ast::BinOp::Less => (rhs, false),
+                    c_ast::BinOp::LessEqual => (rhs, true),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        let increments_by_one = match ctx[increment].kind {
+            CExprKind::Unary(_, c_ast::UnOp::PreIncrement, arg, _)
+            | CExprKind::Unary(_, c_ast::UnOp::PostIncrement, arg, _) => self.names_var(arg, var),
+            CExprKind::Binary(_, c_ast::BinOp::AssignAdd, lhs, rhs, _, _) => {
+                self.names_var(lhs, var) && self.is_literal_one(rhs)
+            }
+            _ => false,
+        };
+        if !increments_by_one {
+            return None;
+        }
+
+        // The counter may only be read in the body and the condition
+        let body_refs = self.var_refs(SomeId::Stmt(body), var);
+        let cond_refs = self.var_refs(SomeId::Expr(condition), var);
+        if body_refs.escapes() || body_refs.writes > 0 || cond_refs.escapes() {
+            return None;
+        }
+
+        if !self.is_loop_invariant(fn_body, stmt_id, end) || self.body_escapes(body, false, false) {
+            return None;
+        }
+
+        Some(RangeLoop {
+            var,
+            start,
+            end,
+            inclusive,
+            body,
+        })
+    }
+
+    /// Whether a bound has the same value every time the condition of the
+    /// given loop is evaluated
+    fn is_loop_invariant(&self, fn_body: CStmtId, loop_id: CStmtId, expr_id: CExprId) -> bool {
+        let ctx = &self.ast_context;
+        DFExpr::new(ctx, expr_id.into()).all(|node| {
+            let expr_id = match node {
+                SomeId::Expr(expr_id) => expr_id,
+                SomeId::Type(_) => return true,
+                _ => return false,
+            };
+            match ctx[expr_id].kind {
+                CExprKind::Literal(..)
+                | CExprKind::ImplicitCast(..)
+                | CExprKind::ExplicitCast(..)
+                | CExprKind::Paren(..)
+                | CExprKind::ConstantExpr(..)
+                | CExprKind::UnaryType(..) => true,
+                CExprKind::Unary(_, op, _, _) => match op {
+                    c_ast::UnOp::Plus
+                    | c_ast::UnOp::Negate
+                    | c_ast::UnOp::Complement
+                    | c_ast::UnOp::Not => true,
+                    _ => false,
+                },
+                CExprKind::Binary(_, op, _, _, _, _) => {
+                    op.underlying_assignment().is_none()
+                        && op != c_ast::BinOp::Assign
+                        && op != c_ast::BinOp::Comma
+                }
+                CExprKind::DeclRef(_, decl_id, _) => match ctx[decl_id].kind {
+                    CDeclKind::EnumConstant { .. } => true,
+                    CDeclKind::Variable {
+                        has_static_duration: false,
+                        has_thread_duration: false,
+                        typ,
+                        ..
+                    } => {
+                        !typ.qualifiers.is_volatile
+                            && !self.var_refs(SomeId::Stmt(fn_body), decl_id).escapes()
+                            && self.var_refs(SomeId::Stmt(loop_id), decl_id).writes == 0
+                    }
+                    _ => false,
+                },
+                _ => false,
+            }
+        })
+    }
+
+    /// Count the references to a variable inside a node
+    fn var_refs(&self, node: SomeId, var: CDeclId) -> VarRefs {
+        let mut refs = VarRefs::default();
+        for node in DFNodes::new(&self.ast_context, node) {
+            let expr_id = match node {
+                SomeId::Expr(expr_id) => expr_id,
+                _ => continue,
+            };
+            match self.ast_context[expr_id].kind {
+                CExprKind::DeclRef(_, decl_id, _) if decl_id == var => refs.total += 1,
+                CExprKind::ImplicitCast(_, arg, CastKind::LValueToRValue, _, _)
+                    if self.names_var(arg, var) =>
+                {
+                    refs.reads += 1
+                }
+                CExprKind::Binary(_, op, lhs, _, _, _)
+                    if (op == c_ast::BinOp::Assign || op.underlying_assignment().is_some())
+                        && self.names_var(lhs, var) =>
+                {
+                    refs.writes += 1
+                }
+                CExprKind::Unary(_, op, arg, _) if self.names_var(arg, var) => match op {
+                    c_ast::UnOp::PreIncrement
+                    | c_ast::UnOp::PreDecrement
+                    | c_ast::UnOp::PostIncrement
+                    | c_ast::UnOp::PostDecrement => refs.writes += 1,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        refs
+    }
+
+    fn names_var(&self, expr_id: CExprId, var: CDeclId) -> bool {
+        match self.ast_context[expr_id].kind {
+            CExprKind::DeclRef(_, decl_id, _) => decl_id == var,
+            _ => false,
+        }
+    }
+
+    /// The variable an expression of the form `var` reads
+    fn reads_var(&self, expr_id: CExprId) -> Option<CDeclId> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::ImplicitCast(_, arg, CastKind::LValueToRValue, _, _) => {
+                match self.ast_context[arg].kind {
+                    CExprKind::DeclRef(_, decl_id, _) => Some(decl_id),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn is_literal_one(&self, expr_id: CExprId) -> bool {
+        match self.ast_context[expr_id].kind {
+            CExprKind::Literal(_, CLiteral::Integer(1, _)) => true,
+            CExprKind::ImplicitCast(_, arg, CastKind::IntegralCast, _, _) => self.is_literal_one(arg),
+            _ => false,
+        }
+    }
+
+    /// Whether control can leave a loop body or branch other than by reaching
+    /// its end or returning, or enter it other than at its start
+    pub fn body_escapes(&self, stmt_id: CStmtId, in_loop: bool, in_switch: bool) -> bool {
+        let escapes = |stmt_id: CStmtId| self.body_escapes(stmt_id, in_loop, in_switch);
+        let escapes_loop = |stmt_id: CStmtId| self.body_escapes(stmt_id, true, in_switch);
+        match self.ast_context[stmt_id].kind {
+            CStmtKind::Goto(_) | CStmtKind::Label(_) => true,
+            CStmtKind::Break => !in_loop && !in_switch,
+            CStmtKind::Continue => !in_loop,
+            CStmtKind::Case(_, body, _) | CStmtKind::Default(body) => !in_switch || escapes(body),
+            CStmtKind::Compound(ref stmts) => stmts.iter().any(|&stmt| escapes(stmt)),
+            CStmtKind::If { true_variant, false_variant, .. } => {
+                escapes(true_variant) || false_variant.map_or(false, escapes)
+            }
+            CStmtKind::Switch { body, .. } => self.body_escapes(body, in_loop, true),
+            CStmtKind::While { body, .. } | CStmtKind::DoWhile { body, .. } => escapes_loop(body),
+            CStmtKind::ForLoop { init, body, .. } => {
+                init.map_or(false, escapes) || escapes_loop(body)
+            }
+            CStmtKind::Return(_)
+            | CStmtKind::Expr(_)
+            | CStmtKind::Empty
+            | CStmtKind::Decls(_)
+            | CStmtKind::Asm { .. } => false,
+        }
+    }
+}
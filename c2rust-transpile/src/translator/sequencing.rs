@@ -0,0 +1,75 @@
+//! Sequencing of side effects between the operands of a call or initializer.
+//!
+//! C evaluates every argument of a call and every element of an initializer
+//! list exactly once, but in an unspecified order. We evaluate them left to
+//! right. Translating an operand like `i++` produces statements that must run
+//! before the expression using its value, so in `f(g(), i++)` the statements
+//! for `i++` would run before `g()` is called. To keep the left-to-right order,
+//! the values of the operands to the left of one with statements are bound to
+//! temporaries first:
+//!
+//! ```ignore
+//! let fresh0 = g();
+//! let fresh1 = i;
+//! i = i + 1;
+//! f(fresh0, fresh1)
+//! ```
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Combine translated operands, evaluated left to right. Operands that
+    /// don't come from a C expression, like the default values padding an
+    /// array initializer, are given as `None`.
+    pub fn sequence_operands(
+        &self,
+        operands: Vec<(Option<CExprId>, WithStmts<P<Expr>>)>,
+    ) -> WithStmts<Vec<P<Expr>>> {
+        let mut stmts = vec![];
+        let mut vals: Vec<P<Expr>> = vec![];
+        let mut is_unsafe = false;
+        // Operands whose value could still change if the statements of a later
+        // operand ran first
+        let mut unbound = vec![];
+
+        for (expr_id, mut operand) in operands {
+            is_unsafe |= operand.is_unsafe();
+            if !operand.stmts().is_empty() {
+                for idx in unbound.drain(..) {
+                    let name = self.renamer.borrow_mut().fresh();
+                    let val = mem::replace(&mut vals[idx], mk().ident_expr(&name));
+                    stmts.push(mk().local_stmt(P(mk().local(
+                        mk().ident_pat(&name),
+                        None as Option<P<Ty>>,
+                        Some(val),
+                    ))));
+                }
+                stmts.append(operand.stmts_mut());
+            }
+            if !expr_id.map_or(true, |expr_id| self.is_order_independent(expr_id)) {
+                unbound.push(vals.len());
+            }
+            vals.push(operand.into_value());
+        }
+
+        let mut res = WithStmts::new(stmts, vals);
+        res.merge_unsafe(is_unsafe);
+        res
+    }
+
+    /// Whether an expression has no side effects and doesn't read any
+    /// variable, so evaluating it earlier or later makes no difference
+    fn is_order_independent(&self, expr_id: CExprId) -> bool {
+        self.ast_context.is_expr_pure(expr_id)
+            && DFExpr::new(&self.ast_context, expr_id.into()).all(|node| match node {
+                SomeId::Expr(expr_id) => match self.ast_context[expr_id].kind {
+                    CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                        CDeclKind::Variable { .. } => false,
+                        _ => true,
+                    },
+                    _ => true,
+                },
+                _ => true,
+            })
+    }
+}
@@ -67,6 +67,81 @@ static SIMD_X86_64_ONLY: &[&str] = &[
     "_mm_crc32_u64",
 ];
 
+/// ARM NEON vector typedefs from `<arm_neon.h>`, imported from `core::arch` with
+/// `--translate-simd`.
+static NEON_TYPES: &[&str] = &[
+    "int8x8_t",
+    "int8x16_t",
+    "int16x4_t",
+    "int16x8_t",
+    "int32x2_t",
+    "int32x4_t",
+    "int64x1_t",
+    "int64x2_t",
+    "uint8x8_t",
+    "uint8x16_t",
+    "uint16x4_t",
+    "uint16x8_t",
+    "uint32x2_t",
+    "uint32x4_t",
+    "uint64x1_t",
+    "uint64x2_t",
+    "float32x2_t",
+    "float32x4_t",
+    "float64x1_t",
+    "float64x2_t",
+    "poly8x8_t",
+    "poly8x16_t",
+    "poly16x4_t",
+    "poly16x8_t",
+];
+
+/// Common NEON operations. Intrinsic names are formed from one of these followed by an
+/// element type suffix, e.g. `vaddq` + `_s32`.
+static NEON_OPERATIONS: &[&str] = &[
+    "vld1", "vld1q", "vst1", "vst1q", "vadd", "vaddq", "vsub", "vsubq", "vmul", "vmulq", "vmla",
+    "vmlaq", "vand", "vandq", "vorr", "vorrq", "veor", "veorq", "vmax", "vmaxq", "vmin", "vminq",
+    "vabs", "vabsq", "vneg", "vnegq", "vceq", "vceqq", "vcgt", "vcgtq", "vclt", "vcltq",
+    "vdup_n", "vdupq_n", "vget_lane", "vgetq_lane", "vset_lane", "vsetq_lane", "vcombine",
+    "vget_low", "vget_high",
+];
+
+static NEON_ELEMENT_SUFFIXES: &[&str] = &[
+    "s8", "s16", "s32", "s64", "u8", "u16", "u32", "u64", "f32", "f64", "p8", "p16",
+];
+
+fn is_neon_function(name: &str) -> bool {
+    match name.rfind('_') {
+        Some(idx) => {
+            NEON_OPERATIONS.contains(&&name[..idx])
+                && NEON_ELEMENT_SUFFIXES.contains(&&name[idx + 1..])
+        }
+        None => false,
+    }
+}
+
+/// Decode the element type suffix from the type code clang passes as the last argument
+/// of `__builtin_neon_*` calls (see `NeonTypeFlags` in clang).
+fn neon_element_suffix(type_code: u64) -> Option<&'static str> {
+    let unsigned = type_code & 0x10 != 0;
+    let suffix = match (type_code & 0xf, unsigned) {
+        (0, false) => "s8",
+        (0, true) => "u8",
+        (1, false) => "s16",
+        (1, true) => "u16",
+        (2, false) => "s32",
+        (2, true) => "u32",
+        (3, false) => "s64",
+        (3, true) => "u64",
+        (4, _) => "p8",
+        (5, _) => "p16",
+        (9, _) => "f32",
+        (10, _) => "f64",
+        _ => return None,
+    };
+    Some(suffix)
+}
+
 impl<'c> Translation<'c> {
     /// Given the name of a typedef check if its one of the SIMD types.
     /// This function returns `true` when the name of the type is one that
@@ -137,6 +212,10 @@ impl<'c> Translation<'c> {
             | "__v16hu"
             | "__mm_loadh_pi_v2f32"
             | "__mm_loadl_pi_v2f32" => true,
+            _ if self.tcfg.translate_simd && NEON_TYPES.contains(&name) => {
+                self.import_neon(name);
+                true
+            }
             _ => false,
         }
     }
@@ -185,9 +264,44 @@ impl<'c> Translation<'c> {
             return Ok(true);
         }
 
+        if self.tcfg.translate_simd && is_neon_function(name) {
+            self.use_feature("stdsimd");
+            self.import_neon(name);
+
+            return Ok(true);
+        }
+
         Ok(false)
     }
 
+    /// Import a NEON type or function from `core::arch::aarch64`, and also from
+    /// `core::arch::arm` unless it operates on doubles, which 32-bit ARM lacks.
+    fn import_neon(&self, name: &str) {
+        self.with_cur_file_item_store(|item_store| {
+            let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" }.to_string();
+
+            if !name.contains("f64") && !name.starts_with("float64") {
+                let arm_attr = mk().call_attr("cfg", vec!["target_arch = \"arm\""]).pub_();
+
+                item_store.add_use_with_attr(
+                    vec![std_or_core.clone(), "arch".into(), "arm".into()],
+                    name,
+                    arm_attr,
+                );
+            }
+
+            let aarch64_attr = mk()
+                .call_attr("cfg", vec!["target_arch = \"aarch64\""])
+                .pub_();
+
+            item_store.add_use_with_attr(
+                vec![std_or_core, "arch".into(), "aarch64".into()],
+                name,
+                aarch64_attr,
+            );
+        });
+    }
+
     /// This function will strip either an implicitly casted int or explicitly casted
     /// vector as both casts are unnecessary (and problematic) for our purposes
     fn clean_int_or_vector_param(&self, expr_id: CExprId) -> CExprId {
@@ -239,6 +353,73 @@ impl<'c> Translation<'c> {
         })
     }
 
+    /// The NEON load and store macros in `<arm_neon.h>` expand to generic builtins such as
+    /// `__builtin_neon_vld1q_v(ptr, type_code)`. Recover the typed intrinsic, e.g.
+    /// `vld1q_f32`, from the type code and call it instead.
+    pub fn convert_neon_builtin(
+        &self,
+        ctx: ExprContext,
+        builtin_name: &str,
+        args: &[CExprId],
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let (type_code_id, args) = args
+            .split_last()
+            .ok_or_else(|| format_err!("{} is missing its type code", builtin_name))?;
+        let type_code = match self.ast_context.resolve_expr(*type_code_id).1 {
+            Literal(_, Integer(code, _)) => *code,
+            e => Err(format_err!("Expected a literal NEON type code, found {:?}", e))?,
+        };
+        let suffix = neon_element_suffix(type_code)
+            .ok_or_else(|| format_err!("Unknown NEON type code {}", type_code))?;
+        let op = builtin_name
+            .trim_start_matches("__builtin_neon_")
+            .trim_end_matches("_v");
+        let fn_name = format!("{}_{}", op, suffix);
+        self.import_simd_function(&fn_name)?;
+
+        let is_store = op.starts_with("vst");
+        let (ptr_arg, vector_args) = args
+            .split_first()
+            .ok_or_else(|| format_err!("{} is missing its pointer argument", builtin_name))?;
+
+        // The vector operand of a store is bitcast to `int8x16_t` for the builtin
+        let vector_args = vector_args
+            .iter()
+            .map(|&arg| match self.ast_context[arg].kind {
+                ExplicitCast(_, expr_id, BitCast, _, _) => expr_id,
+                _ => arg,
+            })
+            .collect::<Vec<_>>();
+
+        let ptr = self.convert_expr(ctx.used(), *ptr_arg)?;
+        let vectors = self.convert_exprs(ctx.used(), &vector_args)?;
+
+        ptr.and_then(|ptr| {
+            // The pointer has been cast to `void *`, let the intrinsic's signature pick the
+            // element type back
+            let ptr_ty = if is_store {
+                mk().mutbl().ptr_ty(mk().infer_ty())
+            } else {
+                mk().ptr_ty(mk().infer_ty())
+            };
+            let mut call_args = vec![mk().cast_expr(ptr, ptr_ty)];
+
+            vectors.and_then(|vectors| {
+                call_args.extend(vectors);
+                let call = mk().call_expr(mk().ident_expr(&fn_name), call_args);
+
+                if ctx.is_used() {
+                    Ok(WithStmts::new_val(call))
+                } else {
+                    Ok(WithStmts::new(
+                        vec![mk().semi_stmt(call)],
+                        self.panic_or_err("No value for unused NEON builtin"),
+                    ))
+                }
+            })
+        })
+    }
+
     /// Generate a zero value to be used for initialization of a given vector type. The type
     /// is specified with the underlying element type and the number of elements in the vector.
     pub fn implicit_vector_default(
@@ -571,7 +752,8 @@ impl<'c> Translation<'c> {
                         let decl = &self.ast_context[*decl_id].kind;
 
                         if let CDeclKind::Function { ref name, .. } = decl {
-                            return name.starts_with("__builtin_ia32_");
+                            return name.starts_with("__builtin_ia32_")
+                                || name.starts_with("__builtin_neon_");
                         }
                     }
                 }
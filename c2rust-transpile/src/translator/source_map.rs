@@ -0,0 +1,125 @@
+//! Source maps from a translated module back to the C it was translated from,
+//! written next to the module with `--source-map`.
+//!
+//! The pretty-printer doesn't tell us where it printed a node, so we find out
+//! the same way comments get printed before the nodes they belong to: while
+//! locating comments, every C declaration and statement with a source
+//! location gets a marker comment `//@c2rust-src <n>` naming its location. Once
+//! the module is printed, `extract_source_map` removes the markers and maps
+//! the line following each marker to the marker's location. A marker is
+//! usually on a line of its own, but the pretty-printer puts a comment at the
+//! end of the previous line if the node follows another one on the same line
+//! in the C source.
+//!
+//! Compound statements don't get a marker, since the opening brace of a block
+//! has to stay on the line of the statement it belongs to. Declarations and
+//! statements whose translation is dropped or merged, e.g. by the relooper,
+//! lose their markers along with their comments.
+
+use super::*;
+
+const SOURCE_MARKER: &str = "//@c2rust-src ";
+
+/// Where a line of a translated module came from in the C source
+#[derive(Serialize, Debug, Clone)]
+pub struct SourceMapEntry {
+    /// Line of the translated module, starting at 1
+    pub rust_line: usize,
+    pub c_file: Option<PathBuf>,
+    pub c_line: u64,
+    pub c_column: u64,
+}
+
+/// The marker comment for the `index`th source location
+pub fn source_marker(index: usize) -> String {
+    format!("{}{}", SOURCE_MARKER, index)
+}
+
+/// Whether a node gets a source map marker
+pub fn has_source_marker(ast_context: &TypedAstContext, id: SomeId) -> bool {
+    match id {
+        SomeId::Decl(_) => true,
+        SomeId::Stmt(stmt_id) => match ast_context[stmt_id].kind {
+            CStmtKind::Compound(_) => false,
+            _ => true,
+        },
+        SomeId::Expr(_) | SomeId::Type(_) => false,
+    }
+}
+
+/// Remove the source map markers from a printed module, returning the module
+/// and the line each marker's node starts on, paired with the marker's index
+fn strip_source_markers(translation: &str) -> (String, Vec<(usize, usize)>) {
+    let mut lines = vec![];
+    let mut stripped = String::with_capacity(translation.len());
+    let mut pending = vec![];
+    let mut rust_line = 0;
+    for line in translation.lines() {
+        let (code, marker) = match line.find(SOURCE_MARKER) {
+            Some(pos) => match line[pos + SOURCE_MARKER.len()..].trim().parse::<usize>() {
+                Ok(index) => (line[..pos].trim_end(), Some(index)),
+                Err(_) => (line, None),
+            },
+            None => (line, None),
+        };
+        if !code.trim().is_empty() || marker.is_none() {
+            rust_line += 1;
+            stripped.push_str(code);
+            stripped.push('\n');
+            lines.extend(pending.drain(..).map(|index| (rust_line, index)));
+        }
+        // A trailing marker belongs to the node printed on the next line
+        pending.extend(marker);
+    }
+    (stripped, lines)
+}
+
+impl<'c> Translation<'c> {
+    /// Remove the source map markers from a printed module, returning the
+    /// module and its source map
+    pub fn extract_source_map(&self, translation: String) -> (String, Vec<SourceMapEntry>) {
+        let (stripped, lines) = strip_source_markers(&translation);
+        let source_map = lines
+            .into_iter()
+            .map(|(rust_line, index)| {
+                let loc = self.source_locs[index];
+                SourceMapEntry {
+                    rust_line,
+                    c_file: self.ast_context.get_span_path(&loc).map(path::Path::to_path_buf),
+                    c_line: loc.begin_line,
+                    c_column: loc.begin_column,
+                }
+            })
+            .collect();
+        (stripped, source_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_own_line_markers() {
+        let printed = "//@c2rust-src 0\nfn f() {\n    //@c2rust-src 1\n    g();\n}\n";
+        let (stripped, lines) = strip_source_markers(printed);
+        assert_eq!(stripped, "fn f() {\n    g();\n}\n");
+        assert_eq!(lines, vec![(1, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn strip_trailing_markers() {
+        let printed = "fn f() {\n    //@c2rust-src 1\n    a = 1; //@c2rust-src 2\n    b = 2;\n}\n";
+        let (stripped, lines) = strip_source_markers(printed);
+        assert_eq!(stripped, "fn f() {\n    a = 1;\n    b = 2;\n}\n");
+        assert_eq!(lines, vec![(2, 1), (3, 2)]);
+    }
+
+    #[test]
+    fn keep_other_comments() {
+        let printed = "// not a marker: //@c2rust-src x\nfn f() {}\n";
+        let (stripped, lines) = strip_source_markers(printed);
+        assert_eq!(stripped, printed);
+        assert!(lines.is_empty());
+    }
+}
@@ -3,6 +3,7 @@
 //! requires the use of the c2rust-bitfields crate.
 
 use std::collections::HashSet;
+use std::mem;
 use std::ops::Index;
 
 use super::TranslationError;
@@ -12,8 +13,9 @@ use crate::with_stmts::WithStmts;
 use c2rust_ast_builder::mk;
 use c2rust_ast_printer::pprust;
 use syntax::ast::{
-    self, AttrStyle, BinOpKind, Expr, ExprKind, Lit, LitIntType, LitKind, MetaItemKind,
-    NestedMetaItem, StmtKind, StrStyle, StructField, Ty, TyKind,
+    self, AttrStyle, BinOpKind, Expr, ExprKind, FunctionRetTy, Item, Lit, LitIntType, LitKind,
+    MetaItemKind, Mutability, NestedMetaItem, SelfKind, StmtKind, StrStyle, StructField, Ty,
+    TyKind,
 };
 use syntax::ptr::P;
 use syntax::source_map::symbol::Symbol;
@@ -127,6 +129,14 @@ impl<'a> Translation<'a> {
                                 let bytes = (platform_bit_offset / 8) - next_byte_pos;
                                 reorganized_fields.push(FieldType::Padding { bytes });
                             }
+                        } else if (platform_bit_offset / 8) > next_byte_pos
+                            && self.int128_record_alignment(record_id).is_some()
+                            && self.is_int128_field_type(ctype)
+                        {
+                            // Rust would place a 128-bit integer at its own, smaller
+                            // alignment, so we pad up to its C offset
+                            let bytes = (platform_bit_offset / 8) - next_byte_pos;
+                            reorganized_fields.push(FieldType::Padding { bytes });
                         }
 
                         let mut use_inner_type = false;
@@ -282,6 +292,10 @@ impl<'a> Translation<'a> {
         // We need to clobber bitfields in consecutive bytes together (leaving
         // regular fields alone) and add in padding as necessary
         let reorganized_fields = self.get_field_types(struct_id, field_ids, platform_byte_size)?;
+        let has_bitfields = reorganized_fields.iter().any(|field| match field {
+            FieldType::BitfieldGroup { .. } => true,
+            _ => false,
+        });
 
         let mut padding_count = 0;
         let mut next_padding_field = || {
@@ -333,16 +347,18 @@ impl<'a> Translation<'a> {
                         mk().lit_expr(mk().int_lit(bytes.into(), LitIntType::Unsuffixed)),
                     );
 
-                    // Mark it with `#[bitfield(padding)]`
-                    let field_padding_inner = mk().meta_item("padding", MetaItemKind::Word);
-                    let field_padding_inner =
-                        vec![mk().nested_meta_item(NestedMetaItem::MetaItem(field_padding_inner))];
-                    let field_padding_outer =
-                        mk().meta_item("bitfield", MetaItemKind::List(field_padding_inner));
-                    let field = mk()
-                        .meta_item_attr(AttrStyle::Outer, field_padding_outer)
-                        .pub_()
-                        .struct_field(field_name, ty);
+                    // Mark it with `#[bitfield(padding)]` in structs deriving `BitfieldStruct`
+                    let mut field = mk();
+                    if has_bitfields {
+                        let field_padding_inner = mk().meta_item("padding", MetaItemKind::Word);
+                        let field_padding_inner = vec![
+                            mk().nested_meta_item(NestedMetaItem::MetaItem(field_padding_inner)),
+                        ];
+                        let field_padding_outer =
+                            mk().meta_item("bitfield", MetaItemKind::List(field_padding_inner));
+                        field = field.meta_item_attr(AttrStyle::Outer, field_padding_outer);
+                    }
+                    let field = field.pub_().struct_field(field_name, ty);
 
                     field_entries.push(field);
                 }
@@ -361,6 +377,199 @@ impl<'a> Translation<'a> {
         Ok(field_entries)
     }
 
+    /// Emit static assertions checking that the generated `#[repr(C)]` struct or union has the
+    /// size and alignment clang computed for the C record, and, if `check_fields` is set, that
+    /// the fields of a struct sit at the offsets clang computed. Each assertion is an unnamed
+    /// constant whose array length only type checks if the layouts agree:
+    ///
+    /// ```no_run
+    /// const _: [(); 16] = [(); ::std::mem::size_of::<Foo>()];
+    /// const _: [(); 8] = [(); ::std::mem::align_of::<Foo>()];
+    /// const _: [(); 8] = [(); (0 + ::std::mem::size_of::<libc::c_int>()
+    ///     + ::std::mem::align_of::<libc::c_long>() - 1)
+    ///     / ::std::mem::align_of::<libc::c_long>() * ::std::mem::align_of::<libc::c_long>()];
+    /// ```
+    ///
+    /// Field offsets are derived from the offset clang reports for the preceding field using
+    /// the `#[repr(C)]` layout rules, which rules out depending on the memoffset crate in a
+    /// constant context. They are not checked for bitfield structs, whose fields are grouped
+    /// into byte arrays, for packed structs embedding aligned ones, nor for structs with
+    /// `#pragma pack(N)` for `N > 1`.
+    pub fn convert_record_layout_assertions(
+        &self,
+        record_id: CRecordId,
+        check_fields: bool,
+    ) -> Result<Vec<P<Item>>, TranslationError> {
+        let (
+            fields,
+            is_union,
+            is_packed,
+            max_field_alignment,
+            platform_byte_size,
+            platform_alignment,
+        ) = match self.ast_context.index(record_id).kind {
+            CDeclKind::Struct {
+                fields: Some(ref fields),
+                is_packed,
+                max_field_alignment,
+                platform_byte_size,
+                platform_alignment,
+                ..
+            } => (
+                fields,
+                false,
+                is_packed,
+                max_field_alignment,
+                platform_byte_size,
+                platform_alignment,
+            ),
+            CDeclKind::Union {
+                fields: Some(ref fields),
+                platform_byte_size,
+                platform_alignment,
+                ..
+            } => (fields, true, false, None, platform_byte_size, platform_alignment),
+            _ => return Err(TranslationError::generic(
+                "Layout assertions are only supported for record definitions",
+            )),
+        };
+        let name = self
+            .type_converter
+            .borrow()
+            .resolve_decl_name(record_id)
+            .unwrap();
+        let ty = mk().path_ty(vec![name]);
+
+        let mut assertions = vec![
+            self.layout_assertion(platform_byte_size, self.mem_fn_call("size_of", ty.clone())),
+            self.layout_assertion(platform_alignment, self.mem_fn_call("align_of", ty)),
+        ];
+
+        // Union fields all start at offset zero
+        if !check_fields || is_union {
+            return Ok(assertions);
+        }
+
+        // Aligned structs embedded in packed ones are replaced by their `_Inner` version and
+        // explicit padding, so their field types no longer describe the layout
+        let packed = is_packed || max_field_alignment == Some(1);
+        let has_irregular_fields = fields
+            .iter()
+            .any(|field_id| match self.ast_context.index(*field_id).kind {
+                CDeclKind::Field { bitfield_width, typ, .. } => {
+                    bitfield_width.is_some()
+                        || (packed && self.ast_context.is_aligned_struct_type(typ.ctype))
+                }
+                _ => false,
+            });
+        if has_irregular_fields || (!packed && max_field_alignment.is_some()) {
+            return Ok(assertions);
+        }
+
+        let mut prev_field: Option<(u64, P<Ty>)> = None;
+        for field_id in fields {
+            if let CDeclKind::Field {
+                typ,
+                platform_bit_offset,
+                ..
+            } = self.ast_context.index(*field_id).kind
+            {
+                let offset = platform_bit_offset / 8;
+                let field_ty = self.convert_type(typ.ctype)?;
+                if let Some((prev_offset, prev_ty)) = prev_field.take() {
+                    // The field starts right after the previous one,
+                    // rounded up to its own alignment unless the struct is packed
+                    let prev_end = mk().binary_expr(
+                        BinOpKind::Add,
+                        mk().lit_expr(mk().int_lit(prev_offset as u128, LitIntType::Unsuffixed)),
+                        self.mem_fn_call("size_of", prev_ty),
+                    );
+                    let field_offset = if packed {
+                        prev_end
+                    } else {
+                        let align = self.mem_fn_call("align_of", field_ty.clone());
+                        let one = mk().lit_expr(mk().int_lit(1, LitIntType::Unsuffixed));
+                        let padded = mk().binary_expr(
+                            BinOpKind::Sub,
+                            mk().binary_expr(BinOpKind::Add, prev_end, align.clone()),
+                            one,
+                        );
+                        let quotient =
+                            mk().binary_expr(BinOpKind::Div, mk().paren_expr(padded), align.clone());
+                        mk().binary_expr(BinOpKind::Mul, quotient, align)
+                    };
+                    assertions.push(self.layout_assertion(offset, field_offset));
+                }
+                prev_field = Some((offset, field_ty));
+            }
+        }
+
+        Ok(assertions)
+    }
+
+    /// Build `const _: [(); expected] = [(); actual];`
+    fn layout_assertion(&self, expected: u64, actual: P<Expr>) -> P<Item> {
+        let expected = mk().lit_expr(mk().int_lit(expected as u128, LitIntType::Unsuffixed));
+        let unit_ty = mk().tuple_ty(vec![] as Vec<P<Ty>>);
+        let unit = mk().tuple_expr(vec![] as Vec<P<Expr>>);
+        mk().const_item(
+            "_",
+            mk().array_ty(unit_ty, expected),
+            mk().repeat_expr(unit, actual),
+        )
+    }
+
+    /// Build a call to one of `std::mem::{size_of, align_of}` for the given type
+    fn mem_fn_call(&self, name: &str, ty: P<Ty>) -> P<Expr> {
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let path = vec![
+            mk().path_segment(""),
+            mk().path_segment(std_or_core),
+            mk().path_segment("mem"),
+            mk().path_segment_with_args(name, mk().angle_bracketed_args(vec![ty])),
+        ];
+        mk().call_expr(mk().path_expr(path), vec![] as Vec<P<Expr>>)
+    }
+
+    /// Builds an inherent impl for the union `name` that wraps each of its `fields` in an unsafe
+    /// getter and setter, so that raw field accesses can later be replaced by method calls:
+    ///
+    /// ```no_run
+    /// impl Foo {
+    ///     pub unsafe fn x(&self) -> libc::c_int { self.x }
+    ///     pub unsafe fn set_x(&mut self, value: libc::c_int) { self.x = value; }
+    /// }
+    /// ```
+    pub fn convert_union_accessors(&self, name: &str, fields: Vec<(String, P<Ty>)>) -> P<Item> {
+        let mut methods = Vec::with_capacity(2 * fields.len());
+        for (field, ty) in fields {
+            let self_field = mk().field_expr(mk().ident_expr("self"), &field);
+
+            let getter_decl = mk().fn_decl(
+                vec![mk().self_arg(SelfKind::Region(None, Mutability::Immutable))],
+                FunctionRetTy::Ty(ty.clone()),
+            );
+            let getter_body = mk().block(vec![mk().expr_stmt(self_field.clone())]);
+            methods.push(mk().pub_().unsafe_().fn_impl_item(&field, getter_decl, getter_body));
+
+            let setter_decl = mk().fn_decl(
+                vec![
+                    mk().self_arg(SelfKind::Region(None, Mutability::Mutable)),
+                    mk().arg(ty, mk().ident_pat("value")),
+                ],
+                FunctionRetTy::Default(DUMMY_SP),
+            );
+            let assign = mk().assign_expr(self_field, mk().ident_expr("value"));
+            let setter_body = mk().block(vec![mk().semi_stmt(assign)]);
+            methods.push(mk().pub_().unsafe_().fn_impl_item(
+                format!("set_{}", field),
+                setter_decl,
+                setter_body,
+            ));
+        }
+        mk().impl_item(mk().path_ty(vec![name]), methods)
+    }
+
     /// Here we output a block to generate a struct literal initializer in.
     /// It looks like this in locals and (sectioned) statics:
     ///
@@ -479,7 +688,13 @@ impl<'a> Translation<'a> {
                 _ => None,
             }
         });
-        let zipped_iter = field_expr_ids.iter().zip_longest(field_info_iter);
+        // Evaluate the initializers in order before building the struct from their values
+        let mut field_exprs = self.convert_exprs(ctx.used(), field_expr_ids)?;
+        let fields_unsafe = field_exprs.is_unsafe();
+        let field_stmts = mem::replace(field_exprs.stmts_mut(), vec![]);
+        let field_vals = field_exprs.into_value();
+
+        let zipped_iter = field_vals.into_iter().zip_longest(field_info_iter);
         let mut bitfield_inits = Vec::new();
 
         // Specified record fields which are not bitfields need to be added
@@ -505,14 +720,9 @@ impl<'a> Translation<'a> {
                     let field = init.map(|init| mk().field(field_name, init));
                     fields.push(field);
                 }
-                Both(field_id, (field_name, _, bitfield_width, use_inner_type)) => {
-                    let mut expr = self.convert_expr(ctx.used(), *field_id)?;
-
-                    if !expr.is_pure() {
-                        return Err(TranslationError::generic(
-                            "Expected no statements in field expression",
-                        ));
-                    }
+                Both(val, (field_name, _, bitfield_width, use_inner_type)) => {
+                    let mut expr = WithStmts::new_val(val);
+                    expr.merge_unsafe(fields_unsafe);
 
                     if use_inner_type {
                         // See comment above
@@ -531,7 +741,7 @@ impl<'a> Translation<'a> {
             }
         }
 
-        fields
+        let literal = fields
             .into_iter()
             .collect::<WithStmts<Vec<ast::Field>>>()
             .and_then(|fields| {
@@ -566,7 +776,8 @@ impl<'a> Translation<'a> {
                 } else {
                     Ok(WithStmts::new_val(val))
                 }
-            })
+            })?;
+        WithStmts::new(field_stmts, ()).and_then(|()| Ok(literal))
     }
 
     /// This method handles zero-initializing bitfield structs including bitfields
@@ -0,0 +1,145 @@
+#![deny(missing_docs)]
+//! Support for `--best-effort` translation, where functions whose bodies we cannot
+//! translate are replaced by stubs instead of failing the translation unit, and every
+//! item we skipped or stubbed out is reported. Static functions using C extensions we
+//! can't translate, like block literals, are stubbed out even without it, because
+//! unlike other functions they can't be declared `extern` to link their C definition.
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+
+use super::*;
+use crate::report::SkippedItem;
+
+impl<'c> Translation<'c> {
+    /// Add an item that failed to translate to the report
+    pub fn record_skipped(
+        &self,
+        decl_id: CDeclId,
+        kind: &'static str,
+        reason: String,
+        stubbed: bool,
+    ) {
+        let decl = &self.ast_context[decl_id];
+        self.report.borrow_mut().skipped.push(SkippedItem {
+            name: decl.kind.get_name().cloned(),
+            kind,
+            reason,
+            c_file: self
+                .ast_context
+                .get_source_path(decl)
+                .map(path::Path::to_path_buf),
+            c_line: decl.loc.map(|loc| loc.begin_line),
+            c_column: decl.loc.map(|loc| loc.begin_column),
+            stubbed,
+        });
+    }
+
+    /// Convert a function body with `convert`. In best-effort mode, a body that fails
+    /// to translate, including by hitting a translator panic, is replaced with
+    /// `unimplemented!()` following the original C source of the function in a comment.
+    /// Otherwise, only the bodies of static functions using unsupported extensions are.
+    /// A failure can leave per-function state behind, like the scopes of the body's
+    /// locals, so that state is restored to what it was before the body.
+    pub fn convert_body_or_stub<F>(
+        &self,
+        decl_id: CDeclId,
+        convert: F,
+    ) -> Result<Vec<Stmt>, TranslationError>
+    where
+        F: FnOnce() -> Result<Vec<Stmt>, TranslationError>,
+    {
+        let is_static = match self.ast_context[decl_id].kind {
+            CDeclKind::Function { is_global, .. } => !is_global,
+            _ => false,
+        };
+        if !self.tcfg.best_effort && !is_static {
+            return convert();
+        }
+
+        let scope_depth = self.renamer.borrow().scope_depth();
+        let function_context = self.function_context.borrow().clone();
+        let macro_arg_substitutions = self.macro_arg_substitutions.borrow().clone();
+
+        let result = if self.tcfg.best_effort {
+            panic::catch_unwind(AssertUnwindSafe(convert))
+        } else {
+            let result = convert();
+            let unsupported = match result {
+                Err(ref e) => e.kind() == TranslationErrorKind::UnsupportedExtension,
+                Ok(_) => false,
+            };
+            if !unsupported {
+                return result;
+            }
+            Ok(result)
+        };
+        let failed = match result {
+            Ok(Ok(_)) => false,
+            _ => true,
+        };
+        if failed {
+            self.renamer.borrow_mut().truncate_scopes(scope_depth);
+            *self.function_context.borrow_mut() = function_context;
+            *self.macro_arg_substitutions.borrow_mut() = macro_arg_substitutions;
+            self.alloca_scratch.borrow_mut().take();
+        }
+
+        let (kind, reason) = match result {
+            Ok(Ok(stmts)) => return Ok(stmts),
+            Ok(Err(e)) => (e.kind().name(), e.message()),
+            Err(payload) => {
+                let msg = match payload.downcast::<String>() {
+                    Ok(msg) => *msg,
+                    Err(payload) => match payload.downcast::<&'static str>() {
+                        Ok(msg) => msg.to_string(),
+                        Err(_) => "translator panicked".to_string(),
+                    },
+                };
+                ("panic", msg)
+            }
+        };
+        warn!(
+            "Replacing the body of {} with a stub: {}",
+            self.ast_context[decl_id].kind.get_name().map_or("function", String::as_str),
+            reason,
+        );
+        self.record_skipped(decl_id, kind, reason.clone(), true);
+
+        let mut comment = vec![format!("// Translation failed: {}", reason)];
+        comment.extend(self.c_source_lines(decl_id).into_iter().map(|line| {
+            format!("// {}", line).trim_end().to_string()
+        }));
+        let span = self
+            .comment_store
+            .borrow_mut()
+            .add_comments(&comment)
+            .map(pos_to_span)
+            .unwrap_or(DUMMY_SP);
+        let stub = mk().mac_expr(mk().mac(
+            vec!["unimplemented"],
+            vec![],
+            MacDelimiter::Parenthesis,
+        ));
+        Ok(vec![mk().span(span).semi_stmt(stub)])
+    }
+
+    /// The lines of C source a declaration spans, if its source file is still readable
+    fn c_source_lines(&self, decl_id: CDeclId) -> Vec<String> {
+        let decl = &self.ast_context[decl_id];
+        let (loc, path) = match (decl.loc, self.ast_context.get_source_path(decl)) {
+            (Some(loc), Some(path)) => (loc, path),
+            _ => return vec![],
+        };
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(_) => return vec![],
+        };
+        source
+            .lines()
+            .skip(loc.begin_line.saturating_sub(1) as usize)
+            .take((loc.end_line.saturating_sub(loc.begin_line) + 1) as usize)
+            .map(String::from)
+            .collect()
+    }
+}
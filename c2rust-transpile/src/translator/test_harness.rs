@@ -0,0 +1,110 @@
+//! Translation of C test drivers. A translation unit matching `--test-files`
+//! is emitted to the crate's `tests/` directory instead of its library, its
+//! `assert`s become `assert!`s, and its test functions are run by `#[test]`
+//! functions in a `tests` module, so that
+//!
+//! ```c
+//! void test_push(void) {
+//!     struct stack *s = stack_new();
+//!     stack_push(s, 1);
+//!     assert(stack_len(s) == 1);
+//! }
+//! ```
+//!
+//! is run by `cargo test` as
+//!
+//! ```rust,ignore
+//! mod tests {
+//!     #[test]
+//!     fn test_push() {
+//!         unsafe { super::test_push(); }
+//!     }
+//! }
+//! ```
+//!
+//! Test functions are the functions named `test...` that take no arguments.
+//! If there are none, `main` is the only test, unless it takes `argc` and
+//! `argv`. A test function returning an integer fails unless it returns 0.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// The `tests` module running the test functions of a test driver, or
+    /// `None` if it has none
+    pub fn convert_test_harness(&self) -> Option<P<Item>> {
+        let mut test_fns = vec![];
+        let mut main_id = None;
+        for &decl_id in &self.ast_context.c_decls_top {
+            match self.ast_context[decl_id].kind {
+                CDeclKind::Function {
+                    ref name,
+                    ref parameters,
+                    body: Some(_),
+                    ..
+                } if parameters.is_empty() => {
+                    if name.starts_with("test") && self.is_selected_function(decl_id, name) {
+                        test_fns.push((decl_id, name.clone()));
+                    } else if Some(decl_id) == self.ast_context.c_main {
+                        main_id = Some((decl_id, name.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if test_fns.is_empty() {
+            test_fns.extend(main_id);
+        }
+
+        let items = test_fns
+            .into_iter()
+            .filter_map(|(decl_id, name)| {
+                let rust_name = self.renamer.borrow().get(&decl_id)?;
+                let call = mk().call_expr(
+                    mk().path_expr(vec!["super".to_string(), rust_name]),
+                    Vec::<P<Expr>>::new(),
+                );
+                let stmt = if self.returns_int(decl_id) {
+                    let args = vec![
+                        TokenTree::token(
+                            token::Interpolated(Rc::new(Nonterminal::NtExpr(call))),
+                            DUMMY_SP,
+                        ),
+                        TokenTree::token(token::Comma, DUMMY_SP),
+                        TokenTree::token(
+                            token::Interpolated(Rc::new(Nonterminal::NtExpr(mk().lit_expr(
+                                mk().int_lit(0, LitIntType::Unsuffixed),
+                            )))),
+                            DUMMY_SP,
+                        ),
+                    ];
+                    let assert_eq =
+                        mk().mac_expr(mk().mac(vec!["assert_eq"], args, MacDelimiter::Parenthesis));
+                    mk().semi_stmt(assert_eq)
+                } else {
+                    mk().semi_stmt(call)
+                };
+                let unsafe_block = mk().unsafe_().block(vec![stmt]);
+                let body = mk().block(vec![mk().expr_stmt(mk().block_expr(unsafe_block))]);
+                let decl = mk().fn_decl(vec![], FunctionRetTy::Default(DUMMY_SP));
+                Some(mk().single_attr("test").fn_item(name, decl, body))
+            })
+            .collect::<Vec<_>>();
+        if items.is_empty() {
+            return None;
+        }
+        Some(mk().mod_item("tests", mk().mod_(items)))
+    }
+
+    fn returns_int(&self, decl_id: CDeclId) -> bool {
+        let typ = match self.ast_context[decl_id].kind {
+            CDeclKind::Function { typ, .. } => typ,
+            _ => return false,
+        };
+        match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Function(ret, ..) => {
+                self.ast_context.resolve_type(ret.ctype).kind.is_integral_type()
+            }
+            _ => false,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::*;
+use crate::c_ast::iterators::DFNodes;
 
 #[derive(Copy, Clone, Debug)]
 pub enum VaPart {
@@ -24,6 +25,40 @@ macro_rules! match_or {
 
 impl<'c> Translation<'c> {
 
+    /// Whether a function definition is a `gnu_inline` wrapper forwarding its
+    /// variable arguments with `__builtin_va_arg_pack()`, like the fortified
+    /// `printf` of glibc's `_FORTIFY_SOURCE` headers. C compilers never emit
+    /// such a definition, only inline it, so we declare the function instead,
+    /// which calls the non-fortified library function of the same name.
+    pub fn is_fortified_wrapper(&self, decl_id: CDeclId) -> bool {
+        let body = match self.ast_context[decl_id].kind {
+            CDeclKind::Function {
+                is_extern: true,
+                is_inline: true,
+                body: Some(body),
+                ref attrs,
+                ..
+            } if attrs.contains(&c_ast::Attribute::GnuInline) => body,
+            _ => return false,
+        };
+        DFNodes::new(&self.ast_context, SomeId::Stmt(body)).any(|node| {
+            let expr_id = match node {
+                SomeId::Expr(expr_id) => expr_id,
+                _ => return false,
+            };
+            let decl_id = match self.ast_context[expr_id].kind {
+                CExprKind::DeclRef(_, decl_id, _) => decl_id,
+                _ => return false,
+            };
+            match self.ast_context[decl_id].kind {
+                CDeclKind::Function { ref name, .. } => {
+                    name == "__builtin_va_arg_pack" || name == "__builtin_va_arg_pack_len"
+                }
+                _ => false,
+            }
+        })
+    }
+
     /// Returns true iff `va_start`, `va_end`, or `va_copy` may be called on `decl_id`.
     pub fn is_va_decl(&self, decl_id: CDeclId) -> bool {
         let fn_ctx = self.function_context.borrow();
@@ -125,13 +160,20 @@ impl<'c> Translation<'c> {
                                         ret,
                                         ref params,
                                         is_variadic,
-                                        is_noreturn, _) = resolved_ctype.kind {
+                                        is_noreturn, _,
+                                        ref calling_conv) = resolved_ctype.kind {
                         // ty is a function pointer type -> build Rust unsafe function pointer type
                         let opt_ret = if is_noreturn { None } else { Some(ret) };
                         
                         let fn_ty = self.type_converter
                             .borrow_mut()
-                            .convert_function(&self.ast_context, opt_ret, params, is_variadic)?;
+                            .convert_function(
+                                &self.ast_context,
+                                opt_ret,
+                                params,
+                                is_variadic,
+                                calling_conv.as_ref(),
+                            )?;
 
                         let m = if p.qualifiers.is_const { Mutability::Immutable } else { Mutability::Mutable };
                         Some(mk().set_mutbl(m).ptr_ty(fn_ty))
@@ -147,7 +189,7 @@ impl<'c> Translation<'c> {
                 .map_or(false, |ty| self.ast_context.is_forward_declared_type(ty.ctype))
             {
                 real_arg_ty = Some(arg_ty.clone());
-                arg_ty = mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_void"]));
+                arg_ty = mk().mutbl().ptr_ty(self.ffi_ty("c_void"));
             }
 
             val.and_then(|val| {
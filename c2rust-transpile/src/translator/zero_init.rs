@@ -0,0 +1,67 @@
+#![deny(missing_docs)]
+//! Zero values of types that have no zero literal. C zero-initializes objects
+//! of static duration and the members missing from an initializer like `{0}`,
+//! which `implicit_default_expr` translates field by field into a constant
+//! expression. A union is zeroed through a field that covers all of its bytes,
+//! since its other bytes would be left uninitialized in Rust. Values of a union
+//! without such a field, or of a type with no zero literal, are zeroed as a
+//! whole: with `MaybeUninit::zeroed` in function bodies, and by transmuting
+//! zero bytes in statics, where `MaybeUninit::zeroed` can't be called.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// The zero value of a type, built by zeroing its memory
+    pub fn zeroed_value(
+        &self,
+        ty_id: CTypeId,
+        is_static: bool,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let ty = self.convert_type(ty_id)?;
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        if is_static {
+            self.use_feature("const_transmute");
+            let size = self.compute_size_of_ty(ty.clone())?.to_expr();
+            let bytes_ty = mk().array_ty(mk().path_ty(vec!["u8"]), size.clone());
+            let zeros = mk().repeat_expr(mk().lit_expr(mk().int_lit(0, "u8")), size);
+            Ok(WithStmts::new_unsafe_val(transmute_expr(
+                bytes_ty,
+                ty,
+                zeros,
+                self.tcfg.emit_no_std,
+            )))
+        } else {
+            let zeroed = mk().path_expr(vec![
+                mk().path_segment(""),
+                mk().path_segment(std_or_core),
+                mk().path_segment("mem"),
+                mk().path_segment_with_args("MaybeUninit", mk().angle_bracketed_args(vec![ty])),
+                mk().path_segment("zeroed"),
+            ]);
+            let zeroed = mk().call_expr(zeroed, vec![] as Vec<P<Expr>>);
+            let value = mk().method_call_expr(zeroed, "assume_init", vec![] as Vec<P<Expr>>);
+            Ok(WithStmts::new_unsafe_val(value))
+        }
+    }
+
+    /// The first field of a union that covers all of its bytes, which
+    /// zeroing zeroes the whole union
+    pub fn union_covering_field(&self, decl_id: CRecordId) -> Option<CFieldId> {
+        let (fields, platform_byte_size) = match self.ast_context[decl_id].kind {
+            CDeclKind::Union {
+                fields: Some(ref fields),
+                platform_byte_size,
+                ..
+            } => (fields, platform_byte_size),
+            _ => return None,
+        };
+        fields.iter().cloned().find(|&field_id| match self.ast_context[field_id].kind {
+            CDeclKind::Field {
+                bitfield_width: None,
+                platform_type_bitwidth,
+                ..
+            } => platform_type_bitwidth == platform_byte_size * 8,
+            _ => false,
+        })
+    }
+}
@@ -0,0 +1,299 @@
+//! User-provided substitutions of existing Rust types for C types, loaded from
+//! the file given to `--type-map`. The file is TOML, or JSON if its extension
+//! is `.json`, and looks like
+//!
+//! ```toml
+//! [types]
+//! "struct timeval" = "libc::timeval"
+//!
+//! [functions.fill_buffer]
+//! "uint8_t *" = "&mut [u8]"
+//! ```
+//!
+//! Entries under `types` replace a C type everywhere it is used, and the
+//! definition of a replaced struct, union, enum or typedef is not emitted.
+//! Entries under `functions.NAME` only replace the types of the parameters and
+//! return value of function `NAME`, if it is defined in the translation unit.
+//! Its body and its callers convert the values between the C and the Rust
+//! type, see `translator::mapped_signatures`.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use rustc_parse::new_parser_from_source_str;
+use syntax::ast::{GenericArg, GenericArgs, Mutability, MutTy, Ty, TyKind};
+use syntax::mut_visit::MutVisitor;
+use syntax::ptr::P;
+use syntax::sess::ParseSess;
+use syntax::source_map::FilePathMapping;
+use syntax_pos::edition::Edition;
+use syntax_pos::{FileName, Span, DUMMY_SP};
+
+use crate::c_ast::*;
+use crate::error::TranspileError;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct TypeMap {
+    /// C type spelling to Rust type
+    #[serde(default)]
+    types: HashMap<String, String>,
+    /// Function name to C type spelling to Rust type
+    #[serde(default)]
+    functions: HashMap<String, HashMap<String, String>>,
+}
+
+impl TypeMap {
+    pub fn load(path: &Path) -> Result<Self, TranspileError> {
+        let contents = fs::read_to_string(path).map_err(|cause| TranspileError::Read {
+            what: "type map",
+            path: path.to_path_buf(),
+            cause,
+        })?;
+        let parse_error = |message| TranspileError::Parse {
+            what: "type map",
+            path: path.to_path_buf(),
+            message,
+        };
+        let map: TypeMap = if path.extension().map_or(false, |ext| ext == "json") {
+            serde_json::from_str(&contents).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&contents).map_err(|e| e.to_string())
+        }
+        .map_err(parse_error)?;
+
+        let normalize = |entries: HashMap<String, String>| {
+            entries
+                .into_iter()
+                .map(|(c_ty, rust_ty)| (normalize_spelling(&c_ty), rust_ty))
+                .collect::<HashMap<_, _>>()
+        };
+        let map = TypeMap {
+            types: normalize(map.types),
+            functions: map
+                .functions
+                .into_iter()
+                .map(|(name, entries)| (name, normalize(entries)))
+                .collect(),
+        };
+
+        // Report malformed Rust types now rather than in the middle of a translation
+        syntax::with_globals(Edition::Edition2018, || {
+            let rust_tys = map.types.values().chain(map.functions.values().flat_map(|f| f.values()));
+            for rust_ty in rust_tys {
+                parse_ty(rust_ty)?;
+            }
+            Ok(())
+        })
+        .map_err(parse_error)?;
+
+        Ok(map)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.functions.is_empty()
+    }
+
+    /// The Rust type to use for a C type everywhere, if the map replaces it
+    pub fn lookup(&self, ctxt: &TypedAstContext, ctype: CTypeId) -> Option<P<Ty>> {
+        if self.types.is_empty() {
+            return None;
+        }
+        let rust_ty = self.types.get(&type_spelling(ctxt, ctype)?)?;
+        parse_ty(rust_ty).ok()
+    }
+
+    /// The Rust type to use for a C type in the signature of the given function,
+    /// if the map replaces it
+    pub fn lookup_in_function(
+        &self,
+        ctxt: &TypedAstContext,
+        function: &str,
+        ctype: CTypeId,
+    ) -> Option<P<Ty>> {
+        let rust_ty = self.functions.get(function)?.get(&type_spelling(ctxt, ctype)?)?;
+        parse_ty(rust_ty).ok()
+    }
+
+    /// Whether the map replaces types in the signature of the given function
+    pub fn has_function(&self, function: &str) -> bool {
+        self.functions.contains_key(function)
+    }
+
+    /// Whether the type a declaration defines is replaced, so that the
+    /// declaration doesn't need to be emitted
+    pub fn replaces_decl(&self, ctxt: &TypedAstContext, decl_id: CDeclId) -> bool {
+        if self.types.is_empty() {
+            return false;
+        }
+        let spelling = match ctxt[decl_id].kind {
+            CDeclKind::Struct { name: Some(ref name), .. } => format!("struct {}", name),
+            CDeclKind::Union { name: Some(ref name), .. } => format!("union {}", name),
+            CDeclKind::Enum { name: Some(ref name), .. } => format!("enum {}", name),
+            CDeclKind::Typedef { ref name, .. } => name.clone(),
+            _ => return false,
+        };
+        self.types.contains_key(&spelling)
+    }
+}
+
+/// How the values of a type can be copied, ordered from least to most
+/// capable, so that a struct can be copied like its least capable field
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Copyability {
+    /// Not at all
+    NotClone,
+    /// With `.clone()`
+    Clone,
+    /// Implicitly
+    Copy,
+}
+
+/// Std types that are `Clone` but not `Copy`
+const CLONE_TYPES: &[&str] = &[
+    "Vec", "VecDeque", "String", "CString", "OsString", "PathBuf", "Box", "Rc", "Arc", "Cell",
+    "RefCell", "HashMap", "HashSet", "BTreeMap", "BTreeSet",
+];
+
+/// Std types that are neither `Clone` nor `Copy`
+const NOT_CLONE_TYPES: &[&str] = &["Mutex", "RwLock", "UnsafeCell", "Condvar"];
+
+/// How the values of a Rust type from the map can be copied. We only know
+/// the std types that aren't `Copy`; other types are assumed to be.
+pub fn copyability(ty: &Ty) -> Copyability {
+    match ty.kind {
+        TyKind::Rptr(_, MutTy { mutbl: Mutability::Mutable, .. }) => Copyability::NotClone,
+        TyKind::Array(ref elt, _) | TyKind::Paren(ref elt) => copyability(elt),
+        TyKind::Tup(ref elts) => elts
+            .iter()
+            .map(|elt| copyability(elt))
+            .min()
+            .unwrap_or(Copyability::Copy),
+        TyKind::Path(_, ref path) => {
+            let segment = match path.segments.last() {
+                Some(segment) => segment,
+                None => return Copyability::Copy,
+            };
+            let name = segment.ident.as_str();
+            let own = if NOT_CLONE_TYPES.contains(&&*name) || name.starts_with("Atomic") {
+                Copyability::NotClone
+            } else if CLONE_TYPES.contains(&&*name) {
+                Copyability::Clone
+            } else {
+                Copyability::Copy
+            };
+            // Shared pointers are `Clone` whatever they point to
+            if &*name == "Rc" || &*name == "Arc" {
+                return own;
+            }
+            let args = match segment.args.as_ref().map(|args| &**args) {
+                Some(GenericArgs::AngleBracketed(ref args)) => &args.args[..],
+                _ => &[],
+            };
+            args.iter()
+                .filter_map(|arg| match *arg {
+                    GenericArg::Type(ref ty) => Some(copyability(ty)),
+                    _ => None,
+                })
+                .fold(own, cmp::min)
+        }
+        _ => Copyability::Copy,
+    }
+}
+
+/// Spell a C type the way keys of the map are normalized to, ignoring qualifiers
+fn type_spelling(ctxt: &TypedAstContext, ctype: CTypeId) -> Option<String> {
+    let decl_name = |decl_id: CDeclId| ctxt.get_decl(&decl_id)?.kind.get_name().cloned();
+    let spelling = match ctxt[ctype].kind {
+        CTypeKind::Struct(decl_id) => format!("struct {}", decl_name(decl_id)?),
+        CTypeKind::Union(decl_id) => format!("union {}", decl_name(decl_id)?),
+        CTypeKind::Enum(decl_id) => format!("enum {}", decl_name(decl_id)?),
+        CTypeKind::Typedef(decl_id) => decl_name(decl_id)?,
+        CTypeKind::Elaborated(ty) | CTypeKind::Paren(ty) | CTypeKind::Decayed(ty) => {
+            return type_spelling(ctxt, ty)
+        }
+        CTypeKind::Attributed(qty, _) => return type_spelling(ctxt, qty.ctype),
+        CTypeKind::Pointer(qty) => format!("{} *", type_spelling(ctxt, qty.ctype)?),
+        CTypeKind::Void => "void".to_string(),
+        CTypeKind::Bool => "_Bool".to_string(),
+        CTypeKind::Char => "char".to_string(),
+        CTypeKind::SChar => "signed char".to_string(),
+        CTypeKind::UChar => "unsigned char".to_string(),
+        CTypeKind::Short => "short".to_string(),
+        CTypeKind::UShort => "unsigned short".to_string(),
+        CTypeKind::Int => "int".to_string(),
+        CTypeKind::UInt => "unsigned int".to_string(),
+        CTypeKind::Long => "long".to_string(),
+        CTypeKind::ULong => "unsigned long".to_string(),
+        CTypeKind::LongLong => "long long".to_string(),
+        CTypeKind::ULongLong => "unsigned long long".to_string(),
+        CTypeKind::Float => "float".to_string(),
+        CTypeKind::Double => "double".to_string(),
+        CTypeKind::LongDouble => "long double".to_string(),
+        CTypeKind::Half => "__fp16".to_string(),
+        CTypeKind::Complex(ty) => format!("_Complex {}", type_spelling(ctxt, ty)?),
+        CTypeKind::Unsupported(ref name) => name.clone(),
+        _ => return None,
+    };
+    Some(spelling)
+}
+
+/// Normalize a C type as written in the map: drop qualifiers and separate
+/// every `*` by single spaces, so that `const uint8_t*` matches `uint8_t *`
+fn normalize_spelling(c_ty: &str) -> String {
+    c_ty.replace('*', " * ")
+        .split_whitespace()
+        .filter(|word| !["const", "volatile", "restrict"].contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Spans of parsed types point into a source map that only exists while parsing
+struct ClearSpans;
+
+impl MutVisitor for ClearSpans {
+    fn visit_span(&mut self, span: &mut Span) {
+        *span = DUMMY_SP;
+    }
+}
+
+fn parse_ty(src: &str) -> Result<P<Ty>, String> {
+    let sess = ParseSess::new(FilePathMapping::empty());
+    let mut parser = new_parser_from_source_str(
+        &sess,
+        FileName::Custom("type map".to_string()),
+        src.to_string(),
+    );
+    let mut ty = parser.parse_ty().map_err(|mut e| {
+        e.cancel();
+        format!("Could not parse Rust type `{}` in type map", src)
+    })?;
+    ClearSpans.visit_ty(&mut ty);
+    Ok(ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_c_types() {
+        assert_eq!(normalize_spelling("const uint8_t*"), "uint8_t *");
+        assert_eq!(normalize_spelling("struct  timeval"), "struct timeval");
+        assert_eq!(normalize_spelling("char **"), "char * *");
+    }
+
+    #[test]
+    fn copyability_of_rust_types() {
+        syntax::with_globals(Edition::Edition2018, || {
+            let copyability_of = |src: &str| copyability(&parse_ty(src).unwrap());
+            assert_eq!(copyability_of("libc::timeval"), Copyability::Copy);
+            assert_eq!(copyability_of("*mut [u8; 4]"), Copyability::Copy);
+            assert_eq!(copyability_of("Option<Box<u8>>"), Copyability::Clone);
+            assert_eq!(copyability_of("std::rc::Rc<std::sync::Mutex<u8>>"), Copyability::Clone);
+            assert_eq!(copyability_of("(u8, Vec<AtomicU32>)"), Copyability::NotClone);
+            assert_eq!(copyability_of("&mut [u8]"), Copyability::NotClone);
+        })
+    }
+}
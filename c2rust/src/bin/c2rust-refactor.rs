@@ -9,6 +9,7 @@ extern crate shlex;
 use clap::{App, ArgMatches};
 use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
 use std::process;
 use std::str::FromStr;
 
@@ -132,6 +133,8 @@ fn parse_opts(args: &ArgMatches) -> Option<Options> {
     let plugins = args.values_of_lossy("plugin-name").unwrap_or(vec![]);
     let plugin_dirs = args.values_of_lossy("plugin-dir").unwrap_or(vec![]);
 
+    let checkpoint_dir = args.value_of("checkpoint").map(PathBuf::from);
+
     // Handle --cargo and rustc-args
     let rustc_args = match args.values_of_lossy("rustc-args") {
         Some(args) => RustcArgSource::CmdLine(args),
@@ -199,5 +202,6 @@ fn parse_opts(args: &ArgMatches) -> Option<Options> {
         marks,
         plugins,
         plugin_dirs,
+        checkpoint_dir,
     })
 }
@@ -6,9 +6,14 @@ use clap::{App, Values};
 use regex::Regex;
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::process;
 use std::str::FromStr;
 
-use c2rust_transpile::{Diagnostic, ReplaceMode, TranspilerConfig};
+use c2rust_transpile::{
+    AllocaMode, CfgConfiguration, CompileCmdOptions, Derive, Diagnostic, ExportList, IntegerConversionMode,
+    LintAllowMode, LongDoubleMode, NameCase, NamingPolicy, ReplaceMode, ReservedStyle,
+    SignedArithmeticMode, SysCrate, ThreadLocalMode, TranslationHooks, TranspilerConfig, TypeMap,
+};
 
 fn main() {
     let yaml = load_yaml!("../transpile.yaml");
@@ -32,6 +37,9 @@ fn main() {
         .map(|s| Diagnostic::from_str(s).unwrap())
         .collect();
 
+    // Options that aren't given keep the defaults of the library
+    let defaults = TranspilerConfig::default();
+
     let log_level = match matches.value_of("log-level") {
         Some("off") => log::LevelFilter::Off,
         Some("error") => log::LevelFilter::Error,
@@ -39,9 +47,23 @@ fn main() {
         Some("info") => log::LevelFilter::Info,
         Some("debug") => log::LevelFilter::Debug,
         Some("trace") => log::LevelFilter::Trace,
+        None => defaults.log_level,
         _ => panic!("Invalid log level"),
     };
 
+    // The embedded profile, which implies the other no_std options
+    let no_std = matches.is_present("no-std");
+
+    // `--rust-naming` changes the defaults of the `--*-case` options
+    let rust_naming = matches.is_present("rust-naming");
+    let name_case = |arg: &str, rust_case: NameCase, default_case: NameCase| {
+        match matches.value_of(arg) {
+            Some(case) => parse_name_case(case),
+            None if rust_naming => rust_case,
+            None => default_case,
+        }
+    };
+
     let mut tcfg = TranspilerConfig {
         dump_untyped_context: matches.is_present("dump-untyped-clang-ast"),
         dump_typed_context: matches.is_present("dump-typed-clang-ast"),
@@ -55,6 +77,7 @@ fn main() {
 
         incremental_relooper: !matches.is_present("no-incremental-relooper"),
         fail_on_error: matches.is_present("fail-on-error"),
+        best_effort: matches.is_present("best-effort"),
         fail_on_multiple: matches.is_present("fail-on-multiple"),
         filter: {
             if matches.is_present("filter") {
@@ -64,12 +87,37 @@ fn main() {
                 None
             }
         },
+        compile_cmd_options: CompileCmdOptions {
+            include_files: matches
+                .values_of("include-file")
+                .map(|vals| vals.map(String::from).collect())
+                .unwrap_or_default(),
+            exclude_files: matches
+                .values_of("exclude-file")
+                .map(|vals| vals.map(String::from).collect())
+                .unwrap_or_default(),
+            defines: matches
+                .values_of("define")
+                .map(|vals| vals.map(String::from).collect())
+                .unwrap_or_default(),
+            include_dirs: matches
+                .values_of("include-dir")
+                .map(|vals| vals.map(absolute_path).collect())
+                .unwrap_or_default(),
+            sysroot: matches.value_of("sysroot").map(absolute_path),
+        },
+        function_filter: matches
+            .value_of("filter-function")
+            .map(|filter| Regex::new(filter).unwrap()),
+        file_filter: matches
+            .value_of("filter-file")
+            .map(|filter| Regex::new(filter).unwrap()),
         debug_relooper_labels: matches.is_present("debug-labels"),
         cross_checks: matches.is_present("cross-checks"),
         cross_check_backend: matches
             .value_of("cross-check-backend")
             .map(String::from)
-            .unwrap(),
+            .unwrap_or(defaults.cross_check_backend),
         cross_check_configs: matches
             .values_of("cross-check-config")
             .map(|vals| vals.map(String::from).collect::<Vec<_>>())
@@ -86,8 +134,29 @@ fn main() {
         // stable rust output.
         translate_valist: true,
 
+        translate_simd: matches.is_present("translate-simd"),
+        check_abi: matches.is_present("check-abi"),
+        emit_layout_assertions: matches.is_present("emit-layout-assertions"),
+        union_accessors: matches.is_present("union-accessors"),
+
         translate_const_macros: matches.is_present("translate-const-macros"),
         translate_fn_macros: matches.is_present("translate-fn-macros"),
+        lower_libc_calls: matches.is_present("lower-libc-calls"),
+        debug_asserts: matches.is_present("debug-asserts"),
+        portable_errno: matches.is_present("portable-errno"),
+        cfg_configurations: matches
+            .values_of("cfg-config")
+            .map(|vals| {
+                vals.map(|val| CfgConfiguration::parse(val).unwrap_or_else(|e| panic!("{}", e)))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        target: matches.value_of("target").map(String::from),
+        report_file: matches.value_of("report-file").map(PathBuf::from),
+        rename_map: matches.value_of("rename-map").map(PathBuf::from),
+        source_map: matches.is_present("source-map"),
+        annotate_with_c_source: matches.is_present("annotate-with-c-source"),
+        rustfmt: matches.is_present("rustfmt"),
         disable_refactoring: matches.is_present("disable-refactoring"),
 
         use_c_loop_info: !matches.is_present("ignore-c-loop-info"),
@@ -95,7 +164,14 @@ fn main() {
         simplify_structures: !matches.is_present("no-simplify-structures"),
         overwrite_existing: matches.is_present("overwrite-existing"),
         reduce_type_annotations: matches.is_present("reduce-type-annotations"),
+        range_loops: matches.is_present("range-loops"),
+        owned_allocations: matches.is_present("owned-allocations"),
+        option_pointers: matches.is_present("option-pointers"),
+        structured_cleanup: matches.is_present("structured-cleanup"),
+        checked_indexing: matches.is_present("checked-indexing"),
+        checked_unreachable: matches.is_present("checked-unreachable"),
         reorganize_definitions: matches.is_present("reorganize-definitions"),
+        split_included_sources: matches.is_present("split-included-sources"),
         emit_modules: matches.is_present("emit-modules"),
         emit_build_files: matches.is_present("emit-build-files"),
         output_dir: matches.value_of("output-dir").map(PathBuf::from),
@@ -103,26 +179,158 @@ fn main() {
             .values_of("binary")
             .map(|values| values.map(String::from).collect())
             .unwrap_or_else(|| vec![]),
+        test_files: matches
+            .value_of("test-files")
+            .map(|pattern| Regex::new(pattern).unwrap()),
+        differential_harness: matches.is_present("differential-harness"),
         panic_on_translator_failure: {
             match matches.value_of("invalid-code") {
                 Some("panic") => true,
                 Some("compile_error") => false,
+                None => defaults.panic_on_translator_failure,
                 _ => panic!("Invalid option"),
             }
         },
         replace_unsupported_decls: ReplaceMode::Extern,
-        emit_no_std: matches.is_present("emit-no-std"),
+        thread_local_mode: match matches.value_of("thread-locals") {
+            Some("attribute") => ThreadLocalMode::Attribute,
+            Some("macro") => ThreadLocalMode::Macro,
+            None => defaults.thread_local_mode,
+            _ => panic!("Invalid option"),
+        },
+        long_double_mode: match matches.value_of("long-double") {
+            Some("f128") => LongDoubleMode::F128,
+            Some("f64") => LongDoubleMode::F64,
+            // The f128 crate needs std
+            None if no_std => LongDoubleMode::F64,
+            None => defaults.long_double_mode,
+            _ => panic!("Invalid option"),
+        },
+        integer_conversions: match matches.value_of("integer-conversions") {
+            Some("default") => IntegerConversionMode::Default,
+            Some("exact") => IntegerConversionMode::Exact,
+            Some("checked") => IntegerConversionMode::Checked,
+            None => defaults.integer_conversions,
+            _ => panic!("Invalid option"),
+        },
+        signed_arithmetic: match matches.value_of("signed-arithmetic") {
+            Some("plain") => SignedArithmeticMode::Plain,
+            Some("wrapping") => SignedArithmeticMode::WrappingMethods,
+            Some("wrapping-type") => SignedArithmeticMode::WrappingType,
+            None => defaults.signed_arithmetic,
+            _ => panic!("Invalid option"),
+        },
+        naming: NamingPolicy {
+            collision_prefix: matches
+                .value_of("collision-prefix")
+                .map(String::from)
+                .unwrap_or(defaults.naming.collision_prefix),
+            collision_suffix: matches
+                .value_of("collision-suffix")
+                .map(String::from)
+                .unwrap_or(defaults.naming.collision_suffix),
+            type_case: name_case("type-case", NameCase::UpperCamel, defaults.naming.type_case),
+            const_case: name_case("const-case", NameCase::UpperSnake, defaults.naming.const_case),
+            value_case: name_case("value-case", NameCase::Snake, defaults.naming.value_case),
+            reserved_style: match matches.value_of("reserved-names") {
+                Some("numbered") => ReservedStyle::Numbered,
+                Some("underscore") => ReservedStyle::TrailingUnderscore,
+                Some("raw") => ReservedStyle::Raw,
+                None => defaults.naming.reserved_style,
+                _ => panic!("Invalid option"),
+            },
+        },
+        lint_allow_mode: match matches.value_of("allow-lints") {
+            Some("crate") => LintAllowMode::Crate,
+            Some("item") => LintAllowMode::Item,
+            Some("none") => LintAllowMode::None,
+            None => defaults.lint_allow_mode,
+            _ => panic!("Invalid option"),
+        },
+        fn_attributes: matches
+            .values_of("fn-attribute")
+            .map(|vals| vals.map(String::from).collect())
+            .unwrap_or_default(),
+        alloca_mode: match matches.value_of("alloca") {
+            Some("vec") => AllocaMode::Vec,
+            Some("crate") => AllocaMode::Crate,
+            None => defaults.alloca_mode,
+            _ => panic!("Invalid option"),
+        },
+        derives: matches
+            .values_of("derive")
+            .map(|vals| {
+                vals.map(|val| match val {
+                    "debug" => Derive::Debug,
+                    "default" => Derive::Default,
+                    "partial-eq" => Derive::PartialEq,
+                    _ => panic!("Invalid option"),
+                })
+                .collect()
+            })
+            .unwrap_or_default(),
+        emit_no_std: no_std || matches.is_present("emit-no-std") || matches.is_present("no-libc"),
+        emit_no_libc: no_std || matches.is_present("no-libc"),
+        emit_no_std_crate: no_std || matches.is_present("no-libc"),
+        alloc_shim: matches.value_of("alloc-shim").map(String::from),
         enabled_warnings,
         log_level,
+        jobs: matches
+            .value_of("jobs")
+            .map(|jobs| jobs.parse().expect("--jobs must be a number")),
+        hooks: TranslationHooks::new(),
+        type_map: matches
+            .value_of("type-map")
+            .map(|path| TypeMap::load(Path::new(path)).unwrap_or_else(|e| panic!("{}", e)))
+            .unwrap_or_default(),
+        sys_crate: matches.value_of("sys-crate").map(|name| {
+            let version = matches.value_of("sys-crate-version").unwrap();
+            let symbols = Path::new(matches.value_of("sys-crate-symbols").unwrap());
+            SysCrate::load(name, version, symbols).unwrap_or_else(|e| panic!("{}", e))
+        }),
+        export_list: matches.value_of("export-list").map(|path| {
+            ExportList::load(Path::new(path)).unwrap_or_else(|e| panic!("{}", e))
+        }),
+        emit_bindings_only: matches.is_present("emit-bindings-only"),
+        share_inline_fns: matches.is_present("share-inline-fns"),
+        order_items: matches.is_present("order-items"),
+        keep_unused: matches.is_present("keep-unused"),
+        root_decls: matches
+            .values_of("root-decl")
+            .map(|vals| vals.map(|val| Regex::new(val).unwrap()).collect())
+            .unwrap_or_default(),
     };
-    // binaries imply emit-build-files
-    if !tcfg.binaries.is_empty() {
-        tcfg.emit_build_files = true
-    };
-    // emit-build-files implies emit-modules
-    if tcfg.emit_build_files {
-        tcfg.emit_modules = true
-    };
+    tcfg.apply_implied_options();
+
+    // Exit with 1 if the run failed, and with 2 if some input files failed
+    // to translate
+    match c2rust_transpile::transpile(tcfg, &cc_json_path, &extra_args) {
+        Ok(result) => {
+            if !result.report.failed_files.is_empty() {
+                process::exit(2);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_name_case(case: &str) -> NameCase {
+    match case {
+        "preserve" => NameCase::Preserve,
+        "snake" => NameCase::Snake,
+        "upper-snake" => NameCase::UpperSnake,
+        "upper-camel" => NameCase::UpperCamel,
+        _ => panic!("Invalid option"),
+    }
+}
 
-    c2rust_transpile::transpile(tcfg, &cc_json_path, &extra_args);
+/// Paths on the command line are relative to the current directory, but
+/// clang resolves the paths of a compile command relative to its directory
+fn absolute_path(path: &str) -> PathBuf {
+    std::env::current_dir()
+        .expect("Could not get the current directory")
+        .join(path)
 }
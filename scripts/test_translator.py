@@ -6,6 +6,7 @@ import sys
 import logging
 import argparse
 import re
+import shutil
 
 from common import (
     config as c,
@@ -71,8 +72,48 @@ class CFile:
         self.disable_incremental_relooper = "disable_incremental_relooper" in flags
         self.disallow_current_block = "disallow_current_block" in flags
         self.translate_const_macros = "translate_const_macros" in flags
+        self.translate_fn_macros = "translate_fn_macros" in flags
+        self.lower_libc_calls = "lower_libc_calls" in flags
         self.reorganize_definitions = "reorganize_definitions" in flags
+        self.split_included_sources = "split_included_sources" in flags
         self.emit_build_files = "emit_build_files" in flags
+        self.thread_locals_macro = "thread_locals_macro" in flags
+        self.translate_simd = "translate_simd" in flags
+        self.long_double_f64 = "long_double_f64" in flags
+        self.check_abi = "check_abi" in flags
+        self.emit_layout_assertions = "emit_layout_assertions" in flags
+        self.union_accessors = "union_accessors" in flags
+        self.owned_allocations = "owned_allocations" in flags
+        self.option_pointers = "option_pointers" in flags
+        self.emit_bindings_only = "emit_bindings_only" in flags
+        self.rust_naming = "rust_naming" in flags
+        self.annotate_with_c_source = "annotate_with_c_source" in flags
+        self.rustfmt = "rustfmt" in flags
+        self.structured_cleanup = "structured_cleanup" in flags
+        self.checked_indexing = "checked_indexing" in flags
+        self.portable_errno = "portable_errno" in flags
+        self.alloca_crate = "alloca_crate" in flags
+        self.derives = "derives" in flags
+        self.keep_unused = "keep_unused" in flags
+        self.order_items = "order_items" in flags
+        self.no_libc = "no_libc" in flags
+        self.report_file = "report_file" in flags
+        self.share_inline_fns = "share_inline_fns" in flags
+        self.signed_wrapping = "signed_wrapping" in flags
+        self.signed_wrapping_type = "signed_wrapping_type" in flags
+        self.allow_lints_item = "allow_lints_item" in flags
+        self.fn_attribute = "fn_attribute" in flags
+        self.emit_no_std = "emit_no_std" in flags
+        self.best_effort = "best_effort" in flags
+        self.filter_function = "filter_function" in flags
+        self.type_map = "type_map" in flags
+        self.exact_conversions = "exact_conversions" in flags
+        self.checked_conversions = "checked_conversions" in flags
+        self.range_loops = "range_loops" in flags
+        self.test_files = "test_files" in flags
+        self.export_list = "export_list" in flags
+        self.source_map = "source_map" in flags
+        self.blocks = "blocks" in flags
 
     def translate(self, cc_db, extra_args: List[str] = []) -> RustFile:
         extensionless_file, _ = os.path.splitext(self.path)
@@ -98,16 +139,128 @@ class CFile:
             args.append("--fail-on-multiple")
         if self.translate_const_macros:
             args.append("--translate-const-macros")
+        if self.translate_fn_macros:
+            args.append("--translate-fn-macros")
+        if self.lower_libc_calls:
+            args.append("--lower-libc-calls")
         if self.reorganize_definitions:
             args.append("--reorganize-definitions")
+        if self.split_included_sources:
+            args.append("--split-included-sources")
         if self.emit_build_files:
             args.append("--emit-build-files")
+        if self.thread_locals_macro:
+            args.append("--thread-locals=macro")
+        if self.translate_simd:
+            args.append("--translate-simd")
+        if self.long_double_f64:
+            args.append("--long-double=f64")
+        if self.check_abi:
+            args.append("--check-abi")
+        if self.emit_layout_assertions:
+            args.append("--emit-layout-assertions")
+        if self.union_accessors:
+            args.append("--union-accessors")
+        if self.owned_allocations:
+            args.append("--owned-allocations")
+
+        if self.option_pointers:
+            args.append("--option-pointers")
+
+        if self.emit_bindings_only:
+            args.append("--emit-bindings-only")
+
+        if self.rust_naming:
+            args.append("--rust-naming")
+
+        if self.annotate_with_c_source:
+            args.append("--annotate-with-c-source")
+
+        if self.rustfmt:
+            args.append("--rustfmt")
+
+        if self.structured_cleanup:
+            args.append("--structured-cleanup")
+
+        if self.checked_indexing:
+            args.append("--checked-indexing")
+
+        if self.portable_errno:
+            args.append("--portable-errno")
+
+        if self.alloca_crate:
+            args.append("--alloca=crate")
+
+        if self.derives:
+            args.extend(["--derive=debug", "--derive=default", "--derive=partial-eq"])
+
+        if self.keep_unused:
+            args.append("--keep-unused")
+
+        if self.order_items:
+            args.append("--order-items")
+
+        if self.no_libc:
+            args.append("--no-libc")
+
+        if self.report_file:
+            args.append("--report-file=" + extensionless_file + ".report.json")
+
+        if self.share_inline_fns:
+            args.append("--share-inline-fns")
+
+        if self.signed_wrapping:
+            args.append("--signed-arithmetic=wrapping")
+
+        if self.signed_wrapping_type:
+            args.append("--signed-arithmetic=wrapping-type")
+
+        if self.allow_lints_item:
+            args.append("--allow-lints=item")
+
+        if self.fn_attribute:
+            args.append("--fn-attribute=must_use")
+
+        if self.emit_no_std:
+            args.append("--emit-no-std")
+
+        if self.best_effort:
+            args.append("--best-effort")
+
+        if self.filter_function:
+            args.append("--filter-function=^selected")
+
+        if self.type_map:
+            args.append("--type-map=" + extensionless_file + ".toml")
+
+        if self.exact_conversions:
+            args.append("--integer-conversions=exact")
+
+        if self.checked_conversions:
+            args.append("--integer-conversions=checked")
+
+        if self.range_loops:
+            args.append("--range-loops")
+
+        if self.export_list:
+            args.append("--export-list=" + extensionless_file + ".exports")
+
+        if self.source_map:
+            args.append("--source-map")
+
+        # Test drivers are emitted to the `tests/` directory of a crate
+        crate_dir = extensionless_file + "_crate"
+        if self.test_files:
+            args.append("--test-files=.")
+            args.append("--output-dir=" + crate_dir)
 
         if self.logLevel == 'DEBUG':
             args.append("--log-level=debug")
 
         args.append("--")
         args.extend(extra_args)
+        if self.blocks:
+            args.append("-fblocks")
 
         with pb.local.env(RUST_BACKTRACE='1', LD_LIBRARY_PATH=ld_lib_path):
             # log the command in a format that's easy to re-run
@@ -123,6 +276,13 @@ class CFile:
         if retcode != 0:
             raise NonZeroReturn(stderr)
 
+        if self.test_files:
+            # Keep only the test driver, next to the C file like other translations
+            _, file_name = os.path.split(extensionless_file)
+            shutil.move(os.path.join(crate_dir, "tests", file_name + ".rs"),
+                        extensionless_file + ".rs")
+            shutil.rmtree(crate_dir)
+
         return RustFile(extensionless_file + ".rs")
 
 
@@ -134,6 +294,8 @@ def build_static_library(c_files: Iterable[CFile],
 
     # create .o files
     args = ["-c", "-fPIC", "-march=native"]
+    if any(c_file.blocks for c_file in c_files):
+        args.append("-fblocks")
 
     args.extend(c_file.path for c_file in c_files)
 
@@ -216,7 +378,7 @@ class TestDirectory:
                 _, ext = os.path.splitext(path)
                 filename = os.path.splitext(os.path.basename(path))[0]
 
-                if ext == ".c":
+                if ext in (".c", ".cpp"):
                     c_file = self._read_c_file(path)
 
                     if c_file:
@@ -351,6 +513,7 @@ class TestDirectory:
             "const_transmute",
             "nll",
             "linkage",
+            "raw_ref_op",
             "register_tool",
         ])
         rust_file_builder.add_pragma("register_tool", ["c2rust"])
@@ -379,6 +542,13 @@ class TestDirectory:
                 continue
 
             self.generated_files["rust_src"].append(translated_rust_file)
+            extensionless_c_file, _ = os.path.splitext(c_file.path)
+            if c_file.report_file:
+                self.generated_files["rust_src"].append(extensionless_c_file + ".report.json")
+            if c_file.best_effort:
+                self.generated_files["rust_src"].append(extensionless_c_file + ".skipped.json")
+            if c_file.source_map:
+                self.generated_files["rust_src"].append(extensionless_c_file + ".source_map.json")
             if c_file.emit_build_files:
                 self.generated_files["rust_src"].append(self.full_path + "/src/Cargo.toml")
                 self.generated_files["rust_src"].append(self.full_path + "/src/build.rs")
@@ -388,8 +558,27 @@ class TestDirectory:
             _, rust_file_short = os.path.split(translated_rust_file.path)
             extensionless_rust_file, _ = os.path.splitext(rust_file_short)
 
-            rust_file_builder.add_mod(RustMod(extensionless_rust_file,
-                                              RustVisibility.Public))
+            # Test drivers import the crate they test, so they are only
+            # inspected, not built
+            if not c_file.test_files:
+                rust_file_builder.add_mod(RustMod(extensionless_rust_file,
+                                                  RustVisibility.Public))
+
+            # Shared inline functions are imported from the crate's `inline_fns` module
+            if c_file.share_inline_fns and c_file.emit_build_files:
+                self.generated_files["rust_src"].append(self.full_path + "/src/inline_fns.rs")
+                rust_file_builder.add_mod(RustMod("inline_fns", RustVisibility.Public))
+
+            # Variables with external linkage are resolved through the crate's
+            # `tentative_defs` and `extern_statics` modules
+            if c_file.emit_build_files:
+                for linked_mod in ["tentative_defs", "extern_statics"]:
+                    linked_module = self.full_path + "/src/" + linked_mod + ".rs"
+                    if os.path.exists(linked_module) and \
+                            linked_module not in self.generated_files["rust_src"]:
+                        self.generated_files["rust_src"].append(linked_module)
+                        rust_file_builder.add_mod(RustMod(linked_mod,
+                                                          RustVisibility.Public))
 
         match_arms = []
         rustc_extra_args = ["-C", "target-cpu=native"]
@@ -0,0 +1,45 @@
+#include <alloca.h>
+#include <stdint.h>
+#include <string.h>
+
+// The memory `alloca` returns lives until the function returns, so it can
+// outlive the block and the loop iteration of the call
+int alloca_lifetimes(unsigned n, int buf[])
+{
+    int *squares[4];
+    for (int i = 0; i < 4; i++) {
+        squares[i] = alloca(sizeof(int) * n);
+        for (unsigned j = 0; j < n; j++) {
+            squares[i][j] = (i + 1) * j * j;
+        }
+    }
+
+    char *name;
+    if (n > 2) {
+        name = __builtin_alloca(16);
+        strcpy(name, "alloca");
+    } else {
+        name = alloca(1);
+        name[0] = '\0';
+    }
+
+    int counter = 0;
+    for (int i = 0; i < 4; i++) {
+        for (unsigned j = 0; j < n; j++) {
+            buf[counter++] = squares[i][j];
+        }
+    }
+    return counter + (int)strlen(name);
+}
+
+// The memory `alloca` returns is aligned for any type
+int alloca_aligned(unsigned n)
+{
+    for (unsigned size = 1; size <= n; size++) {
+        char *bytes = alloca(size);
+        if ((uintptr_t)bytes % 16 != 0) {
+            return 0;
+        }
+    }
+    return 1;
+}
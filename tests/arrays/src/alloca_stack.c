@@ -0,0 +1,52 @@
+//! alloca_crate
+#include <alloca.h>
+#include <stdarg.h>
+
+struct pair {
+    double x;
+    long y;
+};
+
+// Constant sized calls outside of loops are allocated on the stack
+long alloca_stack(int flag)
+{
+    struct pair *pairs = alloca(3 * sizeof(struct pair));
+    int *ints = alloca(5 * sizeof(int));
+    long total = 0;
+
+    for (int i = 0; i < 5; i++) {
+        ints[i] = i * flag;
+    }
+    for (int i = 0; i < 3; i++) {
+        pairs[i].x = i * 0.5;
+        pairs[i].y = ints[i + 2];
+    }
+
+    if (flag < 0) {
+        return -1;
+    }
+    for (int i = 0; i < 3; i++) {
+        total += pairs[i].y + (long)(pairs[i].x * 4);
+    }
+    return total;
+}
+
+// Variadic functions keep their alloca calls on the heap, since a closure
+// can't use their va_list
+long alloca_variadic(int count, ...)
+{
+    long *values = alloca(4 * sizeof(long));
+    long total = 0;
+    va_list ap;
+
+    va_start(ap, count);
+    for (int i = 0; i < 4; i++) {
+        values[i] = i < count ? va_arg(ap, long) : 0;
+    }
+    va_end(ap);
+
+    for (int i = 0; i < 4; i++) {
+        total += values[i] * (i + 1);
+    }
+    return total;
+}
@@ -0,0 +1,17 @@
+int array_alias_sum(void) {
+    int squares[8];
+    int *p = squares;
+    for (int i = 0; i < 8; i++) {
+        p[i] = i * i;
+    }
+
+    // The address of an element is taken, so `q` stays a pointer
+    int *q = squares;
+    int *last = &q[7];
+
+    int sum = 0;
+    for (unsigned i = 0; i < 8; i++) {
+        sum += p[i];
+    }
+    return sum + *last;
+}
@@ -0,0 +1,16 @@
+//! checked_indexing
+
+int checked_tail_sum(int n) {
+    int values[6];
+    for (int i = 0; i < 6; i++) {
+        values[i] = i * n;
+    }
+
+    // Pointers into the array, one of them one past its end
+    int *end = &values[6];
+    int sum = 0;
+    for (int *p = &values[1]; p != end; p++) {
+        sum += *p;
+    }
+    return sum;
+}
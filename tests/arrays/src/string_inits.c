@@ -0,0 +1,46 @@
+struct person {
+    char name[8];
+    int age;
+};
+
+struct team {
+    char title[6];
+    struct person members[3];
+};
+
+static struct team global_team = {"red", {{"alice", 30}, {"bob", 25}}};
+static char global_names[1][8] = {"carol"};
+static char global_grid[2][3] = {"ab", "cde"};
+
+static unsigned sum_bytes(const char *bytes, unsigned len) {
+    unsigned sum = 0;
+    for (unsigned i = 0; i < len; i++) {
+        sum = sum * 31 + (unsigned char)bytes[i];
+    }
+    return sum;
+}
+
+unsigned string_inits(void) {
+    struct person p = {"dave", 40};
+    struct person unterminated = {"margaret", 50};
+    struct team local_team = {"blue", {{"frank", 1}, {"grace", 2}, {"heidi", 3}}};
+    char names[1][8] = {"ivan"};
+    char grid[2][3] = {"xy", "z"};
+    char braced[8] = {"judy"};
+
+    unsigned sum = 0;
+    sum += sum_bytes(p.name, sizeof(p.name)) + p.age;
+    sum += sum_bytes(unterminated.name, sizeof(unterminated.name)) + unterminated.age;
+    sum += sum_bytes((const char *)&local_team, sizeof(local_team.title));
+    for (int i = 0; i < 3; i++) {
+        sum += sum_bytes(local_team.members[i].name, 8) + local_team.members[i].age;
+        sum += sum_bytes(global_team.members[i].name, 8) + global_team.members[i].age;
+    }
+    sum += sum_bytes(global_team.title, sizeof(global_team.title));
+    sum += sum_bytes(names[0], sizeof(names[0]));
+    sum += sum_bytes(global_names[0], sizeof(global_names[0]));
+    sum += sum_bytes(grid[0], sizeof(grid));
+    sum += sum_bytes(global_grid[0], sizeof(global_grid));
+    sum += sum_bytes(braced, sizeof(braced));
+    return sum;
+}
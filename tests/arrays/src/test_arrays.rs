@@ -1,9 +1,16 @@
+//! extern_crate_alloca, feature_c_variadic
+
 extern crate libc;
 
+use alloca_buffers::{rust_alloca_aligned, rust_alloca_lifetimes};
+use alloca_stack::{rust_alloca_stack, rust_alloca_variadic};
+use array_aliases::rust_array_alias_sum;
 use arrays::rust_entry;
+use checked_indexing::rust_checked_tail_sum;
 use incomplete_arrays::{rust_test_sized_array,rust_entry2,rust_check_some_ints};
+use string_inits::rust_string_inits;
 use variable_arrays::{rust_variable_arrays, rust_alloca_arrays};
-use self::libc::{c_int, c_uint};
+use self::libc::{c_int, c_long, c_uint};
 
 #[link(name = "test")]
 extern "C" {
@@ -24,6 +31,24 @@ extern "C" {
 
     #[no_mangle]
     fn check_some_ints() -> bool;
+
+    #[no_mangle]
+    fn array_alias_sum() -> c_int;
+
+    #[no_mangle]
+    fn checked_tail_sum(_: c_int) -> c_int;
+
+    #[no_mangle]
+    fn string_inits() -> c_uint;
+
+    #[no_mangle]
+    fn alloca_lifetimes(_: c_uint, _: *mut c_int) -> c_int;
+
+    #[no_mangle]
+    fn alloca_stack(_: c_int) -> c_long;
+
+    #[no_mangle]
+    fn alloca_variadic(_: c_int, ...) -> c_long;
 }
 
 #[no_mangle]
@@ -47,6 +72,33 @@ pub fn test_global_incomplete_array() {
     }
 }
 
+pub fn test_array_aliases() {
+    unsafe {
+        assert_eq!(rust_array_alias_sum(), array_alias_sum());
+        assert_eq!(rust_array_alias_sum(), 189);
+    }
+}
+
+pub fn test_checked_indexing() {
+    unsafe {
+        assert_eq!(rust_checked_tail_sum(3), checked_tail_sum(3));
+        assert_eq!(rust_checked_tail_sum(3), 45);
+    }
+
+    // Element addresses are taken from bounds-checked slices
+    let src = include_str!("checked_indexing.rs");
+    assert!(src.contains("..].as_mut_ptr()"));
+    assert!(!src.contains(".as_mut_ptr().offset("));
+    // and used as raw pointers, since a reference one past the end is invalid
+    assert!(!src.contains("&mut *values["));
+}
+
+pub fn test_string_inits() {
+    unsafe {
+        assert_eq!(rust_string_inits(), string_inits());
+    }
+}
+
 pub fn test_buffer() {
     let mut buffer = [0; BUFFER_SIZE];
     let mut rust_buffer = [0; BUFFER_SIZE];
@@ -134,3 +186,43 @@ pub fn test_alloca_arrays() {
         assert_eq!(buffer[index], rust_buffer[index], "index: {}", index);
     }
 }
+
+pub fn test_alloca_lifetimes() {
+    const N: usize = 5;
+    let mut buffer = [0; 4 * N];
+    let mut rust_buffer = [0; 4 * N];
+    let (ret, rust_ret) = unsafe {
+        (
+            alloca_lifetimes(N as c_uint, buffer.as_mut_ptr()),
+            rust_alloca_lifetimes(N as c_uint, rust_buffer.as_mut_ptr()),
+        )
+    };
+
+    assert_eq!(ret, 4 * N as c_int + 6);
+    assert_eq!(rust_ret, ret);
+    assert_eq!(&buffer[N..2 * N], &[0, 2, 8, 18, 32]);
+    assert_eq!(&rust_buffer[..], &buffer[..]);
+}
+
+pub fn test_alloca_stack() {
+    for &flag in &[-1, 0, 3] {
+        let ret = unsafe { alloca_stack(flag) };
+        let rust_ret = unsafe { rust_alloca_stack(flag) };
+        assert_eq!(ret, rust_ret, "flag: {}", flag);
+    }
+    assert_eq!(unsafe { rust_alloca_stack(3) }, 6 + 9 + 12 + 6);
+}
+
+pub fn test_alloca_aligned() {
+    assert_eq!(unsafe { rust_alloca_aligned(40) }, 1);
+}
+
+pub fn test_alloca_variadic() {
+    let ret = unsafe { alloca_variadic(3, 5 as c_long, 7 as c_long, 11 as c_long) };
+    let rust_ret = unsafe { rust_alloca_variadic(3, 5 as c_long, 7 as c_long, 11 as c_long) };
+    assert_eq!(ret, 5 + 14 + 33);
+    assert_eq!(rust_ret, ret);
+
+    let src = include_str!("alloca_stack.rs");
+    assert!(src.contains("struct AllocaChunk("));
+}
@@ -0,0 +1,46 @@
+//! lower_libc_calls
+#include <assert.h>
+#include <math.h>
+#include <stdlib.h>
+#include <string.h>
+
+static _Noreturn void fail(void)
+{
+    abort();
+}
+
+static int checked(int x)
+{
+    if (x >= 0) {
+        return x;
+    }
+    fail();
+}
+
+void libc_calls(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+    const char *s = "hello, world";
+    char copy[16];
+
+    memset(copy, 0, sizeof(copy));
+    memcpy(copy, s, 5);
+    memmove(copy + 1, copy, 4);
+
+    buffer[i++] = strlen(s);
+    buffer[i++] = strlen(copy);
+    buffer[i++] = copy[1];
+    buffer[i++] = abs(-7);
+    buffer[i++] = labs(-8L);
+    buffer[i++] = llabs(9LL);
+    buffer[i++] = fabs(-2.5) * 2;
+    buffer[i++] = sqrt(16.0);
+    buffer[i++] = floor(-1.5);
+    buffer[i++] = ceil(-1.5);
+    buffer[i++] = trunc(-1.5);
+    buffer[i++] = round(2.5);
+    buffer[i++] = checked(4);
+
+    assert(i == 13);
+    assert(strlen(s) > 5 && copy[0] == 'h');
+}
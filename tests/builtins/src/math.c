@@ -20,4 +20,19 @@ int isnan(double a) {
 
 int isinf_sign(double a) {
     return __builtin_isinf_sign(a);
-}
\ No newline at end of file
+}
+int clrsb(int a) {
+    return __builtin_clrsb(a);
+}
+
+int parity(unsigned a) {
+    return __builtin_parity(a);
+}
+
+unsigned rotateleft32(unsigned a, unsigned n) {
+    return __builtin_rotateleft32(a, n);
+}
+
+int abs_(int a) {
+    return __builtin_abs(a);
+}
@@ -0,0 +1,7 @@
+//! no_libc
+
+unsigned long no_libc_copy(int *dst, const int *src, unsigned long n) {
+    if (__builtin_memcpy(dst, src, n * sizeof(int)) != dst)
+        return 0;
+    return __builtin_strlen("copied") + (dst[0] == src[0]);
+}
@@ -0,0 +1,9 @@
+//! lower_libc_calls
+#include <stdio.h>
+
+void printf_calls(int x)
+{
+    printf("a%d", x);
+    fputs("b\n", stdout);
+    printf("c%x\n", x + 10);
+}
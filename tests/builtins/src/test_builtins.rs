@@ -3,18 +3,29 @@ extern crate libc;
 
 use atomics::{rust_atomics_entry, rust_new_atomics};
 use mem_x_fns::{rust_mem_x, rust_assume_aligned};
+use libc_calls::rust_libc_calls;
+use fortified::rust_format_pair;
 use math::{rust_ffs, rust_ffsl, rust_ffsll, rust_isfinite, rust_isnan, rust_isinf_sign};
-use self::libc::{c_int, c_uint, c_char, c_long, c_longlong, c_double};
+use math::{rust_clrsb, rust_parity, rust_rotateleft32, rust_abs_};
+use unreachable::{rust_abs_or_unreachable, rust_classify};
+use no_libc::rust_no_libc_copy;
+use self::libc::{c_int, c_uint, c_char, c_long, c_longlong, c_ulong, c_double, size_t};
 
 #[link(name = "test")]
 extern "C" {
+    #[no_mangle]
+    fn no_libc_copy(_: *mut c_int, _: *const c_int, _: c_ulong) -> c_ulong;
     #[no_mangle]
     fn atomics_entry(_: c_uint, _: *mut c_int);
     #[no_mangle]
     fn new_atomics(_: c_uint, _: *mut c_int);
     #[no_mangle]
+    fn libc_calls(_: c_uint, _: *mut c_int);
+    #[no_mangle]
     fn mem_x(_: *const c_char, _: *mut c_char);
     #[no_mangle]
+    fn format_pair(_: *mut c_char, _: size_t, _: c_int, _: c_int) -> c_int;
+    #[no_mangle]
     fn ffs(_: c_int) -> c_int;
     #[no_mangle]
     fn ffsl(_: c_long) -> c_int;
@@ -26,6 +37,18 @@ extern "C" {
     fn isnan(_: c_double) -> c_int;
     #[no_mangle]
     fn isinf_sign(_: c_double) -> c_int;
+    #[no_mangle]
+    fn clrsb(_: c_int) -> c_int;
+    #[no_mangle]
+    fn parity(_: c_uint) -> c_int;
+    #[no_mangle]
+    fn rotateleft32(_: c_uint, _: c_uint) -> c_uint;
+    #[no_mangle]
+    fn abs_(_: c_int) -> c_int;
+    #[no_mangle]
+    fn classify(_: c_int) -> c_int;
+    #[no_mangle]
+    fn abs_or_unreachable(_: c_int) -> c_int;
 }
 
 const BUFFER_SIZE: usize = 1024;
@@ -155,6 +178,19 @@ pub fn test_clang9_intrinsics() {
     }
 }
 
+pub fn test_bit_builtins() {
+    for &i in &[0, 1, -1, 2, -2, 255, -256, 0x7fff_ffff, -0x7fff_ffff] {
+        unsafe {
+            assert_eq!(clrsb(i), rust_clrsb(i));
+            assert_eq!(parity(i as u32), rust_parity(i as u32));
+            assert_eq!(abs_(i), rust_abs_(i));
+            for n in 0..32 {
+                assert_eq!(rotateleft32(i as u32, n), rust_rotateleft32(i as u32, n));
+            }
+        }
+    }
+}
+
 pub fn test_assume_aligned() {
     let null = std::ptr::null_mut();
 
@@ -162,3 +198,65 @@ pub fn test_assume_aligned() {
         assert_eq!(rust_assume_aligned(null), null);
     }
 }
+
+pub fn test_libc_calls() {
+    let mut buffer = [0; 13];
+    let mut rust_buffer = [0; 13];
+    let expected_buffer = [12, 5, 104, 7, 8, 9, 5, 4, -2, -1, -1, 3, 4];
+
+    unsafe {
+        libc_calls(13, buffer.as_mut_ptr());
+        rust_libc_calls(13, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_fortified_wrapper() {
+    let mut buffer = [0 as c_char; 16];
+    let mut rust_buffer = [0 as c_char; 16];
+
+    unsafe {
+        let len = format_pair(buffer.as_mut_ptr(), 16, 4, -2);
+        let rust_len = rust_format_pair(rust_buffer.as_mut_ptr(), 16, 4, -2);
+        assert_eq!(len, rust_len);
+        assert_eq!(len, 4);
+    }
+
+    assert_eq!(buffer, rust_buffer);
+}
+
+pub fn test_unreachable() {
+    for kind in 0..2 {
+        unsafe {
+            assert_eq!(classify(kind), rust_classify(kind));
+        }
+    }
+    for &x in &[-3, 0, 7] {
+        unsafe {
+            assert_eq!(abs_or_unreachable(x), rust_abs_or_unreachable(x));
+        }
+    }
+
+    let src = include_str!("unreachable.rs");
+    assert!(src.contains("::std::hint::unreachable_unchecked()"));
+}
+
+pub fn test_no_libc() {
+    let src = [4, 5, 6];
+    let mut dst = [0; 3];
+    let mut rust_dst = [0; 3];
+
+    let ret = unsafe { no_libc_copy(dst.as_mut_ptr(), src.as_ptr(), 3) };
+    let rust_ret = unsafe { rust_no_libc_copy(rust_dst.as_mut_ptr(), src.as_ptr(), 3) };
+
+    assert_eq!(ret, 7);
+    assert_eq!(rust_ret, ret);
+    assert_eq!(rust_dst, dst);
+
+    let src = include_str!("no_libc.rs");
+    assert!(!src.contains("libc::"));
+    assert!(src.contains("fn memcpy("));
+    assert!(src.contains("fn strlen("));
+}
@@ -0,0 +1,38 @@
+extern crate libc;
+
+use printf_calls::rust_printf_calls;
+
+use std::fs;
+use std::os::unix::io::AsRawFd;
+
+use self::libc::c_int;
+
+/// Run `f` with the standard output of the process redirected to a file and
+/// return what it wrote
+fn capture_stdout<F: FnOnce()>(f: F) -> String {
+    let path = std::env::temp_dir().join(format!("printf_calls_{}.txt", std::process::id()));
+    let file = fs::File::create(&path).unwrap();
+    unsafe {
+        libc::fflush(0 as *mut libc::FILE);
+        let stdout = libc::dup(1);
+        libc::dup2(file.as_raw_fd(), 1);
+        f();
+        libc::fflush(0 as *mut libc::FILE);
+        libc::dup2(stdout, 1);
+        libc::close(stdout);
+    }
+    let output = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+pub fn test_printf_calls() {
+    let x: c_int = 7;
+    // The Rust writes stay in order with the C library's buffered ones
+    let output = capture_stdout(|| unsafe { rust_printf_calls(x) });
+    assert_eq!(output, "a7b\nc11\n");
+
+    let src = include_str!("printf_calls.rs");
+    assert!(src.contains("::std::io::Write::write_fmt"));
+    assert!(!src.contains("print!"));
+}
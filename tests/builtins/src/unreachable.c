@@ -0,0 +1,15 @@
+/* Functions whose end is never reached, which don't need a return value
+   after the diverging call */
+int classify(int kind) {
+    switch (kind) {
+    case 0: return 10;
+    case 1: return 20;
+    default: __builtin_unreachable();
+    }
+}
+
+int abs_or_unreachable(int x) {
+    if (x >= 0) return x;
+    if (x < 0) return -x;
+    __builtin_unreachable();
+}
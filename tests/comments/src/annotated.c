@@ -0,0 +1,8 @@
+//! annotate_with_c_source
+
+int annotated_total = 3;
+
+/* doubles its argument */
+int annotated_double(int x) {
+    return 2 * x + annotated_total;
+}
@@ -0,0 +1,12 @@
+use crate::annotated::rust_annotated_double;
+
+pub fn test_annotated() {
+    let val = unsafe { rust_annotated_double(2) };
+    assert_eq!(7, val);
+
+    // The C source follows the C comments of each item
+    let src = include_str!("annotated.rs");
+    assert!(src.contains("// int annotated_total = 3;\n"));
+    assert!(src.contains("/* doubles its argument */\n// "));
+    assert!(src.contains("// int annotated_double(int x) {\n//     return 2 * x + annotated_total;\n// }\n"));
+}
@@ -8,4 +8,16 @@ void entry3(const unsigned sz, int buf[const])
 
         (void) (add(buf+2, 2, 0) ?: add(buf+3, 3, 0));
         (void) (add(buf+4, 4, 1) ?: add(buf+5, 5, 0));
+
+        buf[6] = (id(4), id(5)) ?: 9;
+        long l = id(0) ?: 7L;
+        buf[7] = (int) l;
+}
+
+volatile int vol_operand = 3;
+
+int volatile_operands(void)
+{
+        int a = (vol_operand, 1);
+        return (vol_operand ?: 5) + a;
 }
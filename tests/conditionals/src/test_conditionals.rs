@@ -2,7 +2,7 @@ extern crate libc;
 
 use conditional::rust_entry;
 use conditionals::{rust_entry2, rust_ternaries};
-use binary_conditional::rust_entry3;
+use binary_conditional::{rust_entry3, rust_volatile_operands};
 use unused_conditionals::{rust_unused_conditional1, rust_unused_conditional2, rust_unused_conditional3};
 use self::libc::{c_int, c_uint};
 
@@ -17,6 +17,9 @@ extern "C" {
     #[no_mangle]
     fn entry3(_: c_uint, _: *mut c_int);
 
+    #[no_mangle]
+    fn volatile_operands() -> c_int;
+
     #[no_mangle]
     fn unused_conditional1() -> c_int;
     #[no_mangle]
@@ -27,7 +30,7 @@ extern "C" {
 
 const BUFFER_SIZE: usize = 4;
 const BUFFER_SIZE2: usize = 30;
-const BUFFER_SIZE3: usize = 6;
+const BUFFER_SIZE3: usize = 8;
 
 pub fn test_buffer() {
     let mut buffer = [0; BUFFER_SIZE];
@@ -64,7 +67,7 @@ pub fn test_buffer2() {
 pub fn test_binary_conditionals() {
     let mut buffer = [0; BUFFER_SIZE3];
     let mut rust_buffer = [0; BUFFER_SIZE3];
-    let expected_buffer = [1, 2, 2, 3, 4, 0];
+    let expected_buffer = [1, 2, 2, 3, 4, 0, 5, 7];
 
     unsafe {
         entry3(BUFFER_SIZE3 as u32, buffer.as_mut_ptr());
@@ -75,6 +78,17 @@ pub fn test_binary_conditionals() {
     assert_eq!(buffer, expected_buffer);
 }
 
+pub fn test_volatile_operands() {
+    unsafe {
+        assert_eq!(volatile_operands(), rust_volatile_operands());
+    }
+
+    // The volatile operand of the comma is read even though its value is
+    // discarded, and the one of `?:` is read only once
+    let src = include_str!("binary_conditional.rs");
+    assert_eq!(src.matches("read_volatile").count(), 2);
+}
+
 pub fn test_unused_conditional() {
   unsafe {
     assert_eq!(unused_conditional1(), rust_unused_conditional1());
@@ -0,0 +1,18 @@
+enum { FLAG_READ = 1, FLAG_WRITE = 2, FLAG_EXEC = 4 };
+
+enum { NEGATIVE = -3, POSITIVE = 3 };
+
+// Still needs a type, since a variable is declared with it
+enum { FIRST, SECOND } which = SECOND;
+
+void entry6(const unsigned buffer_size, int buffer[const]) {
+    if (buffer_size < 4) {
+        return;
+    }
+
+    int flags = FLAG_READ | FLAG_EXEC;
+    buffer[0] = flags;
+    buffer[1] = (flags & FLAG_WRITE) != 0;
+    buffer[2] = NEGATIVE + POSITIVE * 2;
+    buffer[3] = which;
+}
@@ -6,6 +6,7 @@ use enum_duplicate::{e, rust_entry3};
 use top_enum::{E as otherE, rust_entry4};
 use big_enum::{E1, E2, E3, rust_entry5};
 use non_canonical_enum_def::{rust_abc, hrtimer_restart, HRTIMER_RESTART, HRTIMER_NORESTART};
+use anonymous_enum::{FLAG_READ, FLAG_WRITE, FLAG_EXEC, NEGATIVE, rust_entry6};
 
 use self::libc::{c_int, c_uint};
 
@@ -25,6 +26,9 @@ extern "C" {
 
     #[no_mangle]
     fn entry5(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn entry6(_: c_uint, _: *mut c_int);
 }
 
 const BUFFER_SIZE: usize = 10;
@@ -32,6 +36,7 @@ const BUFFER_SIZE2: usize = 7;
 const BUFFER_SIZE3: usize = 4;
 const BUFFER_SIZE4: usize = 1;
 const BUFFER_SIZE5: usize = 6;
+const BUFFER_SIZE6: usize = 4;
 
 
 pub fn test_variants() {
@@ -109,3 +114,23 @@ pub fn test_buffer5() {
     assert_eq!(buffer, rust_buffer);
     assert_eq!(buffer, expected_buffer);
 }
+
+pub fn test_anonymous_enum() {
+    // The constants are plain integers rather than values of a synthesized type
+    let flags: c_uint = FLAG_READ | FLAG_WRITE | FLAG_EXEC;
+    let negative: c_int = NEGATIVE;
+    assert_eq!(flags, 7);
+    assert_eq!(negative, -3);
+
+    let mut buffer = [0; BUFFER_SIZE6];
+    let mut rust_buffer = [0; BUFFER_SIZE6];
+    let expected_buffer = [5, 0, 3, 1];
+
+    unsafe {
+        entry6(BUFFER_SIZE6 as u32, buffer.as_mut_ptr());
+        rust_entry6(BUFFER_SIZE6 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,25 @@
+typedef __fp16 f16;
+
+// `__fp16` only stores values, arithmetic on it is done in `float`
+void half_ops(float *out) {
+    __fp16 a = 1.5;
+    f16 b = a * 3;
+    __fp16 c = 0;
+    __fp16 tenth = 0.1;
+    __fp16 rest[2] = {7};
+
+    c += a;
+    c++;
+    ++c;
+    b = -b;
+
+    int truncated = b;
+
+    out[0] = a;
+    out[1] = b;
+    out[2] = c;
+    out[3] = tenth;
+    out[4] = rest[0] + rest[1];
+    out[5] = truncated;
+    out[6] = rest[1] ? 1 : 0;
+}
@@ -0,0 +1,27 @@
+extern crate half;
+extern crate libc;
+
+use half_floats::rust_half_ops;
+use self::libc::c_float;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn half_ops(_: *mut c_float);
+}
+
+const BUFFER_SIZE: usize = 7;
+
+pub fn test_half_ops() {
+    let mut buffer = [0.; BUFFER_SIZE];
+    let mut rust_buffer = [0.; BUFFER_SIZE];
+    let expected_buffer = [1.5, -4.5, 3.5, 0.0999755859375, 7., -4., 0.];
+
+    unsafe {
+        half_ops(buffer.as_mut_ptr());
+        rust_half_ops(rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,32 @@
+//! structured_cleanup
+
+#include <stdlib.h>
+
+static int released = 0;
+
+static void release(int *buf) {
+    released++;
+    free(buf);
+}
+
+int cleanup(int n) {
+    int rc = -1;
+    int *buf = malloc(4 * sizeof(int));
+    if (!buf)
+        goto out;
+    if (n < 0)
+        goto out;
+    for (int i = 0; i < 4; i++) {
+        if (i == n)
+            goto out;
+        buf[i] = i;
+    }
+    rc = buf[3];
+out:
+    release(buf);
+    return rc;
+}
+
+int cleanup_released(void) {
+    return released;
+}
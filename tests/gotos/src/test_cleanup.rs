@@ -0,0 +1,16 @@
+extern crate libc;
+
+use cleanup::{rust_cleanup, rust_cleanup_released};
+
+pub fn test_cleanup() {
+    unsafe {
+        assert_eq!(rust_cleanup(-1), -1);
+        assert_eq!(rust_cleanup(2), -1);
+        assert_eq!(rust_cleanup(7), 3);
+        assert_eq!(rust_cleanup_released(), 3);
+    }
+
+    // The error paths return early instead of jumping
+    let src = include_str!("cleanup.rs");
+    assert!(!src.contains("current_block"));
+}
@@ -0,0 +1,27 @@
+// _BitInt requires a C2x-capable clang; arithmetic must wrap at the declared width.
+
+void bit_int_entry(const unsigned buffer_size, int buffer[])
+{
+    if (buffer_size < 8) { return; }
+
+    unsigned _BitInt(5) u = 30;
+    u += 3;
+    buffer[0] = u;
+    u = u * 7;
+    buffer[1] = u;
+    u = ~u;
+    buffer[2] = u;
+    u--;
+    buffer[3] = u;
+
+    _BitInt(6) s = 31;
+    s += 1;
+    buffer[4] = s;
+    s = -s;
+    buffer[5] = s;
+
+    unsigned _BitInt(12) w = 1000;
+    w = w << 3;
+    buffer[6] = w;
+    buffer[7] = (unsigned _BitInt(3)) 13;
+}
@@ -0,0 +1,5 @@
+//! checked_conversions
+
+unsigned char checked_narrow(int x) {
+    return x;
+}
@@ -0,0 +1,15 @@
+//! exact_conversions
+
+signed char exact_step_up(signed char c) {
+    c++;
+    return c;
+}
+
+short exact_step_down(short s) {
+    --s;
+    return s;
+}
+
+int exact_promoted(unsigned char a, unsigned char b) {
+    return a - b;
+}
@@ -0,0 +1,35 @@
+#include <stddef.h>
+
+// 128-bit arithmetic helpers of libgcc and compiler-rt
+__int128 __divti3(__int128, __int128);
+__int128 __modti3(__int128, __int128);
+__int128 __lshrti3(__int128, int);
+
+struct wide {
+    int tag;
+    __int128 value;
+    char after;
+};
+
+size_t wide_size(void) {
+    return sizeof(struct wide);
+}
+
+int wide_check(const struct wide *w) {
+    return w->tag + (int)(w->value >> 99) + w->after;
+}
+
+void int128_entry(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 6) { return; }
+
+    __int128 big = (__int128)1 << 100;
+    unsigned __int128 ubig = ~(unsigned __int128)0;
+    buffer[0] = (int)(big >> 98);
+    buffer[1] = (int)(ubig >> 120);
+    buffer[2] = (int)__divti3(big, (__int128)1 << 96);
+    buffer[3] = (int)__modti3(big + 12, 7);
+    buffer[4] = (int)__lshrti3(-big, 120);
+
+    struct wide w = { 1, big, 2 };
+    buffer[5] = wide_check(&w);
+}
@@ -0,0 +1,7 @@
+//! signed_wrapping
+
+int signed_wrapping_eval(int a, int b) {
+    int x = a + b;
+    x = x * 3 - b;
+    return -x + x / 2 + b % 7;
+}
@@ -0,0 +1,26 @@
+extern crate libc;
+
+use bit_int::rust_bit_int_entry;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn bit_int_entry(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_bit_int() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [1, 7, 24, 23, -32, -32, 3904, 5];
+
+    unsafe {
+        bit_int_entry(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_bit_int_entry(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
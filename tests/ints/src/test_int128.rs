@@ -0,0 +1,39 @@
+extern crate libc;
+
+use int128::{rust_int128_entry, wide};
+use std::mem::{align_of, size_of};
+use self::libc::{c_int, c_uint, size_t};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn int128_entry(_: c_uint, _: *mut c_int);
+    #[no_mangle]
+    fn wide_size() -> size_t;
+    #[no_mangle]
+    fn wide_check(_: *const wide) -> c_int;
+}
+
+const BUFFER_SIZE: usize = 6;
+
+pub fn test_int128() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [4, 255, 16, 0, 255, 5];
+
+    unsafe {
+        int128_entry(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_int128_entry(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_int128_layout() {
+    assert_eq!(size_of::<wide>(), unsafe { wide_size() });
+    assert_eq!(align_of::<wide>(), 16);
+
+    let w = wide { tag: 1, c2rust_padding: [0; 12], value: 1 << 100, after: 2 };
+    assert_eq!(unsafe { wide_check(&w) }, 5);
+}
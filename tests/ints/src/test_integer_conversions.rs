@@ -0,0 +1,40 @@
+extern crate libc;
+
+use checked_conversions::rust_checked_narrow;
+use exact_conversions::{rust_exact_promoted, rust_exact_step_down, rust_exact_step_up};
+
+use self::libc::{c_int, c_schar, c_short, c_uchar};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn exact_step_up(c: c_schar) -> c_schar;
+    #[no_mangle]
+    fn exact_step_down(s: c_short) -> c_short;
+    #[no_mangle]
+    fn exact_promoted(a: c_uchar, b: c_uchar) -> c_int;
+}
+
+pub fn test_exact_conversions() {
+    unsafe {
+        // The increment is done in `int`, and converting the result back wraps
+        // around instead of overflowing
+        assert_eq!(exact_step_up(127), rust_exact_step_up(127));
+        assert_eq!(exact_step_up(-3), rust_exact_step_up(-3));
+        assert_eq!(exact_step_down(-32768), rust_exact_step_down(-32768));
+        // The operands are promoted to `int` before subtracting
+        assert_eq!(exact_promoted(1, 2), rust_exact_promoted(1, 2));
+        assert_eq!(rust_exact_promoted(1, 2), -1);
+    }
+}
+
+pub fn test_checked_conversions() {
+    unsafe {
+        assert_eq!(rust_checked_narrow(200), 200);
+    }
+    // Unwinding out of an `extern "C"` function is undefined, so look for the
+    // conversion that panics on 300 in the source instead of calling it
+    let src = include_str!("checked_conversions.rs");
+    assert!(src.contains("::try_into("));
+    assert!(src.contains(".expect(\"implicit integer conversion changed the value\")"));
+}
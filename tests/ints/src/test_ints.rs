@@ -2,6 +2,7 @@ extern crate libc;
 
 use size_t::rust_entry;
 use chars::rust_multibyte_chars;
+use wide_chars::rust_wide_chars;
 use self::libc::{c_int, c_uint};
 
 #[link(name = "test")]
@@ -11,6 +12,9 @@ extern "C" {
 
     #[no_mangle]
     fn multibyte_chars(_: c_uint, _: *mut c_int) -> c_int;
+
+    #[no_mangle]
+    fn wide_chars(_: c_uint, _: *mut c_int) -> c_int;
 }
 
 const BUFFER_SIZE: usize = 10;
@@ -42,3 +46,25 @@ pub fn test_chars_buffer() {
     assert_eq!(buffer, rust_buffer);
     assert_eq!(buffer, expected_buffer);
 }
+
+pub fn test_wide_chars() {
+    const WIDE_BUFFER_SIZE: usize = 16;
+    let mut buffer = [0; WIDE_BUFFER_SIZE];
+    let mut rust_buffer = [0; WIDE_BUFFER_SIZE];
+    let expected_buffer = [
+        'w' as i32, 'i' as i32, 'd' as i32, 'e' as i32,
+        '✓' as i32, 'x' as i32,
+        '😱' as i32, 'y' as i32,
+        5, 'l' as i32,
+        'z' as i32, '😱' as i32,
+        0, 0, 0, 0,
+    ];
+
+    unsafe {
+        assert_eq!(wide_chars(WIDE_BUFFER_SIZE as u32, buffer.as_mut_ptr()), 12);
+        assert_eq!(rust_wide_chars(WIDE_BUFFER_SIZE as u32, rust_buffer.as_mut_ptr()), 12);
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,38 @@
+extern crate libc;
+
+use signed_wrapping::rust_signed_wrapping_eval;
+use signed_wrapping_type::rust_signed_wrapping_type_eval;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn signed_wrapping_eval(a: c_int, b: c_int) -> c_int;
+    #[no_mangle]
+    fn signed_wrapping_type_eval(a: c_int, b: c_int) -> c_int;
+}
+
+pub fn test_signed_arithmetic() {
+    for &(a, b) in &[(0, 1), (17, -5), (-300, 42)] {
+        unsafe {
+            assert_eq!(signed_wrapping_eval(a, b), rust_signed_wrapping_eval(a, b));
+            assert_eq!(signed_wrapping_type_eval(a, b), rust_signed_wrapping_type_eval(a, b));
+        }
+    }
+
+    // Overflow is undefined in C, so only check that Rust wraps around
+    // instead of panicking
+    let wrapped = c_int::max_value().wrapping_add(1).wrapping_mul(3).wrapping_sub(1);
+    let expected = wrapped.wrapping_neg().wrapping_add(wrapped / 2).wrapping_add(1 % 7);
+    unsafe {
+        assert_eq!(rust_signed_wrapping_eval(c_int::max_value(), 1), expected);
+        assert_eq!(rust_signed_wrapping_type_eval(c_int::max_value(), 1), expected);
+    }
+
+    let src = include_str!("signed_wrapping.rs");
+    assert!(src.contains("wrapping_add"));
+    assert!(src.contains("wrapping_neg"));
+    let src = include_str!("signed_wrapping_type.rs");
+    assert!(src.contains("Wrapping("));
+}
@@ -9,12 +9,12 @@ extern "C" {
     fn entry3(_: c_uint, _: *mut c_int);
 }
 
-const BUFFER_SIZE: usize = 9;
+const BUFFER_SIZE: usize = 12;
 
 pub fn test_buffer() {
     let mut buffer = [0; BUFFER_SIZE];
     let mut rust_buffer = [0; BUFFER_SIZE];
-    let expected_buffer = [5, 11, 5, 9, 99, 116, 101, 115, 116];
+    let expected_buffer = [5, 11, 5, 9, 99, 116, 101, 115, 116, 4, 3, 7];
 
     unsafe {
         entry3(BUFFER_SIZE as u32, buffer.as_mut_ptr());
@@ -6,6 +6,8 @@ typedef struct some_struct {
     char buffer[10];
 } some_struct;
 
+typedef volatile unsigned int reg_t;
+
 void mutate_buffer(volatile char *dest, volatile const char *src, int size) {
     while(size-- != 0)
         *(dest++) = *(src++);
@@ -42,6 +44,15 @@ void entry3(const unsigned buffer_size, int buffer[])
     buffer[6] = s.buffer[1];
     buffer[7] = s.buffer[2];
     buffer[8] = s.buffer[3];
+
+    // volatile hidden behind a typedef
+    reg_t regs[2] = {1, 2};
+    regs[0] += 3;
+    regs[1]++;
+    buffer[9] = regs[0];
+    buffer[10] = regs[1];
+    reg_t *r = &regs[0];
+    buffer[11] = *r + regs[1];
 }
 
 
@@ -0,0 +1,28 @@
+#include <uchar.h>
+#include <wchar.h>
+
+static const wchar_t wide[] = L"wide";
+static const char16_t utf16[] = u"✓x";
+static const char32_t utf32[] = U"\U0001F631y";
+
+int wide_chars(const unsigned sz, int buffer[]) {
+  int i = 0;
+
+  for (int j = 0; wide[j]; j++)
+    buffer[i++] = wide[j];
+  for (int j = 0; utf16[j]; j++)
+    buffer[i++] = utf16[j];
+  for (int j = 0; utf32[j]; j++)
+    buffer[i++] = utf32[j];
+
+  const wchar_t *local = L"local";
+  buffer[i++] = wcslen(local);
+  buffer[i++] = local[4];
+
+  char16_t c16 = u'z';
+  char32_t c32 = U'\U0001F631';
+  buffer[i++] = c16;
+  buffer[i++] = c32;
+
+  return i;
+}
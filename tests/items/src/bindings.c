@@ -0,0 +1,17 @@
+//! emit_bindings_only
+
+struct point {
+    int x;
+    int y;
+};
+
+int bindings_version = 2;
+static int hidden_counter;
+
+static int helper(void) {
+    return ++hidden_counter;
+}
+
+int point_sum(struct point p) {
+    return p.x + p.y + helper();
+}
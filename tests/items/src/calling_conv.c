@@ -0,0 +1,18 @@
+/* Functions and function pointers using the Windows x64 calling convention,
+   which clang accepts on any x86_64 target */
+typedef int (__attribute__((ms_abi)) *ms_binop)(int, int);
+
+__attribute__((ms_abi)) int ms_add(int a, int b) {
+  return a + b;
+}
+
+static int apply(ms_binop f, int a, int b) {
+  return f(a, b);
+}
+
+void calling_conv(unsigned buffer_size, int buffer[]) {
+  if (buffer_size < 2) return;
+
+  buffer[0] = ms_add(1, 2);
+  buffer[1] = apply(ms_add, 3, 4);
+}
@@ -0,0 +1,14 @@
+static int ctor_count = 0;
+static int dtor_count = 0;
+
+__attribute__((constructor)) static void run_before_main(void) {
+    ctor_count += 1;
+}
+
+__attribute__((destructor)) static void run_at_exit(void) {
+    dtor_count += 1;
+}
+
+int get_ctor_count(void) {
+    return ctor_count;
+}
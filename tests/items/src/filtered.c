@@ -0,0 +1,13 @@
+//! filter_function
+
+static int helper(int x) {
+    return x * 2;
+}
+
+int unselected(int x) {
+    return helper(x) + 1;
+}
+
+int selected(int x) {
+    return helper(x) + unselected(x);
+}
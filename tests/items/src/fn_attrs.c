@@ -16,7 +16,11 @@ extern void inline __attribute__((gnu_inline)) gnu_inline_non_canonical_definiti
 #ifndef __APPLE__
 // aliases are not allowed on darwin
 void __attribute__((alias("inline_extern"))) aliased_fn(void);
+// darwin section names need a segment
+void __attribute__((section(".text.c2rust"))) sectioned_fn(void) {}
 #endif // __APPLE__
+void __attribute__((weak)) weak_fn(void) {}
+__attribute__((noreturn)) void noreturn_fn(void) { for (;;) {} }
 
 void ensure_use(void) {
     always_inline_static();
@@ -33,5 +37,7 @@ void ensure_use(void) {
     gnu_inline_non_canonical_definition_extern();
 #ifndef __APPLE__
     aliased_fn();
+    sectioned_fn();
 #endif // __APPLE__
+    weak_fn();
 }
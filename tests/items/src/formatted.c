@@ -0,0 +1,11 @@
+//! rustfmt
+
+struct formatted_pair {
+    int first;
+    int second;
+};
+
+int formatted_sum(int x) {
+    struct formatted_pair p = { x, 2 };
+    return p.first + p.second;
+}
@@ -0,0 +1,11 @@
+//! allow_lints_item, fn_attribute
+
+typedef struct lint_Pair {
+    int first;
+    int second;
+} lint_pair;
+
+int item_lints_sum(int x) {
+    lint_pair p = { x, 2 };
+    return p.first + p.second;
+}
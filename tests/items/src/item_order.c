@@ -0,0 +1,19 @@
+//! order_items
+
+struct list;
+
+struct holder {
+    struct list *items;
+    int count;
+};
+
+static int total = 0;
+
+struct list {
+    int len;
+};
+
+int holder_len(struct holder *h) {
+    total += h->items->len;
+    return h->count * h->items->len + total;
+}
@@ -0,0 +1,20 @@
+//! keep_unused
+
+typedef unsigned long unused_size;
+
+struct unused_pair {
+    int first;
+    int second;
+};
+
+static int unused_twice(int x) {
+    return 2 * x;
+}
+
+static inline int used_square(int x) {
+    return x * x;
+}
+
+int sum_of_squares(int a, int b) {
+    return used_square(a) + used_square(b);
+}
@@ -0,0 +1,18 @@
+//! report_file
+
+int type = 3;
+
+// Rust has no complex types, so this is skipped
+_Complex double report_complex;
+
+int report_sum(int n) {
+    int total = 0;
+    int i = 0;
+loop:
+    if (i < n) {
+        total += i + type;
+        i++;
+        goto loop;
+    }
+    return total;
+}
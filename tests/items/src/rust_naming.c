@@ -0,0 +1,23 @@
+//! rust_naming
+
+enum Direction { DirUp, DirDown };
+
+typedef struct pointPair {
+    int firstX;
+    int secondX;
+} pointPair;
+
+int globalOffset = 10;
+
+static int pairSum(pointPair pair) {
+    int localTotal = pair.firstX + pair.secondX;
+    return localTotal + globalOffset;
+}
+
+void fillBuffer(unsigned bufferSize, int buffer[]) {
+    pointPair somePair = { 1, 2 };
+    enum Direction dir = DirDown;
+    for (unsigned idx = 0; idx < bufferSize; idx++) {
+        buffer[idx] = pairSum(somePair) + (int)idx * dir;
+    }
+}
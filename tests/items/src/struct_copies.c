@@ -0,0 +1,15 @@
+//! type_map
+
+// The type map replaces the name with a `Vec<u8>`, so the struct holding it
+// can only be cloned
+typedef struct name *name_t;
+
+struct labeled {
+    int id;
+    name_t name;
+};
+
+int labeled_id(const struct labeled *l) {
+    struct labeled copy = *l;
+    return copy.id + 1;
+}
@@ -0,0 +1,12 @@
+pub fn test_bindings() {
+    // Bindings only declare what other translation units can link to, so
+    // there is nothing to call and we check the source itself
+    let src = include_str!("bindings.rs");
+
+    assert!(src.contains("pub struct point {"));
+    assert!(src.contains("pub static mut rust_bindings_version: libc::c_int;"));
+    assert!(src.contains("pub fn rust_point_sum(p: point) -> libc::c_int;"));
+    assert!(!src.contains("extern \"C\" fn rust_point_sum"));
+    assert!(!src.contains("helper"));
+    assert!(!src.contains("hidden_counter"));
+}
@@ -0,0 +1,31 @@
+extern crate libc;
+
+use calling_conv::rust_calling_conv;
+use libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn calling_conv(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 2;
+
+pub fn test_calling_conv() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [3, 7];
+
+    unsafe {
+        calling_conv(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_calling_conv(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, expected_buffer);
+    assert_eq!(rust_buffer, expected_buffer);
+
+    let src = include_str!("calling_conv.rs");
+    assert!(src.contains("pub unsafe extern \"win64\" fn rust_ms_add"));
+    // Callbacks keep the convention of their function pointer type
+    assert!(src.contains("Option<unsafe extern \"win64\" fn(_: libc::c_int, _: libc::c_int)"));
+}
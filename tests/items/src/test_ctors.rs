@@ -0,0 +1,26 @@
+extern crate libc;
+
+use ctors::rust_get_ctor_count;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn get_ctor_count() -> c_int;
+}
+
+pub fn test_ctors() {
+    let ret = unsafe { get_ctor_count() };
+    let rust_ret = unsafe { rust_get_ctor_count() };
+
+    assert_eq!(ret, 1);
+    assert_eq!(rust_ret, 1);
+
+    // Destructors can only be observed after exit, so make sure the
+    // translation at least registers them with the loader
+    let src = include_str!("ctors.rs");
+
+    assert!(src.contains("link_section = \".init_array\""));
+    assert!(src.contains("link_section = \".fini_array\""));
+}
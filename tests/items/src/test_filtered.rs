@@ -0,0 +1,26 @@
+extern crate libc;
+
+use filtered::rust_selected;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn selected(x: c_int) -> c_int;
+}
+
+pub fn test_filtered() {
+    unsafe {
+        assert_eq!(selected(3), rust_selected(3));
+    }
+
+    let src = include_str!("filtered.rs");
+    // The static helper can't be linked against, so it is translated even
+    // though the filter doesn't select it
+    assert!(src.contains("unsafe extern \"C\" fn rust_helper("));
+    // Functions left out by the filter link against the C definition under
+    // their own name
+    assert!(src.contains("fn unselected(x: libc::c_int) -> libc::c_int;"));
+    assert!(!src.contains("rust_unselected"));
+}
@@ -47,8 +47,16 @@ pub fn test_fn_attrs() {
     assert!(src.contains("#[inline(always)]\nunsafe extern \"C\" fn rust_always_inline_gnu_inline_extern"));
     assert!(src.contains("#[inline]\nunsafe extern \"C\" fn rust_gnu_inline_non_canonical_definition_extern"));
 
+    // void __attribute__((weak)) weak_fn(void) {}
+    // __attribute__((noreturn)) void noreturn_fn(void) { for (;;) {} }
+    assert!(src.contains("#[linkage = \"weak\"]\npub unsafe extern \"C\" fn rust_weak_fn"));
+    assert!(src.contains("pub unsafe extern \"C\" fn rust_noreturn_fn() -> !"));
+
     if cfg!(not(target_os = "macos")) {
         // aliased_fn is aliased to the inline_extern function
         assert!(src.contains("#[no_mangle]\n    #[link_name = \"inline_extern\"]\n    fn aliased_fn();"));
+
+        // void __attribute__((section(".text.c2rust"))) sectioned_fn(void) {}
+        assert!(src.contains("#[link_section = \".text.c2rust\"]\npub unsafe extern \"C\" fn rust_sectioned_fn"));
     }
 }
@@ -0,0 +1,23 @@
+extern crate libc;
+
+use formatted::rust_formatted_sum;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn formatted_sum(x: c_int) -> c_int;
+}
+
+pub fn test_formatted() {
+    unsafe {
+        assert_eq!(formatted_sum(5), rust_formatted_sum(5));
+    }
+
+    // The pretty printer leaves out the spaces around struct literal fields,
+    // rustfmt doesn't
+    let src = include_str!("formatted.rs");
+    assert!(!src.contains("formatted_pair{"));
+    assert!(src.contains("formatted_pair {"));
+}
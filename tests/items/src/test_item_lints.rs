@@ -0,0 +1,24 @@
+extern crate libc;
+
+use item_lints::rust_item_lints_sum;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn item_lints_sum(x: c_int) -> c_int;
+}
+
+pub fn test_item_lints() {
+    unsafe {
+        assert_eq!(item_lints_sum(5), rust_item_lints_sum(5));
+    }
+
+    let src = include_str!("item_lints.rs");
+    // Every item allows the lints translated code triggers
+    let allow = "#[allow(non_upper_case_globals, non_camel_case_types, non_snake_case";
+    assert!(src.matches(allow).count() >= 3);
+    // `--fn-attribute` applies to functions only
+    assert_eq!(src.matches("#[must_use]").count(), 1);
+}
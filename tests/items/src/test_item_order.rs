@@ -0,0 +1,40 @@
+extern crate libc;
+
+use item_order::{holder as rust_holder, list as rust_list, rust_holder_len};
+
+use self::libc::c_int;
+
+#[repr(C)]
+struct list {
+    len: c_int,
+}
+
+#[repr(C)]
+struct holder {
+    items: *mut list,
+    count: c_int,
+}
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn holder_len(h: *mut holder) -> c_int;
+}
+
+pub fn test_holder_len() {
+    let mut items = list { len: 3 };
+    let mut h = holder { items: &mut items, count: 2 };
+    let mut rust_items = rust_list { len: 3 };
+    let mut rust_h = rust_holder { items: &mut rust_items, count: 2 };
+    unsafe {
+        assert_eq!(holder_len(&mut h), rust_holder_len(&mut rust_h));
+    }
+
+    // Types come before the types using them, and statics after all types
+    let src = include_str!("item_order.rs");
+    let list_pos = src.find("pub struct list").unwrap();
+    let holder_pos = src.find("pub struct holder").unwrap();
+    let total_pos = src.find("static mut total").unwrap();
+    assert!(list_pos < holder_pos);
+    assert!(holder_pos < total_pos);
+}
@@ -0,0 +1,23 @@
+extern crate libc;
+
+use keep_unused::rust_sum_of_squares;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn sum_of_squares(a: c_int, b: c_int) -> c_int;
+}
+
+pub fn test_sum_of_squares() {
+    unsafe {
+        assert_eq!(sum_of_squares(3, 4), rust_sum_of_squares(3, 4));
+    }
+
+    // Declarations nothing uses are translated too
+    let src = include_str!("keep_unused.rs");
+    assert!(src.contains("pub type unused_size"));
+    assert!(src.contains("pub struct unused_pair"));
+    assert!(src.contains("fn unused_twice("));
+}
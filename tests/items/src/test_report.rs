@@ -0,0 +1,31 @@
+extern crate libc;
+
+use report::rust_report_sum;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn report_sum(n: c_int) -> c_int;
+}
+
+pub fn test_report_sum() {
+    for &n in &[0, 1, 5] {
+        unsafe {
+            assert_eq!(report_sum(n), rust_report_sum(n));
+        }
+    }
+
+    let report = include_str!("report.report.json");
+    assert!(report.contains("report.c"));
+    assert!(report.contains("\"c_name\": \"type\""));
+    assert!(report.contains("\"rust_name\": \"type_0\""));
+    assert!(report.contains("\"gotos\": 1"));
+    assert!(report.contains("\"failed_files\": []"));
+
+    // Failures are categorized and located
+    assert!(report.contains("\"name\": \"report_complex\""));
+    assert!(report.contains("\"kind\": \"unsupported-type\""));
+    assert!(report.contains("\"c_line\": 6"));
+}
@@ -0,0 +1,34 @@
+extern crate libc;
+
+use rust_naming::rust_fill_buffer;
+
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    fn fillBuffer(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 4;
+
+pub fn test_rust_naming() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [13, 14, 15, 16];
+
+    unsafe {
+        fillBuffer(BUFFER_SIZE as c_uint, buffer.as_mut_ptr());
+        rust_fill_buffer(BUFFER_SIZE as c_uint, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // Renamed items keep their C symbols
+    let src = include_str!("rust_naming.rs");
+    assert!(src.contains("pub struct PointPair {"));
+    assert!(src.contains("pub const DIR_DOWN: Direction"));
+    assert!(src.contains("#[export_name = \"rust_fillBuffer\"]"));
+    assert!(src.contains("#[export_name = \"rust_globalOffset\"]"));
+    assert!(src.contains("pub static mut rust_global_offset: libc::c_int = 10"));
+}
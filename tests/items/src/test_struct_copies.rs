@@ -0,0 +1,14 @@
+use struct_copies::{labeled, rust_labeled_id};
+
+pub fn test_struct_copies() {
+    let l = labeled {
+        id: 4,
+        name: b"four".to_vec(),
+    };
+    assert_eq!(unsafe { rust_labeled_id(&l) }, 5);
+    assert_eq!(l.name, b"four");
+
+    let src = include_str!("struct_copies.rs");
+    assert!(src.contains("#[derive(Clone)]"));
+    assert!(src.contains(".clone()"));
+}
@@ -0,0 +1,23 @@
+extern crate libc;
+
+use type_mapped::{rust_fill_buffer, rust_pair_sum, rust_sum_mapped};
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn sum_mapped() -> c_int;
+}
+
+pub fn test_type_mapped() {
+    unsafe {
+        assert_eq!(sum_mapped(), rust_sum_mapped());
+
+        // The signatures use the types of the map
+        let mut buf = [0u8; 4];
+        rust_fill_buffer(&mut buf, 4);
+        assert_eq!(buf, [0, 3, 6, 9]);
+        assert_eq!(rust_pair_sum([3, 4]), 7);
+    }
+}
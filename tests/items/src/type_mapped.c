@@ -0,0 +1,38 @@
+//! type_map
+
+#include <stddef.h>
+#include <stdint.h>
+
+struct pair {
+    int a;
+    int b;
+};
+
+void fill_buffer(uint8_t *buf, size_t len) {
+    for (size_t i = 0; i < len; i++) {
+        buf[i] = (uint8_t)(i * 3);
+    }
+}
+
+int *first(int *p) {
+    return p;
+}
+
+int pair_sum(struct pair p) {
+    return p.a + p.b;
+}
+
+int sum_mapped(void) {
+    uint8_t data[8];
+    fill_buffer(data, 8);
+
+    int x = 1;
+    *first(&x) += 4;
+
+    struct pair p = { 2, 7 };
+    int total = x + pair_sum(p);
+    for (int i = 0; i < 8; i++) {
+        total += data[i];
+    }
+    return total;
+}
@@ -0,0 +1,11 @@
+//! long_double_f64
+
+double ld_average(const double *xs, unsigned n) {
+    long double sum = 0.0L;
+
+    for (unsigned i = 0; i < n; i++) {
+        sum += xs[i];
+    }
+
+    return (double)(sum / n);
+}
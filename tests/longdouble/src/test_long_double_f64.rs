@@ -0,0 +1,20 @@
+extern crate libc;
+
+use long_double_f64::rust_ld_average;
+use self::libc::{c_double, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn ld_average(_: *const c_double, _: c_uint) -> c_double;
+}
+
+pub fn test_long_double_as_f64() {
+    let xs = [1.5, 2.5, 5.0];
+
+    let ret = unsafe { ld_average(xs.as_ptr(), xs.len() as c_uint) };
+    let rust_ret = unsafe { rust_ld_average(xs.as_ptr(), xs.len() as c_uint) };
+
+    assert_eq!(ret, rust_ret);
+    assert_eq!(rust_ret, 3.0);
+}
@@ -0,0 +1,32 @@
+//! range_loops
+
+int range_loops(int n, int buf[]) {
+    int total = 0;
+
+    for (int i = 0; i < n; i++) {
+        buf[i] = i * 2;
+        total += buf[i];
+    }
+
+    for (int j = 1; j <= n; ++j) {
+        total += j;
+    }
+
+    // The bound changes in the body, so the condition must be evaluated
+    // before every iteration
+    int bound = n;
+    for (int k = 0; k < bound; k++) {
+        if (k == 1) {
+            bound--;
+        }
+        total += k * 10;
+    }
+
+    // The counter changes in the body
+    for (int m = 0; m < n; m++) {
+        m += 1;
+        total += m * 100;
+    }
+
+    return total;
+}
@@ -0,0 +1,35 @@
+extern crate libc;
+
+use range_loops::rust_range_loops;
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn range_loops(n: c_int, buf: *mut c_int) -> c_int;
+}
+
+const BUFFER_SIZE: usize = 5;
+
+pub fn test_range_loops() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+
+    unsafe {
+        let total = range_loops(BUFFER_SIZE as c_int, buffer.as_mut_ptr());
+        let rust_total = rust_range_loops(BUFFER_SIZE as c_int, rust_buffer.as_mut_ptr());
+        assert_eq!(total, rust_total);
+        assert_eq!(rust_range_loops(0, rust_buffer.as_mut_ptr()), 0);
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, [0, 2, 4, 6, 8]);
+
+    let src = include_str!("range_loops.rs");
+    assert!(src.contains("for i in "));
+    assert!(src.contains("for j in "));
+    assert!(src.contains("..="));
+    // Loops that change their counter or bound keep evaluating the condition
+    assert!(!src.contains("for k in "));
+    assert!(!src.contains("for m in "));
+}
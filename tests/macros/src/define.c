@@ -80,3 +80,11 @@ int test_switch(int x) {
 
   return 0;
 }
+
+// Every use converts this macro to unsigned long, so it should be translated
+// at that type
+#define USE_SITE_LIMIT 100
+unsigned long use_site_limit(unsigned long x) {
+  unsigned long limit = USE_SITE_LIMIT;
+  return x < USE_SITE_LIMIT ? x : limit;
+}
@@ -0,0 +1,27 @@
+//! translate_fn_macros
+
+#define SQUARE(x) ((x) * (x))
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+#define ANSWER() 42
+#define SCALE(x) ((x) * scale_factor)
+#define CLAMP(x, lo, hi) ({ int _x = (x); _x < (lo) ? (lo) : _x > (hi) ? (hi) : _x; })
+#define FIRST_OR(p, d) ((p) ? *(p) : (d))
+#define AND(a, b) ((a) && (b))
+
+int scale_factor = 3;
+
+int fn_macros(int a, int b) {
+  return SQUARE(a) + MAX(a, b) + ANSWER() + SCALE(b);
+}
+
+int fn_macros_nested(int c) {
+  return SQUARE(c + 1) + MAX(SQUARE(c), 10) + SCALE(ANSWER());
+}
+
+int fn_macros_stmt_expr(int a) {
+  return CLAMP(a, -3, 3) + CLAMP(a * 2, 0, 10);
+}
+
+int fn_macros_conditional(const int *p) {
+  return FIRST_OR(p, 0) + FIRST_OR(p, -1) + AND(p, *p > 2);
+}
@@ -3,12 +3,16 @@ extern crate libc;
 use define::{TEST_CONST1, TEST_CONST2, TEST_PARENS, rust_reference_define};
 use define::{ZSTD_WINDOWLOG_MAX_32, ZSTD_WINDOWLOG_MAX_64, rust_test_zstd};
 use define::{rust_fns, rust_stmt_expr_inc};
+use define::{USE_SITE_LIMIT, rust_use_site_limit};
 use self::libc::{c_int, c_uint, c_ulong};
 
 #[link(name = "test")]
 extern "C" {
     #[no_mangle]
     fn reference_define() -> c_uint;
+
+    #[no_mangle]
+    fn use_site_limit(x: c_ulong) -> c_ulong;
 }
 
 pub fn test_define() {
@@ -27,3 +31,15 @@ pub fn test_macro_stmt_expr() {
 
     assert_eq!(ret, 2);
 }
+
+pub fn test_use_site_type() {
+    let limit: c_ulong = USE_SITE_LIMIT;
+    assert_eq!(limit, 100);
+
+    for &x in &[0, 99, 100, 101, 1000] {
+        let c_res = unsafe { use_site_limit(x) };
+        let rust_res = unsafe { rust_use_site_limit(x) };
+
+        assert_eq!(c_res, rust_res);
+    }
+}
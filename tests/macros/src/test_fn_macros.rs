@@ -0,0 +1,62 @@
+extern crate libc;
+
+use fn_macros::{
+    rust_fn_macros, rust_fn_macros_conditional, rust_fn_macros_nested, rust_fn_macros_stmt_expr,
+    ANSWER, CLAMP, MAX, SQUARE,
+};
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn fn_macros(a: c_int, b: c_int) -> c_int;
+
+    #[no_mangle]
+    fn fn_macros_nested(c: c_int) -> c_int;
+
+    #[no_mangle]
+    fn fn_macros_stmt_expr(a: c_int) -> c_int;
+
+    #[no_mangle]
+    fn fn_macros_conditional(p: *const c_int) -> c_int;
+}
+
+pub fn test_recovered_macros() {
+    assert_eq!(SQUARE(7), 49);
+    assert_eq!(MAX(-1, 4), 4);
+    assert_eq!(ANSWER, 42);
+    assert_eq!(CLAMP(12, -3, 3), 3);
+}
+
+pub fn test_fn_macros() {
+    for &(a, b) in &[(0, 0), (3, -2), (-5, 8), (11, 11)] {
+        let c_res = unsafe { fn_macros(a, b) };
+        let rust_res = unsafe { rust_fn_macros(a, b) };
+
+        assert_eq!(c_res, rust_res);
+    }
+
+    for &c in &[-4, 0, 2, 9] {
+        let c_res = unsafe { fn_macros_nested(c) };
+        let rust_res = unsafe { rust_fn_macros_nested(c) };
+
+        assert_eq!(c_res, rust_res);
+    }
+
+    for &a in &[-9, -1, 0, 4, 7] {
+        let c_res = unsafe { fn_macros_stmt_expr(a) };
+        let rust_res = unsafe { rust_fn_macros_stmt_expr(a) };
+
+        assert_eq!(c_res, rust_res);
+    }
+}
+
+pub fn test_conditional_macro_args() {
+    let x: c_int = 5;
+    for &p in &[&x as *const c_int, std::ptr::null()] {
+        let c_res = unsafe { fn_macros_conditional(p) };
+        let rust_res = unsafe { rust_fn_macros_conditional(p) };
+
+        assert_eq!(c_res, rust_res);
+    }
+}
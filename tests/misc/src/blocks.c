@@ -0,0 +1,22 @@
+//! blocks
+
+// Stack blocks only need the class their isa field points to, so this
+// stands in for the blocks runtime
+void *_NSConcreteStackBlock[32];
+
+// Static functions using block literals have no symbol to link, so their
+// bodies are stubbed out
+static int block_twice(int x) {
+    int (^twice)(int) = ^(int y) { return 2 * y; };
+    return twice(x);
+}
+
+// Other functions using block literals are declared extern
+int blocks_twice(int x) {
+    int (^twice)(int) = ^(int y) { return 2 * y; };
+    return twice(x);
+}
+
+int blocks_abs(int x) {
+    return x >= 0 ? x : block_twice(x);
+}
@@ -0,0 +1,23 @@
+//! portable_errno
+#include <errno.h>
+#include <stdlib.h>
+#include <unistd.h>
+
+// Reads of `errno` become `last_os_error().raw_os_error()`, writes still go
+// through the C library's `errno` location
+int errno_values(int out[3])
+{
+    errno = 0;
+    out[0] = errno;
+
+    // Fails with EBADF
+    if (close(-1) == -1) {
+        out[1] = errno;
+    }
+
+    errno = 0;
+    strtol("99999999999999999999999", NULL, 10);
+    out[2] = errno == ERANGE;
+
+    return errno != 0;
+}
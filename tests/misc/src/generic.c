@@ -0,0 +1,10 @@
+#define type_code(x) _Generic((x), int: 1, double: 2, char *: 3, default: 0)
+
+int generic_codes(int i, double d, char *s, long l) {
+    return type_code(i) * 1000 + type_code(d) * 100 + type_code(s) * 10 + type_code(l);
+}
+
+int generic_abs(int x) {
+    int r = _Generic(x, long: -1, int: x < 0 ? -x : x);
+    return r;
+}
@@ -0,0 +1,6 @@
+//! emit_no_std, long_double_f64
+
+double no_std_fabs(double x) {
+    long double y = x;
+    return __builtin_fabs(x) + __builtin_fabsf((float)x) + (double)__builtin_fabsl(y);
+}
@@ -0,0 +1,5 @@
+//! emit_no_std
+
+int main(void) {
+    return 3;
+}
@@ -0,0 +1,33 @@
+static int counter = 0;
+
+static int next(void) {
+        return counter++;
+}
+
+static int pair(int a, int b) {
+        return a * 10 + b;
+}
+
+struct point {
+        int x;
+        int y;
+};
+
+void sequencing(unsigned buffer_size, int buffer[]) {
+        int i = 1;
+        counter = 0;
+
+        // The operands modify different objects, so their order doesn't
+        // change the result, but each must be evaluated exactly once
+        buffer[0] = pair(next(), i++);
+
+        int arr[2] = { next(), i++ };
+        buffer[1] = arr[0];
+        buffer[2] = arr[1];
+
+        struct point p = { next(), i++ };
+        buffer[3] = p.x;
+        buffer[4] = p.y;
+        buffer[5] = i;
+        buffer[6] = counter;
+}
@@ -12,6 +12,9 @@ union a_union {
 
 typedef unsigned long long ull_typedef;
 
+typedef int arr_typedef[6];
+typedef arr_typedef arr2_typedef[2];
+
 void sizeofs(const unsigned n, int * const buffer) {
 
         unsigned long i = 0;
@@ -73,5 +76,17 @@ void sizeofs(const unsigned n, int * const buffer) {
         CHECK(x6);
         CHECK(x7);
 
+        CHECK(arr_typedef);
+        CHECK(arr2_typedef);
+
+        /* operands of sizeof are not evaluated */
+        int counter = 0;
+        CHECK(counter++);
+        buffer[i++] = counter;
+
+        /* GNU extension: void and function types have size and alignment 1 */
+        CHECK(void);
+        CHECK(sizeofs);
+
         assert(i == n);
 }
@@ -0,0 +1,11 @@
+//! source_map
+
+void source_map(unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 2) {
+        return;
+    }
+
+    // Statements sharing a line get trailing markers
+    int a = 1; int b = 2;
+    buffer[0] = a; buffer[1] = b;
+}
@@ -0,0 +1,21 @@
+//! best_effort
+
+int stubbed_fn(int n) {
+    int total = n;
+    {
+        int inner = 1;
+        // Rust has no complex types, so this body is replaced by a stub
+        _Complex double c = 0;
+        total += inner;
+    }
+    return total;
+}
+
+int after_stub(int n) {
+    int total = n;
+    {
+        int inner = 2;
+        total += inner;
+    }
+    return total;
+}
@@ -0,0 +1,25 @@
+extern crate libc;
+
+use blocks::rust_blocks_abs;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn blocks_twice(x: c_int) -> c_int;
+}
+
+pub fn test_blocks() {
+    unsafe {
+        assert_eq!(blocks_twice(4), 8);
+        assert_eq!(rust_blocks_abs(3), 3);
+    }
+
+    let src = include_str!("blocks.rs");
+    // The static function is defined with a stub body rather than declared
+    assert!(src.contains("unimplemented!()"));
+    // The exported one is declared in an extern block, to link its C definition
+    assert!(src.contains("fn rust_blocks_twice("));
+    assert!(!src.contains("extern \"C\" fn rust_blocks_twice("));
+}
@@ -0,0 +1,25 @@
+extern crate libc;
+
+use errno::rust_errno_values;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn errno_values(out: *mut c_int) -> c_int;
+}
+
+const BUFFER_SIZE: usize = 3;
+
+pub fn test_errno() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let ret = unsafe { errno_values(buffer.as_mut_ptr()) };
+    let rust_ret = unsafe { rust_errno_values(rust_buffer.as_mut_ptr()) };
+
+    assert_eq!(ret, 1);
+    assert_eq!(rust_ret, ret);
+    assert_eq!(buffer, [0, libc::EBADF, 1]);
+    assert_eq!(rust_buffer, buffer);
+}
@@ -0,0 +1,35 @@
+extern crate libc;
+
+use generic::{rust_generic_abs, rust_generic_codes};
+use self::libc::{c_char, c_double, c_int, c_long};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn generic_codes(_: c_int, _: c_double, _: *mut c_char, _: c_long) -> c_int;
+
+    #[no_mangle]
+    fn generic_abs(_: c_int) -> c_int;
+}
+
+pub fn test_generic_codes() {
+    let mut s = [0 as c_char; 1];
+    let codes = unsafe { generic_codes(1, 2.0, s.as_mut_ptr(), 3) };
+    let rust_codes = unsafe { rust_generic_codes(1, 2.0, s.as_mut_ptr(), 3) };
+
+    assert_eq!(codes, rust_codes);
+    assert_eq!(codes, 1230);
+}
+
+pub fn test_generic_abs() {
+    for i in -5..5 {
+        let abs = unsafe { generic_abs(i) };
+        let rust_abs = unsafe { rust_generic_abs(i) };
+
+        assert_eq!(abs, rust_abs);
+    }
+
+    // The original selection is noted on the statement containing it
+    let src = include_str!("generic.rs");
+    assert!(src.contains("// _Generic selection on `x` resolved to its `int` association"));
+}
@@ -0,0 +1,32 @@
+extern crate libc;
+
+use no_std_fabs::rust_no_std_fabs;
+use no_std_main::rust_main;
+
+use self::libc::c_double;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn no_std_fabs(x: c_double) -> c_double;
+}
+
+pub fn test_no_std_main() {
+    // `--emit-no-std` only avoids `std` paths, so `main` still gets a wrapper
+    unsafe {
+        assert_eq!(rust_main(), 3);
+    }
+    let src = include_str!("no_std_main.rs");
+    assert!(src.contains("pub fn main()"));
+}
+
+pub fn test_no_std_fabs() {
+    for &x in &[-2.5, 0.0, 7.25] {
+        unsafe {
+            assert_eq!(no_std_fabs(x), rust_no_std_fabs(x));
+        }
+    }
+    // The float methods of std aren't used
+    let src = include_str!("no_std_fabs.rs");
+    assert!(!src.contains(".abs()"));
+}
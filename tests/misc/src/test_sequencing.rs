@@ -0,0 +1,27 @@
+extern crate libc;
+
+use sequencing::rust_sequencing;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn sequencing(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 7;
+
+pub fn test_sequencing() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    // Every operand is evaluated exactly once
+    let expected_buffer = [1, 1, 2, 2, 3, 4, 3];
+
+    unsafe {
+        sequencing(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_sequencing(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
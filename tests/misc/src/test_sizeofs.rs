@@ -11,7 +11,7 @@ extern "C" {
     fn sizeofs(_: c_uint, _: *mut c_int);
 }
 
-const BUFFER_SIZE: usize = 60;
+const BUFFER_SIZE: usize = 71;
 
 pub fn test_sizeofs() {
     let mut buffer = [0; BUFFER_SIZE];
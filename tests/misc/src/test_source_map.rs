@@ -0,0 +1,33 @@
+extern crate libc;
+
+use self::libc::{c_int, c_uint};
+use source_map::rust_source_map;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn source_map(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 2;
+
+pub fn test_source_map() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [1, 2];
+
+    unsafe {
+        source_map(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_source_map(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // The markers locating the translated nodes are all removed
+    let src = include_str!("source_map.rs");
+    assert!(!src.contains("//@c2rust-src"));
+    let map = include_str!("source_map.source_map.json");
+    assert!(map.contains("\"rust_line\""));
+    assert!(map.contains("\"c_line\": 9"));
+}
@@ -0,0 +1,25 @@
+extern crate libc;
+
+use stubs::rust_after_stub;
+
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn after_stub(n: c_int) -> c_int;
+}
+
+pub fn test_stubs() {
+    unsafe {
+        assert_eq!(after_stub(3), rust_after_stub(3));
+    }
+
+    let src = include_str!("stubs.rs");
+    assert!(src.contains("unimplemented!()"));
+    // The scopes of the failed body don't leak into the next function, whose
+    // locals keep their names
+    assert!(src.contains("let mut inner:"));
+    assert!(!src.contains("inner_0"));
+    assert!(!src.contains("total_0"));
+}
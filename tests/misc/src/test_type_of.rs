@@ -0,0 +1,19 @@
+extern crate libc;
+
+use type_of::rust_type_of;
+
+use self::libc::c_long;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn type_of() -> c_long;
+}
+
+pub fn test_type_of() {
+    let ret = unsafe { type_of() };
+    let rust_ret = unsafe { rust_type_of() };
+
+    assert_eq!(ret, 5000000000 + 3 + 4 + 2 + 6);
+    assert_eq!(rust_ret, ret);
+}
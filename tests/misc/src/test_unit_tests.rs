@@ -0,0 +1,17 @@
+pub fn test_test_functions() {
+    // Every function named `test...` without arguments is run by a `#[test]`
+    let src = include_str!("unit_tests.rs");
+    assert!(src.contains("mod tests"));
+    assert!(src.contains("#[test]"));
+    assert!(src.contains("fn test_square()"));
+    assert!(src.contains("super::rust_test_square()"));
+    assert!(src.contains("fn test_negative()"));
+    assert!(src.contains("super::rust_test_negative()"));
+    assert!(!src.contains("fn test_helper()"));
+}
+
+pub fn test_test_assertions() {
+    // The `assert`s of test drivers are translated as `assert!`s
+    let src = include_str!("unit_tests.rs");
+    assert!(src.contains("assert!("));
+}
@@ -0,0 +1,36 @@
+// `typeof` declarations are translated with the type clang resolves them to
+struct point {
+    int x;
+    long y;
+};
+
+static struct point origin = {0, 0};
+
+#define MAX(a, b) ({                \
+    __typeof__(a) _a = (a);         \
+    __typeof__(b) _b = (b);         \
+    _a > _b ? _a : _b;              \
+})
+
+typedef typeof(origin) point_t;             // 'type point_t = point'
+
+long type_of(void)
+{
+    typeof(origin.y) total = 0;             // 'total: libc::c_long'
+    typeof(int[3]) values = {1, 2, 3};      // 'values: [libc::c_int; 3]'
+    point_t p = {4, 5};
+    typeof(&p) pp = &p;                     // 'pp: *mut point_t'
+
+    // `__auto_type` locals take their type from the initializer, like `let`
+    __auto_type sum = values[0] + values[1];
+    __auto_type big = pp->y * 1000000000L;
+    __auto_type ptr = &values[2];
+    __auto_type half = 0.5;
+
+    total += MAX(sum, *ptr) + big + pp->x;
+    total += (long)(half * 4);
+    for (__auto_type i = 0; i < 3; i++) {
+        total += values[i];
+    }
+    return total;
+}
@@ -0,0 +1,19 @@
+//! test_files
+#include <assert.h>
+
+static int square(int x) {
+    return x * x;
+}
+
+void test_square(void) {
+    assert(square(3) == 9);
+}
+
+int test_negative(void) {
+    return square(-2) == 4 ? 0 : 1;
+}
+
+// Not a test function, since it takes arguments
+void test_helper(int x) {
+    assert(square(x) >= 0);
+}
@@ -0,0 +1,15 @@
+//! emit_build_files, export_list
+#include <stdlib.h>
+
+int exported_total = 0;
+int hidden_total = 0;
+
+int hidden_add(int x) {
+    hidden_total += x;
+    return hidden_total;
+}
+
+int exported_add(int x) {
+    exported_total += hidden_add(abs(x));
+    return exported_total;
+}
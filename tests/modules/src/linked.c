@@ -0,0 +1,17 @@
+//! emit_build_files
+
+// Tentative definitions, merged into one variable by the linker
+int linked_counter;
+int linked_counter;
+
+int linked_bump(void) {
+    linked_counter += 1;
+    return linked_counter;
+}
+
+// Defined in linked_external.c, which is translated separately
+extern int linked_external;
+
+int linked_read_external(void) {
+    return linked_external;
+}
@@ -0,0 +1,5 @@
+int linked_external = 42;
+
+int linked_external_value(void) {
+    return linked_external;
+}
@@ -0,0 +1,7 @@
+//! emit_build_files, share_inline_fns
+
+#include "shared_inline.h"
+
+int shared_inline_sum(int a, int b) {
+    return shared_square(a) + shared_square(b);
+}
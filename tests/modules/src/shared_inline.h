@@ -0,0 +1,3 @@
+static inline int shared_square(int x) {
+    return x * x;
+}
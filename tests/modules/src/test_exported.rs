@@ -0,0 +1,27 @@
+extern crate libc;
+
+use self::libc::c_int;
+use exported::rust_exported_add;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn exported_add(x: c_int) -> c_int;
+}
+
+pub fn test_export_list() {
+    unsafe {
+        assert_eq!(exported_add(2), 2);
+        assert_eq!(exported_add(-3), 5);
+        assert_eq!(rust_exported_add(2), 2);
+        assert_eq!(rust_exported_add(-3), 5);
+    }
+
+    // Only the listed function keeps its symbol, and the C library's
+    // functions are declared as usual
+    let src = include_str!("exported.rs");
+    assert!(src.contains("pub(crate) unsafe extern \"C\" fn rust_hidden_add("));
+    assert!(src.contains("pub unsafe extern \"C\" fn rust_exported_add("));
+    assert!(src.contains("fn abs("));
+    assert!(!src.contains("extern_statics"));
+}
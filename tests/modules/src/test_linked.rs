@@ -0,0 +1,40 @@
+extern crate libc;
+
+use self::libc::c_int;
+use linked::{rust_linked_bump, rust_linked_read_external};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn linked_bump() -> c_int;
+
+    #[no_mangle]
+    fn linked_read_external() -> c_int;
+}
+
+pub fn test_tentative_def() {
+    unsafe {
+        assert_eq!(linked_bump(), 1);
+        assert_eq!(linked_bump(), 2);
+        assert_eq!(rust_linked_bump(), 1);
+        assert_eq!(rust_linked_bump(), 2);
+    }
+
+    // The variable is defined once in the crate's shared module and imported
+    let src = include_str!("linked.rs");
+    assert!(src.contains("use crate::tentative_defs::rust_linked_counter;"));
+    assert!(!src.contains("static mut rust_linked_counter"));
+}
+
+pub fn test_extern_static() {
+    unsafe {
+        assert_eq!(linked_read_external(), 42);
+        assert_eq!(rust_linked_read_external(), 42);
+    }
+
+    // No translated module of this crate defines the variable, so the shared
+    // module declares it and the module imports it from there
+    let src = include_str!("linked.rs");
+    assert!(src.contains("use crate::extern_statics::rust_linked_external;"));
+    assert!(!src.contains("static mut rust_linked_external"));
+}
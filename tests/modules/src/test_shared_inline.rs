@@ -0,0 +1,23 @@
+extern crate libc;
+
+use self::libc::c_int;
+use shared_inline::rust_shared_inline_sum;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn shared_inline_sum(a: c_int, b: c_int) -> c_int;
+}
+
+pub fn test_shared_inline_sum() {
+    for &(a, b) in &[(0, 0), (3, -4), (7, 2)] {
+        unsafe {
+            assert_eq!(shared_inline_sum(a, b), rust_shared_inline_sum(a, b));
+        }
+    }
+
+    // The header function is imported from the shared module, not copied
+    let src = include_str!("shared_inline.rs");
+    assert!(src.contains("use crate::inline_fns::shared_square_"));
+    assert!(!src.contains("x * x"));
+}
@@ -0,0 +1,33 @@
+extern crate libc;
+
+use self::libc::{c_int, c_uint};
+use unity::rust_unity_entry;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn unity_entry(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 3;
+
+pub fn test_unity() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [3, 6, 7];
+
+    unsafe {
+        unity_entry(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_unity_entry(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, expected_buffer);
+    assert_eq!(rust_buffer, expected_buffer);
+}
+
+pub fn test_unity_split() {
+    // The definitions of the included source live in a submodule of their own
+    let src = include_str!("unity.rs");
+    assert!(src.contains("pub mod unity_part_c"));
+    assert!(src.contains("use self::unity_part_c::"));
+}
@@ -0,0 +1,11 @@
+//! split_included_sources
+
+#include "unity/unity_part.c"
+
+void unity_entry(const unsigned buffer_size, int buffer[]) {
+  if (buffer_size < 3) return;
+
+  buffer[0] = part_add(1, 2);
+  buffer[1] = part_counter();
+  buffer[2] = part_counter();
+}
@@ -0,0 +1,12 @@
+// Included textually by unity.c, as in a unity build, so it is not
+// translated on its own.
+
+static int counter = 5;
+
+int part_add(int a, int b) {
+  return a + b;
+}
+
+int part_counter(void) {
+  return ++counter;
+}
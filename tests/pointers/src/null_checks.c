@@ -0,0 +1,87 @@
+//! option_pointers
+#include <stddef.h>
+
+struct counter {
+    int hits;
+};
+
+static struct counter counters[2];
+
+static struct counter *find(int key) {
+    if (key < 0 || key >= 2) {
+        return NULL;
+    }
+    return &counters[key];
+}
+
+static int twice(int x) {
+    return 2 * x;
+}
+
+static struct counter *lookup(int key) {
+    if (key != 0) {
+        return NULL;
+    }
+    return &counters[0];
+}
+
+static int apply(int (*op)(int), int x) {
+    if (op != NULL) {
+        return op(x);
+    }
+    return x;
+}
+
+static int read_or(const int *value, int fallback) {
+    if (value) {
+        return *value;
+    }
+    return fallback;
+}
+
+void null_checks(unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 7) {
+        return;
+    }
+
+    struct counter *c = find(1);
+    if (c != NULL) {
+        c->hits += 5;
+        buffer[0] = c->hits;
+    }
+
+    struct counter *missing = find(7);
+    if (!missing) {
+        buffer[1] = -1;
+    } else {
+        buffer[1] = missing->hits;
+    }
+
+    int (*f)(int) = twice;
+    if (f) {
+        buffer[2] = f(21);
+    }
+
+    // Every caller of `lookup` checks its result, so it returns an `Option`
+    struct counter *found = lookup(0);
+    if (found) {
+        found->hits = 11;
+        buffer[4] = found->hits;
+    }
+    struct counter *none = lookup(1);
+    if (none != NULL) {
+        buffer[4] = 0;
+    } else {
+        buffer[4] += 1;
+    }
+
+    buffer[5] = apply(twice, 4) + apply(NULL, 1);
+
+    int value = 6;
+    buffer[6] = read_or(&value, 0) + read_or(NULL, 1);
+
+    // Used without a check, so it stays a raw pointer
+    struct counter *unchecked = find(0);
+    unchecked->hits = 3;
+    buffer[3] = unchecked->hits;
+}
@@ -0,0 +1,46 @@
+//! owned_allocations
+#include <stdlib.h>
+
+struct point {
+    int x;
+    int y;
+};
+
+static int sum_squares(int n) {
+    int *squares = malloc(n * sizeof(int));
+    for (int i = 0; i < n; i++) {
+        squares[i] = i * i;
+    }
+    int sum = 0;
+    for (int i = 0; i < n; i++) {
+        sum += squares[i];
+    }
+    free(squares);
+    return sum;
+}
+
+static int read_first(int *values) {
+    return values[0];
+}
+
+void owned_allocations(unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 4) {
+        return;
+    }
+
+    struct point *p = malloc(sizeof(struct point));
+    p->x = 3;
+    p->y = 4;
+    buffer[0] = p->x * p->x + p->y * p->y;
+    (*p).x = 7;
+    buffer[1] = p->x;
+    free(p);
+
+    buffer[2] = sum_squares(5);
+
+    // Passed to another function, so it stays a raw pointer
+    int *escaped = malloc(sizeof(int));
+    *escaped = 0;
+    buffer[3] = sum_squares(read_first(escaped) + 1);
+    free(escaped);
+}
@@ -12,3 +12,28 @@ void entry2(const unsigned sz, int buf[const]) {
         *(p--) = 34;
         *(p-=2) = 35;
 }
+
+struct pair {
+        int first;
+        int second;
+};
+
+static int counter;
+
+void entry5(const unsigned sz, int buf[const]) {
+        // Pointers one past the end are only compared, never dereferenced
+        int *end = buf + sz;
+        unsigned i = 0;
+        for (int *cursor = buf; cursor != end; ++cursor) {
+                *cursor = i++;
+        }
+        buf[i - 1] += 10;
+        *(end - 2U) += 20;
+
+        struct pair p = { 1, 2 };
+        int *second = &p.second;
+        *second += 3;
+        int *c = &counter;
+        *c = 7;
+        buf[0] = p.second + counter;
+}
@@ -0,0 +1,33 @@
+typedef const int cint;
+
+static void copy(int n, int *restrict dst, const int *restrict src) {
+    for (int i = 0; i < n; i++) {
+        dst[i] = src[i];
+    }
+}
+
+static int sum(int n, cint *xs) {
+    int total = 0;
+    for (int i = 0; i < n; i++) {
+        total += xs[i];
+    }
+    return total;
+}
+
+static int read_flag(volatile int *flag) {
+    return *flag;
+}
+
+void entry4(const unsigned buffer_size, int buffer[const]) {
+    if (buffer_size < 6) {
+        return;
+    }
+
+    int src[3] = {1, 2, 3};
+    volatile int flag = 7;
+
+    copy(3, buffer, src);
+    buffer[3] = sum(3, src);
+    buffer[4] = read_flag(&flag);
+    buffer[5] = sum(2, buffer + 1);
+}
@@ -3,8 +3,11 @@
 extern crate libc;
 
 use pointer_init::rust_entry;
-use pointer_arith::rust_entry2;
+use pointer_arith::{rust_entry2, rust_entry5};
 use function_pointers::rust_entry3;
+use qualifiers::rust_entry4;
+use owned_allocations::rust_owned_allocations;
+use null_checks::rust_null_checks;
 use ref_decay::{rust_f, rust_bar, rust_bitcast, rust_foobar, rust_calls_all, rust_address_cast};
 use self::libc::{c_int, c_uint};
 
@@ -18,11 +21,27 @@ extern "C" {
 
     #[no_mangle]
     fn entry3(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn entry4(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn entry5(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn owned_allocations(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn null_checks(_: c_uint, _: *mut c_int);
 }
 
 const BUFFER_SIZE: usize = 5;
 const BUFFER_SIZE2: usize = 31;
 const BUFFER_SIZE3: usize = 18;
+const BUFFER_SIZE4: usize = 6;
+const BUFFER_SIZE5: usize = 6;
+const BUFFER_SIZE6: usize = 4;
+const BUFFER_SIZE7: usize = 7;
 
 pub fn test_init() {
     let mut buffer = [0; BUFFER_SIZE];
@@ -57,6 +76,61 @@ pub fn test_arith() {
     assert_eq!(buffer, expected_buffer);
 }
 
+pub fn test_arith_bounds() {
+    let mut buffer = [0; BUFFER_SIZE5];
+    let mut rust_buffer = [0; BUFFER_SIZE5];
+    let expected_buffer = [12, 1, 2, 3, 24, 15];
+
+    unsafe {
+        entry5(BUFFER_SIZE5 as u32, buffer.as_mut_ptr());
+        rust_entry5(BUFFER_SIZE5 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_owned_allocations() {
+    let mut buffer = [0; BUFFER_SIZE6];
+    let mut rust_buffer = [0; BUFFER_SIZE6];
+    let expected_buffer = [25, 7, 30, 0];
+
+    unsafe {
+        owned_allocations(BUFFER_SIZE6 as u32, buffer.as_mut_ptr());
+        rust_owned_allocations(BUFFER_SIZE6 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // The pointer passed to `read_first` escapes, so it stays a raw pointer
+    let src = include_str!("owned_allocations.rs");
+    assert!(src.contains("Box<point>"));
+    assert!(src.contains("Vec<libc::c_int>"));
+    assert!(src.contains("escaped: *mut libc::c_int"));
+}
+
+pub fn test_null_checks() {
+    let mut buffer = [0; BUFFER_SIZE7];
+    let mut rust_buffer = [0; BUFFER_SIZE7];
+    let expected_buffer = [5, -1, 42, 3, 12, 9, 7];
+
+    unsafe {
+        null_checks(BUFFER_SIZE7 as u32, buffer.as_mut_ptr());
+        rust_null_checks(BUFFER_SIZE7 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // `find` is also used unchecked, so only `lookup` returns an `Option`
+    let src = include_str!("null_checks.rs");
+    assert!(src.contains("-> Option<::std::ptr::NonNull<counter>>"));
+    assert!(src.contains("-> *mut counter"));
+    assert!(src.contains("if let Some(op) = op"));
+    assert!(src.contains("if let Some(value) = value"));
+}
+
 pub fn test_fn_ptrs() {
     let mut buffer = [0; BUFFER_SIZE3];
     let mut rust_buffer = [0; BUFFER_SIZE3];
@@ -70,3 +144,24 @@ pub fn test_fn_ptrs() {
     assert_eq!(&buffer[..],      &expected_buffer[..], "c version");
     assert_eq!(&rust_buffer[..], &expected_buffer[..], "rust version");
 }
+
+pub fn test_qualifiers() {
+    let mut buffer = [0; BUFFER_SIZE4];
+    let mut rust_buffer = [0; BUFFER_SIZE4];
+    let expected_buffer = [1, 2, 3, 6, 7, 5];
+
+    unsafe {
+        entry4(BUFFER_SIZE4 as u32, buffer.as_mut_ptr());
+        rust_entry4(BUFFER_SIZE4 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_qualifier_attrs() {
+    // `restrict` and `volatile` pointer arguments are named in attributes
+    let src = include_str!("qualifiers.rs");
+    assert!(src.contains("#[c2rust::restrict(dst, src)]"));
+    assert!(src.contains("#[c2rust::volatile(flag)]"));
+}
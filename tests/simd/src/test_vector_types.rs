@@ -0,0 +1,26 @@
+extern crate libc;
+
+use self::libc::{c_longlong, c_uint};
+use vector_types::rust_add_pairs;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn add_pairs(_: c_uint, _: *mut c_longlong);
+}
+
+const BUFFER_SIZE: usize = 2;
+
+pub fn test_add_pairs() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [11, 22];
+
+    unsafe {
+        add_pairs(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_add_pairs(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,22 @@
+//! translate_simd
+
+#include <emmintrin.h>
+
+// A vector type declared without any of the <immintrin.h> typedefs
+typedef long long v2di __attribute__((vector_size(16)));
+
+static v2di pair(long long lo, long long hi) {
+    return (v2di)_mm_set_epi64x(hi, lo);
+}
+
+void add_pairs(const unsigned buffer_size, long long buffer[const]) {
+    if (buffer_size < 2) {
+        return;
+    }
+
+    v2di a = pair(1, 2);
+    v2di b = pair(10, 20);
+    __m128i sum = _mm_add_epi64((__m128i)a, (__m128i)b);
+
+    _mm_storeu_si128((__m128i *)buffer, sum);
+}
@@ -50,6 +50,12 @@ stat s = {&bar.bar};
 stat2 s2 = {&bar.bar};
 static stat3 selfref = { NULL, &selfref.first };
 void (*int_to_fn_ptr)(void) = -1;
+
+// These are folded into literals:
+enum { FOLD_SHIFT = 4 };
+unsigned folded_mask = ~0U << FOLD_SHIFT;
+unsigned char folded_wrap = 255 + 2;
+
 void (*int_to_fn_ptr2)(int, ...) = -1;
 void_fn_ptr int_to_fn_ptr3 = -1;
 
@@ -18,6 +18,8 @@ pub fn test_sectioned_statics() {
         assert_eq!(rust_section_foo_b_field.c, 1.2);
         assert_eq!(rust_section_num_params, 2);
         assert!(rust_if_expr == 30 || rust_if_expr == 31);
+        assert_eq!(rust_folded_mask, c_uint::max_value() << 4);
+        assert_eq!(rust_folded_wrap, 1);
 
         // There's not really a way to test the function scoped static
         // directly since it's (rightly) private. But this does prove
@@ -0,0 +1,39 @@
+extern crate libc;
+
+use self::libc::{c_uint, c_int};
+use std::thread;
+use thread_locals_macro::rust_thread_macro_entry;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn thread_macro_entry(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 6;
+
+fn run_test() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    unsafe {
+        thread_macro_entry(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_thread_macro_entry(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+}
+
+pub fn test_thread_locals_macro() {
+    let src = include_str!("thread_locals_macro.rs");
+    assert!(src.contains("thread_local!"));
+    assert!(!src.contains("#[thread_local]"));
+
+    run_test();
+    run_test();
+    let t = thread::spawn(|| {
+        run_test();
+        run_test();
+    });
+    let _ = t.join().unwrap();
+    run_test();
+}
@@ -0,0 +1,17 @@
+//! thread_locals_macro
+
+static __thread int gsti = 37;
+__thread int gti = 41;
+
+void thread_macro_entry(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 6) return;
+
+    static __thread int fsti = 59;
+
+    int i = 0;
+    int *p = &fsti;
+#define ADD_VAR(x)  do { buffer[i++] = (x); (x) += 1337; buffer[i++] = (x); } while (0)
+    ADD_VAR(gsti);
+    ADD_VAR(gti);
+    ADD_VAR(*p);
+}
@@ -0,0 +1,21 @@
+//! check_abi
+
+struct pair {
+    char tag;
+    long value;
+    short extra;
+};
+
+struct __attribute__((packed)) packed_pair {
+    char tag;
+    int value;
+};
+
+struct pair make_pair(char tag, long value) {
+    struct pair p = { tag, value, 7 };
+    return p;
+}
+
+long sum_pair(struct pair p, struct packed_pair q) {
+    return p.tag + p.value + p.extra + q.tag + q.value;
+}
@@ -0,0 +1,55 @@
+// C with classes
+
+namespace geometry {
+
+class counter {
+    int count;
+    int step;
+
+public:
+    void reset(int s) {
+        count = 0;
+        step = s;
+    }
+
+    void increment() { this->count += step; }
+
+    int get() const { return count; }
+
+    static int twice(int x) { return 2 * x; }
+
+    // Comparisons and logical operators are bool in C++, not int
+    bool same_step(int x, int y) const { return x == y && step == x; }
+
+    bool passed(int limit) const {
+        bool over = count > limit;
+        return over || !limit;
+    }
+};
+
+}
+
+extern "C" {
+
+void classes(unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 7) return;
+
+    geometry::counter c;
+    c.reset(3);
+    c.increment();
+    buffer[0] = c.get();
+
+    geometry::counter *p = &c;
+    p->increment();
+    buffer[1] = p->get();
+
+    buffer[2] = geometry::counter::twice(buffer[1]);
+    buffer[3] = p != nullptr && true;
+
+    bool same = c.same_step(3, 3);
+    buffer[4] = same;
+    buffer[5] = c.passed(5) + c.passed(10);
+    buffer[6] = c.same_step(3, 4) ? 1 : 2;
+}
+
+}
@@ -0,0 +1,27 @@
+//! derives
+
+struct point {
+    int x;
+    int y;
+};
+
+struct shape {
+    struct point corners[4];
+    double scale;
+    const char *name;
+};
+
+union number {
+    int i;
+    float f;
+};
+
+struct tagged {
+    int tag;
+    union number value;
+};
+
+struct shape make_square(int side) {
+    struct shape s = { { { 0, 0 }, { side, 0 }, { side, side }, { 0, side } }, 1.0, 0 };
+    return s;
+}
@@ -0,0 +1,29 @@
+#include <stddef.h>
+
+#define my_offsetof(type, member) ((size_t) &((type *)0)->member)
+#define my_container_of(ptr, type, member) \
+    ((type *)((char *)(ptr) - my_offsetof(type, member)))
+
+struct inner {
+    char tag;
+    double value;
+};
+
+struct outer {
+    int id;
+    struct inner nested;
+    long link;
+};
+
+size_t link_offset(void) {
+    return my_offsetof(struct outer, link);
+}
+
+size_t nested_value_offset(void) {
+    return my_offsetof(struct outer, nested.value);
+}
+
+int id_from_link(long *link) {
+    struct outer *o = my_container_of(link, struct outer, link);
+    return o->id;
+}
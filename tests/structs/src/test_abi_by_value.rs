@@ -0,0 +1,25 @@
+extern crate libc;
+
+use abi_by_value::{packed_pair, pair, rust_make_pair, rust_sum_pair};
+
+use self::libc::{c_char, c_long};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn make_pair(_: c_char, _: c_long) -> pair;
+    #[no_mangle]
+    fn sum_pair(_: pair, _: packed_pair) -> c_long;
+}
+
+pub fn test_abi_by_value() {
+    let c_pair = unsafe { make_pair(3, 40) };
+    let rust_pair = unsafe { rust_make_pair(3, 40) };
+    let q = packed_pair { tag: 1, value: 100 };
+
+    let c_sum = unsafe { sum_pair(c_pair, q) };
+    let rust_sum = unsafe { rust_sum_pair(rust_pair, q) };
+
+    assert_eq!(c_sum, 151);
+    assert_eq!(rust_sum, c_sum);
+}
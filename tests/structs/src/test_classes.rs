@@ -0,0 +1,31 @@
+extern crate libc;
+
+use classes::rust_classes;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn classes(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 7;
+
+pub fn test_methods() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [3, 6, 12, 1, 1, 1, 2];
+
+    unsafe {
+        classes(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_classes(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // Comparisons keep the bool type they have in C++
+    let src = include_str!("classes.rs");
+    assert!(src.contains("over: bool ="));
+    assert!(!src.contains("> limit) as libc::c_int"));
+}
@@ -0,0 +1,24 @@
+use derives::{number, point, rust_make_square, shape, tagged};
+
+pub fn test_default() {
+    let s = shape::default();
+    assert_eq!(s.corners, [point { x: 0, y: 0 }; 4]);
+    assert_eq!(s.scale, 0.0);
+    assert!(s.name.is_null());
+
+    let t = tagged::default();
+    assert_eq!(t.tag, 0);
+    assert_eq!(unsafe { t.value.i }, 0);
+    assert_eq!(unsafe { number::default().f }, 0.0);
+}
+
+pub fn test_debug_partial_eq() {
+    let square = unsafe { rust_make_square(2) };
+    let mut expected = shape::default();
+    expected.corners[1].x = 2;
+    expected.corners[2] = point { x: 2, y: 2 };
+    expected.corners[3].y = 2;
+    expected.scale = 1.0;
+    assert_eq!(square, expected);
+    assert_eq!(format!("{:?}", square.corners[2]), "point { x: 2, y: 2 }");
+}
@@ -0,0 +1,29 @@
+//! extern_crate_memoffset
+
+extern crate libc;
+
+use null_offsetof::{rust_id_from_link, rust_link_offset, rust_nested_value_offset, outer, size_t};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn link_offset() -> size_t;
+    #[no_mangle]
+    fn nested_value_offset() -> size_t;
+}
+
+pub fn test_null_offsetof() {
+    unsafe {
+        assert_eq!(rust_link_offset(), link_offset());
+        assert_eq!(rust_nested_value_offset(), nested_value_offset());
+    }
+}
+
+pub fn test_container_of() {
+    let mut o: outer = unsafe { std::mem::zeroed() };
+    o.id = 42;
+
+    let id = unsafe { rust_id_from_link(&mut o.link) };
+
+    assert_eq!(id, 42);
+}
@@ -0,0 +1,12 @@
+//! union_accessors
+
+union number {
+    int i;
+    float f;
+    unsigned char bytes[4];
+};
+
+int first_byte(union number *n) {
+    n->i = 0x01020304;
+    return n->bytes[0];
+}
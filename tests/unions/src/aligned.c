@@ -0,0 +1,8 @@
+union __attribute__((aligned(16))) aligned_union {
+    int as_int;
+    char as_char;
+};
+
+unsigned long aligned_union_align(void) {
+    return _Alignof(union aligned_union);
+}
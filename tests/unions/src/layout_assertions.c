@@ -0,0 +1,22 @@
+//! emit_layout_assertions
+
+#include <stdint.h>
+
+struct header {
+    uint8_t kind;
+    uint32_t length;
+};
+
+union payload {
+    uint64_t word;
+    uint8_t bytes[12];
+    struct header header;
+};
+
+unsigned payload_size(void) {
+    return sizeof(union payload);
+}
+
+unsigned payload_align(void) {
+    return _Alignof(union payload);
+}
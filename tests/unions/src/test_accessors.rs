@@ -0,0 +1,18 @@
+extern crate libc;
+
+use accessors::{number, rust_first_byte};
+
+pub fn test_accessors() {
+    let mut n = number { i: 0 };
+
+    unsafe {
+        n.set_i(0x01020304);
+        assert_eq!(n.i(), 0x01020304);
+        assert_eq!(n.bytes(), 0x01020304i32.to_ne_bytes());
+
+        n.set_f(1.0);
+        assert_eq!(n.i(), 0x3f800000);
+
+        assert_eq!(rust_first_byte(&mut n), 0x01020304i32.to_ne_bytes()[0] as i32);
+    }
+}
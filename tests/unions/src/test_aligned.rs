@@ -0,0 +1,20 @@
+extern crate libc;
+
+use aligned::rust_aligned_union_align;
+use self::libc::c_ulong;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn aligned_union_align() -> c_ulong;
+}
+
+pub fn test_aligned_union() {
+    unsafe {
+        assert_eq!(aligned_union_align(), 16);
+        assert_eq!(rust_aligned_union_align(), 16);
+    }
+
+    let src = include_str!("aligned.rs");
+    assert!(src.contains("#[repr(C, align(16))]"));
+}
@@ -0,0 +1,24 @@
+extern crate libc;
+
+use layout_assertions::{payload, rust_payload_align, rust_payload_size};
+use std::mem::{align_of, size_of};
+
+use self::libc::c_uint;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn payload_size() -> c_uint;
+    #[no_mangle]
+    fn payload_align() -> c_uint;
+}
+
+pub fn test_layout_assertions() {
+    unsafe {
+        assert_eq!(payload_size(), rust_payload_size());
+        assert_eq!(payload_align(), rust_payload_align());
+    }
+
+    assert_eq!(size_of::<payload>(), 16);
+    assert_eq!(align_of::<payload>(), 8);
+}
@@ -0,0 +1,18 @@
+extern crate libc;
+
+use zero_init::rust_zero_init;
+use self::libc::c_long;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn zero_init() -> c_long;
+}
+
+pub fn test_zero_init() {
+    let c_sum = unsafe { zero_init() };
+    let rust_sum = unsafe { rust_zero_init() };
+
+    assert_eq!(c_sum, 1);
+    assert_eq!(rust_sum, c_sum);
+}
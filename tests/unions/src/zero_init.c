@@ -0,0 +1,25 @@
+union small_first {
+    char c;
+    long l;
+};
+
+union no_covering_field {
+    char c[6];
+    int i;
+};
+
+struct holder {
+    int tag;
+    union small_first small;
+    union no_covering_field other;
+};
+
+static union small_first global_small;
+static union no_covering_field global_other;
+
+long zero_init(void) {
+    struct holder local = { 1 };
+    long sum = global_small.l + global_other.i + global_other.c[5];
+    sum += local.tag + local.small.l + local.other.i + local.other.c[5];
+    return sum;
+}